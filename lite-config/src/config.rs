@@ -1,20 +1,45 @@
+use crate::{Languages, ProjectConfig};
+use serde::de::value::{Error as ValueError, StrDeserializer};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// Main configuration structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Config {
     pub editor: EditorConfig,
+    /// Encryption filters, the first matching a file's name is used
+    pub crypt: Vec<CryptFilter>,
+    /// Language servers and project roots, from `languages.toml`
+    #[serde(skip)]
+    pub languages: Languages,
+    /// Settings of the project being edited, from its `.lite.toml`
+    #[serde(skip)]
+    pub project: ProjectConfig,
 }
 
-impl Default for Config {
-    fn default() -> Self {
-        Self {
-            editor: EditorConfig::default(),
-        }
+impl Config {
+    /// The encryption filter for a file, if its name matches one
+    pub fn crypt_filter(&self, path: &std::path::Path) -> Option<&CryptFilter> {
+        let extension = path.extension()?.to_str()?;
+        self.crypt
+            .iter()
+            .find(|filter| filter.extensions.iter().any(|e| e.eq_ignore_ascii_case(extension)))
     }
 }
 
+/// Shell commands a file is passed through: decrypted into memory when
+/// opened and encrypted on its way to disk when saved
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CryptFilter {
+    /// File extensions, without the dot
+    pub extensions: Vec<String>,
+    /// Reads the file contents on stdin, writes the plaintext to stdout
+    pub decrypt: String,
+    /// Reads the plaintext on stdin, writes the file contents to stdout
+    pub encrypt: String,
+}
+
 /// Editor-specific configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
@@ -31,16 +56,77 @@ pub struct EditorConfig {
     pub mouse: bool,
     /// Scrolloff - minimum lines to keep above/below cursor
     pub scrolloff: usize,
+    /// Minimum columns to keep left/right of the cursor
+    pub sidescrolloff: usize,
+    /// Minimum lines/columns to scroll when the cursor leaves the view
+    pub scroll_jump: usize,
     /// Enable auto-save
     pub auto_save: bool,
     /// Auto-save delay in milliseconds
     pub auto_save_delay: u64,
+    /// Other moments to save the current buffer at
+    pub auto_save_on: AutoSaveTriggers,
     /// Enable soft wrap
     pub soft_wrap: bool,
+    /// Column to hard wrap at (0 to disable)
+    pub text_width: usize,
+    /// Highlight text past `text_width`
+    pub highlight_overlong: bool,
     /// Show whitespace characters
     pub show_whitespace: bool,
+    /// Mark trailing whitespace and a missing final newline
+    pub whitespace_hints: bool,
     /// Cursor blink rate in milliseconds (0 to disable)
     pub cursor_blink: u64,
+    /// Cursor shape per mode
+    pub cursor_shape: CursorShapeConfig,
+    /// How to signal an action with nothing to do, such as undo with
+    /// nothing to undo
+    pub bell: Bell,
+    /// Order of buffers in the tabline and for buffer switching
+    pub buffer_order: BufferOrder,
+    /// Comment keywords collected by `:todo`
+    pub todo_keywords: Vec<String>,
+    /// Digraphs for Ctrl+K besides the built-in ones, such as `"ok" = "✓"`
+    pub digraphs: BTreeMap<String, char>,
+    /// Start the line after a line comment with the same comment prefix, and
+    /// the item after a Markdown list item with its marker
+    pub continue_comments: bool,
+    /// Show the file and the symbols around the cursor above the editor
+    pub breadcrumbs: bool,
+    /// Show lines deleted since the last commit, dimmed, where they were
+    pub show_deleted_lines: bool,
+    /// Animate large scrolls such as Page Down over a few frames
+    pub smooth_scroll: bool,
+    /// Where to report how long keystrokes take to reach the screen
+    pub debug_metrics: DebugMetrics,
+    /// When to draw less so slow terminals and SSH links keep up
+    pub low_bandwidth: LowBandwidth,
+    /// Whether searches tell upper and lower case apart
+    pub search_case: SearchCase,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            editor: EditorConfig::default(),
+            crypt: vec![
+                CryptFilter {
+                    extensions: vec!["gpg".to_string()],
+                    decrypt: "gpg --quiet --batch --decrypt".to_string(),
+                    encrypt: "gpg --quiet --batch --default-recipient-self --encrypt".to_string(),
+                },
+                CryptFilter {
+                    extensions: vec!["asc".to_string()],
+                    decrypt: "gpg --quiet --batch --decrypt".to_string(),
+                    encrypt: "gpg --quiet --batch --default-recipient-self --armor --encrypt"
+                        .to_string(),
+                },
+            ],
+            languages: Languages::default(),
+            project: ProjectConfig::default(),
+        }
+    }
 }
 
 impl Default for EditorConfig {
@@ -52,15 +138,186 @@ impl Default for EditorConfig {
             relative_line_numbers: false,
             mouse: true,
             scrolloff: 5,
+            sidescrolloff: 0,
+            scroll_jump: 1,
             auto_save: false,
             auto_save_delay: 1000,
+            auto_save_on: AutoSaveTriggers::default(),
             soft_wrap: false,
+            text_width: 80,
+            highlight_overlong: false,
             show_whitespace: false,
+            whitespace_hints: true,
             cursor_blink: 530,
+            cursor_shape: CursorShapeConfig::default(),
+            bell: Bell::Off,
+            buffer_order: BufferOrder::Insertion,
+            todo_keywords: ["TODO", "FIXME", "HACK"].map(String::from).to_vec(),
+            digraphs: BTreeMap::new(),
+            continue_comments: true,
+            breadcrumbs: true,
+            show_deleted_lines: false,
+            smooth_scroll: false,
+            debug_metrics: DebugMetrics::Off,
+            low_bandwidth: LowBandwidth::Off,
+            search_case: SearchCase::Smart,
+        }
+    }
+}
+
+impl EditorConfig {
+    /// Names and descriptions of all options
+    pub const OPTIONS: &'static [(&'static str, &'static str)] = &[
+        ("tab_width", "Number of spaces for a tab"),
+        ("indent_style", "Indent with \"spaces\" or \"tabs\""),
+        ("line_numbers", "Show line numbers"),
+        ("relative_line_numbers", "Show relative line numbers"),
+        ("mouse", "Capture the mouse; off leaves it to the terminal's own selection"),
+        ("scrolloff", "Minimum lines to keep above/below cursor"),
+        ("sidescrolloff", "Minimum columns to keep left/right of cursor"),
+        ("scroll_jump", "Minimum lines/columns to scroll when the cursor leaves the view"),
+        ("auto_save", "Enable auto-save"),
+        ("auto_save_delay", "Auto-save delay in milliseconds"),
+        ("auto_save_on.focus_lost", "Save the current buffer when the terminal loses focus"),
+        ("auto_save_on.buffer_switch", "Save a buffer when another one takes its place"),
+        ("auto_save_on.before_tasks", "Save the current buffer before :format, :lint and :task"),
+        ("soft_wrap", "Enable soft wrap"),
+        ("text_width", "Column to hard wrap at (0 to disable)"),
+        ("highlight_overlong", "Highlight text past text_width"),
+        ("show_whitespace", "Show whitespace characters"),
+        ("whitespace_hints", "Mark trailing whitespace and a missing final newline"),
+        ("cursor_blink", "Cursor blink rate in milliseconds (0 to disable)"),
+        ("cursor_shape.insert", "Cursor shape: \"block\", \"bar\" or \"underline\""),
+        ("bell", "Signal actions with nothing to do: \"off\", \"statusline\", \"screen\" or \"terminal\""),
+        ("buffer_order", "Tab order: \"insertion\" or \"mru\" (most recently used)"),
+        ("todo_keywords", "Comment keywords collected by :todo"),
+        ("digraphs", "Digraphs for Ctrl+K besides the built-in ones"),
+        ("continue_comments", "Continue line comments and Markdown lists on Enter (Shift+Enter doesn't)"),
+        ("breadcrumbs", "Show the file and the symbols around the cursor above the editor"),
+        ("show_deleted_lines", "Show lines deleted since the last commit where they were (Ctrl+Alt+D)"),
+        ("smooth_scroll", "Animate large scrolls; turns itself off if the terminal draws too slowly"),
+        ("debug_metrics", "Report input latency and frame timings: \"off\", \"screen\" or \"log\""),
+        ("low_bandwidth", "Draw less for slow terminals: \"on\", \"off\" or \"auto\" while frames are slow"),
+        ("search_case", "Search case: \"smart\" (sensitive if the query has capitals), \"sensitive\" or \"ignore\""),
+    ];
+
+    /// Get the current value of an option, formatted for display
+    pub fn get(&self, name: &str) -> Option<String> {
+        Some(match name {
+            "tab_width" => self.tab_width.to_string(),
+            "indent_style" => self.indent_style.as_str().to_string(),
+            "line_numbers" => self.line_numbers.to_string(),
+            "relative_line_numbers" => self.relative_line_numbers.to_string(),
+            "mouse" => self.mouse.to_string(),
+            "scrolloff" => self.scrolloff.to_string(),
+            "sidescrolloff" => self.sidescrolloff.to_string(),
+            "scroll_jump" => self.scroll_jump.to_string(),
+            "auto_save" => self.auto_save.to_string(),
+            "auto_save_delay" => self.auto_save_delay.to_string(),
+            "auto_save_on.focus_lost" => self.auto_save_on.focus_lost.to_string(),
+            "auto_save_on.buffer_switch" => self.auto_save_on.buffer_switch.to_string(),
+            "auto_save_on.before_tasks" => self.auto_save_on.before_tasks.to_string(),
+            "soft_wrap" => self.soft_wrap.to_string(),
+            "text_width" => self.text_width.to_string(),
+            "highlight_overlong" => self.highlight_overlong.to_string(),
+            "show_whitespace" => self.show_whitespace.to_string(),
+            "whitespace_hints" => self.whitespace_hints.to_string(),
+            "cursor_blink" => self.cursor_blink.to_string(),
+            "cursor_shape.insert" => self.cursor_shape.insert.as_str().to_string(),
+            "bell" => self.bell.as_str().to_string(),
+            "buffer_order" => self.buffer_order.as_str().to_string(),
+            "todo_keywords" => self.todo_keywords.join(", "),
+            "digraphs" => {
+                let digraphs: Vec<_> = self.digraphs.iter().map(|(name, c)| format!("{} {}", name, c)).collect();
+                digraphs.join(", ")
+            }
+            "continue_comments" => self.continue_comments.to_string(),
+            "breadcrumbs" => self.breadcrumbs.to_string(),
+            "show_deleted_lines" => self.show_deleted_lines.to_string(),
+            "smooth_scroll" => self.smooth_scroll.to_string(),
+            "debug_metrics" => self.debug_metrics.as_str().to_string(),
+            "low_bandwidth" => self.low_bandwidth.as_str().to_string(),
+            "search_case" => self.search_case.as_str().to_string(),
+            _ => return None,
+        })
+    }
+
+    /// Change an option from a value written as `get` shows it. Switches
+    /// also take `on`/`off` and `yes`/`no`.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let value = value.trim();
+        match name {
+            "tab_width" => match number(name, value)? {
+                0 => return Err("tab_width must be at least 1".to_string()),
+                width => self.tab_width = width,
+            },
+            "indent_style" => self.indent_style = choice(name, value)?,
+            "line_numbers" => self.line_numbers = switch(name, value)?,
+            "relative_line_numbers" => self.relative_line_numbers = switch(name, value)?,
+            "mouse" => self.mouse = switch(name, value)?,
+            "scrolloff" => self.scrolloff = number(name, value)?,
+            "sidescrolloff" => self.sidescrolloff = number(name, value)?,
+            "scroll_jump" => self.scroll_jump = number(name, value)?,
+            "auto_save" => self.auto_save = switch(name, value)?,
+            "auto_save_delay" => self.auto_save_delay = number(name, value)?,
+            "auto_save_on.focus_lost" => self.auto_save_on.focus_lost = switch(name, value)?,
+            "auto_save_on.buffer_switch" => self.auto_save_on.buffer_switch = switch(name, value)?,
+            "auto_save_on.before_tasks" => self.auto_save_on.before_tasks = switch(name, value)?,
+            "soft_wrap" => self.soft_wrap = switch(name, value)?,
+            "text_width" => self.text_width = number(name, value)?,
+            "highlight_overlong" => self.highlight_overlong = switch(name, value)?,
+            "show_whitespace" => self.show_whitespace = switch(name, value)?,
+            "whitespace_hints" => self.whitespace_hints = switch(name, value)?,
+            "cursor_blink" => self.cursor_blink = number(name, value)?,
+            "cursor_shape.insert" => self.cursor_shape.insert = choice(name, value)?,
+            "bell" => self.bell = choice(name, value)?,
+            "buffer_order" => self.buffer_order = choice(name, value)?,
+            "todo_keywords" => {
+                let keywords = value.split(',').map(str::trim).filter(|keyword| !keyword.is_empty());
+                self.todo_keywords = keywords.map(String::from).collect();
+            }
+            "digraphs" => {
+                let mut digraphs = BTreeMap::new();
+                for digraph in value.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+                    let (name, c) = digraph.split_once(' ').ok_or(format!("No char for digraph {}", digraph))?;
+                    let mut chars = c.trim().chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) => digraphs.insert(name.to_string(), c),
+                        _ => return Err(format!("A digraph stands for one char, not {}", c.trim())),
+                    };
+                }
+                self.digraphs = digraphs;
+            }
+            "continue_comments" => self.continue_comments = switch(name, value)?,
+            "breadcrumbs" => self.breadcrumbs = switch(name, value)?,
+            "show_deleted_lines" => self.show_deleted_lines = switch(name, value)?,
+            "smooth_scroll" => self.smooth_scroll = switch(name, value)?,
+            "debug_metrics" => self.debug_metrics = choice(name, value)?,
+            "low_bandwidth" => self.low_bandwidth = choice(name, value)?,
+            "search_case" => self.search_case = choice(name, value)?,
+            _ => return Err(format!("Unknown option: {}", name)),
         }
+        Ok(())
     }
 }
 
+fn switch(name: &str, value: &str) -> Result<bool, String> {
+    match value {
+        "true" | "on" | "yes" => Ok(true),
+        "false" | "off" | "no" => Ok(false),
+        _ => Err(format!("{} is on or off, not {}", name, value)),
+    }
+}
+
+fn number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("{} is a number, not {}", name, value))
+}
+
+/// One of the lowercase names of an option's variants
+fn choice<'de, T: Deserialize<'de>>(name: &str, value: &'de str) -> Result<T, String> {
+    T::deserialize(StrDeserializer::<ValueError>::new(value)).map_err(|e| format!("{}: {}", name, e))
+}
+
 /// Indentation style
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -68,3 +325,210 @@ pub enum IndentStyle {
     Tabs,
     Spaces,
 }
+
+impl IndentStyle {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IndentStyle::Tabs => "tabs",
+            IndentStyle::Spaces => "spaces",
+        }
+    }
+}
+
+/// Moments to save at besides the auto-save delay. Only files with
+/// changes are saved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoSaveTriggers {
+    /// When the terminal loses focus
+    pub focus_lost: bool,
+    /// When another buffer is shown in its view
+    pub buffer_switch: bool,
+    /// Before a formatter, lint command or task runs
+    pub before_tasks: bool,
+}
+
+/// Cursor shape for each editing mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CursorShapeConfig {
+    /// Shape while inserting text
+    pub insert: CursorShape,
+}
+
+/// Shape of the terminal cursor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CursorShape {
+    Block,
+    #[default]
+    Bar,
+    Underline,
+}
+
+impl CursorShape {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CursorShape::Block => "block",
+            CursorShape::Bar => "bar",
+            CursorShape::Underline => "underline",
+        }
+    }
+}
+
+/// Order in which open buffers are listed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BufferOrder {
+    /// Order in which buffers were opened
+    Insertion,
+    /// Most recently used first
+    Mru,
+}
+
+impl BufferOrder {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BufferOrder::Insertion => "insertion",
+            BufferOrder::Mru => "mru",
+        }
+    }
+}
+
+/// Where timings of the main loop are reported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DebugMetrics {
+    #[default]
+    Off,
+    /// In the statusline
+    Screen,
+    /// Appended to `metrics.log` in the state directory
+    Log,
+}
+
+impl DebugMetrics {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DebugMetrics::Off => "off",
+            DebugMetrics::Screen => "screen",
+            DebugMetrics::Log => "log",
+        }
+    }
+}
+
+/// How an action with nothing to do, such as moving past the end of the
+/// buffer or searching for text that isn't there, is signalled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Bell {
+    /// Only the status message, if the action has one
+    #[default]
+    Off,
+    /// Flash the statusline
+    Statusline,
+    /// Flash the whole screen
+    Screen,
+    /// Ring the terminal's bell
+    Terminal,
+}
+
+impl Bell {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Bell::Off => "off",
+            Bell::Statusline => "statusline",
+            Bell::Screen => "screen",
+            Bell::Terminal => "terminal",
+        }
+    }
+}
+
+/// When the editor stops animating and decorating text, and draws at most
+/// ten frames a second
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LowBandwidth {
+    /// While frames keep taking too long to reach the terminal
+    Auto,
+    On,
+    #[default]
+    Off,
+}
+
+impl LowBandwidth {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LowBandwidth::Auto => "auto",
+            LowBandwidth::On => "on",
+            LowBandwidth::Off => "off",
+        }
+    }
+}
+
+/// How searches match letters of the other case
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchCase {
+    /// Ignore case unless the query has an upper case letter
+    #[default]
+    Smart,
+    Sensitive,
+    Ignore,
+}
+
+impl SearchCase {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SearchCase::Smart => "smart",
+            SearchCase::Sensitive => "sensitive",
+            SearchCase::Ignore => "ignore",
+        }
+    }
+
+    /// The mode after this one, for toggling in the search prompt
+    pub fn next(self) -> Self {
+        match self {
+            SearchCase::Smart => SearchCase::Sensitive,
+            SearchCase::Sensitive => SearchCase::Ignore,
+            SearchCase::Ignore => SearchCase::Smart,
+        }
+    }
+
+    /// Whether a search for `query` ignores case
+    pub fn ignores_case(self, query: &str) -> bool {
+        match self {
+            SearchCase::Smart => !query.chars().any(char::is_uppercase),
+            SearchCase::Sensitive => false,
+            SearchCase::Ignore => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set() {
+        let mut config = EditorConfig::default();
+        config.set("tab_width", "2").unwrap();
+        config.set("soft_wrap", "on").unwrap();
+        config.set("bell", "screen").unwrap();
+        config.set("digraphs", "ok ✓, ae æ").unwrap();
+        assert_eq!(config.get("tab_width").as_deref(), Some("2"));
+        assert!(config.soft_wrap);
+        assert_eq!(config.bell, Bell::Screen);
+        assert_eq!(config.get("digraphs").as_deref(), Some("ae æ, ok ✓"));
+
+        assert_eq!(config.set("tab_width", "0"), Err("tab_width must be at least 1".to_string()));
+        assert_eq!(config.set("soft_wrap", "maybe"), Err("soft_wrap is on or off, not maybe".to_string()));
+        assert!(config.set("bell", "loud").unwrap_err().contains("expected one of `off`"));
+        assert_eq!(config.set("tabwidth", "2"), Err("Unknown option: tabwidth".to_string()));
+        // Every option listed can be set back to the value it shows
+        for (name, _) in EditorConfig::OPTIONS {
+            let value = config.get(name).unwrap();
+            assert_eq!(config.set(name, &value), Ok(()), "{}", name);
+        }
+    }
+}
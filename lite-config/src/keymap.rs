@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
 
 /// Key modifier flags
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
@@ -74,6 +76,90 @@ impl KeyEvent {
     pub fn alt(c: char) -> Self {
         Self::new(Key::Char(c), Modifier::ALT)
     }
+
+    /// The form used for keymap lookups: a shifted letter is its lowercase
+    /// character plus SHIFT, so `Char('K')` and `Char('k') + SHIFT` are the same key
+    pub fn normalized(&self) -> Self {
+        match self.key {
+            Key::Char(c) if c.is_uppercase() => Self::new(
+                Key::Char(c.to_lowercase().next().unwrap_or(c)),
+                Modifier {
+                    shift: true,
+                    ..self.modifiers
+                },
+            ),
+            _ => self.clone(),
+        }
+    }
+}
+
+impl fmt::Display for KeyEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.ctrl {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.alt {
+            write!(f, "Alt+")?;
+        }
+        if self.modifiers.shift {
+            write!(f, "Shift+")?;
+        }
+        write!(f, "{}", self.key)
+    }
+}
+
+impl FromStr for KeyEvent {
+    type Err = String;
+
+    /// Read a key as `Display` writes it, such as `Ctrl+Shift+K` or
+    /// `Alt+Enter`. Letters with Ctrl or Alt are the lowercase key, as
+    /// bindings name them; alone they're the character typed.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (names, key) = match s.strip_suffix("++") {
+            Some(names) => (names, "+"),
+            None => s.rsplit_once('+').unwrap_or(("", s)),
+        };
+        let mut modifiers = Modifier::NONE;
+        for name in names.split('+').filter(|name| !name.is_empty()) {
+            match name.to_ascii_lowercase().as_str() {
+                "ctrl" => modifiers.ctrl = true,
+                "alt" => modifiers.alt = true,
+                "shift" => modifiers.shift = true,
+                _ => return Err(format!("unknown modifier {} in {}", name, s)),
+            }
+        }
+
+        let lower = key.to_ascii_lowercase();
+        let key = match lower.as_str() {
+            "space" => Key::Char(' '),
+            "backspace" => Key::Backspace,
+            "enter" => Key::Enter,
+            "tab" => Key::Tab,
+            "esc" | "escape" => Key::Escape,
+            "up" => Key::Up,
+            "down" => Key::Down,
+            "left" => Key::Left,
+            "right" => Key::Right,
+            "home" => Key::Home,
+            "end" => Key::End,
+            "pageup" => Key::PageUp,
+            "pagedown" => Key::PageDown,
+            "insert" => Key::Insert,
+            "delete" => Key::Delete,
+            _ => {
+                let mut chars = key.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) if modifiers.ctrl || modifiers.alt => Key::Char(c.to_ascii_lowercase()),
+                    (Some(c), None) => Key::Char(c),
+                    _ => match lower.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                        Some(n) => Key::F(n),
+                        None => return Err(format!("unknown key {}", s)),
+                    },
+                }
+            }
+        };
+        Ok(Self::new(key, modifiers))
+    }
 }
 
 /// Key codes
@@ -97,6 +183,30 @@ pub enum Key {
     Delete,
 }
 
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Key::Char(' ') => write!(f, "Space"),
+            Key::Char(c) => write!(f, "{}", c.to_ascii_uppercase()),
+            Key::F(n) => write!(f, "F{}", n),
+            Key::Backspace => write!(f, "Backspace"),
+            Key::Enter => write!(f, "Enter"),
+            Key::Tab => write!(f, "Tab"),
+            Key::Escape => write!(f, "Esc"),
+            Key::Up => write!(f, "Up"),
+            Key::Down => write!(f, "Down"),
+            Key::Left => write!(f, "Left"),
+            Key::Right => write!(f, "Right"),
+            Key::Home => write!(f, "Home"),
+            Key::End => write!(f, "End"),
+            Key::PageUp => write!(f, "PageUp"),
+            Key::PageDown => write!(f, "PageDown"),
+            Key::Insert => write!(f, "Insert"),
+            Key::Delete => write!(f, "Delete"),
+        }
+    }
+}
+
 /// Editor actions
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Action {
@@ -105,6 +215,7 @@ pub enum Action {
     SaveAs,
     Open,
     QuickOpen,
+    OpenRecent,
     CloseBuffer,
     CloseWindow,
     Quit,
@@ -126,10 +237,21 @@ pub enum Action {
     GotoSymbol,
     JumpBack,
     JumpForward,
+    NextCell,
+    PreviousCell,
+    NextSiblingKey,
+    PreviousSiblingKey,
+    /// Open the URL or jump to the `file:line` under the cursor
+    OpenLink,
+    /// Move to the next `file:line` reference, as in build output
+    NextReference,
+    PreviousReference,
 
     // Editing
     InsertChar(char),
     InsertNewline,
+    /// Like `InsertNewline`, without continuing a comment
+    InsertPlainNewline,
     InsertNewlineBelow,
     InsertNewlineAbove,
     Backspace,
@@ -141,6 +263,9 @@ pub enum Action {
     Indent,
     Unindent,
     ToggleComment,
+    FormatParagraph,
+    /// Insert the character the next two keys name, such as `e:` for `ë`
+    Digraph,
 
     // Selection
     SelectAll,
@@ -148,6 +273,8 @@ pub enum Action {
     SelectWord,
     SelectNextOccurrence,
     SelectAllOccurrences,
+    /// Select the matches of a query inside the selection, from a prompt
+    SelectInSelection,
     SplitSelectionLines,
     AddCursorAbove,
     AddCursorBelow,
@@ -170,25 +297,43 @@ pub enum Action {
     FindInFiles,
     UseSelectionForFind,
 
+    // Changes since the last commit
+    NextHunk,
+    PrevHunk,
+    RevertHunk,
+    StageHunk,
+    UnstageHunk,
+    ToggleDeletedLines,
+
     // Buffer/Tab management
     NextBuffer,
     PreviousBuffer,
     SwitchToBuffer(usize),
+    SwitchRecentBuffer,
+    SwitchRecentBufferReverse,
+    /// Pick an open buffer by name
+    BufferPicker,
 
     // Splits
     SplitVertical,
     SplitHorizontal,
     FocusNextSplit,
     FocusPreviousSplit,
+    /// Show the next layout page, each with splits of its own
+    NextPage,
+    PreviousPage,
 
     // LSP
     Autocomplete,
     GotoDefinition,
+    PeekDefinition,
     FindReferences,
     RenameSymbol,
     QuickFix,
     SignatureHelp,
     Hover,
+    /// Pick one of the current buffer's diagnostics to go to
+    DiagnosticPicker,
 
     // Code folding
     Fold,
@@ -196,13 +341,30 @@ pub enum Action {
 
     // UI
     CommandPalette,
+    /// Open, focus or close the file tree sidebar
     ToggleFileTree,
+    /// Open, focus or close the outline panel
+    ToggleOutline,
+    /// Keep the cursor line centered in the current view
+    ToggleTypewriter,
+    Help,
+    /// Quit and install the release the update check found
+    InstallUpdate,
 
     // Internal - Prompt results
     ExecuteGotoLine(String),
     ExecuteSearch(String),
     ExecuteOpen(String),
     ExecuteSaveAs(String),
+    ExecuteCommand(String),
+    /// Close the current buffer after the unsaved-changes prompt; `true` saves first
+    ExecuteCloseBuffer(bool),
+    /// Create a file or directory next to the file tree's pick
+    ExecuteCreateFile(String),
+    /// Rename the file tree's pick
+    ExecuteRenameFile(String),
+    /// Delete the file tree's pick after the prompt
+    ExecuteDeleteFile,
 
     // Misc
     Noop,
@@ -223,6 +385,7 @@ impl Default for Keymap {
         bindings.insert(KeyEvent::ctrl_shift('s'), Action::SaveAs);
         bindings.insert(KeyEvent::ctrl('o'), Action::Open);
         bindings.insert(KeyEvent::ctrl('p'), Action::QuickOpen);
+        bindings.insert(KeyEvent::alt('o'), Action::OpenRecent);
         bindings.insert(KeyEvent::ctrl('w'), Action::CloseBuffer);
         bindings.insert(KeyEvent::ctrl_shift('w'), Action::CloseWindow);
         bindings.insert(KeyEvent::ctrl('q'), Action::Quit);
@@ -286,12 +449,35 @@ impl Default for Keymap {
             KeyEvent::new(Key::Right, Modifier::ALT),
             Action::JumpForward,
         );
+        bindings.insert(KeyEvent::alt(']'), Action::NextCell);
+        bindings.insert(KeyEvent::alt('['), Action::PreviousCell);
+        bindings.insert(
+            KeyEvent::new(Key::Down, Modifier::ALT),
+            Action::NextSiblingKey,
+        );
+        bindings.insert(
+            KeyEvent::new(Key::Up, Modifier::ALT),
+            Action::PreviousSiblingKey,
+        );
+        bindings.insert(
+            KeyEvent::new(Key::Enter, Modifier::ALT),
+            Action::OpenLink,
+        );
+        bindings.insert(KeyEvent::new(Key::F(8), Modifier::NONE), Action::NextReference);
+        bindings.insert(
+            KeyEvent::new(Key::F(8), Modifier::SHIFT),
+            Action::PreviousReference,
+        );
 
         // Editing
         bindings.insert(
             KeyEvent::new(Key::Enter, Modifier::NONE),
             Action::InsertNewline,
         );
+        bindings.insert(
+            KeyEvent::new(Key::Enter, Modifier::SHIFT),
+            Action::InsertPlainNewline,
+        );
         bindings.insert(
             KeyEvent::new(Key::Enter, Modifier::CTRL),
             Action::InsertNewlineBelow,
@@ -327,12 +513,18 @@ impl Default for Keymap {
             Action::Unindent,
         );
         bindings.insert(KeyEvent::ctrl('/'), Action::ToggleComment);
+        bindings.insert(KeyEvent::alt('q'), Action::FormatParagraph);
+        bindings.insert(KeyEvent::ctrl('k'), Action::Digraph);
 
         // Selection
         bindings.insert(KeyEvent::ctrl('a'), Action::SelectAll);
         bindings.insert(KeyEvent::ctrl('l'), Action::SelectLine);
         bindings.insert(KeyEvent::ctrl('d'), Action::SelectNextOccurrence);
         bindings.insert(KeyEvent::ctrl_shift('a'), Action::SelectAllOccurrences);
+        bindings.insert(
+            KeyEvent::new(Key::Char('a'), Modifier::CTRL_ALT),
+            Action::SelectInSelection,
+        );
         bindings.insert(KeyEvent::ctrl_shift('l'), Action::SplitSelectionLines);
         bindings.insert(
             KeyEvent::new(Key::Up, Modifier::ALT_SHIFT),
@@ -368,13 +560,32 @@ impl Default for Keymap {
         bindings.insert(KeyEvent::ctrl_shift('f'), Action::FindInFiles);
         bindings.insert(KeyEvent::ctrl('e'), Action::UseSelectionForFind);
 
+        // Changes since the last commit
+        bindings.insert(KeyEvent::new(Key::PageDown, Modifier::ALT), Action::NextHunk);
+        bindings.insert(KeyEvent::new(Key::PageUp, Modifier::ALT), Action::PrevHunk);
+        bindings.insert(KeyEvent::new(Key::Char('z'), Modifier::CTRL_ALT), Action::RevertHunk);
+        bindings.insert(KeyEvent::new(Key::Char('s'), Modifier::CTRL_ALT), Action::StageHunk);
+        bindings.insert(KeyEvent::new(Key::Char('u'), Modifier::CTRL_ALT), Action::UnstageHunk);
+        bindings.insert(
+            KeyEvent::new(Key::Char('d'), Modifier::CTRL_ALT),
+            Action::ToggleDeletedLines,
+        );
+
         // Buffer/Tab management
         bindings.insert(
             KeyEvent::new(Key::Tab, Modifier::CTRL),
-            Action::NextBuffer,
+            Action::SwitchRecentBuffer,
         );
         bindings.insert(
             KeyEvent::new(Key::Tab, Modifier::CTRL_SHIFT),
+            Action::SwitchRecentBufferReverse,
+        );
+        bindings.insert(
+            KeyEvent::new(Key::PageDown, Modifier::CTRL),
+            Action::NextBuffer,
+        );
+        bindings.insert(
+            KeyEvent::new(Key::PageUp, Modifier::CTRL),
             Action::PreviousBuffer,
         );
         for i in 1..=9 {
@@ -383,10 +594,13 @@ impl Default for Keymap {
                 Action::SwitchToBuffer(i as usize),
             );
         }
+        bindings.insert(KeyEvent::ctrl_shift('b'), Action::BufferPicker);
 
         // Splits
         bindings.insert(KeyEvent::ctrl('\\'), Action::SplitVertical);
         bindings.insert(KeyEvent::ctrl_shift('\\'), Action::SplitHorizontal);
+        bindings.insert(KeyEvent::new(Key::PageDown, Modifier::CTRL_ALT), Action::NextPage);
+        bindings.insert(KeyEvent::new(Key::PageUp, Modifier::CTRL_ALT), Action::PreviousPage);
 
         // LSP
         bindings.insert(KeyEvent::ctrl(' '), Action::Autocomplete);
@@ -394,6 +608,10 @@ impl Default for Keymap {
             KeyEvent::new(Key::F(12), Modifier::NONE),
             Action::GotoDefinition,
         );
+        bindings.insert(
+            KeyEvent::new(Key::F(12), Modifier::ALT),
+            Action::PeekDefinition,
+        );
         bindings.insert(
             KeyEvent::new(Key::F(12), Modifier::SHIFT),
             Action::FindReferences,
@@ -401,6 +619,7 @@ impl Default for Keymap {
         bindings.insert(KeyEvent::new(Key::F(2), Modifier::NONE), Action::RenameSymbol);
         bindings.insert(KeyEvent::ctrl('.'), Action::QuickFix);
         bindings.insert(KeyEvent::ctrl_shift(' '), Action::SignatureHelp);
+        bindings.insert(KeyEvent::ctrl_shift('m'), Action::DiagnosticPicker);
 
         // Code folding
         bindings.insert(KeyEvent::ctrl_shift('['), Action::Fold);
@@ -409,17 +628,41 @@ impl Default for Keymap {
         // UI
         bindings.insert(KeyEvent::ctrl_shift('p'), Action::CommandPalette);
         bindings.insert(KeyEvent::ctrl('b'), Action::ToggleFileTree);
+        bindings.insert(KeyEvent::ctrl_shift('o'), Action::ToggleOutline);
+        bindings.insert(KeyEvent::alt('t'), Action::ToggleTypewriter);
+        bindings.insert(KeyEvent::new(Key::F(1), Modifier::NONE), Action::Help);
+        bindings.insert(KeyEvent::alt('u'), Action::InstallUpdate);
 
         Self { bindings }
     }
 }
 
 impl Keymap {
+    /// Look up the action bound to a key.
+    ///
+    /// Letters match regardless of how the terminal reports shift (see
+    /// `KeyEvent::normalized`). Symbols are already shifted (`|` rather than
+    /// Shift+`\`), so a reported SHIFT is ignored for them if nothing is bound with it.
     pub fn get(&self, event: &KeyEvent) -> Option<&Action> {
-        self.bindings.get(event)
+        let event = event.normalized();
+        self.bindings.get(&event).or_else(|| match event.key {
+            Key::Char(c) if event.modifiers.shift && !c.is_alphabetic() => {
+                let unshifted = Modifier {
+                    shift: false,
+                    ..event.modifiers
+                };
+                self.bindings.get(&KeyEvent::new(event.key, unshifted))
+            }
+            _ => None,
+        })
     }
 
     pub fn insert(&mut self, event: KeyEvent, action: Action) {
-        self.bindings.insert(event, action);
+        self.bindings.insert(event.normalized(), action);
+    }
+
+    /// Iterate over all key bindings
+    pub fn bindings(&self) -> impl Iterator<Item = (&KeyEvent, &Action)> {
+        self.bindings.iter()
     }
 }
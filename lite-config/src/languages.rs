@@ -0,0 +1,175 @@
+//! Per-language settings from `languages.toml`: which language server to
+//! run and how to find a file's project.
+//!
+//! ```toml
+//! [python]
+//! command = "pyright-langserver"
+//! args = ["--stdio"]
+//! roots = ["pyproject.toml", "setup.py"]
+//!
+//! [python.initialization-options]
+//! python.analysis.typeCheckingMode = "strict"
+//! ```
+//!
+//! Settings for a language replace the built-in ones field by field.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Language servers known without any configuration: (language, command, arguments)
+const DEFAULT_SERVERS: &[(&str, &str, &[&str])] = &[
+    ("rust", "rust-analyzer", &[]),
+    ("python", "pylsp", &[]),
+    ("go", "gopls", &[]),
+    ("c", "clangd", &[]),
+    ("cpp", "clangd", &[]),
+    ("javascript", "typescript-language-server", &["--stdio"]),
+    ("typescript", "typescript-language-server", &["--stdio"]),
+];
+
+/// Error reading `languages.toml`
+#[derive(Debug, thiserror::Error)]
+pub enum LanguagesError {
+    #[error("cannot read {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+    #[error("invalid {0}: {1}")]
+    Parse(PathBuf, toml::de::Error),
+}
+
+/// Settings of one language
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct LanguageConfig {
+    /// Language server command; no server runs without one
+    pub command: Option<String>,
+    pub args: Vec<String>,
+    /// Sent to the server as `initializationOptions`
+    pub initialization_options: Option<toml::Value>,
+    /// Files or directories marking a project root, nearest first. Without
+    /// any the git repository or a common build file decides.
+    pub roots: Vec<String>,
+}
+
+/// Settings of every language, by language id
+#[derive(Debug, Clone, PartialEq)]
+pub struct Languages {
+    languages: HashMap<String, LanguageConfig>,
+}
+
+impl Default for Languages {
+    fn default() -> Self {
+        let languages = DEFAULT_SERVERS
+            .iter()
+            .map(|(language, command, args)| {
+                let config = LanguageConfig {
+                    command: Some(command.to_string()),
+                    args: args.iter().map(|arg| arg.to_string()).collect(),
+                    ..Default::default()
+                };
+                (language.to_string(), config)
+            })
+            .collect();
+        Self { languages }
+    }
+}
+
+impl Languages {
+    /// Where the user's file lives: `languages.toml` next to `config.toml`
+    pub fn path() -> Option<PathBuf> {
+        let dirs = directories::ProjectDirs::from("", "", "lite")?;
+        Some(dirs.config_dir().join("languages.toml"))
+    }
+
+    /// The built-in settings with the user's file applied, if there is one
+    pub fn load() -> Result<Self, LanguagesError> {
+        let mut languages = Self::default();
+        if let Some(path) = Self::path().filter(|path| path.exists()) {
+            languages.merge_file(&path)?;
+        }
+        Ok(languages)
+    }
+
+    /// Apply the settings of a file over the current ones
+    pub fn merge_file(&mut self, path: &Path) -> Result<(), LanguagesError> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| LanguagesError::Io(path.to_path_buf(), e))?;
+        self.merge_str(&text)
+            .map_err(|e| LanguagesError::Parse(path.to_path_buf(), e))
+    }
+
+    /// Apply settings in `languages.toml` syntax over the current ones
+    pub fn merge_str(&mut self, text: &str) -> Result<(), toml::de::Error> {
+        self.merge_tables(toml::from_str(text)?)
+    }
+
+    /// Apply the settings of each language's table over the current ones
+    pub fn merge_tables(&mut self, tables: HashMap<String, toml::Table>) -> Result<(), toml::de::Error> {
+        for (language, table) in tables {
+            let overrides: LanguageConfig = toml::Value::Table(table.clone()).try_into()?;
+            let config = self.languages.entry(language).or_default();
+            if table.contains_key("command") {
+                config.command = overrides.command;
+            }
+            if table.contains_key("args") {
+                config.args = overrides.args;
+            }
+            if table.contains_key("initialization-options") {
+                config.initialization_options = overrides.initialization_options;
+            }
+            if table.contains_key("roots") {
+                config.roots = overrides.roots;
+            }
+        }
+        Ok(())
+    }
+
+    /// Settings of a language, if any
+    pub fn get(&self, language: &str) -> Option<&LanguageConfig> {
+        self.languages.get(language)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge() {
+        let mut languages = Languages::default();
+        languages
+            .merge_str(
+                r#"
+                [python]
+                command = "pyright-langserver"
+                args = ["--stdio"]
+
+                [python.initialization-options]
+                strict = true
+
+                [rust]
+                roots = ["Cargo.toml"]
+
+                [zig]
+                command = "zls"
+                "#,
+            )
+            .unwrap();
+
+        let python = languages.get("python").unwrap();
+        assert_eq!(python.command.as_deref(), Some("pyright-langserver"));
+        assert_eq!(python.args, vec!["--stdio"]);
+        assert_eq!(
+            python.initialization_options.as_ref().and_then(|o| o.get("strict")),
+            Some(&toml::Value::Boolean(true))
+        );
+
+        // Fields left out keep their built-in values
+        let rust = languages.get("rust").unwrap();
+        assert_eq!(rust.command.as_deref(), Some("rust-analyzer"));
+        assert_eq!(rust.roots, vec!["Cargo.toml"]);
+        assert_eq!(languages.get("zig").unwrap().command.as_deref(), Some("zls"));
+
+        assert!(languages.merge_str("[go]\ncmd = \"gopls\"").is_err());
+    }
+}
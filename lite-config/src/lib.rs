@@ -2,8 +2,15 @@
 
 mod config;
 mod keymap;
+mod languages;
+mod project;
 mod theme;
 
-pub use config::{Config, EditorConfig, IndentStyle};
+pub use config::{
+    AutoSaveTriggers, Bell, BufferOrder, Config, CryptFilter, CursorShape, CursorShapeConfig, DebugMetrics,
+    EditorConfig, IndentStyle, LowBandwidth, SearchCase,
+};
 pub use keymap::{Action, Key, KeyEvent, Keymap, Modifier};
-pub use theme::{Style, Theme};
+pub use languages::{LanguageConfig, Languages, LanguagesError};
+pub use project::{ProjectConfig, ProjectError};
+pub use theme::{Style, Theme, ThemeError};
@@ -0,0 +1,159 @@
+//! Settings a project shares with everyone working on it, from a
+//! `.lite.toml` at its top. They apply over the user's own settings.
+//!
+//! ```toml
+//! [editor]
+//! tab_width = 2
+//!
+//! [tasks]
+//! test = "cargo test"
+//!
+//! [formatters]
+//! rust = "rustfmt --edition 2021"
+//! javascript = "prettier --stdin-filepath {file}"
+//!
+//! [lint]
+//! rust = "cargo clippy --message-format short"
+//!
+//! [languages.python]
+//! args = ["--stdio"]
+//! ```
+//!
+//! Language servers start as soon as a file is opened, so a project can't
+//! choose their commands, only how they're started.
+
+use crate::{Config, EditorConfig};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// Error reading `.lite.toml`
+#[derive(Debug, thiserror::Error)]
+pub enum ProjectError {
+    #[error("cannot read {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+    #[error("invalid {0}: {1}")]
+    Parse(PathBuf, toml::de::Error),
+}
+
+/// Settings of a project
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProjectConfig {
+    /// Directory the file was found in; commands run there
+    #[serde(skip)]
+    pub root: PathBuf,
+    /// Editor options, replacing the user's one by one
+    editor: toml::Table,
+    /// Shell commands by name, run with `:task`
+    pub tasks: BTreeMap<String, String>,
+    /// Commands formatting a language, by language id: the text goes to
+    /// stdin, the result is read from stdout. `{file}` is the file's path.
+    pub formatters: HashMap<String, String>,
+    /// Commands checking a language, by language id, run with `:lint`
+    pub lint: HashMap<String, String>,
+    /// Language server settings, as in `languages.toml` but without the
+    /// command
+    languages: HashMap<String, toml::Table>,
+}
+
+impl ProjectConfig {
+    pub const FILE_NAME: &'static str = ".lite.toml";
+
+    /// The nearest `.lite.toml` in a directory or above it
+    pub fn find(dir: &Path) -> Option<PathBuf> {
+        dir.ancestors()
+            .map(|dir| dir.join(Self::FILE_NAME))
+            .find(|path| path.is_file())
+    }
+
+    /// Read a `.lite.toml`
+    pub fn load(path: &Path) -> Result<Self, ProjectError> {
+        let text = std::fs::read_to_string(path).map_err(|e| ProjectError::Io(path.to_path_buf(), e))?;
+        let mut project =
+            Self::parse(&text).map_err(|e| ProjectError::Parse(path.to_path_buf(), e))?;
+        project.root = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        Ok(project)
+    }
+
+    /// Parse settings in `.lite.toml` syntax. The editor options are checked
+    /// against the defaults, so mistakes show up before they're applied.
+    pub fn parse(text: &str) -> Result<Self, toml::de::Error> {
+        let project: Self = toml::from_str(text)?;
+        project.apply_editor(&EditorConfig::default())?;
+        if let Some(language) = project.languages.iter().find(|(_, table)| table.contains_key("command")) {
+            return Err(serde::de::Error::custom(format!(
+                "languages.{}.command can only be set in languages.toml",
+                language.0
+            )));
+        }
+        Ok(project)
+    }
+
+    /// Editor options with the project's applied over them
+    fn apply_editor(&self, config: &EditorConfig) -> Result<EditorConfig, toml::de::Error> {
+        let mut table = toml::Table::try_from(config).map_err(serde::de::Error::custom)?;
+        for (name, value) in &self.editor {
+            if !table.contains_key(name) {
+                return Err(serde::de::Error::custom(format!("unknown editor option `{}`", name)));
+            }
+            table.insert(name.clone(), value.clone());
+        }
+        toml::Value::Table(table).try_into()
+    }
+}
+
+impl Config {
+    /// Apply a project's settings over the current ones
+    pub fn apply_project(&mut self, project: ProjectConfig) -> Result<(), ProjectError> {
+        let path = project.root.join(ProjectConfig::FILE_NAME);
+        let parse = |e| ProjectError::Parse(path.clone(), e);
+        self.editor = project.apply_editor(&self.editor).map_err(parse)?;
+        self.languages.merge_tables(project.languages.clone()).map_err(parse)?;
+        self.project = project;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_project() {
+        let project = ProjectConfig::parse(
+            r#"
+            [editor]
+            tab_width = 2
+
+            [tasks]
+            test = "cargo test"
+
+            [formatters]
+            rust = "rustfmt --edition 2021"
+
+            [languages.rust]
+            args = ["--log-file", "ra.log"]
+            "#,
+        )
+        .unwrap();
+
+        let mut config = Config::default();
+        config.editor.breadcrumbs = false;
+        config.apply_project(project).unwrap();
+        assert_eq!(config.editor.tab_width, 2);
+        // Options the project doesn't set stay the user's
+        assert!(!config.editor.breadcrumbs);
+        assert_eq!(config.project.tasks["test"], "cargo test");
+        assert_eq!(config.project.formatters["rust"], "rustfmt --edition 2021");
+        let rust = config.languages.get("rust").unwrap();
+        assert_eq!(rust.command.as_deref(), Some("rust-analyzer"));
+        assert_eq!(rust.args, vec!["--log-file", "ra.log"]);
+
+        assert!(ProjectConfig::parse("[editor]\ntab_width = \"wide\"").is_err());
+        assert!(ProjectConfig::parse("[editor]\ntabwidth = 2").is_err());
+        assert!(ProjectConfig::parse("[task]\ntest = \"make\"").is_err());
+        // Opening a file in a cloned repo mustn't run a command it chose
+        assert!(ProjectConfig::parse("[languages.rust]\ncommand = \"./evil\"").is_err());
+    }
+}
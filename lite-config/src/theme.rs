@@ -1,5 +1,17 @@
 use ratatui::style::{Color, Modifier};
 use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Error loading a theme for `:theme`
+#[derive(Debug, thiserror::Error)]
+pub enum ThemeError {
+    #[error("no theme named {0}")]
+    Unknown(String),
+    #[error("cannot read {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+    #[error("invalid {0}: {1}")]
+    Parse(PathBuf, toml::de::Error),
+}
 
 /// Theme configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -11,6 +23,8 @@ pub struct Theme {
     pub foreground: Style,
     pub cursor: Style,
     pub selection: Style,
+    /// Background of the matches of the search query
+    pub search_match: Style,
     pub line_number: Style,
     pub line_number_current: Style,
     pub statusline: Style,
@@ -36,6 +50,12 @@ pub struct Theme {
     pub diff_add: Style,
     pub diff_delete: Style,
     pub diff_modify: Style,
+    /// Background of lines that differ between compared buffers
+    pub diff_line: Style,
+    /// Background of lines only the right compared buffer has
+    pub diff_line_added: Style,
+    /// Background of lines only the left compared buffer has
+    pub diff_line_removed: Style,
 
     // Diagnostics
     pub error: Style,
@@ -53,6 +73,7 @@ impl Default for Theme {
             foreground: Style::new().fg(Color::Rgb(171, 178, 191)),
             cursor: Style::new().bg(Color::Rgb(97, 175, 239)).fg(Color::Black),
             selection: Style::new().bg(Color::Rgb(62, 68, 81)),
+            search_match: Style::new().bg(Color::Rgb(78, 72, 52)),
             line_number: Style::new().fg(Color::Rgb(76, 82, 99)),
             line_number_current: Style::new().fg(Color::Rgb(171, 178, 191)),
             statusline: Style::new()
@@ -88,6 +109,9 @@ impl Default for Theme {
             diff_add: Style::new().fg(Color::Rgb(152, 195, 121)),
             diff_delete: Style::new().fg(Color::Rgb(224, 108, 117)),
             diff_modify: Style::new().fg(Color::Rgb(229, 192, 123)),
+            diff_line: Style::new().bg(Color::Rgb(50, 56, 68)),
+            diff_line_added: Style::new().bg(Color::Rgb(43, 58, 44)),
+            diff_line_removed: Style::new().bg(Color::Rgb(64, 45, 50)),
 
             // Diagnostics
             error: Style::new().fg(Color::Rgb(224, 108, 117)),
@@ -98,6 +122,52 @@ impl Default for Theme {
     }
 }
 
+impl Theme {
+    /// Where the user's themes live: `themes/<name>.toml` next to
+    /// `config.toml`
+    pub fn dir() -> Option<PathBuf> {
+        let dirs = directories::ProjectDirs::from("", "", "lite")?;
+        Some(dirs.config_dir().join("themes"))
+    }
+
+    /// Names of the themes there are, sorted: the built-in `default` and
+    /// the user's
+    pub fn names() -> Vec<String> {
+        Self::names_in(Self::dir().as_deref())
+    }
+
+    fn names_in(dir: Option<&Path>) -> Vec<String> {
+        let entries = dir.and_then(|dir| std::fs::read_dir(dir).ok()).into_iter().flatten().flatten();
+        let files = entries.map(|entry| entry.path()).filter(|path| path.extension().is_some_and(|ext| ext == "toml"));
+        let mut names: Vec<String> = files
+            .filter_map(|path| Some(path.file_stem()?.to_string_lossy().into_owned()))
+            .chain(["default".to_string()])
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// A theme by name. The user's file sets the styles it lists over the
+    /// default ones.
+    pub fn load(name: &str) -> Result<Self, ThemeError> {
+        Self::load_from(Self::dir().as_deref(), name)
+    }
+
+    fn load_from(dir: Option<&Path>, name: &str) -> Result<Self, ThemeError> {
+        let path = dir.map(|dir| dir.join(format!("{}.toml", name))).filter(|path| path.exists());
+        let Some(path) = path else {
+            return match name {
+                "default" => Ok(Self::default()),
+                _ => Err(ThemeError::Unknown(name.to_string())),
+            };
+        };
+        let text = std::fs::read_to_string(&path).map_err(|e| ThemeError::Io(path.clone(), e))?;
+        let theme: Self = toml::from_str(&text).map_err(|e| ThemeError::Parse(path, e))?;
+        Ok(Self { name: name.to_string(), ..theme })
+    }
+}
+
 /// Style with foreground, background, and modifiers
 #[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
 pub struct Style {
@@ -171,3 +241,30 @@ impl Style {
         style.add_modifier(mods)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_theme() {
+        let dir = std::env::temp_dir().join(format!("lite-themes-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("plain.toml"), "[keyword]\nbold = true\n").unwrap();
+        std::fs::write(dir.join("notes.txt"), "").unwrap();
+
+        assert_eq!(Theme::names_in(Some(&dir)), vec!["default", "plain"]);
+        assert_eq!(Theme::names_in(None), vec!["default"]);
+        let theme = Theme::load_from(Some(&dir), "plain").unwrap();
+        assert_eq!(theme.name, "plain");
+        assert!(theme.keyword.bold && theme.keyword.fg.is_none());
+        // Styles the file leaves out are the default ones
+        assert_eq!(theme.cursor.bg, Theme::default().cursor.bg);
+        assert_eq!(Theme::load_from(Some(&dir), "default").unwrap().name, "default");
+        assert!(matches!(Theme::load_from(Some(&dir), "dark"), Err(ThemeError::Unknown(_))));
+        std::fs::write(dir.join("broken.toml"), "[keyword\n").unwrap();
+        assert!(matches!(Theme::load_from(Some(&dir), "broken"), Err(ThemeError::Parse(..))));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,285 @@
+//! Line diffs, using Myers' algorithm for a shortest edit script
+
+use std::ops::Range;
+
+/// A run of differing lines: `before` in the old text became `after` in the
+/// new one. Either may be empty, for pure insertions and deletions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    pub before: Range<usize>,
+    pub after: Range<usize>,
+}
+
+/// Differences between two sequences of lines, in order
+pub fn diff<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Hunk> {
+    // Common ends are cheap to skip and usually most of the text
+    let prefix = common_prefix(old, new);
+    let suffix = common_suffix(&old[prefix..], &new[prefix..]);
+    let a = &old[prefix..old.len() - suffix];
+    let b = &new[prefix..new.len() - suffix];
+
+    let mut hunks = Vec::new();
+    let (mut x, mut y) = (0, 0);
+    for (mx, my) in matches(a, b).into_iter().chain([(a.len(), b.len())]) {
+        if mx > x || my > y {
+            hunks.push(Hunk {
+                before: prefix + x..prefix + mx,
+                after: prefix + y..prefix + my,
+            });
+        }
+        x = mx + 1;
+        y = my + 1;
+    }
+    hunks
+}
+
+/// Pairs of equal elements along a shortest edit script, in order.
+///
+/// Uses the linear space refinement of Myers' algorithm: the middle snake of
+/// the edit graph splits it in two halves that are diffed in turn, so only
+/// two diagonal arrays are kept instead of one per edit distance.
+fn matches<T: PartialEq>(a: &[T], b: &[T]) -> Vec<(usize, usize)> {
+    let max = (a.len() + b.len()).div_ceil(2) + 1;
+    let mut forward = Diagonals::new(max);
+    let mut backward = Diagonals::new(max);
+    let mut pairs = Vec::new();
+    conquer(a, 0..a.len(), b, 0..b.len(), &mut forward, &mut backward, &mut pairs);
+    pairs
+}
+
+/// Furthest x reached on each diagonal k = x - y, for k in `-max..=max + 1`
+struct Diagonals {
+    v: Vec<isize>,
+    offset: isize,
+}
+
+impl Diagonals {
+    fn new(max: usize) -> Self {
+        Self {
+            v: vec![0; 2 * max + 3],
+            offset: max as isize + 1,
+        }
+    }
+}
+
+impl std::ops::Index<isize> for Diagonals {
+    type Output = isize;
+
+    fn index(&self, k: isize) -> &isize {
+        &self.v[(k + self.offset) as usize]
+    }
+}
+
+impl std::ops::IndexMut<isize> for Diagonals {
+    fn index_mut(&mut self, k: isize) -> &mut isize {
+        &mut self.v[(k + self.offset) as usize]
+    }
+}
+
+/// Push the matches between `a[x]` and `b[y]` into `pairs`
+fn conquer<T: PartialEq>(
+    a: &[T],
+    mut x: Range<usize>,
+    b: &[T],
+    mut y: Range<usize>,
+    forward: &mut Diagonals,
+    backward: &mut Diagonals,
+    pairs: &mut Vec<(usize, usize)>,
+) {
+    let prefix = common_prefix(&a[x.clone()], &b[y.clone()]);
+    pairs.extend((0..prefix).map(|i| (x.start + i, y.start + i)));
+    x.start += prefix;
+    y.start += prefix;
+    let suffix = common_suffix(&a[x.clone()], &b[y.clone()]);
+    x.end -= suffix;
+    y.end -= suffix;
+
+    if !x.is_empty() && !y.is_empty() {
+        let (sx, sy) = middle_snake(&a[x.clone()], &b[y.clone()], forward, backward);
+        // A split at a corner wouldn't make the halves any smaller. That
+        // can't happen with the common ends stripped, but rather than
+        // recurse forever the whole range is left as one change.
+        if (sx, sy) != (0, 0) && (sx, sy) != (x.len(), y.len()) {
+            let (sx, sy) = (x.start + sx, y.start + sy);
+            conquer(a, x.start..sx, b, y.start..sy, forward, backward, pairs);
+            conquer(a, sx..x.end, b, sy..y.end, forward, backward, pairs);
+        }
+    }
+
+    pairs.extend((0..suffix).map(|i| (x.end + i, y.end + i)));
+}
+
+/// A point on a shortest edit script from the start of `a` and `b` to their
+/// end, found by searching from both ends until the two searches meet
+fn middle_snake<T: PartialEq>(
+    a: &[T],
+    b: &[T],
+    forward: &mut Diagonals,
+    backward: &mut Diagonals,
+) -> (usize, usize) {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let delta = n - m;
+    let odd = delta % 2 != 0;
+    forward[1] = 0;
+    backward[1] = 0;
+
+    for d in 0..=(n + m + 1) / 2 {
+        for k in (-d..=d).step_by(2) {
+            let x0 = if k == -d || (k != d && forward[k - 1] < forward[k + 1]) {
+                forward[k + 1]
+            } else {
+                forward[k - 1] + 1
+            };
+            let y0 = x0 - k;
+            let (mut x, mut y) = (x0, y0);
+            while 0 <= x && x < n && 0 <= y && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            forward[k] = x;
+            // The backward search on the same diagonal got this far at d - 1
+            if odd && (k - delta).abs() < d && x + backward[delta - k] >= n {
+                return (x0 as usize, y0 as usize);
+            }
+        }
+
+        // Backward, x and y count from the ends of `a` and `b`
+        for k in (-d..=d).step_by(2) {
+            let x0 = if k == -d || (k != d && backward[k - 1] < backward[k + 1]) {
+                backward[k + 1]
+            } else {
+                backward[k - 1] + 1
+            };
+            let (mut x, mut y) = (x0, x0 - k);
+            while 0 <= x
+                && x < n
+                && 0 <= y
+                && y < m
+                && a[(n - x - 1) as usize] == b[(m - y - 1) as usize]
+            {
+                x += 1;
+                y += 1;
+            }
+            backward[k] = x;
+            if !odd && (k - delta).abs() <= d && x + forward[delta - k] >= n {
+                return ((n - x) as usize, (m - y) as usize);
+            }
+        }
+    }
+    unreachable!("the searches meet by half the total length")
+}
+
+fn common_prefix<T: PartialEq>(a: &[T], b: &[T]) -> usize {
+    a.iter().zip(b).take_while(|(a, b)| a == b).count()
+}
+
+fn common_suffix<T: PartialEq>(a: &[T], b: &[T]) -> usize {
+    a.iter().rev().zip(b.iter().rev()).take_while(|(a, b)| a == b).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn lines(text: &str) -> Vec<&str> {
+        text.lines().collect()
+    }
+
+    /// Rebuild the new text from the old one and the hunks
+    fn patch<T: Clone>(old: &[T], new: &[T], hunks: &[Hunk]) -> Vec<T> {
+        let mut out = Vec::new();
+        let mut pos = 0;
+        for hunk in hunks {
+            out.extend_from_slice(&old[pos..hunk.before.start]);
+            out.extend_from_slice(&new[hunk.after.clone()]);
+            pos = hunk.before.end;
+        }
+        out.extend_from_slice(&old[pos..]);
+        out
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = lines("a\nb\nc\nd\ne");
+        let new = lines("a\nc\nd\nx\ne\nf");
+        assert_eq!(
+            diff(&old, &new),
+            vec![
+                Hunk { before: 1..2, after: 1..1 },
+                Hunk { before: 4..4, after: 3..4 },
+                Hunk { before: 5..5, after: 5..6 },
+            ]
+        );
+        assert_eq!(diff(&old, &old), Vec::new());
+        assert_eq!(diff::<&str>(&[], &new), vec![Hunk { before: 0..0, after: 0..6 }]);
+    }
+
+    #[test]
+    fn test_diff_patches() {
+        let texts = [
+            "",
+            "a",
+            "a\nb\nc",
+            "c\nb\na",
+            "a\nx\nb\ny\nc",
+            "x\ny\nz",
+            "a\na\nb\na\na",
+            "b\na\na\nb",
+        ];
+        for old in texts {
+            for new in texts {
+                let (old, new) = (lines(old), lines(new));
+                let hunks = diff(&old, &new);
+                assert_eq!(patch(&old, &new, &hunks), new, "{:?} -> {:?}", old, new);
+                // Hunks are separated by at least one unchanged line
+                assert!(hunks.windows(2).all(|w| w[0].before.end < w[1].before.start));
+            }
+        }
+    }
+
+    /// Length of the longest common subsequence, by dynamic programming
+    fn lcs_len(a: &[u8], b: &[u8]) -> usize {
+        let mut row = vec![0; b.len() + 1];
+        for x in a {
+            let mut diagonal = 0;
+            for (j, y) in b.iter().enumerate() {
+                let above = row[j + 1];
+                row[j + 1] = if x == y { diagonal + 1 } else { above.max(row[j]) };
+                diagonal = above;
+            }
+        }
+        row[b.len()]
+    }
+
+    #[test]
+    fn test_diff_large_rewrite() {
+        // Every other line changed and a block moved, so the edit distance
+        // is in the thousands
+        let old: Vec<String> = (0..5000).map(|i| format!("line {}", i)).collect();
+        let mut new: Vec<String> = old
+            .iter()
+            .enumerate()
+            .map(|(i, line)| if i % 2 == 0 { format!("{} changed", line) } else { line.clone() })
+            .collect();
+        new.rotate_left(1000);
+
+        let hunks = diff(&old, &new);
+        assert_eq!(patch(&old, &new, &hunks), new);
+        let kept: usize = old.len() - hunks.iter().map(|h| h.before.len()).sum::<usize>();
+        assert_eq!(kept, 2000);
+    }
+
+    proptest! {
+        #[test]
+        fn test_prop_matches_shortest(
+            a in prop::collection::vec(0u8..4, 0..40),
+            b in prop::collection::vec(0u8..4, 0..40),
+        ) {
+            let pairs = matches(&a, &b);
+            prop_assert!(pairs.iter().all(|&(x, y)| a[x] == b[y]));
+            prop_assert!(pairs.windows(2).all(|w| w[0].0 < w[1].0 && w[0].1 < w[1].1));
+            prop_assert_eq!(pairs.len(), lcs_len(&a, &b));
+        }
+    }
+}
@@ -0,0 +1,55 @@
+//! Fuzzy matching of a typed query against names, for pickers
+
+/// Score `text` against `query`, ignoring case, or `None` if it doesn't
+/// match. Every whitespace-separated word of the query has to appear in the
+/// text with its chars in order. Higher scores are better matches: chars in
+/// a row, at the start of a word or path component, and shorter texts.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    let mut score = 0;
+    for word in query.split_whitespace() {
+        score += score_word(word, &text)?;
+    }
+    Some(score - text.len() as i64)
+}
+
+fn score_word(word: &str, text: &[char]) -> Option<i64> {
+    let mut score = 0;
+    let mut next = 0;
+    let mut previous: Option<usize> = None;
+    for c in word.chars().flat_map(char::to_lowercase) {
+        let i = next + text[next..].iter().position(|&t| t == c)?;
+        score += 1;
+        if previous.is_some_and(|previous| previous + 1 == i) {
+            score += 8;
+        }
+        if i == 0 || matches!(text[i - 1], '/' | '\\' | '_' | '-' | '.' | ' ' | ':') {
+            score += 6;
+        }
+        previous = Some(i);
+        next = i + 1;
+    }
+    Some(score * 4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert!(fuzzy_score("edr", "lite-view/src/editor.rs").is_some());
+        assert!(fuzzy_score("EDITOR", "editor.rs").is_some());
+        assert!(fuzzy_score("rde", "editor.rs").is_none());
+        assert!(fuzzy_score("view ed", "lite-view/src/editor.rs").is_some());
+        assert!(fuzzy_score("view xy", "lite-view/src/editor.rs").is_none());
+        assert_eq!(fuzzy_score("", "a"), Some(-1));
+
+        // Consecutive chars and word starts beat scattered ones
+        let score = |text| fuzzy_score("edit", text).unwrap();
+        assert!(score("src/editor.rs") > score("src/e_d_i_t.rs"));
+        assert!(score("src/editor.rs") > score("src/credits.rs"));
+        // Then shorter names win
+        assert!(score("editor.rs") > score("src/editor.rs"));
+    }
+}
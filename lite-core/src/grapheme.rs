@@ -15,12 +15,42 @@ pub fn grapheme_width(grapheme: &str) -> usize {
     }
 }
 
-/// Iterator over grapheme clusters in a RopeSlice
+/// Columns a grapheme takes when drawn at display column `col`. Tabs reach
+/// the next multiple of `tab_width`.
+pub fn visual_width(grapheme: &str, col: usize, tab_width: usize) -> usize {
+    if grapheme == "\t" {
+        let tab_width = tab_width.max(1);
+        tab_width - col % tab_width
+    } else {
+        grapheme_width(grapheme)
+    }
+}
+
+/// Display column of the char at `char_col` in a line
+pub fn visual_col(line: RopeSlice, char_col: usize, tab_width: usize) -> usize {
+    let mut col = 0;
+    let mut chars = 0;
+    for grapheme in RopeGraphemes::new(line) {
+        if chars >= char_col {
+            break;
+        }
+        col += visual_width(grapheme, col, tab_width);
+        chars += grapheme.chars().count();
+    }
+    col
+}
+
+/// Iterator over grapheme clusters in a RopeSlice. A grapheme split across
+/// two chunks of the rope comes out as two pieces, one from each.
 pub struct RopeGraphemes<'a> {
     text: RopeSlice<'a>,
     chunks: ropey::iter::Chunks<'a>,
     cur_chunk: &'a str,
     cur_chunk_start: usize,
+    /// The chunk before the current one, where a grapheme ending at the
+    /// start of the current chunk is
+    prev_chunk: &'a str,
+    prev_chunk_start: usize,
     cursor: GraphemeCursor,
 }
 
@@ -33,6 +63,8 @@ impl<'a> RopeGraphemes<'a> {
             chunks,
             cur_chunk: first_chunk,
             cur_chunk_start: 0,
+            prev_chunk: "",
+            prev_chunk_start: 0,
             cursor: GraphemeCursor::new(0, text.len_bytes(), true),
         }
     }
@@ -47,32 +79,17 @@ impl<'a> Iterator for RopeGraphemes<'a> {
             return None;
         }
 
-        loop {
+        let end = loop {
             match self
                 .cursor
                 .next_boundary(self.cur_chunk, self.cur_chunk_start)
             {
                 Ok(None) => return None,
-                Ok(Some(end)) => {
-                    // We found the boundary
-                    let start_in_chunk = start - self.cur_chunk_start;
-                    let end_in_chunk = end - self.cur_chunk_start;
-
-                    if end_in_chunk <= self.cur_chunk.len() {
-                        return Some(&self.cur_chunk[start_in_chunk..end_in_chunk]);
-                    } else {
-                        // Grapheme spans multiple chunks - need to collect
-                        // For simplicity, we'll use the rope's byte_slice
-                        let grapheme_bytes = self.text.byte_slice(start..end);
-                        // This is a bit inefficient but handles edge cases
-                        if let Some(cow) = grapheme_bytes.chunks().next() {
-                            return Some(cow);
-                        }
-                        return None;
-                    }
-                }
+                Ok(Some(end)) => break end,
                 Err(GraphemeIncomplete::NextChunk) => {
                     // Need more chunks
+                    self.prev_chunk = self.cur_chunk;
+                    self.prev_chunk_start = self.cur_chunk_start;
                     self.cur_chunk_start += self.cur_chunk.len();
                     self.cur_chunk = self.chunks.next().unwrap_or("");
                 }
@@ -87,7 +104,18 @@ impl<'a> Iterator for RopeGraphemes<'a> {
                 }
                 Err(_) => return None,
             }
+        };
+
+        if start >= self.cur_chunk_start {
+            return Some(&self.cur_chunk[start - self.cur_chunk_start..end - self.cur_chunk_start]);
+        }
+        let prev = &self.prev_chunk[start.checked_sub(self.prev_chunk_start)?..];
+        if end > self.cur_chunk_start {
+            // The rest of the grapheme comes next, from the current chunk
+            self.cursor.set_cursor(self.cur_chunk_start);
+            return Some(prev);
         }
+        Some(&prev[..end - start])
     }
 }
 
@@ -218,10 +246,37 @@ mod tests {
         assert_eq!(grapheme_width("\t"), 1);
     }
 
+    #[test]
+    fn test_visual_col() {
+        let rope = Rope::from("a\tb中c\n");
+        let line = rope.slice(..);
+        assert_eq!(visual_col(line, 1, 4), 1);
+        assert_eq!(visual_col(line, 2, 4), 4);
+        assert_eq!(visual_col(line, 4, 4), 7);
+        assert_eq!(visual_col(line, 2, 8), 8);
+    }
+
     #[test]
     fn test_grapheme_iterator() {
         let rope = Rope::from("hello");
         let graphemes: Vec<_> = RopeGraphemes::new(rope.slice(..)).collect();
         assert_eq!(graphemes, vec!["h", "e", "l", "l", "o"]);
     }
+
+    #[test]
+    fn test_grapheme_iterator_across_chunks() {
+        let text = "a\t中".repeat(2000);
+        let rope = Rope::from(text.as_str());
+        assert!(rope.chunks().count() > 1);
+        let graphemes: Vec<_> = RopeGraphemes::new(rope.slice(..)).collect();
+        assert_eq!(graphemes.len(), 6000);
+        assert_eq!(graphemes.concat(), text);
+
+        // Combining accents may be split from their letter at a chunk
+        // boundary, but no text is lost or repeated
+        let text = "e\u{301}".repeat(3000);
+        let rope = Rope::from(text.as_str());
+        let graphemes: Vec<_> = RopeGraphemes::new(rope.slice(1..)).collect();
+        assert_eq!(graphemes.concat(), text[1..]);
+    }
 }
@@ -5,14 +5,64 @@
 //! - `Selection`: Multi-cursor selections
 //! - `Transaction`: Atomic text operations with undo support
 //! - `Position`: Line/column position utilities
+//!
+//! It has no knowledge of the editor, so scripts and other tools can use it
+//! to edit text the same way lite does.
+//!
+//! # Invariants
+//!
+//! - Every index into a document is a **char index** (a Unicode scalar
+//!   value, as in [`Rope::char_to_line`]), never a byte offset. The only
+//!   exceptions are named as such: `Position::from_offset`/`to_offset` and
+//!   the `*_byte*` helpers of [`RopeExt`].
+//! - A [`ChangeSet`] is built for a document of exactly `doc_len` chars and
+//!   produces one of `new_len()` chars. Applying it to a rope of another
+//!   length is a bug.
+//! - [`ChangeSet::compose`] takes `a` then `b` to one changeset with the same
+//!   effect; it returns `None` unless `a.new_len() == b.doc_len`.
+//! - [`ChangeSet::invert`] needs the text *before* the changeset was applied
+//!   and returns a changeset restoring it.
+//! - [`ChangeSet::map_pos`] moves a position across an edit: positions in
+//!   deleted text collapse to where the deletion was, before any text
+//!   replacing it, and text inserted exactly at a position ends up before it.
+//! - A [`Selection`] always has at least one range; its ranges are sorted by
+//!   start and never overlap or touch (touching ranges are merged).
+//!
+//! # Example
+//!
+//! ```
+//! use lite_core::{Change, Rope, Selection, Transaction};
+//!
+//! let mut rope = Rope::from("hello world");
+//! let original = rope.clone();
+//! let tx = Transaction::changes(
+//!     rope.len_chars(),
+//!     &[Change::replace(0, 5, "goodbye"), Change::insert(11, "!")],
+//! );
+//! tx.apply(&mut rope);
+//! assert_eq!(rope.to_string(), "goodbye world!");
+//!
+//! // A cursor after "world" follows the text it was at
+//! let cursor = Selection::point(11).map(&tx.changes);
+//! assert_eq!(cursor.cursor(), 14);
+//!
+//! tx.invert(&original, &cursor).apply(&mut rope);
+//! assert_eq!(rope, original);
+//! ```
 
+mod diff;
+mod fuzzy;
 mod grapheme;
 mod position;
 mod rope_ext;
 mod selection;
 mod transaction;
 
-pub use grapheme::{grapheme_width, nth_next_grapheme, nth_prev_grapheme, RopeGraphemes};
+pub use diff::{diff, Hunk};
+pub use fuzzy::fuzzy_score;
+pub use grapheme::{
+    grapheme_width, nth_next_grapheme, nth_prev_grapheme, visual_col, visual_width, RopeGraphemes,
+};
 pub use position::Position;
 pub use ropey::{Rope, RopeSlice};
 pub use rope_ext::RopeExt;
@@ -30,7 +30,7 @@ impl Position {
         }
         let line_start = rope.line_to_byte(self.line);
         let line_len = rope.line(self.line).len_bytes();
-        line_start + self.col.min(line_len.saturating_sub(1).max(0))
+        line_start + self.col.min(line_len.saturating_sub(1))
     }
 
     /// Convert Position to char offset
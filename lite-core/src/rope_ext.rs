@@ -35,6 +35,13 @@ pub trait RopeExt {
 
     /// Check if char at index is a word character
     fn is_word_char(&self, char_idx: usize) -> bool;
+
+    /// Find the first occurrence of `needle` at or after `from_char`, as a char index
+    fn find_str(&self, needle: &str, from_char: usize) -> Option<usize>;
+
+    /// Char offset in a line where its trailing spaces and tabs start, if it
+    /// ends with any
+    fn trailing_whitespace(&self, line_idx: usize) -> Option<usize>;
 }
 
 impl RopeExt for Rope {
@@ -138,6 +145,22 @@ impl RopeExt for Rope {
         let c = self.char(char_idx);
         c.is_alphanumeric() || c == '_'
     }
+
+    fn find_str(&self, needle: &str, from_char: usize) -> Option<usize> {
+        if needle.is_empty() || from_char > self.len_chars() {
+            return None;
+        }
+        let haystack: String = self.slice(from_char..).chars().collect();
+        let byte_idx = haystack.find(needle)?;
+        Some(from_char + haystack[..byte_idx].chars().count())
+    }
+
+    fn trailing_whitespace(&self, line_idx: usize) -> Option<usize> {
+        let line = self.get_line(line_idx)?;
+        let len = line_len_chars_without_newline(line);
+        let blanks = line.chars_at(len).reversed().take_while(|c| matches!(c, ' ' | '\t')).count();
+        (blanks > 0).then_some(len - blanks)
+    }
 }
 
 /// Get line length in bytes without trailing newline
@@ -212,4 +235,23 @@ mod tests {
         assert_eq!(rope.char_to_position(6), Position::new(1, 0));
         assert_eq!(rope.position_to_char(Position::new(1, 0)), 6);
     }
+
+    #[test]
+    fn test_find_str() {
+        let rope = Rope::from("héllo wörld wörld");
+        assert_eq!(rope.find_str("wörld", 0), Some(6));
+        assert_eq!(rope.find_str("wörld", 7), Some(12));
+        assert_eq!(rope.find_str("x", 0), None);
+        assert_eq!(rope.find_str("d", 20), None);
+    }
+
+    #[test]
+    fn test_trailing_whitespace() {
+        let rope = Rope::from("clean\nspaces  \r\n\t \nmixed \tend");
+        assert_eq!(rope.trailing_whitespace(0), None);
+        assert_eq!(rope.trailing_whitespace(1), Some(6));
+        assert_eq!(rope.trailing_whitespace(2), Some(0));
+        assert_eq!(rope.trailing_whitespace(3), None);
+        assert_eq!(rope.trailing_whitespace(9), None);
+    }
 }
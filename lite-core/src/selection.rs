@@ -1,3 +1,4 @@
+use crate::ChangeSet;
 use smallvec::SmallVec;
 use std::cmp::Ordering;
 
@@ -219,6 +220,11 @@ impl Selection {
         Self::new(ranges, self.primary_idx)
     }
 
+    /// Move every range across an edit, following [`ChangeSet::map_pos`]
+    pub fn map(&self, changes: &ChangeSet) -> Self {
+        self.transform(|range| Range::new(changes.map_pos(range.anchor), changes.map_pos(range.head)))
+    }
+
     /// Add a new cursor at position
     pub fn add_cursor(&mut self, pos: usize) {
         self.ranges.push(Range::point(pos));
@@ -321,7 +327,7 @@ mod tests {
 
     #[test]
     fn test_selection_normalize() {
-        let mut sel = Selection::new(
+        let sel = Selection::new(
             smallvec::smallvec![
                 Range::new(10, 15),
                 Range::new(0, 5),
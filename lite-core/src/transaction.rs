@@ -98,6 +98,33 @@ impl ChangeSet {
         cs
     }
 
+    /// Create a changeset from several changes, sorted by position and not
+    /// overlapping. Positions refer to the original document.
+    pub fn from_changes(doc_len: usize, changes: &[Change]) -> Self {
+        let mut cs = Self::new(doc_len);
+        let mut pos = 0;
+
+        for change in changes {
+            debug_assert!(change.start >= pos, "changes must be sorted and disjoint");
+            let start = change.start.max(pos);
+            if start > pos {
+                cs.ops.push(Operation::Retain(start - pos));
+            }
+            if change.end > start {
+                cs.ops.push(Operation::Delete(change.end - start));
+            }
+            if !change.insert.is_empty() {
+                cs.ops.push(Operation::Insert(change.insert.to_string()));
+            }
+            pos = change.end.max(start);
+        }
+
+        if pos < doc_len {
+            cs.ops.push(Operation::Retain(doc_len - pos));
+        }
+        cs
+    }
+
     /// Check if the changeset is empty (no actual changes)
     pub fn is_empty(&self) -> bool {
         self.ops.iter().all(|op| matches!(op, Operation::Retain(_)))
@@ -116,8 +143,9 @@ impl ChangeSet {
         len
     }
 
-    /// Apply this changeset to a rope
+    /// Apply this changeset to a rope of `doc_len` chars
     pub fn apply(&self, rope: &mut Rope) {
+        debug_assert_eq!(rope.len_chars(), self.doc_len, "changeset applied to a different document");
         let mut pos = 0;
 
         for op in &self.ops {
@@ -163,77 +191,86 @@ impl ChangeSet {
         inverted
     }
 
-    /// Compose two changesets into one
+    /// Create a changeset that leaves a document of given length untouched
+    pub fn identity(doc_len: usize) -> Self {
+        let mut cs = Self::new(doc_len);
+        if doc_len > 0 {
+            cs.ops.push(Operation::Retain(doc_len));
+        }
+        cs
+    }
+
+    /// Push an operation, merging it with the previous one when they are of the same kind
+    fn push(&mut self, op: Operation) {
+        match (self.ops.last_mut(), op) {
+            (_, Operation::Retain(0) | Operation::Delete(0)) => {}
+            (_, Operation::Insert(s)) if s.is_empty() => {}
+            (Some(Operation::Retain(a)), Operation::Retain(b)) => *a += b,
+            (Some(Operation::Delete(a)), Operation::Delete(b)) => *a += b,
+            (Some(Operation::Insert(a)), Operation::Insert(b)) => a.push_str(&b),
+            (_, op) => self.ops.push(op),
+        }
+    }
+
+    /// Compose two changesets into one.
+    ///
+    /// `other` must apply to the document produced by `self`; returns `None`
+    /// if the lengths don't line up.
     pub fn compose(&self, other: &ChangeSet) -> Option<ChangeSet> {
         if self.new_len() != other.doc_len {
             return None;
         }
 
         let mut composed = ChangeSet::new(self.doc_len);
-        let mut ops_a = self.ops.iter().peekable();
-        let mut ops_b = other.ops.iter().peekable();
-        let mut len_a = 0;
-        let mut len_b = 0;
+        // Empty operations would otherwise be left over once the other side ends
+        let mut ops_a = self.ops.iter().filter(|op| !is_noop(op)).cloned();
+        let mut ops_b = other.ops.iter().filter(|op| !is_noop(op)).cloned();
+        let mut head_a = ops_a.next();
+        let mut head_b = ops_b.next();
 
         loop {
-            let op_a = ops_a.peek();
-            let op_b = ops_b.peek();
-
-            match (op_a, op_b) {
+            match (head_a.take(), head_b.take()) {
                 (None, None) => break,
-                (Some(Operation::Insert(s)), _) => {
-                    composed.ops.push(Operation::Insert(s.clone()));
-                    ops_a.next();
+                // Deletions in the first changeset never reach the second
+                (Some(Operation::Delete(n)), b) => {
+                    composed.push(Operation::Delete(n));
+                    head_a = ops_a.next();
+                    head_b = b;
                 }
-                (_, Some(Operation::Delete(n))) => {
-                    composed.ops.push(Operation::Delete(*n));
-                    ops_b.next();
-                }
-                (None, Some(op)) => {
-                    composed.ops.push((*op).clone());
-                    ops_b.next();
-                }
-                (Some(op), None) => {
-                    composed.ops.push((*op).clone());
-                    ops_a.next();
+                // Insertions in the second changeset don't consume anything from the first
+                (a, Some(Operation::Insert(s))) => {
+                    composed.push(Operation::Insert(s));
+                    head_a = a;
+                    head_b = ops_b.next();
                 }
+                (None, Some(_)) | (Some(_), None) => return None,
                 (Some(Operation::Retain(a)), Some(Operation::Retain(b))) => {
-                    let min = (*a - len_a).min(*b - len_b);
-                    composed.ops.push(Operation::Retain(min));
-                    len_a += min;
-                    len_b += min;
-                    if len_a == *a {
-                        ops_a.next();
-                        len_a = 0;
-                    }
-                    if len_b == *b {
-                        ops_b.next();
-                        len_b = 0;
-                    }
+                    let n = a.min(b);
+                    composed.push(Operation::Retain(n));
+                    head_a = remainder(Operation::Retain(a - n), &mut ops_a);
+                    head_b = remainder(Operation::Retain(b - n), &mut ops_b);
                 }
-                (Some(Operation::Retain(_)), Some(Operation::Insert(s))) => {
-                    composed.ops.push(Operation::Insert(s.clone()));
-                    ops_b.next();
+                (Some(Operation::Retain(a)), Some(Operation::Delete(b))) => {
+                    let n = a.min(b);
+                    composed.push(Operation::Delete(n));
+                    head_a = remainder(Operation::Retain(a - n), &mut ops_a);
+                    head_b = remainder(Operation::Delete(b - n), &mut ops_b);
                 }
-                (Some(Operation::Delete(n)), Some(Operation::Retain(r))) => {
-                    let min = (*n - len_a).min(*r - len_b);
-                    composed.ops.push(Operation::Delete(min));
-                    len_a += min;
-                    len_b += min;
-                    if len_a == *n {
-                        ops_a.next();
-                        len_a = 0;
-                    }
-                    if len_b == *r {
-                        ops_b.next();
-                        len_b = 0;
-                    }
+                (Some(Operation::Insert(s)), Some(Operation::Retain(b))) => {
+                    let len = s.chars().count();
+                    let n = len.min(b);
+                    let (head, tail) = split_at_char(&s, n);
+                    composed.push(Operation::Insert(head.to_string()));
+                    head_a = remainder(Operation::Insert(tail.to_string()), &mut ops_a);
+                    head_b = remainder(Operation::Retain(b - n), &mut ops_b);
                 }
-                (Some(Operation::Delete(n)), Some(Operation::Insert(s))) => {
-                    composed.ops.push(Operation::Delete(*n));
-                    composed.ops.push(Operation::Insert(s.clone()));
-                    ops_a.next();
-                    ops_b.next();
+                (Some(Operation::Insert(s)), Some(Operation::Delete(b))) => {
+                    // Text inserted by the first changeset and deleted by the second cancels out
+                    let len = s.chars().count();
+                    let n = len.min(b);
+                    let (_, tail) = split_at_char(&s, n);
+                    head_a = remainder(Operation::Insert(tail.to_string()), &mut ops_a);
+                    head_b = remainder(Operation::Delete(b - n), &mut ops_b);
                 }
             }
         }
@@ -241,42 +278,68 @@ impl ChangeSet {
         Some(composed)
     }
 
-    /// Map a position through this changeset
-    pub fn map_pos(&self, mut pos: usize) -> usize {
+    /// Map a position through this changeset.
+    ///
+    /// Positions inside a deleted range collapse to its start, before any
+    /// text replacing it; text inserted exactly at `pos` ends up before the
+    /// mapped position.
+    pub fn map_pos(&self, pos: usize) -> usize {
         let mut old_pos = 0;
         let mut new_pos = 0;
+        // Inserted chars not yet counted: where they go relative to `pos`
+        // depends on whether a deletion at the same place covers it
+        let mut inserted = 0;
 
         for op in &self.ops {
-            if old_pos > pos {
-                break;
-            }
-
             match op {
+                Operation::Retain(0) => {}
                 Operation::Retain(n) => {
+                    new_pos += std::mem::take(&mut inserted);
+                    if old_pos + n > pos {
+                        return new_pos + (pos - old_pos);
+                    }
                     old_pos += n;
                     new_pos += n;
                 }
                 Operation::Insert(s) => {
-                    let len = s.chars().count();
-                    if old_pos <= pos {
-                        new_pos += len;
-                    }
+                    inserted += s.chars().count();
                 }
                 Operation::Delete(n) => {
-                    if old_pos + n <= pos {
-                        pos -= n;
-                    } else if old_pos < pos {
-                        pos = old_pos;
+                    if old_pos + n > pos {
+                        return new_pos;
                     }
                     old_pos += n;
                 }
             }
         }
 
-        new_pos.min(pos)
+        new_pos + inserted + pos.saturating_sub(old_pos)
+    }
+}
+
+/// Whether an operation changes nothing and consumes nothing
+fn is_noop(op: &Operation) -> bool {
+    match op {
+        Operation::Retain(n) | Operation::Delete(n) => *n == 0,
+        Operation::Insert(s) => s.is_empty(),
     }
 }
 
+/// Keep a partially consumed operation, or advance to the next one if nothing is left
+fn remainder(op: Operation, ops: &mut impl Iterator<Item = Operation>) -> Option<Operation> {
+    match op {
+        Operation::Retain(0) | Operation::Delete(0) => ops.next(),
+        Operation::Insert(ref s) if s.is_empty() => ops.next(),
+        op => Some(op),
+    }
+}
+
+/// Split a string after `n` chars
+fn split_at_char(s: &str, n: usize) -> (&str, &str) {
+    let idx = s.char_indices().nth(n).map(|(i, _)| i).unwrap_or(s.len());
+    s.split_at(idx)
+}
+
 /// A transaction groups changes with selection and metadata
 #[derive(Debug, Clone)]
 pub struct Transaction {
@@ -300,6 +363,11 @@ impl Transaction {
         Self::new(ChangeSet::from_change(doc_len, &change))
     }
 
+    /// Create a transaction from sorted, non-overlapping changes
+    pub fn changes(doc_len: usize, changes: &[Change]) -> Self {
+        Self::new(ChangeSet::from_changes(doc_len, changes))
+    }
+
     /// Create an insert transaction
     pub fn insert(doc_len: usize, pos: usize, text: impl Into<Cow<'static, str>>) -> Self {
         Self::change(doc_len, Change::insert(pos, text))
@@ -348,6 +416,7 @@ impl Transaction {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
 
     #[test]
     fn test_insert() {
@@ -373,6 +442,18 @@ mod tests {
         assert_eq!(rope.to_string(), "hello rust");
     }
 
+    #[test]
+    fn test_multiple_changes() {
+        let mut rope = Rope::from("use a;\nfn f() { x }");
+        let tx = Transaction::changes(
+            19,
+            &[Change::insert(6, "\nuse b;"), Change::replace(16, 17, "y()")],
+        );
+        tx.apply(&mut rope);
+        assert_eq!(rope.to_string(), "use a;\nuse b;\nfn f() { y() }");
+        assert_eq!(tx.changes.map_pos(18), 27);
+    }
+
     #[test]
     fn test_invert() {
         let original = Rope::from("hello world");
@@ -395,4 +476,267 @@ mod tests {
         let cs = ChangeSet::from_change(21, &Change::delete(5, 15));
         assert_eq!(cs.new_len(), 11);
     }
+
+    #[test]
+    fn test_compose() {
+        let original = Rope::from("hello world");
+        let a = ChangeSet::from_change(11, &Change::insert(5, " beautiful"));
+        let b = ChangeSet::from_change(21, &Change::replace(16, 21, "rust"));
+
+        let mut expected = original.clone();
+        a.apply(&mut expected);
+        b.apply(&mut expected);
+
+        let composed = a.compose(&b).unwrap();
+        let mut rope = original.clone();
+        composed.apply(&mut rope);
+        assert_eq!(rope.to_string(), expected.to_string());
+        assert_eq!(rope.to_string(), "hello beautiful rust");
+        assert_eq!(composed.doc_len, 11);
+        assert_eq!(composed.new_len(), 20);
+    }
+
+    #[test]
+    fn test_compose_insert_then_delete() {
+        let a = ChangeSet::from_change(3, &Change::insert(1, "xyz"));
+        let b = ChangeSet::from_change(6, &Change::delete(0, 3));
+
+        let composed = a.compose(&b).unwrap();
+        let mut rope = Rope::from("abc");
+        composed.apply(&mut rope);
+        assert_eq!(rope.to_string(), "zbc");
+    }
+
+    #[test]
+    fn test_map_pos() {
+        // "hello world" -> "hello beautiful world"
+        let cs = ChangeSet::from_change(11, &Change::insert(5, " beautiful"));
+        assert_eq!(cs.map_pos(0), 0);
+        assert_eq!(cs.map_pos(5), 15);
+        assert_eq!(cs.map_pos(8), 18);
+        assert_eq!(cs.map_pos(11), 21);
+
+        // "hello beautiful world" -> "hello world"
+        let cs = ChangeSet::from_change(21, &Change::delete(5, 15));
+        assert_eq!(cs.map_pos(3), 3);
+        assert_eq!(cs.map_pos(10), 5);
+        assert_eq!(cs.map_pos(15), 5);
+        assert_eq!(cs.map_pos(21), 11);
+    }
+
+    /// Text mixing one to four byte chars and newlines
+    fn text(max_len: usize) -> impl Strategy<Value = String> {
+        const CHARS: &[char] = &['a', 'b', ' ', '\n', 'é', '中', '😀'];
+        prop::collection::vec(prop::sample::select(CHARS), 0..=max_len).prop_map(|chars| chars.into_iter().collect())
+    }
+
+    /// Sorted, disjoint changes for a document of `doc_len` chars, as
+    /// `from_changes` builds them
+    fn changeset(doc_len: usize) -> impl Strategy<Value = ChangeSet> {
+        prop::collection::vec((any::<usize>(), 0..=5usize, text(4)), 0..=4).prop_map(move |parts| {
+            let mut changes = Vec::new();
+            let mut pos = 0;
+            for (skip, len, text) in parts {
+                if pos > doc_len {
+                    break;
+                }
+                let start = pos + skip % (doc_len - pos + 1);
+                let end = start + len.min(doc_len - start);
+                changes.push(Change::replace(start, end, text));
+                // Keep changes apart so none start where the last one ended
+                pos = end + 1;
+            }
+            ChangeSet::from_changes(doc_len, &changes)
+        })
+    }
+
+    /// Operations in any order, including ones `from_changes` never
+    /// produces: adjacent inserts, inserts before deletes, zero-length ops
+    fn raw_changeset(doc_len: usize) -> impl Strategy<Value = ChangeSet> {
+        let op = prop_oneof![
+            text(3).prop_map(Operation::Insert),
+            (0..=4usize).prop_map(Operation::Delete),
+            (0..=4usize).prop_map(Operation::Retain),
+        ];
+        (prop::collection::vec(op, 0..=12), any::<bool>()).prop_map(move |(ops, delete_rest)| {
+            let mut cs = ChangeSet::new(doc_len);
+            let mut left = doc_len;
+            for op in ops {
+                let op = match op {
+                    Operation::Delete(n) => Operation::Delete(n.min(left)),
+                    Operation::Retain(n) => Operation::Retain(n.min(left)),
+                    insert => insert,
+                };
+                if let Operation::Retain(n) | Operation::Delete(n) = op {
+                    left -= n;
+                }
+                cs.ops.push(op);
+            }
+            if left > 0 {
+                cs.ops.push(if delete_rest { Operation::Delete(left) } else { Operation::Retain(left) });
+            }
+            cs
+        })
+    }
+
+    /// A text and a changeset for it
+    fn edit<S: Strategy<Value = ChangeSet>>(
+        changes: impl Fn(usize) -> S + Copy,
+    ) -> impl Strategy<Value = (String, ChangeSet)> {
+        text(20).prop_flat_map(move |text| {
+            let len = text.chars().count();
+            (Just(text), changes(len))
+        })
+    }
+
+    /// A text and two changesets, the second for the result of the first
+    fn two_edits<S: Strategy<Value = ChangeSet>>(
+        changes: impl Fn(usize) -> S + Copy,
+    ) -> impl Strategy<Value = (String, ChangeSet, ChangeSet)> {
+        edit(changes).prop_flat_map(move |(text, a)| {
+            let len = a.new_len();
+            (Just(text), Just(a), changes(len))
+        })
+    }
+
+    /// Reference implementation: edit a plain char vector
+    fn naive_apply(changes: &ChangeSet, text: &str) -> String {
+        let chars: Vec<char> = text.chars().collect();
+        let mut out = String::new();
+        let mut pos = 0;
+        for op in &changes.ops {
+            match op {
+                Operation::Retain(n) => {
+                    out.extend(&chars[pos..pos + n]);
+                    pos += n;
+                }
+                Operation::Delete(n) => pos += n,
+                Operation::Insert(s) => out.push_str(s),
+            }
+        }
+        out
+    }
+
+    fn applied(changes: &ChangeSet, rope: &Rope) -> Rope {
+        let mut rope = rope.clone();
+        changes.apply(&mut rope);
+        rope
+    }
+
+    proptest! {
+        #[test]
+        fn test_prop_invert_roundtrip((text, changes) in edit(changeset)) {
+            let original = Rope::from(text);
+            let edited = applied(&changes, &original);
+            prop_assert_eq!(edited.len_chars(), changes.new_len());
+            let inverse = changes.invert(&original);
+            prop_assert_eq!(applied(&inverse, &edited), original.clone());
+            // Inverting the inverse gives back the same edit
+            prop_assert_eq!(applied(&inverse.invert(&edited), &original), edited);
+        }
+
+        #[test]
+        fn test_prop_compose_associative(
+            (text, a, b, c) in two_edits(changeset).prop_flat_map(|(text, a, b)| {
+                let len = b.new_len();
+                (Just(text), Just(a), Just(b), changeset(len))
+            })
+        ) {
+            let original = Rope::from(text);
+            let sequential = applied(&c, &applied(&b, &applied(&a, &original)));
+            let left = a.compose(&b).unwrap().compose(&c).unwrap();
+            let right = a.compose(&b.compose(&c).unwrap()).unwrap();
+            prop_assert_eq!(applied(&left, &original), sequential.clone());
+            prop_assert_eq!(applied(&right, &original), sequential);
+            prop_assert_eq!(left.new_len(), right.new_len());
+        }
+
+        #[test]
+        fn test_fuzz_apply_and_compose((text, a, b) in two_edits(raw_changeset)) {
+            let original = Rope::from(text.as_str());
+            let after_a = naive_apply(&a, &text);
+            prop_assert_eq!(applied(&a, &original).to_string(), after_a.clone());
+            prop_assert_eq!(a.new_len(), after_a.chars().count());
+
+            let after_b = naive_apply(&b, &after_a);
+            let composed = a.compose(&b).unwrap();
+            prop_assert_eq!(composed.doc_len, a.doc_len);
+            prop_assert_eq!(composed.new_len(), after_b.chars().count());
+            prop_assert_eq!(applied(&composed, &original).to_string(), after_b);
+
+            let mut mismatched = b.clone();
+            mismatched.doc_len += 1;
+            prop_assert!(a.compose(&mismatched).is_none());
+        }
+
+        #[test]
+        fn test_fuzz_map_pos((text, changes) in edit(raw_changeset)) {
+            let chars: Vec<char> = text.chars().collect();
+            let edited: Vec<char> = naive_apply(&changes, &text).chars().collect();
+
+            // Where each original char ended up, if it survived
+            let mut kept = vec![None; chars.len()];
+            let (mut old_pos, mut new_pos) = (0, 0);
+            for op in &changes.ops {
+                match op {
+                    Operation::Retain(n) => {
+                        for i in 0..*n {
+                            kept[old_pos + i] = Some(new_pos + i);
+                        }
+                        old_pos += n;
+                        new_pos += n;
+                    }
+                    Operation::Delete(n) => old_pos += n,
+                    Operation::Insert(s) => new_pos += s.chars().count(),
+                }
+            }
+
+            let mut last = 0;
+            for pos in 0..=chars.len() {
+                let mapped = changes.map_pos(pos);
+                prop_assert!(mapped <= edited.len(), "{} -> {}", pos, mapped);
+                prop_assert!(mapped >= last, "map_pos must not go backwards");
+                last = mapped;
+                if let Some(Some(new)) = kept.get(pos) {
+                    prop_assert_eq!(mapped, *new, "{}", pos);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_map_pos_replace_at_cursor() {
+        // Replacing the text right after a cursor leaves it before the new text,
+        // whichever order the operations come in
+        let delete_first = ChangeSet {
+            doc_len: 6,
+            ops: vec![
+                Operation::Retain(2),
+                Operation::Delete(2),
+                Operation::Insert("xyz".into()),
+                Operation::Retain(2),
+            ],
+        };
+        let insert_first = ChangeSet {
+            doc_len: 6,
+            ops: vec![
+                Operation::Retain(2),
+                Operation::Insert("xyz".into()),
+                Operation::Delete(2),
+                Operation::Retain(2),
+            ],
+        };
+        for changes in [&delete_first, &insert_first] {
+            assert_eq!(changes.map_pos(2), 2);
+            assert_eq!(changes.map_pos(3), 2);
+            assert_eq!(changes.map_pos(4), 5);
+        }
+    }
+
+    #[test]
+    fn test_compose_length_mismatch() {
+        let a = ChangeSet::from_change(3, &Change::insert(1, "x"));
+        let b = ChangeSet::identity(3);
+        assert!(a.compose(&b).is_none());
+    }
 }
@@ -0,0 +1,165 @@
+//! How a file's lines differ from their committed version
+
+use lite_core::Hunk;
+
+/// Line diff status
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffStatus {
+    Added,
+    Modified,
+    /// Lines were removed before this one
+    Removed,
+}
+
+/// Differences between the committed and the current text of a file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileDiff {
+    /// `before` ranges are lines of the committed text, `after` of the current one
+    pub hunks: Vec<Hunk>,
+    /// Changes not staged yet: `before` ranges are lines of the staged text,
+    /// `after` of the current one
+    pub unstaged: Vec<Hunk>,
+    /// Committed lines the current text replaced or dropped, by the line of
+    /// the current text they were above
+    pub removed: Vec<(usize, Vec<String>)>,
+}
+
+impl FileDiff {
+    /// Compare the committed text of a file with its current text. Without
+    /// a committed version every line is new.
+    pub fn new(base: Option<&str>, text: &str) -> Self {
+        Self::with_index(base, base, text)
+    }
+
+    /// Compare the current text with both its committed and staged versions
+    pub fn with_index(base: Option<&str>, index: Option<&str>, text: &str) -> Self {
+        let base = base.unwrap_or_default();
+        let hunks = line_diff(base, text);
+        let base_lines: Vec<&str> = base.lines().collect();
+        let removed = hunks
+            .iter()
+            .filter(|hunk| !hunk.before.is_empty())
+            .map(|hunk| {
+                let lines = base_lines[hunk.before.clone()].iter().map(|line| line.to_string());
+                (hunk.after.start, lines.collect())
+            })
+            .collect();
+        Self {
+            hunks,
+            unstaged: line_diff(index.unwrap_or_default(), text),
+            removed,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hunks.is_empty()
+    }
+
+    /// Whether a changed line of the current text is staged as it is
+    pub fn is_staged(&self, line: usize) -> bool {
+        self.line_status(line).is_some() && hunk_at(&self.unstaged, line).is_none()
+    }
+
+    /// Status of a line of the current text, `None` if it's unchanged
+    pub fn line_status(&self, line: usize) -> Option<DiffStatus> {
+        let hunk = hunk_at(&self.hunks, line)?;
+        Some(if hunk.after.is_empty() {
+            DiffStatus::Removed
+        } else if hunk.before.is_empty() {
+            DiffStatus::Added
+        } else {
+            DiffStatus::Modified
+        })
+    }
+}
+
+/// Hunks between two texts, line by line
+pub(crate) fn line_diff(old: &str, new: &str) -> Vec<Hunk> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    lite_core::diff(&old, &new)
+}
+
+/// The hunk covering a line of the new text. Lines removed before a line
+/// belong to it.
+pub(crate) fn hunk_at(hunks: &[Hunk], line: usize) -> Option<&Hunk> {
+    hunks
+        .iter()
+        .find(|hunk| hunk.after.contains(&line) || (hunk.after.is_empty() && hunk.after.start == line))
+}
+
+/// Where a line of the new text was in the old one; `None` if it's changed
+pub(crate) fn old_line(hunks: &[Hunk], line: usize) -> Option<usize> {
+    let mut offset = 0isize;
+    for hunk in hunks {
+        if hunk.after.contains(&line) {
+            return None;
+        }
+        if hunk.after.start > line {
+            break;
+        }
+        offset += hunk.before.len() as isize - hunk.after.len() as isize;
+    }
+    line.checked_add_signed(offset)
+}
+
+/// `old` with the lines of one of its hunks against `new` replaced by their
+/// version in `new`
+pub(crate) fn apply_hunk(old: &str, new: &str, hunk: &Hunk) -> String {
+    let old: Vec<&str> = old.split_inclusive('\n').collect();
+    let new: Vec<&str> = new.split_inclusive('\n').collect();
+    let lines = old[..hunk.before.start]
+        .iter()
+        .chain(&new[hunk.after.clone()])
+        .chain(&old[hunk.before.end..]);
+    let mut text = String::new();
+    for line in lines {
+        // A last line without a break may not be last anymore
+        if !text.is_empty() && !text.ends_with('\n') {
+            text.push('\n');
+        }
+        text.push_str(line);
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_status() {
+        let diff = FileDiff::new(Some("a\nb\nc\nd\n"), "a\nB\nc\nnew\n");
+        let statuses: Vec<_> = (0..5).map(|line| diff.line_status(line)).collect();
+        assert_eq!(
+            statuses,
+            vec![None, Some(DiffStatus::Modified), None, Some(DiffStatus::Modified), None]
+        );
+
+        let diff = FileDiff::new(Some("a\nb\nc\n"), "a\nc\nd\n");
+        assert_eq!(diff.line_status(1), Some(DiffStatus::Removed));
+        assert_eq!(diff.line_status(2), Some(DiffStatus::Added));
+        assert_eq!(diff.removed, vec![(1, vec!["b".to_string()])]);
+
+        let diff = FileDiff::new(None, "x\n");
+        assert_eq!(diff.line_status(0), Some(DiffStatus::Added));
+        assert!(FileDiff::new(Some("x\n"), "x\n").is_empty());
+    }
+
+    #[test]
+    fn test_apply_hunk() {
+        let (old, new) = ("a\nb\nc\nd\n", "a\nB\nc\nd\ne\n");
+        let hunks = line_diff(old, new);
+        assert_eq!(apply_hunk(old, new, &hunks[0]), "a\nB\nc\nd\n");
+        assert_eq!(apply_hunk(old, new, &hunks[1]), "a\nb\nc\nd\ne\n");
+        assert_eq!(apply_hunk("a", "a\nb\n", &line_diff("a", "a\nb\n")[0]), "a\nb\n");
+        assert_eq!(old_line(&hunks, 3), Some(3));
+        assert_eq!(old_line(&hunks, 1), None);
+
+        // Staged lines are the changed ones the index already has
+        let diff = FileDiff::with_index(Some(old), Some("a\nB\nc\nd\n"), new);
+        assert!(diff.is_staged(1));
+        assert!(!diff.is_staged(4));
+        assert!(!diff.is_staged(0));
+    }
+}
@@ -0,0 +1,17 @@
+//! Errors of the git integration
+
+/// Result type of the git integration
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// An error reading a repository
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("git: {0}")]
+    Git(#[from] git2::Error),
+    #[error("not in the repository: {0}")]
+    OutsideRepository(std::path::PathBuf),
+    #[error("nothing staged to commit")]
+    NothingStaged,
+    #[error("git commit: {0}")]
+    Commit(String),
+}
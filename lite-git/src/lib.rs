@@ -5,22 +5,10 @@
 //! - File status display
 //! - Git blame
 
-// TODO: Implement git integration
+mod diff;
+mod error;
+mod repository;
 
-/// Git repository wrapper
-pub struct Repository;
-
-impl Repository {
-    pub fn open(_path: &std::path::Path) -> Option<Self> {
-        // TODO: Open git repository
-        None
-    }
-}
-
-/// Line diff status
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum DiffStatus {
-    Added,
-    Modified,
-    Removed,
-}
+pub use diff::{DiffStatus, FileDiff};
+pub use error::{Error, Result};
+pub use repository::{FileStatus, Repository};
@@ -0,0 +1,521 @@
+//! Reading a repository: the status of files and their committed text
+
+use crate::diff::{apply_hunk, hunk_at, line_diff, old_line};
+use crate::{Error, FileDiff, Result};
+use lite_core::Hunk;
+use parking_lot::Mutex;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+
+/// State of a file in the working tree compared with HEAD
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileStatus {
+    Unmodified,
+    /// Not tracked yet
+    New,
+    /// Staged as a new file
+    Added,
+    Modified,
+    Deleted,
+    Renamed,
+    /// Has unresolved merge conflicts
+    Conflicted,
+    Ignored,
+}
+
+impl FileStatus {
+    fn from_git(status: git2::Status) -> Self {
+        if status.is_conflicted() {
+            FileStatus::Conflicted
+        } else if status.is_ignored() {
+            FileStatus::Ignored
+        } else if status.contains(git2::Status::INDEX_NEW) {
+            FileStatus::Added
+        } else if status.contains(git2::Status::WT_NEW) {
+            FileStatus::New
+        } else if status.intersects(git2::Status::WT_DELETED | git2::Status::INDEX_DELETED) {
+            FileStatus::Deleted
+        } else if status.intersects(git2::Status::WT_RENAMED | git2::Status::INDEX_RENAMED) {
+            FileStatus::Renamed
+        } else if status.is_empty() {
+            FileStatus::Unmodified
+        } else {
+            FileStatus::Modified
+        }
+    }
+
+    /// Short marker as `git status --short` shows it, `None` for files
+    /// without changes to commit
+    pub fn marker(self) -> Option<&'static str> {
+        Some(match self {
+            FileStatus::Unmodified | FileStatus::Ignored => return None,
+            FileStatus::New => "??",
+            FileStatus::Added => "A",
+            FileStatus::Modified => "M",
+            FileStatus::Deleted => "D",
+            FileStatus::Renamed => "R",
+            FileStatus::Conflicted => "U",
+        })
+    }
+}
+
+/// Diff of a file, kept until the text, the index or HEAD changes
+struct CachedDiff {
+    head: Option<git2::Oid>,
+    /// Blob staged for the file
+    index: Option<git2::Oid>,
+    /// Committed text; `None` if the file isn't in HEAD
+    base: Option<String>,
+    /// Staged text; `None` if the file isn't in the index
+    staged: Option<String>,
+    text_hash: u64,
+    diff: Arc<FileDiff>,
+}
+
+/// Git repository wrapper
+pub struct Repository {
+    repo: Mutex<git2::Repository>,
+    workdir: PathBuf,
+    diffs: Mutex<HashMap<PathBuf, CachedDiff>>,
+}
+
+impl Repository {
+    /// The repository containing a file or directory, if any. Bare
+    /// repositories have no files to edit, so they don't count.
+    pub fn open(path: &Path) -> Option<Self> {
+        let path = std::path::absolute(path).ok()?;
+        let start = if path.is_dir() { path.as_path() } else { path.parent()? };
+        let repo = git2::Repository::discover(start).ok()?;
+        let workdir = repo.workdir()?.to_path_buf();
+        Some(Self {
+            repo: Mutex::new(repo),
+            workdir,
+            diffs: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Top directory of the working tree
+    pub fn workdir(&self) -> &Path {
+        &self.workdir
+    }
+
+    /// Name of the checked out branch, or the short commit id when detached
+    pub fn head_name(&self) -> Option<String> {
+        let repo = self.repo.lock();
+        let head = repo.head().ok()?;
+        if head.is_branch() {
+            return head.shorthand().map(str::to_string);
+        }
+        let id = head.target()?.to_string();
+        Some(id[..7.min(id.len())].to_string())
+    }
+
+    /// Status of a file in the working tree
+    pub fn status(&self, path: &Path) -> Result<FileStatus> {
+        let relative = self.relative(path)?;
+        let status = self.repo.lock().status_file(&relative)?;
+        Ok(FileStatus::from_git(status))
+    }
+
+    /// Status of every file with changes to commit, by its path in the
+    /// working tree
+    pub fn statuses(&self) -> Result<HashMap<PathBuf, FileStatus>> {
+        let mut options = git2::StatusOptions::new();
+        options.include_untracked(true).recurse_untracked_dirs(true);
+        let repo = self.repo.lock();
+        let statuses = repo.statuses(Some(&mut options))?;
+        Ok(statuses
+            .iter()
+            .filter_map(|entry| {
+                let path = self.workdir.join(entry.path()?);
+                Some((path, FileStatus::from_git(entry.status())))
+            })
+            .collect())
+    }
+
+    /// Whether a file or directory is left out by `.gitignore`. Paths
+    /// outside the working tree aren't.
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        let Ok(mut relative) = self.relative(path) else {
+            return false;
+        };
+        // Patterns such as `build/` only match with the trailing slash
+        if path.is_dir() {
+            relative.push("");
+        }
+        self.repo.lock().is_path_ignored(&relative).unwrap_or(false)
+    }
+
+    /// The file's text as committed in HEAD, `None` if it isn't committed
+    pub fn head_text(&self, path: &Path) -> Result<Option<String>> {
+        let relative = self.relative(path)?;
+        let repo = self.repo.lock();
+        let Some(tree) = head_tree(&repo)? else {
+            return Ok(None);
+        };
+        let entry = match tree.get_path(&relative) {
+            Ok(entry) => entry,
+            Err(e) if e.code() == git2::ErrorCode::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let blob = entry.to_object(&repo)?.peel_to_blob()?;
+        // Binary files have no lines to compare
+        Ok(std::str::from_utf8(blob.content()).ok().map(str::to_string))
+    }
+
+    /// The file's text as staged in the index, `None` if it isn't staged
+    pub fn index_text(&self, path: &Path) -> Result<Option<String>> {
+        let relative = self.relative(path)?;
+        let repo = self.repo.lock();
+        let Some(id) = index_entry(&repo, &relative)? else {
+            return Ok(None);
+        };
+        let blob = repo.find_blob(id)?;
+        Ok(std::str::from_utf8(blob.content()).ok().map(str::to_string))
+    }
+
+    /// How the current text of a file differs from HEAD and from the index.
+    /// Results are cached per file, so asking again for the same text is cheap.
+    pub fn diff(&self, path: &Path, text: &str) -> Result<Arc<FileDiff>> {
+        let relative = self.relative(path)?;
+        let path = self.workdir.join(&relative);
+        let (head, index) = {
+            let repo = self.repo.lock();
+            (repo.head().ok().and_then(|head| head.target()), index_entry(&repo, &relative)?)
+        };
+        let mut hasher = DefaultHasher::new();
+        text.hash(&mut hasher);
+        let text_hash = hasher.finish();
+
+        let mut diffs = self.diffs.lock();
+        if let Some(cached) = diffs.get_mut(&path) {
+            if cached.head == head && cached.index == index {
+                if cached.text_hash != text_hash {
+                    cached.diff = Arc::new(FileDiff::with_index(
+                        cached.base.as_deref(),
+                        cached.staged.as_deref(),
+                        text,
+                    ));
+                    cached.text_hash = text_hash;
+                }
+                return Ok(cached.diff.clone());
+            }
+        }
+
+        let base = self.head_text(&path)?;
+        let staged = self.index_text(&path)?;
+        let diff = Arc::new(FileDiff::with_index(base.as_deref(), staged.as_deref(), text));
+        diffs.insert(
+            path,
+            CachedDiff {
+                head,
+                index,
+                base,
+                staged,
+                text_hash,
+                diff: diff.clone(),
+            },
+        );
+        Ok(diff)
+    }
+
+    /// Stage the unstaged hunk at a line of the file's current text.
+    /// Returns `false` if there is none there.
+    pub fn stage_hunk(&self, path: &Path, text: &str, line: usize) -> Result<bool> {
+        let staged = self.index_text(path)?.unwrap_or_default();
+        let hunks = line_diff(&staged, text);
+        let Some(hunk) = hunk_at(&hunks, line) else {
+            return Ok(false);
+        };
+        self.write_index(path, Some(&apply_hunk(&staged, text, hunk)))?;
+        Ok(true)
+    }
+
+    /// Take the staged hunk at a line of the file's current text back out of
+    /// the index. Returns `false` if there is none there.
+    pub fn unstage_hunk(&self, path: &Path, text: &str, line: usize) -> Result<bool> {
+        let Some(staged) = self.index_text(path)? else {
+            return Ok(false);
+        };
+        // The line as it is in the index, unless it has unstaged changes
+        let Some(line) = old_line(&line_diff(&staged, text), line) else {
+            return Ok(false);
+        };
+        let base = self.head_text(path)?;
+        let committed = base.as_deref().unwrap_or_default();
+        let hunks = line_diff(committed, &staged);
+        let Some(hunk) = hunk_at(&hunks, line) else {
+            return Ok(false);
+        };
+        let reverse = Hunk {
+            before: hunk.after.clone(),
+            after: hunk.before.clone(),
+        };
+        let staged = apply_hunk(&staged, committed, &reverse);
+        // A new file with nothing staged anymore leaves the index
+        let staged = (base.is_some() || !staged.is_empty()).then_some(staged);
+        self.write_index(path, staged.as_deref())?;
+        Ok(true)
+    }
+
+    /// Stage a text for a file, or remove the file from the index
+    fn write_index(&self, path: &Path, text: Option<&str>) -> Result<()> {
+        let relative = self.relative(path)?;
+        let repo = self.repo.lock();
+        let mut index = repo.index()?;
+        index.read(false)?;
+        match text {
+            Some(text) => {
+                let entry = match index.get_path(&relative, 0) {
+                    Some(entry) => entry,
+                    None => new_entry(&relative).ok_or_else(|| Error::OutsideRepository(path.to_path_buf()))?,
+                };
+                index.add_frombuffer(&entry, text.as_bytes())?;
+            }
+            None => index.remove_path(&relative)?,
+        }
+        index.write()?;
+        Ok(())
+    }
+
+    /// The changes staged for the next commit, as a patch
+    pub fn staged_diff(&self) -> Result<String> {
+        let repo = self.repo.lock();
+        let tree = head_tree(&repo)?;
+        let diff = repo.diff_tree_to_index(tree.as_ref(), None, None)?;
+        let mut patch = String::new();
+        diff.print(git2::DiffFormat::Patch, |_, _, line| {
+            if matches!(line.origin(), '+' | '-' | ' ') {
+                patch.push(line.origin());
+            }
+            patch.push_str(&String::from_utf8_lossy(line.content()));
+            true
+        })?;
+        Ok(patch)
+    }
+
+    /// Commit the staged changes on top of HEAD with the configured author.
+    /// `git commit` makes the commit, so hooks run and `commit.gpgsign`
+    /// signs it. Returns the short id of the new commit.
+    pub fn commit(&self, message: &str) -> Result<String> {
+        {
+            let repo = self.repo.lock();
+            let mut index = repo.index()?;
+            index.read(false)?;
+            let tree = index.write_tree()?;
+            let parent = match repo.head() {
+                Ok(head) => Some(head.peel_to_commit()?),
+                Err(e) if matches!(e.code(), git2::ErrorCode::UnbornBranch | git2::ErrorCode::NotFound) => None,
+                Err(e) => return Err(e.into()),
+            };
+            if parent.is_some_and(|parent| parent.tree_id() == tree) {
+                return Err(Error::NothingStaged);
+            }
+        }
+
+        let failed = |e: std::io::Error| Error::Commit(e.to_string());
+        let mut child = Command::new("git")
+            .args(["commit", "--quiet", "--cleanup=verbatim", "--file=-"])
+            .current_dir(&self.workdir)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(failed)?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(message.as_bytes()).map_err(failed)?;
+        }
+        let output = child.wait_with_output().map_err(failed)?;
+        if !output.status.success() {
+            // What a hook or git printed, on one line for the status bar
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let reason = stderr.split_whitespace().collect::<Vec<_>>().join(" ");
+            return Err(Error::Commit(if reason.is_empty() { output.status.to_string() } else { reason }));
+        }
+
+        let id = self.repo.lock().head()?.peel_to_commit()?.id().to_string();
+        Ok(id[..7.min(id.len())].to_string())
+    }
+
+    /// Forget the cached diff of a file, e.g. once it's closed
+    pub fn forget(&self, path: &Path) {
+        if let Ok(relative) = self.relative(path) {
+            self.diffs.lock().remove(&self.workdir.join(relative));
+        }
+    }
+
+    /// A path relative to the working tree, as git names files
+    fn relative(&self, path: &Path) -> Result<PathBuf> {
+        let outside = || Error::OutsideRepository(path.to_path_buf());
+        let absolute = std::path::absolute(path).map_err(|_| outside())?;
+        if let Ok(relative) = absolute.strip_prefix(&self.workdir) {
+            return Ok(relative.to_path_buf());
+        }
+        // git reports the working tree with symlinks resolved. The file
+        // itself may be gone, its directory has to exist.
+        let name = absolute.file_name().ok_or_else(outside)?;
+        let dir = absolute.parent().ok_or_else(outside)?.canonicalize().map_err(|_| outside())?;
+        dir.join(name)
+            .strip_prefix(&self.workdir)
+            .map(Path::to_path_buf)
+            .map_err(|_| outside())
+    }
+}
+
+/// Blob staged for a file, if any. The index is read again first, as other
+/// programs change it.
+fn index_entry(repo: &git2::Repository, relative: &Path) -> Result<Option<git2::Oid>> {
+    let mut index = repo.index()?;
+    index.read(false)?;
+    Ok(index.get_path(relative, 0).map(|entry| entry.id))
+}
+
+/// Index entry for a file not staged before, as a regular file
+fn new_entry(relative: &Path) -> Option<git2::IndexEntry> {
+    let time = git2::IndexTime::new(0, 0);
+    Some(git2::IndexEntry {
+        ctime: time,
+        mtime: time,
+        dev: 0,
+        ino: 0,
+        mode: 0o100644,
+        uid: 0,
+        gid: 0,
+        file_size: 0,
+        id: git2::Oid::zero(),
+        flags: 0,
+        flags_extended: 0,
+        path: relative.to_str()?.replace('\\', "/").into_bytes(),
+    })
+}
+
+/// Tree of the HEAD commit; `None` in a repository without commits
+fn head_tree(repo: &git2::Repository) -> Result<Option<git2::Tree<'_>>> {
+    match repo.head() {
+        Ok(head) => Ok(Some(head.peel_to_tree()?)),
+        Err(e) if matches!(e.code(), git2::ErrorCode::UnbornBranch | git2::ErrorCode::NotFound) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DiffStatus;
+    use std::fs;
+
+    #[test]
+    fn test_status_and_diff() {
+        let dir = std::env::temp_dir().join(format!("lite-git-{}", std::process::id()));
+        fs::create_dir_all(dir.join("src")).unwrap();
+        let file = dir.join("src/main.rs");
+        fs::write(&file, "a\nb\n").unwrap();
+
+        let git = git2::Repository::init(&dir).unwrap();
+        let repo = Repository::open(&file).unwrap();
+        assert_eq!(repo.workdir(), dir.canonicalize().unwrap());
+        assert_eq!(repo.status(&file).unwrap(), FileStatus::New);
+        assert!(repo.head_text(&file).unwrap().is_none());
+
+        fs::write(dir.join(".gitignore"), "build/\n*.log\n").unwrap();
+        fs::create_dir_all(dir.join("build")).unwrap();
+        assert!(repo.is_ignored(&dir.join("build")));
+        assert!(repo.is_ignored(&dir.join("src/debug.log")));
+        assert!(!repo.is_ignored(&file));
+
+        // Commit the file
+        let mut index = git.index().unwrap();
+        index.add_path(Path::new("src/main.rs")).unwrap();
+        let tree = git.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("lite", "lite@example.com").unwrap();
+        git.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[]).unwrap();
+        index.write().unwrap();
+
+        assert_eq!(repo.status(&file).unwrap(), FileStatus::Unmodified);
+        assert_eq!(repo.head_text(&file).unwrap().as_deref(), Some("a\nb\n"));
+        assert!(repo.diff(&file, "a\nb\n").unwrap().is_empty());
+
+        fs::write(&file, "a\nc\nd\n").unwrap();
+        assert_eq!(repo.status(&file).unwrap(), FileStatus::Modified);
+        let statuses = repo.statuses().unwrap();
+        assert_eq!(statuses.get(&repo.workdir().join("src/main.rs")), Some(&FileStatus::Modified));
+        assert_eq!(statuses.get(&repo.workdir().join(".gitignore")), Some(&FileStatus::New));
+        assert!(!statuses.contains_key(&repo.workdir().join("build")));
+        let diff = repo.diff(&file, "a\nc\nd\n").unwrap();
+        assert_eq!(diff.line_status(0), None);
+        assert_eq!(diff.line_status(2), Some(DiffStatus::Modified));
+        assert!(repo.head_name().is_some());
+
+        // Stage the change and take it back
+        let text = "a\nc\nd\n";
+        assert!(!repo.stage_hunk(&file, text, 0).unwrap());
+        assert!(repo.stage_hunk(&file, text, 1).unwrap());
+        assert_eq!(repo.index_text(&file).unwrap().as_deref(), Some(text));
+        assert!(repo.diff(&file, text).unwrap().is_staged(1));
+        assert_eq!(repo.status(&file).unwrap(), FileStatus::Modified);
+        assert!(repo.unstage_hunk(&file, text, 2).unwrap());
+        assert_eq!(repo.index_text(&file).unwrap().as_deref(), Some("a\nb\n"));
+        assert!(!repo.diff(&file, text).unwrap().is_staged(1));
+
+        // A new file is added to the index, and leaves it again
+        let new = dir.join("new.txt");
+        fs::write(&new, "x\n").unwrap();
+        assert_eq!(repo.status(&new).unwrap().marker(), Some("??"));
+        assert!(repo.stage_hunk(&new, "x\n", 0).unwrap());
+        assert_eq!(repo.index_text(&new).unwrap().as_deref(), Some("x\n"));
+        assert_eq!(repo.status(&new).unwrap(), FileStatus::Added);
+        assert!(repo.unstage_hunk(&new, "x\n", 0).unwrap());
+        assert_eq!(repo.index_text(&new).unwrap(), None);
+
+        // Commit a staged change
+        assert!(matches!(repo.commit("nothing"), Err(Error::NothingStaged)));
+        assert!(repo.stage_hunk(&file, text, 1).unwrap());
+        assert!(repo.staged_diff().unwrap().contains("-b\n+c\n+d\n"));
+        let mut config = git.config().unwrap();
+        config.set_str("user.name", "lite").unwrap();
+        config.set_str("user.email", "lite@example.com").unwrap();
+        // Hooks run, and can stop the commit
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let hook = dir.join(".git/hooks/pre-commit");
+            fs::create_dir_all(hook.parent().unwrap()).unwrap();
+            fs::write(&hook, "#!/bin/sh\necho 'no tabs allowed' >&2\nexit 1\n").unwrap();
+            fs::set_permissions(&hook, fs::Permissions::from_mode(0o755)).unwrap();
+            assert!(matches!(repo.commit("Change b"), Err(Error::Commit(reason)) if reason == "no tabs allowed"));
+            fs::remove_file(&hook).unwrap();
+        }
+        assert_eq!(repo.commit("Change b").unwrap().len(), 7);
+        assert_eq!(repo.head_text(&file).unwrap().as_deref(), Some(text));
+        assert!(repo.staged_diff().unwrap().is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_forget_through_symlink() {
+        let dir = std::env::temp_dir().join(format!("lite-git-forget-{}", std::process::id()));
+        let link = std::env::temp_dir().join(format!("lite-git-forget-link-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        std::os::unix::fs::symlink(&dir, &link).unwrap();
+        git2::Repository::init(&dir).unwrap();
+        fs::write(dir.join("a.txt"), "a\n").unwrap();
+
+        // The same file by another path is the same cache entry
+        let file = link.join("a.txt");
+        let repo = Repository::open(&file).unwrap();
+        repo.diff(&file, "a\n").unwrap();
+        assert_eq!(repo.diffs.lock().len(), 1);
+        repo.forget(&file);
+        assert!(repo.diffs.lock().is_empty());
+
+        fs::remove_file(&link).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,387 @@
+//! A language server process and the JSON-RPC session with it
+
+use crate::error::{Error, Result};
+use crate::transport::{self, Message, RequestId, ResponseError, METHOD_NOT_FOUND};
+use crate::uri::uri_from_path;
+use lsp_types::notification::{Exit, Initialized, Notification};
+use lsp_types::request::{Initialize, Request, Shutdown};
+use lsp_types::{
+    ClientCapabilities, ClientInfo, CompletionClientCapabilities, CompletionItemCapability,
+    InitializeParams, InitializedParams, ParameterInformationSettings,
+    PublishDiagnosticsClientCapabilities, ServerCapabilities, SignatureHelpClientCapabilities,
+    SignatureInformationSettings, TextDocumentClientCapabilities, WindowClientCapabilities,
+    WorkspaceFolder,
+};
+use parking_lot::Mutex;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::sync::{mpsc, oneshot};
+
+/// Requests waiting for their response, by id
+type Pending = Arc<Mutex<HashMap<i64, oneshot::Sender<std::result::Result<Value, ResponseError>>>>>;
+
+/// A notification sent by the server
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerNotification {
+    pub method: String,
+    pub params: Value,
+}
+
+impl ServerNotification {
+    /// Decode the params if this is the notification `N`
+    pub fn parse<N: Notification>(&self) -> Option<N::Params> {
+        if self.method != N::METHOD {
+            return None;
+        }
+        serde_json::from_value(self.params.clone()).ok()
+    }
+}
+
+/// Client side of a session with one language server.
+///
+/// Reading and writing happen on tokio tasks, so requests can be awaited
+/// concurrently and notifications are queued until polled.
+pub struct LspClient {
+    /// Name of the server command, for messages
+    name: String,
+    child: tokio::sync::Mutex<Child>,
+    outgoing: mpsc::UnboundedSender<Message>,
+    notifications: tokio::sync::Mutex<mpsc::UnboundedReceiver<ServerNotification>>,
+    pending: Pending,
+    next_id: AtomicI64,
+    capabilities: Option<ServerCapabilities>,
+}
+
+impl LspClient {
+    /// Spawn a language server speaking LSP over stdio.
+    ///
+    /// Must be called within a tokio runtime.
+    pub fn start(command: &str, args: &[String], root: Option<&Path>) -> Result<Self> {
+        let mut process = Command::new(command);
+        process
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        if let Some(root) = root {
+            process.current_dir(root);
+        }
+        let mut child = process.spawn()?;
+
+        let stdin = child.stdin.take().ok_or(Error::ServerExited)?;
+        let stdout = child.stdout.take().ok_or(Error::ServerExited)?;
+        let stderr = child.stderr.take().ok_or(Error::ServerExited)?;
+
+        let (outgoing, outgoing_rx) = mpsc::unbounded_channel();
+        let (notifications_tx, notifications) = mpsc::unbounded_channel();
+        let pending = Pending::default();
+
+        tokio::spawn(write_loop(stdin, outgoing_rx));
+        tokio::spawn(read_loop(stdout, outgoing.clone(), notifications_tx, pending.clone()));
+
+        let name = command.to_string();
+        let log_name = name.clone();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                tracing::debug!("{}: {}", log_name, line);
+            }
+        });
+
+        Ok(Self {
+            name,
+            child: tokio::sync::Mutex::new(child),
+            outgoing,
+            notifications: tokio::sync::Mutex::new(notifications),
+            pending,
+            next_id: AtomicI64::new(1),
+            capabilities: None,
+        })
+    }
+
+    /// Name of the server command
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Capabilities announced by the server, once initialized
+    pub fn capabilities(&self) -> Option<&ServerCapabilities> {
+        self.capabilities.as_ref()
+    }
+
+    /// Perform the `initialize`/`initialized` handshake for a workspace,
+    /// passing server specific `initializationOptions` if there are any
+    pub async fn initialize(
+        &mut self,
+        root: Option<&Path>,
+        initialization_options: Option<serde_json::Value>,
+    ) -> Result<&ServerCapabilities> {
+        let workspace_folders = match root {
+            Some(root) => Some(vec![WorkspaceFolder {
+                uri: uri_from_path(root)?,
+                name: root
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+            }]),
+            None => None,
+        };
+
+        let params = InitializeParams {
+            process_id: Some(std::process::id()),
+            capabilities: client_capabilities(),
+            workspace_folders,
+            initialization_options,
+            client_info: Some(ClientInfo {
+                name: "lite".to_string(),
+                version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            }),
+            ..Default::default()
+        };
+
+        let result = self.request::<Initialize>(params).await?;
+        self.notify::<Initialized>(InitializedParams {})?;
+        Ok(self.capabilities.insert(result.capabilities))
+    }
+
+    /// Ask the server to shut down, tell it to exit and wait for the process
+    pub async fn shutdown(&self) -> Result<()> {
+        self.request::<Shutdown>(()).await?;
+        self.notify::<Exit>(())?;
+        self.child.lock().await.wait().await?;
+        Ok(())
+    }
+
+    /// Send a request and wait for its response
+    pub async fn request<R: Request>(&self, params: R::Params) -> Result<R::Result>
+    where
+        R::Params: Serialize,
+        R::Result: DeserializeOwned,
+    {
+        let value = self.request_raw(R::METHOD, serde_json::to_value(params)?).await?;
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Send a request with untyped params
+    pub async fn request_raw(&self, method: &str, params: Value) -> Result<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().insert(id, tx);
+
+        let message = Message::Request {
+            id: RequestId::Number(id),
+            method: method.to_string(),
+            params,
+        };
+        if self.outgoing.send(message).is_err() {
+            self.pending.lock().remove(&id);
+            return Err(Error::ServerExited);
+        }
+
+        match rx.await {
+            Ok(Ok(value)) => Ok(value),
+            Ok(Err(error)) => Err(Error::Rpc {
+                code: error.code,
+                message: error.message,
+            }),
+            Err(_) => Err(Error::ServerExited),
+        }
+    }
+
+    /// Send a notification
+    pub fn notify<N: Notification>(&self, params: N::Params) -> Result<()>
+    where
+        N::Params: Serialize,
+    {
+        let message = Message::Notification {
+            method: N::METHOD.to_string(),
+            params: serde_json::to_value(params)?,
+        };
+        self.outgoing.send(message).map_err(|_| Error::ServerExited)
+    }
+
+    /// Get a queued server notification without waiting.
+    ///
+    /// Returns `None` while another caller is waiting in `next_notification`.
+    pub fn try_next_notification(&self) -> Option<ServerNotification> {
+        self.notifications.try_lock().ok()?.try_recv().ok()
+    }
+
+    /// Wait for the next server notification; `None` once the server is gone
+    pub async fn next_notification(&self) -> Option<ServerNotification> {
+        self.notifications.lock().await.recv().await
+    }
+}
+
+/// Features of the editor servers may rely on
+fn client_capabilities() -> ClientCapabilities {
+    ClientCapabilities {
+        text_document: Some(TextDocumentClientCapabilities {
+            completion: Some(CompletionClientCapabilities {
+                // Snippets aren't expanded, so items must be plain text
+                completion_item: Some(CompletionItemCapability {
+                    snippet_support: Some(false),
+                    insert_replace_support: Some(true),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            signature_help: Some(SignatureHelpClientCapabilities {
+                signature_information: Some(SignatureInformationSettings {
+                    parameter_information: Some(ParameterInformationSettings {
+                        label_offset_support: Some(true),
+                    }),
+                    active_parameter_support: Some(true),
+                    ..Default::default()
+                }),
+                context_support: Some(true),
+                ..Default::default()
+            }),
+            publish_diagnostics: Some(PublishDiagnosticsClientCapabilities {
+                version_support: Some(true),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+        // Progress is shown in the statusline
+        window: Some(WindowClientCapabilities {
+            work_done_progress: Some(true),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// Write queued messages to the server until the client is dropped
+async fn write_loop(mut stdin: ChildStdin, mut outgoing: mpsc::UnboundedReceiver<Message>) {
+    while let Some(message) = outgoing.recv().await {
+        if let Err(e) = transport::write_message(&mut stdin, &message).await {
+            tracing::warn!("failed to write to language server: {}", e);
+            break;
+        }
+    }
+}
+
+/// Dispatch messages from the server until it closes its output
+async fn read_loop(
+    stdout: ChildStdout,
+    outgoing: mpsc::UnboundedSender<Message>,
+    notifications: mpsc::UnboundedSender<ServerNotification>,
+    pending: Pending,
+) {
+    let mut reader = BufReader::new(stdout);
+    loop {
+        let message = match transport::read_message(&mut reader).await {
+            Ok(Some(message)) => message,
+            Ok(None) => break,
+            Err(e) => {
+                tracing::warn!("failed to read from language server: {}", e);
+                break;
+            }
+        };
+
+        match message {
+            Message::Response { id: RequestId::Number(id), result } => {
+                if let Some(tx) = pending.lock().remove(&id) {
+                    let _ = tx.send(result);
+                }
+            }
+            Message::Response { .. } => {}
+            Message::Notification { method, params } => {
+                let _ = notifications.send(ServerNotification { method, params });
+            }
+            Message::Request { id, method, params } => {
+                let _ = outgoing.send(Message::Response {
+                    id,
+                    result: answer_server_request(&method, &params),
+                });
+            }
+        }
+    }
+
+    // Fail requests still waiting, the server won't answer them
+    pending.lock().clear();
+}
+
+/// Reply to requests a server sends to the client
+fn answer_server_request(method: &str, params: &Value) -> std::result::Result<Value, ResponseError> {
+    match method {
+        "window/workDoneProgress/create"
+        | "client/registerCapability"
+        | "client/unregisterCapability" => Ok(Value::Null),
+        // No settings: one null per requested item
+        "workspace/configuration" => {
+            let items = params
+                .get("items")
+                .and_then(Value::as_array)
+                .map_or(0, Vec::len);
+            Ok(Value::Array(vec![Value::Null; items]))
+        }
+        _ => Err(ResponseError {
+            code: METHOD_NOT_FOUND,
+            message: format!("unhandled method {}", method),
+            data: None,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_answer_server_request() {
+        assert_eq!(
+            answer_server_request("workspace/configuration", &json!({ "items": [{}, {}] })),
+            Ok(json!([null, null]))
+        );
+        assert_eq!(
+            answer_server_request("workspace/applyEdit", &Value::Null).unwrap_err().code,
+            METHOD_NOT_FOUND
+        );
+    }
+
+    /// A shell script playing language server: answers every request with
+    /// its id and a fixed result, then exits once `exit` arrives
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_handshake() {
+        let script = r#"
+            while true; do
+                read -r header || exit 1
+                read -r blank
+                length=$(echo "$header" | tr -dc 0-9)
+                body=$(dd bs=1 count="$length" 2>/dev/null)
+                case "$body" in
+                    *'"method":"exit"'*) exit 0 ;;
+                    *'"method":"initialized"'*) continue ;;
+                esac
+                id=$(echo "$body" | sed 's/.*"id":\([0-9]*\).*/\1/')
+                case "$body" in
+                    *'"method":"initialize"'*) result='{"capabilities":{"hoverProvider":true}}' ;;
+                    *) result='null' ;;
+                esac
+                reply="{\"jsonrpc\":\"2.0\",\"id\":$id,\"result\":$result}"
+                printf 'Content-Length: %d\r\n\r\n%s' "${#reply}" "$reply"
+            done
+        "#;
+
+        let mut client = LspClient::start("sh", &["-c".to_string(), script.to_string()], None).unwrap();
+        let capabilities = client.initialize(None, None).await.unwrap();
+        assert_eq!(
+            capabilities.hover_provider,
+            Some(lsp_types::HoverProviderCapability::Simple(true))
+        );
+        client.shutdown().await.unwrap();
+    }
+}
@@ -0,0 +1,21 @@
+//! Errors of the LSP client
+
+/// Result type of the LSP client
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// An error talking to a language server
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("failed to talk to the language server: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid message: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("malformed message header: {0}")]
+    Header(String),
+    #[error("language server error {code}: {message}")]
+    Rpc { code: i64, message: String },
+    #[error("language server exited")]
+    ServerExited,
+    #[error("invalid path for a URI: {0}")]
+    InvalidPath(String),
+}
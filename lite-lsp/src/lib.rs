@@ -7,19 +7,14 @@
 //! - Hover information
 //! - Diagnostics
 
-// TODO: Implement LSP client
+mod client;
+mod error;
+mod position;
+pub mod transport;
+mod uri;
 
-/// LSP client placeholder
-pub struct LspClient;
-
-impl LspClient {
-    pub fn new() -> Self {
-        Self
-    }
-}
-
-impl Default for LspClient {
-    fn default() -> Self {
-        Self::new()
-    }
-}
+pub use client::{LspClient, ServerNotification};
+pub use error::{Error, Result};
+pub use lsp_types;
+pub use position::{char_from_lsp, lsp_from_char};
+pub use uri::{path_from_uri, uri_from_path};
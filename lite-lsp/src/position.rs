@@ -0,0 +1,52 @@
+//! Conversion between char indices and LSP positions, whose columns count
+//! UTF-16 code units
+
+use lite_core::{Rope, RopeExt};
+
+/// Char index of an LSP position, clamped to the document
+pub fn char_from_lsp(rope: &Rope, pos: lsp_types::Position) -> usize {
+    let line = pos.line as usize;
+    if line >= rope.len_lines() {
+        return rope.len_chars();
+    }
+
+    let line_start = rope.line_to_char(line);
+    let mut units = 0;
+    let mut col = 0;
+    for c in rope.line(line).chars().take(rope.line_len_chars(line)) {
+        if units >= pos.character as usize {
+            break;
+        }
+        units += c.len_utf16();
+        col += 1;
+    }
+    line_start + col
+}
+
+/// LSP position of a char index
+pub fn lsp_from_char(rope: &Rope, char_idx: usize) -> lsp_types::Position {
+    let char_idx = char_idx.min(rope.len_chars());
+    let line = rope.char_to_line(char_idx);
+    let line_start = rope.line_to_char(line);
+    let character: usize = rope
+        .slice(line_start..char_idx)
+        .chars()
+        .map(char::len_utf16)
+        .sum();
+    lsp_types::Position::new(line as u32, character as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utf16_columns() {
+        let rope = Rope::from("a😀b\nc");
+        assert_eq!(lsp_from_char(&rope, 2), lsp_types::Position::new(0, 3));
+        assert_eq!(char_from_lsp(&rope, lsp_types::Position::new(0, 3)), 2);
+        assert_eq!(char_from_lsp(&rope, lsp_types::Position::new(0, 99)), 3);
+        assert_eq!(char_from_lsp(&rope, lsp_types::Position::new(1, 1)), 5);
+        assert_eq!(char_from_lsp(&rope, lsp_types::Position::new(7, 0)), 5);
+    }
+}
@@ -0,0 +1,214 @@
+//! JSON-RPC messages and their `Content-Length` framing over stdio
+
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Id of a request
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RequestId {
+    Number(i64),
+    String(String),
+}
+
+/// Error object of a failed response
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResponseError {
+    pub code: i64,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+/// Error code for requests the client doesn't handle
+pub const METHOD_NOT_FOUND: i64 = -32601;
+
+/// A JSON-RPC message in either direction
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    Request {
+        id: RequestId,
+        method: String,
+        params: Value,
+    },
+    Response {
+        id: RequestId,
+        result: std::result::Result<Value, ResponseError>,
+    },
+    Notification {
+        method: String,
+        params: Value,
+    },
+}
+
+/// The wire representation shared by all message kinds
+#[derive(Serialize, Deserialize)]
+struct RawMessage {
+    jsonrpc: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<RequestId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    method: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    params: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<ResponseError>,
+}
+
+impl Message {
+    /// Serialize to a JSON-RPC 2.0 object
+    pub fn to_json(&self) -> Result<String> {
+        let mut raw = RawMessage {
+            jsonrpc: "2.0".to_string(),
+            id: None,
+            method: None,
+            params: None,
+            result: None,
+            error: None,
+        };
+        match self {
+            Message::Request { id, method, params } => {
+                raw.id = Some(id.clone());
+                raw.method = Some(method.clone());
+                raw.params = Some(params.clone());
+            }
+            Message::Response { id, result } => {
+                raw.id = Some(id.clone());
+                match result {
+                    // A null result must still be sent
+                    Ok(result) => raw.result = Some(result.clone()),
+                    Err(error) => raw.error = Some(error.clone()),
+                }
+            }
+            Message::Notification { method, params } => {
+                raw.method = Some(method.clone());
+                raw.params = Some(params.clone());
+            }
+        }
+
+        let mut json = serde_json::to_value(&raw)?;
+        if let (Message::Response { result: Ok(_), .. }, Some(object)) = (self, json.as_object_mut()) {
+            object.entry("result").or_insert(Value::Null);
+        }
+        Ok(serde_json::to_string(&json)?)
+    }
+
+    /// Parse a JSON-RPC 2.0 object
+    pub fn from_json(json: &str) -> Result<Self> {
+        let raw: RawMessage = serde_json::from_str(json)?;
+        let params = raw.params.unwrap_or(Value::Null);
+        Ok(match (raw.id, raw.method) {
+            (Some(id), Some(method)) => Message::Request { id, method, params },
+            (None, Some(method)) => Message::Notification { method, params },
+            (Some(id), None) => Message::Response {
+                id,
+                result: match raw.error {
+                    Some(error) => Err(error),
+                    None => Ok(raw.result.unwrap_or(Value::Null)),
+                },
+            },
+            (None, None) => return Err(Error::Header("message without id or method".to_string())),
+        })
+    }
+}
+
+/// Read one framed message, or `None` at end of stream
+pub async fn read_message<R: AsyncBufRead + Unpin>(reader: &mut R) -> Result<Option<Message>> {
+    let mut content_length = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).await? == 0 {
+            return Ok(None);
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let (name, value) = header
+            .split_once(':')
+            .ok_or_else(|| Error::Header(header.to_string()))?;
+        if name.eq_ignore_ascii_case("Content-Length") {
+            let length = value
+                .trim()
+                .parse()
+                .map_err(|_| Error::Header(header.to_string()))?;
+            content_length = Some(length);
+        }
+    }
+
+    let length = content_length.ok_or_else(|| Error::Header("missing Content-Length".to_string()))?;
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await?;
+    let body = String::from_utf8(body).map_err(|e| Error::Header(e.to_string()))?;
+    Message::from_json(&body).map(Some)
+}
+
+/// Write one framed message
+pub async fn write_message<W: AsyncWrite + Unpin>(writer: &mut W, message: &Message) -> Result<()> {
+    let body = message.to_json()?;
+    let header = format!("Content-Length: {}\r\n\r\n", body.len());
+    writer.write_all(header.as_bytes()).await?;
+    writer.write_all(body.as_bytes()).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_framing_round_trip() {
+        let messages = vec![
+            Message::Request {
+                id: RequestId::Number(1),
+                method: "initialize".to_string(),
+                params: json!({ "processId": null }),
+            },
+            Message::Response {
+                id: RequestId::String("a".to_string()),
+                result: Ok(Value::Null),
+            },
+            Message::Notification {
+                method: "textDocument/publishDiagnostics".to_string(),
+                params: json!({ "uri": "file:///ü.rs", "diagnostics": [] }),
+            },
+        ];
+
+        let mut buffer = Vec::new();
+        for message in &messages {
+            write_message(&mut buffer, message).await.unwrap();
+        }
+
+        let mut reader = tokio::io::BufReader::new(buffer.as_slice());
+        for message in &messages {
+            assert_eq!(read_message(&mut reader).await.unwrap().as_ref(), Some(message));
+        }
+        assert_eq!(read_message(&mut reader).await.unwrap(), None);
+    }
+
+    #[test]
+    fn test_error_response() {
+        let message = Message::from_json(
+            r#"{"jsonrpc":"2.0","id":3,"error":{"code":-32601,"message":"nope"}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            message,
+            Message::Response {
+                id: RequestId::Number(3),
+                result: Err(ResponseError {
+                    code: METHOD_NOT_FOUND,
+                    message: "nope".to_string(),
+                    data: None,
+                }),
+            }
+        );
+    }
+}
@@ -0,0 +1,70 @@
+//! Conversion between file paths and `file://` URIs
+
+use crate::error::{Error, Result};
+use lsp_types::Uri;
+use std::path::{Path, PathBuf};
+
+/// Build a `file://` URI from an absolute path
+pub fn uri_from_path(path: &Path) -> Result<Uri> {
+    let invalid = || Error::InvalidPath(path.display().to_string());
+    if !path.is_absolute() {
+        return Err(invalid());
+    }
+    let path = path.to_str().ok_or_else(invalid)?.replace('\\', "/");
+
+    let mut uri = String::from("file://");
+    if !path.starts_with('/') {
+        // Windows drive letters
+        uri.push('/');
+    }
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri.parse().map_err(|_| invalid())
+}
+
+/// Get the path of a `file://` URI
+pub fn path_from_uri(uri: &Uri) -> Option<PathBuf> {
+    let encoded = uri.as_str().strip_prefix("file://")?;
+
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut rest = encoded.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' && tail.len() >= 2 {
+            let hex = std::str::from_utf8(&tail[..2]).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+
+    let path = String::from_utf8(bytes).ok()?;
+    // "/C:/dir" on Windows
+    let path = match path.as_bytes() {
+        [b'/', _, b':', ..] => path[1..].to_string(),
+        _ => path,
+    };
+    Some(PathBuf::from(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_uri_round_trip() {
+        let path = Path::new("/home/me/my project/ünï.rs");
+        let uri = uri_from_path(path).unwrap();
+        assert_eq!(uri.as_str(), "file:///home/me/my%20project/%C3%BCn%C3%AF.rs");
+        assert_eq!(path_from_uri(&uri).unwrap(), path);
+        assert!(uri_from_path(Path::new("relative.rs")).is_err());
+    }
+}
@@ -1,60 +1,176 @@
-use crate::{execute_action, Event, EventHandler};
+use crate::metrics::Metrics;
+use crate::prompt_completion;
+use crate::record::{self, Recorder};
+use crate::{
+    execute_action, insert_text, newer_release, Event, EventHandler, Frontend, GitManager,
+    IdleScheduler, IdleTask, LspManager, TerminalFrontend,
+};
 use anyhow::Result;
-use crossterm::{
-    event::{DisableMouseCapture, EnableMouseCapture},
-    execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+use lite_config::{Action, Bell, CursorShape, DebugMetrics, Key, KeyEvent, LowBandwidth, Modifier};
+use lite_ui::{
+    Breadcrumbs, BufferSwitcherPopup, CompletionPopup, Compositor, Component, Context, EditorView, EventResult,
+    FileTreePanel, HelpBar, OutlinePanel, PeekPopup, SignatureHelpPopup, StatusLine, TabLine,
 };
-use lite_config::{Action, Key, KeyEvent, Modifier};
-use lite_core::RopeExt;
-use lite_ui::{Compositor, Component, Context, EditorView, EventResult, HelpBar, StatusLine, TabLine};
-use lite_view::Editor;
-use ratatui::{backend::CrosstermBackend, layout::Rect, Terminal};
-use std::io::{self, Stdout};
+use lite_view::{digraph, highlighter, DebugCommand, Editor, Severity};
+use ratatui::{layout::Rect, Terminal};
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
 
 use lite_ui::{Prompt, PromptType};
 
-/// Main application struct
-pub struct Application {
+/// Frames taking longer than this to reach the terminal don't fit between
+/// two animation ticks
+const SLOW_FRAME: Duration = Duration::from_millis(50);
+
+/// Slow frames in a row that turn smooth scrolling off, or the low
+/// bandwidth mode on
+const SLOW_FRAMES_LIMIT: usize = 3;
+
+/// Frames reaching the terminal faster than this show it keeps up again
+const FAST_FRAME: Duration = Duration::from_millis(10);
+
+/// Fast frames in a row that turn the low bandwidth mode off, when `auto`
+/// turned it on
+const FAST_FRAMES_LIMIT: usize = 50;
+
+/// Shortest time between two frames in low bandwidth mode: input arriving
+/// in between is drawn together
+const LOW_BANDWIDTH_FRAME: Duration = Duration::from_millis(100);
+
+/// Most events handled between two frames; the rest wait for the next one,
+/// so a huge paste or a flood of input doesn't hold the screen back
+const MAX_EVENTS_PER_FRAME: usize = 1024;
+
+/// Main application struct, running in the terminal unless given another
+/// frontend
+pub struct Application<F: Frontend = TerminalFrontend> {
     /// The editor state
     editor: Editor,
     /// UI compositor
     compositor: Compositor,
     /// Terminal
-    terminal: Terminal<CrosstermBackend<Stdout>>,
+    terminal: Terminal<F>,
     /// Event handler
     events: EventHandler,
+    /// Language servers of the open documents
+    lsp: LspManager,
+    /// Changes of the open files since their last commit
+    git: GitManager,
+    /// Work deferred until the user pauses
+    idle: IdleScheduler,
+    /// Recent files as last saved; `None` when they aren't kept between
+    /// sessions, as with a backend other than the terminal
+    saved_recent: Option<Vec<PathBuf>>,
+    /// Answer of the update check running in the background
+    update_check: Option<mpsc::Receiver<String>>,
+    /// Whether to quit and install the newer release
+    update_requested: bool,
+    /// Cursor shape and blinking last sent to the terminal
+    cursor_style: Option<(CursorShape, bool)>,
+    /// Whether the terminal was last told to send mouse input
+    mouse_capture: Option<bool>,
+    /// Frames in a row that took longer than `SLOW_FRAME` to write
+    slow_frames: usize,
+    /// Frames in a row written faster than `FAST_FRAME`
+    fast_frames: usize,
+    /// When the last frame was drawn
+    last_frame: Option<Instant>,
+    /// Timings of the main loop for `debug_metrics`
+    metrics: Metrics,
+    /// The input, while `:debug-record` is on
+    recorder: Recorder,
+    /// The user's session, put aside while a recording plays back
+    replay: Option<Replay>,
+}
+
+/// What `:debug-replay` puts aside while the scratch editor it plays a
+/// recording into is up, and gives back on `:debug-replay off`
+struct Replay {
+    editor: Editor,
+    /// The working directory; the replay runs in an empty one
+    cwd: PathBuf,
+    /// The empty directory
+    dir: PathBuf,
 }
 
 impl Application {
     /// Create a new application
     pub fn new() -> Result<Self> {
-        // Setup terminal
-        enable_raw_mode()?;
-        let mut stdout = io::stdout();
-        execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-        let backend = CrosstermBackend::new(stdout);
-        let terminal = Terminal::new(backend)?;
+        let mut app = Self::with_frontend(TerminalFrontend::new())?;
+        app.editor.recent_files = crate::recent::load();
+        app.saved_recent = Some(app.editor.recent_files.clone());
+        app.editor.prompt_history = crate::prompt_history::load();
 
-        // Create editor
-        let mut editor = Editor::new();
+        match lite_config::Languages::load() {
+            Ok(languages) => app.editor.config.languages = languages,
+            Err(e) => app.editor.set_status(e.to_string(), lite_view::Severity::Error),
+        }
+        // The project's own settings apply over the user's
+        crate::project::load(&mut app.editor, &std::env::current_dir().unwrap_or_default());
+        app.resize()?;
 
-        // Get terminal size
-        let size = terminal.size()?;
-        editor.resize(size.width, size.height);
+        app.idle.schedule(IdleTask::CheckUpdate);
+        Ok(app)
+    }
+}
 
-        // Create compositor with layers
-        let compositor = Compositor::new();
+impl<F: Frontend> Application<F> {
+    /// Create an application running in a frontend, with the default
+    /// settings and without loading any, for tests and other frontends
+    pub fn with_frontend(mut frontend: F) -> Result<Self> {
+        frontend.enter()?;
+        let mut app = Self {
+            editor: Editor::new(),
+            compositor: Compositor::new(),
+            terminal: Terminal::new(frontend)?,
+            events: EventHandler::new(),
+            lsp: LspManager::new(),
+            git: GitManager::new(),
+            idle: IdleScheduler::new(),
+            saved_recent: None,
+            update_check: None,
+            update_requested: false,
+            cursor_style: None,
+            mouse_capture: None,
+            slow_frames: 0,
+            fast_frames: 0,
+            last_frame: None,
+            metrics: Metrics::new(),
+            recorder: Recorder::new(),
+            replay: None,
+        };
+        app.editor.ignored_files = Some(crate::git::ignore_filter());
+        app.resize()?;
+        Ok(app)
+    }
 
-        // Create event handler
-        let events = EventHandler::new();
+    /// Fit the views to the terminal size
+    fn resize(&mut self) -> Result<()> {
+        let size = self.terminal.size()?;
+        let screen = Rect::new(0, 0, size.width, size.height);
+        self.editor.resize(view_area(screen, self.editor.config.editor.breadcrumbs));
+        Ok(())
+    }
+
+    /// The editor state
+    pub fn editor(&self) -> &Editor {
+        &self.editor
+    }
+
+    /// The editor state, to set up tests
+    pub fn editor_mut(&mut self) -> &mut Editor {
+        &mut self.editor
+    }
+
+    /// The frontend drawn to
+    pub fn frontend(&self) -> &F {
+        self.terminal.backend()
+    }
 
-        Ok(Self {
-            editor,
-            compositor,
-            terminal,
-            events,
-        })
+    /// The frontend drawn to
+    pub fn frontend_mut(&mut self) -> &mut F {
+        self.terminal.backend_mut()
     }
 
     /// Open a file
@@ -63,96 +179,536 @@ impl Application {
         Ok(())
     }
 
-    /// Set update notice to show in status bar
-    pub fn set_update_notice(&mut self, msg: String) {
-        self.editor.set_status(msg, lite_view::Severity::Info);
+    /// Whether the user asked to install a newer release on the way out
+    pub fn update_requested(&self) -> bool {
+        self.update_requested
     }
 
     /// Run the application main loop
     pub async fn run(&mut self) -> Result<()> {
-        // Start event handler
-        self.events.start();
+        // Start taking input
+        self.terminal.backend_mut().listen(&self.events);
+
+        // TODO counts in the statusline, for projects only: scanning a home
+        // directory would be slow and pointless
+        let cwd = std::env::current_dir().unwrap_or_default();
+        if lite_view::workspace::find_root(&cwd).is_some() {
+            self.editor.scan_todos();
+        }
 
         // Main loop
         while !self.editor.should_quit {
-            // Render
-            self.render()?;
+            self.update();
+            if self.frame_due() {
+                self.render()?;
+            }
 
             // Handle events
             if let Some(event) = self.events.next().await {
-                self.handle_event(event)?;
+                self.handle_pending_events(event)?;
+                self.stop_replay_on_quit()?;
             }
         }
 
+        // Nowhere to report a failure once the screen is given back
+        let _ = crate::recent::save(&self.editor.recent_files);
+        let _ = crate::prompt_history::save(&self.editor.prompt_history);
+        self.lsp.shutdown().await;
         Ok(())
     }
 
+    /// Handle events as if the terminal had sent them, then update and draw
+    /// the screen as the main loop does before waiting for more
+    pub fn handle_events(&mut self, events: impl IntoIterator<Item = Event>) -> Result<()> {
+        for event in events {
+            self.events.send(event);
+        }
+        while let Some(event) = self.events.try_next() {
+            self.handle_pending_events(event)?;
+            self.stop_replay_on_quit()?;
+        }
+        self.update();
+        self.render()
+    }
+
+    /// Whether to draw now. In low bandwidth mode frames are held back for
+    /// a while; the ticks of an idle loop draw what is left.
+    fn frame_due(&self) -> bool {
+        !self.editor.low_bandwidth || self.last_frame.is_none_or(|last| last.elapsed() >= LOW_BANDWIDTH_FRAME)
+    }
+
+    /// Bring the state derived from the documents up to date
+    fn update(&mut self) {
+        match self.editor.config.editor.low_bandwidth {
+            LowBandwidth::On if !self.editor.low_bandwidth => self.editor.set_low_bandwidth(true),
+            LowBandwidth::Off if self.editor.low_bandwidth => self.editor.set_low_bandwidth(false),
+            _ => {}
+        }
+        let started = Instant::now();
+        self.lsp.sync(&mut self.editor);
+        self.metrics.step("language servers", started);
+        let started = Instant::now();
+        self.git.sync(&mut self.editor);
+        self.metrics.step("git", started);
+
+        let started = Instant::now();
+        self.editor.follow_changes();
+        self.editor.sync_diff_scroll();
+        self.editor.sync_scroll_bind();
+        self.editor.update_outline();
+        self.editor.update_breadcrumbs(self.idle.is_idle(Instant::now()));
+        self.editor.update_search_decorations(self.idle.is_idle(Instant::now()));
+        if self.editor.poll_todos() {
+            crate::todo::refresh(&mut self.editor);
+        }
+        self.editor.poll_jobs();
+        self.compositor.poll();
+        self.metrics.step("views", started);
+    }
+
     /// Render the UI
     fn render(&mut self) -> Result<()> {
         let ctx = Context::new(&mut self.editor);
 
+        let started = Instant::now();
+        // The frame is ready here; what's left is writing it out
+        let mut drawn = started;
         self.terminal.draw(|frame| {
             let area = frame.area();
 
-            // Layout: tab line (1), editor (remaining - 4), status line (1), help bar (2)
-            let tab_area = Rect {
-                x: area.x,
-                y: area.y,
-                width: area.width,
-                height: 1,
-            };
-            let editor_area = Rect {
-                x: area.x,
-                y: area.y + 1,
-                width: area.width,
-                height: area.height.saturating_sub(4),
+            let [tab_area, breadcrumbs_area, editor_area, status_area, help_area] =
+                screen_layout(area, ctx.editor.config.editor.breadcrumbs);
+            // The outline panel takes the right side of the views' area
+            let outline_width = ctx.editor.outline_width(editor_area.width);
+            let outline_area = Rect {
+                x: editor_area.right() - outline_width,
+                width: outline_width,
+                ..editor_area
             };
-            let status_area = Rect {
-                x: area.x,
-                y: area.height.saturating_sub(3),
-                width: area.width,
-                height: 1,
+            // The file tree takes the left side
+            let tree_width = ctx.editor.file_tree_width(editor_area.width);
+            let tree_area = Rect {
+                width: tree_width,
+                ..editor_area
             };
-            let help_area = Rect {
-                x: area.x,
-                y: area.height.saturating_sub(2),
-                width: area.width,
-                height: 2,
+            let editor_area = Rect {
+                x: editor_area.x + tree_width,
+                width: editor_area.width - tree_width - outline_width,
+                ..editor_area
             };
 
             // Render base layers
             TabLine::new().render(frame, tab_area, &ctx);
+            Breadcrumbs::new().render(frame, breadcrumbs_area, &ctx);
             EditorView::new().render(frame, editor_area, &ctx);
+            FileTreePanel::new().render(frame, tree_area, &ctx);
+            OutlinePanel::new().render(frame, outline_area, &ctx);
             StatusLine::new().render(frame, status_area, &ctx);
             HelpBar::new().render(frame, help_area, &ctx);
 
+            if ctx.editor.buffer_switcher.is_some() {
+                BufferSwitcherPopup::new().render(frame, editor_area, &ctx);
+            }
+            if ctx.editor.peek.is_some() {
+                PeekPopup::new().render(frame, editor_area, &ctx);
+            }
+            if ctx.editor.signature_help.is_some() {
+                SignatureHelpPopup::new().render(frame, editor_area, &ctx);
+            }
+            if ctx.editor.completion.is_some() {
+                CompletionPopup::new().render(frame, editor_area, &ctx);
+            }
+
             // Render compositor layers (popups, etc.)
             self.compositor.render(frame, area, &ctx);
 
+            if ctx.editor.bell_flashing(Bell::Screen) {
+                let flash = ratatui::style::Style::new().add_modifier(ratatui::style::Modifier::REVERSED);
+                frame.buffer_mut().set_style(area, flash);
+            }
+
             // Set cursor position
             if let Some((x, y)) = EditorView::new().cursor(editor_area, &ctx) {
                 frame.set_cursor_position((x, y));
             }
+            drawn = Instant::now();
         })?;
+        self.last_frame = Some(Instant::now());
+        self.check_frame_time(drawn.elapsed());
+        self.report_metrics(started);
 
+        self.update_cursor_style()?;
+        self.update_mouse_capture()?;
         Ok(())
     }
 
+    /// Draw less when the terminal can't keep up with what a frame writes
+    /// to it: the low bandwidth mode when it's `auto`, or else no smooth
+    /// scrolling. `auto` turns the mode off again once frames are fast.
+    fn check_frame_time(&mut self, written: Duration) {
+        self.slow_frames = if written > SLOW_FRAME { self.slow_frames + 1 } else { 0 };
+        self.fast_frames = if written < FAST_FRAME { self.fast_frames + 1 } else { 0 };
+        let auto = self.editor.config.editor.low_bandwidth == LowBandwidth::Auto;
+        if auto && self.editor.low_bandwidth && self.fast_frames >= FAST_FRAMES_LIMIT {
+            self.editor.set_low_bandwidth(false);
+            self.editor.set_status(
+                "Low bandwidth mode off: the terminal keeps up again",
+                lite_view::Severity::Info,
+            );
+            return;
+        }
+        if self.slow_frames < SLOW_FRAMES_LIMIT || self.editor.low_bandwidth {
+            return;
+        }
+        if auto {
+            self.editor.set_low_bandwidth(true);
+            self.editor.set_status(
+                "Low bandwidth mode on: the terminal draws too slowly",
+                lite_view::Severity::Info,
+            );
+        } else if self.editor.config.editor.smooth_scroll {
+            self.editor.config.editor.smooth_scroll = false;
+            while self.editor.animate_scroll() {}
+            self.editor.set_status(
+                "Smooth scrolling turned off: the terminal draws too slowly",
+                lite_view::Severity::Info,
+            );
+        }
+    }
+
+    /// Report the timings of a frame drawn from `started` as `debug_metrics`
+    /// asks
+    fn report_metrics(&mut self, started: Instant) {
+        let frame = started.elapsed();
+        self.metrics.step("draw", started);
+        let highlight = highlighter().take_time();
+        let Some(metrics) = self.metrics.frame(frame, highlight, Instant::now()) else {
+            return;
+        };
+        match self.editor.config.editor.debug_metrics {
+            DebugMetrics::Off => {}
+            DebugMetrics::Screen => self.editor.frame_metrics = Some(metrics),
+            DebugMetrics::Log => {
+                if let Err(e) = self.metrics.log(&metrics) {
+                    self.editor.config.editor.debug_metrics = DebugMetrics::Off;
+                    self.editor.set_status(
+                        format!("Cannot write metrics.log, metrics turned off: {}", e),
+                        lite_view::Severity::Error,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Send the configured cursor shape to the terminal if it changed
+    fn update_cursor_style(&mut self) -> Result<()> {
+        let config = &self.editor.config.editor;
+        let style = (config.cursor_shape.insert, config.cursor_blink > 0);
+        if self.cursor_style == Some(style) {
+            return Ok(());
+        }
+
+        self.terminal.backend_mut().set_cursor_style(style.0, style.1)?;
+        self.cursor_style = Some(style);
+        Ok(())
+    }
+
+    /// Capture the mouse or give it back to the terminal as the `mouse`
+    /// option changes, as by `:set mouse off`
+    fn update_mouse_capture(&mut self) -> Result<()> {
+        let mouse = self.editor.config.editor.mouse;
+        if self.mouse_capture == Some(mouse) {
+            return Ok(());
+        }
+
+        self.terminal.backend_mut().set_mouse_capture(mouse)?;
+        self.mouse_capture = Some(mouse);
+        Ok(())
+    }
+
+    /// Handle an event and everything queued behind it before the next render.
+    ///
+    /// Runs of typed characters (fast typing, or a paste in terminals without
+    /// bracketed paste) are inserted as a single edit. At most
+    /// `MAX_EVENTS_PER_FRAME` events are handled.
+    fn handle_pending_events(&mut self, first: Event) -> Result<()> {
+        let mut typed = String::new();
+        let mut next = Some(first);
+        let mut handled = 0;
+
+        while let Some(event) = next {
+            self.recorder.event(&event);
+            let typed_char = match &event {
+                Event::Key(key) => self.typed_char(key),
+                _ => None,
+            };
+            if !matches!(event, Event::Tick) {
+                self.metrics.input(Instant::now());
+            }
+            if let Some(c) = typed_char {
+                typed.push(c);
+            } else {
+                self.insert_typed(&mut typed);
+                let (name, started) = (event.name(), Instant::now());
+                self.handle_event(event)?;
+                self.metrics.step(name, started);
+            }
+            handled += 1;
+            next = match handled < MAX_EVENTS_PER_FRAME {
+                true => self.events.try_next(),
+                false => None,
+            };
+        }
+
+        self.insert_typed(&mut typed);
+        let started = Instant::now();
+        self.editor.update_completion();
+        self.editor.update_signature_help();
+        self.editor.update_peek();
+        self.editor.update_diff();
+        self.metrics.step("popups", started);
+
+        if self.editor.config.editor.bell == Bell::Terminal && self.editor.bell.take().is_some() {
+            self.terminal.backend_mut().ring_bell()?;
+        }
+
+        if let Some((pattern, files)) = self.editor.glob_matches.take() {
+            self.compositor.push(Box::new(crate::pickers::glob_matches(&pattern, files)));
+        }
+        match self.editor.debug_command.take() {
+            Some(command) => self.run_debug_command(command),
+            None => Ok(()),
+        }
+    }
+
+    /// Start or stop recording the input, or play a recording back
+    fn run_debug_command(&mut self, command: DebugCommand) -> Result<()> {
+        match command {
+            DebugCommand::Record(path) => {
+                let size = self.terminal.size()?;
+                match self.recorder.start(&path, size.width, size.height) {
+                    Ok(()) => self
+                        .editor
+                        .set_status(format!("Recording to {}", path.display()), Severity::Info),
+                    Err(e) => self
+                        .editor
+                        .set_status(format!("Cannot record to {}: {}", path.display(), e), Severity::Error),
+                }
+            }
+            DebugCommand::StopRecording => {
+                if self.recorder.stop() {
+                    self.editor.set_status("Recording stopped", Severity::Info);
+                } else {
+                    self.editor.set_status("Not recording", Severity::Error);
+                }
+            }
+            DebugCommand::Replay(_) if self.replay.is_some() => {
+                self.editor
+                    .set_status("Already replaying; :debug-replay off first", Severity::Error);
+            }
+            DebugCommand::Replay(path) => match record::load(&path) {
+                Ok(events) => {
+                    if let Err(e) = self.start_replay() {
+                        self.editor.set_status(format!("Cannot replay: {}", e), Severity::Error);
+                        return Ok(());
+                    }
+                    // One event at a time, so popups open between keys as they did
+                    let count = events.len();
+                    for event in events {
+                        if self.editor.should_quit {
+                            break;
+                        }
+                        self.handle_pending_events(event)?;
+                        self.update();
+                    }
+                    let message = format!(
+                        "Replayed {} events from {}; :debug-replay off to go back",
+                        count,
+                        path.display()
+                    );
+                    self.editor.set_status(message, Severity::Info);
+                    self.stop_replay_on_quit()?;
+                }
+                Err(e) => self
+                    .editor
+                    .set_status(format!("Cannot replay {}: {}", path.display(), e), Severity::Error),
+            },
+            DebugCommand::StopReplay => {
+                if self.replay.is_some() {
+                    self.stop_replay()?;
+                    self.editor.set_status("Replay closed", Severity::Info);
+                } else {
+                    self.editor.set_status("Not replaying", Severity::Error);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Put the session aside for a scratch editor with the same settings,
+    /// in an empty working directory. What a recording types is made of
+    /// placeholders, which must not end up in the user's buffers or files.
+    fn start_replay(&mut self) -> Result<()> {
+        let cwd = std::env::current_dir()?;
+        let dir = std::env::temp_dir().join(format!("lite-replay-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        std::env::set_current_dir(&dir)?;
+
+        let mut scratch = Editor::new();
+        scratch.config = self.editor.config.clone();
+        // The project's tasks and linters would run in the project
+        scratch.config.project = Default::default();
+        scratch.keymap = self.editor.keymap.clone();
+        scratch.theme = self.editor.theme.clone();
+        scratch.sandboxed = true;
+        let editor = std::mem::replace(&mut self.editor, scratch);
+        self.compositor = Compositor::new();
+        self.replay = Some(Replay { editor, cwd, dir });
+        self.resize()
+    }
+
+    /// Give the session back
+    fn stop_replay(&mut self) -> Result<()> {
+        let Some(replay) = self.replay.take() else {
+            return Ok(());
+        };
+        self.editor = replay.editor;
+        self.compositor = Compositor::new();
+        std::env::set_current_dir(&replay.cwd)?;
+        let _ = std::fs::remove_dir_all(&replay.dir);
+        self.resize()
+    }
+
+    /// Quitting the scratch editor of a replay goes back to the session
+    fn stop_replay_on_quit(&mut self) -> Result<()> {
+        if self.replay.is_some() && self.editor.should_quit {
+            self.stop_replay()?;
+            self.editor.set_status("Replay closed", Severity::Info);
+        }
+        Ok(())
+    }
+
+    /// The character a key inserts if no layer handles it
+    fn typed_char(&self, key: &KeyEvent) -> Option<char> {
+        let Key::Char(c) = key.key else {
+            return None;
+        };
+        let unclaimed = self.compositor.is_empty()
+            && self.editor.digraph.is_none()
+            && !self.panel_focused()
+            && self.editor.buffer_switcher.is_none()
+            && !self.editor.has_buffer_handler()
+            && self.editor.keymap.get(key).is_none();
+        (unclaimed && key.modifiers.ctrl == key.modifiers.alt).then_some(c)
+    }
+
+    /// Insert and clear batched typed characters
+    fn insert_typed(&mut self, typed: &mut String) {
+        if !typed.is_empty() {
+            let started = Instant::now();
+            self.editor.clear_status();
+            let text = std::mem::take(typed);
+            insert_text(&mut self.editor, &text);
+
+            // Opening or moving on in a call shows its signature
+            if let Some(c @ ('(' | ',')) = text.chars().last() {
+                self.lsp.request_signature_help(&mut self.editor, Some(c));
+            }
+            self.metrics.step("typing", started);
+        }
+    }
+
+    /// Run a task the idle scheduler handed out
+    fn run_idle_task(&mut self, task: IdleTask) {
+        match task {
+            IdleTask::CompactHistory => {
+                for doc in self.editor.documents.values_mut() {
+                    doc.compact();
+                }
+            }
+            IdleTask::PreparseHighlights => {
+                // Those on screen first, one document a task
+                let shown: Vec<_> = (self.editor.tree.views().into_iter())
+                    .filter_map(|view_id| self.editor.views.get(&view_id).map(|view| view.doc_id))
+                    .collect();
+                let mut stale = shown
+                    .into_iter()
+                    .chain(self.editor.documents.keys().copied())
+                    .filter(|id| self.editor.documents.get(id).is_some_and(|doc| !doc.highlights_ready()));
+                if let Some(doc) = stale.next().and_then(|id| self.editor.documents.get(&id)) {
+                    doc.syntax_highlights();
+                }
+                if stale.next().is_some() {
+                    self.idle.schedule(IdleTask::PreparseHighlights);
+                }
+            }
+            IdleTask::SaveRecentFiles => {
+                let changed = self.saved_recent.as_ref().is_some_and(|saved| *saved != self.editor.recent_files);
+                if changed && !self.editor.sandboxed && crate::recent::save(&self.editor.recent_files).is_ok() {
+                    self.saved_recent = Some(self.editor.recent_files.clone());
+                }
+            }
+            IdleTask::CheckUpdate => {
+                let (sender, receiver) = mpsc::channel();
+                std::thread::spawn(move || {
+                    if let Some(version) = newer_release() {
+                        let _ = sender.send(version);
+                    }
+                });
+                self.update_check = Some(receiver);
+            }
+        }
+    }
+
     /// Handle an event
     fn handle_event(&mut self, event: Event) -> Result<()> {
+        if !matches!(event, Event::Tick) {
+            self.idle.input(Instant::now());
+        }
         match event {
             Event::Key(key_event) => {
                 self.handle_key(key_event)?;
+                self.idle.schedule(IdleTask::PreparseHighlights);
+                self.idle.schedule(IdleTask::SaveRecentFiles);
+                self.idle.schedule(IdleTask::CompactHistory);
+            }
+            Event::KeyRelease(held) => {
+                if !held.ctrl {
+                    self.editor.commit_buffer_switcher();
+                }
             }
             Event::Resize(width, height) => {
-                self.editor.resize(width, height);
+                let screen = Rect::new(0, 0, width, height);
+                self.editor.resize(view_area(screen, self.editor.config.editor.breadcrumbs));
+            }
+            Event::Preedit(text) => {
+                self.editor.preedit = Some(text).filter(|text| !text.is_empty());
+            }
+            Event::Commit(text) => {
+                self.editor.preedit = None;
+                self.editor.clear_status();
+                insert_text(&mut self.editor, &text);
+            }
+            Event::FocusLost => {
+                if self.editor.config.editor.auto_save_on.focus_lost {
+                    let doc_id = self.editor.current_doc().id;
+                    self.editor.auto_save(doc_id);
+                }
             }
             Event::Mouse(_mouse) => {
                 // TODO: Mouse handling
             }
             Event::Tick => {
+                self.editor.animate_scroll();
                 // Clear old status messages
                 // TODO: Add timeout for status messages
+                if let Some(task) = self.idle.next(Instant::now()) {
+                    self.run_idle_task(task);
+                }
+                if let Some(version) = self.update_check.as_ref().and_then(|rx| rx.try_recv().ok()) {
+                    self.editor.update_available = Some(version);
+                    self.update_check = None;
+                }
             }
         }
 
@@ -164,6 +720,28 @@ impl Application {
         // Clear status message on any key
         self.editor.clear_status();
 
+        if self.editor.digraph.is_some() {
+            self.handle_digraph_key(&key_event);
+            return Ok(());
+        }
+        if self.editor.buffer_switcher.is_some() && self.handle_buffer_switcher_key(&key_event) {
+            return Ok(());
+        }
+        if self.editor.completion.is_some() && self.handle_completion_key(&key_event) {
+            return Ok(());
+        }
+        if self.editor.outline.as_ref().is_some_and(|outline| outline.focused)
+            && self.handle_outline_key(&key_event)
+        {
+            return Ok(());
+        }
+        if self.compositor.is_empty()
+            && self.editor.file_tree.as_ref().is_some_and(|tree| tree.focused)
+            && self.handle_file_tree_key(&key_event)
+        {
+            return Ok(());
+        }
+
         // First, let compositor handle it (for prompts, etc.)
         {
             let mut ctx = Context::new(&mut self.editor);
@@ -193,6 +771,13 @@ impl Application {
                             self.handle_save_as_file(path)?;
                             return Ok(());
                         }
+                        Action::ExecuteCommand(_)
+                        | Action::ExecuteCloseBuffer(_)
+                        | Action::ExecuteCreateFile(_)
+                        | Action::ExecuteRenameFile(_)
+                        | Action::ExecuteDeleteFile => {
+                            self.compositor.pop(); // Remove the prompt
+                        }
                         Action::Noop => {
                             // Escape was pressed
                             self.compositor.pop();
@@ -201,47 +786,330 @@ impl Application {
                         _ => {}
                     }
                     execute_action(&mut self.editor, &action);
+                    // `:set breadcrumbs` changes the room the views have
+                    if matches!(action, Action::ExecuteCommand(_)) {
+                        self.resize()?;
+                    }
                     return Ok(());
                 }
                 EventResult::Ignored => {}
             }
         }
 
-        // Handle character input
-        if let Key::Char(c) = key_event.key {
-            if key_event.modifiers == Modifier::NONE || key_event.modifiers == Modifier::SHIFT {
-                execute_action(&mut self.editor, &Action::InsertChar(c));
-                return Ok(());
-            }
+        // Virtual buffers get the next chance at the key
+        if self.editor.handle_buffer_key(&key_event) {
+            return Ok(());
         }
 
-        // Check keymap
-        if let Some(action) = self.editor.keymap.get(&key_event).cloned() {
-            // Handle actions that require prompts
-            match &action {
-                Action::GotoLine => {
-                    self.compositor.push(Box::new(Prompt::new(PromptType::GotoLine)));
+        if self.editor.signature_help.is_some()
+            && key_event.key == Key::Escape
+            && key_event.modifiers == Modifier::NONE
+        {
+            self.editor.signature_help = None;
+            return Ok(());
+        }
+        if self.editor.peek.is_some()
+            && key_event.key == Key::Escape
+            && key_event.modifiers == Modifier::NONE
+        {
+            self.editor.peek = None;
+            return Ok(());
+        }
+        if self.editor.update_available.is_some()
+            && key_event.key == Key::Escape
+            && key_event.modifiers == Modifier::NONE
+        {
+            self.editor.update_available = None;
+            return Ok(());
+        }
+
+        // The keymap gets first refusal, even for plain characters
+        let Some(action) = self.editor.keymap.get(&key_event).cloned() else {
+            // Unbound printable keys insert text, unless only one of Ctrl and Alt is
+            // held. Ctrl+Alt is how AltGr arrives on some platforms.
+            if let Key::Char(c) = key_event.key {
+                if key_event.modifiers.ctrl == key_event.modifiers.alt {
+                    execute_action(&mut self.editor, &Action::InsertChar(c));
                 }
-                Action::Find => {
-                    self.compositor.push(Box::new(Prompt::new(PromptType::Search)));
+            }
+            return Ok(());
+        };
+        self.recorder.action(&action);
+
+        // Handle actions that require prompts
+        match &action {
+            Action::GotoLine => {
+                self.compositor.push(Box::new(Prompt::new(PromptType::GotoLine)));
+            }
+            Action::Find => {
+                self.compositor.push(Box::new(Prompt::new(PromptType::Search)));
+            }
+            Action::Replace => {
+                // A substitution of the last search, as a regex, ignoring
+                // case and finding whole words if the search did. A selection
+                // other than a match of it is where to replace; a match is
+                // only where the search stopped, so the buffer is.
+                let query = &self.editor.search_query;
+                let ignore_case = !query.is_empty() && self.editor.config.editor.search_case.ignores_case(query);
+                let doc = self.editor.current_doc();
+                let in_selection = doc.selection(self.editor.tree.focus()).ranges().iter().any(|range| {
+                    let text = doc.rope.slice(range.start()..range.end()).to_string();
+                    let matches = match ignore_case {
+                        true => text.to_lowercase() == query.to_lowercase(),
+                        false => text == *query,
+                    };
+                    !range.is_empty() && !matches
+                });
+                let mut pattern = regex::escape(query).replace('/', "\\/");
+                if self.editor.search_whole_word {
+                    let is_word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+                    if is_word(query.chars().next()) {
+                        pattern.insert_str(0, "\\b");
+                    }
+                    if is_word(query.chars().last()) {
+                        pattern.push_str("\\b");
+                    }
                 }
-                Action::Replace => {
-                    // TODO: Implement proper replace with two prompts
-                    self.compositor.push(Box::new(Prompt::new(PromptType::Search)));
+                let flags: String = [(in_selection, 's'), (ignore_case, 'i')]
+                    .into_iter()
+                    .filter_map(|(set, flag)| set.then_some(flag))
+                    .collect();
+                let mut prompt = match flags.is_empty() {
+                    true => command_prompt().with_initial(format!("s/{}/", pattern)),
+                    false => command_prompt()
+                        .with_initial(format!("s/{}//{}", pattern, flags))
+                        .with_cursor(pattern.len() + 3),
+                };
+                if in_selection {
+                    prompt = prompt.with_message("Replace in selection :");
+                } else {
+                    let view_id = self.editor.tree.focus();
+                    let doc = self.editor.current_doc_mut();
+                    let cursors = doc.selection(view_id).collapse();
+                    doc.set_selection(view_id, cursors);
                 }
-                Action::Open => {
-                    self.compositor.push(Box::new(Prompt::new(PromptType::Open)));
+                self.compositor.push(Box::new(prompt));
+            }
+            Action::Open => {
+                let prompt = Prompt::new(PromptType::Open).with_completion(prompt_completion::complete_path);
+                self.compositor.push(Box::new(prompt));
+            }
+            Action::SaveAs => {
+                self.compositor.push(Box::new(Prompt::new(PromptType::SaveAs)));
+            }
+            Action::CloseBuffer if self.editor.current_doc().modified => {
+                let message = format!(
+                    "Save changes to {}? (y/n, Esc to cancel) ",
+                    self.editor.current_doc().name()
+                );
+                self.compositor
+                    .push(Box::new(Prompt::new(PromptType::CloseBuffer).with_message(message)));
+            }
+            Action::FindInFiles => {
+                self.compositor.push(Box::new(command_prompt().with_initial("grep ")));
+            }
+            Action::SelectInSelection => {
+                self.compositor.push(Box::new(command_prompt().with_initial("select-in ")));
+            }
+            Action::CommandPalette => {
+                self.compositor.push(Box::new(command_prompt()));
+            }
+            Action::QuickOpen => {
+                self.compositor.push(Box::new(crate::pickers::files(&mut self.editor)));
+            }
+            Action::OpenRecent => {
+                self.compositor.push(Box::new(crate::pickers::recent(&self.editor)));
+            }
+            Action::BufferPicker => {
+                self.compositor.push(Box::new(crate::pickers::buffers(&self.editor)));
+            }
+            Action::GotoSymbol => {
+                self.compositor.push(Box::new(crate::pickers::symbols(&self.editor)));
+            }
+            Action::DiagnosticPicker => {
+                self.compositor.push(Box::new(crate::pickers::diagnostics(&self.editor)));
+            }
+            Action::Autocomplete => {
+                self.lsp.request_completion(&mut self.editor);
+            }
+            Action::GotoDefinition => {
+                self.lsp.goto_definition(&mut self.editor);
+            }
+            Action::PeekDefinition => {
+                self.lsp.peek_definition(&mut self.editor);
+            }
+            Action::SignatureHelp => {
+                self.lsp.request_signature_help(&mut self.editor, None);
+            }
+            Action::RevertHunk => {
+                self.git.revert_hunk(&mut self.editor);
+            }
+            Action::StageHunk | Action::UnstageHunk => {
+                self.git.stage_hunk(&mut self.editor, action == Action::StageHunk);
+            }
+            Action::InstallUpdate => {
+                if self.editor.update_available.is_none() {
+                    self.editor.set_status("No update available", lite_view::Severity::Info);
+                } else if self.editor.documents.values().any(|doc| doc.modified) {
+                    self.editor.set_status(
+                        "Save or close modified buffers before updating",
+                        lite_view::Severity::Warning,
+                    );
+                } else {
+                    self.update_requested = true;
+                    self.editor.should_quit = true;
                 }
-                Action::SaveAs => {
-                    self.compositor.push(Box::new(Prompt::new(PromptType::SaveAs)));
+            }
+            _ => {
+                execute_action(&mut self.editor, &action);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle a key while the outline panel has the focus. Returns `true` if
+    /// consumed; keys bound to actions, such as closing the panel, pass.
+    fn handle_outline_key(&mut self, key_event: &KeyEvent) -> bool {
+        if key_event.modifiers.ctrl || key_event.modifiers.alt {
+            return false;
+        }
+        match key_event.key {
+            Key::Up => self.editor.move_outline_selection(-1),
+            Key::Down => self.editor.move_outline_selection(1),
+            Key::PageUp => self.editor.move_outline_selection(-10),
+            Key::PageDown => self.editor.move_outline_selection(10),
+            Key::Home => self.editor.move_outline_selection(isize::MIN),
+            Key::End => self.editor.move_outline_selection(isize::MAX),
+            Key::Enter => self.editor.goto_outline_selection(),
+            Key::Escape => {
+                if let Some(outline) = &mut self.editor.outline {
+                    outline.focused = false;
                 }
-                _ => {
-                    execute_action(&mut self.editor, &action);
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// Handle a key while the file tree has the focus. Returns `true` if
+    /// consumed; keys bound to actions, such as closing the tree, pass.
+    fn handle_file_tree_key(&mut self, key_event: &KeyEvent) -> bool {
+        if key_event.modifiers.ctrl || key_event.modifiers.alt {
+            return false;
+        }
+        let name = crate::file_tree::selected_name(&self.editor);
+        let Some(tree) = &mut self.editor.file_tree else {
+            return false;
+        };
+        match (&key_event.key, name) {
+            (Key::Up, _) => tree.move_selection(-1),
+            (Key::Down, _) => tree.move_selection(1),
+            (Key::PageUp, _) => tree.move_selection(-10),
+            (Key::PageDown, _) => tree.move_selection(10),
+            (Key::Home, _) => tree.move_selection(isize::MIN),
+            (Key::End, _) => tree.move_selection(isize::MAX),
+            (Key::Enter, _) => crate::file_tree::activate(&mut self.editor),
+            (Key::Right, _) => crate::file_tree::expand(&mut self.editor),
+            (Key::Left, _) => crate::file_tree::collapse(&mut self.editor),
+            (Key::Char('a'), _) => {
+                self.compositor.push(Box::new(Prompt::new(PromptType::CreateFile)));
+            }
+            (Key::Char('r'), Some(name)) => {
+                self.compositor
+                    .push(Box::new(Prompt::new(PromptType::RenameFile).with_initial(name)));
+            }
+            (Key::Char('d') | Key::Delete, Some(name)) => {
+                let message = format!("Delete {}? (y/n) ", name);
+                self.compositor
+                    .push(Box::new(Prompt::new(PromptType::DeleteFile).with_message(message)));
+            }
+            (Key::Escape, _) => tree.focused = false,
+            _ => {}
+        }
+        true
+    }
+
+    /// Whether a side panel has the focus, so typing doesn't edit
+    fn panel_focused(&self) -> bool {
+        self.editor.outline.as_ref().is_some_and(|outline| outline.focused)
+            || self.editor.file_tree.as_ref().is_some_and(|tree| tree.focused)
+    }
+
+    /// Handle a key while the buffer switcher is open. Returns `true` if consumed.
+    fn handle_buffer_switcher_key(&mut self, key_event: &KeyEvent) -> bool {
+        match self.editor.keymap.get(key_event) {
+            Some(Action::SwitchRecentBuffer) => {
+                self.editor.cycle_buffer_switcher(true);
+                return true;
+            }
+            Some(Action::SwitchRecentBufferReverse) => {
+                self.editor.cycle_buffer_switcher(false);
+                return true;
+            }
+            _ => {}
+        }
+
+        match key_event.key {
+            Key::Escape => {
+                self.editor.cancel_buffer_switcher();
+                true
+            }
+            Key::Enter => {
+                self.editor.commit_buffer_switcher();
+                true
+            }
+            _ => {
+                // Without release events any other key commits, then gets handled as usual
+                self.editor.commit_buffer_switcher();
+                false
+            }
+        }
+    }
+
+    /// Handle a key while the completion menu is open. Returns `true` if consumed;
+    /// other keys edit as usual and the menu refilters afterwards.
+    fn handle_completion_key(&mut self, key_event: &KeyEvent) -> bool {
+        if key_event.modifiers.ctrl || key_event.modifiers.alt {
+            return false;
+        }
+        match key_event.key {
+            Key::Up | Key::Down => {
+                if let Some(completion) = &mut self.editor.completion {
+                    completion.cycle(key_event.key == Key::Down);
                 }
             }
+            Key::Enter | Key::Tab => self.editor.accept_completion(),
+            Key::Escape => self.editor.cancel_completion(),
+            _ => return false,
         }
+        true
+    }
 
-        Ok(())
+    /// Take a key of a digraph after Ctrl+K: the first is shown at the
+    /// cursor, the second inserts the character the two name. Any key that
+    /// types nothing gives up.
+    fn handle_digraph_key(&mut self, key_event: &KeyEvent) {
+        self.editor.preedit = None;
+        let Some(mut keys) = self.editor.digraph.take() else {
+            return;
+        };
+        let c = match key_event.key {
+            Key::Char(c) if key_event.modifiers.ctrl == key_event.modifiers.alt => c,
+            _ => return,
+        };
+        let Some(first) = keys.chars().next() else {
+            keys.push(c);
+            self.editor.preedit = Some(keys.clone());
+            self.editor.digraph = Some(keys);
+            return;
+        };
+        match digraph::lookup(&self.editor.config.editor.digraphs, first, c) {
+            Some(c) => execute_action(&mut self.editor, &Action::InsertChar(c)),
+            None => self
+                .editor
+                .set_status(format!("No digraph {}{}", first, c), Severity::Error),
+        }
     }
 
     /// Handle goto line command
@@ -254,12 +1122,7 @@ impl Application {
                 let char_pos = doc.rope.line_to_char(target_line);
                 doc.set_selection(view_id, lite_core::Selection::point(char_pos));
 
-                // Ensure cursor is visible
-                let pos = doc.rope.char_to_position(char_pos);
-                let scrolloff = self.editor.config.editor.scrolloff;
-                self.editor
-                    .current_view_mut()
-                    .ensure_cursor_visible(pos.line, pos.col, scrolloff);
+                self.editor.ensure_cursor_visible();
             }
         }
         Ok(())
@@ -268,26 +1131,12 @@ impl Application {
     /// Handle search command
     fn handle_search(&mut self, search_text: &str) -> Result<()> {
         if !search_text.is_empty() {
+            // Kept for F3 and Shift+F3 to cycle through the matches
+            self.editor.search_query = search_text.to_string();
+            // Searching as the query was typed selected the match already
             let view_id = self.editor.tree.focus();
-            let doc = self.editor.current_doc_mut();
-            let text: String = doc.rope.chars().collect();
-
-            if let Some(pos) = text.find(search_text) {
-                let end = pos + search_text.len();
-                let range = lite_core::Range::new(pos, end);
-                doc.set_selection(view_id, lite_core::Selection::single(range));
-
-                // Ensure selection is visible
-                let pos = doc.rope.char_to_position(pos);
-                let scrolloff = self.editor.config.editor.scrolloff;
-                self.editor
-                    .current_view_mut()
-                    .ensure_cursor_visible(pos.line, pos.col, scrolloff);
-
-                self.editor.set_status("Found", lite_view::Severity::Info);
-            } else {
-                self.editor.set_status("Not found", lite_view::Severity::Error);
-            }
+            let start = self.editor.current_doc().selection(view_id).primary().start();
+            self.editor.find_from(start, true);
         }
         Ok(())
     }
@@ -295,9 +1144,7 @@ impl Application {
     /// Handle open file command
     fn handle_open_file(&mut self, path: &str) -> Result<()> {
         if !path.is_empty() {
-            if let Err(e) = self.editor.open(path) {
-                self.editor.set_status(format!("Error: {}", e), lite_view::Severity::Error);
-            }
+            crate::commands::open_path(&mut self.editor, path);
         }
         Ok(())
     }
@@ -313,15 +1160,33 @@ impl Application {
     }
 }
 
-impl Drop for Application {
+impl<F: Frontend> Drop for Application<F> {
     fn drop(&mut self) {
-        // Restore terminal
-        let _ = disable_raw_mode();
-        let _ = execute!(
-            self.terminal.backend_mut(),
-            LeaveAlternateScreen,
-            DisableMouseCapture
-        );
-        let _ = self.terminal.show_cursor();
+        let _ = self.terminal.backend_mut().leave();
     }
 }
+
+/// Split the screen into tab line (1), breadcrumbs (1 when shown), editor
+/// (remaining), status line (1) and help bar (2)
+fn screen_layout(area: Rect, breadcrumbs: bool) -> [Rect; 5] {
+    let top = 1 + u16::from(breadcrumbs);
+    let editor_height = area.height.saturating_sub(top + 3);
+    [
+        Rect::new(area.x, area.y, area.width, 1.min(area.height)),
+        Rect::new(area.x, area.y + 1, area.width, top - 1),
+        Rect::new(area.x, area.y + top, area.width, editor_height),
+        Rect::new(area.x, area.y + top + editor_height, area.width, 1),
+        Rect::new(area.x, area.y + top + 1 + editor_height, area.width, 2),
+    ]
+}
+
+/// The part of the screen shared by the editor's views
+fn view_area(screen: Rect, breadcrumbs: bool) -> lite_view::Rect {
+    let [_, _, editor_area, _, _] = screen_layout(screen, breadcrumbs);
+    lite_view::Rect::new(editor_area.x, editor_area.y, editor_area.width, editor_area.height)
+}
+
+/// The command prompt, completing commands and their arguments with Tab
+fn command_prompt() -> Prompt {
+    Prompt::new(PromptType::Command).with_completion(prompt_completion::complete_command)
+}
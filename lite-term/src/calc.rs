@@ -0,0 +1,162 @@
+//! Arithmetic for `:=`: numbers (`0x` for hex), `+ - * / %`, `^` for
+//! powers and parentheses
+
+/// The value of an expression, or what's wrong with it
+pub fn evaluate(expr: &str) -> Result<f64, String> {
+    let mut parser = Parser {
+        chars: expr.chars().collect(),
+        pos: 0,
+    };
+    if expr.trim().is_empty() {
+        return Err("Nothing to calculate".to_string());
+    }
+    let value = parser.sum()?;
+    match parser.peek() {
+        Some(c) => Err(format!("Unexpected '{}'", c)),
+        None if value.is_finite() => Ok(value),
+        None => Err("Result out of range".to_string()),
+    }
+}
+
+/// A value as it is inserted: whole numbers without a fraction, others to
+/// ten decimals at most
+pub fn format(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        return format!("{}", value as i64);
+    }
+    let text = format!("{:.10}", value);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// Recursive descent, one method per precedence level
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    /// The next character that isn't a space
+    fn peek(&mut self) -> Option<char> {
+        while self.chars.get(self.pos).is_some_and(|c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.peek() == Some(c);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn sum(&mut self) -> Result<f64, String> {
+        let mut value = self.product()?;
+        loop {
+            if self.eat('+') {
+                value += self.product()?;
+            } else if self.eat('-') {
+                value -= self.product()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn product(&mut self) -> Result<f64, String> {
+        let mut value = self.unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(op @ ('*' | '/' | '%')) => op,
+                _ => return Ok(value),
+            };
+            self.pos += 1;
+            let rhs = self.unary()?;
+            if op != '*' && rhs == 0.0 {
+                return Err("Division by zero".to_string());
+            }
+            value = match op {
+                '*' => value * rhs,
+                '/' => value / rhs,
+                _ => value % rhs,
+            };
+        }
+    }
+
+    /// Signs bind looser than `^`, so `-2^2` is -4
+    fn unary(&mut self) -> Result<f64, String> {
+        if self.eat('-') {
+            return Ok(-self.unary()?);
+        }
+        if self.eat('+') {
+            return self.unary();
+        }
+        self.power()
+    }
+
+    /// `^` groups to the right: `2^3^2` is 2^9
+    fn power(&mut self) -> Result<f64, String> {
+        let base = self.atom()?;
+        if self.eat('^') {
+            return Ok(base.powf(self.unary()?));
+        }
+        Ok(base)
+    }
+
+    fn atom(&mut self) -> Result<f64, String> {
+        if self.eat('(') {
+            let value = self.sum()?;
+            if !self.eat(')') {
+                return Err("Missing ')'".to_string());
+            }
+            return Ok(value);
+        }
+        self.peek();
+        let start = self.pos;
+        let in_number = |c: &char| c.is_ascii_alphanumeric() || *c == '.' || *c == '_';
+        while self.chars.get(self.pos).is_some_and(in_number) {
+            self.pos += 1;
+        }
+        let number: String = self.chars[start..self.pos].iter().filter(|c| **c != '_').collect();
+        if number.is_empty() {
+            return Err(match self.peek() {
+                Some(c) => format!("Unexpected '{}'", c),
+                None => "Unexpected end".to_string(),
+            });
+        }
+        let value = match number.strip_prefix("0x").or_else(|| number.strip_prefix("0X")) {
+            Some(hex) => i64::from_str_radix(hex, 16).ok().map(|n| n as f64),
+            None => number.parse().ok(),
+        };
+        value.ok_or_else(|| format!("Not a number: {}", number))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evaluate() {
+        let eval = |expr| evaluate(expr).map(format);
+        assert_eq!(eval("1 + 2 * 3").unwrap(), "7");
+        assert_eq!(eval("(1 + 2) * 3").unwrap(), "9");
+        assert_eq!(eval("10 - 4 - 3").unwrap(), "3");
+        assert_eq!(eval("7 / 2").unwrap(), "3.5");
+        assert_eq!(eval("10 / 3").unwrap(), "3.3333333333");
+        assert_eq!(eval("17 % 5").unwrap(), "2");
+        assert_eq!(eval("2^3^2").unwrap(), "512");
+        assert_eq!(eval("-2^2").unwrap(), "-4");
+        assert_eq!(eval("0x1000 + 1_024").unwrap(), "5120");
+        assert_eq!(eval("0.1 + 0.2").unwrap(), "0.3");
+
+        assert_eq!(eval("").unwrap_err(), "Nothing to calculate");
+        assert_eq!(eval("1 / 0").unwrap_err(), "Division by zero");
+        assert_eq!(eval("(1 + 2").unwrap_err(), "Missing ')'");
+        assert_eq!(eval("1 + ").unwrap_err(), "Unexpected end");
+        assert_eq!(eval("2 ) ").unwrap_err(), "Unexpected ')'");
+        assert_eq!(eval("12px").unwrap_err(), "Not a number: 12px");
+        assert_eq!(eval("2 3").unwrap_err(), "Unexpected '3'");
+    }
+}
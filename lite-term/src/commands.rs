@@ -1,6 +1,807 @@
-use lite_config::Action;
-use lite_core::{Range, RopeExt, Selection, Transaction};
-use lite_view::{Editor, Layout, Severity};
+use crate::prompt_completion::{buffers, paths, words};
+use crate::{
+    calc, commit, directory, expand, file_tree, grep, help, line_range, markdown, number, project, record, todo, wrap,
+};
+use lite_config::{Action, EditorConfig, Theme};
+use lite_core::{Change, Range, RopeExt, Selection, Transaction};
+use lite_view::{
+    comment, fold, link, structure, table, DebugCommand, Diagnostic, DiagnosticSeverity, Document, DocumentId, Editor,
+    Layout, Severity, ViewId,
+};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+/// A command of the command prompt. New commands are registered by adding
+/// them to [`COMMANDS`].
+pub struct Command {
+    /// The name and its abbreviations
+    pub names: &'static [&'static str],
+    /// The arguments taken, as `:help commands` shows them
+    pub args: &'static str,
+    pub description: &'static str,
+    run: fn(&mut Editor, &Args),
+    /// What the arguments typed so far could be, for Tab in the prompt
+    pub complete: Option<fn(&Editor, &str) -> Vec<String>>,
+    /// Whether a line range may come before the name, as in `:10,20d`
+    pub ranged: bool,
+}
+
+impl Command {
+    /// Complete the arguments with the candidates a function lists
+    const fn completing(mut self, complete: fn(&Editor, &str) -> Vec<String>) -> Self {
+        self.complete = Some(complete);
+        self
+    }
+
+    /// Take a line range, given to the command in [`Args::range`]
+    const fn ranged(mut self) -> Self {
+        self.ranged = true;
+        self
+    }
+}
+
+const fn command(
+    names: &'static [&'static str],
+    args: &'static str,
+    description: &'static str,
+    run: fn(&mut Editor, &Args),
+) -> Command {
+    Command {
+        names,
+        args,
+        description,
+        run,
+        complete: None,
+        ranged: false,
+    }
+}
+
+/// What a command is given: the text after its name, whether the name
+/// ended with `!` to do it even if changes are lost, as in `:bd!`, and the
+/// line range before it
+pub struct Args<'a> {
+    pub text: &'a str,
+    pub force: bool,
+    /// Lines from 0, the end excluded
+    pub range: Option<std::ops::Range<usize>>,
+}
+
+impl Args<'_> {
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// The lines of the range, or the cursor's line without one
+    fn lines(&self, editor: &Editor) -> std::ops::Range<usize> {
+        self.range.clone().unwrap_or_else(|| {
+            let doc = editor.current_doc();
+            let line = doc.rope.char_to_line(doc.selection(editor.tree.focus()).cursor());
+            line..line + 1
+        })
+    }
+
+    /// The arguments split at whitespace, see [`split_args`]
+    pub fn split(&self) -> Result<Vec<String>, String> {
+        split_args(self.text)
+    }
+}
+
+/// Commands available from the command prompt
+pub const COMMANDS: &[Command] = &[
+    command(
+        &["help", "h"],
+        "[topic]",
+        "Open the help browser",
+        |editor, args| {
+            help::open_help(editor, if args.is_empty() { "index" } else { args.text })
+        },
+    )
+    .completing(|_, _| help::TOPICS.iter().map(|(topic, _)| topic.to_string()).collect()),
+    command(&["set"], "<option>[=value]", "Show or change an option, as in :set tab_width=2", set)
+        .completing(|_, _| EditorConfig::OPTIONS.iter().map(|(name, _)| name.to_string()).collect()),
+    command(&["theme"], "[name]", "Show the theme or switch to a built-in or ~/.config/lite/themes one", theme)
+        .completing(|_, _| Theme::names()),
+    command(&["write", "w"], "[path]", "Save the current buffer", write).completing(paths),
+    command(&["edit", "e"], "<path>", "Open a file, directory or glob; ~, $VAR and %:h expand", edit).completing(paths),
+    command(&["buffer", "b"], "<buffer>", "Switch to a buffer by name or path", buffer).completing(buffers),
+    command(
+        &["buffer-delete", "bd"],
+        "[buffer...]",
+        "Close buffers, by default this one (bd! drops changes)",
+        buffer_delete,
+    )
+    .completing(buffers),
+    command(&["dir"], "[dir]", "Edit a directory listing, by default the current file's", dir).completing(paths),
+    command(&["hard-wrap"], "[width]", "Rewrap the paragraph or selected lines", hard_wrap),
+    command(
+        &["substitute", "s"],
+        "/regex/replacement/[gis]",
+        "Replace in the selection or buffer, or the lines of a range (s: only the selection); $1, ${name} are groups",
+        |editor, args| substitute(editor, args.text, args.range.clone()),
+    )
+    .ranged(),
+    command(&["delete", "d"], "", "Delete lines, by default the cursor's, as in :10,20d", delete_lines).ranged(),
+    command(&[">"], "", "Indent lines, a level per >, as in :5,30>", |editor, args| shift_lines(editor, args, true))
+        .ranged(),
+    command(&["<"], "", "Unindent lines, a level per <", |editor, args| shift_lines(editor, args, false)).ranged(),
+    command(
+        &["select-in"],
+        "<text>",
+        "Put a cursor on each match of the text inside the selection",
+        |editor, args| select_in_selection(editor, args.text),
+    ),
+    command(&["table"], "", "Toggle aligned columns for CSV/TSV files", |editor, _| toggle_table(editor)),
+    command(
+        &["md-check"],
+        "",
+        "Tick or clear the Markdown checkbox of the selected lines, adding one if missing",
+        |editor, _| edit_lines(editor, markdown::toggle_checkbox),
+    ),
+    command(
+        &["md-promote"],
+        "",
+        "Raise the selected Markdown headings a level (fewer #)",
+        |editor, _| edit_lines(editor, |line| markdown::shift_heading(line, true)),
+    ),
+    command(
+        &["md-demote"],
+        "",
+        "Lower the selected Markdown headings a level; past 6 they become text",
+        |editor, _| edit_lines(editor, |line| markdown::shift_heading(line, false)),
+    ),
+    command(
+        &["md-bold"],
+        "",
+        "Make the selection **bold**, or no longer",
+        |editor, _| toggle_emphasis(editor, "**"),
+    ),
+    command(
+        &["md-italic"],
+        "",
+        "Make the selection *italic*, or no longer",
+        |editor, _| toggle_emphasis(editor, "*"),
+    ),
+    command(
+        &["md-code"],
+        "",
+        "Make the selection `code`, or no longer",
+        |editor, _| toggle_emphasis(editor, "`"),
+    ),
+    command(
+        &["md-table"],
+        "",
+        "Line up the pipes of the Markdown table under the cursor",
+        |editor, _| format_markdown_table(editor),
+    ),
+    command(
+        &["fold-level"],
+        "[n]",
+        "Fold sections of level n and deeper, as headings from n #s; without n, unfold all",
+        |editor, args| match args.text.parse::<usize>() {
+            _ if args.is_empty() || args.text == "0" => editor.current_doc_mut().folds.clear(),
+            Ok(level) => fold_level(editor, level),
+            Err(_) => editor.set_status(format!("Invalid level: {}", args.text), Severity::Error),
+        },
+    ),
+    command(
+        &["json-format"],
+        "",
+        "Pretty-print the JSON buffer or selection",
+        |editor, _| {
+            let indent = indent_unit(editor);
+            format_json(editor, Some(&indent));
+        },
+    ),
+    command(
+        &["json-minify"],
+        "",
+        "Minify the JSON buffer or selection",
+        |editor, _| format_json(editor, None),
+    ),
+    command(&["validate"], "", "Check JSON, YAML or TOML syntax", |editor, _| validate(editor)),
+    command(&["cd"], "[dir]", "Change the working directory, by default to the project root", cd).completing(paths),
+    command(&["root"], "[dir|-]", "Show or set the project root of this buffer (- to detect it)", root)
+        .completing(paths),
+    command(
+        &["calc", "="],
+        "[expr]",
+        "Calculate, as in := 4096 / 3; without an expression, the selection",
+        |editor, args| calculate(editor, args.text, false),
+    ),
+    command(
+        &["calc-insert"],
+        "[expr]",
+        "Insert the result of a calculation, or replace the selection with it",
+        |editor, args| calculate(editor, args.text, true),
+    ),
+    command(
+        &["number"],
+        "",
+        "Show the number under the cursor in every base, and as a date if it's a timestamp",
+        |editor, _| match number_at_cursor(editor) {
+            Some((_, _, value)) => editor.set_status(number::describe(value), Severity::Info),
+            None => editor.set_status("No number under the cursor", Severity::Error),
+        },
+    ),
+    command(
+        &["convert"],
+        "<dec|hex|oct|bin|date>",
+        "Rewrite the number under the cursor in a base, or a timestamp as a date",
+        |editor, args| convert_number(editor, args.text),
+    )
+    .completing(|_, _| words(&["dec", "hex", "oct", "bin", "date"])),
+    command(&["bom"], "[on|off]", "Show, add or remove the byte order mark written on save", bom)
+        .completing(|_, _| words(&["on", "off"])),
+    command(&["workspace-add"], "<dir>", "Add a folder to the session", workspace_add).completing(paths),
+    command(&["workspace-remove"], "<dir>", "Remove a folder from the session", workspace_remove).completing(paths),
+    command(&["workspaces"], "", "List the folders in the session", workspaces),
+    command(
+        &["scroll-bind", "scb"],
+        "[on|off]",
+        "Scroll this split with the other bound ones, keeping the lines between them",
+        scroll_bind,
+    )
+    .completing(|_, _| words(&["on", "off"])),
+    command(
+        &["follow"],
+        "",
+        "Split to show the lines after this split, the two scrolling as one page",
+        |editor, _| editor.follow(),
+    ),
+    command(&["page-new"], "[name]", "Add a layout page with splits of its own", |editor, args| {
+        editor.new_page(args.text)
+    }),
+    command(&["page"], "[number|name]", "Show a layout page, or list them", page)
+        .completing(|editor, _| editor.pages().filter(|name| !name.is_empty()).map(String::from).collect()),
+    command(&["page-next"], "", "Show the next layout page (Ctrl+Alt+PageDown)", |editor, _| {
+        editor.cycle_page(true)
+    }),
+    command(&["page-prev"], "", "Show the previous layout page (Ctrl+Alt+PageUp)", |editor, _| {
+        editor.cycle_page(false)
+    }),
+    command(&["page-rename"], "<name>", "Name the current layout page", |editor, args| {
+        editor.rename_page(args.text)
+    }),
+    command(&["page-close"], "", "Close the current layout page and its splits", |editor, _| {
+        if !editor.close_page() {
+            editor.set_status("Can't close the last page", Severity::Error);
+        }
+    }),
+    command(
+        &["grep"],
+        "<regex>",
+        "List the lines of the project matching a regex (Ctrl+Shift+F)",
+        |editor, args| grep::grep(editor, args.text),
+    ),
+    command(
+        &["grep-replace"],
+        "/regex/replacement/[i]",
+        "Preview a replacement across the project, then apply it",
+        |editor, args| grep::grep_replace(editor, args.text),
+    ),
+    command(&["todo"], "", "List TODO/FIXME comments in the project", |editor, _| todo::open_todos(editor)),
+    command(
+        &["format"],
+        "",
+        "Run the .lite.toml formatter of the buffer's language over it",
+        |editor, _| project::format(editor),
+    ),
+    command(
+        &["lint"],
+        "",
+        "Run the .lite.toml lint command of the buffer's language",
+        |editor, _| project::lint(editor),
+    ),
+    command(
+        &["task"],
+        "[name]",
+        "Run a task from .lite.toml, or list them",
+        |editor, args| project::task(editor, args.text),
+    )
+    .completing(|editor, _| editor.config.project.tasks.keys().cloned().collect()),
+    command(
+        &["git"],
+        "commit",
+        "Write a message for the staged changes and commit them on save",
+        |editor, args| match args.text {
+            "commit" => commit::open_commit(editor),
+            _ => editor.set_status("Usage: git commit", Severity::Error),
+        },
+    )
+    .completing(|_, _| words(&["commit"])),
+    command(
+        &["diff-buffers"],
+        "[buffer] [buffer]",
+        "Compare two buffers, by default this and the previous one",
+        diff_buffers,
+    )
+    .completing(buffers),
+    command(
+        &["diff-head"],
+        "",
+        "Compare the buffer with its last committed version",
+        |editor, _| diff_head(editor),
+    ),
+    command(
+        &["diff-push"],
+        "",
+        "Copy the difference under the cursor to the other buffer",
+        |editor, _| transfer_hunk(editor, true),
+    ),
+    command(
+        &["diff-pull"],
+        "",
+        "Take the other buffer's version of the difference under the cursor",
+        |editor, _| transfer_hunk(editor, false),
+    ),
+    command(&["diff-next"], "", "Go to the next difference", |editor, _| goto_diff_hunk(editor, true)),
+    command(&["diff-prev"], "", "Go to the previous difference", |editor, _| goto_diff_hunk(editor, false)),
+    command(&["diff-off"], "", "Stop comparing buffers", |editor, _| editor.stop_diff()),
+    command(
+        &["debug-record"],
+        "[path|off]",
+        "Record keys and actions, not buffer contents, to replay a bug",
+        |editor, args| debug_recording(editor, args, true),
+    ),
+    command(
+        &["debug-replay"],
+        "[path|off]",
+        "Play a recording back in a scratch editor",
+        |editor, args| debug_recording(editor, args, false),
+    ),
+    command(&["quit", "q"], "", "Quit the editor", |editor, _| editor.should_quit = true),
+];
+
+/// Execute a command typed at the command prompt
+pub fn execute_command(editor: &mut Editor, input: &str) {
+    let input = input.trim().trim_start_matches(':');
+    let doc = editor.current_doc();
+    let (range, input) = match line_range::parse(input, &doc.rope, &doc.selection(editor.tree.focus())) {
+        Ok((range, input)) => (range, input.trim_start()),
+        Err(e) => return editor.set_status(e, Severity::Error),
+    };
+    let (name, text) = match input.split_once(char::is_whitespace) {
+        // `:=` needs no space before the expression
+        _ if input.starts_with('=') => ("=", input[1..].trim()),
+        _ if input.starts_with("s/") => ("s", &input[1..]),
+        // Nor `:>` the other `>`s of `:>>`
+        _ if input.starts_with(['>', '<']) => input.split_at(1),
+        Some((name, args)) => (name, args.trim()),
+        None => (input, ""),
+    };
+    if name.is_empty() {
+        // A range alone goes to its last line
+        if let Some(range) = range {
+            editor.goto_line(range.end - 1);
+        }
+        return;
+    }
+    let (name, force) = match name.strip_suffix('!') {
+        Some(name) => (name, true),
+        None => (name, false),
+    };
+
+    match find_command(name) {
+        Some(command) if range.is_some() && !command.ranged => {
+            editor.set_status(format!("{} takes no range", name), Severity::Error)
+        }
+        Some(command) => (command.run)(editor, &Args { text: text.trim(), force, range }),
+        None => editor.set_status(format!("Unknown command: {}", name), Severity::Error),
+    }
+}
+
+/// The command a name or abbreviation stands for
+pub fn find_command(name: &str) -> Option<&'static Command> {
+    COMMANDS.iter().find(|command| command.names.contains(&name))
+}
+
+/// Split arguments at whitespace. Quotes keep whitespace in an argument,
+/// as in `"my notes.md"`, and `\` takes the next char as it is, except
+/// inside single quotes.
+pub fn split_args(text: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let mut arg: Option<String> = None;
+    let mut quote = None;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match (c, quote) {
+            ('\\', quote) if quote != Some('\'') => {
+                let escaped = chars.next().ok_or("Nothing after \\ to escape")?;
+                arg.get_or_insert_default().push(escaped);
+            }
+            (c, Some(open)) if c == open => quote = None,
+            (c, Some(_)) => arg.get_or_insert_default().push(c),
+            ('"' | '\'', None) => {
+                quote = Some(c);
+                arg.get_or_insert_default();
+            }
+            (c, None) if c.is_whitespace() => args.extend(arg.take()),
+            (c, None) => arg.get_or_insert_default().push(c),
+        }
+    }
+    if let Some(open) = quote {
+        return Err(format!("No closing {}", open));
+    }
+    args.extend(arg);
+    Ok(args)
+}
+
+/// Change an option for this session, as in `:set tab_width=2` or `:set
+/// soft_wrap on`, and show what it is
+fn set(editor: &mut Editor, args: &Args) {
+    let (name, value) = match args.text.split_once(['=', ' ']) {
+        Some((name, value)) => (name, Some(value.trim_start_matches([' ', '=']))),
+        None => (args.text, None),
+    };
+    if name.is_empty() {
+        editor.set_status("Usage: set <option>[=value] (:help config lists them)", Severity::Error);
+        return;
+    }
+    if let Some(Err(e)) = value.map(|value| editor.config.editor.set(name, value)) {
+        editor.set_status(e, Severity::Error);
+        return;
+    }
+    match editor.config.editor.get(name) {
+        Some(value) => editor.set_status(format!("{} = {}", name, value), Severity::Info),
+        None => editor.set_status(format!("Unknown option: {}", name), Severity::Error),
+    }
+}
+
+/// Switch to a theme for this session, or show the current one
+fn theme(editor: &mut Editor, args: &Args) {
+    if args.is_empty() {
+        editor.set_status(format!("theme = {}", editor.theme.name), Severity::Info);
+        return;
+    }
+    match Theme::load(args.text) {
+        Ok(theme) => {
+            editor.set_status(format!("theme = {}", theme.name), Severity::Info);
+            editor.theme = theme;
+        }
+        Err(e) => editor.set_status(format!("Error: {}", e), Severity::Error),
+    }
+}
+
+fn write(editor: &mut Editor, args: &Args) {
+    let result = if args.is_empty() {
+        editor.save()
+    } else {
+        editor.save_as(args.text)
+    };
+    if let Err(e) = result {
+        editor.set_status(format!("Error saving: {}", e), Severity::Error);
+    }
+}
+
+fn edit(editor: &mut Editor, args: &Args) {
+    if args.is_empty() {
+        editor.set_status("Usage: edit <path>", Severity::Error);
+    } else {
+        open_path(editor, args.text);
+    }
+}
+
+fn buffer(editor: &mut Editor, args: &Args) {
+    match args.split().as_deref() {
+        Ok([name]) => match find_buffer(editor, name) {
+            Ok(doc_id) => editor.switch_to_document(doc_id),
+            Err(e) => editor.set_status(e, Severity::Error),
+        },
+        Ok(_) => editor.set_status("Usage: buffer <buffer>", Severity::Error),
+        Err(e) => editor.set_status(e, Severity::Error),
+    }
+}
+
+/// Close the named buffers, or the current one. Buffers with changes stay
+/// open unless forced.
+fn buffer_delete(editor: &mut Editor, args: &Args) {
+    let names = match args.split() {
+        Ok(names) => names,
+        Err(e) => {
+            editor.set_status(e, Severity::Error);
+            return;
+        }
+    };
+    let doc_ids = match names.is_empty() {
+        true => Ok(vec![editor.current_doc().id]),
+        false => names.iter().map(|name| find_buffer(editor, name)).collect(),
+    };
+    match doc_ids {
+        Ok(doc_ids) => {
+            for doc_id in doc_ids {
+                if !editor.close_document(doc_id, args.force) {
+                    let name = editor.documents[&doc_id].name();
+                    let message = format!("{} has unsaved changes (bd! closes it anyway)", name);
+                    editor.set_status(message, Severity::Warning);
+                    return;
+                }
+            }
+        }
+        Err(e) => editor.set_status(e, Severity::Error),
+    }
+}
+
+fn hard_wrap(editor: &mut Editor, args: &Args) {
+    match args.text {
+        "" => format_paragraph(editor, editor.config.editor.text_width),
+        _ => match args.text.parse() {
+            Ok(width) if width > 0 => format_paragraph(editor, width),
+            _ => editor.set_status(format!("Invalid width: {}", args.text), Severity::Error),
+        },
+    }
+}
+
+fn diff_buffers(editor: &mut Editor, args: &Args) {
+    let names = match args.split() {
+        Ok(names) => names,
+        Err(e) => {
+            editor.set_status(e, Severity::Error);
+            return;
+        }
+    };
+    let current = editor.current_doc().id;
+    let previous = editor.buffers_mru().iter().copied().find(|id| *id != current);
+    let found = match &names[..] {
+        [] => previous.map(|previous| Ok((current, previous))),
+        [right] => Some(find_buffer(editor, right).map(|right| (current, right))),
+        [left, right] => Some(find_buffer(editor, left).and_then(|left| {
+            find_buffer(editor, right).map(|right| (left, right))
+        })),
+        _ => Some(Err("Usage: diff-buffers [buffer] [buffer]".to_string())),
+    };
+    match found {
+        Some(Ok((left, right))) if left != right => editor.diff_buffers(left, right),
+        Some(Ok(_)) => editor.set_status("Can't compare a buffer with itself", Severity::Error),
+        Some(Err(e)) => editor.set_status(e, Severity::Error),
+        None => editor.set_status("No other buffer to compare with", Severity::Error),
+    }
+}
+
+fn transfer_hunk(editor: &mut Editor, push: bool) {
+    if editor.diff.is_none() {
+        editor.set_status("Not comparing buffers", Severity::Error);
+    } else if !editor.transfer_hunk(push) {
+        editor.set_status("No difference under the cursor", Severity::Info);
+    }
+}
+
+fn goto_diff_hunk(editor: &mut Editor, forward: bool) {
+    if editor.diff.is_none() {
+        editor.set_status("Not comparing buffers", Severity::Error);
+    } else if !editor.goto_hunk(forward) {
+        editor.set_status("No more differences", Severity::Info);
+    }
+}
+
+fn dir(editor: &mut Editor, args: &Args) {
+    let dir = match args.text {
+        "" => editor
+            .current_doc()
+            .path
+            .as_ref()
+            .and_then(|path| path.parent())
+            .map(|dir| dir.to_path_buf())
+            .unwrap_or_else(|| PathBuf::from(".")),
+        _ => expand_home(args.text),
+    };
+    directory::open_directory(editor, &dir);
+}
+
+fn cd(editor: &mut Editor, args: &Args) {
+    let dir = match args.text {
+        "" => editor.workspace_root(editor.current_doc().id),
+        _ => expand_home(args.text),
+    };
+    match editor.change_dir(&dir) {
+        Ok(()) => editor.set_status(format!("Working directory: {}", dir.display()), Severity::Info),
+        Err(e) => editor.set_status(format!("Cannot change directory: {}", e), Severity::Error),
+    }
+}
+
+fn bom(editor: &mut Editor, args: &Args) {
+    let doc = editor.current_doc_mut();
+    match args.text {
+        "" => {}
+        "on" => doc.set_bom(true),
+        "off" => doc.set_bom(false),
+        _ => {
+            editor.set_status(format!("Usage: bom [on|off], not {}", args.text), Severity::Error);
+            return;
+        }
+    }
+    let state = if editor.current_doc().bom { "on" } else { "off" };
+    editor.set_status(format!("Byte order mark: {}", state), Severity::Info);
+}
+
+/// Bind or unbind the current view's scrolling, toggling it without an
+/// argument
+fn scroll_bind(editor: &mut Editor, args: &Args) {
+    let view_id = editor.tree.focus();
+    let bind = match args.text {
+        "" => editor.current_view().scroll_bind.is_none(),
+        "on" => true,
+        "off" => false,
+        _ => return editor.set_status(format!("Usage: scroll-bind [on|off], not {}", args.text), Severity::Error),
+    };
+    editor.bind_scroll(view_id, bind);
+    let state = if bind { "on" } else { "off" };
+    editor.set_status(format!("Scroll bind {}", state), Severity::Info);
+}
+
+fn workspace_add(editor: &mut Editor, args: &Args) {
+    if args.is_empty() {
+        editor.set_status("Usage: workspace-add <dir>", Severity::Error);
+        return;
+    }
+    match editor.add_workspace(&expand_home(args.text)) {
+        Ok(dir) => editor.set_status(format!("Added workspace: {}", dir.display()), Severity::Info),
+        Err(e) => editor.set_status(format!("Cannot add workspace: {}", e), Severity::Error),
+    }
+}
+
+fn workspace_remove(editor: &mut Editor, args: &Args) {
+    if editor.remove_workspace(&expand_home(args.text)) {
+        editor.set_status(format!("Removed workspace: {}", args.text), Severity::Info);
+    } else {
+        editor.set_status(format!("Not a workspace: {}", args.text), Severity::Error);
+    }
+}
+
+fn workspaces(editor: &mut Editor, _: &Args) {
+    let list: Vec<String> = editor
+        .workspaces()
+        .iter()
+        .map(|dir| dir.display().to_string())
+        .collect();
+    if list.is_empty() {
+        editor.set_status("No workspaces added", Severity::Info);
+    } else {
+        editor.set_status(format!("Workspaces: {}", list.join(", ")), Severity::Info);
+    }
+}
+
+/// Show a layout page by number, from 1, or name. Without one, list them.
+fn page(editor: &mut Editor, args: &Args) {
+    let index = match args.text.parse::<usize>() {
+        _ if args.is_empty() => {
+            let titles = editor.page_titles();
+            let current = &titles[editor.current_page()];
+            editor.set_status(format!("Pages: {} (showing {})", titles.join(", "), current), Severity::Info);
+            return;
+        }
+        Ok(number) => number.checked_sub(1),
+        Err(_) => editor.pages().position(|name| name == args.text),
+    };
+    if !index.is_some_and(|index| editor.switch_page(index)) {
+        editor.set_status(format!("No page {}", args.text), Severity::Error);
+    }
+}
+
+fn root(editor: &mut Editor, args: &Args) {
+    let doc_id = editor.current_doc().id;
+    match args.text {
+        "" => {}
+        "-" => editor.set_workspace_root(doc_id, None),
+        _ => match std::path::absolute(expand_home(args.text)) {
+            Ok(dir) if dir.is_dir() => editor.set_workspace_root(doc_id, Some(dir)),
+            _ => {
+                editor.set_status(format!("Not a directory: {}", args.text), Severity::Error);
+                return;
+            }
+        },
+    }
+    let root = editor.workspace_root(doc_id);
+    editor.set_status(format!("Project root: {}", root.display()), Severity::Info);
+}
+
+/// Start or stop recording, or replay a recording, by the application
+fn debug_recording(editor: &mut Editor, args: &Args, record: bool) {
+    let path = match args.text {
+        "" => record::default_path(),
+        "off" => {
+            editor.debug_command = Some(match record {
+                true => DebugCommand::StopRecording,
+                false => DebugCommand::StopReplay,
+            });
+            return;
+        }
+        _ => Some(expand_home(args.text)),
+    };
+    match path {
+        Some(path) if record => editor.debug_command = Some(DebugCommand::Record(path)),
+        Some(path) => editor.debug_command = Some(DebugCommand::Replay(path)),
+        None => editor.set_status("No state directory for the recording", Severity::Error),
+    }
+}
+
+/// Open what a path typed to `:e` or the Open prompt names after expanding
+/// it: a file, a folder listed as text, or the files a glob matches, to
+/// pick from if there are several
+pub fn open_path(editor: &mut Editor, input: &str) {
+    let current = editor.current_doc().path.clone();
+    let path = match expand::expand(input, current.as_deref()) {
+        Ok(path) => path,
+        Err(e) => {
+            editor.set_status(e, Severity::Error);
+            return;
+        }
+    };
+
+    if expand::is_glob(&path) && !Path::new(&path).exists() {
+        let mut files = expand::glob(&path);
+        match files.len() {
+            0 => editor.set_status(format!("No files match {}", path), Severity::Error),
+            1 => open_path(editor, &files.remove(0).to_string_lossy()),
+            _ => editor.glob_matches = Some((path, files)),
+        }
+    } else if Path::new(&path).is_dir() {
+        directory::open_directory(editor, Path::new(&path));
+    } else if let Err(e) = editor.open(&path) {
+        editor.set_status(format!("Error: {}", e), Severity::Error);
+    }
+}
+
+/// Expand a leading `~` to the home directory
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix('~'), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+            PathBuf::from(home).join(rest.trim_start_matches('/'))
+        }
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Compare the current buffer with the file as of the last commit, shown
+/// read-only on the left
+fn diff_head(editor: &mut Editor) {
+    let doc = editor.current_doc();
+    let Some(path) = doc.path.clone() else {
+        editor.set_status("Not a file", Severity::Error);
+        return;
+    };
+    let base = match lite_git::Repository::open(&path).map(|repo| repo.head_text(&path)) {
+        Some(Ok(base)) => base.unwrap_or_default(),
+        Some(Err(e)) => {
+            editor.set_status(e.to_string(), Severity::Error);
+            return;
+        }
+        None => {
+            editor.set_status("Not in a git repository", Severity::Error);
+            return;
+        }
+    };
+
+    let current = doc.id;
+    let name = format!("{}@HEAD", doc.name());
+    let language = doc.language.clone();
+    let head = match editor.find_virtual(&name) {
+        Some(head) => {
+            if let Some(doc) = editor.documents.get_mut(&head) {
+                doc.set_text(&base);
+            }
+            head
+        }
+        None => {
+            let mut doc = Document::new_virtual(name, &base);
+            doc.language = language;
+            editor.open_virtual(doc, None)
+        }
+    };
+    editor.diff_buffers(head, current);
+}
+
+/// An open buffer by name or path
+fn find_buffer(editor: &Editor, name: &str) -> Result<DocumentId, String> {
+    editor
+        .buffer_ids()
+        .iter()
+        .copied()
+        .find(|id| {
+            let doc = &editor.documents[id];
+            doc.name() == name || doc.path.as_deref().is_some_and(|path| path.ends_with(name))
+        })
+        .ok_or_else(|| format!("No buffer named {}", name))
+}
 
 /// Execute an action on the editor
 pub fn execute_action(editor: &mut Editor, action: &Action) {
@@ -18,10 +819,13 @@ pub fn execute_action(editor: &mut Editor, action: &Action) {
             // This should open a prompt - handled by application
         }
         Action::QuickOpen => {
-            // This should open a file picker - handled by application
+            // Opens the file picker - handled by application
+        }
+        Action::OpenRecent => {
+            // Opens the recent files picker - handled by application
         }
         Action::CloseBuffer => {
-            editor.close_buffer();
+            editor.close_buffer(false);
         }
         Action::CloseWindow => {
             editor.close_view();
@@ -48,15 +852,28 @@ pub fn execute_action(editor: &mut Editor, action: &Action) {
             // Handled by application - opens prompt
         }
         Action::GotoSymbol => {
-            // TODO: LSP integration
+            // Opens the symbol picker - handled by application
         }
         Action::JumpBack | Action::JumpForward => {
             // TODO: Jump list
         }
+        Action::NextCell => move_cell(editor, Direction::Right),
+        Action::PreviousCell => move_cell(editor, Direction::Left),
+        Action::NextSiblingKey => move_sibling_key(editor, Direction::Right),
+        Action::PreviousSiblingKey => move_sibling_key(editor, Direction::Left),
+        Action::OpenLink => open_link(editor),
+        Action::NextReference => move_reference(editor, true),
+        Action::PreviousReference => move_reference(editor, false),
 
         // Editing
         Action::InsertChar(c) => insert_char(editor, *c),
-        Action::InsertNewline => insert_newline(editor),
+        Action::FormatParagraph => format_paragraph(editor, editor.config.editor.text_width),
+        Action::Digraph => {
+            editor.digraph = Some(String::new());
+            editor.preedit = Some("?".to_string());
+        }
+        Action::InsertNewline => insert_newline(editor, true),
+        Action::InsertPlainNewline => insert_newline(editor, false),
         Action::InsertNewlineBelow => insert_newline_below(editor),
         Action::InsertNewlineAbove => insert_newline_above(editor),
         Action::Backspace => delete_backward(editor),
@@ -65,7 +882,11 @@ pub fn execute_action(editor: &mut Editor, action: &Action) {
         Action::DuplicateLine => duplicate_line(editor),
         Action::MoveLineUp => move_line(editor, Direction::Up),
         Action::MoveLineDown => move_line(editor, Direction::Down),
-        Action::Indent => indent(editor),
+        Action::Indent => {
+            if !cycle_heading(editor) {
+                indent(editor)
+            }
+        }
         Action::Unindent => unindent(editor),
         Action::ToggleComment => toggle_comment(editor),
 
@@ -74,9 +895,7 @@ pub fn execute_action(editor: &mut Editor, action: &Action) {
         Action::SelectLine => select_line(editor),
         Action::SelectWord => select_word(editor),
         Action::SelectNextOccurrence => select_next_occurrence(editor),
-        Action::SelectAllOccurrences => {
-            // TODO
-        }
+        Action::SelectAllOccurrences => select_all_occurrences(editor),
         Action::SplitSelectionLines => {
             // TODO
         }
@@ -93,61 +912,112 @@ pub fn execute_action(editor: &mut Editor, action: &Action) {
         Action::Undo => undo(editor),
         Action::Redo => redo(editor),
 
+        Action::FindNext => {
+            editor.find(true);
+        }
+        Action::FindPrevious => {
+            editor.find(false);
+        }
+        Action::UseSelectionForFind => use_selection_for_find(editor),
+        Action::NextHunk | Action::PrevHunk => {
+            if !editor.goto_hunk(matches!(action, Action::NextHunk)) {
+                editor.set_status("No more changes", Severity::Info);
+            }
+        }
+        Action::ToggleDeletedLines => {
+            let config = &mut editor.config.editor;
+            config.show_deleted_lines = !config.show_deleted_lines;
+            let shown = if config.show_deleted_lines { "shown" } else { "hidden" };
+            editor.set_status(format!("Deleted lines {}", shown), Severity::Info);
+        }
+
         // Search - handled by application
         Action::Find
-        | Action::FindNext
-        | Action::FindPrevious
         | Action::Replace
         | Action::FindInFiles
-        | Action::UseSelectionForFind => {}
+        | Action::SelectInSelection => {}
 
         // Buffer/Tab management
-        Action::NextBuffer => {
-            let buffers = editor.buffer_list();
-            let current = editor.current_view().doc_id;
-            if let Some((idx, _)) = buffers.iter().enumerate().find(|(_, (id, _))| *id == current) {
-                let next_idx = (idx + 1) % buffers.len();
-                editor.switch_to_document(buffers[next_idx].0);
-            }
-        }
-        Action::PreviousBuffer => {
-            let buffers = editor.buffer_list();
-            let current = editor.current_view().doc_id;
-            if let Some((idx, _)) = buffers.iter().enumerate().find(|(_, (id, _))| *id == current) {
-                let prev_idx = if idx == 0 { buffers.len() - 1 } else { idx - 1 };
-                editor.switch_to_document(buffers[prev_idx].0);
-            }
-        }
+        Action::NextBuffer => editor.cycle_buffer(true),
+        Action::PreviousBuffer => editor.cycle_buffer(false),
         Action::SwitchToBuffer(n) => {
             let buffers = editor.buffer_list();
             if *n > 0 && *n <= buffers.len() {
                 editor.switch_to_document(buffers[n - 1].0);
             }
         }
+        Action::SwitchRecentBuffer => editor.cycle_buffer_switcher(true),
+        Action::SwitchRecentBufferReverse => editor.cycle_buffer_switcher(false),
+        Action::BufferPicker => {
+            // Opens the buffer picker - handled by application
+        }
 
         // Splits
         Action::SplitVertical => editor.split(Layout::Vertical),
         Action::SplitHorizontal => editor.split(Layout::Horizontal),
         Action::FocusNextSplit => editor.tree.focus_next(),
         Action::FocusPreviousSplit => editor.tree.focus_prev(),
+        Action::NextPage => editor.cycle_page(true),
+        Action::PreviousPage => editor.cycle_page(false),
 
         // LSP - handled elsewhere
         Action::Autocomplete
         | Action::GotoDefinition
+        | Action::PeekDefinition
         | Action::FindReferences
         | Action::RenameSymbol
         | Action::QuickFix
         | Action::SignatureHelp
-        | Action::Hover => {}
-
-        // Code folding - TODO
-        Action::Fold | Action::Unfold => {}
+        | Action::Hover
+        | Action::DiagnosticPicker => {}
+
+        // Code folding
+        Action::Fold => fold_at_cursor(editor),
+        Action::Unfold => {
+            let view_id = editor.tree.focus();
+            let doc = editor.current_doc_mut();
+            let line = doc.rope.char_to_line(doc.selection(view_id).cursor());
+            if !doc.folds.unfold(&doc.rope, line) {
+                editor.set_status("No fold here", Severity::Warning);
+            }
+        }
 
         // UI - handled by application
-        Action::CommandPalette | Action::ToggleFileTree => {}
+        Action::CommandPalette
+        | Action::InstallUpdate
+        | Action::RevertHunk
+        | Action::StageHunk
+        | Action::UnstageHunk => {}
+        Action::ToggleFileTree => file_tree::toggle(editor),
+        Action::ToggleOutline => editor.toggle_outline(),
+        Action::ToggleTypewriter => {
+            let view = editor.current_view_mut();
+            view.typewriter = !view.typewriter;
+            let state = if view.typewriter { "on" } else { "off" };
+            editor.ensure_cursor_visible();
+            editor.set_status(format!("Typewriter scrolling {}", state), Severity::Info);
+        }
+        Action::Help => help::open_help(editor, "index"),
 
         // Prompt results - handled by application
         Action::ExecuteGotoLine(_) | Action::ExecuteSearch(_) | Action::ExecuteOpen(_) | Action::ExecuteSaveAs(_) => {}
+        Action::ExecuteCommand(input) => execute_command(editor, input),
+        Action::ExecuteCloseBuffer(save) => {
+            let doc_id = editor.current_doc().id;
+            if *save {
+                if let Err(e) = editor.save() {
+                    editor.set_status(format!("Error saving: {}", e), Severity::Error);
+                    return;
+                }
+            }
+            // Saving may have opened a buffer asking for confirmation
+            if editor.current_doc().id == doc_id {
+                editor.close_buffer(true);
+            }
+        }
+        Action::ExecuteCreateFile(name) => file_tree::create(editor, name),
+        Action::ExecuteRenameFile(name) => file_tree::rename(editor, name),
+        Action::ExecuteDeleteFile => file_tree::delete(editor),
 
         Action::Noop => {}
     }
@@ -165,15 +1035,18 @@ fn move_cursor(editor: &mut Editor, direction: Direction, count: usize) {
     let view_id = editor.tree.focus();
     let doc = editor.current_doc_mut();
     let selection = doc.selection(view_id);
+    // Folded lines are stepped over
+    let hidden = doc.folds.hidden(&doc.rope);
+    let len_lines = doc.len_lines();
+    let vertical = |line: usize, down: bool| {
+        (0..count).fold(line, |line, _| fold::step(&hidden, line, down, len_lines))
+    };
 
     let new_selection = selection.transform(|range| {
         let pos = doc.rope.char_to_position(range.head);
         let new_pos = match direction {
-            Direction::Up => lite_core::Position::new(pos.line.saturating_sub(count), pos.col),
-            Direction::Down => lite_core::Position::new(
-                (pos.line + count).min(doc.len_lines().saturating_sub(1)),
-                pos.col,
-            ),
+            Direction::Up => lite_core::Position::new(vertical(pos.line, false), pos.col),
+            Direction::Down => lite_core::Position::new(vertical(pos.line, true), pos.col),
             Direction::Left => {
                 let new_char = range.head.saturating_sub(count);
                 return Range::point(new_char);
@@ -191,14 +1064,14 @@ fn move_cursor(editor: &mut Editor, direction: Direction, count: usize) {
         Range::point(new_char)
     });
 
+    // Already against the edge of the buffer
+    let stuck = new_selection == selection;
     doc.set_selection(view_id, new_selection);
+    if stuck {
+        editor.ring_bell();
+    }
 
-    // Ensure cursor visibility
-    let cursor_pos = doc.rope.char_to_position(doc.selection(view_id).cursor());
-    let scrolloff = editor.config.editor.scrolloff;
-    editor
-        .current_view_mut()
-        .ensure_cursor_visible(cursor_pos.line, cursor_pos.col, scrolloff);
+    editor.ensure_cursor_visible();
 }
 
 fn move_word(editor: &mut Editor, direction: Direction) {
@@ -268,6 +1141,601 @@ fn move_line_end(editor: &mut Editor) {
     doc.set_selection(view_id, new_selection);
 }
 
+/// Move to the start of the next or previous table cell, across rows
+fn move_cell(editor: &mut Editor, direction: Direction) {
+    let view_id = editor.tree.focus();
+    let doc = editor.current_doc_mut();
+    let Some(delimiter) = doc.table_delimiter else {
+        return;
+    };
+    let selection = doc.selection(view_id);
+
+    let new_selection = selection.transform(|range| {
+        let pos = doc.rope.char_to_position(range.head);
+        let cells = table::cell_ranges(&table::line_text(&doc.rope, pos.line), delimiter);
+        let current = cells.iter().rposition(|cell| cell.start <= pos.col).unwrap_or(0);
+
+        let target = match direction {
+            Direction::Right if current + 1 < cells.len() => Some((pos.line, cells[current + 1].start)),
+            Direction::Right if pos.line + 1 < doc.len_lines() => Some((pos.line + 1, 0)),
+            Direction::Left if pos.col > cells[current].start => Some((pos.line, cells[current].start)),
+            Direction::Left if current > 0 => Some((pos.line, cells[current - 1].start)),
+            Direction::Left if pos.line > 0 => {
+                let line = pos.line - 1;
+                let cells = table::cell_ranges(&table::line_text(&doc.rope, line), delimiter);
+                Some((line, cells.last().map_or(0, |cell| cell.start)))
+            }
+            _ => None,
+        };
+
+        match target {
+            Some((line, col)) => Range::point(doc.rope.line_to_char(line) + col),
+            None => Range::point(range.head),
+        }
+    });
+
+    doc.set_selection(view_id, new_selection);
+    editor.ensure_cursor_visible();
+}
+
+/// Put the cursor on the next or previous file reference, going round the
+/// end of the buffer, so build output can be stepped through with Alt+Enter
+fn move_reference(editor: &mut Editor, forward: bool) {
+    let view_id = editor.tree.focus();
+    let doc = editor.current_doc();
+    let cursor = doc.selection(view_id).cursor();
+    let lines = doc.len_lines();
+    let cursor_line = doc.rope.char_to_line(cursor);
+    let references = |line: usize| {
+        let start = doc.rope.line_to_char(line);
+        link::find_links(&doc.rope.line(line).to_string())
+            .into_iter()
+            .filter(|link| matches!(link.target, link::LinkTarget::File { .. }))
+            .map(move |link| (start + link.range.start, link.target))
+    };
+
+    // The cursor's line comes last too, for the references on the other
+    // side of the cursor
+    let found = (0..=lines).find_map(|i| {
+        let line = match forward {
+            true => (cursor_line + i) % lines,
+            false => (cursor_line + lines - i) % lines,
+        };
+        let mut refs = references(line).filter(|(pos, _)| match (i, forward) {
+            (0, true) => *pos > cursor,
+            (0, false) => *pos < cursor,
+            _ => true,
+        });
+        match forward {
+            true => refs.next(),
+            false => refs.next_back(),
+        }
+    });
+    let Some((pos, link::LinkTarget::File { path, line, .. })) = found else {
+        editor.set_status("No file references in this buffer", Severity::Info);
+        return;
+    };
+
+    editor.current_doc_mut().set_selection(view_id, Selection::point(pos));
+    editor.ensure_cursor_visible();
+    editor.set_status(format!("{}:{} (Alt+Enter opens it)", path.display(), line), Severity::Info);
+}
+
+/// Open the URL under the cursor in the browser, or jump to the location
+/// of a `file:line` reference
+fn open_link(editor: &mut Editor) {
+    let view_id = editor.tree.focus();
+    let doc = editor.current_doc();
+    let pos = doc.rope.char_to_position(doc.selection(view_id).cursor());
+    let line = doc.rope.line(pos.line).to_string();
+    let Some(link) = link::link_at(&line, pos.col) else {
+        editor.set_status("No link under the cursor", Severity::Info);
+        return;
+    };
+
+    match link.target {
+        link::LinkTarget::Url(url) => match open_url(&url) {
+            Ok(()) => editor.set_status(format!("Opened {}", url), Severity::Info),
+            Err(e) => editor.set_status(format!("Cannot open {}: {}", url, e), Severity::Error),
+        },
+        link::LinkTarget::File { path, line, col } => {
+            // Relative to the working directory, else to the buffer's file
+            // or project, where tool output usually comes from
+            let doc = editor.current_doc();
+            let candidates = [
+                Some(path.clone()),
+                doc.path
+                    .as_ref()
+                    .and_then(|file| file.parent())
+                    .map(|dir| dir.join(&path)),
+                Some(editor.workspace_root(doc.id).join(&path)),
+            ];
+            let Some(path) = candidates.into_iter().flatten().find(|path| path.is_file()) else {
+                editor.set_status(format!("No such file: {}", path.display()), Severity::Error);
+                return;
+            };
+            if let Err(e) = editor.open(path) {
+                editor.set_status(format!("Error: {}", e), Severity::Error);
+                return;
+            }
+
+            let doc = editor.current_doc_mut();
+            let line = (line.max(1) - 1).min(doc.len_lines().saturating_sub(1));
+            let col = col.unwrap_or(1).max(1) - 1;
+            let pos = doc.rope.line_to_char(line) + col.min(doc.rope.line_len_chars(line));
+            doc.set_selection(view_id, Selection::point(pos));
+            editor.ensure_cursor_visible();
+        }
+    }
+}
+
+/// Launch the system's handler for a URL without waiting for it
+fn open_url(url: &str) -> std::io::Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        std::process::Command::new("open")
+    } else if cfg!(windows) {
+        // Not `cmd /C start`, which would run what follows a `&` in the URL
+        let mut command = std::process::Command::new("rundll32");
+        command.arg("url.dll,FileProtocolHandler");
+        command
+    } else {
+        std::process::Command::new("xdg-open")
+    };
+    command
+        .arg(url)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()?;
+    Ok(())
+}
+
+/// Move to the next or previous key at the same level in a data file
+fn move_sibling_key(editor: &mut Editor, direction: Direction) {
+    let view_id = editor.tree.focus();
+    let doc = editor.current_doc_mut();
+    let Some(language) = doc.language.clone() else {
+        return;
+    };
+    let source = doc.text();
+    let cursor = doc.selection(view_id).cursor();
+    let offset = doc.rope.char_to_byte(cursor);
+
+    let forward = matches!(direction, Direction::Right);
+    if let Some(byte) = structure::sibling_key(&language, &source, offset, forward) {
+        let pos = doc.rope.byte_to_char(byte);
+        doc.set_selection(view_id, Selection::point(pos));
+        editor.ensure_cursor_visible();
+    }
+}
+
+/// Reformat the JSON selection, or the whole buffer, indented with `indent`
+/// or minified
+fn format_json(editor: &mut Editor, indent: Option<&str>) {
+    let view_id = editor.tree.focus();
+    let doc = editor.current_doc_mut();
+    let primary = *doc.selection(view_id).primary();
+    let (start, end) = if primary.is_point() {
+        (0, doc.len_chars())
+    } else {
+        (primary.start(), primary.end())
+    };
+    let source = doc.rope.slice(start..end).to_string();
+
+    match structure::format_json(&source, indent) {
+        Ok(formatted) => {
+            // Keep a selection's formatting inline with its surroundings
+            let formatted = if primary.is_point() {
+                formatted
+            } else {
+                formatted.trim_end().to_string()
+            };
+            let len = formatted.chars().count();
+            replace_text(doc, view_id, start, end, &formatted);
+            let selection = if primary.is_point() {
+                Selection::point(primary.head.min(doc.len_chars()))
+            } else {
+                Selection::single(Range::new(start, start + len))
+            };
+            doc.set_selection(view_id, selection);
+            doc.diagnostics.clear();
+            editor.ensure_cursor_visible();
+        }
+        Err(errors) => {
+            let start_byte = doc.rope.char_to_byte(start);
+            let errors = errors
+                .into_iter()
+                .map(|mut error| {
+                    error.range = start_byte + error.range.start..start_byte + error.range.end;
+                    error
+                })
+                .collect();
+            report_syntax_errors(editor, errors, "json");
+        }
+    }
+}
+
+/// Split `/pattern/replacement/flags` at the delimiter it starts with,
+/// which a backslash escapes
+pub fn parse_substitute(args: &str) -> Option<(String, String, String)> {
+    let mut chars = args.chars();
+    let delimiter = chars.next().filter(|c| !c.is_alphanumeric() && *c != '\\')?;
+    let mut parts = vec![String::new()];
+    while let Some(c) = chars.next() {
+        let part = parts.last_mut().expect("there is always a part");
+        match c {
+            '\\' => match chars.next() {
+                Some(next) if next == delimiter => part.push(next),
+                Some(next) => {
+                    part.push('\\');
+                    part.push(next);
+                }
+                None => part.push('\\'),
+            },
+            _ if c == delimiter => parts.push(String::new()),
+            _ => part.push(c),
+        }
+    }
+    // The closing delimiter is optional without flags
+    if parts.len() == 2 {
+        parts.push(String::new());
+    }
+    let [pattern, replacement, flags] = <[String; 3]>::try_from(parts).ok()?;
+    Some((pattern, replacement, flags))
+}
+
+/// Compile a search pattern; `^` and `$` match at every line, and the `i`
+/// flag ignores case. Flags not in `allowed` are refused.
+pub fn build_regex(pattern: &str, flags: &str, allowed: &str) -> Result<regex::Regex, String> {
+    if let Some(flag) = flags.chars().find(|c| !allowed.contains(*c)) {
+        return Err(format!("Unknown flag: {}", flag));
+    }
+    regex::RegexBuilder::new(pattern)
+        .multi_line(true)
+        .crlf(true)
+        .case_insensitive(flags.contains('i'))
+        .build()
+        .map_err(|e| format!("Invalid regex: {}", e))
+}
+
+/// Replace the matches of a regex in the selections, or the whole buffer
+/// without one, or the lines of a range, as one edit. The `s` flag asks for
+/// a selection. `$1` and `${name}` in the replacement are the groups the
+/// match captured. Every match is replaced, so `g` changes nothing; it's
+/// taken for `:%s/a/b/g`.
+fn substitute(editor: &mut Editor, args: &str, lines: Option<std::ops::Range<usize>>) {
+    let Some((pattern, replacement, flags)) = parse_substitute(args) else {
+        return editor.set_status("Usage: s/regex/replacement/[gis]", Severity::Error);
+    };
+    let regex = match build_regex(&pattern, &flags, "gis") {
+        Ok(regex) => regex,
+        Err(e) => return editor.set_status(e, Severity::Error),
+    };
+    if lines.is_some() && flags.contains('s') {
+        return editor.set_status("Give a range or the s flag, not both", Severity::Error);
+    }
+
+    let view_id = editor.tree.focus();
+    let doc = editor.current_doc_mut();
+    let selected: Vec<(usize, usize)> = doc
+        .selection(view_id)
+        .ranges()
+        .iter()
+        .filter(|range| !range.is_empty())
+        .map(|range| (range.start(), range.end()))
+        .collect();
+    let mut scopes = match (flags.contains('s'), lines) {
+        (false, Some(lines)) => vec![line_chars(&doc.rope, lines)],
+        (false, None) if selected.is_empty() => vec![(0, doc.len_chars())],
+        _ => selected,
+    };
+    if scopes.is_empty() {
+        return editor.set_status("Nothing selected to replace in", Severity::Error);
+    }
+    scopes.sort_unstable();
+
+    let mut changes = Vec::new();
+    for (start, end) in scopes {
+        let text = doc.rope.slice(start..end).to_string();
+        // Match offsets are bytes, the changes' chars
+        let (mut byte, mut char) = (0, start);
+        for captures in regex.captures_iter(&text) {
+            let found = captures.get(0).expect("group 0 is the whole match");
+            // After the last newline is the next line, or no line at all
+            if found.is_empty() && found.start() == text.len() && text.ends_with('\n') {
+                break;
+            }
+            char += text[byte..found.start()].chars().count();
+            let match_start = char;
+            char += found.as_str().chars().count();
+            byte = found.end();
+            let mut replaced = String::new();
+            captures.expand(&replacement, &mut replaced);
+            changes.push(Change::replace(match_start, char, replaced));
+        }
+    }
+    if changes.is_empty() {
+        editor.set_status("Not found", Severity::Error);
+        return editor.ring_bell();
+    }
+    if !doc.apply(&Transaction::changes(doc.len_chars(), &changes), view_id) {
+        return editor.set_status("The buffer is read-only", Severity::Error);
+    }
+    editor.ensure_cursor_visible();
+    let s = if changes.len() == 1 { "" } else { "es" };
+    editor.set_status(format!("Replaced {} match{}", changes.len(), s), Severity::Info);
+}
+
+/// The number under the cursor: where it starts and ends, and its value
+fn number_at_cursor(editor: &Editor) -> Option<(usize, usize, i128)> {
+    let doc = editor.current_doc();
+    let cursor = doc.selection(editor.tree.focus()).cursor();
+    let line = doc.rope.char_to_line(cursor);
+    let line_start = doc.rope.line_to_char(line);
+    let text = wrap::line_text(&doc.rope, line);
+    let (start, end) = number::find(&text, cursor - line_start)?;
+    let word: String = text.chars().skip(start).take(end - start).collect();
+    let (value, _) = number::parse(&word)?;
+    Some((line_start + start, line_start + end, value))
+}
+
+/// Rewrite the number under the cursor in another base, or as a date
+fn convert_number(editor: &mut Editor, to: &str) {
+    let Some((start, end, value)) = number_at_cursor(editor) else {
+        return editor.set_status("No number under the cursor", Severity::Error);
+    };
+    let text = match (to, number::Base::from_name(to)) {
+        (_, Some(base)) => number::format(value, base),
+        ("date", None) => match number::date(value) {
+            Some(date) => date,
+            None => return editor.set_status(format!("{} is not a unix timestamp", value), Severity::Error),
+        },
+        _ => return editor.set_status("Usage: convert <dec|hex|oct|bin|date>", Severity::Error),
+    };
+    let view_id = editor.tree.focus();
+    replace_text(editor.current_doc_mut(), view_id, start, end, &text);
+    editor.ensure_cursor_visible();
+}
+
+/// Evaluate an expression, or the selection without one. The result is
+/// shown, or inserted over the selection.
+fn calculate(editor: &mut Editor, expr: &str, insert: bool) {
+    let view_id = editor.tree.focus();
+    let doc = editor.current_doc();
+    let range = *doc.selection(view_id).primary();
+    let expr = match expr {
+        "" => doc.rope.slice(range.start()..range.end()).to_string(),
+        _ => expr.to_string(),
+    };
+    let value = match calc::evaluate(&expr) {
+        Ok(value) => value,
+        Err(e) => return editor.set_status(e, Severity::Error),
+    };
+    let result = calc::format(value);
+    if insert {
+        let doc = editor.current_doc_mut();
+        replace_text(doc, view_id, range.start(), range.end(), &result);
+        editor.ensure_cursor_visible();
+    } else if value.fract() == 0.0 && (0.0..1e15).contains(&value) {
+        // Whole numbers in hex too, for offsets
+        editor.set_status(format!("= {} ({:#x})", result, value as i64), Severity::Info);
+    } else {
+        editor.set_status(format!("= {}", result), Severity::Info);
+    }
+}
+
+/// Check the syntax of the current data file and show problems as diagnostics
+fn validate(editor: &mut Editor) {
+    let doc = editor.current_doc();
+    let Some(language) = doc.language.clone().filter(|l| structure::can_validate(l)) else {
+        editor.set_status("Only JSON, YAML and TOML can be validated", Severity::Warning);
+        return;
+    };
+    let errors = structure::validate(&language, &doc.text());
+    report_syntax_errors(editor, errors, &language);
+}
+
+/// Replace the current document's diagnostics with syntax errors and
+/// summarize them in the status line
+fn report_syntax_errors(editor: &mut Editor, errors: Vec<structure::SyntaxError>, source: &str) {
+    let doc = editor.current_doc_mut();
+    let len_bytes = doc.rope.len_bytes();
+    doc.diagnostics = errors
+        .iter()
+        .map(|error| {
+            let start = doc.rope.byte_to_char(error.range.start.min(len_bytes));
+            let end = doc.rope.byte_to_char(error.range.end.min(len_bytes));
+            Diagnostic::new(start..end, DiagnosticSeverity::Error, &error.message)
+                .with_source(source)
+        })
+        .collect();
+
+    match doc.diagnostics.first() {
+        None => editor.set_status("No syntax errors", Severity::Info),
+        Some(first) => {
+            let pos = doc.rope.char_to_position(first.range.start);
+            let message = format!(
+                "{} syntax error(s), first at {}:{}: {}",
+                doc.diagnostics.len(),
+                pos.line + 1,
+                pos.col + 1,
+                first.message
+            );
+            editor.set_status(message, Severity::Error);
+        }
+    }
+}
+
+/// Switch the current document between aligned columns and plain text
+fn toggle_table(editor: &mut Editor) {
+    let doc = editor.current_doc_mut();
+    doc.table_delimiter = match doc.table_delimiter {
+        Some(_) => None,
+        None => match doc.language.as_deref().and_then(table::delimiter_for) {
+            Some(delimiter) => Some(delimiter),
+            None => {
+                editor.set_status("Not a CSV or TSV file", Severity::Warning);
+                return;
+            }
+        },
+    };
+    editor.ensure_cursor_visible();
+}
+
+/// The edit turning a line into `new`, changing only what differs so
+/// cursors in the rest of the line stay put
+fn line_change(doc: &Document, line: usize, new: &str) -> Option<Change> {
+    let old = wrap::line_text(&doc.rope, line);
+    if old == new {
+        return None;
+    }
+    let (old, new): (Vec<char>, Vec<char>) = (old.chars().collect(), new.chars().collect());
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let max_suffix = old.len().min(new.len()) - prefix;
+    let suffix = old.iter().rev().zip(new.iter().rev()).take(max_suffix).take_while(|(a, b)| a == b).count();
+    let start = doc.rope.line_to_char(line);
+    let inserted: String = new[prefix..new.len() - suffix].iter().collect();
+    Some(Change::replace(start + prefix, start + old.len() - suffix, inserted))
+}
+
+/// Rewrite every line the selections touch
+fn edit_lines(editor: &mut Editor, edit: impl Fn(&str) -> String) {
+    let view_id = editor.tree.focus();
+    let doc = editor.current_doc_mut();
+    let mut lines = BTreeSet::new();
+    for range in doc.selection(view_id).ranges() {
+        let first = doc.rope.char_to_line(range.start());
+        let mut last = doc.rope.char_to_line(range.end());
+        // A selection ending at the start of a line doesn't include that line
+        if last > first && doc.rope.line_to_char(last) == range.end() {
+            last -= 1;
+        }
+        lines.extend(first..=last);
+    }
+    let changes: Vec<Change> = lines
+        .into_iter()
+        .filter_map(|line| line_change(doc, line, &edit(&wrap::line_text(&doc.rope, line))))
+        .collect();
+    doc.apply(&Transaction::changes(doc.len_chars(), &changes), view_id);
+}
+
+/// Wrap each selection in a Markdown marker, or unwrap it. Cursors get an
+/// empty pair to type into.
+fn toggle_emphasis(editor: &mut Editor, marker: &str) {
+    let view_id = editor.tree.focus();
+    let doc = editor.current_doc_mut();
+    let selection = doc.selection(view_id);
+    let mut changes = Vec::new();
+    let mut ranges = Vec::new();
+    // Ranges are sorted, so each moves by what the edits before it added
+    let mut shift = 0isize;
+    for range in selection.ranges() {
+        let text = doc.rope.slice(range.start()..range.end()).to_string();
+        let toggled = markdown::toggle_emphasis(&text, marker);
+        let len = toggled.chars().count();
+        let start = range.start().saturating_add_signed(shift);
+        ranges.push(match range.is_empty() {
+            true => Range::point(start + marker.len()),
+            false if range.anchor > range.head => Range::new(start + len, start),
+            false => Range::new(start, start + len),
+        });
+        shift += len as isize - range.len() as isize;
+        changes.push(Change::replace(range.start(), range.end(), toggled));
+    }
+    let tx = Transaction::changes(doc.len_chars(), &changes)
+        .with_selection(Selection::new(ranges.into_iter().collect(), selection.primary_idx()));
+    doc.apply(&tx, view_id);
+}
+
+/// Line up the Markdown table around the cursor: the lines with pipes
+/// next to it
+fn format_markdown_table(editor: &mut Editor) {
+    let view_id = editor.tree.focus();
+    let doc = editor.current_doc_mut();
+    let line = doc.rope.char_to_line(doc.selection(view_id).cursor());
+    let is_row = |line: usize| markdown::is_table_row(&wrap::line_text(&doc.rope, line));
+    if !is_row(line) {
+        return editor.set_status("No table under the cursor", Severity::Error);
+    }
+    let first = (0..line).rev().take_while(|line| is_row(*line)).last().unwrap_or(line);
+    let last = (line + 1..doc.rope.len_lines()).take_while(|line| is_row(*line)).last().unwrap_or(line);
+    let rows: Vec<String> = (first..=last).map(|line| wrap::line_text(&doc.rope, line)).collect();
+    let Some(formatted) = markdown::format_table(&rows) else {
+        return editor.set_status("Not a table: the second row isn't |---|", Severity::Error);
+    };
+    let changes: Vec<Change> = formatted
+        .iter()
+        .enumerate()
+        .filter_map(|(i, row)| line_change(doc, first + i, row))
+        .collect();
+    doc.apply(&Transaction::changes(doc.len_chars(), &changes), view_id);
+}
+
+/// Fold the innermost section around the cursor not folded yet, so
+/// folding again folds the one around it
+fn fold_at_cursor(editor: &mut Editor) {
+    let view_id = editor.tree.focus();
+    let doc = editor.current_doc_mut();
+    let line = doc.rope.char_to_line(doc.selection(view_id).cursor());
+    let ranges = fold::ranges(&doc.rope, doc.language.as_deref());
+    let Some(range) = ranges
+        .iter()
+        .rev()
+        .find(|range| range.lines.contains(&line) && !doc.folds.is_folded(&doc.rope, range.lines.start))
+    else {
+        return editor.set_status("Nothing to fold here", Severity::Warning);
+    };
+    doc.folds.fold(&doc.rope, range.lines.clone());
+    leave_folds(doc, view_id);
+}
+
+/// Fold every section of a level or deeper
+fn fold_level(editor: &mut Editor, level: usize) {
+    let view_id = editor.tree.focus();
+    let doc = editor.current_doc_mut();
+    for range in fold::ranges(&doc.rope, doc.language.as_deref()) {
+        if range.level >= level {
+            doc.folds.fold(&doc.rope, range.lines);
+        }
+    }
+    leave_folds(doc, view_id);
+}
+
+/// Move a cursor that a fold hides to the line the fold is under
+fn leave_folds(doc: &mut Document, view_id: ViewId) {
+    let hidden = doc.folds.hidden(&doc.rope);
+    let mut line = doc.rope.char_to_line(doc.selection(view_id).cursor());
+    if fold::is_hidden(&hidden, line) {
+        while fold::is_hidden(&hidden, line) {
+            line -= 1;
+        }
+        doc.set_selection(view_id, Selection::point(doc.rope.line_to_char(line)));
+    }
+}
+
+/// Cycle the section of the Markdown heading under the cursor as Org mode
+/// does with Tab: folded, its subsections folded, all shown. Returns
+/// whether the cursor was on a heading.
+fn cycle_heading(editor: &mut Editor) -> bool {
+    let view_id = editor.tree.focus();
+    let doc = editor.current_doc_mut();
+    let selection = doc.selection(view_id);
+    let line = doc.rope.char_to_line(selection.cursor());
+    let heading = markdown::heading_level(&wrap::line_text(&doc.rope, line)).is_some();
+    if doc.language.as_deref() != Some("markdown") || selection.has_selection() || !heading {
+        return false;
+    }
+    let ranges = fold::ranges(&doc.rope, doc.language.as_deref());
+    let status = match doc.folds.cycle(&doc.rope, &ranges, line) {
+        Some(fold::Cycle::Folded) => "Folded",
+        Some(fold::Cycle::Children) => "Showing subsections",
+        Some(fold::Cycle::Subtree) => "Showing everything",
+        None => "Nothing under this heading",
+    };
+    editor.set_status(status, Severity::Info);
+    true
+}
+
 fn move_file_start(editor: &mut Editor) {
     let view_id = editor.tree.focus();
     let doc = editor.current_doc_mut();
@@ -287,6 +1755,12 @@ fn page_move(editor: &mut Editor, direction: Direction) {
 }
 
 fn insert_char(editor: &mut Editor, c: char) {
+    let mut buf = [0; 4];
+    insert_text(editor, c.encode_utf8(&mut buf));
+}
+
+/// Insert typed text at the cursor as a single edit, expanding tabs if indenting with spaces
+pub fn insert_text(editor: &mut Editor, text: &str) {
     let view_id = editor.tree.focus();
     let indent_style = editor.config.editor.indent_style;
     let tab_width = editor.config.editor.tab_width;
@@ -295,33 +1769,111 @@ fn insert_char(editor: &mut Editor, c: char) {
     let selection = doc.selection(view_id);
     let cursor = selection.cursor();
 
-    let text = if c == '\t' && indent_style == lite_config::IndentStyle::Spaces {
-        " ".repeat(tab_width)
+    let text = if indent_style == lite_config::IndentStyle::Spaces {
+        text.replace('\t', &" ".repeat(tab_width))
     } else {
-        c.to_string()
+        text.to_string()
     };
 
-    let tx =
-        Transaction::insert(doc.len_chars(), cursor, text.clone()).with_selection(Selection::point(
-            cursor + text.chars().count(),
-        ));
-
-    doc.apply(&tx, view_id);
+    replace_text(doc, view_id, cursor, cursor, &text);
 }
 
-fn insert_newline(editor: &mut Editor) {
+/// Break the line at the cursor, keeping its indentation. After an opening
+/// bracket the new line is indented one level deeper, and a closing bracket
+/// right after the cursor moves to a line of its own at the original indent.
+/// In a line comment the new line starts with the same comment prefix if
+/// `continue_comment` is set and comments are continued by configuration,
+/// and so do Markdown list items and quotes. Enter on an empty item ends
+/// the list, clearing its marker.
+fn insert_newline(editor: &mut Editor, continue_comment: bool) {
     let view_id = editor.tree.focus();
+    let unit = indent_unit(editor);
+    let continue_comment = continue_comment && editor.config.editor.continue_comments;
     let doc = editor.current_doc_mut();
     let selection = doc.selection(view_id);
     let cursor = selection.cursor();
 
-    let line_ending = doc.line_ending.as_str();
-    let tx = Transaction::insert(doc.len_chars(), cursor, line_ending)
-        .with_selection(Selection::point(cursor + line_ending.len()));
+    let line = doc.rope.char_to_line(cursor);
+    let line_start = doc.rope.line_to_char(line);
+    let line_end = line_start + doc.rope.line_len_chars(line);
+    let before: String = doc.rope.slice(line_start..cursor).chars().collect();
+    let after: String = doc.rope.slice(cursor..line_end).chars().collect();
+
+    let comment = doc
+        .language
+        .as_deref()
+        .and_then(comment::line_comment)
+        .filter(|_| continue_comment)
+        .and_then(|token| comment::line_prefix(&before, token));
+    let item = Some(&before)
+        .filter(|_| continue_comment && doc.language.as_deref() == Some("markdown"))
+        .and_then(|before| markdown::continuation(before));
+    let comment = match &item {
+        Some((_, true)) if after.trim().is_empty() => {
+            let tx = Transaction::delete(doc.len_chars(), line_start, cursor)
+                .with_selection(Selection::point(line_start));
+            doc.apply(&tx, view_id);
+            return;
+        }
+        Some((marker, _)) => Some(marker.as_str()),
+        None => comment,
+    };
 
+    let line_ending = doc.line_ending.as_str();
+    let (text, skipped, cursor_offset) = newline_text(&before, &after, comment, &unit, line_ending);
+    let tx = Transaction::replace(doc.len_chars(), cursor, cursor + skipped, text)
+        .with_selection(Selection::point(cursor + cursor_offset));
     doc.apply(&tx, view_id);
 }
 
+/// What Enter inserts between the text before and after the cursor on its
+/// line, `comment` being the prefix to continue: the text, how many chars
+/// after the cursor it replaces (blanks before a closing bracket) and where
+/// the cursor goes, in chars from the start of the inserted text
+fn newline_text(
+    before: &str,
+    after: &str,
+    comment: Option<&str>,
+    unit: &str,
+    line_ending: &str,
+) -> (String, usize, usize) {
+    let indent: String = before.chars().take_while(|c| *c == ' ' || *c == '\t').collect();
+    // Brackets in comments are prose, not code to indent
+    if let Some(prefix) = comment {
+        let text = format!("{}{}{}", line_ending, indent, prefix);
+        let cursor = text.chars().count();
+        return (text, 0, cursor);
+    }
+    let opener = before.trim_end().chars().next_back();
+    let trimmed = after.trim_start_matches([' ', '\t']);
+    let closer = trimmed.chars().next();
+
+    let pair = |open: char| match open {
+        '{' => Some('}'),
+        '[' => Some(']'),
+        '(' => Some(')'),
+        _ => None,
+    };
+    match opener.and_then(pair) {
+        Some(close) if closer == Some(close) => {
+            let inner = format!("{}{}{}", line_ending, indent, unit);
+            let text = format!("{}{}{}", inner, line_ending, indent);
+            let cursor = inner.chars().count();
+            (text, after.chars().count() - trimmed.chars().count(), cursor)
+        }
+        Some(_) => {
+            let text = format!("{}{}{}", line_ending, indent, unit);
+            let cursor = text.chars().count();
+            (text, 0, cursor)
+        }
+        None => {
+            let text = format!("{}{}", line_ending, indent);
+            let cursor = text.chars().count();
+            (text, 0, cursor)
+        }
+    }
+}
+
 fn insert_newline_below(editor: &mut Editor) {
     let view_id = editor.tree.focus();
     let doc = editor.current_doc_mut();
@@ -331,11 +1883,7 @@ fn insert_newline_below(editor: &mut Editor) {
     let line = doc.rope.char_to_line(cursor);
     let line_end = doc.rope.line_to_char(line) + doc.rope.line_len_chars(line);
     let line_ending = doc.line_ending.as_str();
-
-    let tx = Transaction::insert(doc.len_chars(), line_end, line_ending)
-        .with_selection(Selection::point(line_end + line_ending.len()));
-
-    doc.apply(&tx, view_id);
+    replace_text(doc, view_id, line_end, line_end, line_ending);
 }
 
 fn insert_newline_above(editor: &mut Editor) {
@@ -431,11 +1979,7 @@ fn duplicate_line(editor: &mut Editor) {
 
     // Insert at end of line
     let insert_pos = line_start + doc.rope.line_len_chars(line);
-    let new_text = if line_text.ends_with('\n') {
-        line_text
-    } else {
-        format!("{}\n", line_text.trim_end_matches('\n'))
-    };
+    let new_text = format!("{}{}", doc.line_ending.as_str(), line_text.trim_end_matches(['\n', '\r']));
 
     let tx = Transaction::insert(doc.len_chars(), insert_pos, new_text);
     doc.apply(&tx, view_id);
@@ -492,12 +2036,16 @@ fn move_line(editor: &mut Editor, direction: Direction) {
     }
 }
 
+/// One level of indentation as configured
+fn indent_unit(editor: &Editor) -> String {
+    match editor.config.editor.indent_style {
+        lite_config::IndentStyle::Spaces => " ".repeat(editor.config.editor.tab_width),
+        lite_config::IndentStyle::Tabs => "\t".to_string(),
+    }
+}
+
 fn indent(editor: &mut Editor) {
-    let indent_str = if editor.config.editor.indent_style == lite_config::IndentStyle::Spaces {
-        " ".repeat(editor.config.editor.tab_width)
-    } else {
-        "\t".to_string()
-    };
+    let indent_str = indent_unit(editor);
 
     let view_id = editor.tree.focus();
     let doc = editor.current_doc_mut();
@@ -536,6 +2084,86 @@ fn unindent(editor: &mut Editor) {
     }
 }
 
+/// Where a range of lines starts and ends, newlines included
+fn line_chars(rope: &lite_core::Rope, lines: std::ops::Range<usize>) -> (usize, usize) {
+    let end = match lines.end < rope.len_lines() {
+        true => rope.line_to_char(lines.end),
+        false => rope.len_chars(),
+    };
+    (rope.line_to_char(lines.start), end)
+}
+
+/// Delete the lines of the range, or the cursor's line, as one edit
+fn delete_lines(editor: &mut Editor, args: &Args) {
+    if !args.is_empty() {
+        return editor.set_status("Usage: [range]d", Severity::Error);
+    }
+    let lines = args.lines(editor);
+    let count = lines.len();
+    let view_id = editor.tree.focus();
+    let doc = editor.current_doc_mut();
+    let (mut start, end) = line_chars(&doc.rope, lines);
+    // The last line has no newline to take, so the one before it goes
+    if end == doc.len_chars() && start > 0 && doc.rope.char(end - 1) != '\n' {
+        start -= 1;
+        if start > 0 && doc.rope.char(start - 1) == '\r' {
+            start -= 1;
+        }
+    }
+    if start < end {
+        let tx = Transaction::delete(doc.len_chars(), start, end).with_selection(Selection::point(start));
+        if !doc.apply(&tx, view_id) {
+            return editor.set_status("The buffer is read-only", Severity::Error);
+        }
+    }
+    editor.ensure_cursor_visible();
+    let s = if count == 1 { "" } else { "s" };
+    editor.set_status(format!("Deleted {} line{}", count, s), Severity::Info);
+}
+
+/// Indent or unindent the lines of the range, or the cursor's line, as one
+/// edit: a level for the command's `>` or `<` and one for each repeated
+/// after it. Blank lines aren't indented.
+fn shift_lines(editor: &mut Editor, args: &Args, indent: bool) {
+    let shift = if indent { '>' } else { '<' };
+    if args.text.contains(|c| c != shift) {
+        return editor.set_status(format!("Usage: [range]{}", shift), Severity::Error);
+    }
+    let levels = args.text.len() + 1;
+    let unit = indent_unit(editor).repeat(levels);
+    let tab_width = editor.config.editor.tab_width;
+    let lines = args.lines(editor);
+    let view_id = editor.tree.focus();
+    let doc = editor.current_doc_mut();
+
+    let mut changes = Vec::new();
+    for line in lines {
+        let start = doc.rope.line_to_char(line);
+        let text: String = doc.rope.line(line).chars().collect();
+        if indent {
+            if !text.trim().is_empty() {
+                changes.push(Change::insert(start, unit.clone()));
+            }
+            continue;
+        }
+        // A tab, or up to a tab's width of spaces, per level
+        let mut removed = 0;
+        for _ in 0..levels {
+            let rest = &text[removed..];
+            removed += match rest.starts_with('\t') {
+                true => 1,
+                false => rest.chars().take_while(|c| *c == ' ').count().min(tab_width),
+            };
+        }
+        if removed > 0 {
+            changes.push(Change::delete(start, start + removed));
+        }
+    }
+    if !changes.is_empty() {
+        doc.apply(&Transaction::changes(doc.len_chars(), &changes), view_id);
+    }
+}
+
 fn toggle_comment(editor: &mut Editor) {
     // Simple // comment for now
     let comment_prefix = "// ";
@@ -550,18 +2178,17 @@ fn toggle_comment(editor: &mut Editor) {
     let line_text: String = doc.rope.line(line).chars().collect();
     let trimmed = line_text.trim_start();
 
+    let whitespace_len = line_text.chars().count() - trimmed.chars().count();
     if trimmed.starts_with(comment_prefix) {
         // Remove comment
-        let whitespace_len = line_text.len() - trimmed.len();
         let tx = Transaction::delete(
             doc.len_chars(),
             line_start + whitespace_len,
-            line_start + whitespace_len + comment_prefix.len(),
+            line_start + whitespace_len + comment_prefix.chars().count(),
         );
         doc.apply(&tx, view_id);
     } else {
         // Add comment
-        let whitespace_len = line_text.len() - trimmed.len();
         let tx = Transaction::insert(
             doc.len_chars(),
             line_start + whitespace_len,
@@ -571,6 +2198,45 @@ fn toggle_comment(editor: &mut Editor) {
     }
 }
 
+/// Rewrap the selected lines, or the paragraph under the cursor, at `width` columns
+fn format_paragraph(editor: &mut Editor, width: usize) {
+    if width == 0 {
+        editor.set_status("Set text_width to hard wrap", Severity::Warning);
+        return;
+    }
+
+    let view_id = editor.tree.focus();
+    let doc = editor.current_doc_mut();
+    let primary = *doc.selection(view_id).primary();
+    let markdown = doc.language.as_deref() == Some("markdown");
+
+    let (first, last) = if primary.is_point() {
+        match wrap::paragraph_at(&doc.rope, doc.rope.char_to_line(primary.head), markdown) {
+            Some(bounds) => bounds,
+            None => return,
+        }
+    } else {
+        // A selection ending at the start of a line doesn't include that line
+        let last = doc.rope.char_to_line(primary.end());
+        let last = if last > doc.rope.char_to_line(primary.start())
+            && doc.rope.line_to_char(last) == primary.end()
+        {
+            last - 1
+        } else {
+            last
+        };
+        (doc.rope.char_to_line(primary.start()), last)
+    };
+
+    let lines: Vec<String> = (first..=last).map(|line| wrap::line_text(&doc.rope, line)).collect();
+    let wrapped = wrap::hard_wrap(&lines, width, markdown).join(doc.line_ending.as_str());
+
+    let start = doc.rope.line_to_char(first);
+    let end = doc.rope.line_to_char(last) + doc.rope.line_len_chars(last);
+    replace_text(doc, view_id, start, end, &wrapped);
+    editor.ensure_cursor_visible();
+}
+
 fn select_all(editor: &mut Editor) {
     let view_id = editor.tree.focus();
     let doc = editor.current_doc_mut();
@@ -620,16 +2286,97 @@ fn select_next_occurrence(editor: &mut Editor) {
     }
 
     // Find next occurrence after the primary selection
-    let text: String = doc.rope.chars().collect();
-    let search_start = primary.end();
-
-    if let Some(pos) = text[search_start..].find(&search_text) {
-        let abs_pos = search_start + pos;
-        selection.add_range(Range::new(abs_pos, abs_pos + search_text.len()));
+    if let Some(pos) = doc.rope.find_str(&search_text, primary.end()) {
+        selection.add_range(Range::new(pos, pos + search_text.chars().count()));
         doc.set_selection(view_id, selection);
     }
 }
 
+/// Make the selected text, or the word under the cursor, what F3 and
+/// Shift+F3 find, without the search prompt. Up in the prompt recalls it.
+fn use_selection_for_find(editor: &mut Editor) {
+    let view_id = editor.tree.focus();
+    let doc = editor.current_doc();
+    let primary = *doc.selection(view_id).primary();
+    let (start, end) = match primary.is_point() {
+        true => doc.rope.word_at(primary.head),
+        false => (primary.start(), primary.end()),
+    };
+    let text: String = doc.rope.slice(start..end).chars().collect();
+    if text.is_empty() {
+        editor.set_status("Nothing to find: select text or put the cursor on a word", Severity::Info);
+        return;
+    }
+
+    editor.search_query = text.clone();
+    editor.remember_input("search", &text);
+    let count = editor.search_matches().len();
+    let matches = if count == 1 { "match" } else { "matches" };
+    editor.set_status(format!("Find {}: {} {}", text.escape_debug(), count, matches), Severity::Info);
+}
+
+/// Select every occurrence of the word under the cursor or of the selected
+/// text, the one already there staying primary
+fn select_all_occurrences(editor: &mut Editor) {
+    let view_id = editor.tree.focus();
+    let doc = editor.current_doc_mut();
+    let primary = *doc.selection(view_id).primary();
+    let (start, end) = match primary.is_point() {
+        true => doc.rope.word_at(primary.head),
+        false => (primary.start(), primary.end()),
+    };
+    let text: String = doc.rope.slice(start..end).chars().collect();
+    if text.is_empty() {
+        return;
+    }
+
+    let mut ranges = Vec::new();
+    let mut from = 0;
+    while let Some(pos) = doc.rope.find_str(&text, from) {
+        ranges.push(Range::new(pos, pos + (end - start)));
+        from = pos + (end - start);
+    }
+    let primary_idx = ranges.iter().position(|range| range.start() == start).unwrap_or(0);
+    let count = ranges.len();
+    doc.set_selection(view_id, Selection::new(ranges.into_iter().collect(), primary_idx));
+    editor.set_status(format!("{} occurrences selected", count), Severity::Info);
+}
+
+/// Put a cursor on each match of `query` inside the selected text, found
+/// the way the search finds it, for changes limited to part of a buffer.
+/// Without a selection the whole buffer is searched.
+fn select_in_selection(editor: &mut Editor, query: &str) {
+    if query.is_empty() {
+        editor.set_status("Usage: select-in <text>", Severity::Error);
+        return;
+    }
+    let view_id = editor.tree.focus();
+    let selection = editor.current_doc().selection(view_id);
+    let scopes: Vec<Range> = selection.ranges().iter().filter(|range| !range.is_empty()).copied().collect();
+
+    // The query is the search's now, so F3 goes on through its matches
+    editor.search_query = query.to_string();
+    let len = query.chars().count();
+    let ranges: Vec<Range> = editor
+        .search_matches()
+        .into_iter()
+        .map(|start| Range::new(start, start + len))
+        .filter(|found| {
+            scopes.is_empty() || scopes.iter().any(|scope| scope.start() <= found.start() && found.end() <= scope.end())
+        })
+        .collect();
+    if ranges.is_empty() {
+        let place = if scopes.is_empty() { "the buffer" } else { "the selection" };
+        editor.set_status(format!("No {} in {}", query, place), Severity::Error);
+        return;
+    }
+
+    let count = ranges.len();
+    editor.current_doc_mut().set_selection(view_id, Selection::new(ranges.into_iter().collect(), 0));
+    editor.ensure_cursor_visible();
+    editor.set_status(format!("{} matches selected", count), Severity::Info);
+}
+
 fn add_cursor(editor: &mut Editor, direction: Direction) {
     let view_id = editor.tree.focus();
     let doc = editor.current_doc_mut();
@@ -654,6 +2401,7 @@ fn add_cursor(editor: &mut Editor, direction: Direction) {
 }
 
 fn clear_selection(editor: &mut Editor) {
+    editor.clear_search_decorations();
     let view_id = editor.tree.focus();
     let doc = editor.current_doc_mut();
     let selection = doc.selection(view_id);
@@ -717,8 +2465,15 @@ fn paste(editor: &mut Editor) {
         (primary.start(), primary.end())
     };
 
-    let tx = Transaction::replace(doc.len_chars(), start, end, text.clone())
-        .with_selection(Selection::point(start + text.len()));
+    replace_text(doc, view_id, start, end, &text);
+}
+
+/// Replace `start..end` with `text` and put the cursor after the inserted text.
+///
+/// Positions are char indices; `text` may contain multi-byte characters.
+fn replace_text(doc: &mut Document, view_id: ViewId, start: usize, end: usize, text: &str) {
+    let tx = Transaction::replace(doc.len_chars(), start, end, text.to_string())
+        .with_selection(Selection::point(start + text.chars().count()));
     doc.apply(&tx, view_id);
 }
 
@@ -727,6 +2482,7 @@ fn undo(editor: &mut Editor) {
     let doc = editor.current_doc_mut();
     if !doc.undo(view_id) {
         editor.set_status("Nothing to undo", Severity::Info);
+        editor.ring_bell();
     }
 }
 
@@ -735,5 +2491,323 @@ fn redo(editor: &mut Editor) {
     let doc = editor.current_doc_mut();
     if !doc.redo(view_id) {
         editor.set_status("Nothing to redo", Severity::Info);
+        editor.ring_bell();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn editor_with(text: &str, selection: Selection) -> Editor {
+        let mut editor = Editor::new();
+        let view_id = editor.tree.focus();
+        let doc = editor.current_doc_mut();
+        doc.apply(&Transaction::insert(0, 0, text.to_string()), view_id);
+        doc.set_selection(view_id, selection);
+        editor
+    }
+
+    fn cursor(editor: &Editor) -> usize {
+        editor.current_doc().selection(editor.tree.focus()).cursor()
+    }
+
+    #[test]
+    fn test_copy_paste_unicode() {
+        let mut editor = editor_with("héllo wörld", Selection::single(Range::new(6, 11)));
+        execute_action(&mut editor, &Action::Copy);
+        assert_eq!(editor.clipboard, "wörld");
+
+        let view_id = editor.tree.focus();
+        editor.current_doc_mut().set_selection(view_id, Selection::point(0));
+        execute_action(&mut editor, &Action::Paste);
+        assert_eq!(editor.current_doc().text(), "wörldhéllo wörld");
+        assert_eq!(cursor(&editor), 5);
+    }
+
+    #[test]
+    fn test_cut_unicode() {
+        let mut editor = editor_with("日本語 text", Selection::single(Range::new(0, 3)));
+        execute_action(&mut editor, &Action::Cut);
+        assert_eq!(editor.clipboard, "日本語");
+        assert_eq!(editor.current_doc().text(), " text");
+        assert_eq!(cursor(&editor), 0);
+
+        execute_action(&mut editor, &Action::Paste);
+        assert_eq!(editor.current_doc().text(), "日本語 text");
+        assert_eq!(cursor(&editor), 3);
+    }
+
+    #[test]
+    fn test_duplicate_line_unicode() {
+        let mut editor = editor_with("añb\nčd", Selection::point(5));
+        execute_action(&mut editor, &Action::DuplicateLine);
+        assert_eq!(editor.current_doc().text(), "añb\nčd\nčd");
+
+        // The copy goes below, the cursor stays on the line copied
+        let view_id = editor.tree.focus();
+        editor.current_doc_mut().set_selection(view_id, Selection::point(1));
+        execute_action(&mut editor, &Action::DuplicateLine);
+        assert_eq!(editor.current_doc().text(), "añb\nañb\nčd\nčd");
+        assert_eq!(editor.current_doc().selection(view_id).cursor(), 1);
+    }
+
+    #[test]
+    fn test_select_next_occurrence_unicode() {
+        let mut editor = editor_with("ünï x ünï", Selection::point(1));
+        execute_action(&mut editor, &Action::SelectNextOccurrence);
+        execute_action(&mut editor, &Action::SelectNextOccurrence);
+        let selection = editor.current_doc().selection(editor.tree.focus());
+        assert!(selection.ranges().contains(&Range::new(6, 9)));
+    }
+
+    #[test]
+    fn test_insert_text_is_one_edit() {
+        let mut editor = editor_with("", Selection::point(0));
+        insert_text(&mut editor, "a\tç");
+        assert_eq!(editor.current_doc().text(), "a    ç");
+        assert_eq!(cursor(&editor), 6);
+
+        execute_action(&mut editor, &Action::Undo);
+        assert_eq!(editor.current_doc().text(), "");
+    }
+
+    #[test]
+    fn test_insert_newline_indents() {
+        let mut editor = editor_with("    if x {}", Selection::point(10));
+        execute_action(&mut editor, &Action::InsertNewline);
+        assert_eq!(editor.current_doc().text(), "    if x {\n        \n    }");
+        assert_eq!(cursor(&editor), 19);
+
+        let mut editor = editor_with("  f(a,", Selection::point(6));
+        execute_action(&mut editor, &Action::InsertNewline);
+        assert_eq!(editor.current_doc().text(), "  f(a,\n  ");
+
+        let mut editor = editor_with("  let v = [  ]", Selection::point(11));
+        execute_action(&mut editor, &Action::InsertNewline);
+        assert_eq!(editor.current_doc().text(), "  let v = [\n      \n  ]");
+        assert_eq!(cursor(&editor), 18);
+    }
+
+    #[test]
+    fn test_insert_newline_continues_comments() {
+        let mut editor = editor_with("    /// Doc {", Selection::point(13));
+        editor.current_doc_mut().language = Some("rust".to_string());
+        execute_action(&mut editor, &Action::InsertNewline);
+        assert_eq!(editor.current_doc().text(), "    /// Doc {\n    /// ");
+
+        execute_action(&mut editor, &Action::InsertPlainNewline);
+        assert_eq!(editor.current_doc().text(), "    /// Doc {\n    /// \n    ");
+
+        let mut editor = editor_with("# note", Selection::point(6));
+        editor.current_doc_mut().language = Some("python".to_string());
+        editor.config.editor.continue_comments = false;
+        execute_action(&mut editor, &Action::InsertNewline);
+        assert_eq!(editor.current_doc().text(), "# note\n");
+    }
+
+    #[test]
+    fn test_move_cell() {
+        let mut editor = editor_with("a,\"b,c\",d\nee,f", Selection::point(0));
+        editor.current_doc_mut().table_delimiter = Some(',');
+
+        let mut stops = Vec::new();
+        for _ in 0..5 {
+            execute_action(&mut editor, &Action::NextCell);
+            stops.push(cursor(&editor));
+        }
+        assert_eq!(stops, vec![2, 8, 10, 13, 13]);
+
+        execute_action(&mut editor, &Action::PreviousCell);
+        assert_eq!(cursor(&editor), 10);
+        execute_action(&mut editor, &Action::PreviousCell);
+        assert_eq!(cursor(&editor), 8);
+    }
+
+    #[test]
+    fn test_json_format_selection() {
+        let text = "x = {\"a\": [1, 2]} # config";
+        let mut editor = editor_with(text, Selection::single(Range::new(4, 17)));
+        editor.current_doc_mut().language = Some("json".to_string());
+
+        execute_command(&mut editor, "json-minify");
+        assert_eq!(editor.current_doc().text(), "x = {\"a\":[1,2]} # config");
+
+        let view_id = editor.tree.focus();
+        editor.current_doc_mut().set_selection(view_id, Selection::point(0));
+        execute_command(&mut editor, "json-format");
+        assert!(!editor.current_doc().diagnostics.is_empty());
+        assert_eq!(editor.current_doc().text(), "x = {\"a\":[1,2]} # config");
+    }
+
+    #[test]
+    fn test_calculate() {
+        let mut editor = editor_with("width: 80*2px", Selection::single(Range::new(7, 11)));
+        execute_command(&mut editor, ":=0x100 / 2");
+        assert_eq!(editor.status_msg.as_ref().unwrap().0, "= 128 (0x80)");
+        execute_command(&mut editor, "calc 1/");
+        assert_eq!(editor.status_msg.as_ref().unwrap().0, "Unexpected end");
+        assert_eq!(editor.current_doc().text(), "width: 80*2px");
+
+        // The selection is the expression, and the result replaces it
+        execute_command(&mut editor, "calc-insert");
+        assert_eq!(editor.current_doc().text(), "width: 160px");
+        assert_eq!(cursor(&editor), 10);
+        execute_command(&mut editor, "calc-insert 1/4");
+        assert_eq!(editor.current_doc().text(), "width: 1600.25px");
+    }
+
+    #[test]
+    fn test_substitute() {
+        let text = "call(a, b);\ncall(ä, cd);\n";
+        let mut editor = editor_with(text, Selection::point(0));
+        execute_command(&mut editor, r":s/call\((\w+), (?<second>\w+)\)/call(${second}, $1)/");
+        assert_eq!(editor.current_doc().text(), "call(b, a);\ncall(cd, ä);\n");
+        assert_eq!(editor.status_msg.as_ref().unwrap().0, "Replaced 2 matches");
+        // One edit to undo
+        execute_action(&mut editor, &Action::Undo);
+        assert_eq!(editor.current_doc().text(), text);
+
+        // Only the selection, with another delimiter and an escaped one
+        let view_id = editor.tree.focus();
+        editor.current_doc_mut().set_selection(view_id, Selection::single(Range::new(12, 24)));
+        execute_command(&mut editor, r"substitute #CALL\#?#f#i");
+        assert_eq!(editor.current_doc().text(), "call(a, b);\nf(ä, cd);\n");
+
+        // The s flag asks for a selection
+        execute_action(&mut editor, &Action::Undo);
+        editor.current_doc_mut().set_selection(view_id, Selection::point(0));
+        execute_command(&mut editor, r"s/call/f/s");
+        assert_eq!(editor.status_msg.as_ref().unwrap().0, "Nothing selected to replace in");
+        let mut selection = Selection::single(Range::new(0, 2));
+        selection.add_range(Range::new(12, 24));
+        editor.current_doc_mut().set_selection(view_id, selection);
+        execute_command(&mut editor, r"s/call/f/s");
+        assert_eq!(editor.current_doc().text(), "call(a, b);\nf(ä, cd);\n");
+
+        execute_command(&mut editor, "s/(/x/");
+        assert!(editor.status_msg.as_ref().unwrap().0.starts_with("Invalid regex"));
+        execute_command(&mut editor, "s/a/b/q");
+        assert_eq!(editor.status_msg.as_ref().unwrap().0, "Unknown flag: q");
+        assert_eq!(parse_substitute("/a/b"), Some(("a".into(), "b".into(), String::new())));
+        assert_eq!(parse_substitute("/a"), None);
+    }
+
+    #[test]
+    fn test_convert_number() {
+        let mut editor = editor_with("flags = 255;", Selection::point(9));
+        execute_command(&mut editor, "number");
+        assert_eq!(editor.status_msg.as_ref().unwrap().0, "255 = 0xff = 0o377 = 0b11111111");
+        execute_command(&mut editor, "convert hex");
+        assert_eq!(editor.current_doc().text(), "flags = 0xff;");
+        execute_command(&mut editor, "convert bin");
+        assert_eq!(editor.current_doc().text(), "flags = 0b11111111;");
+        execute_command(&mut editor, "convert date");
+        assert_eq!(editor.status_msg.as_ref().unwrap().0, "255 is not a unix timestamp");
+
+        let mut editor = editor_with("at 1700000000", Selection::point(13));
+        execute_command(&mut editor, "convert date");
+        assert_eq!(editor.current_doc().text(), "at 2023-11-14T22:13:20Z");
+        execute_command(&mut editor, "convert dec");
+        assert_eq!(editor.status_msg.as_ref().unwrap().0, "No number under the cursor");
+    }
+
+    #[test]
+    fn test_markdown_editing() {
+        let mut editor = editor_with("1. one", Selection::point(6));
+        editor.current_doc_mut().language = Some("markdown".to_string());
+        execute_action(&mut editor, &Action::InsertNewline);
+        assert_eq!(editor.current_doc().text(), "1. one\n2. ");
+        // An empty item ends the list
+        execute_action(&mut editor, &Action::InsertNewline);
+        assert_eq!(editor.current_doc().text(), "1. one\n");
+
+        let mut editor = editor_with("## Todo\n- [ ] a\n- b", Selection::point(0));
+        let view_id = editor.tree.focus();
+        execute_command(&mut editor, "md-promote");
+        assert_eq!(editor.current_doc().text(), "# Todo\n- [ ] a\n- b");
+        // Lines the selection touches, not one it ends at the start of
+        editor.current_doc_mut().set_selection(view_id, Selection::single(Range::new(8, 18)));
+        execute_command(&mut editor, "md-check");
+        assert_eq!(editor.current_doc().text(), "# Todo\n- [x] a\n- [ ] b");
+
+        let mut editor = editor_with("a word", Selection::single(Range::new(2, 6)));
+        execute_command(&mut editor, "md-bold");
+        assert_eq!(editor.current_doc().text(), "a **word**");
+        assert_eq!(editor.current_doc().selection(editor.tree.focus()).primary(), &Range::new(2, 10));
+        execute_command(&mut editor, "md-bold");
+        assert_eq!(editor.current_doc().text(), "a word");
+
+        let mut editor = editor_with("text\n|a|b|\n|-|-|\n|ccc|d|\n", Selection::point(7));
+        execute_command(&mut editor, "md-table");
+        assert_eq!(editor.current_doc().text(), "text\n| a   | b   |\n| --- | --- |\n| ccc | d   |\n");
+    }
+
+    #[test]
+    fn test_select_occurrences() {
+        let text = "let a = 1;\nfn f(a: u8) { a + 1 }\nlet b = a;\n";
+        let mut editor = editor_with(text, Selection::point(4));
+        let selected = |editor: &Editor| -> Vec<(usize, usize)> {
+            let selection = editor.current_doc().selection(editor.tree.focus());
+            selection.ranges().iter().map(|range| (range.start(), range.end())).collect()
+        };
+        execute_action(&mut editor, &Action::SelectAllOccurrences);
+        assert_eq!(selected(&editor), vec![(4, 5), (16, 17), (25, 26), (41, 42)]);
+
+        // Only the matches inside the function's line
+        let view_id = editor.tree.focus();
+        editor.current_doc_mut().set_selection(view_id, Selection::single(Range::new(11, 33)));
+        execute_command(&mut editor, "select-in a");
+        assert_eq!(selected(&editor), vec![(16, 17), (25, 26)]);
+        execute_command(&mut editor, "select-in b");
+        assert_eq!(editor.status_msg.as_ref().unwrap().0, "No b in the selection");
+    }
+
+    #[test]
+    fn test_use_selection_for_find() {
+        let mut editor = editor_with("foo bar\nfoo.baz foo", Selection::point(1));
+        execute_action(&mut editor, &Action::UseSelectionForFind);
+        assert_eq!(editor.search_query, "foo");
+        assert_eq!(editor.status_msg.as_ref().unwrap().0, "Find foo: 3 matches");
+        execute_action(&mut editor, &Action::FindNext);
+        assert_eq!(cursor(&editor), 11);
+
+        let view_id = editor.tree.focus();
+        editor.current_doc_mut().set_selection(view_id, Selection::single(Range::new(8, 15)));
+        execute_action(&mut editor, &Action::UseSelectionForFind);
+        assert_eq!(editor.search_query, "foo.baz");
+        assert_eq!(editor.prompt_history["search"], vec!["foo.baz", "foo"]);
+    }
+
+    #[test]
+    fn test_split_args() {
+        assert_eq!(split_args(r#"a "my notes.md"  b\ c 'x\y'"#).unwrap(), vec!["a", "my notes.md", "b c", "x\\y"]);
+        assert_eq!(split_args(r#"'' """#).unwrap(), vec!["", ""]);
+        assert!(split_args("\"open").is_err());
+        assert!(find_command("bd").is_some() && find_command("nope").is_none());
+    }
+
+    #[test]
+    fn test_buffer_delete() {
+        let mut editor = Editor::new();
+        editor.open_virtual(Document::new_virtual("one", "1"), None);
+        editor.open_virtual(Document::new_virtual("two words", "2"), None);
+        let names = |editor: &Editor| -> Vec<String> {
+            editor.buffer_ids().iter().map(|id| editor.documents[id].name().to_string()).collect()
+        };
+        assert_eq!(names(&editor).len(), 3);
+
+        execute_command(&mut editor, "bd \"two words\"");
+        assert!(!names(&editor).contains(&"two words".to_string()));
+        execute_command(&mut editor, "bd three");
+        assert_eq!(editor.status_msg.as_ref().unwrap().0, "No buffer named three");
+
+        // Changes keep a buffer open unless forced
+        editor.switch_to_document(editor.buffer_ids()[0]);
+        editor.current_doc_mut().modified = true;
+        execute_command(&mut editor, "bd");
+        assert_eq!(names(&editor).len(), 2);
+        execute_command(&mut editor, "bd!");
+        assert_eq!(names(&editor), vec!["one"]);
     }
 }
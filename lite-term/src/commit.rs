@@ -0,0 +1,118 @@
+//! Committing from the editor: `:git commit` opens a message buffer with the
+//! staged changes below it, and saving the buffer makes the commit.
+
+use lite_config::KeyEvent;
+use lite_git::Repository;
+use lite_view::{BufferHandler, Document, DocumentId, Editor, Severity};
+use std::path::PathBuf;
+
+/// Display name of the commit message buffer, as git names the file
+const COMMIT_BUFFER_NAME: &str = "COMMIT_EDITMSG";
+
+/// Line separating the message from the staged diff
+const SCISSORS: &str = "# ------------------------ >8 ------------------------";
+
+/// Open a commit message buffer for the repository of the current file, or
+/// of the working directory
+pub fn open_commit(editor: &mut Editor) {
+    if let Some(doc_id) = editor.find_virtual(COMMIT_BUFFER_NAME) {
+        editor.switch_to_document(doc_id);
+        return;
+    }
+
+    let path = editor.current_doc().path.clone().unwrap_or_else(|| PathBuf::from("."));
+    let Some(repo) = Repository::open(&path) else {
+        editor.set_status("Not in a git repository", Severity::Error);
+        return;
+    };
+    let diff = match repo.staged_diff() {
+        Ok(diff) if diff.is_empty() => {
+            editor.set_status("Nothing staged to commit", Severity::Info);
+            return;
+        }
+        Ok(diff) => diff,
+        Err(e) => {
+            editor.set_status(e.to_string(), Severity::Error);
+            return;
+        }
+    };
+
+    let tail = format!(
+        "{}\n# Do not modify or remove the line above.\n# Everything below it will be ignored.\n{}",
+        SCISSORS, diff
+    );
+    let text = format!(
+        "\n\n# Enter the commit message. Lines starting with '#' are ignored,\n\
+         # and an empty message aborts the commit. Save to commit.\n{}",
+        tail
+    );
+    let mut doc = Document::new_virtual(COMMIT_BUFFER_NAME, text);
+    doc.read_only = false;
+    doc.read_only_tail = tail.chars().count();
+    doc.language = Some("git-commit".to_string());
+    editor.open_virtual(doc, Some(Box::new(CommitHandler { repo })));
+}
+
+/// The message of a commit buffer: the text before the read-only tail
+/// without comment lines and surrounding blank lines
+fn message(text: &str, tail: usize) -> String {
+    let kept = text.chars().count().saturating_sub(tail);
+    let end = text.char_indices().nth(kept).map_or(text.len(), |(i, _)| i);
+    let lines: Vec<&str> = text[..end]
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .map(str::trim_end)
+        .collect();
+    let message = lines.join("\n");
+    let message = message.trim_matches('\n');
+    if message.is_empty() {
+        String::new()
+    } else {
+        format!("{}\n", message)
+    }
+}
+
+/// Commits the staged changes with the buffer's message on save
+struct CommitHandler {
+    repo: Repository,
+}
+
+impl BufferHandler for CommitHandler {
+    fn handle_key(&mut self, _editor: &mut Editor, _doc_id: DocumentId, _key: &KeyEvent) -> bool {
+        false
+    }
+
+    fn save(&mut self, editor: &mut Editor, doc_id: DocumentId) -> bool {
+        let Some(doc) = editor.documents.get(&doc_id) else {
+            return false;
+        };
+        let message = message(&doc.text(), doc.read_only_tail);
+        if message.is_empty() {
+            editor.set_status("Empty commit message, nothing committed", Severity::Warning);
+            return true;
+        }
+        match self.repo.commit(&message) {
+            Ok(id) => {
+                editor.close_document(doc_id, true);
+                let subject = message.lines().next().unwrap_or_default();
+                editor.set_status(format!("Committed {}: {}", id, subject), Severity::Info);
+            }
+            Err(e) => editor.set_status(e.to_string(), Severity::Error),
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message() {
+        let tail = format!("{}\n+x\n", SCISSORS);
+        let text = format!("\nFix it  \n\n# comment\nBody\n\n{}", tail);
+        assert_eq!(message(&text, tail.chars().count()), "Fix it\n\nBody\n");
+        assert_eq!(message(&format!("# only\n{}", tail), tail.chars().count()), "");
+        assert_eq!(message("Subject", 0), "Subject\n");
+    }
+}
@@ -0,0 +1,445 @@
+//! Directory buffers: a listing that is edited like text. Saving turns the
+//! edits into renames, deletes and new entries, shown for confirmation first.
+
+use lite_config::{Key, KeyEvent, Modifier};
+use lite_core::Selection;
+use lite_view::{BufferHandler, Document, DocumentId, Editor, Severity};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+
+/// Display name of the buffer confirming directory changes
+const CHANGES_BUFFER_NAME: &str = "directory changes";
+
+/// An entry of a listed directory. Its id is its index in the listing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Entry {
+    name: String,
+    dir: bool,
+}
+
+/// A filesystem change, with names relative to the listed directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum DirOp {
+    Delete { name: String, dir: bool },
+    Rename { from: String, to: String },
+    Create { name: String, dir: bool },
+}
+
+/// The directory shown in a buffer and the entries the ids refer to
+struct Listing {
+    dir: PathBuf,
+    entries: Vec<Entry>,
+}
+
+impl Listing {
+    fn read(dir: PathBuf) -> io::Result<Self> {
+        let entries = read_entries(&dir)?;
+        Ok(Self { dir, entries })
+    }
+
+    fn buffer_name(&self) -> String {
+        format!("{}/", self.dir.display())
+    }
+}
+
+/// Open a directory as an editable listing
+pub fn open_directory(editor: &mut Editor, dir: &Path) {
+    let listing = match std::path::absolute(dir).and_then(Listing::read) {
+        Ok(listing) => listing,
+        Err(e) => {
+            editor.set_status(format!("Error: {}", e), Severity::Error);
+            return;
+        }
+    };
+
+    // Show a directory at most once
+    if let Some(doc_id) = editor.find_virtual(&listing.buffer_name()) {
+        editor.switch_to_document(doc_id);
+        return;
+    }
+
+    let mut doc = Document::new_virtual(listing.buffer_name(), render(&listing.entries));
+    doc.read_only = false;
+    let handler = DirectoryHandler {
+        listing: Rc::new(RefCell::new(listing)),
+    };
+    editor.open_virtual(doc, Some(Box::new(handler)));
+}
+
+/// Read a directory, subdirectories first, each group sorted by name
+fn read_entries(dir: &Path) -> io::Result<Vec<Entry>> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| {
+            let entry = entry?;
+            Ok(Entry {
+                name: entry.file_name().to_string_lossy().into_owned(),
+                dir: entry.file_type()?.is_dir(),
+            })
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by(|a, b| b.dir.cmp(&a.dir).then_with(|| a.name.cmp(&b.name)));
+    Ok(entries)
+}
+
+/// One line per entry: `/<id> <name>`, with a trailing `/` on directories
+fn render(entries: &[Entry]) -> String {
+    let width = entries.len().saturating_sub(1).to_string().len();
+    entries
+        .iter()
+        .enumerate()
+        .map(|(id, entry)| {
+            let slash = if entry.dir { "/" } else { "" };
+            format!("/{:0width$} {}{}\n", id, entry.name, slash, width = width)
+        })
+        .collect()
+}
+
+/// Split a line into its entry id, if it has one, and the name
+fn parse_line(line: &str) -> (Option<usize>, &str) {
+    if let Some(rest) = line.strip_prefix('/') {
+        let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        if digits > 0 && rest[digits..].starts_with(' ') {
+            if let Ok(id) = rest[..digits].parse() {
+                return (Some(id), &rest[digits + 1..]);
+            }
+        }
+    }
+    (None, line)
+}
+
+/// Work out the changes turning `entries` into the edited listing `text`
+fn plan(entries: &[Entry], text: &str) -> Result<Vec<DirOp>, String> {
+    let mut kept = vec![false; entries.len()];
+    let mut targets = HashSet::new();
+    let mut renames = Vec::new();
+    let mut creates = Vec::new();
+
+    for line in text.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (id, name) = parse_line(line);
+        let dir = name.ends_with('/');
+        let name = name.trim().trim_end_matches('/');
+
+        let valid = !name.is_empty()
+            && Path::new(name).components().all(|c| matches!(c, Component::Normal(_)));
+        if !valid {
+            return Err(format!("Invalid name: {}", line.trim()));
+        }
+        if !targets.insert(name.to_string()) {
+            return Err(format!("Listed twice: {}", name));
+        }
+
+        match id {
+            Some(id) => {
+                let entry = entries.get(id).ok_or_else(|| format!("Unknown entry: /{}", id))?;
+                if std::mem::replace(&mut kept[id], true) {
+                    return Err(format!("Entry listed twice: {}", entry.name));
+                }
+                if entry.name != name {
+                    renames.push(DirOp::Rename {
+                        from: entry.name.clone(),
+                        to: name.to_string(),
+                    });
+                }
+            }
+            None => creates.push(DirOp::Create {
+                name: name.to_string(),
+                dir,
+            }),
+        }
+    }
+
+    let deletes = entries
+        .iter()
+        .zip(kept)
+        .filter(|(_, kept)| !kept)
+        .map(|(entry, _)| DirOp::Delete {
+            name: entry.name.clone(),
+            dir: entry.dir,
+        });
+    Ok(deletes.chain(renames).chain(creates).collect())
+}
+
+/// Describe the changes as a diff
+fn render_plan(dir: &Path, ops: &[DirOp]) -> String {
+    let mut out = format!("Apply these changes to {}? (y/n)\n\n", dir.display());
+    for op in ops {
+        let line = match op {
+            DirOp::Delete { name, dir: true } => format!("- {}/ (and everything in it)", name),
+            DirOp::Delete { name, dir: false } => format!("- {}", name),
+            DirOp::Rename { from, to } => format!("~ {} -> {}", from, to),
+            DirOp::Create { name, dir: true } => format!("+ {}/", name),
+            DirOp::Create { name, dir: false } => format!("+ {}", name),
+        };
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out
+}
+
+/// Perform the changes, stopping at the first failure
+fn apply(dir: &Path, ops: &[DirOp]) -> io::Result<()> {
+    // Renames onto a name that is itself being renamed go through a
+    // temporary name, so swaps and cycles work
+    let sources: HashSet<&str> = ops
+        .iter()
+        .filter_map(|op| match op {
+            DirOp::Rename { from, .. } => Some(from.as_str()),
+            _ => None,
+        })
+        .collect();
+    let mut staged = Vec::new();
+
+    for (i, op) in ops.iter().enumerate() {
+        match op {
+            DirOp::Delete { name, dir: true } => fs::remove_dir_all(dir.join(name))?,
+            DirOp::Delete { name, dir: false } => fs::remove_file(dir.join(name))?,
+            DirOp::Rename { from, to } if sources.contains(to.as_str()) => {
+                let temp = dir.join(format!(".lite-rename-{}-{}", std::process::id(), i));
+                fs::rename(dir.join(from), &temp)?;
+                staged.push((temp, to));
+            }
+            DirOp::Rename { from, to } => move_entry(&dir.join(from), &dir.join(to))?,
+            DirOp::Create { .. } => {}
+        }
+    }
+    for (temp, to) in staged {
+        move_entry(&temp, &dir.join(to))?;
+    }
+
+    for op in ops {
+        if let DirOp::Create { name, dir: is_dir } = op {
+            let path = dir.join(name);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            if *is_dir {
+                fs::create_dir(&path)?;
+            } else {
+                fs::File::create_new(&path)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Rename without replacing an existing entry, creating missing parents
+fn move_entry(from: &Path, to: &Path) -> io::Result<()> {
+    if to.symlink_metadata().is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", to.display()),
+        ));
+    }
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::rename(from, to)
+}
+
+/// Show the directory again after it changed on disk or was left
+fn refresh(editor: &mut Editor, doc_id: DocumentId, listing: &mut Listing) {
+    match read_entries(&listing.dir) {
+        Ok(entries) => listing.entries = entries,
+        Err(e) => {
+            editor.set_status(format!("Error: {}", e), Severity::Error);
+            return;
+        }
+    }
+
+    let name = listing.buffer_name();
+    let view_id = editor.tree.focus();
+    if let Some(doc) = editor.documents.get_mut(&doc_id) {
+        doc.set_text(render(&listing.entries));
+        doc.set_selection(view_id, Selection::point(0));
+        doc.kind = lite_view::BufferKind::Virtual(name);
+        doc.history.clear();
+        doc.history.mark_saved();
+        doc.modified = false;
+    }
+}
+
+/// Key handler for directory buffers: opens entries and applies edits on save
+struct DirectoryHandler {
+    listing: Rc<RefCell<Listing>>,
+}
+
+impl BufferHandler for DirectoryHandler {
+    fn handle_key(&mut self, editor: &mut Editor, doc_id: DocumentId, key: &KeyEvent) -> bool {
+        let target = match (&key.key, key.modifiers) {
+            (Key::Enter, Modifier::ALT) => {
+                let view_id = editor.tree.focus();
+                let Some(doc) = editor.documents.get(&doc_id) else {
+                    return false;
+                };
+                let line = doc.rope.char_to_line(doc.selection(view_id).cursor());
+                let text: String = doc.rope.line(line).chars().collect();
+                let (id, name) = parse_line(text.trim_end_matches(['\n', '\r']));
+                let listing = self.listing.borrow();
+                match id.and_then(|id| listing.entries.get(id)) {
+                    Some(entry) => listing.dir.join(&entry.name),
+                    None if !name.trim().is_empty() => listing.dir.join(name.trim()),
+                    None => return true,
+                }
+            }
+            (Key::Up, Modifier::ALT) => match self.listing.borrow().dir.parent() {
+                Some(parent) => parent.to_path_buf(),
+                None => return true,
+            },
+            _ => return false,
+        };
+
+        if target.is_dir() {
+            if editor.documents.get(&doc_id).is_some_and(|doc| doc.modified) {
+                editor.set_status("Save or undo the changes first", Severity::Warning);
+                return true;
+            }
+            let mut listing = self.listing.borrow_mut();
+            listing.dir = target;
+            refresh(editor, doc_id, &mut listing);
+        } else if let Err(e) = editor.open(&target) {
+            editor.set_status(format!("Error: {}", e), Severity::Error);
+        }
+        true
+    }
+
+    fn save(&mut self, editor: &mut Editor, doc_id: DocumentId) -> bool {
+        let Some(doc) = editor.documents.get(&doc_id) else {
+            return false;
+        };
+        let text = doc.rope.to_string();
+        let listing = self.listing.borrow();
+
+        match plan(&listing.entries, &text) {
+            Ok(ops) if ops.is_empty() => {
+                drop(listing);
+                refresh(editor, doc_id, &mut self.listing.borrow_mut());
+                editor.set_status("No changes", Severity::Info);
+            }
+            Ok(ops) => {
+                let doc = Document::new_virtual(CHANGES_BUFFER_NAME, render_plan(&listing.dir, &ops));
+                let handler = ConfirmHandler {
+                    listing: self.listing.clone(),
+                    listing_doc: doc_id,
+                    ops,
+                };
+                editor.open_virtual(doc, Some(Box::new(handler)));
+            }
+            Err(e) => editor.set_status(e, Severity::Error),
+        }
+        true
+    }
+}
+
+/// Key handler for the diff of pending directory changes
+struct ConfirmHandler {
+    listing: Rc<RefCell<Listing>>,
+    listing_doc: DocumentId,
+    ops: Vec<DirOp>,
+}
+
+impl BufferHandler for ConfirmHandler {
+    fn handle_key(&mut self, editor: &mut Editor, _doc_id: DocumentId, key: &KeyEvent) -> bool {
+        let confirmed = match key.key {
+            Key::Char('y') | Key::Char('Y') => true,
+            Key::Char('n') | Key::Char('N') | Key::Char('q') | Key::Escape => false,
+            _ => return false,
+        };
+
+        editor.close_buffer(true);
+        editor.switch_to_document(self.listing_doc);
+        if confirmed {
+            let mut listing = self.listing.borrow_mut();
+            let result = apply(&listing.dir, &self.ops);
+            refresh(editor, self.listing_doc, &mut listing);
+            match result {
+                Ok(()) => editor.set_status(format!("Applied {} changes", self.ops.len()), Severity::Info),
+                Err(e) => editor.set_status(format!("Error: {}", e), Severity::Error),
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, dir: bool) -> Entry {
+        Entry {
+            name: name.to_string(),
+            dir,
+        }
+    }
+
+    #[test]
+    fn test_plan() {
+        let entries = vec![entry("src", true), entry("a.txt", false), entry("b.txt", false)];
+        let text = render(&entries);
+        assert_eq!(text, "/0 src/\n/1 a.txt\n/2 b.txt\n");
+        assert_eq!(plan(&entries, &text), Ok(Vec::new()));
+
+        let edited = "/0 lib/\n/1 b.txt\nnotes/\nnew.rs\n";
+        assert_eq!(
+            plan(&entries, edited),
+            Ok(vec![
+                DirOp::Delete {
+                    name: "b.txt".to_string(),
+                    dir: false
+                },
+                DirOp::Rename {
+                    from: "src".to_string(),
+                    to: "lib".to_string()
+                },
+                DirOp::Rename {
+                    from: "a.txt".to_string(),
+                    to: "b.txt".to_string()
+                },
+                DirOp::Create {
+                    name: "notes".to_string(),
+                    dir: true
+                },
+                DirOp::Create {
+                    name: "new.rs".to_string(),
+                    dir: false
+                },
+            ])
+        );
+
+        assert!(plan(&entries, "/1 a.txt\n/1 c.txt\n").is_err());
+        assert!(plan(&entries, "/7 x\n").is_err());
+        assert!(plan(&entries, "/1 ../a.txt\n").is_err());
+        assert!(plan(&entries, "/1 x\n/2 x\n").is_err());
+    }
+
+    #[test]
+    fn test_apply_swaps_names() {
+        let dir = std::env::temp_dir().join(format!("lite-dir-{}", std::process::id()));
+        fs::create_dir_all(dir.join("old")).unwrap();
+        fs::write(dir.join("a"), "a").unwrap();
+        fs::write(dir.join("b"), "b").unwrap();
+
+        let entries = read_entries(&dir).unwrap();
+        let ops = plan(&entries, "/1 b\n/2 a\nsub/c\n").unwrap();
+        let result = apply(&dir, &ops);
+        let contents = (
+            fs::read_to_string(dir.join("a")).unwrap(),
+            fs::read_to_string(dir.join("b")).unwrap(),
+        );
+        let remaining = read_entries(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        result.unwrap();
+        assert_eq!(contents, ("b".to_string(), "a".to_string()));
+        assert_eq!(
+            remaining,
+            vec![entry("sub", true), entry("a", false), entry("b", false)]
+        );
+    }
+}
@@ -1,4 +1,6 @@
-use crossterm::event::{self, Event as CrosstermEvent, KeyCode, KeyModifiers};
+use crossterm::event::{
+    self, Event as CrosstermEvent, KeyCode, KeyEventKind, KeyModifiers, ModifierKeyCode,
+};
 use lite_config::{Key, KeyEvent, Modifier};
 use std::time::Duration;
 use tokio::sync::mpsc;
@@ -8,14 +10,41 @@ use tokio::sync::mpsc;
 pub enum Event {
     /// Key press
     Key(KeyEvent),
+    /// Key release, with the modifiers still held afterwards.
+    /// Only reported by terminals supporting keyboard enhancement.
+    KeyRelease(Modifier),
     /// Mouse event
     Mouse(crossterm::event::MouseEvent),
     /// Terminal resize
     Resize(u16, u16),
+    /// The terminal window lost focus
+    FocusLost,
+    /// Text an input method is composing, replacing the earlier; empty
+    /// when it's given up. Terminals compose in their own window and send
+    /// only the result, as keys, so this comes from other frontends.
+    Preedit(String),
+    /// Text an input method composed, inserted as one edit
+    Commit(String),
     /// Tick for animations/timeouts
     Tick,
 }
 
+impl Event {
+    /// What the event is, for timings
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::Key(_) => "key",
+            Event::KeyRelease(_) => "key release",
+            Event::Mouse(_) => "mouse",
+            Event::Resize(..) => "resize",
+            Event::FocusLost => "focus lost",
+            Event::Preedit(_) => "preedit",
+            Event::Commit(_) => "commit",
+            Event::Tick => "tick",
+        }
+    }
+}
+
 /// Event handler that reads terminal events
 pub struct EventHandler {
     /// Event sender
@@ -41,11 +70,19 @@ impl EventHandler {
                 if event::poll(Duration::from_millis(50)).unwrap_or(false) {
                     if let Ok(evt) = event::read() {
                         let event = match evt {
+                            CrosstermEvent::Key(key) if key.kind == KeyEventKind::Release => {
+                                Some(Event::KeyRelease(held_modifiers(key)))
+                            }
+                            // Modifier keys on their own only matter when released
+                            CrosstermEvent::Key(key) if matches!(key.code, KeyCode::Modifier(_)) => {
+                                None
+                            }
                             CrosstermEvent::Key(key) => {
                                 Some(Event::Key(convert_key_event(key)))
                             }
                             CrosstermEvent::Mouse(mouse) => Some(Event::Mouse(mouse)),
                             CrosstermEvent::Resize(w, h) => Some(Event::Resize(w, h)),
+                            CrosstermEvent::FocusLost => Some(Event::FocusLost),
                             _ => None,
                         };
 
@@ -69,6 +106,16 @@ impl EventHandler {
     pub async fn next(&mut self) -> Option<Event> {
         self.receiver.recv().await
     }
+
+    /// Queue an event as if the terminal had sent it
+    pub fn send(&self, event: Event) {
+        let _ = self.sender.send(event);
+    }
+
+    /// Take the next event if one is already queued
+    pub fn try_next(&mut self) -> Option<Event> {
+        self.receiver.try_recv().ok()
+    }
 }
 
 impl Default for EventHandler {
@@ -77,16 +124,49 @@ impl Default for EventHandler {
     }
 }
 
-/// Convert crossterm key event to our KeyEvent
+/// Modifiers still held after a key release
+fn held_modifiers(key: crossterm::event::KeyEvent) -> Modifier {
+    let mut modifiers = Modifier {
+        ctrl: key.modifiers.contains(KeyModifiers::CONTROL),
+        alt: key.modifiers.contains(KeyModifiers::ALT),
+        shift: key.modifiers.contains(KeyModifiers::SHIFT),
+    };
+
+    // Some terminals still report a modifier as held when it's the key being released
+    if let KeyCode::Modifier(code) = key.code {
+        match code {
+            ModifierKeyCode::LeftControl | ModifierKeyCode::RightControl => modifiers.ctrl = false,
+            ModifierKeyCode::LeftAlt | ModifierKeyCode::RightAlt => modifiers.alt = false,
+            ModifierKeyCode::LeftShift | ModifierKeyCode::RightShift => modifiers.shift = false,
+            _ => {}
+        }
+    }
+    modifiers
+}
+
+/// Convert crossterm key event to our KeyEvent.
+///
+/// Terminals disagree on shifted letters: some send `'A'`, some `'A'` + SHIFT
+/// and some `'a'` + SHIFT. They are always delivered as the typed uppercase
+/// character plus SHIFT, and Shift+Tab as Tab + SHIFT.
 fn convert_key_event(key: crossterm::event::KeyEvent) -> KeyEvent {
-    let modifiers = Modifier {
+    let mut modifiers = Modifier {
         ctrl: key.modifiers.contains(KeyModifiers::CONTROL),
         alt: key.modifiers.contains(KeyModifiers::ALT),
         shift: key.modifiers.contains(KeyModifiers::SHIFT),
     };
 
     let key = match key.code {
+        KeyCode::Char(c) if c.is_uppercase() => {
+            modifiers.shift = true;
+            Key::Char(c)
+        }
+        KeyCode::Char(c) if c.is_lowercase() && modifiers.shift => Key::Char(to_upper(c)),
         KeyCode::Char(c) => Key::Char(c),
+        KeyCode::BackTab => {
+            modifiers.shift = true;
+            Key::Tab
+        }
         KeyCode::F(n) => Key::F(n),
         KeyCode::Backspace => Key::Backspace,
         KeyCode::Enter => Key::Enter,
@@ -107,3 +187,51 @@ fn convert_key_event(key: crossterm::event::KeyEvent) -> KeyEvent {
 
     KeyEvent::new(key, modifiers)
 }
+
+/// Uppercase a character, keeping it if it doesn't map to a single character
+fn to_upper(c: char) -> char {
+    let mut upper = c.to_uppercase();
+    match (upper.next(), upper.next()) {
+        (Some(u), None) => u,
+        _ => c,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lite_config::{Action, Keymap};
+
+    fn crossterm_key(code: KeyCode, modifiers: KeyModifiers) -> crossterm::event::KeyEvent {
+        crossterm::event::KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn test_shifted_letters_are_normalized() {
+        let expected = KeyEvent::new(Key::Char('A'), Modifier::SHIFT);
+        for event in [
+            crossterm_key(KeyCode::Char('A'), KeyModifiers::NONE),
+            crossterm_key(KeyCode::Char('A'), KeyModifiers::SHIFT),
+            crossterm_key(KeyCode::Char('a'), KeyModifiers::SHIFT),
+        ] {
+            assert_eq!(convert_key_event(event), expected);
+        }
+
+        let back_tab = crossterm_key(KeyCode::BackTab, KeyModifiers::SHIFT);
+        assert_eq!(convert_key_event(back_tab), KeyEvent::new(Key::Tab, Modifier::SHIFT));
+    }
+
+    #[test]
+    fn test_ctrl_shift_bindings_match() {
+        let keymap = Keymap::default();
+        let ctrl_shift = KeyModifiers::CONTROL | KeyModifiers::SHIFT;
+        for event in [
+            crossterm_key(KeyCode::Char('K'), KeyModifiers::CONTROL),
+            crossterm_key(KeyCode::Char('K'), ctrl_shift),
+            crossterm_key(KeyCode::Char('k'), ctrl_shift),
+        ] {
+            let event = convert_key_event(event);
+            assert_eq!(keymap.get(&event), Some(&Action::DeleteLine));
+        }
+    }
+}
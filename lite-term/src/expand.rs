@@ -0,0 +1,242 @@
+//! Paths as typed to `:e` and the Open prompt: `~` and `$NAME` expand,
+//! `%` is the current buffer's file and `%:h` its directory, and `*`, `?`,
+//! `[a-z]` and `**` match files. `\%` and `\$` are the characters themselves.
+
+use crate::file_tree::read_dir;
+use lite_git::Repository;
+use lite_view::workspace::SKIPPED_DIRS;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Most files a glob matches
+const MAX_MATCHES: usize = 1000;
+
+/// Most directories a glob reads, so `**` over a huge tree stays cheap
+const MAX_DIRS: usize = 5000;
+
+/// Expand a typed path. `current` is the current buffer's file, for `%`,
+/// `%:h` (its directory) and `%:t` (its name).
+pub fn expand(input: &str, current: Option<&Path>) -> Result<String, String> {
+    let mut expanded = String::new();
+    let mut rest = input;
+    if let Some(after) = rest.strip_prefix('~').filter(|after| after.is_empty() || after.starts_with('/')) {
+        expanded.push_str(&variable("HOME")?);
+        rest = after;
+    }
+
+    while let Some(i) = rest.find(['$', '%']) {
+        // Escaped, it's the character itself
+        if rest[..i].ends_with('\\') {
+            expanded.push_str(&rest[..i - 1]);
+            expanded.push_str(&rest[i..i + 1]);
+            rest = &rest[i + 1..];
+            continue;
+        }
+        expanded.push_str(&rest[..i]);
+        rest = &rest[i..];
+        if let Some(after) = rest.strip_prefix('%') {
+            let file = current.ok_or("The buffer has no file for %")?;
+            let (value, after) = match after {
+                _ if after.starts_with(":h") => {
+                    let dir = file.parent().filter(|dir| !dir.as_os_str().is_empty());
+                    (dir.unwrap_or(Path::new(".")), &after[2..])
+                }
+                _ if after.starts_with(":t") => (file.file_name().map(Path::new).unwrap_or(file), &after[2..]),
+                _ => (file, after),
+            };
+            expanded.push_str(&value.to_string_lossy());
+            rest = after;
+            continue;
+        }
+
+        let after = &rest[1..];
+        let (name, after) = match after.strip_prefix('{').and_then(|braced| braced.split_once('}')) {
+            Some((name, after)) => (name, after),
+            None => {
+                let len = after.len() - after.trim_start_matches(|c: char| c.is_alphanumeric() || c == '_').len();
+                after.split_at(len)
+            }
+        };
+        match name {
+            "" => expanded.push('$'),
+            _ => expanded.push_str(&variable(name)?),
+        }
+        rest = after;
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+fn variable(name: &str) -> Result<String, String> {
+    std::env::var(name).map_err(|_| format!("${} is not set", name))
+}
+
+/// Whether a path is a glob
+pub fn is_glob(path: &str) -> bool {
+    path.contains(['*', '?', '['])
+}
+
+/// The files a glob matches, sorted. Folders are searched as the file tree
+/// lists them, so ignored files and hidden ones a part doesn't start with
+/// `.` are left out, and `**` doesn't go into build folders either. The
+/// search stops after `MAX_DIRS` folders.
+pub fn glob(pattern: &str) -> Vec<PathBuf> {
+    let parts: Vec<&str> = pattern.split('/').filter(|part| !part.is_empty() && *part != ".").collect();
+    let literal = parts.iter().take_while(|part| !is_glob(part)).count();
+    let mut base: PathBuf = parts[..literal].iter().collect();
+    if pattern.starts_with('/') {
+        base = Path::new("/").join(base);
+    }
+
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let root = cwd.join(&base);
+    let repo = Repository::open(&root);
+    let mut matches = Vec::new();
+    let mut visited = HashSet::new();
+    walk(&root, &parts[literal..], repo.as_ref(), &mut matches, &mut visited);
+    matches.sort();
+    matches.dedup();
+    if pattern.starts_with('/') {
+        return matches;
+    }
+    matches.into_iter().map(|path| path.strip_prefix(&cwd).map(Path::to_path_buf).unwrap_or(path)).collect()
+}
+
+/// Match the rest of a glob in a folder. `visited` holds the real paths of
+/// the folders read and the parts left there, so a folder reached again
+/// through a symlink isn't searched twice.
+fn walk(
+    dir: &Path,
+    parts: &[&str],
+    repo: Option<&Repository>,
+    matches: &mut Vec<PathBuf>,
+    visited: &mut HashSet<(PathBuf, usize)>,
+) {
+    let Some((part, rest)) = parts.split_first() else {
+        return;
+    };
+    if matches.len() >= MAX_MATCHES || visited.len() >= MAX_DIRS {
+        return;
+    }
+    if !dir.canonicalize().is_ok_and(|real| visited.insert((real, parts.len()))) {
+        return;
+    }
+    let entries = read_dir(dir, repo);
+    // Any number of folders, none included
+    if *part == "**" {
+        walk(dir, rest, repo, matches, visited);
+        for (name, is_dir) in entries {
+            if is_dir && !name.starts_with('.') && !SKIPPED_DIRS.contains(&name.as_str()) {
+                walk(&dir.join(name), parts, repo, matches, visited);
+            }
+        }
+        return;
+    }
+
+    for (name, is_dir) in entries {
+        if (name.starts_with('.') && !part.starts_with('.')) || !matches_part(part, &name) {
+            continue;
+        }
+        match (rest.is_empty(), is_dir) {
+            (true, false) if matches.len() < MAX_MATCHES => matches.push(dir.join(name)),
+            (false, true) => walk(&dir.join(name), rest, repo, matches, visited),
+            _ => {}
+        }
+    }
+}
+
+/// Whether a file name matches one part of a glob
+fn matches_part(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    matches_chars(&pattern, &name)
+}
+
+fn matches_chars(pattern: &[char], name: &[char]) -> bool {
+    match pattern.first() {
+        None => name.is_empty(),
+        Some('*') => (0..=name.len()).any(|skip| matches_chars(&pattern[1..], &name[skip..])),
+        Some('?') => !name.is_empty() && matches_chars(&pattern[1..], &name[1..]),
+        Some('[') => {
+            let Some(close) = pattern.iter().skip(2).position(|c| *c == ']').map(|i| i + 2) else {
+                return name.first() == Some(&'[') && matches_chars(&pattern[1..], &name[1..]);
+            };
+            let Some(c) = name.first() else {
+                return false;
+            };
+            let (negated, class) = match pattern[1] {
+                '!' | '^' => (true, &pattern[2..close]),
+                _ => (false, &pattern[1..close]),
+            };
+            let mut found = false;
+            let mut i = 0;
+            while i < class.len() {
+                if i + 2 < class.len() && class[i + 1] == '-' {
+                    found |= (class[i]..=class[i + 2]).contains(c);
+                    i += 3;
+                } else {
+                    found |= class[i] == *c;
+                    i += 1;
+                }
+            }
+            found != negated && matches_chars(&pattern[close + 1..], &name[1..])
+        }
+        Some(p) => name.first() == Some(p) && matches_chars(&pattern[1..], &name[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_expand() {
+        let current = Some(Path::new("src/main.rs"));
+        if let Ok(home) = std::env::var("HOME") {
+            assert_eq!(expand("~/notes.md", None).unwrap(), format!("{}/notes.md", home));
+            assert_eq!(expand("${HOME}/a$", None).unwrap(), format!("{}/a$", home));
+        }
+        assert_eq!(expand(r"100\%/\$HOME\", current).unwrap(), r"100%/$HOME\");
+        assert_eq!(expand("%:h/lib.rs", current).unwrap(), "src/lib.rs");
+        assert_eq!(expand("%:t.bak", current).unwrap(), "main.rs.bak");
+        assert_eq!(expand("%:h/x", Some(Path::new("a.rs"))).unwrap(), "./x");
+        assert!(expand("%", None).is_err());
+        assert_eq!(expand("$LITE_NOT_SET_ANYWHERE/x", None), Err("$LITE_NOT_SET_ANYWHERE is not set".to_string()));
+    }
+
+    #[test]
+    fn test_matches_part() {
+        assert!(matches_part("*.toml", "Cargo.toml"));
+        assert!(!matches_part("*.toml", "Cargo.lock"));
+        assert!(matches_part("ma?n.[a-r]s", "main.rs"));
+        assert!(!matches_part("[!m]*", "main.rs"));
+        assert!(matches_part("[x", "[x"));
+    }
+
+    #[test]
+    fn test_glob() {
+        let dir = std::env::temp_dir().join(format!("lite-glob-{}", std::process::id()));
+        fs::create_dir_all(dir.join("a/b")).unwrap();
+        fs::create_dir_all(dir.join(".hidden")).unwrap();
+        fs::create_dir_all(dir.join("target")).unwrap();
+        for file in ["top.toml", "a/one.toml", "a/b/two.toml", "a/b/two.rs", ".hidden/three.toml", "target/x.toml"] {
+            fs::write(dir.join(file), "").unwrap();
+        }
+
+        let found = glob(&format!("{}/**/*.toml", dir.display()));
+        let expected: Vec<PathBuf> = ["a/b/two.toml", "a/one.toml", "top.toml"].iter().map(|f| dir.join(f)).collect();
+        assert_eq!(found, expected);
+        assert_eq!(glob(&format!("{}/target/*.toml", dir.display())).len(), 1);
+        assert_eq!(glob(&format!("{}/*/b/*", dir.display())).len(), 2);
+        assert!(glob(&format!("{}/*.rs", dir.display())).is_empty());
+        // A symlink back up the tree doesn't repeat the search forever
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(&dir, dir.join("a/up")).unwrap();
+            assert_eq!(glob(&format!("{}/**/*.toml", dir.display())), expected);
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,281 @@
+//! The file tree sidebar: directories listed as `.gitignore` allows, and
+//! the entries picked in it created, renamed and deleted.
+
+use lite_git::Repository;
+use lite_view::{Editor, Severity};
+use std::fs;
+use std::io;
+use std::path::{Component, Path};
+
+/// Open, focus or close the file tree of the working directory. Opening
+/// it shows the current file.
+pub fn toggle(editor: &mut Editor) {
+    let opening = editor.file_tree.is_none();
+    let root = std::env::current_dir().unwrap_or_default();
+    editor.toggle_file_tree(root);
+
+    let current = editor.current_doc().path.as_ref().and_then(|path| std::path::absolute(path).ok());
+    match current.filter(|_| opening) {
+        Some(current) => show(editor, &current),
+        None => refresh(editor),
+    }
+}
+
+/// List the entries of the tree again, leaving out the ignored ones
+pub fn refresh(editor: &mut Editor) {
+    let Some(tree) = &mut editor.file_tree else {
+        return;
+    };
+    let repo = Repository::open(&tree.root);
+    tree.rebuild(&|dir| read_dir(dir, repo.as_ref()));
+}
+
+/// Entries of a directory, subdirectories first, each group sorted by name.
+/// Symlinks to directories are directories too; walks over the tree keep
+/// the real paths they've been to, so a cycle can't make them endless.
+pub fn read_dir(dir: &Path, repo: Option<&Repository>) -> Vec<(String, bool)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut entries: Vec<(String, bool)> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_name() != ".git")
+        .filter(|entry| !repo.is_some_and(|repo| repo.is_ignored(&entry.path())))
+        .filter_map(|entry| {
+            let file_type = entry.file_type().ok()?;
+            let is_dir = match file_type.is_symlink() {
+                true => fs::metadata(entry.path()).is_ok_and(|metadata| metadata.is_dir()),
+                false => file_type.is_dir(),
+            };
+            Some((entry.file_name().to_string_lossy().into_owned(), is_dir))
+        })
+        .collect();
+    entries.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    entries
+}
+
+/// Open the picked file, or expand or collapse the picked directory
+pub fn activate(editor: &mut Editor) {
+    let Some(tree) = &mut editor.file_tree else {
+        return;
+    };
+    if tree.toggle_selected() {
+        refresh(editor);
+        return;
+    }
+    let Some(path) = tree.selected_entry().map(|entry| entry.path.clone()) else {
+        return;
+    };
+    tree.focused = false;
+    if let Err(e) = editor.open(&path) {
+        editor.set_status(format!("Error: {}", e), Severity::Error);
+    }
+}
+
+/// Expand the picked directory
+pub fn expand(editor: &mut Editor) {
+    if let Some(tree) = &mut editor.file_tree {
+        let collapsed = tree
+            .selected_entry()
+            .is_some_and(|entry| entry.dir && !tree.expanded.contains(&entry.path));
+        if collapsed && tree.toggle_selected() {
+            refresh(editor);
+        }
+    }
+}
+
+/// Collapse the picked directory, or go to the one holding the pick
+pub fn collapse(editor: &mut Editor) {
+    if let Some(tree) = &mut editor.file_tree {
+        tree.collapse_selected();
+        refresh(editor);
+    }
+}
+
+/// Create a file, or a directory if the name ends with `/`, next to the
+/// pick. New files are opened.
+pub fn create(editor: &mut Editor, input: &str) {
+    let Some(tree) = &editor.file_tree else {
+        return;
+    };
+    let dir = input.trim().ends_with('/');
+    let name = input.trim().trim_end_matches('/');
+    if !valid_name(name) {
+        editor.set_status(format!("Invalid name: {}", input.trim()), Severity::Error);
+        return;
+    }
+    let path = tree.target_dir().join(name);
+    if let Err(e) = create_entry(&path, dir) {
+        editor.set_status(format!("Error: {}", e), Severity::Error);
+        return;
+    }
+
+    show(editor, &path);
+    if !dir {
+        activate(editor);
+    }
+    editor.set_status(format!("Created {}", name), Severity::Info);
+}
+
+fn create_entry(path: &Path, dir: bool) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if dir {
+        fs::create_dir(path)
+    } else {
+        fs::File::create_new(path).map(|_| ())
+    }
+}
+
+/// Rename the pick within its directory. Buffers of the renamed files
+/// follow them.
+pub fn rename(editor: &mut Editor, input: &str) {
+    let Some(from) = editor.file_tree.as_ref().and_then(|tree| tree.selected_entry()).map(|entry| entry.path.clone())
+    else {
+        return;
+    };
+    let name = input.trim().trim_end_matches('/');
+    if !valid_name(name) {
+        editor.set_status(format!("Invalid name: {}", input.trim()), Severity::Error);
+        return;
+    }
+    let to = from.parent().unwrap_or(Path::new("")).join(name);
+    if to == from {
+        return;
+    }
+    let result = if to.symlink_metadata().is_ok() {
+        Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} already exists", name)))
+    } else {
+        to.parent().map_or(Ok(()), fs::create_dir_all).and_then(|_| fs::rename(&from, &to))
+    };
+    if let Err(e) = result {
+        editor.set_status(format!("Error: {}", e), Severity::Error);
+        return;
+    }
+
+    let moved = |path: &Path| {
+        let rest = path.strip_prefix(&from).ok()?;
+        Some(if rest.as_os_str().is_empty() { to.clone() } else { to.join(rest) })
+    };
+    for doc in editor.documents.values_mut() {
+        let path = doc.path.as_ref().and_then(|path| std::path::absolute(path).ok());
+        if let Some(path) = path.as_deref().and_then(moved) {
+            doc.path = Some(path);
+        }
+    }
+    if let Some(tree) = &mut editor.file_tree {
+        tree.expanded = tree.expanded.iter().map(|dir| moved(dir).unwrap_or_else(|| dir.clone())).collect();
+    }
+    show(editor, &to);
+    editor.set_status(format!("Renamed to {}", name), Severity::Info);
+}
+
+/// Delete the pick, with everything in it if it's a directory
+pub fn delete(editor: &mut Editor) {
+    let Some(entry) = editor.file_tree.as_ref().and_then(|tree| tree.selected_entry()).cloned() else {
+        return;
+    };
+    let result = if entry.dir {
+        fs::remove_dir_all(&entry.path)
+    } else {
+        fs::remove_file(&entry.path)
+    };
+    match result {
+        Ok(()) => editor.set_status(format!("Deleted {}", entry.name), Severity::Info),
+        Err(e) => editor.set_status(format!("Error: {}", e), Severity::Error),
+    }
+    refresh(editor);
+}
+
+/// Expand the directories down to a path and pick it
+fn show(editor: &mut Editor, path: &Path) {
+    let Some(tree) = &mut editor.file_tree else {
+        return;
+    };
+    let root = tree.root.clone();
+    tree.expanded.extend(
+        path.ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(&root) && *dir != root)
+            .map(Path::to_path_buf),
+    );
+    refresh(editor);
+    if let Some(tree) = &mut editor.file_tree {
+        tree.select_path(path);
+    }
+}
+
+/// A name relative to a directory that stays inside it
+fn valid_name(name: &str) -> bool {
+    !name.is_empty() && Path::new(name).components().all(|c| matches!(c, Component::Normal(_)))
+}
+
+/// Name of the pick, for prompts
+pub fn selected_name(editor: &Editor) -> Option<String> {
+    let entry = editor.file_tree.as_ref()?.selected_entry()?;
+    Some(entry.name.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(editor: &Editor) -> Vec<&str> {
+        let tree = editor.file_tree.as_ref().unwrap();
+        tree.entries.iter().map(|entry| entry.name.as_str()).collect()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_read_dir_lists_symlinked_dirs() {
+        let dir = std::env::temp_dir().join(format!("lite-tree-link-{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        std::os::unix::fs::symlink(&dir, dir.join("sub/loop")).unwrap();
+        std::os::unix::fs::symlink(dir.join("missing"), dir.join("sub/broken")).unwrap();
+        let entries = read_dir(&dir.join("sub"), None);
+        assert_eq!(entries, vec![("loop".to_string(), true), ("broken".to_string(), false)]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_create_rename_delete() {
+        let dir = std::env::temp_dir().join(format!("lite-tree-{}", std::process::id()));
+        fs::create_dir_all(dir.join("target")).unwrap();
+        fs::write(dir.join(".gitignore"), "target/\n").unwrap();
+        git2::Repository::init(&dir).unwrap();
+        let dir = dir.canonicalize().unwrap();
+
+        let mut editor = Editor::new();
+        editor.toggle_file_tree(dir.clone());
+        refresh(&mut editor);
+        assert_eq!(names(&editor), vec![".gitignore"]);
+
+        // New files open, in the picked directory
+        create(&mut editor, "src/");
+        assert_eq!(names(&editor), vec!["src", ".gitignore"]);
+        create(&mut editor, "main.rs");
+        assert_eq!(names(&editor), vec!["src", "main.rs", ".gitignore"]);
+        assert_eq!(editor.current_doc().path.as_deref(), Some(dir.join("src/main.rs").as_path()));
+        assert!(!editor.file_tree.as_ref().unwrap().focused);
+
+        create(&mut editor, "../escape");
+        assert!(!dir.join("escape").exists());
+
+        rename(&mut editor, "lib.rs");
+        assert_eq!(names(&editor), vec!["src", "lib.rs", ".gitignore"]);
+        assert_eq!(editor.current_doc().path.as_deref(), Some(dir.join("src/lib.rs").as_path()));
+
+        // Renaming a directory moves the buffers in it along
+        collapse(&mut editor);
+        rename(&mut editor, "core");
+        assert_eq!(names(&editor), vec!["core", "lib.rs", ".gitignore"]);
+        assert_eq!(editor.current_doc().path.as_deref(), Some(dir.join("core/lib.rs").as_path()));
+
+        delete(&mut editor);
+        assert_eq!(names(&editor), vec![".gitignore"]);
+        assert!(!dir.join("core").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
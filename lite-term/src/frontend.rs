@@ -0,0 +1,191 @@
+//! What the application needs from the screen it runs on. Cells are drawn
+//! through ratatui's `Backend`; taking the screen over, input and the cursor
+//! shape go through `Frontend`, so another frontend (a GUI shell, a web
+//! terminal, the test backend) runs lite-view and lite-ui unchanged.
+
+use crate::EventHandler;
+use crossterm::{
+    cursor::SetCursorStyle,
+    event::{
+        DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture,
+        KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
+    execute,
+    style::Print,
+    terminal::{
+        disable_raw_mode, enable_raw_mode, supports_keyboard_enhancement, EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
+};
+use lite_config::CursorShape;
+use ratatui::backend::{Backend, ClearType, CrosstermBackend, TestBackend, WindowSize};
+use ratatui::buffer::Cell;
+use ratatui::layout::{Position, Size};
+use std::io::{self, Stdout};
+
+/// A screen the application draws to and takes input from
+pub trait Frontend: Backend {
+    /// Take the screen over, before the first frame
+    fn enter(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Give the screen back, when the application ends
+    fn leave(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Start queueing input into `events`. Frontends without input of their
+    /// own leave it to `Application::handle_events`.
+    fn listen(&mut self, _events: &EventHandler) {}
+
+    /// Show the cursor with a shape, blinking or not
+    fn set_cursor_style(&mut self, _shape: CursorShape, _blinking: bool) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Ring the terminal's bell
+    fn ring_bell(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Take mouse input, or leave the mouse to the terminal's own selection
+    fn set_mouse_capture(&mut self, _on: bool) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The fake terminal of tests: no input, and nothing to set up
+impl Frontend for TestBackend {}
+
+/// The terminal lite runs in, through crossterm
+pub struct TerminalFrontend {
+    backend: CrosstermBackend<Stdout>,
+    /// Whether the terminal reports key releases (kitty keyboard protocol)
+    keyboard_enhanced: bool,
+}
+
+impl TerminalFrontend {
+    pub fn new() -> Self {
+        Self {
+            backend: CrosstermBackend::new(io::stdout()),
+            keyboard_enhanced: false,
+        }
+    }
+}
+
+impl Default for TerminalFrontend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Frontend for TerminalFrontend {
+    fn enter(&mut self) -> io::Result<()> {
+        enable_raw_mode()?;
+        let mut stdout = io::stdout();
+        execute!(stdout, EnterAlternateScreen, EnableFocusChange)?;
+
+        // Ask for key release events so Ctrl+Tab can commit when Ctrl is let go
+        self.keyboard_enhanced = supports_keyboard_enhancement().unwrap_or(false);
+        if self.keyboard_enhanced {
+            execute!(
+                stdout,
+                PushKeyboardEnhancementFlags(
+                    KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+                        | KeyboardEnhancementFlags::REPORT_EVENT_TYPES
+                        | KeyboardEnhancementFlags::REPORT_ALTERNATE_KEYS
+                        | KeyboardEnhancementFlags::REPORT_ALL_KEYS_AS_ESCAPE_CODES
+                )
+            )?;
+        }
+        Ok(())
+    }
+
+    fn leave(&mut self) -> io::Result<()> {
+        let mut stdout = io::stdout();
+        if self.keyboard_enhanced {
+            let _ = execute!(stdout, PopKeyboardEnhancementFlags);
+        }
+        let _ = disable_raw_mode();
+        let _ = execute!(stdout, LeaveAlternateScreen, DisableMouseCapture, DisableFocusChange);
+        let _ = execute!(stdout, SetCursorStyle::DefaultUserShape);
+        self.backend.show_cursor()
+    }
+
+    fn listen(&mut self, events: &EventHandler) {
+        events.start();
+    }
+
+    fn set_cursor_style(&mut self, shape: CursorShape, blinking: bool) -> io::Result<()> {
+        let command = match (shape, blinking) {
+            (CursorShape::Block, true) => SetCursorStyle::BlinkingBlock,
+            (CursorShape::Block, false) => SetCursorStyle::SteadyBlock,
+            (CursorShape::Bar, true) => SetCursorStyle::BlinkingBar,
+            (CursorShape::Bar, false) => SetCursorStyle::SteadyBar,
+            (CursorShape::Underline, true) => SetCursorStyle::BlinkingUnderScore,
+            (CursorShape::Underline, false) => SetCursorStyle::SteadyUnderScore,
+        };
+        execute!(io::stdout(), command)
+    }
+
+    fn ring_bell(&mut self) -> io::Result<()> {
+        execute!(io::stdout(), Print('\x07'))
+    }
+
+    fn set_mouse_capture(&mut self, on: bool) -> io::Result<()> {
+        match on {
+            true => execute!(io::stdout(), EnableMouseCapture),
+            false => execute!(io::stdout(), DisableMouseCapture),
+        }
+    }
+}
+
+impl Backend for TerminalFrontend {
+    fn draw<'a, I>(&mut self, content: I) -> io::Result<()>
+    where
+        I: Iterator<Item = (u16, u16, &'a Cell)>,
+    {
+        self.backend.draw(content)
+    }
+
+    fn append_lines(&mut self, n: u16) -> io::Result<()> {
+        self.backend.append_lines(n)
+    }
+
+    fn hide_cursor(&mut self) -> io::Result<()> {
+        self.backend.hide_cursor()
+    }
+
+    fn show_cursor(&mut self) -> io::Result<()> {
+        self.backend.show_cursor()
+    }
+
+    fn get_cursor_position(&mut self) -> io::Result<Position> {
+        self.backend.get_cursor_position()
+    }
+
+    fn set_cursor_position<P: Into<Position>>(&mut self, position: P) -> io::Result<()> {
+        self.backend.set_cursor_position(position)
+    }
+
+    fn clear(&mut self) -> io::Result<()> {
+        self.backend.clear()
+    }
+
+    fn clear_region(&mut self, clear_type: ClearType) -> io::Result<()> {
+        self.backend.clear_region(clear_type)
+    }
+
+    fn size(&self) -> io::Result<Size> {
+        self.backend.size()
+    }
+
+    fn window_size(&mut self) -> io::Result<WindowSize> {
+        self.backend.window_size()
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.backend.flush()
+    }
+}
@@ -0,0 +1,338 @@
+//! Git state of open documents: which lines changed since the last commit
+
+use lite_core::Rope;
+use lite_git::{FileDiff, FileStatus, Repository};
+use lite_view::{DocumentId, Editor, IgnoreFilter, Severity};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How often unedited documents are compared again, so commits and
+/// checkouts made outside the editor show up
+const REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Work for the thread that reads git state, off the main loop
+enum Job {
+    /// Compare a document's text with the repository
+    Diff {
+        id: DocumentId,
+        repo: Arc<Repository>,
+        path: PathBuf,
+        text: Rope,
+    },
+    /// Drop the cached diff of a closed document
+    Forget { repo: Arc<Repository>, path: PathBuf },
+}
+
+/// A finished `Job::Diff`
+struct Update {
+    id: DocumentId,
+    status: Option<FileStatus>,
+    diff: Arc<FileDiff>,
+}
+
+/// Keeps the git diff of every open file up to date
+pub struct GitManager {
+    /// Repository of each directory with open files, `None` outside one
+    repos: HashMap<PathBuf, Option<Arc<Repository>>>,
+    /// Path, version and modified flag each document's diff was last
+    /// started for; saving changes the status of the file
+    synced: HashMap<DocumentId, (PathBuf, usize, bool)>,
+    /// Documents with a diff being computed. Edits made meanwhile are
+    /// diffed once it's back, so typing doesn't queue a diff per key.
+    pending: HashSet<DocumentId>,
+    jobs: mpsc::Sender<Job>,
+    updates: mpsc::Receiver<Update>,
+    last_refresh: Instant,
+}
+
+impl GitManager {
+    pub fn new() -> Self {
+        let (jobs, job_rx) = mpsc::channel();
+        let (update_tx, updates) = mpsc::channel();
+        std::thread::spawn(move || work(job_rx, update_tx));
+        Self {
+            repos: HashMap::new(),
+            synced: HashMap::new(),
+            pending: HashSet::new(),
+            jobs,
+            updates,
+            last_refresh: Instant::now(),
+        }
+    }
+
+    /// Show the diffs that came back, and start recomputing those of
+    /// documents edited since, and of all of them every `REFRESH_INTERVAL`
+    pub fn sync(&mut self, editor: &mut Editor) {
+        while let Ok(update) = self.updates.try_recv() {
+            self.show(editor, update);
+        }
+
+        let refresh = self.last_refresh.elapsed() >= REFRESH_INTERVAL;
+        if refresh {
+            self.last_refresh = Instant::now();
+        }
+
+        // Closed documents
+        let closed: Vec<DocumentId> = self
+            .synced
+            .keys()
+            .filter(|id| !editor.documents.contains_key(id))
+            .copied()
+            .collect();
+        for id in closed {
+            if let Some((path, ..)) = self.synced.remove(&id) {
+                if let Some(repo) = self.repository(&path) {
+                    let _ = self.jobs.send(Job::Forget { repo, path });
+                }
+            }
+        }
+
+        let ids: Vec<DocumentId> = editor.documents.keys().copied().collect();
+        for id in ids {
+            let doc = &editor.documents[&id];
+            let Some(path) = doc.path.clone() else {
+                continue;
+            };
+            let current = (path.clone(), doc.version(), doc.modified);
+            if self.pending.contains(&id) || (!refresh && self.synced.get(&id) == Some(&current)) {
+                continue;
+            }
+            // The rope is shared, not copied
+            let text = doc.rope.clone();
+            self.synced.insert(id, current);
+
+            match self.repository(&path) {
+                Some(repo) => {
+                    if self.jobs.send(Job::Diff { id, repo, path, text }).is_ok() {
+                        self.pending.insert(id);
+                    }
+                }
+                None => {
+                    let diff = Arc::default();
+                    self.show(editor, Update { id, status: None, diff });
+                }
+            }
+        }
+    }
+
+    /// `sync` and wait for the diffs, of the current text too if an older
+    /// one was being diffed
+    pub fn sync_now(&mut self, editor: &mut Editor) {
+        self.wait(editor);
+        self.sync(editor);
+        self.wait(editor);
+    }
+
+    /// Show the diffs being computed once they are done
+    fn wait(&mut self, editor: &mut Editor) {
+        while !self.pending.is_empty() {
+            match self.updates.recv() {
+                Ok(update) => self.show(editor, update),
+                Err(_) => self.pending.clear(),
+            }
+        }
+    }
+
+    fn show(&mut self, editor: &mut Editor, update: Update) {
+        self.pending.remove(&update.id);
+        if let Some(doc) = editor.documents.get_mut(&update.id) {
+            doc.git_status = update.status.and_then(FileStatus::marker);
+            doc.git_hunks = update.diff.hunks.clone();
+            doc.git_unstaged = update.diff.unstaged.clone();
+            doc.git_removed = update.diff.removed.clone();
+        }
+    }
+
+    /// Restore the hunk under the cursor to its committed version
+    pub fn revert_hunk(&mut self, editor: &mut Editor) {
+        let Some(path) = editor.current_doc().path.clone() else {
+            editor.set_status("Not a file", Severity::Error);
+            return;
+        };
+        let base = match self.repository(&path).map(|repo| repo.head_text(&path)) {
+            Some(Ok(base)) => base.unwrap_or_default(),
+            Some(Err(e)) => {
+                editor.set_status(e.to_string(), Severity::Error);
+                return;
+            }
+            None => {
+                editor.set_status("Not in a git repository", Severity::Error);
+                return;
+            }
+        };
+        if !editor.revert_hunk(&base) {
+            editor.set_status("No change under the cursor", Severity::Info);
+        }
+    }
+
+    /// Stage the hunk under the cursor, or with `stage` false, take it out
+    /// of the index again
+    pub fn stage_hunk(&mut self, editor: &mut Editor, stage: bool) {
+        let Some(path) = editor.current_doc().path.clone() else {
+            editor.set_status("Not a file", Severity::Error);
+            return;
+        };
+        let Some(repo) = self.repository(&path) else {
+            editor.set_status("Not in a git repository", Severity::Error);
+            return;
+        };
+        let doc = editor.current_doc();
+        let line = doc.rope.char_to_line(doc.selection(editor.tree.focus()).cursor());
+        let text = doc.text();
+        let result = if stage {
+            repo.stage_hunk(&path, &text, line)
+        } else {
+            repo.unstage_hunk(&path, &text, line)
+        };
+        match result {
+            Ok(true) => {
+                // Show the new state right away
+                self.synced.remove(&doc.id);
+                self.sync_now(editor);
+            }
+            Ok(false) if stage => editor.set_status("No unstaged change under the cursor", Severity::Info),
+            Ok(false) => editor.set_status("No staged change under the cursor", Severity::Info),
+            Err(e) => editor.set_status(e.to_string(), Severity::Error),
+        }
+    }
+
+    /// The repository a file is in, opened once per directory and shared by
+    /// directories of the same working tree
+    fn repository(&mut self, path: &Path) -> Option<Arc<Repository>> {
+        let dir = path.parent()?.to_path_buf();
+        if let Some(repo) = self.repos.get(&dir) {
+            return repo.clone();
+        }
+        let repo = Repository::open(path).map(|repo| {
+            self.repos
+                .values()
+                .flatten()
+                .find(|open| open.workdir() == repo.workdir())
+                .cloned()
+                .unwrap_or_else(|| Arc::new(repo))
+        });
+        self.repos.insert(dir, repo.clone());
+        repo
+    }
+}
+
+/// Run jobs until the manager is dropped
+fn work(jobs: mpsc::Receiver<Job>, updates: mpsc::Sender<Update>) {
+    for job in jobs {
+        match job {
+            Job::Diff { id, repo, path, text } => {
+                let status = repo.status(&path).ok();
+                let diff = match status {
+                    Some(FileStatus::Ignored) => None,
+                    _ => repo.diff(&path, &text.to_string()).ok(),
+                };
+                let diff = diff.unwrap_or_default();
+                if updates.send(Update { id, status, diff }).is_err() {
+                    return;
+                }
+            }
+            Job::Forget { repo, path } => repo.forget(&path),
+        }
+    }
+}
+
+impl Default for GitManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Whether `.gitignore` leaves a file or directory out, for the project
+/// scans of lite-view. Each repository is opened once, by the first path
+/// checked in it.
+pub fn ignore_filter() -> IgnoreFilter {
+    let repos: Mutex<Vec<Arc<Repository>>> = Mutex::default();
+    let outside: Mutex<HashSet<PathBuf>> = Mutex::default();
+    Arc::new(move |path: &Path| {
+        let Ok(path) = std::path::absolute(path) else {
+            return false;
+        };
+        let dir = path.parent().unwrap_or(&path);
+        let mut repos = repos.lock().expect("ignore filter lock");
+        let known = repos.iter().filter(|repo| dir.starts_with(repo.workdir()));
+        let repo = match known.max_by_key(|repo| repo.workdir().as_os_str().len()) {
+            Some(repo) => repo.clone(),
+            None if outside.lock().expect("ignore filter lock").contains(dir) => return false,
+            None => match Repository::open(dir) {
+                Some(repo) => {
+                    let repo = Arc::new(repo);
+                    repos.push(repo.clone());
+                    repo
+                }
+                None => {
+                    outside.lock().expect("ignore filter lock").insert(dir.to_path_buf());
+                    return false;
+                }
+            },
+        };
+        drop(repos);
+        repo.is_ignored(&path)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_ignore_filter() {
+        let dir = std::env::temp_dir().join(format!("lite-term-ignore-{}", std::process::id()));
+        fs::create_dir_all(dir.join("repo/build")).unwrap();
+        fs::write(dir.join("repo/.gitignore"), "build/\n*.log\n").unwrap();
+        git2::Repository::init(dir.join("repo")).unwrap();
+
+        let ignored = ignore_filter();
+        assert!(ignored(&dir.join("repo/build")));
+        assert!(ignored(&dir.join("repo/run.log")));
+        assert!(!ignored(&dir.join("repo/main.rs")));
+        assert!(!ignored(&dir.join("outside.log")));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_sync_marks_changed_lines() {
+        let dir = std::env::temp_dir().join(format!("lite-term-git-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("notes.txt");
+        fs::write(&file, "a\nb\nc\n").unwrap();
+        let git = git2::Repository::init(&dir).unwrap();
+        let mut index = git.index().unwrap();
+        index.add_path(Path::new("notes.txt")).unwrap();
+        let tree = git.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("lite", "lite@example.com").unwrap();
+        git.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[]).unwrap();
+        index.write().unwrap();
+
+        let mut editor = Editor::new();
+        editor.open(file.to_str().unwrap()).unwrap();
+        let mut manager = GitManager::new();
+        manager.sync_now(&mut editor);
+        assert!(editor.current_doc().git_hunks.is_empty());
+
+        // Edits show on the next sync
+        let view_id = editor.tree.focus();
+        let doc = editor.current_doc_mut();
+        doc.apply(&lite_core::Transaction::insert(doc.len_chars(), 2, "new\n"), view_id);
+        manager.sync_now(&mut editor);
+        let hunks = &editor.current_doc().git_hunks;
+        assert_eq!(hunks.len(), 1);
+        assert_eq!((hunks[0].before.clone(), hunks[0].after.clone()), (1..1, 1..2));
+
+        // The status marker follows the file on disk
+        assert_eq!(editor.current_doc().git_status, None);
+        editor.save().unwrap();
+        manager.sync_now(&mut editor);
+        assert_eq!(editor.current_doc().git_status, Some("M"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,307 @@
+//! Searching and replacing across the project: `:grep` lists the lines
+//! matching a regex as `path:line: text`, and `:grep-replace` previews what
+//! a substitution would change in every file, as a diff, and makes the
+//! changes once confirmed. Open buffers are searched as they are, unsaved
+//! changes included. Both read the project on another thread.
+
+use crate::commands::{build_regex, parse_substitute};
+use lite_config::{Action, Config, Key, KeyEvent, Modifier};
+use lite_core::{Change, Rope, Selection, Transaction};
+use lite_view::{todo, workspace, BufferHandler, Document, DocumentId, Editor, Severity};
+use regex::Regex;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const RESULTS_BUFFER_NAME: &str = "search results";
+const PREVIEW_BUFFER_NAME: &str = "replace preview";
+
+/// Most matching lines listed, so a pattern matching everything stays usable
+const MAX_RESULTS: usize = 10_000;
+
+/// List the lines of the project matching a regex
+pub fn grep(editor: &mut Editor, pattern: &str) {
+    if pattern.is_empty() {
+        return editor.set_status("Usage: grep <regex>", Severity::Error);
+    }
+    let regex = match build_regex(pattern, "", "") {
+        Ok(regex) => regex,
+        Err(e) => return editor.set_status(e, Severity::Error),
+    };
+
+    let project = Project::new(editor);
+    let pattern = pattern.to_string();
+    editor.set_status(format!("Searching for {}...", pattern), Severity::Info);
+    editor.spawn_job(move || {
+        let mut results = Vec::new();
+        let mut matched = 0;
+        project.for_each_text(|path, text| {
+            for (i, line) in text.lines().enumerate() {
+                if regex.is_match(line) {
+                    matched += 1;
+                    if results.len() < MAX_RESULTS {
+                        let path = todo::display_path(path);
+                        results.push(format!("{}:{}: {}", path.display(), i + 1, line.trim_end()));
+                    }
+                }
+            }
+        });
+        Box::new(move |editor: &mut Editor| {
+            let text = match matched {
+                0 => format!("No lines match {}\n", pattern),
+                n => format!("{} lines match {}  (Enter to open, q to close)\n\n{}\n", n, pattern, results.join("\n")),
+            };
+            show(editor, RESULTS_BUFFER_NAME, text, Box::new(ResultsHandler));
+            if matched > results.len() {
+                let message = format!("{} lines match, only the first {} are listed", matched, results.len());
+                editor.set_status(message, Severity::Warning);
+            } else {
+                editor.clear_status();
+            }
+        })
+    });
+}
+
+/// Preview a substitution, `/regex/replacement/[i]`, over the project
+pub fn grep_replace(editor: &mut Editor, args: &str) {
+    let Some((pattern, replacement, flags)) = parse_substitute(args) else {
+        return editor.set_status("Usage: grep-replace /regex/replacement/[i]", Severity::Error);
+    };
+    let regex = match build_regex(&pattern, &flags, "i") {
+        Ok(regex) => regex,
+        Err(e) => return editor.set_status(e, Severity::Error),
+    };
+
+    let project = Project::new(editor);
+    editor.set_status(format!("Searching for {}...", pattern), Severity::Info);
+    editor.spawn_job(move || {
+        let mut edits = Vec::new();
+        project.for_each_text(|path, text| {
+            edits.extend(FileEdit::new(&regex, &replacement, path.to_path_buf(), text));
+        });
+        Box::new(move |editor: &mut Editor| preview(editor, &pattern, edits))
+    });
+}
+
+/// Show the changes a project-wide replacement would make
+fn preview(editor: &mut Editor, pattern: &str, edits: Vec<FileEdit>) {
+    if edits.is_empty() {
+        editor.set_status(format!("No lines match {}", pattern), Severity::Error);
+        return editor.ring_bell();
+    }
+    editor.clear_status();
+    let lines: usize = edits.iter().map(|edit| edit.lines.len()).sum();
+    let mut text = format!("Replace {} lines in {} files? (y/n)\n", lines, edits.len());
+    for edit in &edits {
+        text.push_str(&format!("\n--- {}\n", todo::display_path(&edit.path).display()));
+        for (i, old, new) in &edit.lines {
+            text.push_str(&format!("-{}: {}\n+{}: {}\n", i + 1, old, i + 1, new));
+        }
+    }
+    show(editor, PREVIEW_BUFFER_NAME, text, Box::new(PreviewHandler { edits }));
+    editor.current_doc_mut().language = Some("diff".to_string());
+}
+
+/// Show a list in its buffer, replacing an earlier one
+fn show(editor: &mut Editor, name: &str, text: String, handler: Box<dyn BufferHandler>) {
+    if let Some(doc_id) = editor.find_virtual(name) {
+        editor.switch_to_document(doc_id);
+        editor.close_buffer(true);
+    }
+    editor.open_virtual(Document::new_virtual(name, text), Some(handler));
+}
+
+/// What a project search needs from the editor to run on another thread
+struct Project {
+    roots: Vec<PathBuf>,
+    config: Config,
+    /// Text of the open files, by absolute path
+    buffers: HashMap<PathBuf, Rope>,
+}
+
+impl Project {
+    fn new(editor: &Editor) -> Self {
+        let buffers = editor
+            .documents
+            .values()
+            .filter_map(|doc| Some((std::path::absolute(doc.path.as_deref()?).ok()?, doc.rope.clone())))
+            .collect();
+        Self {
+            roots: editor.search_roots(),
+            config: editor.config.clone(),
+            buffers,
+        }
+    }
+
+    /// Call `f` with the text of every file a project search reads, one at
+    /// a time: that of its buffer if it's open, else what's on disk.
+    /// Encrypted and binary files are left out.
+    fn for_each_text(&self, mut f: impl FnMut(&Path, String)) {
+        for path in workspace::text_files(&self.roots, &|_| false) {
+            if self.config.crypt_filter(&path).is_some() {
+                continue;
+            }
+            let buffer = std::path::absolute(&path).ok().and_then(|path| self.buffers.get(&path));
+            let text = match buffer {
+                Some(rope) => rope.to_string(),
+                None => match fs::read_to_string(&path) {
+                    Ok(text) => text,
+                    Err(_) => continue,
+                },
+            };
+            f(&path, text);
+        }
+    }
+}
+
+/// The buffer of a file, if it's open
+fn open_document<'a>(editor: &'a Editor, path: &Path) -> Option<&'a Document> {
+    let path = std::path::absolute(path).ok()?;
+    editor.documents.values().find(|doc| {
+        doc.path
+            .as_deref()
+            .and_then(|doc_path| std::path::absolute(doc_path).ok())
+            .is_some_and(|doc_path| doc_path == path)
+    })
+}
+
+/// What a substitution changes in one file, line by line as `sed` does
+struct FileEdit {
+    path: PathBuf,
+    /// Text the preview was made from, so a file changed since isn't
+    /// overwritten
+    old: String,
+    new: String,
+    /// Changed lines: index, before and after
+    lines: Vec<(usize, String, String)>,
+}
+
+impl FileEdit {
+    fn new(regex: &Regex, replacement: &str, path: PathBuf, old: String) -> Option<FileEdit> {
+        let mut new = String::with_capacity(old.len());
+        let mut lines = Vec::new();
+        for (i, line) in old.split_inclusive('\n').enumerate() {
+            let content = line.trim_end_matches(['\n', '\r']);
+            let replaced = regex.replace_all(content, replacement);
+            if replaced != content {
+                lines.push((i, content.to_string(), replaced.to_string()));
+            }
+            new.push_str(&replaced);
+            new.push_str(&line[content.len()..]);
+        }
+        (!lines.is_empty()).then_some(FileEdit { path, old, new, lines })
+    }
+
+    /// Make the change: to the buffer if the file is open, to be saved with
+    /// the rest of its changes, else to the file on disk. Returns whether it
+    /// went to a buffer.
+    fn apply(&self, editor: &mut Editor) -> Result<bool, String> {
+        let changed_since = || format!("{} changed since the preview", self.path.display());
+        let Some(doc_id) = open_document(editor, &self.path).map(|doc| doc.id) else {
+            let text = fs::read_to_string(&self.path).map_err(|e| e.to_string())?;
+            if text != self.old {
+                return Err(changed_since());
+            }
+            return fs::write(&self.path, &self.new).map(|_| false).map_err(|e| e.to_string());
+        };
+
+        let view_id = editor.tree.focus();
+        let doc = editor.documents.get_mut(&doc_id).expect("the document was just found");
+        if doc.text() != self.old {
+            return Err(changed_since());
+        }
+        // Line by line, so cursors elsewhere stay where they are
+        let changes: Vec<Change> = self
+            .lines
+            .iter()
+            .map(|(i, old, new)| {
+                let start = doc.rope.line_to_char(*i);
+                Change::replace(start, start + old.chars().count(), new.clone())
+            })
+            .collect();
+        if !doc.apply(&Transaction::changes(doc.len_chars(), &changes), view_id) {
+            return Err(format!("{} is read-only", self.path.display()));
+        }
+        Ok(true)
+    }
+}
+
+/// Key handler for the search results
+struct ResultsHandler;
+
+impl BufferHandler for ResultsHandler {
+    fn handle_key(&mut self, editor: &mut Editor, doc_id: DocumentId, key: &KeyEvent) -> bool {
+        match (&key.key, key.modifiers) {
+            (Key::Enter, Modifier::NONE) => {
+                // Lines start with a file reference
+                let view_id = editor.tree.focus();
+                if let Some(doc) = editor.documents.get_mut(&doc_id) {
+                    let line = doc.rope.char_to_line(doc.selection(view_id).cursor());
+                    let start = doc.rope.line_to_char(line);
+                    doc.set_selection(view_id, Selection::point(start));
+                }
+                crate::execute_action(editor, &Action::OpenLink);
+                true
+            }
+            (Key::Char('q'), Modifier::NONE) | (Key::Escape, Modifier::NONE) => {
+                editor.close_buffer(true);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Key handler for the preview of a project-wide replacement
+struct PreviewHandler {
+    edits: Vec<FileEdit>,
+}
+
+impl BufferHandler for PreviewHandler {
+    fn handle_key(&mut self, editor: &mut Editor, _doc_id: DocumentId, key: &KeyEvent) -> bool {
+        let confirmed = match key.key {
+            Key::Char('y') | Key::Char('Y') => true,
+            Key::Char('n') | Key::Char('N') | Key::Char('q') | Key::Escape => false,
+            _ => return false,
+        };
+
+        editor.close_buffer(true);
+        if !confirmed {
+            return true;
+        }
+        let mut errors = Vec::new();
+        let mut unsaved = 0;
+        for edit in &self.edits {
+            match edit.apply(editor) {
+                Ok(in_buffer) => unsaved += in_buffer as usize,
+                Err(e) => errors.push(e),
+            }
+        }
+        let done = self.edits.len() - errors.len();
+        let mut message = format!("Replaced in {} files", done);
+        if unsaved > 0 {
+            message.push_str(&format!(", {} of them open and not saved yet", unsaved));
+        }
+        match errors.first() {
+            None => editor.set_status(message, Severity::Info),
+            Some(e) => editor.set_status(format!("{}, {} failed: {}", message, errors.len(), e), Severity::Error),
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_edit() {
+        let regex = Regex::new(r"(\w+)\.len\(\)").unwrap();
+        let old = "a.len()\r\nb\nc.len() + d.len()".to_string();
+        let edit = FileEdit::new(&regex, "len($1)", PathBuf::from("x"), old).unwrap();
+        assert_eq!(edit.new, "len(a)\r\nb\nlen(c) + len(d)");
+        let lines: Vec<_> = edit.lines.iter().map(|(i, _, new)| (*i, new.as_str())).collect();
+        assert_eq!(lines, vec![(0, "len(a)"), (2, "len(c) + len(d)")]);
+        assert!(FileEdit::new(&regex, "$0", PathBuf::from("x"), "a.len()".to_string()).is_none());
+    }
+}
@@ -0,0 +1,349 @@
+//! Running without a terminal, driven by JSON-RPC 2.0 requests one per
+//! line, for scripts and tests of the whole editor.
+//!
+//! ```text
+//! {"jsonrpc": "2.0", "id": 1, "method": "open", "params": {"path": "main.rs"}}
+//! {"jsonrpc": "2.0", "id": 2, "method": "keys", "params": {"keys": ["Ctrl+End", "Enter"]}}
+//! {"jsonrpc": "2.0", "id": 3, "method": "type", "params": {"text": "fn main() {}"}}
+//! {"jsonrpc": "2.0", "id": 4, "method": "buffer"}
+//! ```
+//!
+//! Keys go through the keymap and buffer handlers as typed ones do, but
+//! there are no prompts: `command` runs what they would. No language
+//! servers are started, so `diagnostics` only has those of the editor's own
+//! checks, such as `json-format`.
+
+use crate::{execute_action, execute_command, insert_text, GitManager};
+use lite_config::{Action, Key, KeyEvent};
+use lite_core::RopeExt;
+use lite_view::{DiagnosticSeverity, Editor, Rect, Severity};
+use serde_json::{json, Value};
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+/// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// An editor without a terminal
+pub struct Headless {
+    editor: Editor,
+    git: GitManager,
+}
+
+impl Headless {
+    pub fn new() -> Self {
+        let mut editor = Editor::new();
+        if let Ok(languages) = lite_config::Languages::load() {
+            editor.config.languages = languages;
+        }
+        crate::project::load(&mut editor, &std::env::current_dir().unwrap_or_default());
+        editor.resize(Rect::new(0, 1, 80, 20));
+        Self {
+            editor,
+            git: GitManager::new(),
+        }
+    }
+
+    /// Open a file
+    pub fn open(&mut self, path: &str) -> io::Result<()> {
+        self.editor.open(path)?;
+        Ok(())
+    }
+
+    /// Answer requests read from `input` until it ends or `quit` is asked
+    pub fn serve(&mut self, input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(response) = self.handle(&line) {
+                writeln!(output, "{}", response)?;
+                output.flush()?;
+            }
+            if self.editor.should_quit {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Listen on a Unix socket, answering one connection at a time. A socket
+    /// left at the path by an earlier run is replaced; any other file there
+    /// is an error. A client whose connection fails is reported on stderr
+    /// and the next one is served; the socket is removed when serving ends.
+    #[cfg(unix)]
+    pub fn serve_socket(&mut self, path: &Path) -> io::Result<()> {
+        use std::os::unix::fs::FileTypeExt;
+        match std::fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.file_type().is_socket() => std::fs::remove_file(path)?,
+            Ok(_) => {
+                let message = format!("{} exists and isn't a socket", path.display());
+                return Err(io::Error::new(io::ErrorKind::AlreadyExists, message));
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        let listener = std::os::unix::net::UnixListener::bind(path)?;
+        for stream in listener.incoming() {
+            let served = stream.and_then(|stream| self.serve(io::BufReader::new(stream.try_clone()?), stream));
+            if let Err(e) = served {
+                eprintln!("Client of {}: {}", path.display(), e);
+            }
+            if self.editor.should_quit {
+                break;
+            }
+        }
+        std::fs::remove_file(path)
+    }
+
+    /// The response to a request line; `None` for notifications, which
+    /// have no id
+    pub fn handle(&mut self, line: &str) -> Option<String> {
+        let request: Value = match serde_json::from_str(line) {
+            Ok(request) => request,
+            Err(e) => return Some(error(Value::Null, PARSE_ERROR, &e.to_string())),
+        };
+        let id = request.get("id").cloned();
+        let Some(method) = request.get("method").and_then(Value::as_str) else {
+            return Some(error(id.unwrap_or(Value::Null), INVALID_REQUEST, "missing method"));
+        };
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+        let result = self.call(method, &params);
+        // Clients see the result of what they asked for
+        self.editor.wait_jobs();
+        self.git.sync(&mut self.editor);
+        self.editor.follow_changes();
+
+        let id = id?;
+        Some(match result {
+            Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}).to_string(),
+            Err((code, message)) => error(id, code, &message),
+        })
+    }
+
+    fn call(&mut self, method: &str, params: &Value) -> Result<Value, (i64, String)> {
+        let param = |name: &str| {
+            params
+                .get(name)
+                .and_then(Value::as_str)
+                .ok_or((INVALID_PARAMS, format!("missing string parameter {}", name)))
+        };
+        match method {
+            "open" => {
+                let path = param("path")?;
+                self.editor.open(path).map_err(|e| (INVALID_PARAMS, e.to_string()))?;
+                Ok(self.buffer())
+            }
+            "keys" => {
+                let keys = params
+                    .get("keys")
+                    .and_then(Value::as_array)
+                    .ok_or((INVALID_PARAMS, "missing array parameter keys".to_string()))?;
+                for key in keys {
+                    let key: KeyEvent = key
+                        .as_str()
+                        .ok_or((INVALID_PARAMS, format!("not a key: {}", key)))?
+                        .parse()
+                        .map_err(|e| (INVALID_PARAMS, e))?;
+                    self.press(&key);
+                }
+                Ok(self.buffer())
+            }
+            "type" => {
+                self.editor.clear_status();
+                insert_text(&mut self.editor, param("text")?);
+                Ok(self.buffer())
+            }
+            "action" => {
+                let action: Action = serde_json::from_value(params.get("action").cloned().unwrap_or_default())
+                    .map_err(|e| (INVALID_PARAMS, e.to_string()))?;
+                self.editor.clear_status();
+                execute_action(&mut self.editor, &action);
+                Ok(self.buffer())
+            }
+            "command" => {
+                self.editor.clear_status();
+                execute_command(&mut self.editor, param("command")?);
+                Ok(self.buffer())
+            }
+            "buffer" => Ok(self.buffer()),
+            "buffers" => {
+                let current = self.editor.current_doc().id;
+                let buffers: Vec<Value> = self
+                    .editor
+                    .buffer_list()
+                    .into_iter()
+                    .map(|(id, name)| json!({"name": name, "current": id == current}))
+                    .collect();
+                Ok(Value::Array(buffers))
+            }
+            "diagnostics" => Ok(self.diagnostics()),
+            "quit" => {
+                self.editor.should_quit = true;
+                Ok(Value::Null)
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("unknown method {}", method))),
+        }
+    }
+
+    /// Handle a key as the application would without its prompts and popups
+    fn press(&mut self, key: &KeyEvent) {
+        self.editor.clear_status();
+        if self.editor.handle_buffer_key(key) {
+            return;
+        }
+        match self.editor.keymap.get(key).cloned() {
+            Some(action) => execute_action(&mut self.editor, &action),
+            None => {
+                if let Key::Char(c) = key.key {
+                    if key.modifiers.ctrl == key.modifiers.alt {
+                        execute_action(&mut self.editor, &Action::InsertChar(c));
+                    }
+                }
+            }
+        }
+    }
+
+    /// The current buffer: its text, cursor and the status message
+    fn buffer(&self) -> Value {
+        let doc = self.editor.current_doc();
+        let pos = doc.rope.char_to_position(doc.selection(self.editor.tree.focus()).cursor());
+        let status = self.editor.status_msg.as_ref().map(|(message, severity)| {
+            let severity = match severity {
+                Severity::Info => "info",
+                Severity::Warning => "warning",
+                Severity::Error => "error",
+            };
+            json!({"message": message, "severity": severity})
+        });
+        json!({
+            "name": doc.name(),
+            "path": doc.path.as_ref().map(|path| path.display().to_string()),
+            "language": doc.language,
+            "modified": doc.modified,
+            "text": doc.text(),
+            "cursor": {"line": pos.line, "col": pos.col},
+            "status": status,
+        })
+    }
+
+    /// Diagnostics of the current buffer, with 0-based positions. Without
+    /// language servers these are only the editor's own.
+    fn diagnostics(&self) -> Value {
+        let doc = self.editor.current_doc();
+        let diagnostics: Vec<Value> = doc
+            .diagnostics
+            .iter()
+            .map(|diagnostic| {
+                let start = doc.rope.char_to_position(diagnostic.range.start.min(doc.len_chars()));
+                let severity = match diagnostic.severity {
+                    DiagnosticSeverity::Error => "error",
+                    DiagnosticSeverity::Warning => "warning",
+                    DiagnosticSeverity::Info => "info",
+                    DiagnosticSeverity::Hint => "hint",
+                };
+                json!({
+                    "line": start.line,
+                    "col": start.col,
+                    "severity": severity,
+                    "message": diagnostic.message,
+                    "source": diagnostic.source,
+                })
+            })
+            .collect();
+        Value::Array(diagnostics)
+    }
+}
+
+impl Default for Headless {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn error(id: Value, code: i64, message: &str) -> String {
+    json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message}}).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(headless: &mut Headless, method: &str, params: Value) -> Value {
+        let request = json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": params});
+        let response: Value = serde_json::from_str(&headless.handle(&request.to_string()).unwrap()).unwrap();
+        response.get("result").cloned().unwrap_or_else(|| response["error"].clone())
+    }
+
+    #[test]
+    fn test_requests() {
+        let mut headless = Headless::new();
+        call(&mut headless, "type", json!({"text": "hello"}));
+        let buffer = call(&mut headless, "keys", json!({"keys": ["Home", "Enter", "Ctrl+Z"]}));
+        assert_eq!(buffer["text"], "hello");
+        assert_eq!(buffer["cursor"], json!({"line": 0, "col": 0}));
+
+        let buffer = call(&mut headless, "keys", json!({"keys": ["Ctrl+Shift+D", "X"]}));
+        assert_eq!(buffer["text"], "Xhello\nhello");
+        let buffer = call(&mut headless, "action", json!({"action": {"InsertChar": "!"}}));
+        assert_eq!(buffer["text"], "X!hello\nhello");
+
+        // Diagnostics of a check run as a command
+        call(&mut headless, "command", json!({"command": "json-format"}));
+        let diagnostics = call(&mut headless, "diagnostics", Value::Null);
+        assert_eq!(diagnostics[0]["line"], 0);
+        assert_eq!(diagnostics[0]["severity"], "error");
+
+        assert_eq!(call(&mut headless, "keys", json!({"keys": ["Hyper+A"]}))["code"], INVALID_PARAMS);
+        assert_eq!(call(&mut headless, "fly", Value::Null)["code"], METHOD_NOT_FOUND);
+        assert!(headless.handle(r#"{"jsonrpc": "2.0", "method": "buffer"}"#).is_none());
+        assert!(headless.handle("{").unwrap().contains("-32700"));
+
+        let mut output = Vec::new();
+        let input = "{\"jsonrpc\": \"2.0\", \"id\": 7, \"method\": \"quit\"}\n{\"id\": 8, \"method\": \"buffer\"}\n";
+        headless.serve(input.as_bytes(), &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "{\"id\":7,\"jsonrpc\":\"2.0\",\"result\":null}\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_serve_socket_keeps_other_files() {
+        let path = std::env::temp_dir().join(format!("lite-socket-{}", std::process::id()));
+        std::fs::write(&path, "notes").unwrap();
+        let error = Headless::new().serve_socket(&path).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "notes");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_serve_socket_outlives_clients() {
+        use std::os::unix::net::UnixStream;
+        let path = std::env::temp_dir().join(format!("lite-clients-{}", std::process::id()));
+        let client_path = path.clone();
+        let clients = std::thread::spawn(move || {
+            let connect = || loop {
+                if let Ok(stream) = UnixStream::connect(&client_path) {
+                    return stream;
+                }
+                std::thread::sleep(std::time::Duration::from_millis(10));
+            };
+            // Not UTF-8, which ends this client's connection with an error
+            connect().write_all(b"\xff\n").unwrap();
+            let mut stream = connect();
+            stream.write_all(b"{\"jsonrpc\": \"2.0\", \"id\": 1, \"method\": \"quit\"}\n").unwrap();
+            let mut answer = String::new();
+            io::BufReader::new(stream).read_line(&mut answer).unwrap();
+            answer
+        });
+        Headless::new().serve_socket(&path).unwrap();
+        assert!(clients.join().unwrap().contains("\"result\":null"));
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,181 @@
+use crate::commands::COMMANDS;
+use lite_config::{EditorConfig, Key, KeyEvent, Modifier};
+use lite_core::{RopeExt, Selection};
+use lite_view::{BufferHandler, Document, DocumentId, Editor, Severity};
+
+/// Display name of help buffers
+const HELP_BUFFER_NAME: &str = "help";
+
+/// Topics that can be passed to `:help`
+pub const TOPICS: &[(&str, &str)] = &[
+    ("index", "List of help topics"),
+    ("keys", "Key bindings"),
+    ("commands", "Command prompt commands"),
+    ("config", "Configuration options"),
+];
+
+/// Open the help browser on a topic
+pub fn open_help(editor: &mut Editor, topic: &str) {
+    let Some(text) = render_topic(editor, topic) else {
+        editor.set_status(format!("No help for: {}", topic), Severity::Error);
+        return;
+    };
+
+    let previous = editor.find_virtual(HELP_BUFFER_NAME);
+
+    let doc = Document::new_virtual(HELP_BUFFER_NAME, text);
+    let handler = HelpHandler {
+        topic: topic.to_string(),
+        back: Vec::new(),
+    };
+    editor.open_virtual(doc, Some(Box::new(handler)));
+
+    // Replace an earlier help buffer rather than piling them up
+    if let Some(previous) = previous {
+        editor.close_document(previous, true);
+    }
+}
+
+/// Generate the text of a help topic
+fn render_topic(editor: &Editor, topic: &str) -> Option<String> {
+    let mut out = String::new();
+    match topic {
+        "index" => {
+            out.push_str("lite help\n\n");
+            out.push_str("Move the cursor onto a |link| and press Enter to follow it.\n");
+            out.push_str("Backspace goes back, q or Esc closes help.\n\n");
+            out.push_str("Topics:\n");
+            for (name, desc) in TOPICS.iter().skip(1) {
+                out.push_str(&format!("  {:<14}{}\n", format!("|{}|", name), desc));
+            }
+        }
+        "keys" => {
+            out.push_str("Key bindings                                          |index|\n\n");
+            let mut bindings: Vec<(String, String)> = editor
+                .keymap
+                .bindings()
+                .map(|(key, action)| (format!("{:?}", action), key.to_string()))
+                .collect();
+            bindings.sort();
+            for (action, key) in bindings {
+                out.push_str(&format!("  {:<24}{}\n", key, action));
+            }
+        }
+        "commands" => {
+            out.push_str("Commands                                              |index|\n\n");
+            out.push_str("Open the command prompt with Ctrl+Shift+P.\n\n");
+            let ranged: Vec<&str> = COMMANDS.iter().filter(|c| c.ranged).filter_map(|c| c.names.last().copied()).collect();
+            out.push_str(&format!(
+                "Lines before {} are a range, as in :10,20d, :5,30> or :%s/a/b/g: numbers,\n\
+                 . (cursor), $ (last line), '< and '> (selection) with +n or -n, or %\n\
+                 for all. A range alone goes to its last line.\n\n",
+                ranged.join(", ")
+            ));
+            for command in COMMANDS {
+                let usage = format!("{} {}", command.names.join(", "), command.args);
+                out.push_str(&format!("  {:<24}{}\n", usage.trim_end(), command.description));
+            }
+        }
+        "config" => {
+            out.push_str("Configuration options                                 |index|\n\n");
+            out.push_str("Set in the [editor] table of the config file.\n\n");
+            for (name, desc) in EditorConfig::OPTIONS {
+                let value = editor.config.editor.get(name).unwrap_or_default();
+                out.push_str(&format!("  {:<24}{} (current: {})\n", name, desc, value));
+            }
+        }
+        _ => return None,
+    }
+    Some(out)
+}
+
+/// Find the `|link|` under the cursor, or the first one on the line
+fn link_at(line: &str, col: usize) -> Option<String> {
+    let bars: Vec<usize> = line
+        .char_indices()
+        .filter(|(_, c)| *c == '|')
+        .map(|(i, _)| line[..i].chars().count())
+        .collect();
+
+    let links: Vec<(usize, usize)> = bars.chunks_exact(2).map(|p| (p[0], p[1])).collect();
+    let (start, end) = links
+        .iter()
+        .find(|(start, end)| col >= *start && col <= *end)
+        .or_else(|| links.first())
+        .copied()?;
+
+    let link: String = line.chars().skip(start + 1).take(end - start - 1).collect();
+    (!link.is_empty()).then_some(link)
+}
+
+/// Key handler for help buffers: follows links and keeps a back stack
+struct HelpHandler {
+    topic: String,
+    back: Vec<String>,
+}
+
+impl HelpHandler {
+    fn show(&mut self, editor: &mut Editor, doc_id: DocumentId, topic: String) -> bool {
+        let Some(text) = render_topic(editor, &topic) else {
+            editor.set_status(format!("No help for: {}", topic), Severity::Error);
+            return false;
+        };
+
+        let view_id = editor.tree.focus();
+        if let Some(doc) = editor.documents.get_mut(&doc_id) {
+            doc.set_text(text);
+            doc.set_selection(view_id, Selection::point(0));
+        }
+        editor.current_view_mut().scroll_y = 0;
+        self.topic = topic;
+        true
+    }
+}
+
+impl BufferHandler for HelpHandler {
+    fn handle_key(&mut self, editor: &mut Editor, doc_id: DocumentId, key: &KeyEvent) -> bool {
+        match (&key.key, key.modifiers) {
+            (Key::Enter, Modifier::NONE) => {
+                let view_id = editor.tree.focus();
+                let Some(doc) = editor.documents.get(&doc_id) else {
+                    return false;
+                };
+                let pos = doc.rope.char_to_position(doc.selection(view_id).cursor());
+                let line: String = doc.rope.line(pos.line).chars().collect();
+
+                if let Some(topic) = link_at(&line, pos.col) {
+                    let previous = self.topic.clone();
+                    if self.show(editor, doc_id, topic) {
+                        self.back.push(previous);
+                    }
+                }
+                true
+            }
+            (Key::Backspace, Modifier::NONE) | (Key::Left, Modifier::ALT) => {
+                if let Some(topic) = self.back.pop() {
+                    self.show(editor, doc_id, topic);
+                }
+                true
+            }
+            (Key::Char('q'), Modifier::NONE) | (Key::Escape, Modifier::NONE) => {
+                editor.close_buffer(true);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_link_at() {
+        let line = "  |keys|       Key bindings and |config|";
+        assert_eq!(link_at(line, 4).as_deref(), Some("keys"));
+        assert_eq!(link_at(line, 35).as_deref(), Some("config"));
+        assert_eq!(link_at(line, 20).as_deref(), Some("keys"));
+        assert_eq!(link_at("no links here", 0), None);
+    }
+}
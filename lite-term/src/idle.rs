@@ -0,0 +1,96 @@
+//! Deferred work that runs only while the user is idle, one task per tick,
+//! so typing never waits on it
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// How long without input before the user counts as idle
+const IDLE_DELAY: Duration = Duration::from_secs(1);
+
+/// Work that can wait for an idle moment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdleTask {
+    /// Drop what documents kept for text they no longer have, and release
+    /// memory the undo histories and change logs no longer use
+    CompactHistory,
+    /// Highlight the documents whose text changed since they were drawn,
+    /// those on screen first, so the next frame or switch finds them ready
+    PreparseHighlights,
+    /// Keep the recent files for the next session, should this one end
+    /// without saying goodbye
+    SaveRecentFiles,
+    /// Ask whether a newer release exists
+    CheckUpdate,
+}
+
+/// Queue of idle tasks and when the user last did something
+#[derive(Debug)]
+pub struct IdleScheduler {
+    last_input: Instant,
+    /// Waiting for the next idle moment, in the order they run
+    pending: VecDeque<IdleTask>,
+}
+
+impl IdleScheduler {
+    pub fn new() -> Self {
+        Self {
+            last_input: Instant::now(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Note user input; idle tasks wait until `IDLE_DELAY` after it
+    pub fn input(&mut self, now: Instant) {
+        self.last_input = now;
+    }
+
+    /// Queue a task unless it's queued already
+    pub fn schedule(&mut self, task: IdleTask) {
+        if !self.pending.contains(&task) {
+            self.pending.push_back(task);
+        }
+    }
+
+    pub fn is_idle(&self, now: Instant) -> bool {
+        now.saturating_duration_since(self.last_input) >= IDLE_DELAY
+    }
+
+    /// The next task to run, if the user is idle
+    pub fn next(&mut self, now: Instant) -> Option<IdleTask> {
+        if self.is_idle(now) {
+            self.pending.pop_front()
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for IdleScheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tasks_wait_for_idle() {
+        let mut idle = IdleScheduler::new();
+        let start = Instant::now();
+        idle.input(start);
+        idle.schedule(IdleTask::CheckUpdate);
+        idle.schedule(IdleTask::CompactHistory);
+        idle.schedule(IdleTask::CheckUpdate);
+        assert_eq!(idle.next(start + Duration::from_millis(200)), None);
+
+        // One task per call, in the order they were queued
+        let later = start + IDLE_DELAY;
+        assert_eq!(idle.next(later), Some(IdleTask::CheckUpdate));
+        idle.input(later);
+        assert_eq!(idle.next(later), None);
+        assert_eq!(idle.next(later + IDLE_DELAY), Some(IdleTask::CompactHistory));
+        assert_eq!(idle.next(later + IDLE_DELAY), None);
+    }
+}
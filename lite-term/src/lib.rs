@@ -1,9 +1,40 @@
 //! Terminal application for lite editor
 
 mod application;
+mod calc;
 mod commands;
+mod commit;
+mod directory;
 mod event;
+mod expand;
+mod file_tree;
+mod frontend;
+mod git;
+mod grep;
+mod headless;
+mod help;
+mod idle;
+mod line_range;
+mod lsp;
+mod markdown;
+mod metrics;
+mod number;
+mod pickers;
+mod project;
+mod prompt_completion;
+mod prompt_history;
+mod recent;
+mod record;
+mod todo;
+mod update;
+mod wrap;
 
 pub use application::Application;
-pub use commands::execute_action;
+pub use commands::{execute_action, execute_command, insert_text};
 pub use event::{Event, EventHandler};
+pub use frontend::{Frontend, TerminalFrontend};
+pub use git::GitManager;
+pub use headless::Headless;
+pub use idle::{IdleScheduler, IdleTask};
+pub use lsp::LspManager;
+pub use update::{is_newer, latest_release, newer_release, RELEASE_REPO};
@@ -0,0 +1,120 @@
+//! Line ranges before commands, as in `:10,20d`, `:5,30>` and
+//! `:%s/foo/bar/g`. An address is a line number, `.` the cursor's line, `$`
+//! the last line, or `'<` and `'>` the first and last lines of the
+//! selection, followed by any `+n` and `-n`; `%` is every line.
+
+use lite_core::{Rope, Selection};
+use std::ops::Range;
+
+/// Lines the addresses of a range are resolved against
+struct Lines {
+    cursor: usize,
+    last: usize,
+    selected: (usize, usize),
+}
+
+/// Split the range off the start of a command. It's resolved to lines
+/// from 0, the end excluded; a range given backwards is turned around.
+pub fn parse<'a>(input: &'a str, rope: &Rope, selection: &Selection) -> Result<(Option<Range<usize>>, &'a str), String> {
+    if let Some(rest) = input.strip_prefix('%') {
+        return Ok((Some(0..last_line(rope) + 1), rest));
+    }
+
+    let primary = selection.primary();
+    let first = rope.char_to_line(primary.start());
+    // A selection ending at the start of a line doesn't include that line
+    let end = rope.char_to_line(primary.end());
+    let end = match end > first && rope.line_to_char(end) == primary.end() {
+        true => end - 1,
+        false => end,
+    };
+    let lines = Lines {
+        cursor: rope.char_to_line(selection.cursor()),
+        last: last_line(rope),
+        selected: (first, end),
+    };
+
+    let Some((start, rest)) = address(input, &lines)? else {
+        return Ok((None, input));
+    };
+    let (end, rest) = match rest.strip_prefix(',') {
+        Some(rest) => address(rest, &lines)?.ok_or("Invalid range")?,
+        None => (start, rest),
+    };
+    let (start, end) = (start.min(end), start.max(end));
+    Ok((Some(start..end + 1), rest))
+}
+
+/// The line an address at the start of the input stands for, and what
+/// follows it
+fn address<'a>(input: &'a str, lines: &Lines) -> Result<Option<(usize, &'a str)>, String> {
+    let (base, mut rest) = match input {
+        _ if input.starts_with('.') => (Some(lines.cursor as isize), &input[1..]),
+        _ if input.starts_with('$') => (Some(lines.last as isize), &input[1..]),
+        _ if input.starts_with("'<") => (Some(lines.selected.0 as isize), &input[2..]),
+        _ if input.starts_with("'>") => (Some(lines.selected.1 as isize), &input[2..]),
+        _ => match number(input) {
+            // Numbers count from 1
+            Some((n, rest)) => (Some(n as isize - 1), rest),
+            None => (None, input),
+        },
+    };
+
+    // Offsets alone are from the cursor's line
+    let mut line = base.unwrap_or(lines.cursor as isize);
+    let mut offset = false;
+    while let Some(sign) = rest.chars().next().filter(|c| *c == '+' || *c == '-') {
+        let (n, after) = number(&rest[1..]).unwrap_or((1, &rest[1..]));
+        line += if sign == '+' { n as isize } else { -(n as isize) };
+        rest = after;
+        offset = true;
+    }
+    if base.is_none() && !offset {
+        return Ok(None);
+    }
+    match usize::try_from(line) {
+        Ok(line) if line <= lines.last => Ok(Some((line, rest))),
+        _ => Err(format!("No line {}", line + 1)),
+    }
+}
+
+fn number(input: &str) -> Option<(usize, &str)> {
+    let len = input.len() - input.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let n = input[..len].parse().ok()?;
+    Some((n, &input[len..]))
+}
+
+/// The last line, not counting the empty one after a final newline
+fn last_line(rope: &Rope) -> usize {
+    let last = rope.len_lines() - 1;
+    match last > 0 && rope.line(last).len_chars() == 0 {
+        true => last - 1,
+        false => last,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lite_core::Range as Selected;
+
+    #[test]
+    fn test_parse() {
+        let rope = Rope::from("one\ntwo\nthree\nfour\nfive\n");
+        // The cursor on `three`, the selection over `two` and `three`
+        let selection = Selection::single(Selected::new(4, 13));
+        let parse = |input| parse(input, &rope, &selection);
+        assert_eq!(parse("2,4d"), Ok((Some(1..4), "d")));
+        assert_eq!(parse("%s/a/b/g"), Ok((Some(0..5), "s/a/b/g")));
+        assert_eq!(parse(".,$>"), Ok((Some(2..5), ">")));
+        assert_eq!(parse("'<,'>d"), Ok((Some(1..3), "d")));
+        assert_eq!(parse("-,+2"), Ok((Some(1..5), "")));
+        assert_eq!(parse("4,2"), Ok((Some(1..4), "")));
+        assert_eq!(parse("$-1"), Ok((Some(3..4), "")));
+        assert_eq!(parse("w"), Ok((None, "w")));
+        assert_eq!(parse("= 1+1"), Ok((None, "= 1+1")));
+        assert_eq!(parse("6d"), Err("No line 6".to_string()));
+        assert_eq!(parse("0"), Err("No line 0".to_string()));
+        assert_eq!(parse("1,"), Err("Invalid range".to_string()));
+    }
+}
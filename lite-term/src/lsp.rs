@@ -0,0 +1,1116 @@
+//! Language servers for the open documents: starting them, keeping them in
+//! sync with document contents and applying what they report
+
+use lite_core::{ChangeSet, Operation, Rope, RopeExt};
+use lite_lsp::lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Progress as ProgressNotification,
+    PublishDiagnostics,
+};
+use lite_lsp::lsp_types::{self, TextDocumentIdentifier, Uri};
+use lite_lsp::{char_from_lsp, lsp_from_char, path_from_uri, uri_from_path, LspClient};
+use lite_view::{
+    Completion, CompletionItem, Diagnostic, DiagnosticSeverity, Document, DocumentId, Editor, Peek,
+    ServerState, ServerStatus, Severity, Signature, SignatureHelp, TextEdit, ViewId,
+};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// How long quitting waits for servers to shut down
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A server runs per language and project root
+type ServerKey = (String, PathBuf);
+
+/// A document opened on a server
+struct SyncedDocument {
+    server: ServerKey,
+    uri: Uri,
+    /// Document version last sent
+    version: usize,
+    /// Text of that version, which positions of the next changes refer to
+    text: Rope,
+}
+
+/// Work a server reported progress for, such as indexing
+#[derive(Debug, Clone, PartialEq)]
+struct Progress {
+    title: String,
+    message: Option<String>,
+    percentage: Option<u32>,
+}
+
+impl Progress {
+    /// The progress in a few words, e.g. "Indexing 12/40 (30%)"
+    fn describe(&self) -> String {
+        let mut text = self.title.clone();
+        if let Some(message) = &self.message {
+            text = format!("{} {}", text, message);
+        }
+        if let Some(percentage) = self.percentage {
+            text = format!("{} ({}%)", text, percentage);
+        }
+        text
+    }
+}
+
+/// Result of a request, handed back to the main loop
+enum Reply {
+    Completion(Completion),
+    /// Target of goto definition, if the server found one
+    Definition(Option<lsp_types::Location>),
+    /// Target of peek definition, for the cursor at `trigger` in a view
+    Peek {
+        location: Option<lsp_types::Location>,
+        doc_id: DocumentId,
+        view_id: ViewId,
+        trigger: usize,
+    },
+    /// Signatures of the call at the cursor; `manual` if asked for with a key
+    /// rather than by typing
+    SignatureHelp {
+        help: Option<SignatureHelp>,
+        manual: bool,
+    },
+}
+
+/// Language servers of the editor session, one per language and project
+pub struct LspManager {
+    servers: HashMap<ServerKey, Arc<LspClient>>,
+    /// Servers starting or that couldn't be started
+    pending: HashSet<ServerKey>,
+    /// Command names of the servers still starting
+    starting: HashMap<ServerKey, String>,
+    /// Work in progress per server, the latest started last
+    progress: HashMap<ServerKey, Vec<(lsp_types::ProgressToken, Progress)>>,
+    started_tx: mpsc::UnboundedSender<(ServerKey, lite_lsp::Result<LspClient>)>,
+    started_rx: mpsc::UnboundedReceiver<(ServerKey, lite_lsp::Result<LspClient>)>,
+    documents: HashMap<DocumentId, SyncedDocument>,
+    /// Project root of each document, found when its path was first seen
+    roots: HashMap<DocumentId, (PathBuf, PathBuf)>,
+    replies_tx: mpsc::UnboundedSender<lite_lsp::Result<Reply>>,
+    replies_rx: mpsc::UnboundedReceiver<lite_lsp::Result<Reply>>,
+}
+
+impl LspManager {
+    pub fn new() -> Self {
+        let (started_tx, started_rx) = mpsc::unbounded_channel();
+        let (replies_tx, replies_rx) = mpsc::unbounded_channel();
+        Self {
+            servers: HashMap::new(),
+            pending: HashSet::new(),
+            starting: HashMap::new(),
+            progress: HashMap::new(),
+            started_tx,
+            started_rx,
+            documents: HashMap::new(),
+            roots: HashMap::new(),
+            replies_tx,
+            replies_rx,
+        }
+    }
+
+    /// Start servers for new documents, send document changes and apply
+    /// server notifications. Called from the main loop; never blocks.
+    pub fn sync(&mut self, editor: &mut Editor) {
+        while let Ok((key, result)) = self.started_rx.try_recv() {
+            self.starting.remove(&key);
+            match result {
+                Ok(client) => {
+                    self.servers.insert(key, Arc::new(client));
+                }
+                // Servers that aren't installed are simply not used
+                Err(lite_lsp::Error::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => editor.set_status(
+                    format!("Language server for {} failed: {}", key.0, e),
+                    Severity::Warning,
+                ),
+            }
+        }
+
+        // Documents closed in the editor
+        let closed: Vec<DocumentId> = self
+            .documents
+            .keys()
+            .filter(|id| !editor.documents.contains_key(id))
+            .copied()
+            .collect();
+        for doc_id in closed {
+            if let Some(synced) = self.documents.remove(&doc_id) {
+                if let Some(server) = self.servers.get(&synced.server) {
+                    let _ = server.notify::<DidCloseTextDocument>(
+                        lsp_types::DidCloseTextDocumentParams {
+                            text_document: TextDocumentIdentifier { uri: synced.uri },
+                        },
+                    );
+                }
+            }
+        }
+
+        self.roots.retain(|doc_id, _| editor.documents.contains_key(doc_id));
+
+        for doc in editor.documents.values() {
+            self.sync_document(editor, doc);
+        }
+
+        let mut notifications = Vec::new();
+        for (key, server) in &self.servers {
+            while let Some(notification) = server.try_next_notification() {
+                notifications.push((key.clone(), notification));
+            }
+        }
+        for (key, notification) in notifications {
+            if let Some(params) = notification.parse::<PublishDiagnostics>() {
+                self.publish_diagnostics(editor, params);
+            } else if let Some(params) = notification.parse::<ProgressNotification>() {
+                self.update_progress(key, params);
+            }
+        }
+        self.update_status(editor);
+
+        while let Ok(result) = self.replies_rx.try_recv() {
+            match result {
+                Ok(Reply::Completion(completion)) if completion.items.is_empty() => {
+                    editor.set_status("No completions", Severity::Info);
+                }
+                Ok(Reply::Completion(completion)) => {
+                    // Closes again if the cursor moved on while it was computed
+                    editor.completion = Some(completion);
+                    editor.update_completion();
+                }
+                Ok(Reply::Definition(Some(location))) => goto_location(editor, &location),
+                Ok(Reply::Definition(None) | Reply::Peek { location: None, .. }) => {
+                    editor.set_status("No definition found", Severity::Info);
+                }
+                Ok(Reply::Peek {
+                    location: Some(location),
+                    doc_id,
+                    view_id,
+                    trigger,
+                }) => {
+                    peek_location(editor, &location, doc_id, view_id, trigger);
+                    editor.update_peek();
+                }
+                Ok(Reply::SignatureHelp { help: Some(help), .. }) => {
+                    editor.signature_help = Some(help);
+                    editor.update_signature_help();
+                }
+                Ok(Reply::SignatureHelp { help: None, manual }) => {
+                    editor.signature_help = None;
+                    if manual {
+                        editor.set_status("No signature help", Severity::Info);
+                    }
+                }
+                Err(e) => editor.set_status(format!("Language server: {}", e), Severity::Warning),
+            }
+        }
+    }
+
+    /// The server of the current document with the document's URI, after
+    /// sending it the latest text
+    fn current_server(&mut self, editor: &mut Editor) -> Option<(Arc<LspClient>, Uri)> {
+        let server = self.attached_server(editor);
+        if server.is_none() {
+            editor.set_status("No language server for this file", Severity::Info);
+        }
+        server
+    }
+
+    /// Like `current_server`, without telling the user when there is none
+    fn attached_server(&mut self, editor: &mut Editor) -> Option<(Arc<LspClient>, Uri)> {
+        self.sync(editor);
+
+        let doc = editor.current_doc();
+        self.documents
+            .get(&doc.id)
+            .and_then(|synced| Some((self.servers.get(&synced.server)?.clone(), synced.uri.clone())))
+    }
+
+    /// Ask the current document's server for the signatures of the call at
+    /// the cursor. `typed` is the character whose typing triggered it; those
+    /// requests stay quiet when there's no server or nothing to show.
+    pub fn request_signature_help(&mut self, editor: &mut Editor, typed: Option<char>) {
+        let server = match typed {
+            Some(_) => self.attached_server(editor).filter(|(server, _)| {
+                server
+                    .capabilities()
+                    .is_some_and(|capabilities| capabilities.signature_help_provider.is_some())
+            }),
+            None => self.current_server(editor),
+        };
+        let Some((server, uri)) = server else {
+            return;
+        };
+        let view_id = editor.tree.focus();
+        let doc = editor.current_doc();
+        let cursor = doc.selection(view_id).primary().head;
+
+        let params = lsp_types::SignatureHelpParams {
+            context: Some(lsp_types::SignatureHelpContext {
+                trigger_kind: match typed {
+                    Some(_) => lsp_types::SignatureHelpTriggerKind::TRIGGER_CHARACTER,
+                    None => lsp_types::SignatureHelpTriggerKind::INVOKED,
+                },
+                trigger_character: typed.map(String::from),
+                is_retrigger: editor.signature_help.is_some(),
+                active_signature_help: None,
+            }),
+            text_document_position_params: lsp_types::TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri },
+                position: lsp_from_char(&doc.rope, cursor),
+            },
+            work_done_progress_params: Default::default(),
+        };
+        let doc_id = doc.id;
+        let replies = self.replies_tx.clone();
+        tokio::spawn(async move {
+            let response = server.request::<lsp_types::request::SignatureHelpRequest>(params).await;
+            let result = response.map(|response| Reply::SignatureHelp {
+                help: response.and_then(|help| convert_signature_help(doc_id, view_id, cursor, help)),
+                manual: typed.is_none(),
+            });
+            let _ = replies.send(result);
+        });
+    }
+
+    /// Ask the current document's server where the symbol at the cursor is
+    /// defined, and jump there once it answers
+    pub fn goto_definition(&mut self, editor: &mut Editor) {
+        self.request_definition(editor, Reply::Definition);
+    }
+
+    /// Ask the current document's server where the symbol at the cursor is
+    /// defined and show it in a popup under the cursor line
+    pub fn peek_definition(&mut self, editor: &mut Editor) {
+        let view_id = editor.tree.focus();
+        let doc = editor.current_doc();
+        let doc_id = doc.id;
+        let trigger = doc.selection(view_id).primary().head;
+        self.request_definition(editor, move |location| Reply::Peek {
+            location,
+            doc_id,
+            view_id,
+            trigger,
+        });
+    }
+
+    fn request_definition(
+        &mut self,
+        editor: &mut Editor,
+        reply: impl FnOnce(Option<lsp_types::Location>) -> Reply + Send + 'static,
+    ) {
+        let Some((server, uri)) = self.current_server(editor) else {
+            return;
+        };
+        let doc = editor.current_doc();
+        let cursor = doc.selection(editor.tree.focus()).cursor();
+        let params = lsp_types::GotoDefinitionParams {
+            text_document_position_params: lsp_types::TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri },
+                position: lsp_from_char(&doc.rope, cursor),
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        };
+
+        let replies = self.replies_tx.clone();
+        tokio::spawn(async move {
+            let response = server.request::<lsp_types::request::GotoDefinition>(params).await;
+            let result = response.map(|response| {
+                // Several targets (a trait method, say) go to the first
+                let location = match response {
+                    Some(lsp_types::GotoDefinitionResponse::Scalar(location)) => Some(location),
+                    Some(lsp_types::GotoDefinitionResponse::Array(locations)) => {
+                        locations.into_iter().next()
+                    }
+                    Some(lsp_types::GotoDefinitionResponse::Link(links)) => {
+                        links.into_iter().next().map(|link| lsp_types::Location {
+                            uri: link.target_uri,
+                            range: link.target_selection_range,
+                        })
+                    }
+                    None => None,
+                };
+                reply(location)
+            });
+            let _ = replies.send(result);
+        });
+    }
+
+    /// Ask the current document's server for completions at the cursor. The
+    /// menu opens once the response arrives in `sync`.
+    pub fn request_completion(&mut self, editor: &mut Editor) {
+        let Some((server, uri)) = self.current_server(editor) else {
+            return;
+        };
+        let view_id = editor.tree.focus();
+        let doc = editor.current_doc();
+
+        let cursor = doc.selection(view_id).primary().head;
+        let mut start = cursor;
+        while start > 0 && doc.rope.is_word_char(start - 1) {
+            start -= 1;
+        }
+
+        let params = lsp_types::CompletionParams {
+            text_document_position: lsp_types::TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri },
+                position: lsp_from_char(&doc.rope, cursor),
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+            context: None,
+        };
+        let doc_id = doc.id;
+        let rope = doc.rope.clone();
+        let replies = self.replies_tx.clone();
+        tokio::spawn(async move {
+            let response = server.request::<lsp_types::request::Completion>(params).await;
+            let result = response.map(|response| {
+                let items = match response {
+                    Some(lsp_types::CompletionResponse::Array(items)) => items,
+                    Some(lsp_types::CompletionResponse::List(list)) => list.items,
+                    None => Vec::new(),
+                };
+                let items = items
+                    .into_iter()
+                    .map(|item| convert_completion_item(&rope, start, cursor, item))
+                    .collect();
+                Reply::Completion(Completion::new(doc_id, view_id, cursor, start, items))
+            });
+            let _ = replies.send(result);
+        });
+    }
+
+    /// Open, update or start the server for one document
+    fn sync_document(&mut self, editor: &Editor, doc: &Document) {
+        // Plaintext of encrypted files isn't handed to other programs
+        if doc.is_virtual() || doc.crypt.is_some() {
+            return;
+        }
+        let (Some(language), Some(path)) = (doc.language.as_deref(), doc.path.as_deref()) else {
+            return;
+        };
+
+        // Looking for the root touches the file system, so it's done once per path
+        let root = match self.roots.get(&doc.id) {
+            Some((root_of, root)) if root_of == path => root.clone(),
+            _ => {
+                let root = editor.workspace_root(doc.id);
+                self.roots.insert(doc.id, (path.to_path_buf(), root.clone()));
+                root
+            }
+        };
+        let key = (language.to_string(), root);
+
+        let Some(server) = self.servers.get(&key) else {
+            if !self.pending.contains(&key) {
+                self.start_server(editor, key);
+            }
+            return;
+        };
+
+        match self.documents.get_mut(&doc.id) {
+            Some(synced) if synced.version != doc.version() => {
+                // Only the edits when the server takes them and the change
+                // log still reaches back to the version it has
+                let content_changes = doc
+                    .changes_since(synced.version)
+                    .filter(|_| syncs_incrementally(server))
+                    .map(|changes| content_changes(&synced.text, &changes))
+                    .unwrap_or_else(|| {
+                        vec![lsp_types::TextDocumentContentChangeEvent {
+                            range: None,
+                            range_length: None,
+                            text: doc.text(),
+                        }]
+                    });
+                synced.version = doc.version();
+                synced.text = doc.rope.clone();
+                let _ = server.notify::<DidChangeTextDocument>(lsp_types::DidChangeTextDocumentParams {
+                    text_document: lsp_types::VersionedTextDocumentIdentifier {
+                        uri: synced.uri.clone(),
+                        version: doc.version() as i32,
+                    },
+                    content_changes,
+                });
+            }
+            Some(_) => {}
+            None => {
+                let Some(uri) = absolute(path).and_then(|path| uri_from_path(&path).ok()) else {
+                    return;
+                };
+                let _ = server.notify::<DidOpenTextDocument>(lsp_types::DidOpenTextDocumentParams {
+                    text_document: lsp_types::TextDocumentItem {
+                        uri: uri.clone(),
+                        language_id: language.to_string(),
+                        version: doc.version() as i32,
+                        text: doc.text(),
+                    },
+                });
+                self.documents.insert(
+                    doc.id,
+                    SyncedDocument {
+                        server: key,
+                        uri,
+                        version: doc.version(),
+                        text: doc.rope.clone(),
+                    },
+                );
+            }
+        }
+    }
+
+    /// Spawn and initialize a language's server for a project in the background
+    fn start_server(&mut self, editor: &Editor, key: ServerKey) {
+        let Some(config) = editor.config.languages.get(&key.0) else {
+            return;
+        };
+        let Some(command) = config.command.clone() else {
+            return;
+        };
+        self.pending.insert(key.clone());
+        self.starting.insert(key.clone(), command.clone());
+
+        let root = key.1.clone();
+        let args = config.args.clone();
+        let options = config
+            .initialization_options
+            .as_ref()
+            .and_then(|options| serde_json::to_value(options).ok());
+        let started = self.started_tx.clone();
+        tokio::spawn(async move {
+            let result = async {
+                let mut client = LspClient::start(&command, &args, Some(&root))?;
+                client.initialize(Some(&root), options).await?;
+                Ok(client)
+            };
+            let _ = started.send((key, result.await));
+        });
+    }
+
+    /// Track the begin, report and end notifications of a server's work
+    fn update_progress(&mut self, key: ServerKey, params: lsp_types::ProgressParams) {
+        let lsp_types::ProgressParamsValue::WorkDone(work) = params.value;
+        let progress = self.progress.entry(key).or_default();
+        match work {
+            lsp_types::WorkDoneProgress::Begin(begin) => {
+                progress.retain(|(token, _)| *token != params.token);
+                progress.push((
+                    params.token,
+                    Progress {
+                        title: begin.title,
+                        message: begin.message,
+                        percentage: begin.percentage,
+                    },
+                ));
+            }
+            lsp_types::WorkDoneProgress::Report(report) => {
+                if let Some((_, work)) = progress.iter_mut().find(|(token, _)| *token == params.token) {
+                    // Left out fields keep their previous values
+                    work.message = report.message.or(work.message.take());
+                    work.percentage = report.percentage.or(work.percentage);
+                }
+            }
+            lsp_types::WorkDoneProgress::End(_) => {
+                progress.retain(|(token, _)| *token != params.token);
+            }
+        }
+    }
+
+    /// Tell each document what its server is doing
+    fn update_status(&self, editor: &mut Editor) {
+        for doc in editor.documents.values_mut() {
+            let key = match self.documents.get(&doc.id) {
+                Some(synced) => Some(synced.server.clone()),
+                None => self
+                    .roots
+                    .get(&doc.id)
+                    .zip(doc.language.as_ref())
+                    .map(|((_, root), language)| (language.clone(), root.clone())),
+            };
+            doc.server = key.and_then(|key| self.server_status(&key));
+        }
+    }
+
+    /// What a server is doing; `None` if there's no server or it couldn't be started
+    fn server_status(&self, key: &ServerKey) -> Option<ServerStatus> {
+        // Commands may be given as paths; their file name is enough here
+        let name = |command: &str| {
+            let path = Path::new(command);
+            path.file_name().unwrap_or(path.as_os_str()).to_string_lossy().into_owned()
+        };
+        if let Some(command) = self.starting.get(key) {
+            return Some(ServerStatus {
+                name: name(command),
+                state: ServerState::Starting,
+            });
+        }
+        let server = self.servers.get(key)?;
+        let state = match self.progress.get(key).and_then(|progress| progress.last()) {
+            Some((_, progress)) => ServerState::Busy(progress.describe()),
+            None => ServerState::Ready,
+        };
+        Some(ServerStatus {
+            name: name(server.name()),
+            state,
+        })
+    }
+
+    /// Replace a document's diagnostics with those a server published
+    fn publish_diagnostics(&self, editor: &mut Editor, params: lsp_types::PublishDiagnosticsParams) {
+        // Servers may escape URIs differently, so compare paths as well
+        let path = path_from_uri(&params.uri);
+        let Some((doc_id, synced)) = self.documents.iter().find(|(_, synced)| {
+            synced.uri == params.uri || (path.is_some() && path_from_uri(&synced.uri) == path)
+        }) else {
+            return;
+        };
+        let Some(doc) = editor.documents.get_mut(doc_id) else {
+            return;
+        };
+        // Positions of diagnostics for older versions no longer match the text
+        if params.version.is_some_and(|version| version as usize != synced.version) {
+            return;
+        }
+
+        doc.diagnostics = convert_diagnostics(doc, params.diagnostics);
+    }
+
+    /// Ask every server to shut down, giving up after a short while
+    pub async fn shutdown(&self) {
+        for server in self.servers.values() {
+            let _ = tokio::time::timeout(SHUTDOWN_TIMEOUT, server.shutdown()).await;
+        }
+    }
+}
+
+impl Default for LspManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Absolute form of a document path, as servers expect
+fn absolute(path: &Path) -> Option<PathBuf> {
+    std::path::absolute(path).ok()
+}
+
+/// Show a location, switching to its document if it's open already and
+/// opening it otherwise
+fn goto_location(editor: &mut Editor, location: &lsp_types::Location) {
+    let Some(path) = path_from_uri(&location.uri) else {
+        editor.set_status("Definition is not in a file", Severity::Warning);
+        return;
+    };
+
+    // Documents may be open under a relative path
+    let open = editor
+        .documents
+        .values()
+        .find(|doc| doc.path.as_deref().and_then(absolute).as_ref() == Some(&path))
+        .map(|doc| doc.id);
+    match open {
+        Some(doc_id) => editor.switch_to_document(doc_id),
+        None => {
+            let cwd = std::env::current_dir().unwrap_or_default();
+            let path = path.strip_prefix(&cwd).unwrap_or(&path).to_path_buf();
+            if let Err(e) = editor.open(path) {
+                editor.set_status(format!("Cannot open definition: {}", e), Severity::Error);
+                return;
+            }
+        }
+    }
+
+    let view_id = editor.tree.focus();
+    let doc = editor.current_doc_mut();
+    let pos = char_from_lsp(&doc.rope, location.range.start);
+    doc.set_selection(view_id, lite_core::Selection::point(pos));
+    editor.ensure_cursor_visible();
+}
+
+/// Show the lines around a location in the peek popup, from its document
+/// if it's open and from disk otherwise
+fn peek_location(
+    editor: &mut Editor,
+    location: &lsp_types::Location,
+    doc_id: DocumentId,
+    view_id: ViewId,
+    trigger: usize,
+) {
+    let Some(path) = path_from_uri(&location.uri) else {
+        editor.set_status("Definition is not in a file", Severity::Warning);
+        return;
+    };
+    let open = editor
+        .documents
+        .values()
+        .find(|doc| doc.path.as_deref().and_then(absolute).as_ref() == Some(&path));
+    let text = match open {
+        Some(doc) => doc.rope.clone(),
+        None => match std::fs::read_to_string(&path) {
+            Ok(text) => Rope::from_str(&text),
+            Err(e) => {
+                editor.set_status(format!("Cannot read definition: {}", e), Severity::Error);
+                return;
+            }
+        },
+    };
+
+    let line = char_from_lsp(&text, location.range.start);
+    let line = text.char_to_line(line);
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let title = format!("{}:{}", path.strip_prefix(&cwd).unwrap_or(&path).display(), line + 1);
+    editor.peek = Some(Peek::new(doc_id, view_id, trigger, title, &text, line));
+}
+
+/// Convert LSP diagnostics to document diagnostics, sorted by position
+fn convert_diagnostics(doc: &Document, diagnostics: Vec<lsp_types::Diagnostic>) -> Vec<Diagnostic> {
+    let mut converted: Vec<Diagnostic> = diagnostics
+        .into_iter()
+        .map(|diagnostic| {
+            let start = char_from_lsp(&doc.rope, diagnostic.range.start);
+            let end = char_from_lsp(&doc.rope, diagnostic.range.end).max(start);
+            let severity = match diagnostic.severity {
+                Some(lsp_types::DiagnosticSeverity::WARNING) => DiagnosticSeverity::Warning,
+                Some(lsp_types::DiagnosticSeverity::INFORMATION) => DiagnosticSeverity::Info,
+                Some(lsp_types::DiagnosticSeverity::HINT) => DiagnosticSeverity::Hint,
+                _ => DiagnosticSeverity::Error,
+            };
+            let converted = Diagnostic::new(start..end, severity, diagnostic.message);
+            match diagnostic.source {
+                Some(source) => converted.with_source(source),
+                None => converted,
+            }
+        })
+        .collect();
+    converted.sort_by_key(|diagnostic| (diagnostic.range.start, diagnostic.severity));
+    converted
+}
+
+/// Convert an LSP completion item, given the text it was requested for.
+/// Items without an edit replace the word before the cursor.
+fn convert_completion_item(
+    rope: &Rope,
+    start: usize,
+    cursor: usize,
+    item: lsp_types::CompletionItem,
+) -> CompletionItem {
+    let text_edit = |edit: &lsp_types::TextEdit| TextEdit {
+        range: char_from_lsp(rope, edit.range.start)..char_from_lsp(rope, edit.range.end),
+        text: edit.new_text.clone(),
+    };
+
+    let edit = match &item.text_edit {
+        Some(lsp_types::CompletionTextEdit::Edit(edit)) => text_edit(edit),
+        Some(lsp_types::CompletionTextEdit::InsertAndReplace(edit)) => TextEdit {
+            range: char_from_lsp(rope, edit.replace.start)..char_from_lsp(rope, edit.replace.end),
+            text: edit.new_text.clone(),
+        },
+        None => TextEdit {
+            range: start..cursor,
+            text: item.insert_text.clone().unwrap_or_else(|| item.label.clone()),
+        },
+    };
+    let additional_edits = item
+        .additional_text_edits
+        .iter()
+        .flatten()
+        .map(text_edit)
+        .collect();
+
+    CompletionItem {
+        filter_text: item.filter_text.unwrap_or_else(|| item.label.clone()),
+        label: item.label,
+        detail: item.detail,
+        edit,
+        additional_edits,
+    }
+}
+
+/// Convert an LSP signature help response shown at `cursor`; `None` if it
+/// has no signatures
+fn convert_signature_help(
+    doc_id: DocumentId,
+    view_id: ViewId,
+    cursor: usize,
+    help: lsp_types::SignatureHelp,
+) -> Option<SignatureHelp> {
+    if help.signatures.is_empty() {
+        return None;
+    }
+    let active = (help.active_signature.unwrap_or(0) as usize).min(help.signatures.len() - 1);
+
+    let signatures = help
+        .signatures
+        .into_iter()
+        .map(|signature| {
+            let label = &signature.label;
+            let parameters = signature
+                .parameters
+                .iter()
+                .flatten()
+                .map(|parameter| match &parameter.label {
+                    lsp_types::ParameterLabel::Simple(name) => match label.find(name.as_str()) {
+                        Some(byte) => {
+                            let start = label[..byte].chars().count();
+                            start..start + name.chars().count()
+                        }
+                        None => 0..0,
+                    },
+                    // Offsets count UTF-16 code units
+                    lsp_types::ParameterLabel::LabelOffsets([start, end]) => {
+                        char_from_utf16(label, *start as usize)..char_from_utf16(label, *end as usize)
+                    }
+                })
+                .collect();
+            Signature {
+                active_parameter: signature
+                    .active_parameter
+                    .or(help.active_parameter)
+                    .map(|parameter| parameter as usize),
+                label: signature.label,
+                parameters,
+            }
+        })
+        .collect();
+
+    Some(SignatureHelp {
+        doc_id,
+        view_id,
+        trigger: cursor,
+        signatures,
+        active,
+    })
+}
+
+/// Char index of a UTF-16 offset into `text`
+fn char_from_utf16(text: &str, offset: usize) -> usize {
+    let mut units = 0;
+    text.chars()
+        .take_while(|c| {
+            units += c.len_utf16();
+            units <= offset
+        })
+        .count()
+}
+
+/// Whether a server accepts changes as edits rather than the whole text
+fn syncs_incrementally(server: &LspClient) -> bool {
+    let sync = server
+        .capabilities()
+        .and_then(|capabilities| capabilities.text_document_sync.as_ref());
+    let kind = match sync {
+        Some(lsp_types::TextDocumentSyncCapability::Kind(kind)) => Some(*kind),
+        Some(lsp_types::TextDocumentSyncCapability::Options(options)) => options.change,
+        None => None,
+    };
+    kind == Some(lsp_types::TextDocumentSyncKind::INCREMENTAL)
+}
+
+/// The edits of a changeset as LSP content changes, for `old`, the text it
+/// applies to. Adjacent deletions and insertions become one replacement.
+/// Changes are applied one after another, so they're listed from the end
+/// of the document back, keeping each range valid in `old`.
+fn content_changes(old: &Rope, changes: &ChangeSet) -> Vec<lsp_types::TextDocumentContentChangeEvent> {
+    let mut edits: Vec<(usize, usize, String)> = Vec::new();
+    let mut pos = 0;
+    let mut editing = false;
+    for op in &changes.ops {
+        match op {
+            Operation::Retain(n) => {
+                pos += n;
+                editing = false;
+            }
+            Operation::Delete(n) => {
+                if !editing {
+                    edits.push((pos, pos, String::new()));
+                    editing = true;
+                }
+                pos += n;
+                if let Some(edit) = edits.last_mut() {
+                    edit.1 = pos;
+                }
+            }
+            Operation::Insert(text) => {
+                if !editing {
+                    edits.push((pos, pos, String::new()));
+                    editing = true;
+                }
+                if let Some(edit) = edits.last_mut() {
+                    edit.2.push_str(text);
+                }
+            }
+        }
+    }
+
+    edits
+        .into_iter()
+        .rev()
+        .map(|(start, end, text)| lsp_types::TextDocumentContentChangeEvent {
+            range: Some(lsp_types::Range {
+                start: lsp_from_char(old, start),
+                end: lsp_from_char(old, end),
+            }),
+            range_length: None,
+            text,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lite_lsp::lsp_types::{Position, Range};
+
+    /// Apply content changes the way a server does
+    fn apply_content_changes(text: &str, events: &[lsp_types::TextDocumentContentChangeEvent]) -> String {
+        let mut rope = Rope::from(text);
+        for event in events {
+            let range = event.range.unwrap();
+            let start = lite_lsp::char_from_lsp(&rope, range.start);
+            let end = lite_lsp::char_from_lsp(&rope, range.end);
+            rope.remove(start..end);
+            rope.insert(start, &event.text);
+        }
+        rope.to_string()
+    }
+
+    #[test]
+    fn test_content_changes() {
+        let old = Rope::from("fn 𝔸() {\n    a\n}\n");
+        let changes = lite_core::Transaction::changes(
+            old.len_chars(),
+            &[
+                lite_core::Change::replace(3, 4, "b"),
+                lite_core::Change::insert(9, "// x\n"),
+                lite_core::Change::delete(13, 14),
+            ],
+        )
+        .changes;
+        let events = content_changes(&old, &changes);
+
+        assert_eq!(events.len(), 3);
+        // The last edit comes first; UTF-16 columns count 𝔸 as two units
+        assert_eq!(events[2].range.unwrap().end, Position::new(0, 5));
+        let mut new = old.clone();
+        changes.apply(&mut new);
+        assert_eq!(apply_content_changes(&old.to_string(), &events), new.to_string());
+    }
+
+    fn location(path: &Path, line: u32, character: u32) -> lsp_types::Location {
+        let start = Position::new(line, character);
+        lsp_types::Location::new(uri_from_path(path).unwrap(), Range::new(start, start))
+    }
+
+    #[test]
+    fn test_goto_location() {
+        let dir = std::env::temp_dir().join(format!("lite-goto-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("lib.rs");
+        std::fs::write(&file, "mod a;\n\nfn 𝔸b() {}\n").unwrap();
+        let mut editor = Editor::new();
+
+        // A file not open yet is opened, the cursor at the line and column;
+        // UTF-16 columns count 𝔸 as two units
+        goto_location(&mut editor, &location(&file, 2, 5));
+        let cursor = |editor: &Editor| {
+            let doc = editor.current_doc();
+            doc.rope.char_to_position(doc.selection(editor.tree.focus()).cursor())
+        };
+        assert_eq!(editor.current_doc().path.as_deref().and_then(absolute), Some(file.clone()));
+        assert_eq!((cursor(&editor).line, cursor(&editor).col), (2, 4));
+        let doc_id = editor.current_doc().id;
+
+        // An open one is switched to
+        editor.new_document();
+        goto_location(&mut editor, &location(&file, 0, 4));
+        assert_eq!(editor.current_doc().id, doc_id);
+        assert_eq!((cursor(&editor).line, cursor(&editor).col), (0, 4));
+
+        // A missing file leaves the current document as it is
+        goto_location(&mut editor, &location(&dir.join("gone.rs"), 3, 0));
+        assert_eq!(editor.current_doc().id, doc_id);
+        assert!(editor.status_msg.as_ref().unwrap().0.starts_with("Cannot open definition"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_peek_location() {
+        let dir = std::env::temp_dir().join(format!("lite-peek-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("lib.rs");
+        std::fs::write(&file, "mod a;\n\nfn b() {}\n").unwrap();
+        let mut editor = Editor::new();
+        let (doc_id, view_id) = (editor.current_doc().id, editor.tree.focus());
+
+        // The lines around the definition are read from disk, the current
+        // document staying as it is
+        peek_location(&mut editor, &location(&file, 2, 3), doc_id, view_id, 0);
+        let peek = editor.peek.as_ref().unwrap();
+        assert!(peek.title.ends_with("lib.rs:3"));
+        assert_eq!((peek.first_line, peek.target), (1, 2));
+        assert_eq!(peek.lines, vec!["", "fn b() {}", ""]);
+        assert_eq!(editor.current_doc().id, doc_id);
+
+        // It stays while the cursor does, and closes once it moves
+        editor.update_peek();
+        assert!(editor.peek.is_some());
+        crate::insert_text(&mut editor, "x");
+        editor.update_peek();
+        assert!(editor.peek.is_none());
+
+        // An open document shows its unsaved text
+        editor.open(&file).unwrap();
+        let (doc_id, view_id) = (editor.current_doc().id, editor.tree.focus());
+        crate::insert_text(&mut editor, "// edited\n");
+        let cursor = editor.current_doc().selection(view_id).cursor();
+        peek_location(&mut editor, &location(&file, 0, 0), doc_id, view_id, cursor);
+        assert_eq!(editor.peek.as_ref().unwrap().lines[0], "// edited");
+        editor.update_peek();
+        assert!(editor.peek.is_some());
+        editor.new_document();
+        editor.update_peek();
+        assert!(editor.peek.is_none());
+
+        // A missing file shows nothing
+        peek_location(&mut editor, &location(&dir.join("gone.rs"), 0, 0), doc_id, view_id, 0);
+        assert!(editor.peek.is_none());
+        assert!(editor.status_msg.as_ref().unwrap().0.starts_with("Cannot read definition"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_update_progress() {
+        let mut manager = LspManager::new();
+        let key: ServerKey = ("rust".to_string(), PathBuf::from("/project"));
+        let notify = |manager: &mut LspManager, token: &str, work| {
+            let params = lsp_types::ProgressParams {
+                token: lsp_types::NumberOrString::String(token.to_string()),
+                value: lsp_types::ProgressParamsValue::WorkDone(work),
+            };
+            manager.update_progress(key.clone(), params);
+        };
+        let latest = |manager: &LspManager| {
+            manager.progress[&key].last().map(|(_, progress)| progress.describe())
+        };
+
+        notify(
+            &mut manager,
+            "index",
+            lsp_types::WorkDoneProgress::Begin(lsp_types::WorkDoneProgressBegin {
+                title: "Indexing".to_string(),
+                percentage: Some(0),
+                ..Default::default()
+            }),
+        );
+        notify(
+            &mut manager,
+            "index",
+            lsp_types::WorkDoneProgress::Report(lsp_types::WorkDoneProgressReport {
+                message: Some("12/40".to_string()),
+                percentage: Some(30),
+                ..Default::default()
+            }),
+        );
+        assert_eq!(latest(&manager).as_deref(), Some("Indexing 12/40 (30%)"));
+
+        notify(
+            &mut manager,
+            "check",
+            lsp_types::WorkDoneProgress::Begin(lsp_types::WorkDoneProgressBegin {
+                title: "cargo check".to_string(),
+                ..Default::default()
+            }),
+        );
+        assert_eq!(latest(&manager).as_deref(), Some("cargo check"));
+
+        let end = || lsp_types::WorkDoneProgress::End(lsp_types::WorkDoneProgressEnd { message: None });
+        notify(&mut manager, "check", end());
+        assert_eq!(latest(&manager).as_deref(), Some("Indexing 12/40 (30%)"));
+        notify(&mut manager, "index", end());
+        assert_eq!(latest(&manager), None);
+    }
+
+    #[test]
+    fn test_convert_completion_item() {
+        let rope = Rope::from("let v = x.it");
+        let item = lsp_types::CompletionItem {
+            label: "iter".to_string(),
+            detail: Some("fn iter(&self)".to_string()),
+            ..Default::default()
+        };
+        let converted = convert_completion_item(&rope, 10, 12, item);
+        assert_eq!(converted.edit, TextEdit { range: 10..12, text: "iter".to_string() });
+        assert_eq!(converted.filter_text, "iter");
+
+        let item = lsp_types::CompletionItem {
+            label: "into_iter".to_string(),
+            text_edit: Some(lsp_types::CompletionTextEdit::Edit(lsp_types::TextEdit {
+                range: Range::new(Position::new(0, 10), Position::new(0, 12)),
+                new_text: "into_iter()".to_string(),
+            })),
+            ..Default::default()
+        };
+        let converted = convert_completion_item(&rope, 10, 12, item);
+        assert_eq!(converted.edit.range, 10..12);
+        assert_eq!(converted.edit.text, "into_iter()");
+    }
+
+    #[test]
+    fn test_convert_signature_help() {
+        let parameter = |label| lsp_types::ParameterInformation {
+            label,
+            documentation: None,
+        };
+        let help = lsp_types::SignatureHelp {
+            signatures: vec![lsp_types::SignatureInformation {
+                label: "fn ü(a: u8, b: &str)".to_string(),
+                documentation: None,
+                parameters: Some(vec![
+                    parameter(lsp_types::ParameterLabel::LabelOffsets([5, 10])),
+                    parameter(lsp_types::ParameterLabel::Simple("b: &str".to_string())),
+                ]),
+                active_parameter: None,
+            }],
+            active_signature: Some(3),
+            active_parameter: Some(1),
+        };
+
+        let help = convert_signature_help(DocumentId::next(), ViewId::next(), 7, help).unwrap();
+        assert_eq!(help.active, 0);
+        let signature = &help.signatures[0];
+        assert_eq!(signature.parameters, vec![5..10, 12..19]);
+        assert_eq!(signature.active_range(), Some(12..19));
+    }
+
+    #[test]
+    fn test_convert_diagnostics() {
+        let doc = Document::from_text("fn ü() {}\nlet x;\n");
+        let diagnostic = |line, start, end, severity| lsp_types::Diagnostic {
+            range: Range::new(Position::new(line, start), Position::new(line, end)),
+            severity,
+            message: "problem".to_string(),
+            ..Default::default()
+        };
+
+        let converted = convert_diagnostics(
+            &doc,
+            vec![
+                diagnostic(1, 4, 5, Some(lsp_types::DiagnosticSeverity::WARNING)),
+                diagnostic(0, 3, 4, None),
+            ],
+        );
+        assert_eq!(
+            converted,
+            vec![
+                Diagnostic::new(3..4, DiagnosticSeverity::Error, "problem"),
+                Diagnostic::new(14..15, DiagnosticSeverity::Warning, "problem"),
+            ]
+        );
+    }
+}
@@ -1,10 +1,9 @@
 use anyhow::Result;
-use lite_term::Application;
+use lite_term::{is_newer, latest_release, Application, Headless, RELEASE_REPO as REPO};
 use std::env;
 use std::process::Command;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
-const REPO: &str = "IrfanArsyad/lite";
 
 fn print_help() {
     println!("lite - A lightweight terminal text editor");
@@ -16,75 +15,27 @@ fn print_help() {
     println!("    -h, --help       Print help information");
     println!("    -v, --version    Print version information");
     println!("    -u, --update     Update to latest version");
+    println!("    --headless       Run without a terminal, taking JSON-RPC requests on stdin");
+    println!("    --socket PATH    With --headless, take them on a Unix socket instead");
     println!();
     println!("EXAMPLES:");
     println!("    lite                  Open new buffer");
     println!("    lite file.txt         Open file");
     println!("    lite a.rs b.rs        Open multiple files");
     println!("    lite --update         Update lite to latest");
+    println!("    lite --headless a.rs  Script the editor over stdin and stdout");
 }
 
 fn print_version() {
     println!("lite {}", VERSION);
 }
 
-fn check_for_update() -> Option<String> {
-    let output = Command::new("curl")
-        .args(["-sL", "--connect-timeout", "2", &format!("https://api.github.com/repos/{}/releases/latest", REPO)])
-        .output()
-        .ok()?;
-
-    let json = String::from_utf8_lossy(&output.stdout);
-    if let Some(start) = json.find("\"tag_name\"") {
-        let rest = &json[start..];
-        if let Some(colon) = rest.find(':') {
-            let after_colon = &rest[colon + 1..];
-            let trimmed = after_colon.trim().trim_start_matches('"');
-            if let Some(end) = trimmed.find('"') {
-                let latest = trimmed[..end].trim_start_matches('v').to_string();
-                if latest != VERSION {
-                    return Some(latest);
-                }
-            }
-        }
-    }
-    None
-}
-
 fn update() -> Result<()> {
     println!("Checking for updates...");
 
     // Check latest version from GitHub API
-    let output = Command::new("curl")
-        .args(["-sL", &format!("https://api.github.com/repos/{}/releases/latest", REPO)])
-        .output();
-
-    let latest_version = match output {
-        Ok(out) => {
-            let json = String::from_utf8_lossy(&out.stdout);
-            // Simple parse for tag_name
-            if let Some(start) = json.find("\"tag_name\"") {
-                let rest = &json[start..];
-                if let Some(colon) = rest.find(':') {
-                    let after_colon = &rest[colon + 1..];
-                    let trimmed = after_colon.trim().trim_start_matches('"');
-                    if let Some(end) = trimmed.find('"') {
-                        Some(trimmed[..end].trim_start_matches('v').to_string())
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                }
-            } else {
-                None
-            }
-        }
-        Err(_) => None,
-    };
-
-    match latest_version {
-        Some(latest) if latest != VERSION => {
+    match latest_release() {
+        Some(latest) if is_newer(&latest, VERSION) => {
             println!("New version available: v{} (current: v{})", latest, VERSION);
             println!("Updating...");
             println!();
@@ -125,6 +76,32 @@ fn update() -> Result<()> {
     Ok(())
 }
 
+/// Answer JSON-RPC requests on stdin, or on a socket given with `--socket`
+fn headless(args: &[String]) -> Result<()> {
+    let mut headless = Headless::new();
+    let mut socket = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--socket" {
+            socket = args.next().map(std::path::PathBuf::from);
+            if socket.is_none() {
+                anyhow::bail!("--socket needs a path");
+            }
+        } else if let Err(e) = headless.open(arg) {
+            eprintln!("Error opening {}: {}", arg, e);
+        }
+    }
+
+    match socket {
+        #[cfg(unix)]
+        Some(path) => headless.serve_socket(&path)?,
+        #[cfg(not(unix))]
+        Some(_) => anyhow::bail!("--socket needs Unix sockets"),
+        None => headless.serve(std::io::stdin().lock(), std::io::stdout().lock())?,
+    }
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let args: Vec<String> = env::args().collect();
@@ -143,6 +120,9 @@ async fn main() -> Result<()> {
             "-u" | "--update" => {
                 return update();
             }
+            "--headless" => {
+                return headless(&args[2..]);
+            }
             _ => {}
         }
     }
@@ -159,25 +139,12 @@ async fn main() -> Result<()> {
         }
     }
 
-    // Check for updates in background (non-blocking)
-    std::thread::spawn(|| {
-        if let Some(new_version) = check_for_update() {
-            // Write to a temp file that we can read later
-            let _ = std::fs::write("/tmp/lite_update_available", new_version);
-        }
-    });
-
-    // Check if update notification exists from previous check
-    if let Ok(new_version) = std::fs::read_to_string("/tmp/lite_update_available") {
-        app.set_update_notice(format!(
-            "Update available: v{} -> v{}. Run 'lite --update' to update.",
-            VERSION, new_version.trim()
-        ));
-        let _ = std::fs::remove_file("/tmp/lite_update_available");
-    }
-
-    // Run the application
+    // Run the application, installing a newer release after it if asked to
     app.run().await?;
+    if app.update_requested() {
+        drop(app);
+        return update();
+    }
 
     Ok(())
 }
@@ -0,0 +1,303 @@
+//! Markdown editing: list items continued on Enter, checkboxes, heading
+//! levels, emphasis and tables, for the `md-*` commands
+
+use lite_core::grapheme_width;
+
+/// A list item or quoted line, split after its indentation
+struct Item<'a> {
+    /// Quote markers, as in `> > `
+    quote: &'a str,
+    /// `- `, `* `, `+ ` or a number with `.` or `)`, and the spaces after it
+    bullet: &'a str,
+    /// `[ ]`, `[x]` or `[X]` and the space after it
+    checkbox: Option<&'a str>,
+    text: &'a str,
+}
+
+/// Split up a line without its indentation, or `None` if it isn't a list
+/// item or quote
+fn parse_item(line: &str) -> Option<Item<'_>> {
+    let quote_len = line.len() - line.trim_start_matches(['>', ' ', '\t']).len();
+    let (quote, rest) = line.split_at(quote_len);
+    let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let marker_len = match rest[digits..].chars().next() {
+        Some('-' | '*' | '+') if digits == 0 => 1,
+        Some('.' | ')') if (1..=9).contains(&digits) => digits + 1,
+        _ => 0,
+    };
+    let after = &rest[marker_len..];
+    let spaced = after.starts_with(' ') || (after.is_empty() && marker_len > 0);
+    let bullet_len = if marker_len > 0 && spaced {
+        rest.len() - after.trim_start_matches(' ').len()
+    } else {
+        0
+    };
+    if bullet_len == 0 && !quote.contains('>') {
+        return None;
+    }
+    let (bullet, rest) = rest.split_at(bullet_len);
+    let checkbox_len = ["[ ]", "[x]", "[X]"]
+        .iter()
+        .find(|checkbox| bullet_len > 0 && rest.starts_with(**checkbox))
+        .map(|_| rest.len() - rest[3..].trim_start_matches(' ').len());
+    let (checkbox, text) = match checkbox_len {
+        Some(len) => (Some(&rest[..len]), &rest[len..]),
+        None => (None, rest),
+    };
+    Some(Item {
+        quote,
+        bullet,
+        checkbox,
+        text,
+    })
+}
+
+/// What the line after a list item or quote starts with, after the
+/// indentation: numbers count up and checkboxes start unchecked. Also
+/// whether the item is empty, which ends the list instead.
+pub fn continuation(line: &str) -> Option<(String, bool)> {
+    let indent = line.len() - line.trim_start_matches([' ', '\t']).len();
+    let item = parse_item(&line[indent..])?;
+    let bullet = match item.bullet.find(['.', ')']) {
+        Some(end) => {
+            let number: usize = item.bullet[..end].parse().ok()?;
+            format!("{}{}", number + 1, &item.bullet[end..])
+        }
+        None => item.bullet.to_string(),
+    };
+    let checkbox = if item.checkbox.is_some() { "[ ] " } else { "" };
+    let marker = format!("{}{}{}", item.quote, bullet, checkbox);
+    Some((marker, item.text.trim().is_empty()))
+}
+
+/// A line with its checkbox ticked or cleared. List items without one get
+/// one, and other lines become unchecked items.
+pub fn toggle_checkbox(line: &str) -> String {
+    let indent = line.len() - line.trim_start_matches([' ', '\t']).len();
+    let (indent, rest) = line.split_at(indent);
+    match parse_item(rest) {
+        Some(item) if !item.bullet.is_empty() => {
+            let checkbox = match item.checkbox.map(|checkbox| &checkbox[..3]) {
+                Some("[ ]") => "[x] ",
+                _ => "[ ] ",
+            };
+            format!("{}{}{}{}{}", indent, item.quote, item.bullet, checkbox, item.text)
+        }
+        Some(item) => format!("{}{}- [ ] {}", indent, item.quote, item.text),
+        None if rest.is_empty() => line.to_string(),
+        None => format!("{}- [ ] {}", indent, rest),
+    }
+}
+
+/// The level of a heading line, 1 to 6 by its `#`s
+pub fn heading_level(line: &str) -> Option<usize> {
+    let hashes = line.len() - line.trim_start_matches('#').len();
+    let rest = &line[hashes..];
+    ((1..=6).contains(&hashes) && (rest.is_empty() || rest.starts_with(' '))).then_some(hashes)
+}
+
+/// A line a heading level up (`promote`, fewer `#`) or down. Level 1 is
+/// as high as it goes, and past level 6 a heading becomes plain text, so
+/// plain text promotes to level 6.
+pub fn shift_heading(line: &str, promote: bool) -> String {
+    let (level, text) = match heading_level(line) {
+        Some(level) => (level, line[level..].trim_start()),
+        None => (7, line.trim_start()),
+    };
+    if text.is_empty() && level == 7 {
+        return line.to_string();
+    }
+    let level = if promote { level.saturating_sub(1).max(1) } else { (level + 1).min(7) };
+    match level {
+        7 => text.to_string(),
+        _ => format!("{} {}", "#".repeat(level), text),
+    }
+}
+
+/// Text wrapped in an emphasis or code marker, or unwrapped if it already
+/// is: `**` for bold, `*` for italic and `` ` `` for code. Bold italic
+/// `***` counts as either.
+pub fn toggle_emphasis(text: &str, marker: &str) -> String {
+    let mark = marker.chars().next().expect("markers aren't empty");
+    let n = marker.len();
+    let wrapped_by = |run: usize| run == n || (mark == '*' && run == 3);
+    let start = text.len() - text.trim_start_matches(mark).len();
+    let end = text.len() - text.trim_end_matches(mark).len();
+    if start < text.len() && wrapped_by(start) && wrapped_by(end) {
+        text[n..text.len() - n].to_string()
+    } else {
+        format!("{}{}{}", marker, text, marker)
+    }
+}
+
+/// The cells of a table row, trimmed. Pipes escaped with `\` or in code
+/// spans don't split cells.
+fn split_row(line: &str) -> Vec<String> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let mut cells = vec![String::new()];
+    let (mut escaped, mut code) = (false, false);
+    for c in line.chars() {
+        match c {
+            '|' if !escaped && !code => cells.push(String::new()),
+            _ => cells.last_mut().expect("there is a cell").push(c),
+        }
+        code ^= c == '`' && !escaped;
+        escaped = c == '\\' && !escaped;
+    }
+    // A closing pipe leaves an empty cell behind it
+    if cells.len() > 1 && cells.last().is_some_and(|cell| cell.trim().is_empty()) {
+        cells.pop();
+    }
+    cells.into_iter().map(|cell| cell.trim().to_string()).collect()
+}
+
+/// How a column is aligned, by the colons of its delimiter cell
+#[derive(Clone, Copy, PartialEq)]
+enum Align {
+    Left,
+    Right,
+    Center,
+    None,
+}
+
+fn parse_delimiter(cell: &str) -> Option<Align> {
+    let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+    if dashes.is_empty() || dashes.chars().any(|c| c != '-') {
+        return None;
+    }
+    Some(match (cell.starts_with(':'), cell.ends_with(':')) {
+        (true, true) => Align::Center,
+        (true, false) => Align::Left,
+        (false, true) => Align::Right,
+        (false, false) => Align::None,
+    })
+}
+
+/// Whether a line could be a table row
+pub fn is_table_row(line: &str) -> bool {
+    line.contains('|')
+}
+
+/// The rows of a table with their pipes lined up and every row as wide as
+/// the widest, or `None` if the second row isn't the `---` delimiter row
+pub fn format_table(lines: &[String]) -> Option<Vec<String>> {
+    let rows: Vec<Vec<String>> = lines.iter().map(|line| split_row(line)).collect();
+    let aligns: Vec<Align> = rows.get(1)?.iter().map(|cell| parse_delimiter(cell)).collect::<Option<_>>()?;
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let mut widths = vec![3; columns];
+    for row in rows.iter().take(1).chain(rows.iter().skip(2)) {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(grapheme_width(cell));
+        }
+    }
+
+    let aligns = (0..columns).map(|i| aligns.get(i).copied().unwrap_or(Align::None));
+    let delimiter: Vec<String> = aligns
+        .clone()
+        .zip(&widths)
+        .map(|(align, width)| match align {
+            Align::Left => format!(":{}", "-".repeat(width - 1)),
+            Align::Right => format!("{}:", "-".repeat(width - 1)),
+            Align::Center => format!(":{}:", "-".repeat(width - 2)),
+            Align::None => "-".repeat(*width),
+        })
+        .collect();
+    let formatted = rows.iter().enumerate().map(|(i, row)| {
+        if i == 1 {
+            return format!("| {} |", delimiter.join(" | "));
+        }
+        let cells: Vec<String> = aligns
+            .clone()
+            .zip(&widths)
+            .enumerate()
+            .map(|(column, (align, width))| {
+                let cell = row.get(column).map(String::as_str).unwrap_or("");
+                let padding = width - grapheme_width(cell);
+                let left = match align {
+                    Align::Right => padding,
+                    Align::Center => padding / 2,
+                    _ => 0,
+                };
+                format!("{}{}{}", " ".repeat(left), cell, " ".repeat(padding - left))
+            })
+            .collect();
+        format!("| {} |", cells.join(" | "))
+    });
+    Some(formatted.collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_continuation() {
+        let next = continuation;
+        assert_eq!(next("- item"), Some(("- ".to_string(), false)));
+        assert_eq!(next("  *   item"), Some(("*   ".to_string(), false)));
+        assert_eq!(next("9. item"), Some(("10. ".to_string(), false)));
+        assert_eq!(next("1) item"), Some(("2) ".to_string(), false)));
+        assert_eq!(next("- [x] done"), Some(("- [ ] ".to_string(), false)));
+        assert_eq!(next("> quoted"), Some(("> ".to_string(), false)));
+        assert_eq!(next("> - in a quote"), Some(("> - ".to_string(), false)));
+        assert_eq!(next("- "), Some(("- ".to_string(), true)));
+        assert_eq!(next("3. [ ] "), Some(("4. [ ] ".to_string(), true)));
+        assert_eq!(next("-not a list"), None);
+        assert_eq!(next("**bold**"), None);
+        assert_eq!(next("3.14 is pi"), None);
+        assert_eq!(next("plain"), None);
+    }
+
+    #[test]
+    fn test_toggle_checkbox() {
+        assert_eq!(toggle_checkbox("- [ ] task"), "- [x] task");
+        assert_eq!(toggle_checkbox("  * [X] task"), "  * [ ] task");
+        assert_eq!(toggle_checkbox("1. task"), "1. [ ] task");
+        assert_eq!(toggle_checkbox("  task"), "  - [ ] task");
+        assert_eq!(toggle_checkbox("> task"), "> - [ ] task");
+        assert_eq!(toggle_checkbox(""), "");
+    }
+
+    #[test]
+    fn test_shift_heading() {
+        assert_eq!(shift_heading("## Usage", true), "# Usage");
+        assert_eq!(shift_heading("# Title", true), "# Title");
+        assert_eq!(shift_heading("## Usage", false), "### Usage");
+        assert_eq!(shift_heading("###### Deep", false), "Deep");
+        assert_eq!(shift_heading("Text", true), "###### Text");
+        assert_eq!(shift_heading("Text", false), "Text");
+        assert_eq!(shift_heading("#hashtag", false), "#hashtag");
+        assert_eq!(shift_heading("", true), "");
+    }
+
+    #[test]
+    fn test_toggle_emphasis() {
+        assert_eq!(toggle_emphasis("word", "**"), "**word**");
+        assert_eq!(toggle_emphasis("**word**", "**"), "word");
+        assert_eq!(toggle_emphasis("**word**", "*"), "***word***");
+        assert_eq!(toggle_emphasis("***word***", "*"), "**word**");
+        assert_eq!(toggle_emphasis("*word*", "*"), "word");
+        assert_eq!(toggle_emphasis("`x`", "`"), "x");
+        assert_eq!(toggle_emphasis("", "`"), "``");
+        assert_eq!(toggle_emphasis("*", "*"), "***");
+    }
+
+    #[test]
+    fn test_format_table() {
+        let lines: Vec<String> = ["|Name|Size|", "|:-|-:|", "| lite | 1.2 MB |", "| ünï | `a|b` | extra"]
+            .iter()
+            .map(|line| line.to_string())
+            .collect();
+        assert_eq!(
+            format_table(&lines).unwrap(),
+            vec![
+                "| Name |   Size |       |",
+                "| :--- | -----: | ----- |",
+                "| lite | 1.2 MB |       |",
+                "| ünï  |  `a|b` | extra |",
+            ]
+        );
+        assert_eq!(format_table(&lines[2..]), None);
+    }
+}
@@ -0,0 +1,112 @@
+//! Instrumentation of the main loop for `debug_metrics`: how long input
+//! takes to reach the screen, and what the time goes to
+
+use lite_view::FrameMetrics;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Timings collected between two frames
+pub struct Metrics {
+    /// When the first input since the last frame reached the main loop
+    input_at: Option<Instant>,
+    /// The slowest step since the last frame
+    slowest: (&'static str, Duration),
+    /// `metrics.log`, opened when the first frame is logged
+    log: Option<File>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self {
+            input_at: None,
+            slowest: ("", Duration::ZERO),
+            log: None,
+        }
+    }
+
+    /// Note input reaching the main loop
+    pub fn input(&mut self, at: Instant) {
+        self.input_at.get_or_insert(at);
+    }
+
+    /// Note a step of the main loop that began at `started` and just ended
+    pub fn step(&mut self, name: &'static str, started: Instant) {
+        let elapsed = started.elapsed();
+        if elapsed > self.slowest.1 {
+            self.slowest = (name, elapsed);
+        }
+    }
+
+    /// Close a frame flushed at `flushed`. Frames with input and frames
+    /// over budget get their timings back; the others aren't worth a report.
+    pub fn frame(&mut self, frame: Duration, highlight: Duration, flushed: Instant) -> Option<FrameMetrics> {
+        let metrics = FrameMetrics {
+            latency: self.input_at.take().map(|at| flushed.saturating_duration_since(at)),
+            frame,
+            highlight,
+            slowest: std::mem::replace(&mut self.slowest, ("", Duration::ZERO)),
+        };
+        (metrics.latency.is_some() || metrics.over_budget()).then_some(metrics)
+    }
+
+    /// Append a frame's timings to `metrics.log`, after the Unix time in
+    /// milliseconds
+    pub fn log(&mut self, metrics: &FrameMetrics) -> io::Result<()> {
+        let log = match &mut self.log {
+            Some(log) => log,
+            None => {
+                let path = log_path().ok_or_else(|| io::Error::other("no state directory"))?;
+                if let Some(dir) = path.parent() {
+                    fs::create_dir_all(dir)?;
+                }
+                self.log.insert(OpenOptions::new().create(true).append(true).open(path)?)
+            }
+        };
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        writeln!(log, "{} {}", now.as_millis(), metrics)
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Where logged timings go
+pub fn log_path() -> Option<PathBuf> {
+    let dirs = directories::ProjectDirs::from("", "", "lite")?;
+    let dir = dirs.state_dir().unwrap_or(dirs.cache_dir());
+    Some(dir.join("metrics.log"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames() {
+        let mut metrics = Metrics::new();
+        let start = Instant::now();
+        metrics.input(start);
+        metrics.input(start + Duration::from_millis(2));
+        metrics.step("key", Instant::now());
+        let frame = metrics
+            .frame(Duration::from_millis(1), Duration::ZERO, start + Duration::from_millis(5))
+            .unwrap();
+        // Latency runs from the first input
+        assert_eq!(frame.latency, Some(Duration::from_millis(5)));
+        assert_eq!(frame.slowest.0, "key");
+
+        // Frames without input are only reported over budget
+        assert_eq!(metrics.frame(Duration::from_millis(1), Duration::ZERO, Instant::now()), None);
+        let started = Instant::now() - Duration::from_millis(40);
+        metrics.step("git", started);
+        let frame = metrics.frame(Duration::from_millis(1), Duration::ZERO, Instant::now()).unwrap();
+        assert_eq!(frame.latency, None);
+        assert_eq!(frame.slowest.0, "git");
+        assert!(frame.to_string().starts_with("frame 1.0ms highlight 0.0ms slowest git 4"));
+    }
+}
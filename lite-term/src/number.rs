@@ -0,0 +1,155 @@
+//! Numbers under the cursor in other bases and as dates, for `:number` and
+//! `:convert`
+
+/// Bases numbers are written in, with their prefixes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Base {
+    Dec,
+    Hex,
+    Oct,
+    Bin,
+}
+
+impl Base {
+    pub fn from_name(name: &str) -> Option<Base> {
+        match name {
+            "dec" => Some(Base::Dec),
+            "hex" => Some(Base::Hex),
+            "oct" => Some(Base::Oct),
+            "bin" => Some(Base::Bin),
+            _ => None,
+        }
+    }
+}
+
+/// Columns of the number at or just before `col` of a line, with its sign
+pub fn find(line: &str, col: usize) -> Option<(usize, usize)> {
+    let chars: Vec<char> = line.chars().collect();
+    let in_number = |i: usize| chars.get(i).is_some_and(|c| c.is_ascii_alphanumeric() || *c == '_');
+    let col = if in_number(col) { col } else { col.checked_sub(1).filter(|col| in_number(*col))? };
+    let mut start = col;
+    while start > 0 && in_number(start - 1) {
+        start -= 1;
+    }
+    let mut end = col;
+    while in_number(end) {
+        end += 1;
+    }
+    if start > 0 && chars[start - 1] == '-' {
+        start -= 1;
+    }
+    Some((start, end))
+}
+
+/// The value of a number written with an optional sign, `0x`, `0o` or `0b`
+/// prefix and `_` separators, and its base
+pub fn parse(text: &str) -> Option<(i128, Base)> {
+    let (negative, text) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let digits = |prefix: &str| text.get(..2).filter(|p| p.eq_ignore_ascii_case(prefix)).map(|_| &text[2..]);
+    let (digits, base, radix) = if let Some(digits) = digits("0x") {
+        (digits, Base::Hex, 16)
+    } else if let Some(digits) = digits("0o") {
+        (digits, Base::Oct, 8)
+    } else if let Some(digits) = digits("0b") {
+        (digits, Base::Bin, 2)
+    } else {
+        (text, Base::Dec, 10)
+    };
+    let digits = digits.replace('_', "");
+    if digits.is_empty() || digits.starts_with(['+', '-']) {
+        return None;
+    }
+    let value = i128::from_str_radix(&digits, radix).ok()?;
+    Some((if negative { -value } else { value }, base))
+}
+
+/// A value written in a base, with its prefix
+pub fn format(value: i128, base: Base) -> String {
+    let sign = if value < 0 { "-" } else { "" };
+    let abs = value.unsigned_abs();
+    match base {
+        Base::Dec => value.to_string(),
+        Base::Hex => format!("{}0x{:x}", sign, abs),
+        Base::Oct => format!("{}0o{:o}", sign, abs),
+        Base::Bin => format!("{}0b{:b}", sign, abs),
+    }
+}
+
+/// A value in every base, and as a date if it looks like a unix timestamp
+pub fn describe(value: i128) -> String {
+    let bases = [Base::Dec, Base::Hex, Base::Oct, Base::Bin].map(|base| format(value, base));
+    match date(value) {
+        Some(date) => format!("{}  {}", bases.join(" = "), date),
+        None => bases.join(" = "),
+    }
+}
+
+/// A unix timestamp as a UTC date. Seconds from 1973 on, and milliseconds
+/// past those, pass for timestamps; smaller numbers are likely something
+/// else.
+pub fn date(value: i128) -> Option<String> {
+    let (seconds, millis) = match value {
+        100_000_000..=99_999_999_999 => (value as i64, None),
+        100_000_000_000..=99_999_999_999_999 => ((value / 1000) as i64, Some(value % 1000)),
+        _ => return None,
+    };
+    let (days, time) = (seconds.div_euclid(86_400), seconds.rem_euclid(86_400));
+    let (year, month, day) = civil_from_days(days);
+    let millis = millis.map(|millis| format!(".{:03}", millis)).unwrap_or_default();
+    Some(format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60,
+        millis
+    ))
+}
+
+/// The date of a day counted from 1970-01-01 (Howard Hinnant's algorithm)
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_and_parse() {
+        let line = "mask = -0xFF_00; n=42";
+        let (start, end) = find(line, 10).unwrap();
+        assert_eq!(&line[start..end], "-0xFF_00");
+        assert_eq!(parse(&line[start..end]), Some((-0xff00, Base::Hex)));
+        assert_eq!(find(line, 21), Some((19, 21)));
+        assert_eq!(find(line, 5), None);
+        assert_eq!(parse("0b101"), Some((5, Base::Bin)));
+        assert_eq!(parse("mask"), None);
+        assert_eq!(parse("0x"), None);
+        assert_eq!(parse("--1"), None);
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(format(-255, Base::Hex), "-0xff");
+        assert_eq!(format(5, Base::Bin), "0b101");
+        assert_eq!(describe(8), "8 = 0x8 = 0o10 = 0b1000");
+        assert_eq!(date(1_700_000_000).unwrap(), "2023-11-14T22:13:20Z");
+        assert_eq!(date(951_782_400_123).unwrap(), "2000-02-29T00:00:00.123Z");
+        assert_eq!(date(4096), None);
+    }
+}
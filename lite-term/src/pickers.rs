@@ -0,0 +1,342 @@
+//! The pickers: files of the project, recent files, open buffers, symbols
+//! and diagnostics of the current buffer, all shown by one `Picker`
+
+use crate::file_tree::read_dir;
+use lite_core::Rope;
+use lite_git::Repository;
+use lite_ui::{Loaded, Picker, Preview};
+use lite_view::{outline, todo, DiagnosticSeverity, DocumentId, Editor, Severity};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+
+/// Most files the file picker lists
+const MAX_FILES: usize = 20_000;
+
+/// Lines of context a preview has before and after the line it's about
+const PREVIEW_CONTEXT: usize = 50;
+
+/// Files of the project or the workspace folders, less the ignored ones,
+/// marked with their git status. They're listed in the background; the
+/// title says when there were more than `MAX_FILES`.
+pub fn files(editor: &mut Editor) -> Picker<PathBuf> {
+    let roots = editor.search_roots();
+    let current = editor.current_doc().path.as_ref().and_then(|path| std::path::absolute(path).ok());
+    let (tx, rx) = mpsc::channel();
+    editor.spawn_job(move || {
+        let (files, truncated) = list_files(&roots);
+        let markers = git_markers(&roots);
+        let _ = tx.send(Loaded {
+            markers: files.iter().map(|path| markers.get(path).copied()).collect(),
+            selected: current.and_then(|current| files.iter().position(|path| *path == current)),
+            note: truncated.then(|| format!("only the first {} are listed", MAX_FILES)),
+            items: files,
+        });
+        Box::new(|_| {})
+    });
+
+    Picker::new(
+        "Files",
+        Vec::new(),
+        |path: &PathBuf| todo::display_path(path).display().to_string(),
+        |path, editor| {
+            if let Err(e) = editor.open(path) {
+                editor.set_status(format!("Error: {}", e), Severity::Error);
+            }
+        },
+    )
+    .with_loading(rx)
+    .with_preview(file_preview())
+}
+
+/// Paths of the files under the roots, sorted, and whether there were more
+/// than `MAX_FILES`, where the walk stops. A directory reached again
+/// through a symlink is listed once.
+fn list_files(roots: &[PathBuf]) -> (Vec<PathBuf>, bool) {
+    let mut files = Vec::new();
+    let mut truncated = false;
+    let mut visited = HashSet::new();
+    'roots: for root in roots {
+        let repo = Repository::open(root);
+        let mut stack = vec![root.clone()];
+        while let Some(dir) = stack.pop() {
+            if !dir.canonicalize().is_ok_and(|real| visited.insert(real)) {
+                continue;
+            }
+            for (name, is_dir) in read_dir(&dir, repo.as_ref()) {
+                let path = dir.join(name);
+                if is_dir {
+                    stack.push(path);
+                } else if files.len() == MAX_FILES {
+                    truncated = true;
+                    break 'roots;
+                } else {
+                    files.push(path);
+                }
+            }
+        }
+    }
+    files.sort();
+    (files, truncated)
+}
+
+/// Git status markers of the changed files under the roots, by their path
+/// as `list_files` gives it
+fn git_markers(roots: &[PathBuf]) -> HashMap<PathBuf, &'static str> {
+    let mut markers = HashMap::new();
+    for root in roots {
+        let Some(repo) = Repository::open(root) else {
+            continue;
+        };
+        // git reports paths with symlinks resolved
+        let (Ok(statuses), Ok(real_root)) = (repo.statuses(), root.canonicalize()) else {
+            continue;
+        };
+        for (path, status) in statuses {
+            if let (Some(marker), Ok(relative)) = (status.marker(), path.strip_prefix(&real_root)) {
+                markers.insert(root.join(relative), marker);
+            }
+        }
+    }
+    markers
+}
+
+/// A preview of the highlighted file, read again only when another one is
+/// highlighted rather than on every frame
+fn file_preview() -> impl Fn(&PathBuf, &Editor) -> Option<Preview> {
+    let last: RefCell<Option<(PathBuf, Option<Preview>)>> = RefCell::new(None);
+    move |path, _| {
+        let mut last = last.borrow_mut();
+        match &*last {
+            Some((last_path, preview)) if last_path == path => preview.clone(),
+            _ => {
+                let preview = preview_file(path);
+                *last = Some((path.clone(), preview.clone()));
+                preview
+            }
+        }
+    }
+}
+
+/// The start of a text file
+fn preview_file(path: &Path) -> Option<Preview> {
+    let file = fs::File::open(path).ok()?;
+    let lines = BufReader::new(file)
+        .lines()
+        .take(PREVIEW_CONTEXT * 2)
+        .collect::<Result<Vec<_>, _>>()
+        .ok()?;
+    Some(Preview { lines, highlight: None })
+}
+
+/// Files opened lately that still exist, the one before the current
+/// highlighted. Those in the working directory show relative to it.
+pub fn recent(editor: &Editor) -> Picker<PathBuf> {
+    let root = std::env::current_dir().unwrap_or_default();
+    let files: Vec<PathBuf> = editor.recent_files.iter().filter(|path| path.is_file()).cloned().collect();
+    let current = editor.current_doc().path.as_ref().and_then(|path| std::path::absolute(path).ok());
+    let skip_current = current.is_some() && files.first() == current.as_ref();
+
+    let picker = Picker::new(
+        "Recent files",
+        files,
+        move |path: &PathBuf| path.strip_prefix(&root).unwrap_or(path).display().to_string(),
+        |path, editor| {
+            if let Err(e) = editor.open(path) {
+                editor.set_status(format!("Error: {}", e), Severity::Error);
+            }
+        },
+    )
+    .with_preview(file_preview());
+    if skip_current {
+        picker.with_selected(1)
+    } else {
+        picker
+    }
+}
+
+/// Files a glob given to `:e` or the Open prompt matched
+pub fn glob_matches(pattern: &str, files: Vec<PathBuf>) -> Picker<PathBuf> {
+    Picker::new(
+        format!("Files matching {}", pattern),
+        files,
+        |path: &PathBuf| path.display().to_string(),
+        |path, editor| {
+            if let Err(e) = editor.open(path) {
+                editor.set_status(format!("Error: {}", e), Severity::Error);
+            }
+        },
+    )
+    .with_preview(file_preview())
+}
+
+/// Open buffers, the one before the current highlighted
+pub fn buffers(editor: &Editor) -> Picker<(DocumentId, String)> {
+    let buffers = editor.buffers_mru().to_vec();
+    let titles: Vec<String> = buffers
+        .iter()
+        .map(|id| editor.documents.get(id).map(|doc| doc.title()).unwrap_or_default())
+        .collect();
+    Picker::new(
+        "Buffers",
+        buffers.into_iter().zip(titles).collect(),
+        |(_, title): &(DocumentId, String)| title.clone(),
+        |&(doc_id, _), editor| editor.switch_to_document(doc_id),
+    )
+    .with_preview(|&(doc_id, _), editor| {
+        let doc = editor.documents.get(&doc_id)?;
+        Some(preview_lines(&doc.rope, None))
+    })
+    .with_selected(1)
+}
+
+/// Symbols of the current buffer, as in the outline
+pub fn symbols(editor: &Editor) -> Picker<outline::Symbol> {
+    let doc = editor.current_doc();
+    let doc_id = doc.id;
+    let symbols = outline::symbols(doc.language.as_deref().unwrap_or_default(), &doc.text());
+    let line = doc.rope.char_to_line(doc.selection(editor.tree.focus()).cursor());
+    let selected = symbols.iter().rposition(|symbol| symbol.lines.contains(&line));
+
+    let picker = Picker::new(
+        "Symbols",
+        symbols,
+        |symbol: &outline::Symbol| format!("{}{} {}", "  ".repeat(symbol.depth), symbol.kind, symbol.name),
+        |symbol, editor| editor.goto_line(symbol.lines.start),
+    )
+    .with_preview(move |symbol, editor| {
+        let doc = editor.documents.get(&doc_id)?;
+        Some(preview_lines(&doc.rope, Some(symbol.lines.start)))
+    });
+    match selected {
+        Some(selected) => picker.with_selected(selected),
+        None => picker,
+    }
+}
+
+/// A diagnostic of the current buffer to go to
+#[derive(Debug, Clone)]
+pub struct DiagnosticItem {
+    doc_id: DocumentId,
+    pos: usize,
+    line: usize,
+    label: String,
+}
+
+/// Diagnostics of the current buffer, in order
+pub fn diagnostics(editor: &Editor) -> Picker<DiagnosticItem> {
+    let doc = editor.current_doc();
+    let mut diagnostics: Vec<_> = doc.diagnostics.iter().collect();
+    diagnostics.sort_by_key(|diagnostic| diagnostic.range.start);
+    let items = diagnostics
+        .into_iter()
+        .map(|diagnostic| {
+            let pos = diagnostic.range.start.min(doc.len_chars());
+            let line = doc.rope.char_to_line(pos);
+            let severity = match diagnostic.severity {
+                DiagnosticSeverity::Error => "error",
+                DiagnosticSeverity::Warning => "warning",
+                DiagnosticSeverity::Info => "info",
+                DiagnosticSeverity::Hint => "hint",
+            };
+            let message = diagnostic.message.lines().next().unwrap_or_default();
+            DiagnosticItem {
+                doc_id: doc.id,
+                pos,
+                line,
+                label: format!("{}: {} {}", line + 1, severity, message),
+            }
+        })
+        .collect();
+
+    Picker::new(
+        "Diagnostics",
+        items,
+        |item: &DiagnosticItem| item.label.clone(),
+        |item, editor| {
+            let view_id = editor.tree.focus();
+            let doc = editor.current_doc_mut();
+            if doc.id == item.doc_id {
+                let pos = item.pos.min(doc.len_chars());
+                doc.set_selection(view_id, lite_core::Selection::point(pos));
+                editor.ensure_cursor_visible();
+            }
+        },
+    )
+    .with_preview(|item, editor| {
+        let doc = editor.documents.get(&item.doc_id)?;
+        Some(preview_lines(&doc.rope, Some(item.line)))
+    })
+}
+
+/// Lines of a text around a line, or from its start
+fn preview_lines(rope: &Rope, line: Option<usize>) -> Preview {
+    let first = line.map_or(0, |line| line.saturating_sub(PREVIEW_CONTEXT));
+    let last = (line.unwrap_or(0) + PREVIEW_CONTEXT).min(rope.len_lines());
+    let lines = (first..last)
+        .map(|i| rope.line(i).to_string().trim_end_matches(['\n', '\r']).to_string())
+        .collect();
+    Preview {
+        lines,
+        highlight: line.map(|line| line - first),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_files() {
+        let dir = std::env::temp_dir().join(format!("lite-pickers-{}", std::process::id()));
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::create_dir_all(dir.join("target")).unwrap();
+        fs::write(dir.join(".gitignore"), "target/\n").unwrap();
+        fs::write(dir.join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(dir.join("target/out"), "").unwrap();
+        git2::Repository::init(&dir).unwrap();
+
+        let roots = [dir.clone()];
+        let (files, truncated) = list_files(&roots);
+        assert_eq!(files, vec![dir.join(".gitignore"), dir.join("src/main.rs")]);
+        assert!(!truncated);
+        // Symlinked folders are followed, but a cycle only once
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(&dir, dir.join("src/root")).unwrap();
+            std::os::unix::fs::symlink(dir.join("src"), dir.join("linked")).unwrap();
+            let (files, _) = list_files(&roots);
+            assert_eq!(files.len(), 2);
+            fs::remove_file(dir.join("src/root")).unwrap();
+            let (files, _) = list_files(&roots);
+            assert_eq!(files.len(), 2);
+            fs::remove_file(dir.join("linked")).unwrap();
+        }
+        // Nothing is committed yet
+        assert_eq!(git_markers(&roots).get(&dir.join("src/main.rs")), Some(&"??"));
+        let preview = preview_file(&dir.join("src/main.rs")).unwrap();
+        assert_eq!(preview.lines, vec!["fn main() {}"]);
+        // The file is read once while it stays highlighted
+        let cached = file_preview();
+        let editor = Editor::new();
+        let main = dir.join("src/main.rs");
+        assert_eq!(cached(&main, &editor).unwrap().lines, vec!["fn main() {}"]);
+        fs::write(&main, "fn main() { run() }\n").unwrap();
+        assert_eq!(cached(&main, &editor).unwrap().lines, vec!["fn main() {}"]);
+        assert!(cached(&dir.join(".gitignore"), &editor).is_some());
+        assert_eq!(cached(&main, &editor).unwrap().lines, vec!["fn main() { run() }"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_preview_lines() {
+        let rope = Rope::from("a\nb\nc\n");
+        let preview = preview_lines(&rope, Some(1));
+        assert_eq!(preview.lines, vec!["a", "b", "c", ""]);
+        assert_eq!(preview.highlight, Some(1));
+    }
+}
@@ -0,0 +1,193 @@
+//! Commands a project declares in its `.lite.toml`: formatters, lint
+//! commands and named tasks
+
+use lite_config::ProjectConfig;
+use lite_core::{RopeExt, Selection, Transaction};
+use lite_view::{run_filter_in, Document, Editor, Severity};
+use std::path::Path;
+use std::process::{Command, Output};
+
+/// Read the `.lite.toml` of the project around a directory, if there is one
+pub fn load(editor: &mut Editor, dir: &Path) {
+    let Some(path) = ProjectConfig::find(dir) else {
+        return;
+    };
+    if let Err(e) = ProjectConfig::load(&path).and_then(|project| editor.config.apply_project(project)) {
+        editor.set_status(e.to_string(), Severity::Error);
+    }
+}
+
+/// Pass the buffer through the project's formatter for its language
+pub fn format(editor: &mut Editor) {
+    save_before_task(editor);
+    let doc = editor.current_doc();
+    let language = doc.language.clone().unwrap_or_default();
+    let Some(command) = editor.config.project.formatters.get(&language) else {
+        editor.set_status(format!("No formatter for {} in .lite.toml", language_name(&language)), Severity::Info);
+        return;
+    };
+    let file = doc.path.as_deref().map(|path| path.display().to_string()).unwrap_or_default();
+    // The path is an argument of the shell, so its name can't add commands
+    let command = command.replace("{file}", "\"$1\"");
+    let text = doc.text();
+
+    // In the project root, where formatters look for their own settings
+    let output = run_filter_in(&command, &[&file], text.as_bytes(), Some(&editor.config.project.root));
+    let formatted = match output.map(String::from_utf8) {
+        Ok(Ok(formatted)) => formatted,
+        Ok(Err(_)) => {
+            editor.set_status("The formatter printed invalid UTF-8", Severity::Error);
+            return;
+        }
+        Err(e) => {
+            editor.set_status(e.to_string(), Severity::Error);
+            return;
+        }
+    };
+    if formatted == text {
+        editor.set_status("Already formatted", Severity::Info);
+        return;
+    }
+
+    // The cursor stays on the same line, as near its column as it can
+    let view_id = editor.tree.focus();
+    let doc = editor.current_doc_mut();
+    let pos = doc.rope.char_to_position(doc.selection(view_id).cursor());
+    let tx = Transaction::replace(doc.len_chars(), 0, doc.len_chars(), formatted);
+    doc.apply(&tx, view_id);
+    let line = pos.line.min(doc.len_lines().saturating_sub(1));
+    let cursor = doc.rope.line_to_char(line) + pos.col.min(doc.rope.line_len_chars(line));
+    doc.set_selection(view_id, Selection::point(cursor));
+    editor.ensure_cursor_visible();
+    editor.set_status("Formatted", Severity::Info);
+}
+
+/// Run the project's lint command for the buffer's language
+pub fn lint(editor: &mut Editor) {
+    let language = editor.current_doc().language.clone().unwrap_or_default();
+    match editor.config.project.lint.get(&language).cloned() {
+        Some(command) => run(editor, &format!("lint: {}", language), &command),
+        None => editor.set_status(format!("No lint command for {} in .lite.toml", language_name(&language)), Severity::Info),
+    }
+}
+
+/// Run a named task of the project, or list them
+pub fn task(editor: &mut Editor, name: &str) {
+    let tasks = &editor.config.project.tasks;
+    if name.is_empty() || !tasks.contains_key(name) {
+        let names: Vec<&str> = tasks.keys().map(String::as_str).collect();
+        let message = match (name, names.is_empty()) {
+            (_, true) => "No tasks in .lite.toml".to_string(),
+            ("", false) => format!("Tasks: {}", names.join(", ")),
+            (_, false) => format!("Unknown task {}; tasks: {}", name, names.join(", ")),
+        };
+        editor.set_status(message, Severity::Info);
+        return;
+    }
+    let command = tasks[name].clone();
+    run(editor, &format!("task: {}", name), &command);
+}
+
+/// Run a command in the project root, in the background, and show what it
+/// printed in a buffer, where `file:line` references can be followed with
+/// Alt+Enter
+fn run(editor: &mut Editor, title: &str, command: &str) {
+    save_before_task(editor);
+    let (title, command) = (title.to_string(), command.to_string());
+    let root = editor.config.project.root.clone();
+    editor.set_status(format!("{} running...", title), Severity::Info);
+    editor.spawn_job(move || {
+        let output = Command::new("sh").arg("-c").arg(&command).current_dir(root).output();
+        Box::new(move |editor: &mut Editor| show_output(editor, &title, &command, output))
+    });
+}
+
+/// The output of a finished command, in the buffer named after it
+fn show_output(editor: &mut Editor, title: &str, command: &str, output: std::io::Result<Output>) {
+    let (text, status) = match output {
+        Ok(output) => {
+            let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+            text.push_str(&String::from_utf8_lossy(&output.stderr));
+            let status = match output.status.success() {
+                true => format!("{} finished", title),
+                false => format!("{} failed ({})", title, output.status),
+            };
+            (text, status)
+        }
+        Err(e) => {
+            editor.set_status(format!("Cannot run {}: {}", command, e), Severity::Error);
+            return;
+        }
+    };
+    let text = format!("$ {}\n\n{}", command, text);
+
+    match editor.find_virtual(title) {
+        Some(doc_id) => {
+            editor.switch_to_document(doc_id);
+            if let Some(doc) = editor.documents.get_mut(&doc_id) {
+                doc.set_text(text);
+            }
+        }
+        None => {
+            editor.open_virtual(Document::new_virtual(title, text), None);
+        }
+    }
+    editor.set_status(status, Severity::Info);
+}
+
+/// Commands may read the file rather than the buffer, so it may need saving
+fn save_before_task(editor: &mut Editor) {
+    if editor.config.editor.auto_save_on.before_tasks {
+        editor.auto_save(editor.current_doc().id);
+    }
+}
+
+fn language_name(language: &str) -> &str {
+    if language.is_empty() {
+        "this buffer"
+    } else {
+        language
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_format_and_task() {
+        let mut editor = Editor::new();
+        let view_id = editor.tree.focus();
+        let doc = editor.current_doc_mut();
+        doc.apply(&Transaction::insert(0, 0, "fn main() {}\nlet x;\n".to_string()), view_id);
+        doc.set_selection(view_id, Selection::point(16));
+        doc.language = Some("rust".to_string());
+
+        let project = &mut editor.config.project;
+        project.root = std::env::temp_dir();
+        project.formatters.insert("rust".to_string(), "tr a-z A-Z".to_string());
+        project.tasks.insert("hello".to_string(), "echo hello".to_string());
+
+        format(&mut editor);
+        assert_eq!(editor.current_doc().text(), "FN MAIN() {}\nLET X;\n");
+        assert_eq!(editor.current_doc().selection(view_id).cursor(), 16);
+
+        // The file's name reaches the formatter as one argument
+        let path = std::env::temp_dir().join(format!("lite format {}; echo injected", std::process::id()));
+        editor.current_doc_mut().path = Some(path.clone());
+        editor.config.project.formatters.insert("rust".to_string(), "printf %s {file}".to_string());
+        format(&mut editor);
+        assert_eq!(editor.current_doc().text(), path.display().to_string());
+        let _ = std::fs::remove_file(&path);
+
+        task(&mut editor, "");
+        assert_eq!(editor.status_msg.as_ref().unwrap().0, "Tasks: hello");
+        task(&mut editor, "hello");
+        assert_eq!(editor.status_msg.as_ref().unwrap().0, "task: hello running...");
+        editor.wait_jobs();
+        assert_eq!(editor.status_msg.as_ref().unwrap().0, "task: hello finished");
+        assert_eq!(editor.current_doc().name(), "task: hello");
+        assert_eq!(editor.current_doc().text(), "$ echo hello\n\nhello\n");
+    }
+}
@@ -0,0 +1,122 @@
+//! Tab completion in prompts: command names, then the arguments of the
+//! command, such as paths for `:e` and buffer names for `:bd`
+
+use crate::commands::{find_command, COMMANDS};
+use crate::expand;
+use lite_view::Editor;
+use std::fs;
+use std::path::Path;
+
+/// Inputs of the command prompt that complete what's typed, in order
+pub fn complete_command(editor: &Editor, input: &str) -> Vec<String> {
+    let lead = &input[..input.len() - input.trim_start_matches([' ', ':']).len()];
+    let rest = &input[lead.len()..];
+    let Some((name, text)) = rest.split_once(char::is_whitespace) else {
+        // Commands taking arguments get the space before them
+        return COMMANDS
+            .iter()
+            .filter(|command| command.names[0].starts_with(rest))
+            .map(|command| {
+                let space = if command.args.is_empty() { "" } else { " " };
+                format!("{}{}{}", lead, command.names[0], space)
+            })
+            .collect();
+    };
+    let Some(complete) = find_command(name.strip_suffix('!').unwrap_or(name)).and_then(|command| command.complete)
+    else {
+        return Vec::new();
+    };
+    let text = text.trim_start();
+    let before = &input[..input.len() - text.len()];
+    complete(editor, text)
+        .into_iter()
+        .filter(|candidate| candidate.starts_with(text) && candidate != text)
+        .map(|candidate| format!("{}{}", before, candidate))
+        .collect()
+}
+
+/// Paths completing what's typed in the Open prompt
+pub fn complete_path(editor: &Editor, input: &str) -> Vec<String> {
+    paths(editor, input).into_iter().filter(|path| path.starts_with(input) && path != input).collect()
+}
+
+/// Entries of the folder a typed path is in, written the way it's typed so
+/// far, folders ending with `/`. Hidden ones are left out unless the name
+/// typed starts with `.`.
+pub fn paths(editor: &Editor, typed: &str) -> Vec<String> {
+    let (dir, name) = typed.split_at(typed.rfind('/').map_or(0, |i| i + 1));
+    let Ok(expanded) = expand::expand(dir, editor.current_doc().path.as_deref()) else {
+        return Vec::new();
+    };
+    let expanded = if expanded.is_empty() { "." } else { expanded.as_str() };
+    let Ok(entries) = fs::read_dir(Path::new(expanded)) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let entry_name = entry.file_name().to_string_lossy().into_owned();
+            if entry_name.starts_with('.') && !name.starts_with('.') {
+                return None;
+            }
+            let slash = if entry.path().is_dir() { "/" } else { "" };
+            Some(format!("{}{}{}", dir, entry_name, slash))
+        })
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// Names of the open buffers as the last of the arguments typed, quoted if
+/// they have whitespace
+pub fn buffers(editor: &Editor, typed: &str) -> Vec<String> {
+    let before = &typed[..typed.rfind(char::is_whitespace).map_or(0, |i| i + 1)];
+    editor
+        .buffer_ids()
+        .iter()
+        .map(|id| {
+            let name = editor.documents[id].name();
+            match name.contains(char::is_whitespace) {
+                true => format!("{}\"{}\"", before, name),
+                false => format!("{}{}", before, name),
+            }
+        })
+        .collect()
+}
+
+/// A fixed set of arguments
+pub fn words(words: &[&str]) -> Vec<String> {
+    words.iter().map(|word| word.to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_complete_command() {
+        let dir = std::env::temp_dir().join(format!("lite-complete-{}", std::process::id()));
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("src/main.rs"), "").unwrap();
+        fs::write(dir.join("src/mod.rs"), "").unwrap();
+        fs::write(dir.join("src/.hidden"), "").unwrap();
+
+        let editor = Editor::new();
+        let name = editor.current_doc().name();
+        assert_eq!(complete_command(&editor, "json-m"), vec!["json-minify"]);
+        assert_eq!(complete_command(&editor, ":workspace-a"), vec![":workspace-add "]);
+        assert_eq!(complete_command(&editor, "bom o"), vec!["bom on", "bom off"]);
+        assert_eq!(complete_command(&editor, "bd! "), vec![format!("bd! {}", name)]);
+        assert!(complete_command(&editor, "quit ").is_empty());
+
+        let shown = dir.display();
+        assert_eq!(
+            complete_command(&editor, &format!("e {}/src/m", shown)),
+            vec![format!("e {}/src/main.rs", shown), format!("e {}/src/mod.rs", shown)]
+        );
+        assert_eq!(complete_path(&editor, &format!("{}/s", shown)), vec![format!("{}/src/", shown)]);
+        assert_eq!(complete_path(&editor, &format!("{}/src/.", shown)), vec![format!("{}/src/.hidden", shown)]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,85 @@
+//! Inputs given to prompts, kept across sessions in the state directory
+//! so Up in a prompt recalls earlier searches and commands
+
+use lite_view::MAX_PROMPT_HISTORY;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+type History = BTreeMap<String, Vec<String>>;
+
+/// The prompt history of earlier sessions
+pub fn load() -> History {
+    let Some(path) = state_path() else {
+        return History::new();
+    };
+    fs::read_to_string(path).map(|text| parse(&text)).unwrap_or_default()
+}
+
+/// Remember the inputs of this session ahead of those other sessions saved
+/// since it started
+pub fn save(history: &History) -> io::Result<()> {
+    let path = state_path().ok_or_else(|| io::Error::other("no state directory"))?;
+    let theirs = fs::read_to_string(&path).map(|text| parse(&text)).unwrap_or_default();
+    let text: String = merge(history, theirs)
+        .iter()
+        .flat_map(|(kind, inputs)| inputs.iter().map(move |input| format!("{}\t{}\n", kind, input)))
+        .collect();
+    crate::recent::write(&path, &text)
+}
+
+/// Where the history is kept, a `kind<Tab>input` line per input
+fn state_path() -> Option<PathBuf> {
+    let dirs = directories::ProjectDirs::from("", "", "lite")?;
+    let dir = dirs.state_dir().unwrap_or(dirs.cache_dir());
+    Some(dir.join("prompt-history"))
+}
+
+fn parse(text: &str) -> History {
+    let mut history = History::new();
+    for (kind, input) in text.lines().filter_map(|line| line.split_once('\t')) {
+        let inputs = history.entry(kind.to_string()).or_default();
+        if inputs.len() < MAX_PROMPT_HISTORY && !input.is_empty() {
+            inputs.push(input.to_string());
+        }
+    }
+    history
+}
+
+/// Each kind's inputs in `ours` then the rest of `theirs`, without repeats.
+/// Inputs on several lines can't be written, so they're left out.
+fn merge(ours: &History, mut theirs: History) -> History {
+    let mut merged = ours.clone();
+    for (kind, inputs) in &mut merged {
+        inputs.extend(theirs.remove(kind).unwrap_or_default());
+    }
+    merged.extend(theirs);
+    for inputs in merged.values_mut() {
+        let mut unique: Vec<String> = Vec::new();
+        for input in inputs.drain(..) {
+            if !input.contains('\n') && !unique.contains(&input) {
+                unique.push(input);
+            }
+        }
+        unique.truncate(MAX_PROMPT_HISTORY);
+        *inputs = unique;
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge() {
+        let theirs = parse("search\tfoo\ncommand\tw\nsearch\tbar\nbroken line\n");
+        assert_eq!(theirs["search"], vec!["foo", "bar"]);
+        let mut ours = History::new();
+        ours.insert("search".to_string(), vec!["bar".to_string(), "a\nb".to_string(), "baz".to_string()]);
+        let merged = merge(&ours, theirs);
+        assert_eq!(merged["search"], vec!["bar", "baz", "foo"]);
+        assert_eq!(merged["command"], vec!["w"]);
+    }
+}
@@ -0,0 +1,84 @@
+//! Recently opened files, kept across sessions in the state directory
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Most files remembered
+const MAX_RECENT_FILES: usize = 100;
+
+/// The recent files of earlier sessions, most recent first
+pub fn load() -> Vec<PathBuf> {
+    let Some(path) = state_path() else {
+        return Vec::new();
+    };
+    fs::read_to_string(path).map(|text| parse(&text)).unwrap_or_default()
+}
+
+/// Remember the recent files of this session ahead of those other sessions
+/// saved since it started
+pub fn save(recent: &[PathBuf]) -> io::Result<()> {
+    let path = state_path().ok_or_else(|| io::Error::other("no state directory"))?;
+    let theirs = fs::read_to_string(&path).map(|text| parse(&text)).unwrap_or_default();
+    let text: String = merge(recent, theirs)
+        .iter()
+        .filter_map(|file| file.to_str())
+        .filter(|file| !file.contains('\n'))
+        .map(|file| format!("{}\n", file))
+        .collect();
+    write(&path, &text)
+}
+
+/// Where the recent files are kept, one per line
+fn state_path() -> Option<PathBuf> {
+    let dirs = directories::ProjectDirs::from("", "", "lite")?;
+    let dir = dirs.state_dir().unwrap_or(dirs.cache_dir());
+    Some(dir.join("recent-files"))
+}
+
+fn parse(text: &str) -> Vec<PathBuf> {
+    text.lines()
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .take(MAX_RECENT_FILES)
+        .collect()
+}
+
+/// `ours` then the rest of `theirs`, without repeats
+fn merge(ours: &[PathBuf], theirs: Vec<PathBuf>) -> Vec<PathBuf> {
+    let mut merged = ours.to_vec();
+    for file in theirs {
+        if !merged.contains(&file) {
+            merged.push(file);
+        }
+    }
+    merged.truncate(MAX_RECENT_FILES);
+    merged
+}
+
+/// Replace the file through a rename, so a crash never leaves half of it
+pub fn write(path: &Path, text: &str) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let temp = path.with_extension("tmp");
+    fs::write(&temp, text)?;
+    fs::rename(&temp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge() {
+        let theirs = parse("/b\n/c\n\n/a\n");
+        assert_eq!(theirs, vec![PathBuf::from("/b"), PathBuf::from("/c"), PathBuf::from("/a")]);
+        let ours = [PathBuf::from("/a"), PathBuf::from("/d")];
+        let merged = merge(&ours, theirs);
+        assert_eq!(merged, ["/a", "/d", "/b", "/c"].map(PathBuf::from));
+
+        let many: Vec<PathBuf> = (0..MAX_RECENT_FILES + 5).map(|i| PathBuf::from(format!("/{}", i))).collect();
+        assert_eq!(merge(&many, Vec::new()).len(), MAX_RECENT_FILES);
+    }
+}
@@ -0,0 +1,240 @@
+//! Recordings of the input for bug reports: `:debug-record` writes the
+//! events and the actions keys ran to a file, one per line after the
+//! milliseconds since recording began, and `:debug-replay` feeds the events
+//! back into a scratch editor. Keys typed are recorded, buffer contents
+//! never are: text typed is written as the kind of each char, `a` for a
+//! letter, `0` for a digit and `.` for most punctuation, so a recording
+//! holds neither what's typed nor the plaintext of an encrypted buffer.
+//! Spaces, brackets and quotes, which editing treats specially, are kept.
+//!
+//! ```text
+//! # lite 0.1.0 recording
+//! 0 resize 80 24
+//! 812 key Ctrl+F
+//! 812 action Find
+//! 1403 char a
+//! 1520 release
+//! ```
+
+use crate::Event;
+use lite_config::{Action, Key, KeyEvent, Modifier};
+use std::fs::{self, File};
+use std::io::{self, LineWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Writes the input to a file while recording
+pub struct Recorder {
+    /// The recording, and when it began
+    file: Option<(LineWriter<File>, Instant)>,
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        Self { file: None }
+    }
+
+    /// Record into `path` from now on, over any earlier recording there.
+    /// The screen size comes first, so a replay starts from the same layout.
+    pub fn start(&mut self, path: &Path, width: u16, height: u16) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = LineWriter::new(File::create(path)?);
+        writeln!(file, "# lite {} recording", env!("CARGO_PKG_VERSION"))?;
+        self.file = Some((file, Instant::now()));
+        self.event(&Event::Resize(width, height));
+        Ok(())
+    }
+
+    /// Stop recording; returns whether it was
+    pub fn stop(&mut self) -> bool {
+        self.file.take().is_some()
+    }
+
+    /// Record an event. Ticks and the mouse, which the editor ignores,
+    /// aren't.
+    pub fn event(&mut self, event: &Event) {
+        if let Some(line) = format_event(event) {
+            self.write(&line);
+        }
+    }
+
+    /// Record the action a key ran, without what it carries, such as the
+    /// text of a search
+    pub fn action(&mut self, action: &Action) {
+        let action = format!("{:?}", action);
+        let name = action.split(['(', ' ']).next().unwrap_or_default();
+        self.write(&format!("action {}", name));
+    }
+
+    /// A recording that can't be written is lost; the editing goes on
+    fn write(&mut self, line: &str) {
+        if let Some((file, started)) = &mut self.file {
+            let _ = writeln!(file, "{} {}", started.elapsed().as_millis(), line);
+        }
+    }
+}
+
+impl Default for Recorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Where recordings go unless the command names a file
+pub fn default_path() -> Option<PathBuf> {
+    let dirs = directories::ProjectDirs::from("", "", "lite")?;
+    let dir = dirs.state_dir().unwrap_or(dirs.cache_dir());
+    Some(dir.join("recording.log"))
+}
+
+/// The events of a recording, in order
+pub fn load(path: &Path) -> io::Result<Vec<Event>> {
+    parse(&fs::read_to_string(path)?).map_err(io::Error::other)
+}
+
+fn parse(text: &str) -> Result<Vec<Event>, String> {
+    let mut events = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut parts = line.splitn(3, ' ');
+        let (_millis, kind, rest) = (parts.next(), parts.next(), parts.next().unwrap_or(""));
+        let event = match kind {
+            Some("action") => continue,
+            Some(kind) => parse_event(kind, rest),
+            None => None,
+        };
+        match event {
+            Some(event) => events.push(event),
+            None => return Err(format!("line {}: can't read {}", i + 1, line)),
+        }
+    }
+    Ok(events)
+}
+
+/// Characters typed alone or with Shift are written as their kind, see
+/// [`char_kind`]. Those typed with Ctrl or Alt are keys.
+fn format_event(event: &Event) -> Option<String> {
+    match event {
+        Event::Key(KeyEvent {
+            key: Key::Char(c),
+            modifiers,
+        }) if !modifiers.ctrl && !modifiers.alt => {
+            Some(format!("char {}{}", if modifiers.shift { "Shift+" } else { "" }, char_kind(*c)))
+        }
+        Event::Key(key) => Some(format!("key {}", key)),
+        Event::KeyRelease(held) => {
+            let names = [(held.ctrl, " ctrl"), (held.alt, " alt"), (held.shift, " shift")];
+            Some(names.iter().filter(|(on, _)| *on).fold("release".to_string(), |line, (_, name)| line + name))
+        }
+        Event::Resize(width, height) => Some(format!("resize {} {}", width, height)),
+        Event::FocusLost => Some("focus-lost".to_string()),
+        Event::Preedit(text) => Some(format!("preedit {}", text.chars().map(char_kind).collect::<String>())),
+        Event::Commit(text) => Some(format!("commit {}", text.chars().map(char_kind).collect::<String>())),
+        Event::Mouse(_) | Event::Tick => None,
+    }
+}
+
+/// A char standing for the kind of one typed: `a` or `A` for a letter, `0`
+/// for a digit, whitespace, brackets and quotes as they are, and `.` for
+/// the rest
+fn char_kind(c: char) -> char {
+    match c {
+        _ if c.is_uppercase() => 'A',
+        _ if c.is_alphabetic() => 'a',
+        _ if c.is_numeric() => '0',
+        ' ' | '\t' | '(' | ')' | '[' | ']' | '{' | '}' | '<' | '>' | '"' | '\'' | '`' => c,
+        _ => '.',
+    }
+}
+
+fn parse_event(kind: &str, rest: &str) -> Option<Event> {
+    match kind {
+        "char" => {
+            let (shift, c) = match rest.chars().count() {
+                1 => (false, rest),
+                _ => (true, rest.strip_prefix("Shift+")?),
+            };
+            let mut chars = c.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Some(Event::Key(KeyEvent::new(
+                    Key::Char(c),
+                    Modifier {
+                        shift,
+                        ..Modifier::NONE
+                    },
+                ))),
+                _ => None,
+            }
+        }
+        "key" => rest.parse().ok().map(Event::Key),
+        "release" => {
+            let mut held = Modifier::NONE;
+            for name in rest.split_whitespace() {
+                match name {
+                    "ctrl" => held.ctrl = true,
+                    "alt" => held.alt = true,
+                    "shift" => held.shift = true,
+                    _ => return None,
+                }
+            }
+            Some(Event::KeyRelease(held))
+        }
+        "focus-lost" => Some(Event::FocusLost),
+        "preedit" => Some(Event::Preedit(rest.to_string())),
+        "commit" => Some(Event::Commit(rest.to_string())),
+        "resize" => {
+            let (width, height) = rest.split_once(' ')?;
+            Some(Event::Resize(width.parse().ok()?, height.parse().ok()?))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let events = [
+            Event::Resize(80, 24),
+            Event::Key(KeyEvent::new(Key::Char('a'), Modifier::NONE)),
+            Event::Key(KeyEvent::new(Key::Char('A'), Modifier::SHIFT)),
+            Event::Key(KeyEvent::new(Key::Char('.'), Modifier::NONE)),
+            Event::Key(KeyEvent::new(Key::Char(' '), Modifier::NONE)),
+            Event::Key(KeyEvent::ctrl('s')),
+            Event::Key(KeyEvent::new(Key::Char('+'), Modifier::CTRL)),
+            Event::Key(KeyEvent::new(Key::Tab, Modifier::SHIFT)),
+            Event::KeyRelease(Modifier::NONE),
+            Event::KeyRelease(Modifier::CTRL_SHIFT),
+            Event::FocusLost,
+            Event::Preedit("aa".to_string()),
+            Event::Preedit(String::new()),
+            Event::Commit("a0 (.)".to_string()),
+        ];
+        let text: String = events
+            .iter()
+            .map(|event| format!("0 {}\n", format_event(event).unwrap()))
+            .collect();
+        let text = format!("# lite recording\n{}12 action Save\n", text);
+        let parsed = parse(&text).unwrap();
+        assert_eq!(format!("{:?}", parsed), format!("{:?}", events));
+
+        assert!(format_event(&Event::Tick).is_none());
+
+        // What's typed is written as the kind of each char
+        let typed = |c, modifiers| format_event(&Event::Key(KeyEvent::new(Key::Char(c), modifiers))).unwrap();
+        assert_eq!(typed('p', Modifier::NONE), "char a");
+        assert_eq!(typed('W', Modifier::SHIFT), "char Shift+A");
+        assert_eq!(typed('7', Modifier::NONE), "char 0");
+        assert_eq!(typed('#', Modifier::NONE), "char .");
+        assert_eq!(typed('(', Modifier::NONE), "char (");
+        assert_eq!(typed('x', Modifier::CTRL), "key Ctrl+X");
+        assert_eq!(format_event(&Event::Commit("日本 2!".to_string())).unwrap(), "commit aa 0.");
+        assert_eq!(parse("0 key Ctrl+Nope").unwrap_err(), "line 1: can't read 0 key Ctrl+Nope");
+    }
+}
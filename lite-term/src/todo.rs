@@ -0,0 +1,87 @@
+//! The TODO list buffer: comments found by the project scan, one per line
+//! as `path:line: text` so they can be followed like any file reference
+
+use lite_config::{Action, Key, KeyEvent, Modifier};
+use lite_core::Selection;
+use lite_view::{todo, BufferHandler, Document, DocumentId, Editor};
+
+/// Display name of the TODO buffer
+const TODO_BUFFER_NAME: &str = "todo";
+
+/// Rescan the project and show the TODO buffer, filled in once the scan ends
+pub fn open_todos(editor: &mut Editor) {
+    editor.scan_todos();
+    if let Some(doc_id) = editor.find_virtual(TODO_BUFFER_NAME) {
+        editor.switch_to_document(doc_id);
+    } else {
+        let doc = Document::new_virtual(TODO_BUFFER_NAME, render(editor));
+        editor.open_virtual(doc, Some(Box::new(TodoHandler)));
+    }
+    refresh(editor);
+}
+
+/// Show the latest scan in the TODO buffer, if it's open
+pub fn refresh(editor: &mut Editor) {
+    let Some(doc_id) = editor.find_virtual(TODO_BUFFER_NAME) else {
+        return;
+    };
+    let text = render(editor);
+    if let Some(doc) = editor.documents.get_mut(&doc_id) {
+        if doc.text() != text {
+            doc.set_text(text);
+        }
+    }
+}
+
+/// Text of the TODO buffer
+fn render(editor: &Editor) -> String {
+    if editor.scanning_todos() {
+        return "Scanning...\n".to_string();
+    }
+    let todos = editor.todos.as_deref().unwrap_or_default();
+    if todos.is_empty() {
+        return "No TODO comments found\n".to_string();
+    }
+
+    let keywords = &editor.config.editor.todo_keywords;
+    let counts: Vec<String> = todo::counts(todos, keywords)
+        .into_iter()
+        .map(|(keyword, n)| format!("{} {}", keyword, n))
+        .collect();
+    let mut out = format!("{}  (Enter to open, r to rescan, q to close)\n\n", counts.join("  "));
+    for todo in todos {
+        out.push_str(&format!("{}:{}: {}\n", todo.path.display(), todo.line + 1, todo.text));
+    }
+    out
+}
+
+/// Key handler for the TODO buffer
+struct TodoHandler;
+
+impl BufferHandler for TodoHandler {
+    fn handle_key(&mut self, editor: &mut Editor, doc_id: DocumentId, key: &KeyEvent) -> bool {
+        match (&key.key, key.modifiers) {
+            (Key::Enter, Modifier::NONE) => {
+                // Lines start with a file reference
+                let view_id = editor.tree.focus();
+                if let Some(doc) = editor.documents.get_mut(&doc_id) {
+                    let line = doc.rope.char_to_line(doc.selection(view_id).cursor());
+                    let start = doc.rope.line_to_char(line);
+                    doc.set_selection(view_id, Selection::point(start));
+                }
+                crate::execute_action(editor, &Action::OpenLink);
+                true
+            }
+            (Key::Char('r'), Modifier::NONE) => {
+                editor.scan_todos();
+                refresh(editor);
+                true
+            }
+            (Key::Char('q'), Modifier::NONE) | (Key::Escape, Modifier::NONE) => {
+                editor.close_buffer(true);
+                true
+            }
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,143 @@
+//! Checking for a newer release. The answer is kept in the state directory
+//! for a day, so starting the editor often doesn't hit the network.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// GitHub repository releases are published in
+pub const RELEASE_REPO: &str = "IrfanArsyad/lite";
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// How long a check's answer is trusted
+const CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Version of the latest release, without the leading `v`
+pub fn latest_release() -> Option<String> {
+    let url = format!("https://api.github.com/repos/{}/releases/latest", RELEASE_REPO);
+    let output = Command::new("curl")
+        .args(["-sL", "--connect-timeout", "2", &url])
+        .output()
+        .ok()?;
+    parse_tag(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// The latest release if it's newer than this version, asking GitHub at
+/// most once per `CHECK_INTERVAL`
+pub fn newer_release() -> Option<String> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    let path = cache_path();
+    let cached = path
+        .as_deref()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|text| cached_release(&text, now));
+    let latest = match cached {
+        Some(latest) => latest,
+        None => {
+            let latest = latest_release();
+            if let Some(path) = &path {
+                let _ = write_cache(path, now, latest.as_deref());
+            }
+            latest
+        }
+    };
+    latest.filter(|latest| is_newer(latest, VERSION))
+}
+
+/// Whether a release is a newer version than `current`, compared as semver
+/// `major.minor.patch`. Pre-releases, such as `0.3.0-beta.1`, and tags that
+/// aren't versions are never offered as updates.
+pub fn is_newer(release: &str, current: &str) -> bool {
+    fn parse(version: &str) -> Option<(Vec<u64>, bool)> {
+        let version = version.trim().trim_start_matches('v');
+        let version = version.split_once('+').map_or(version, |(version, _build)| version);
+        let (core, pre) = match version.split_once('-') {
+            Some((core, _)) => (core, true),
+            None => (version, false),
+        };
+        let mut parts = core.split('.').map(|part| part.parse().ok()).collect::<Option<Vec<u64>>>()?;
+        if parts.len() > 3 {
+            return None;
+        }
+        parts.resize(3, 0);
+        Some((parts, pre))
+    }
+    match (parse(release), parse(current)) {
+        // A release comes after the pre-releases of its version
+        (Some((release, false)), Some((current, pre))) => (release, true) > (current, !pre),
+        _ => false,
+    }
+}
+
+/// `tag_name` of a GitHub release, without the leading `v`
+fn parse_tag(json: &str) -> Option<String> {
+    let rest = &json[json.find("\"tag_name\"")?..];
+    let value = rest[rest.find(':')? + 1..].trim_start().strip_prefix('"')?;
+    let tag = &value[..value.find('"')?];
+    Some(tag.trim_start_matches('v').to_string())
+}
+
+/// Where the last check's time and answer are kept
+fn cache_path() -> Option<PathBuf> {
+    let dirs = directories::ProjectDirs::from("", "", "lite")?;
+    let dir = dirs.state_dir().unwrap_or(dirs.cache_dir());
+    Some(dir.join("update-check"))
+}
+
+/// The answer of a check cached as `<unix time>\n<version>`, if it's recent.
+/// An empty version means the check failed.
+fn cached_release(text: &str, now: u64) -> Option<Option<String>> {
+    let (time, version) = text.split_once('\n')?;
+    let age = now.checked_sub(time.trim().parse().ok()?)?;
+    if age >= CHECK_INTERVAL.as_secs() {
+        return None;
+    }
+    let version = version.trim();
+    Some((!version.is_empty()).then(|| version.to_string()))
+}
+
+/// Replace the cache through a rename, so a crash never leaves half a file
+fn write_cache(path: &Path, now: u64, version: Option<&str>) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let temp = path.with_extension("tmp");
+    fs::write(&temp, format!("{}\n{}\n", now, version.unwrap_or_default()))?;
+    fs::rename(&temp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cached_release() {
+        let json = r#"{"url": "...", "tag_name": "v0.2.0", "name": "lite 0.2.0"}"#;
+        assert_eq!(parse_tag(json).as_deref(), Some("0.2.0"));
+        assert_eq!(parse_tag("{}"), None);
+
+        let day = CHECK_INTERVAL.as_secs();
+        assert_eq!(cached_release("1000\n0.2.0\n", 1000 + day - 1), Some(Some("0.2.0".to_string())));
+        assert_eq!(cached_release("1000\n\n", 1000), Some(None));
+        // Stale, from the future or garbled: check again
+        assert_eq!(cached_release("1000\n0.2.0\n", 1000 + day), None);
+        assert_eq!(cached_release("1000\n0.2.0\n", 999), None);
+        assert_eq!(cached_release("soon\n0.2.0\n", 1000), None);
+    }
+
+    #[test]
+    fn test_is_newer() {
+        assert!(is_newer("0.2.0", "0.1.9"));
+        assert!(is_newer("0.10.0", "0.9.0"));
+        assert!(is_newer("1.0", "0.9.3"));
+        assert!(is_newer("0.3.0", "0.3.0-beta.1"));
+        // Older, the same or a pre-release: no update
+        assert!(!is_newer("0.1.0", "0.2.0"));
+        assert!(!is_newer("0.2.0", "0.2.0"));
+        assert!(!is_newer("0.2.0+build.5", "0.2.0"));
+        assert!(!is_newer("0.3.0-rc.1", "0.2.0"));
+        assert!(!is_newer("nightly", "0.2.0"));
+    }
+}
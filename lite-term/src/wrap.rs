@@ -0,0 +1,176 @@
+//! Hard wrapping of paragraphs and comment blocks
+
+use lite_core::Rope;
+
+/// Comment and quote markers kept at the start of wrapped lines, longest
+/// first. `*` continues block comments.
+const LINE_MARKERS: &[&str] = &["//!", "///", "//", "--", "#", ";", ">", "*"];
+
+/// Markdown has no comments: only quotes are kept, and `*` is a bullet
+const MARKDOWN_LINE_MARKERS: &[&str] = &[">"];
+
+/// Split a line into its prefix (indentation, comment marker and the space
+/// after it) and its content
+fn split_prefix(line: &str, markdown: bool) -> (&str, &str) {
+    let markers = if markdown { MARKDOWN_LINE_MARKERS } else { LINE_MARKERS };
+    let indent = line.len() - line.trim_start().len();
+    let rest = &line[indent..];
+    let Some(marker) = markers.iter().find(|m| rest.starts_with(**m)) else {
+        return line.split_at(indent);
+    };
+    let after = &rest[marker.len()..];
+    let spaces = after.len() - after.trim_start().len();
+    line.split_at(indent + marker.len() + spaces)
+}
+
+/// The comment marker of a line, without indentation and spacing
+fn marker(line: &str, markdown: bool) -> &str {
+    split_prefix(line, markdown).0.trim()
+}
+
+/// Length of the list marker at the start of a line's content, as in
+/// `- `, `* `, `+ `, `1. ` and `2) `, with the spaces after it
+fn list_marker(content: &str) -> Option<usize> {
+    let digits = content.len() - content.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let len = match content[digits..].chars().next()? {
+        '-' | '*' | '+' if digits == 0 => 1,
+        '.' | ')' if (1..=9).contains(&digits) => digits + 1,
+        _ => return None,
+    };
+    let after = &content[len..];
+    let spaces = after.len() - after.trim_start_matches(' ').len();
+    (spaces > 0 && !after.trim().is_empty()).then_some(len + spaces)
+}
+
+/// Get a line's text without its line ending
+pub fn line_text(rope: &Rope, line: usize) -> String {
+    let text: String = rope.line(line).chars().collect();
+    text.trim_end_matches(['\n', '\r']).to_string()
+}
+
+/// Find the paragraph around `line`: adjacent non-blank lines sharing its
+/// comment marker, a list item starting a paragraph of its own. Returns
+/// the first and last line, or `None` on a blank line.
+pub fn paragraph_at(rope: &Rope, line: usize, markdown: bool) -> Option<(usize, usize)> {
+    let text = line_text(rope, line);
+    if split_prefix(&text, markdown).1.trim().is_empty() {
+        return None;
+    }
+
+    let is_item = |other: usize| list_marker(split_prefix(&line_text(rope, other), markdown).1).is_some();
+    let belongs = |other: usize| {
+        let other = line_text(rope, other);
+        !split_prefix(&other, markdown).1.trim().is_empty() && marker(&other, markdown) == marker(&text, markdown)
+    };
+
+    let mut start = line;
+    while start > 0 && !is_item(start) && belongs(start - 1) {
+        start -= 1;
+    }
+    let mut end = line;
+    while end + 1 < rope.len_lines() && belongs(end + 1) && !is_item(end + 1) {
+        end += 1;
+    }
+    Some((start, end))
+}
+
+/// Rewrap lines so none is longer than `width` columns where possible.
+///
+/// Every line gets the first line's prefix, so comment blocks stay comments.
+/// A list marker only starts the first line; the lines after it are
+/// indented to line up with its text. Words longer than the width get a
+/// line of their own.
+pub fn hard_wrap(lines: &[String], width: usize, markdown: bool) -> Vec<String> {
+    let Some(first) = lines.first() else {
+        return Vec::new();
+    };
+    let (prefix, content) = split_prefix(first, markdown);
+    let item = list_marker(content).unwrap_or(0);
+    let first_prefix = &first[..prefix.len() + item];
+    let prefix = format!("{}{}", prefix, " ".repeat(item));
+    let prefix_width = prefix.chars().count();
+
+    let mut wrapped = Vec::new();
+    let mut current = String::new();
+    let mut current_width = 0;
+    let words = std::iter::once(&content[item..])
+        .chain(lines[1..].iter().map(|line| split_prefix(line, markdown).1))
+        .flat_map(str::split_whitespace);
+    for word in words {
+        let word_width = word.chars().count();
+        if current_width > 0 && prefix_width + current_width + 1 + word_width > width {
+            let line_prefix = if wrapped.is_empty() { first_prefix } else { &prefix };
+            wrapped.push(format!("{}{}", line_prefix, current));
+            current.clear();
+            current_width = 0;
+        }
+        if current_width > 0 {
+            current.push(' ');
+            current_width += 1;
+        }
+        current.push_str(word);
+        current_width += word_width;
+    }
+
+    if current_width > 0 {
+        let line_prefix = if wrapped.is_empty() { first_prefix } else { &prefix };
+        wrapped.push(format!("{}{}", line_prefix, current));
+    }
+    if wrapped.is_empty() {
+        return lines.to_vec();
+    }
+    wrapped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_hard_wrap() {
+        let wrapped = hard_wrap(&lines("one two three\nfour five six seven"), 14, false);
+        assert_eq!(wrapped, lines("one two three\nfour five six\nseven"));
+    }
+
+    #[test]
+    fn test_hard_wrap_keeps_comment_prefix() {
+        let text = "    // A comment that is much too long\n    //   for one line";
+        let wrapped = hard_wrap(&lines(text), 24, false);
+        assert_eq!(
+            wrapped,
+            lines("    // A comment that is\n    // much too long for\n    // one line")
+        );
+    }
+
+    #[test]
+    fn test_hard_wrap_list_items() {
+        let wrapped = hard_wrap(&lines("* one two three four"), 10, true);
+        assert_eq!(wrapped, lines("* one two\n  three\n  four"));
+        let wrapped = hard_wrap(&lines("  12. one two\n      three four"), 14, true);
+        assert_eq!(wrapped, lines("  12. one two\n      three\n      four"));
+        // In code, `*` continues a block comment and a list can follow it
+        let wrapped = hard_wrap(&lines(" * - one two three"), 12, false);
+        assert_eq!(wrapped, lines(" * - one two\n *   three"));
+        let wrapped = hard_wrap(&lines(" * one two three"), 10, false);
+        assert_eq!(wrapped, lines(" * one two\n * three"));
+    }
+
+    #[test]
+    fn test_paragraph_at() {
+        let rope = Rope::from("intro\n\n// one\n// two\ncode\n\nlast");
+        assert_eq!(paragraph_at(&rope, 2, false), Some((2, 3)));
+        assert_eq!(paragraph_at(&rope, 4, false), Some((4, 4)));
+        assert_eq!(paragraph_at(&rope, 1, false), None);
+        assert_eq!(paragraph_at(&rope, 6, false), Some((6, 6)));
+
+        // Each list item is a paragraph
+        let rope = Rope::from("- one\n  more\n- two\n* three\n");
+        assert_eq!(paragraph_at(&rope, 1, true), Some((0, 1)));
+        assert_eq!(paragraph_at(&rope, 2, true), Some((2, 2)));
+        assert_eq!(paragraph_at(&rope, 3, true), Some((3, 3)));
+    }
+}
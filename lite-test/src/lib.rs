@@ -0,0 +1,874 @@
+//! End-to-end tests for lite: the whole application drawing to a fake
+//! terminal, driven by scripted events
+//!
+//! ```
+//! use lite_test::TestApp;
+//!
+//! let mut app = TestApp::new(80, 24);
+//! app.type_text("one\ntwo").keys(&["Ctrl+G"]);
+//! app.assert_screen_contains("Goto line: ");
+//! app.type_text("1").keys(&["Enter"]);
+//! assert_eq!(app.cursor(), (0, 0));
+//! ```
+
+use lite_config::{Key, KeyEvent, Modifier};
+use lite_core::RopeExt;
+use lite_term::{Application, Event};
+use lite_view::Editor;
+use ratatui::backend::TestBackend;
+use std::path::Path;
+use tokio::runtime::Runtime;
+
+/// The application on a fake terminal. Every call handles its events, then
+/// updates and draws the screen as the main loop would.
+pub struct TestApp {
+    app: Application<TestBackend>,
+    /// Runtime language servers are started on
+    runtime: Runtime,
+}
+
+impl TestApp {
+    /// An application with the default settings and an empty buffer on a
+    /// terminal of the given size
+    pub fn new(width: u16, height: u16) -> Self {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("cannot start a runtime");
+        let app = Application::with_frontend(TestBackend::new(width, height)).expect("cannot start lite");
+        let mut test = Self { app, runtime };
+        test.send([]);
+        test
+    }
+
+    /// Open a file
+    pub fn open(&mut self, path: impl AsRef<Path>) -> &mut Self {
+        let path = path.as_ref();
+        if let Err(e) = self.app.open(&path.to_string_lossy()) {
+            panic!("cannot open {}: {}", path.display(), e);
+        }
+        self.send([])
+    }
+
+    /// Handle events as if the terminal had sent them, all at once, and
+    /// wait for the background jobs they started
+    pub fn send(&mut self, events: impl IntoIterator<Item = Event>) -> &mut Self {
+        let _runtime = self.runtime.enter();
+        self.app.handle_events(events).expect("cannot handle events");
+        if self.app.editor_mut().wait_jobs() {
+            self.app.handle_events([]).expect("cannot handle events");
+        }
+        self
+    }
+
+    /// Press keys named as in keymaps, such as `"Ctrl+Shift+K"` or `"Enter"`,
+    /// one after the other
+    pub fn keys(&mut self, keys: &[&str]) -> &mut Self {
+        for key in keys {
+            let key: KeyEvent = key.parse().unwrap_or_else(|e| panic!("{}", e));
+            self.send([Event::Key(key)]);
+        }
+        self
+    }
+
+    /// Type text as one burst of keys, the way a fast typist or a paste in
+    /// a terminal without bracketed paste sends it. `\n` is Enter.
+    pub fn type_text(&mut self, text: &str) -> &mut Self {
+        let events: Vec<Event> = text
+            .chars()
+            .map(|c| match c {
+                '\n' => KeyEvent::new(Key::Enter, Modifier::NONE),
+                c if c.is_uppercase() => KeyEvent::new(Key::Char(c), Modifier::SHIFT),
+                c => KeyEvent::new(Key::Char(c), Modifier::NONE),
+            })
+            .map(Event::Key)
+            .collect();
+        self.send(events)
+    }
+
+    /// Resize the terminal
+    pub fn resize(&mut self, width: u16, height: u16) -> &mut Self {
+        self.app.frontend_mut().resize(width, height);
+        self.send([Event::Resize(width, height)])
+    }
+
+    pub fn editor(&self) -> &Editor {
+        self.app.editor()
+    }
+
+    pub fn editor_mut(&mut self) -> &mut Editor {
+        self.app.editor_mut()
+    }
+
+    /// Text of the current buffer
+    pub fn text(&self) -> String {
+        self.editor().current_doc().text()
+    }
+
+    /// Line and column of the cursor in the current buffer
+    pub fn cursor(&self) -> (usize, usize) {
+        let editor = self.editor();
+        let doc = editor.current_doc();
+        let pos = doc.rope.char_to_position(doc.selection(editor.tree.focus()).cursor());
+        (pos.line, pos.col)
+    }
+
+    /// Rows of the screen as last drawn, with trailing spaces trimmed
+    pub fn screen(&self) -> Vec<String> {
+        let buffer = self.app.frontend().buffer();
+        let area = buffer.area;
+        (area.top()..area.bottom())
+            .map(|y| {
+                let row: String = (area.left()..area.right()).map(|x| buffer[(x, y)].symbol()).collect();
+                row.trim_end().to_string()
+            })
+            .collect()
+    }
+
+    /// Panic with the screen if no row of it contains `text`
+    #[track_caller]
+    pub fn assert_screen_contains(&self, text: &str) {
+        let screen = self.screen();
+        if !screen.iter().any(|row| row.contains(text)) {
+            panic!("{:?} is not on the screen:\n{}", text, screen.join("\n"));
+        }
+    }
+
+    /// Panic with the screen if a row of it contains `text`
+    #[track_caller]
+    pub fn assert_screen_lacks(&self, text: &str) {
+        let screen = self.screen();
+        if screen.iter().any(|row| row.contains(text)) {
+            panic!("{:?} is on the screen:\n{}", text, screen.join("\n"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lite_config::IndentStyle;
+    use lite_view::{Annotation, Placement};
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// An empty directory for a test's files
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("lite-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_typing_and_undo() {
+        let mut app = TestApp::new(60, 12);
+        app.type_text("Hello\nbig world");
+        assert_eq!(app.text(), "Hello\nbig world");
+        assert_eq!(app.cursor(), (1, 9));
+        app.assert_screen_contains("big world");
+
+        // A burst of typing between two other keys is one edit
+        app.keys(&["Ctrl+Z"]);
+        assert_eq!(app.text(), "Hello\n");
+        app.assert_screen_lacks("world");
+
+        // A burst too long for one frame is split between frames
+        let mut app = TestApp::new(60, 12);
+        app.type_text(&"a".repeat(1500));
+        assert_eq!(app.text().len(), 1500);
+        app.keys(&["Ctrl+Z"]);
+        assert_eq!(app.text().len(), 1024);
+    }
+
+    #[test]
+    fn test_prompts() {
+        let mut app = TestApp::new(60, 12);
+        app.type_text("one\ntwo\nthree");
+        app.keys(&["Ctrl+G"]);
+        app.assert_screen_contains("Goto line: ");
+        app.type_text("2").keys(&["Enter"]);
+        assert_eq!(app.cursor(), (1, 0));
+        app.assert_screen_lacks("Goto line: ");
+
+        // Escape leaves a prompt without running it
+        app.keys(&["Ctrl+Shift+P"]).type_text("json-format").keys(&["Escape"]);
+        assert_eq!(app.text(), "one\ntwo\nthree");
+
+        app.keys(&["Ctrl+F"]).type_text("three").keys(&["Enter"]);
+        assert_eq!(app.cursor().0, 2);
+    }
+
+    #[test]
+    fn test_pickers() {
+        let dir = temp_dir("pickers");
+        fs::write(dir.join("notes.md"), "# Notes\n\ntext\n\n## Usage\n\nmore\n").unwrap();
+        fs::write(dir.join("other.txt"), "other\n").unwrap();
+        let mut app = TestApp::new(100, 20);
+        app.open(dir.join("notes.md")).open(dir.join("other.txt"));
+
+        // Buffers and symbols, narrowed by a fuzzy query
+        app.keys(&["Ctrl+Shift+B"]);
+        app.assert_screen_contains("Buffers");
+        app.type_text("nts").keys(&["Enter"]);
+        assert_eq!(app.editor().current_doc().name(), "notes.md");
+
+        app.keys(&["Ctrl+R"]);
+        app.assert_screen_contains("Symbols (2/2)");
+        app.type_text("usage");
+        app.assert_screen_contains("Symbols (1/2)");
+        app.keys(&["Enter"]);
+        assert_eq!(app.cursor(), (4, 0));
+        app.assert_screen_lacks("Symbols");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_open_recent() {
+        let dir = temp_dir("recent");
+        fs::write(dir.join("first.txt"), "first\n").unwrap();
+        fs::write(dir.join("second.txt"), "second\n").unwrap();
+        let mut app = TestApp::new(100, 20);
+        app.open(dir.join("first.txt")).open(dir.join("second.txt"));
+        assert_eq!(app.editor().documents.len(), 2);
+
+        // Closed files stay recent
+        app.keys(&["Ctrl+W", "Alt+O"]);
+        assert_eq!(app.editor().documents.len(), 1);
+        app.assert_screen_contains("Recent files (2/2)");
+        app.keys(&["Enter"]);
+        assert_eq!(app.editor().current_doc().name(), "second.txt");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_debug_record() {
+        let dir = temp_dir("record");
+        let path = dir.join("recording.log");
+        let mut app = TestApp::new(80, 12);
+        app.keys(&["Ctrl+Shift+P"])
+            .type_text(&format!("debug-record {}", path.display()))
+            .keys(&["Enter"]);
+        app.type_text("Hi").keys(&["Left"]).type_text("!");
+        app.keys(&["Ctrl+Shift+P"]).type_text("debug-record off").keys(&["Enter"]);
+        assert_eq!(app.text(), "H!i");
+        let recording = fs::read_to_string(&path).unwrap();
+        assert!(recording.contains(" resize 80 12\n"));
+        // What was typed is only there as the kind of each char
+        assert!(recording.contains(" char Shift+A\n"));
+        assert!(!recording.contains("char i") && !recording.contains("off"));
+        assert!(recording.contains(" action MoveLeft\n"));
+
+        // Into a scratch editor, leaving the session alone until it's back
+        let mut replay = TestApp::new(80, 12);
+        replay.type_text("mine");
+        replay
+            .keys(&["Ctrl+Shift+P"])
+            .type_text(&format!("debug-replay {}", path.display()))
+            .keys(&["Enter"]);
+        assert_eq!(replay.text(), "A.a");
+        replay.assert_screen_contains("Replayed");
+        replay.keys(&["Ctrl+S"]);
+        replay.assert_screen_contains("Nothing is saved during a replay");
+        replay.keys(&["Ctrl+Shift+P"]).type_text("debug-replay off").keys(&["Enter"]);
+        assert_eq!(replay.text(), "mine");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_auto_save_triggers() {
+        let dir = temp_dir("auto-save");
+        fs::write(dir.join("a.txt"), "a\n").unwrap();
+        fs::write(dir.join("b.txt"), "b\n").unwrap();
+        let mut app = TestApp::new(80, 12);
+        app.open(dir.join("a.txt")).type_text("1");
+        // Off by default
+        app.open(dir.join("b.txt"));
+        assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "a\n");
+
+        app.editor_mut().config.editor.auto_save_on.buffer_switch = true;
+        app.type_text("2").keys(&["Ctrl+Tab", "Enter"]);
+        assert_eq!(app.editor().current_doc().name(), "a.txt");
+        assert_eq!(fs::read_to_string(dir.join("b.txt")).unwrap(), "2b\n");
+
+        app.editor_mut().config.editor.auto_save_on.focus_lost = true;
+        app.send([Event::FocusLost]);
+        assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "1a\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_incremental_search() {
+        let mut app = TestApp::new(80, 12);
+        app.type_text("one two\nthree two\n").keys(&["Ctrl+Home", "Right"]);
+        // Each key selects the first match from where the search started
+        app.keys(&["Ctrl+F"]).type_text("t");
+        assert_eq!(app.cursor(), (0, 5));
+        app.type_text("h");
+        assert_eq!(app.cursor(), (1, 2));
+        app.keys(&["Backspace", "Backspace"]);
+        assert_eq!(app.cursor(), (0, 1));
+        // Cancelling goes back
+        app.type_text("two");
+        assert_eq!(app.cursor(), (0, 7));
+        app.keys(&["Escape"]);
+        assert_eq!(app.cursor(), (0, 1));
+        app.assert_screen_lacks("/two");
+        // Enter keeps the match
+        app.keys(&["Ctrl+F"]).type_text("two\n");
+        assert_eq!(app.cursor(), (0, 7));
+        app.keys(&["F3"]);
+        assert_eq!(app.cursor(), (1, 9));
+    }
+
+    #[test]
+    fn test_search_case() {
+        let mut app = TestApp::new(80, 12);
+        app.type_text("Error error\nERROR\n").keys(&["Ctrl+Home"]);
+        // Smart case: lower case finds every case, capitals only themselves
+        app.keys(&["Ctrl+F"]).type_text("error");
+        app.assert_screen_contains("match 1 of 3");
+        app.type_text("\n").keys(&["Ctrl+Home", "Ctrl+F"]).type_text("Error");
+        app.assert_screen_contains("match 1 of 1");
+
+        // Alt+C goes on to case sensitive, then ignoring case
+        app.keys(&["Escape", "Ctrl+F"]).type_text("error").keys(&["Alt+C"]);
+        app.assert_screen_contains("(Aa) /error");
+        app.assert_screen_contains("match 1 of 1");
+        assert_eq!(app.cursor(), (0, 11));
+        app.keys(&["Alt+C"]);
+        app.assert_screen_contains("(aa) /error");
+        app.keys(&["Escape", "Ctrl+Home", "Ctrl+F"]).type_text("Error");
+        app.assert_screen_contains("match 1 of 3");
+
+        // Replacing the search ignores case as it did
+        app.keys(&["Enter", "Ctrl+H"]);
+        app.assert_screen_contains(":s/Error//i");
+        app.type_text("x\n");
+        assert_eq!(app.text(), "x x\nx\n");
+    }
+
+    #[test]
+    fn test_search_whole_word() {
+        let mut app = TestApp::new(80, 12);
+        app.type_text("identifier = id;\nid_2 = id + 1;\n").keys(&["Ctrl+Home"]);
+        app.keys(&["Ctrl+F"]).type_text("id");
+        app.assert_screen_contains("match 1 of 4");
+        app.keys(&["Alt+W"]);
+        app.assert_screen_contains("(word) /id");
+        app.assert_screen_contains("match 1 of 2");
+        assert_eq!(app.cursor(), (0, 15));
+        app.keys(&["Enter", "F3"]);
+        assert_eq!(app.cursor(), (1, 9));
+
+        // Replacing the search keeps to whole words too
+        app.keys(&["Ctrl+H"]);
+        app.assert_screen_contains(":s/\\bid\\b//i");
+        app.type_text("key\n");
+        assert_eq!(app.text(), "identifier = key;\nid_2 = key + 1;\n");
+    }
+
+    #[test]
+    fn test_replace() {
+        let mut app = TestApp::new(80, 12);
+        app.type_text("max(a.b, c)\nmax(axb, d)\n");
+        app.keys(&["Ctrl+F"]).type_text("a.b\n").keys(&["Ctrl+Home"]);
+        // The prompt starts with the search, as a regex
+        app.keys(&["Ctrl+H"]);
+        app.assert_screen_contains("s/a\\.b/");
+        app.type_text("<$0>\n");
+        assert_eq!(app.text(), "max(<a.b>, c)\nmax(axb, d)\n");
+        // Groups by number
+        app.keys(&["Ctrl+Shift+P"]).type_text("s/max\\((\\S+), (\\w+)\\)/max($2, $1)\n");
+        assert_eq!(app.text(), "max(c, <a.b>)\nmax(d, axb)\n");
+
+        // With lines selected, only they change
+        app.keys(&["Ctrl+End"]).type_text("max(e, f)\n");
+        app.keys(&["Ctrl+F"]).type_text("max\n");
+        let view_id = app.editor().tree.focus();
+        let lines = lite_core::Selection::single(lite_core::Range::new(14, 36));
+        app.editor_mut().current_doc_mut().set_selection(view_id, lines);
+        app.keys(&["Ctrl+H"]);
+        app.assert_screen_contains("Replace in selection :s/max//s");
+        app.type_text("min\n");
+        assert_eq!(app.text(), "max(c, <a.b>)\nmin(d, axb)\nmin(e, f)\n");
+    }
+
+    #[test]
+    fn test_grep_replace() {
+        let dir = temp_dir("grep");
+        fs::write(dir.join("a.rs"), "let total = 1;\nprint(total);\n").unwrap();
+        fs::write(dir.join("b.rs"), "other\n").unwrap();
+        fs::write(dir.join("c.rs"), "total\n").unwrap();
+        let mut app = TestApp::new(100, 20);
+        app.editor_mut().add_workspace(&dir).unwrap();
+        app.open(dir.join("a.rs"));
+
+        app.keys(&["Ctrl+Shift+F"]).type_text("tot\n");
+        app.assert_screen_contains("3 lines match tot");
+        app.assert_screen_contains("a.rs:2: print(total);");
+        app.keys(&["Escape"]);
+
+        // Nothing changes until confirmed
+        app.keys(&["Ctrl+Shift+P"]).type_text("grep-replace /total/sum/\n");
+        app.assert_screen_contains("Replace 3 lines in 2 files? (y/n)");
+        app.assert_screen_contains("+2: print(sum);");
+        app.keys(&["n"]);
+        assert_eq!(fs::read_to_string(dir.join("a.rs")).unwrap(), "let total = 1;\nprint(total);\n");
+
+        // Open files change in their buffer, to be saved with the rest of
+        // their changes; the others on disk
+        app.type_text("x");
+        app.keys(&["Ctrl+Shift+P"]).type_text("grep-replace /total/sum/\n").type_text("y");
+        app.assert_screen_contains("Replaced in 2 files, 1 of them open and not saved yet");
+        assert_eq!(app.text(), "xlet sum = 1;\nprint(sum);\n");
+        assert_eq!(fs::read_to_string(dir.join("a.rs")).unwrap(), "let total = 1;\nprint(total);\n");
+        assert_eq!(fs::read_to_string(dir.join("c.rs")).unwrap(), "sum\n");
+        assert!(app.editor().current_doc().modified);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_grep_truncated() {
+        let dir = temp_dir("grep-truncated");
+        fs::write(dir.join("a.txt"), "match\n".repeat(10_005)).unwrap();
+        let mut app = TestApp::new(100, 20);
+        app.editor_mut().add_workspace(&dir).unwrap();
+
+        app.keys(&["Ctrl+Shift+F"]).type_text("match\n");
+        app.assert_screen_contains("10005 lines match match");
+        app.assert_screen_contains("10005 lines match, only the first 10000 are listed");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_quick_open() {
+        let dir = temp_dir("quick-open");
+        fs::write(dir.join("alpha.rs"), "fn alpha() {}\n").unwrap();
+        fs::write(dir.join("beta.rs"), "fn beta() {}\n").unwrap();
+        let mut app = TestApp::new(100, 20);
+        app.editor_mut().add_workspace(&dir).unwrap();
+
+        // The files are listed in the background, then previewed
+        app.keys(&["Ctrl+P"]);
+        app.assert_screen_contains("Files (2/2)");
+        app.assert_screen_contains("fn alpha() {}");
+        app.type_text("bet");
+        app.assert_screen_contains("Files (1/2)");
+        app.keys(&["Enter"]);
+        assert_eq!(app.text(), "fn beta() {}\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_folding() {
+        let mut app = TestApp::new(60, 12);
+        app.type_text("# Notes\n## One\nfirst\n## Two\nsecond\n");
+        app.editor_mut().current_doc_mut().language = Some("markdown".to_string());
+
+        // Tab on a heading cycles: folded, subsections folded, everything
+        app.keys(&["Ctrl+Home", "Tab"]);
+        app.assert_screen_contains("# Notes ⋯ 4 lines");
+        app.assert_screen_lacks("## One");
+        app.keys(&["Tab"]);
+        app.assert_screen_contains("## One ⋯ 1 line");
+        app.assert_screen_lacks("first");
+        // Moving down steps over what's folded
+        app.keys(&["Down", "Down"]);
+        assert_eq!(app.cursor(), (3, 0));
+        app.keys(&["Up", "Tab"]);
+        app.assert_screen_contains("first");
+        assert_eq!(app.text(), "# Notes\n## One\nfirst\n## Two\nsecond\n");
+
+        // Everything deeper than a level, and a search opens what it finds
+        app.keys(&["Ctrl+Shift+P"]).type_text("fold-level 2\n");
+        app.assert_screen_lacks("second");
+        app.keys(&["Ctrl+F"]).type_text("nd\n");
+        app.assert_screen_contains("second");
+        app.assert_screen_lacks("first");
+    }
+
+    #[test]
+    fn test_prompt_history() {
+        let mut app = TestApp::new(60, 10);
+        app.type_text("alpha beta\nalpine gamma\n");
+        app.keys(&["Ctrl+F"]).type_text("alpha\n");
+        app.keys(&["Ctrl+F"]).type_text("gamma\n");
+        app.keys(&["Ctrl+F"]).type_text("alpine\n");
+
+        // Up goes back through earlier searches, Down forward to what was typed
+        app.keys(&["Ctrl+F", "Up"]);
+        app.assert_screen_contains("/alpine");
+        app.keys(&["Up", "Up"]);
+        app.assert_screen_contains("/alpha");
+        app.keys(&["Down", "Down", "Down"]);
+        app.assert_screen_lacks("/alp");
+        // Only those starting with what's typed
+        app.type_text("al").keys(&["Up", "Up"]);
+        app.assert_screen_contains("/alpha");
+        app.keys(&["Escape"]);
+
+        // Each kind of prompt has its own
+        app.keys(&["Ctrl+Shift+P", "Up"]);
+        app.assert_screen_lacks(":alp");
+        assert_eq!(app.editor().prompt_history["search"], vec!["alpine", "gamma", "alpha"]);
+    }
+
+    #[test]
+    fn test_build_output_references() {
+        let dir = temp_dir("references");
+        fs::write(dir.join("main.py"), "import app\napp.run()\n").unwrap();
+        fs::write(dir.join("app.ts"), "let a = 1;\nlet b: string = 2;\n").unwrap();
+        let log = "Traceback (most recent call last):\n  File \"main.py\", line 2, in <module>\n\
+                   app.ts(2,8): error TS2322\n";
+        fs::write(dir.join("build.log"), log).unwrap();
+        let mut app = TestApp::new(80, 20);
+        app.open(dir.join("build.log"));
+
+        app.keys(&["F8"]);
+        app.assert_screen_contains("main.py:2 (Alt+Enter opens it)");
+        app.keys(&["F8"]);
+        assert_eq!(app.cursor(), (2, 0));
+        app.keys(&["F8", "Shift+F8"]);
+        assert_eq!(app.cursor(), (2, 0));
+
+        app.keys(&["Alt+Enter"]);
+        assert_eq!(app.editor().current_doc().path, Some(dir.join("app.ts")));
+        assert_eq!(app.cursor(), (1, 7));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_open_expansion() {
+        let dir = temp_dir("expansion");
+        fs::create_dir_all(dir.join("conf")).unwrap();
+        fs::write(dir.join("main.rs"), "fn main() {}\n").unwrap();
+        fs::write(dir.join("lib.rs"), "pub fn lib() {}\n").unwrap();
+        fs::write(dir.join("conf/a.toml"), "a = 1\n").unwrap();
+        fs::write(dir.join("conf/b.toml"), "b = 2\n").unwrap();
+        let mut app = TestApp::new(100, 20);
+        app.open(dir.join("main.rs"));
+
+        // A sibling of the buffer's file
+        app.keys(&["Ctrl+Shift+P"]).type_text("e %:h/lib.rs\n");
+        assert_eq!(app.text(), "pub fn lib() {}\n");
+
+        // One match opens, several go to a picker
+        app.keys(&["Ctrl+Shift+P"]).type_text("e %:h/**/a.t?ml\n");
+        assert_eq!(app.text(), "a = 1\n");
+        app.keys(&["Ctrl+Shift+P"]).type_text(&format!("e {}/**/*.toml\n", dir.display()));
+        app.assert_screen_contains("Files matching");
+        app.type_text("b.toml\n");
+        assert_eq!(app.text(), "b = 2\n");
+
+        app.keys(&["Ctrl+Shift+P"]).type_text("e $LITE_UNSET_VARIABLE/x\n");
+        app.assert_screen_contains("$LITE_UNSET_VARIABLE is not set");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_virtual_text() {
+        let mut app = TestApp::new(60, 10);
+        app.type_text("main\nlet x = 1;\n").keys(&["Ctrl+Home"]);
+        let annotations = &mut app.editor_mut().current_doc_mut().annotations;
+        annotations.add(Annotation::new("inlay-hints", 9, Placement::After, ": i32"));
+        annotations.add(Annotation::new("blame", 10, Placement::EndOfLine, "you, today"));
+        annotations.add(Annotation::new("code-lens", 0, Placement::Above, "Run | Debug"));
+        app.keys(&["Down"]);
+        let screen = app.screen();
+        let row = |text: &str| screen.iter().position(|row| row.contains(text)).unwrap();
+        assert_eq!(row("main"), row("Run | Debug") + 1);
+        app.assert_screen_contains("let x: i32 = 1; you, today");
+        assert_eq!(app.text(), "main\nlet x = 1;\n");
+
+        // Edits move the virtual text with the text it's attached to
+        app.keys(&["Ctrl+Home"]).type_text("// x\n");
+        app.assert_screen_contains("let x: i32 = 1; you, today");
+        app.editor_mut().current_doc_mut().annotations.clear("inlay-hints");
+        app.keys(&["Down"]);
+        app.assert_screen_contains("let x = 1; you, today");
+    }
+
+    #[test]
+    fn test_command_completion() {
+        let dir = temp_dir("completion");
+        fs::write(dir.join("alpha.txt"), "a\n").unwrap();
+        fs::write(dir.join("beta.txt"), "b\n").unwrap();
+        let mut app = TestApp::new(80, 20);
+
+        // Command names, then the files of a folder, one after the other
+        app.keys(&["Ctrl+Shift+P"]).type_text("edi").keys(&["Tab"]);
+        app.assert_screen_contains(":edit");
+        app.type_text(&format!("{}/", dir.display())).keys(&["Tab", "Tab", "Shift+Tab", "Enter"]);
+        assert_eq!(app.editor().current_doc().path, Some(dir.join("alpha.txt")));
+        app.open(dir.join("beta.txt"));
+
+        // Buffer names for :b
+        app.keys(&["Ctrl+Shift+P"]).type_text("b al").keys(&["Tab"]);
+        app.assert_screen_contains(":b alpha.txt");
+        app.keys(&["Enter"]);
+        assert_eq!(app.text(), "a\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_set_option() {
+        let mut app = TestApp::new(80, 20);
+        app.keys(&["Ctrl+Shift+P"]).type_text("set tab_width=2\n");
+        app.assert_screen_contains("tab_width = 2");
+        app.keys(&["Tab"]);
+        assert_eq!(app.text(), "  ");
+
+        app.keys(&["Ctrl+Shift+P"]).type_text("set indent_style tabs\n");
+        assert_eq!(app.editor().config.editor.indent_style, IndentStyle::Tabs);
+        app.keys(&["Ctrl+Shift+P"]).type_text("set tabwidth=8\n");
+        app.assert_screen_contains("Unknown option: tabwidth");
+        app.keys(&["Ctrl+Shift+P"]).type_text("set soft_w").keys(&["Tab", "Enter"]);
+        app.assert_screen_contains("soft_wrap = false");
+
+        // Themes complete by name too
+        app.keys(&["Ctrl+Shift+P"]).type_text("theme def").keys(&["Tab", "Enter"]);
+        app.assert_screen_contains("theme = default");
+        app.keys(&["Ctrl+Shift+P"]).type_text("theme nope\n");
+        app.assert_screen_contains("no theme named nope");
+    }
+
+    #[test]
+    fn test_layout_pages() {
+        let mut app = TestApp::new(80, 20);
+        app.keys(&["Ctrl+\\"]);
+        app.keys(&["Ctrl+Shift+P"]).type_text("page-rename code
+");
+        app.assert_screen_lacks("[1:code]");
+        app.keys(&["Ctrl+Shift+P"]).type_text("page-new tests
+");
+        app.assert_screen_contains("[1:code]  [2:tests]");
+        assert_eq!(app.editor().tree.views().len(), 1);
+
+        app.keys(&["Ctrl+Alt+PageUp"]);
+        assert_eq!(app.editor().tree.views().len(), 2);
+        app.keys(&["Ctrl+Shift+P"]).type_text("page t").keys(&["Tab", "Enter"]);
+        assert_eq!(app.editor().current_page(), 1);
+        app.keys(&["Ctrl+Shift+W"]);
+        app.assert_screen_lacks("[2:tests]");
+        assert_eq!(app.editor().tree.views().len(), 2);
+    }
+
+    #[test]
+    fn test_line_ranges() {
+        let mut app = TestApp::new(80, 20);
+        app.type_text("a\nb\nc\nd\ne");
+        app.keys(&["Ctrl+Shift+P"]).type_text("2,3d\n");
+        assert_eq!(app.text(), "a\nd\ne");
+        app.keys(&["Ctrl+Shift+P"]).type_text("%>>\n");
+        app.keys(&["Ctrl+Shift+P"]).type_text("2<\n");
+        assert_eq!(app.text(), "        a\n    d\n        e");
+        // One edit undoes the whole range
+        app.keys(&["Ctrl+Shift+P"]).type_text(":%s/[ae]/x/g\n");
+        assert_eq!(app.text(), "        x\n    d\n        x");
+        app.keys(&["Ctrl+Z"]);
+        assert_eq!(app.text(), "        a\n    d\n        e");
+
+        app.keys(&["Ctrl+Shift+P"]).type_text("2\n");
+        assert_eq!(app.cursor(), (1, 4));
+        app.keys(&["Ctrl+Shift+P"]).type_text("1,9d\n");
+        app.assert_screen_contains("No line 9");
+        app.keys(&["Ctrl+Shift+P"]).type_text("2w\n");
+        app.assert_screen_contains("w takes no range");
+
+        // ^ and $ are every line's ends
+        app.keys(&["Ctrl+Shift+P"]).type_text(":%s/^ +//\n");
+        app.keys(&["Ctrl+Shift+P"]).type_text("1,2s/$/;/\n");
+        assert_eq!(app.text(), "a;\nd;\ne");
+        app.keys(&["Ctrl+Shift+P"]).type_text(":%s/^/# /\n");
+        assert_eq!(app.text(), "# a;\n# d;\n# e");
+        // The last line goes with the newline before it
+        app.keys(&["Ctrl+Shift+P"]).type_text("3d\n");
+        assert_eq!(app.text(), "# a;\n# d;");
+        app.keys(&["Ctrl+End", "Enter"]).keys(&["Ctrl+Shift+P"]).type_text(":%s/^/# /\n");
+        assert_eq!(app.text(), "# # a;\n# # d;\n");
+    }
+
+    #[test]
+    fn test_follow_mode() {
+        let mut app = TestApp::new(80, 20);
+        let text: String = (1..=80).map(|i| format!("line {}\n", i)).collect();
+        app.type_text(&text).keys(&["Ctrl+Home"]);
+        app.keys(&["Ctrl+Shift+P"]).type_text("follow\n");
+        let scrolls = |app: &TestApp| {
+            let views = app.editor().tree.views();
+            views.iter().map(|id| app.editor().views[id].scroll_y).collect::<Vec<_>>()
+        };
+        let height = app.editor().current_view().height as usize;
+        assert_eq!(scrolls(&app), vec![0, height]);
+        app.assert_screen_contains(&format!("line {}", height + 1));
+
+        // Scrolling either split scrolls both
+        app.keys(&["PageDown", "PageDown"]);
+        let [left, right] = scrolls(&app)[..] else { panic!() };
+        assert!(left > 0);
+        assert_eq!(right, left + height);
+
+        app.keys(&["Ctrl+Shift+P"]).type_text("scroll-bind\n");
+        app.assert_screen_contains("Scroll bind off");
+        app.keys(&["PageUp", "PageUp"]);
+        assert_eq!(scrolls(&app), vec![0, left + height]);
+    }
+
+    #[test]
+    fn test_whitespace_hints() {
+        let mut app = TestApp::new(80, 12);
+        // Nothing is marked while typing at the end
+        app.type_text("one  \ntwo");
+        app.assert_screen_lacks("∅");
+        app.keys(&["Ctrl+Home"]);
+        app.assert_screen_contains("two ∅");
+        // The trailing spaces are underlined
+        let screen = app.screen();
+        let y = screen.iter().position(|row| row.ends_with("one")).unwrap();
+        let x = screen[y].len() as u16;
+        let buffer = app.app.frontend().buffer();
+        assert!(buffer[(x, y as u16)].modifier.contains(ratatui::style::Modifier::UNDERLINED));
+        assert!(!buffer[(x - 1, y as u16)].modifier.contains(ratatui::style::Modifier::UNDERLINED));
+
+        app.editor_mut().config.editor.whitespace_hints = false;
+        app.keys(&["Right"]);
+        app.assert_screen_lacks("∅");
+    }
+
+    #[test]
+    fn test_bell() {
+        use lite_config::Bell;
+        use ratatui::style::Modifier;
+
+        let mut app = TestApp::new(80, 12);
+        app.keys(&["Left"]);
+        assert!(app.editor().bell.is_none());
+
+        // The statusline flashes when there's nothing to undo
+        app.editor_mut().config.editor.bell = Bell::Statusline;
+        app.keys(&["Ctrl+Z"]);
+        assert!(app.editor().bell.is_some());
+        let y = app.screen().iter().position(|row| row.contains("Nothing to undo")).unwrap() as u16;
+        let buffer = app.app.frontend().buffer();
+        assert!(buffer[(0, y)].modifier.contains(Modifier::REVERSED));
+        assert!(!buffer[(0, 0)].modifier.contains(Modifier::REVERSED));
+
+        // The screen flashes at the edge of the buffer
+        app.editor_mut().config.editor.bell = Bell::Screen;
+        app.editor_mut().bell = None;
+        app.type_text("ab").keys(&["Left"]);
+        assert!(app.editor().bell.is_none());
+        app.keys(&["Up"]);
+        assert!(app.app.frontend().buffer()[(0, 0)].modifier.contains(Modifier::REVERSED));
+
+        // The terminal bell rings once
+        app.editor_mut().config.editor.bell = Bell::Terminal;
+        app.keys(&["Ctrl+F"]).type_text("zz\n");
+        assert!(app.editor().bell.is_none());
+        app.assert_screen_contains("Not found");
+    }
+
+    #[test]
+    fn test_input_method() {
+        let mut app = TestApp::new(80, 12);
+        app.type_text("ab").keys(&["Left"]);
+        // Composing draws at the cursor without editing
+        app.send([Event::Preedit("にほ".to_string())]);
+        app.assert_screen_contains("aに");
+        assert_eq!(app.text(), "ab");
+        app.send([Event::Preedit("日本".to_string())]);
+        app.assert_screen_lacks("に");
+        app.send([Event::Commit("日本".to_string())]);
+        assert_eq!(app.text(), "a日本b");
+        assert_eq!(app.cursor(), (0, 3));
+        // One edit to undo
+        app.keys(&["Ctrl+Z"]);
+        assert_eq!(app.text(), "ab");
+    }
+
+    #[test]
+    fn test_digraphs() {
+        let mut app = TestApp::new(80, 12);
+        app.type_text("caf").keys(&["Ctrl+K", "e"]);
+        // The pending key shows at the cursor, and isn't typed
+        app.assert_screen_contains("cafe");
+        assert_eq!(app.text(), "caf");
+        app.type_text("'").keys(&["Ctrl+K", "-", ">", "Ctrl+K", "O", "K"]);
+        assert_eq!(app.text(), "café→✓");
+
+        app.editor_mut().config.editor.digraphs.insert("oo".to_string(), '∞');
+        app.keys(&["Ctrl+K", "o", "o", "Ctrl+K", "q"]);
+        app.keys(&["Escape"]).type_text("!");
+        assert_eq!(app.text(), "café→✓∞!");
+        app.keys(&["Ctrl+K", "q", "q"]);
+        app.assert_screen_contains("No digraph qq");
+    }
+
+    #[test]
+    fn test_splits() {
+        let mut app = TestApp::new(80, 12);
+        app.type_text("shared");
+        app.keys(&["Ctrl+\\"]);
+        assert_eq!(app.editor().tree.views().len(), 2);
+        // Both views show the buffer, and edits in one show in the other
+        let screen = app.screen().join("\n");
+        assert_eq!(screen.matches("shared").count(), 2);
+        app.type_text("!");
+        let screen = app.screen().join("\n");
+        assert_eq!(screen.matches("shared!").count(), 2);
+
+        app.keys(&["Ctrl+Shift+W"]);
+        assert_eq!(app.editor().tree.views().len(), 1);
+    }
+
+    #[test]
+    fn test_debug_metrics() {
+        let mut app = TestApp::new(140, 12);
+        app.type_text("x");
+        app.assert_screen_lacks("highlight");
+
+        app.editor_mut().config.editor.debug_metrics = lite_config::DebugMetrics::Screen;
+        // Timings of a frame show in the one after it
+        app.type_text("y").send([]);
+        app.assert_screen_contains("input ");
+        app.assert_screen_contains("highlight ");
+    }
+
+    #[test]
+    fn test_low_bandwidth() {
+        let mut app = TestApp::new(80, 12);
+        let text: String = (0..100).map(|i| format!("line {}\n", i)).collect();
+        let path = temp_dir("low-bandwidth").join("long.txt");
+        fs::write(&path, text).unwrap();
+        app.open(&path);
+        app.editor_mut().config.editor.smooth_scroll = true;
+
+        // Large scrolls are animated
+        app.keys(&["PageDown", "PageDown", "PageDown"]);
+        let view = app.editor().current_view();
+        assert_ne!(view.shown_scroll_y(), view.scroll_y);
+
+        // and jump in low bandwidth mode
+        app.editor_mut().config.editor.low_bandwidth = lite_config::LowBandwidth::On;
+        app.send([]);
+        assert!(app.editor().low_bandwidth);
+        let view = app.editor().current_view();
+        assert_eq!(view.shown_scroll_y(), view.scroll_y);
+        app.keys(&["PageUp", "PageUp", "PageUp"]);
+        let view = app.editor().current_view();
+        assert_eq!(view.shown_scroll_y(), view.scroll_y);
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
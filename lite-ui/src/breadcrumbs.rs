@@ -0,0 +1,36 @@
+use crate::{Component, Context};
+use ratatui::prelude::*;
+use ratatui::widgets::Paragraph;
+
+/// The file and the symbols around the cursor, above the editor
+pub struct Breadcrumbs;
+
+impl Breadcrumbs {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for Breadcrumbs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Component for Breadcrumbs {
+    fn render(&self, frame: &mut Frame, area: Rect, ctx: &Context) {
+        let theme = &ctx.editor.theme;
+        let separator = Span::styled(" ▸ ", theme.line_number.to_ratatui());
+        let mut spans = vec![Span::raw(" ")];
+        for (i, crumb) in ctx.editor.breadcrumbs.iter().enumerate() {
+            if i > 0 {
+                spans.push(separator.clone());
+            }
+            spans.push(Span::styled(crumb.clone(), theme.foreground.to_ratatui()));
+        }
+        frame.render_widget(
+            Paragraph::new(Line::from(spans)).style(theme.background.to_ratatui()),
+            area,
+        );
+    }
+}
@@ -0,0 +1,84 @@
+use crate::tabline::git_status_span;
+use crate::{Component, Context};
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, Borders, Clear, Paragraph};
+
+/// Overlay listing buffers in most-recently-used order while Ctrl+Tab is held
+pub struct BufferSwitcherPopup;
+
+impl BufferSwitcherPopup {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for BufferSwitcherPopup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Component for BufferSwitcherPopup {
+    fn render(&self, frame: &mut Frame, area: Rect, ctx: &Context) {
+        let Some(switcher) = &ctx.editor.buffer_switcher else {
+            return;
+        };
+
+        let titles: Vec<(String, Option<&'static str>)> = switcher
+            .candidates
+            .iter()
+            .filter_map(|id| ctx.editor.documents.get(id))
+            .map(|doc| (doc.title(), doc.git_status))
+            .collect();
+
+        // Size the box to fit the longest title and its git status, centered
+        // in the editor area
+        let content_width = titles
+            .iter()
+            .map(|(title, status)| title.chars().count() + status.map_or(0, |status| status.len() + 1))
+            .max()
+            .unwrap_or(0)
+            + 4;
+        let width = (content_width as u16 + 2).clamp(20, area.width);
+        let height = (titles.len() as u16 + 2).min(area.height);
+        let popup_area = Rect {
+            x: area.x + area.width.saturating_sub(width) / 2,
+            y: area.y + area.height.saturating_sub(height) / 2,
+            width,
+            height,
+        };
+
+        let popup_style = ctx.editor.theme.popup.to_ratatui();
+        let selected_style = ctx.editor.theme.selection.to_ratatui();
+        let lines: Vec<Line> = titles
+            .into_iter()
+            .enumerate()
+            .map(|(i, (title, status))| {
+                let style = if i == switcher.selected {
+                    popup_style.patch(selected_style)
+                } else {
+                    popup_style
+                };
+                match status {
+                    Some(status) => Line::from(vec![
+                        Span::styled(format!("  {} ", title), style),
+                        git_status_span(status, style, ctx),
+                        Span::styled("  ", style),
+                    ]),
+                    None => Line::from(Span::styled(format!("  {}  ", title), style)),
+                }
+            })
+            .collect();
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(ctx.editor.theme.popup_border.to_ratatui())
+            .title(" Buffers ");
+
+        frame.render_widget(Clear, popup_area);
+        frame.render_widget(
+            Paragraph::new(lines).block(block).style(popup_style),
+            popup_area,
+        );
+    }
+}
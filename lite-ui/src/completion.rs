@@ -0,0 +1,106 @@
+use crate::{Component, Context, EditorView};
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, Borders, Clear, Paragraph};
+
+/// Most completion items shown at once
+const MAX_ROWS: usize = 10;
+
+/// Most columns taken by an item's detail
+const MAX_DETAIL_WIDTH: usize = 30;
+
+/// Completion menu, anchored below the cursor (above it near the bottom)
+pub struct CompletionPopup;
+
+impl CompletionPopup {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for CompletionPopup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Component for CompletionPopup {
+    fn render(&self, frame: &mut Frame, area: Rect, ctx: &Context) {
+        let Some(completion) = &ctx.editor.completion else {
+            return;
+        };
+        let Some((cursor_x, cursor_y)) = EditorView::new().cursor(area, ctx) else {
+            return;
+        };
+        if completion.matches.is_empty() {
+            return;
+        }
+
+        // Scroll so the highlighted item is visible
+        let rows = completion.matches.len().min(MAX_ROWS);
+        let first = completion.selected.saturating_sub(rows - 1);
+        let shown: Vec<_> = completion.matches[first..first + rows]
+            .iter()
+            .map(|&i| &completion.items[i])
+            .collect();
+
+        let label_width = shown.iter().map(|item| item.label.chars().count()).max().unwrap_or(0);
+        let detail_width = shown
+            .iter()
+            .filter_map(|item| item.detail.as_ref())
+            .map(|detail| detail.chars().count().min(MAX_DETAIL_WIDTH))
+            .max()
+            .unwrap_or(0);
+        let content_width = label_width + if detail_width > 0 { detail_width + 2 } else { 0 } + 2;
+        let width = (content_width as u16 + 2).min(area.width);
+        let height = (rows as u16 + 2).min(area.height);
+
+        // Start the box at the word being completed, below the cursor if it fits
+        let cursor = ctx.editor.current_doc().selection(completion.view_id).primary().head;
+        let typed = cursor.saturating_sub(completion.start) as u16;
+        let x = cursor_x
+            .saturating_sub(typed + 2)
+            .max(area.x)
+            .min(area.right().saturating_sub(width));
+        let below = cursor_y + 1;
+        let y = if below + height <= area.bottom() {
+            below
+        } else {
+            cursor_y.saturating_sub(height).max(area.y)
+        };
+        let popup_area = Rect { x, y, width, height };
+
+        let popup_style = ctx.editor.theme.popup.to_ratatui();
+        let selected_style = ctx.editor.theme.selection.to_ratatui();
+        let detail_style = ctx.editor.theme.comment.to_ratatui();
+        let lines: Vec<Line> = shown
+            .iter()
+            .enumerate()
+            .map(|(row, item)| {
+                let style = if first + row == completion.selected {
+                    popup_style.patch(selected_style)
+                } else {
+                    popup_style
+                };
+                let mut spans = vec![Span::styled(
+                    format!(" {:<width$} ", item.label, width = label_width),
+                    style,
+                )];
+                if let Some(detail) = &item.detail {
+                    let detail: String = detail.chars().take(MAX_DETAIL_WIDTH).collect();
+                    spans.push(Span::styled(format!(" {} ", detail), style.patch(detail_style)));
+                }
+                Line::from(spans)
+            })
+            .collect();
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(ctx.editor.theme.popup_border.to_ratatui());
+
+        frame.render_widget(Clear, popup_area);
+        frame.render_widget(
+            Paragraph::new(lines).block(block).style(popup_style),
+            popup_area,
+        );
+    }
+}
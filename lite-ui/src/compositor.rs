@@ -43,6 +43,12 @@ pub trait Component {
     fn is_popup(&self) -> bool {
         false
     }
+
+    /// Take in what work in the background has finished. Returns `true` if
+    /// anything changed.
+    fn poll(&mut self) -> bool {
+        false
+    }
 }
 
 /// Manages layered UI components
@@ -108,6 +114,15 @@ impl Compositor {
         EventResult::Ignored
     }
 
+    /// Let every component take in the background work that finished
+    pub fn poll(&mut self) -> bool {
+        let mut changed = false;
+        for component in &mut self.layers {
+            changed |= component.poll();
+        }
+        changed
+    }
+
     /// Get cursor position from top component that provides one
     pub fn cursor(&self, area: Rect, ctx: &Context) -> Option<(u16, u16)> {
         for component in self.layers.iter().rev() {
@@ -1,8 +1,14 @@
 use crate::{Component, Context};
-use lite_core::RopeExt;
-use lite_view::{highlighter, Highlight, HighlightSpan};
+use lite_core::{grapheme_width, visual_width, Position, RopeExt, RopeGraphemes};
+use lite_view::{
+    fold, link, table, Annotation, AnnotationStyle, Decoration, DecorationStyle, DiagnosticSeverity,
+    Document, Highlight, HighlightSpan, Placement, View, LONG_LINE_LEN,
+};
 use ratatui::prelude::*;
-use ratatui::widgets::Paragraph;
+use ratatui::widgets::{Block, Paragraph};
+
+/// Drawn after the last line of a file that doesn't end with a newline
+const NO_FINAL_NEWLINE: &str = " ∅";
 
 /// Main editor view component
 pub struct EditorView;
@@ -30,6 +36,42 @@ impl EditorView {
             Highlight::Label => ctx.editor.theme.constant.to_ratatui(),
             Highlight::Namespace => ctx.editor.theme.type_name.to_ratatui(),
             Highlight::Attribute => ctx.editor.theme.keyword.to_ratatui(),
+            Highlight::DiffAdded => ctx.editor.theme.diff_add.to_ratatui(),
+            Highlight::DiffRemoved => ctx.editor.theme.diff_delete.to_ratatui(),
+            Highlight::DiffHunk => ctx.editor.theme.diff_modify.to_ratatui(),
+            Highlight::Overlong => ctx.editor.theme.warning.to_ratatui(),
+        }
+    }
+
+    /// Get the theme style of a diagnostic severity
+    fn diagnostic_style(severity: DiagnosticSeverity, ctx: &Context) -> ratatui::style::Style {
+        match severity {
+            DiagnosticSeverity::Error => ctx.editor.theme.error.to_ratatui(),
+            DiagnosticSeverity::Warning => ctx.editor.theme.warning.to_ratatui(),
+            DiagnosticSeverity::Info => ctx.editor.theme.info.to_ratatui(),
+            DiagnosticSeverity::Hint => ctx.editor.theme.hint.to_ratatui(),
+        }
+    }
+
+    /// Get the theme style of virtual text
+    fn annotation_style(style: AnnotationStyle, ctx: &Context) -> ratatui::style::Style {
+        match style {
+            AnnotationStyle::Comment => ctx.editor.theme.comment.to_ratatui(),
+            AnnotationStyle::Hint => ctx.editor.theme.hint.to_ratatui(),
+            AnnotationStyle::Info => ctx.editor.theme.info.to_ratatui(),
+            AnnotationStyle::Warning => ctx.editor.theme.warning.to_ratatui(),
+            AnnotationStyle::Error => ctx.editor.theme.error.to_ratatui(),
+        }
+    }
+
+    /// Get the style a decoration lays over text
+    fn decoration_style(style: DecorationStyle, ctx: &Context) -> ratatui::style::Style {
+        match style {
+            DecorationStyle::SearchMatch => ctx.editor.theme.search_match.to_ratatui(),
+            DecorationStyle::Reference => ratatui::style::Style::new().add_modifier(Modifier::UNDERLINED),
+            DecorationStyle::DiffLine => ctx.editor.theme.diff_line.to_ratatui(),
+            DecorationStyle::DiffLineAdded => ctx.editor.theme.diff_line_added.to_ratatui(),
+            DecorationStyle::DiffLineRemoved => ctx.editor.theme.diff_line_removed.to_ratatui(),
         }
     }
 
@@ -46,21 +88,28 @@ impl EditorView {
         }
         None
     }
-}
 
-impl Default for EditorView {
-    fn default() -> Self {
-        Self::new()
+    /// Draw the views of the editor into a buffer, as `render` does into a
+    /// frame
+    pub fn draw(&self, area: Rect, buf: &mut Buffer, ctx: &Context) {
+        // Gaps between splits show the background
+        Block::default().style(ctx.editor.theme.background.to_ratatui()).render(area, buf);
+
+        for (view_id, view_area) in ctx.editor.tree.layout(to_view_rect(area)) {
+            if let Some(view) = ctx.editor.views.get(&view_id) {
+                self.render_view(buf, to_ratatui_rect(view_area), view, ctx);
+            }
+        }
     }
-}
 
-impl Component for EditorView {
-    fn render(&self, frame: &mut Frame, area: Rect, ctx: &Context) {
-        let view = ctx.editor.current_view();
-        let doc = ctx.editor.current_doc();
+    /// Render a single view into its split
+    fn render_view(&self, buf: &mut Buffer, area: Rect, view: &View, ctx: &Context) {
+        let Some(doc) = ctx.editor.documents.get(&view.doc_id) else {
+            return;
+        };
 
         // Calculate areas
-        let gutter_width = view.gutter_width;
+        let gutter_width = view.gutter_width.min(area.width);
         let text_area = Rect {
             x: area.x + gutter_width,
             y: area.y,
@@ -74,21 +123,96 @@ impl Component for EditorView {
             height: area.height,
         };
 
-        // Get visible line range
-        let first_line = view.scroll_y;
-        let last_line = (first_line + area.height as usize).min(doc.len_lines());
+        // Get visible lines; a table's header stays on the first row
+        let table = doc.table_layout();
+        let rows = visible_rows(view, doc, ctx);
+
+        // Hunks when this document is being compared, as (lines here, lines
+        // in the other document)
+        let compared = matches!(&ctx.editor.diff, Some(diff) if diff.contains(doc.id));
+        let hunks: Vec<_> = match &ctx.editor.diff {
+            Some(diff) if compared => diff.ranges(doc.id).collect(),
+            _ => Vec::new(),
+        };
+        // Otherwise the gutter shows the changes since the last commit
+        let git_hunks: Vec<_> = doc
+            .git_hunks
+            .iter()
+            .filter(|_| !compared)
+            .map(|hunk| (hunk.after.clone(), hunk.before.clone()))
+            .collect();
+        // Changed lines already staged as they are get a thinner mark
+        let unstaged = |line: usize| {
+            doc.git_unstaged
+                .iter()
+                .any(|hunk| hunk.after.contains(&line) || (hunk.after.is_empty() && hunk.after.start == line))
+        };
+        let staged = |line: usize| !compared && !unstaged(line);
 
         // Render gutter (line numbers)
         let mut gutter_lines = Vec::new();
-        for line_num in first_line..last_line {
-            let line_str = format!("{:>width$} ", line_num + 1, width = (gutter_width - 1) as usize);
-            gutter_lines.push(Line::from(Span::styled(
-                line_str,
-                ctx.editor.theme.line_number.to_ratatui(),
-            )));
+        for row in &rows {
+            let line_num = match row {
+                Row::Line(line) => *line,
+                Row::Padding | Row::Virtual(_) => {
+                    gutter_lines.push(Line::from(Span::styled(
+                        " ".repeat(gutter_width as usize),
+                        ctx.editor.theme.line_number.to_ratatui(),
+                    )));
+                    continue;
+                }
+                Row::Deleted(_) => {
+                    gutter_lines.push(Line::from(vec![
+                        Span::styled(
+                            " ".repeat(gutter_width.saturating_sub(1) as usize),
+                            ctx.editor.theme.line_number.to_ratatui(),
+                        ),
+                        Span::styled("-", ctx.editor.theme.diff_delete.to_ratatui()),
+                    ]));
+                    continue;
+                }
+            };
+            let line_str = format!("{:>width$}", line_num + 1, width = gutter_width.saturating_sub(1) as usize);
+            // The column after the number shows the most severe diagnostic
+            // starting on the line
+            let sign = doc
+                .diagnostics
+                .iter()
+                .filter(|d| doc.rope.char_to_line(d.range.start.min(doc.len_chars())) == line_num)
+                .map(|d| d.severity)
+                .min();
+            // Otherwise it marks lines that differ from the compared document
+            // or the committed file, and where lines they have are missing here
+            let hunk_sign = hunks.iter().chain(&git_hunks).find_map(|(this, other)| {
+                let style = if other.is_empty() {
+                    ctx.editor.theme.diff_add
+                } else {
+                    ctx.editor.theme.diff_modify
+                };
+                let last_line = doc.len_lines() - 1;
+                if this.contains(&line_num) {
+                    let mark = if staged(line_num) { "▏" } else { "▌" };
+                    Some(Span::styled(mark, style.to_ratatui()))
+                } else if this.is_empty() && this.start == line_num {
+                    Some(Span::styled("▔", ctx.editor.theme.diff_delete.to_ratatui()))
+                } else if this.is_empty() && this.start > last_line && line_num == last_line {
+                    Some(Span::styled("▁", ctx.editor.theme.diff_delete.to_ratatui()))
+                } else {
+                    None
+                }
+            });
+            let sign = match (sign, hunk_sign) {
+                (Some(severity), _) => Span::styled("●", Self::diagnostic_style(severity, ctx)),
+                (None, Some(hunk_sign)) => hunk_sign,
+                (None, None) => Span::styled(" ", ctx.editor.theme.line_number.to_ratatui()),
+            };
+            gutter_lines.push(Line::from(vec![
+                Span::styled(line_str, ctx.editor.theme.line_number.to_ratatui()),
+                sign,
+            ]));
         }
         // Fill remaining space
-        for _ in last_line..first_line + area.height as usize {
+        for _ in rows.len()..area.height as usize {
             gutter_lines.push(Line::from(Span::styled(
                 " ".repeat(gutter_width as usize),
                 ctx.editor.theme.line_number.to_ratatui(),
@@ -97,55 +221,201 @@ impl Component for EditorView {
 
         let gutter_widget = Paragraph::new(gutter_lines)
             .style(ctx.editor.theme.background.to_ratatui());
-        frame.render_widget(gutter_widget, gutter_area);
+        gutter_widget.render(gutter_area, buf);
 
         // Get syntax highlights
-        let source = doc.text();
-        let highlights = if let Some(ref lang) = doc.language {
-            highlighter().highlight(lang, &source)
-        } else {
-            Vec::new()
-        };
+        let highlights = doc.syntax_highlights();
 
         // Render text content
-        let selection = doc.selection(ctx.editor.tree.focus());
+        let selection = doc.selection(view.id);
         let mut text_lines = Vec::new();
+        // Text past the wrap column is flagged only when asked for
+        let text_width = match ctx.editor.config.editor.highlight_overlong {
+            true => ctx.editor.config.editor.text_width,
+            false => 0,
+        };
+        let tab_width = ctx.editor.config.editor.tab_width;
+        let whitespace_hints =
+            ctx.editor.config.editor.whitespace_hints && table.is_none() && !doc.is_virtual();
+        let cursor = selection.cursor();
+        // Input being composed shows at the cursor of the focused view
+        let preedit = ctx.editor.preedit.as_deref().filter(|_| view.id == ctx.editor.tree.focus());
+        let cursor_line = doc.rope.char_to_line(cursor);
 
-        for line_idx in first_line..last_line {
-            if line_idx >= doc.len_lines() {
-                // Empty line after EOF
-                text_lines.push(Line::from(Span::styled(
-                    "~",
-                    ctx.editor.theme.comment.to_ratatui(),
-                )));
-                continue;
-            }
-
-            let line = doc.rope.line(line_idx);
+        for row in &rows {
+            let line_idx = match row {
+                Row::Line(line) => *line,
+                // Padding for lines only the compared document has
+                Row::Padding => {
+                    text_lines.push(Line::from(Span::styled(
+                        "╱".repeat(text_area.width as usize),
+                        ctx.editor.theme.line_number.to_ratatui(),
+                    )));
+                    continue;
+                }
+                Row::Deleted(text) => {
+                    let text: String = text
+                        .replace('\t', &" ".repeat(ctx.editor.config.editor.tab_width))
+                        .chars()
+                        .skip(view.scroll_x)
+                        .take(text_area.width as usize)
+                        .collect();
+                    let style = ctx.editor.theme.comment.to_ratatui();
+                    let style = style.patch(ctx.editor.theme.diff_line_removed.to_ratatui());
+                    text_lines.push(Line::from(Span::styled(text, style)));
+                    continue;
+                }
+                // Virtual text lines up with the line it's above
+                Row::Virtual(annotation) => {
+                    let line = doc.rope.char_to_line(annotation.pos.min(doc.len_chars()));
+                    let indent = doc.rope.line(line).chars().take_while(|c| *c == ' ' || *c == '\t').count();
+                    let indent = doc.display_col(Position::new(line, indent), tab_width);
+                    let text: String = " "
+                        .repeat(indent)
+                        .chars()
+                        .chain(annotation.text.chars())
+                        .skip(view.scroll_x)
+                        .take(text_area.width as usize)
+                        .collect();
+                    let style = Self::annotation_style(annotation.style, ctx);
+                    text_lines.push(Line::from(Span::styled(text, style)));
+                    continue;
+                }
+            };
             let line_start_char = doc.rope.line_to_char(line_idx);
             let line_start_byte = doc.rope.char_to_byte(line_start_char);
-            let line_text: String = line.chars().collect();
-            let line_text = line_text.trim_end_matches('\n').trim_end_matches('\r');
+            let line_len = doc.rope.line_len_chars(line_idx);
+            let long_line = line_len > LONG_LINE_LEN;
+
+            // Highlights touching this line; long lines are drawn plain
+            let line_end_byte = doc.rope.char_to_byte(line_start_char + line_len);
+            let line_highlights: Vec<HighlightSpan> = if long_line {
+                Vec::new()
+            } else {
+                highlights[..highlights.partition_point(|span| span.start < line_end_byte)]
+                    .iter()
+                    .filter(|span| span.end > line_start_byte)
+                    .cloned()
+                    .collect()
+            };
 
             // Apply horizontal scroll
             let scroll_x = view.scroll_x;
-            let visible_text = if scroll_x < line_text.len() {
-                &line_text[scroll_x..]
+
+            // Cells in view, as (display column, char in the line, text shown),
+            // whether the line goes on past the right edge, and the display
+            // column of its end. Tables are padded into columns without
+            // touching the document; other lines are walked by grapheme from
+            // near the left edge up to the right edge.
+            type Columns = Vec<(usize, Option<usize>, String)>;
+            let (columns, truncated, end_col): (Columns, bool, usize) = match &table {
+                Some(table) if !long_line => {
+                    let line_text = table::line_text(&doc.rope, line_idx);
+                    let line_chars: Vec<char> = line_text.chars().collect();
+                    let columns = table
+                        .layout_line(&line_text)
+                        .into_iter()
+                        .enumerate()
+                        .skip(scroll_x)
+                        .take(text_area.width as usize)
+                        .map(|(col, cell)| match cell {
+                            Some(i) if line_chars.get(i) == Some(&table.delimiter) => {
+                                (col, Some(i), table::COLUMN_SEPARATOR.to_string())
+                            }
+                            Some(i) => (col, Some(i), line_chars[i].to_string()),
+                            None => (col, None, " ".to_string()),
+                        })
+                        .collect();
+                    let end_col = doc.display_col(doc.rope.char_to_position(line_start_char + line_len), tab_width);
+                    (columns, false, end_col)
+                }
+                _ => {
+                    let view_end = scroll_x + text_area.width as usize;
+                    let mut columns = Vec::new();
+                    let mut truncated = false;
+                    let (mut char_offset, mut col) = doc.seek_display_col(line_idx, scroll_x, tab_width);
+                    let line = doc.rope.slice(line_start_char + char_offset..line_start_char + line_len);
+                    for grapheme in RopeGraphemes::new(line) {
+                        if col >= view_end {
+                            truncated = true;
+                            break;
+                        }
+                        let width = visual_width(grapheme, col, tab_width);
+                        let start = col.max(scroll_x);
+                        let end = (col + width).min(view_end);
+                        if end > start {
+                            // Tabs, and wide graphemes cut by an edge of the
+                            // view, are drawn as blanks
+                            let display = if grapheme == "\t" || end - start < width {
+                                " ".repeat(end - start)
+                            } else {
+                                grapheme.to_string()
+                            };
+                            columns.push((start, Some(char_offset), display));
+                        }
+                        col += width;
+                        char_offset += grapheme.chars().count();
+                    }
+                    // A truncated line ends past the right edge
+                    (columns, truncated, col)
+                }
+            };
+            let truncated = long_line && truncated;
+
+            // Trailing whitespace is marked, except while it's being typed
+            let trailing = match doc.rope.trailing_whitespace(line_idx) {
+                Some(start) if whitespace_hints => {
+                    let start = line_start_char + start;
+                    let typing = (start..=line_start_char + line_len).contains(&cursor);
+                    (!typing).then_some(start)
+                }
+                _ => None,
+            };
+
+            // URLs and file:line references are underlined
+            let links = if long_line || table.is_some() || ctx.editor.low_bandwidth {
+                Vec::new()
             } else {
-                ""
+                link::find_links(&doc.rope.line(line_idx).to_string())
             };
 
-            // Build spans with syntax highlighting
-            let mut spans = Vec::new();
-            let line_chars: Vec<char> = visible_text.chars().collect();
+            let decorations: Vec<&Decoration> =
+                doc.decorations.overlapping(view.id, line_start_char..line_start_char + line_len).collect();
 
-            // Calculate byte offset for scroll_x
-            let scroll_byte_offset: usize = line_text.chars().take(scroll_x).map(|c| c.len_utf8()).sum();
+            // Virtual text drawn within the line
+            let annotations: Vec<&Annotation> = doc
+                .annotations
+                .on_line(&doc.rope, line_idx)
+                .filter(|annotation| annotation.is_inline())
+                .collect();
+            let virtual_text = |char_idx: usize, placement: Placement| {
+                annotations
+                    .iter()
+                    .filter(move |annotation| annotation.pos == char_idx && annotation.placement == placement)
+                    .map(|annotation| {
+                        Span::styled(annotation.text.clone(), Self::annotation_style(annotation.style, ctx))
+                    })
+            };
+            let line_end = line_start_char + line_len;
+            let end_shown = !truncated && end_col >= scroll_x;
 
-            let mut byte_offset = 0;
-            for (i, ch) in line_chars.iter().enumerate() {
-                let char_idx = line_start_char + scroll_x + i;
-                let byte_pos = line_start_byte + scroll_byte_offset + byte_offset;
+            // Build spans with syntax highlighting
+            let mut spans = Vec::new();
+            let mut preedit = preedit.filter(|_| line_idx == cursor_line);
+            let preedit_style = ctx.editor.theme.foreground.to_ratatui().add_modifier(Modifier::UNDERLINED);
+            for (i, char_offset, display) in columns {
+                let Some(char_offset) = char_offset else {
+                    spans.push(Span::styled(display, ctx.editor.theme.foreground.to_ratatui()));
+                    continue;
+                };
+                let char_idx = line_start_char + char_offset;
+                spans.extend(virtual_text(char_idx, Placement::Before));
+                if char_idx >= cursor {
+                    if let Some(text) = preedit.take() {
+                        spans.push(Span::styled(text, preedit_style));
+                    }
+                }
+                let byte_pos = doc.rope.char_to_byte(char_idx);
 
                 let in_selection = selection
                     .ranges()
@@ -155,21 +425,96 @@ impl Component for EditorView {
                 // Determine style based on selection and syntax highlighting
                 let style = if in_selection {
                     ctx.editor.theme.selection.to_ratatui()
-                } else if let Some(highlight) = Self::find_highlight(byte_pos, &highlights) {
+                } else if table.is_some() && display.starts_with(table::COLUMN_SEPARATOR) {
+                    ctx.editor.theme.punctuation.to_ratatui()
+                } else if let Some(highlight) = Self::find_highlight(byte_pos, &line_highlights) {
                     Self::highlight_style(highlight, ctx)
                 } else {
                     ctx.editor.theme.foreground.to_ratatui()
                 };
 
-                // Convert tabs to spaces
-                let display_char = if *ch == '\t' {
-                    " ".repeat(ctx.editor.config.editor.tab_width)
+                // Text past the wrap column is flagged without changing the file
+                let style = if !in_selection && table.is_none() && text_width > 0 && i >= text_width {
+                    style.patch(ctx.editor.theme.warning.to_ratatui())
                 } else {
-                    ch.to_string()
+                    style
+                };
+
+                // Diagnostics are underlined in their severity's color
+                let style = match doc.diagnostics.iter().find(|d| {
+                    d.range.contains(&char_idx) || (d.range.is_empty() && d.range.start == char_idx)
+                }) {
+                    Some(diagnostic) => style
+                        .patch(Self::diagnostic_style(diagnostic.severity, ctx))
+                        .add_modifier(Modifier::UNDERLINED),
+                    None => style,
                 };
 
-                spans.push(Span::styled(display_char, style));
-                byte_offset += ch.len_utf8();
+                // Then decorations, such as search matches and the lines
+                // compared buffers differ on
+                let style = decorations
+                    .iter()
+                    .filter(|decoration| !in_selection && decoration.range.contains(&char_idx))
+                    .fold(style, |style, decoration| style.patch(Self::decoration_style(decoration.style, ctx)));
+
+                let style = if links.iter().any(|link| link.range.contains(&char_offset)) {
+                    style.add_modifier(Modifier::UNDERLINED)
+                } else {
+                    style
+                };
+
+                let style = if table.is_some() && line_idx == 0 {
+                    style.add_modifier(Modifier::BOLD)
+                } else {
+                    style
+                };
+
+                let style = match trailing {
+                    Some(start) if !in_selection && char_idx >= start => {
+                        style.patch(ctx.editor.theme.hint.to_ratatui()).add_modifier(Modifier::UNDERLINED)
+                    }
+                    _ => style,
+                };
+
+                spans.push(Span::styled(display, style));
+                spans.extend(virtual_text(char_idx, Placement::After));
+            }
+            if end_shown {
+                spans.extend(virtual_text(line_end, Placement::Before));
+            }
+            if let Some(text) = preedit {
+                spans.push(Span::styled(text, preedit_style));
+            }
+            if end_shown {
+                spans.extend(virtual_text(line_end, Placement::After));
+            }
+
+            // A missing final newline is marked too, unless the cursor is where it would go
+            let last_line = line_idx + 1 == doc.rope.len_lines();
+            let missing_newline = line_len > 0 && !doc.rope.ends_with_newline() && cursor != doc.len_chars();
+            if whitespace_hints && last_line && missing_newline && !truncated {
+                spans.push(Span::styled(NO_FINAL_NEWLINE, ctx.editor.theme.comment.to_ratatui()));
+            }
+
+            // Folded sections show how much they hide
+            if doc.folds.is_folded(&doc.rope, line_idx) {
+                let hidden = hidden_lines(doc, line_idx);
+                let folded = format!(" ⋯ {} line{}", hidden, if hidden == 1 { "" } else { "s" });
+                spans.push(Span::styled(folded, ctx.editor.theme.comment.to_ratatui()));
+            }
+
+            // Long lines show where the rendered part stops
+            if truncated {
+                spans.pop();
+                spans.push(Span::styled("…", ctx.editor.theme.comment.to_ratatui()));
+            } else {
+                // Then virtual text at the end of the line
+                for annotation in doc.annotations.on_line(&doc.rope, line_idx) {
+                    if annotation.placement == Placement::EndOfLine {
+                        let text = format!(" {}", annotation.text);
+                        spans.push(Span::styled(text, Self::annotation_style(annotation.style, ctx)));
+                    }
+                }
             }
 
             if spans.is_empty() {
@@ -189,11 +534,30 @@ impl Component for EditorView {
 
         let text_widget = Paragraph::new(text_lines)
             .style(ctx.editor.theme.background.to_ratatui());
-        frame.render_widget(text_widget, text_area);
+        text_widget.render(text_area, buf);
+    }
+}
+
+impl Default for EditorView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Component for EditorView {
+    fn render(&self, frame: &mut Frame, area: Rect, ctx: &Context) {
+        self.draw(area, frame.buffer_mut(), ctx);
     }
 
     fn cursor(&self, area: Rect, ctx: &Context) -> Option<(u16, u16)> {
         let view = ctx.editor.current_view();
+        let area = ctx
+            .editor
+            .tree
+            .layout(to_view_rect(area))
+            .into_iter()
+            .find(|(view_id, _)| *view_id == view.id)
+            .map(|(_, view_area)| to_ratatui_rect(view_area))?;
         let doc = ctx.editor.current_doc();
         let selection = doc.selection(ctx.editor.tree.focus());
 
@@ -201,21 +565,130 @@ impl Component for EditorView {
         let cursor_char = selection.cursor();
         let cursor_pos = doc.rope.char_to_position(cursor_char);
 
+        let tab_width = ctx.editor.config.editor.tab_width;
+        let col = doc.display_col(cursor_pos, tab_width);
+
         // Check if cursor is visible
-        if cursor_pos.line < view.scroll_y {
-            return None;
-        }
-        if cursor_pos.line >= view.scroll_y + view.height as usize {
-            return None;
-        }
-        if cursor_pos.col < view.scroll_x {
+        let row = visible_rows(view, doc, ctx)
+            .iter()
+            .position(|row| *row == Row::Line(cursor_pos.line))?;
+        if col < view.scroll_x {
             return None;
         }
 
-        // Calculate screen position
-        let screen_y = (cursor_pos.line - view.scroll_y) as u16;
-        let screen_x = (cursor_pos.col - view.scroll_x) as u16 + view.gutter_width;
+        // Virtual text in view before the cursor moves it right
+        let annotations: usize = doc
+            .annotations
+            .on_line(&doc.rope, cursor_pos.line)
+            .filter(|annotation| match annotation.placement {
+                Placement::Before => annotation.pos <= cursor_char,
+                Placement::After => annotation.pos < cursor_char,
+                _ => false,
+            })
+            .filter(|annotation| {
+                doc.display_col(doc.rope.char_to_position(annotation.pos), tab_width) >= view.scroll_x
+            })
+            .map(|annotation| grapheme_width(&annotation.text))
+            .sum();
+
+        // Calculate screen position, after any input being composed
+        let screen_y = row as u16;
+        let preedit = ctx.editor.preedit.as_deref().map_or(0, grapheme_width);
+        let screen_x = (col - view.scroll_x + annotations + preedit) as u16 + view.gutter_width;
 
         Some((area.x + screen_x, area.y + screen_y))
     }
 }
+
+/// Lines shown in a view, top to bottom, skipping folded ones. Tables pin
+/// their header line to the first row once scrolled.
+fn visible_lines(view: &View, len_lines: usize, pin_header: bool, hidden: &[std::ops::Range<usize>]) -> Vec<usize> {
+    let height = view.height as usize;
+    let scroll_y = view.shown_scroll_y();
+    let shown = |lines: std::ops::Range<usize>| lines.filter(|line| !fold::is_hidden(hidden, *line));
+    if pin_header && scroll_y > 0 && height > 0 {
+        std::iter::once(0).chain(shown(scroll_y + 1..len_lines)).take(height).collect()
+    } else {
+        shown(scroll_y..len_lines).take(height).collect()
+    }
+}
+
+/// A row of a view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Row<'a> {
+    Line(usize),
+    /// Blank row lining lines up with a compared document
+    Padding,
+    /// Line deleted since the last commit
+    Deleted(&'a str),
+    /// Virtual text above a line
+    Virtual(&'a Annotation),
+}
+
+/// Rows shown in a view, top to bottom: its lines, when its document is
+/// being compared its side of the aligned rows, and above lines, the
+/// committed lines missing there when deleted lines are shown and virtual
+/// text placed above them
+fn visible_rows<'a>(view: &View, doc: &'a Document, ctx: &Context) -> Vec<Row<'a>> {
+    let height = view.height as usize;
+    if let Some(diff) = ctx.editor.diff.as_ref().filter(|diff| diff.contains(doc.id)) {
+        return diff
+            .side(doc.id)
+            .into_iter()
+            .skip(diff.top)
+            .take(height)
+            .map(|line| line.map_or(Row::Padding, Row::Line))
+            .collect();
+    }
+    let pin_header = doc.table_delimiter.is_some();
+    let hidden = doc.folds.hidden(&doc.rope);
+    let show_deleted = ctx.editor.config.editor.show_deleted_lines && !ctx.editor.low_bandwidth;
+    let show_deleted = show_deleted && !doc.git_removed.is_empty();
+    let show_virtual = doc.annotations.iter().any(|annotation| annotation.placement == Placement::Above);
+    if !(show_deleted || show_virtual) || pin_header {
+        return visible_lines(view, doc.len_lines(), pin_header, &hidden).into_iter().map(Row::Line).collect();
+    }
+
+    // Rows above lines push them down; scrolling counts them to keep the
+    // cursor line in the view
+    let rows_above = |line: usize| {
+        let deleted = doc
+            .git_removed
+            .iter()
+            .filter(move |(above, _)| show_deleted && *above == line)
+            .flat_map(|(_, lines)| lines.iter().map(|text| Row::Deleted(text)));
+        let annotations = (line < doc.len_lines()).then(|| doc.annotations.on_line(&doc.rope, line));
+        let above = annotations.into_iter().flatten().filter(|annotation| annotation.placement == Placement::Above);
+        deleted.chain(above.map(Row::Virtual))
+    };
+    let mut rows = Vec::new();
+    for line in view.shown_scroll_y()..doc.len_lines() {
+        if rows.len() >= height {
+            break;
+        }
+        if fold::is_hidden(&hidden, line) {
+            continue;
+        }
+        rows.extend(rows_above(line));
+        rows.push(Row::Line(line));
+        if line + 1 == doc.len_lines() {
+            rows.extend(rows_above(line + 1));
+        }
+    }
+    rows.truncate(height);
+    rows
+}
+
+/// How many lines the fold of a line hides
+fn hidden_lines(doc: &Document, line: usize) -> usize {
+    let hidden = doc.folds.hidden(&doc.rope);
+    hidden.iter().find(|lines| lines.start == line + 1).map_or(0, |lines| lines.len())
+}
+
+fn to_view_rect(area: Rect) -> lite_view::Rect {
+    lite_view::Rect::new(area.x, area.y, area.width, area.height)
+}
+
+fn to_ratatui_rect(area: lite_view::Rect) -> Rect {
+    Rect::new(area.x, area.y, area.width, area.height)
+}
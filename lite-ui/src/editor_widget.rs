@@ -0,0 +1,248 @@
+//! An editing pane for other ratatui apps: `EditorState` holds the buffer,
+//! `EditorWidget` draws it.
+//!
+//! ```no_run
+//! use lite_config::KeyEvent;
+//! use lite_ui::{EditorState, EditorWidget};
+//! # fn frame(frame: &mut ratatui::Frame, key: KeyEvent) {
+//! let mut state = EditorState::new();
+//! state.set_text("fn main() {}\n", Some("rust"));
+//! // Keys the pane does not use are left to the app
+//! if !state.handle_key(key) {
+//!     // ...
+//! }
+//! frame.render_stateful_widget(EditorWidget::new(), frame.area(), &mut state);
+//! if let Some((x, y)) = state.cursor() {
+//!     frame.set_cursor_position((x, y));
+//! }
+//! let text = state.text();
+//! # }
+//! ```
+//!
+//! Keys go through lite's default keymap, with the editing actions that need
+//! no files, servers or prompts: cursor motion, select all, undo and redo.
+
+use crate::{Component, Context, EditorView};
+use lite_config::{Action, IndentStyle, Key, KeyEvent};
+use lite_core::{Range, RopeExt, Selection, Transaction};
+use lite_view::{Editor, HighlightSpan};
+use ratatui::prelude::*;
+
+/// The buffer, cursor and scroll of an embedded editing pane
+pub struct EditorState {
+    editor: Editor,
+    /// Area of the last draw, the views are sized to it
+    area: Rect,
+    /// Screen position of the cursor after the last draw
+    cursor: Option<(u16, u16)>,
+}
+
+impl EditorState {
+    /// A pane with an empty buffer
+    pub fn new() -> Self {
+        Self {
+            editor: Editor::new(),
+            area: Rect::default(),
+            cursor: None,
+        }
+    }
+
+    /// Replace the text, highlighted as `language` (a name such as
+    /// `"rust"`), with the cursor at the start
+    pub fn set_text(&mut self, text: &str, language: Option<&str>) {
+        let view_id = self.editor.tree.focus();
+        let doc = self.editor.current_doc_mut();
+        doc.set_text(text);
+        doc.language = language.map(str::to_string);
+        doc.set_selection(view_id, Selection::point(0));
+        self.editor.ensure_cursor_visible();
+    }
+
+    /// Text of the buffer
+    pub fn text(&self) -> String {
+        self.editor.current_doc().text()
+    }
+
+    /// Draw the text with these highlights rather than the language's, until
+    /// it next changes. Spans are byte ranges of `text()`.
+    pub fn set_highlights(&mut self, mut highlights: Vec<HighlightSpan>) {
+        highlights.sort_by_key(|span| span.start);
+        let doc = self.editor.current_doc_mut();
+        doc.highlights = Some((doc.version(), highlights));
+    }
+
+    /// Insert typed text at the cursor, over the selection
+    pub fn type_text(&mut self, text: &str) {
+        let range = *self.selection().primary();
+        self.replace(range.start(), range.end(), text);
+    }
+
+    /// Show text an input method is composing at the cursor, without
+    /// editing the buffer; empty when composing is given up
+    pub fn set_preedit(&mut self, text: &str) {
+        self.editor.preedit = Some(text.to_string()).filter(|text| !text.is_empty());
+    }
+
+    /// Insert what an input method composed, as one edit
+    pub fn commit(&mut self, text: &str) {
+        self.editor.preedit = None;
+        self.type_text(text);
+    }
+
+    /// Handle a key. Returns whether the pane used it, so the app can
+    /// handle the others.
+    pub fn handle_key(&mut self, event: KeyEvent) -> bool {
+        let range = *self.selection().primary();
+        let (start, end) = (range.start(), range.end());
+        match event.key {
+            Key::Backspace if !range.is_empty() => self.replace(start, end, ""),
+            Key::Backspace => self.replace(start.saturating_sub(1), end, ""),
+            Key::Delete if !range.is_empty() => self.replace(start, end, ""),
+            Key::Delete => self.replace(start, (end + 1).min(self.editor.current_doc().len_chars()), ""),
+            Key::Enter => {
+                let line_ending = self.editor.current_doc().line_ending.as_str();
+                self.replace(start, end, line_ending);
+            }
+            Key::Tab => {
+                let config = &self.editor.config.editor;
+                let indent = match config.indent_style {
+                    IndentStyle::Tabs => "\t".to_string(),
+                    IndentStyle::Spaces => " ".repeat(config.tab_width),
+                };
+                self.replace(start, end, &indent);
+            }
+            _ => match self.editor.keymap.get(&event).cloned() {
+                Some(action) => return self.run(&action),
+                // Unbound printable keys insert text, as in the editor
+                None => match event.key {
+                    Key::Char(c) if event.modifiers.ctrl == event.modifiers.alt => {
+                        self.replace(start, end, c.encode_utf8(&mut [0; 4]));
+                    }
+                    _ => return false,
+                },
+            },
+        }
+        true
+    }
+
+    /// Screen position of the cursor after the last draw, for
+    /// `Frame::set_cursor_position`
+    pub fn cursor(&self) -> Option<(u16, u16)> {
+        self.cursor
+    }
+
+    /// The editor behind the pane, for what the methods above don't cover
+    pub fn editor(&self) -> &Editor {
+        &self.editor
+    }
+
+    pub fn editor_mut(&mut self) -> &mut Editor {
+        &mut self.editor
+    }
+
+    /// The actions of the keymap that make sense in a bare pane
+    fn run(&mut self, action: &Action) -> bool {
+        let view_id = self.editor.tree.focus();
+        let doc = self.editor.current_doc_mut();
+        let cursor = doc.selection(view_id).cursor();
+        let pos = doc.rope.char_to_position(cursor);
+        let height = self.editor.current_view().height as usize;
+        let target = match action {
+            Action::MoveLeft => cursor.saturating_sub(1),
+            Action::MoveRight => cursor + 1,
+            Action::MoveUp => self.line_col(pos.line.checked_sub(1), pos.col),
+            Action::MoveDown => self.line_col(Some(pos.line + 1), pos.col),
+            Action::PageUp => self.line_col(Some(pos.line.saturating_sub(height)), pos.col),
+            Action::PageDown => self.line_col(Some(pos.line + height), pos.col),
+            Action::MoveLineStart => self.line_col(Some(pos.line), 0),
+            Action::MoveLineEnd => self.line_col(Some(pos.line), usize::MAX),
+            Action::MoveFileStart => 0,
+            Action::MoveFileEnd => usize::MAX,
+            Action::SelectAll => {
+                let doc = self.editor.current_doc_mut();
+                let len = doc.len_chars();
+                doc.set_selection(view_id, Selection::single(Range::new(0, len)));
+                return true;
+            }
+            Action::Undo | Action::Redo => {
+                let doc = self.editor.current_doc_mut();
+                if *action == Action::Undo {
+                    doc.undo(view_id);
+                } else {
+                    doc.redo(view_id);
+                }
+                self.editor.ensure_cursor_visible();
+                return true;
+            }
+            _ => return false,
+        };
+        let doc = self.editor.current_doc_mut();
+        let target = target.min(doc.len_chars());
+        doc.set_selection(view_id, Selection::point(target));
+        self.editor.ensure_cursor_visible();
+        true
+    }
+
+    /// Position of a column of a line, clamped to the line and the buffer.
+    /// No line means before the first.
+    fn line_col(&self, line: Option<usize>, col: usize) -> usize {
+        let rope = &self.editor.current_doc().rope;
+        let Some(line) = line else {
+            return 0;
+        };
+        let line = line.min(rope.len_lines().saturating_sub(1));
+        rope.line_to_char(line) + col.min(rope.line_len_chars(line))
+    }
+
+    fn selection(&self) -> Selection {
+        self.editor.current_doc().selection(self.editor.tree.focus())
+    }
+
+    /// Replace a range of the buffer, leaving the cursor after the new text
+    fn replace(&mut self, start: usize, end: usize, text: &str) {
+        let view_id = self.editor.tree.focus();
+        let doc = self.editor.current_doc_mut();
+        let cursor = start + text.chars().count();
+        let tx = Transaction::replace(doc.len_chars(), start, end, text.to_string())
+            .with_selection(Selection::point(cursor));
+        doc.apply(&tx, view_id);
+        self.editor.ensure_cursor_visible();
+    }
+}
+
+impl Default for EditorState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Draws an `EditorState` with lite's gutter, highlighting and theme
+pub struct EditorWidget;
+
+impl EditorWidget {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for EditorWidget {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StatefulWidget for EditorWidget {
+    type State = EditorState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut EditorState) {
+        if state.area != area {
+            state.area = area;
+            state.editor.resize(lite_view::Rect::new(area.x, area.y, area.width, area.height));
+            state.editor.ensure_cursor_visible();
+        }
+        let ctx = Context::new(&mut state.editor);
+        let view = EditorView::new();
+        view.draw(area, buf, &ctx);
+        state.cursor = view.cursor(area, &ctx);
+    }
+}
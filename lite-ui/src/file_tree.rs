@@ -0,0 +1,85 @@
+use crate::{Component, Context};
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, Borders, Paragraph};
+
+/// Sidebar listing the files of the working directory
+pub struct FileTreePanel;
+
+impl FileTreePanel {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for FileTreePanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Component for FileTreePanel {
+    fn render(&self, frame: &mut Frame, area: Rect, ctx: &Context) {
+        let Some(tree) = &ctx.editor.file_tree else {
+            return;
+        };
+        let theme = &ctx.editor.theme;
+        let border_style = if tree.focused {
+            theme.popup_border.to_ratatui()
+        } else {
+            theme.line_number.to_ratatui()
+        };
+        let title = tree
+            .root
+            .file_name()
+            .map_or_else(|| tree.root.display().to_string(), |name| name.to_string_lossy().into_owned());
+        let block = Block::default()
+            .borders(Borders::RIGHT)
+            .border_style(border_style)
+            .title(format!(" {} ", title));
+        let inner = block.inner(area);
+
+        // The picked row while focused, otherwise the current file
+        let highlighted = if tree.focused {
+            Some(tree.selected)
+        } else {
+            let path = ctx.editor.current_doc().path.as_ref().and_then(|path| std::path::absolute(path).ok());
+            path.and_then(|path| tree.entries.iter().position(|entry| entry.path == path))
+        };
+        let height = inner.height.max(1) as usize;
+        let offset = highlighted.map_or(0, |row| (row + 1).saturating_sub(height));
+
+        let lines: Vec<Line> = if tree.entries.is_empty() {
+            vec![Line::from(Span::styled(" No files", theme.comment.to_ratatui()))]
+        } else {
+            tree.entries
+                .iter()
+                .enumerate()
+                .skip(offset)
+                .take(height)
+                .map(|(i, entry)| {
+                    let indent = " ".repeat(1 + entry.depth * 2);
+                    let (marker, style) = match entry.dir {
+                        true if tree.expanded.contains(&entry.path) => ("▾ ", theme.keyword),
+                        true => ("▸ ", theme.keyword),
+                        false => ("  ", theme.foreground),
+                    };
+                    let line = Line::from(vec![
+                        Span::raw(indent),
+                        Span::styled(marker, theme.line_number.to_ratatui()),
+                        Span::styled(entry.name.clone(), style.to_ratatui()),
+                    ]);
+                    if Some(i) == highlighted {
+                        line.style(theme.selection.to_ratatui())
+                    } else {
+                        line
+                    }
+                })
+                .collect()
+        };
+
+        frame.render_widget(
+            Paragraph::new(lines).block(block).style(theme.background.to_ratatui()),
+            area,
+        );
+    }
+}
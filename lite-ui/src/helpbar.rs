@@ -79,7 +79,7 @@ impl HelpBar {
 
         for (i, (key, desc)) in items.iter().enumerate() {
             // Add key with highlighting
-            spans.push(Span::styled(format!("{}", key), key_style));
+            spans.push(Span::styled(key.to_string(), key_style));
 
             // Add description with padding
             let desc_str = format!(" {}", desc);
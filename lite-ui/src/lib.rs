@@ -1,15 +1,33 @@
 //! UI widgets for lite editor
 
+mod breadcrumbs;
+mod buffer_switcher;
+mod completion;
 mod compositor;
 mod editor_view;
+mod editor_widget;
+mod file_tree;
 mod helpbar;
+mod outline;
+mod peek;
+mod picker;
 mod prompt;
+mod signature;
 mod statusline;
 mod tabline;
 
+pub use breadcrumbs::Breadcrumbs;
+pub use buffer_switcher::BufferSwitcherPopup;
+pub use completion::CompletionPopup;
 pub use compositor::{Component, Compositor, Context, EventResult};
 pub use editor_view::EditorView;
+pub use editor_widget::{EditorState, EditorWidget};
+pub use file_tree::FileTreePanel;
 pub use helpbar::HelpBar;
+pub use outline::OutlinePanel;
+pub use peek::PeekPopup;
+pub use picker::{Loaded, Picker, Preview};
 pub use prompt::{Prompt, PromptType};
+pub use signature::SignatureHelpPopup;
 pub use statusline::StatusLine;
 pub use tabline::TabLine;
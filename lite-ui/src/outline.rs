@@ -0,0 +1,77 @@
+use crate::{Component, Context};
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, Borders, Paragraph};
+
+/// Side panel listing the symbols of the current document
+pub struct OutlinePanel;
+
+impl OutlinePanel {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for OutlinePanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Component for OutlinePanel {
+    fn render(&self, frame: &mut Frame, area: Rect, ctx: &Context) {
+        let Some(outline) = &ctx.editor.outline else {
+            return;
+        };
+        let theme = &ctx.editor.theme;
+        let border_style = if outline.focused {
+            theme.popup_border.to_ratatui()
+        } else {
+            theme.line_number.to_ratatui()
+        };
+        let block = Block::default()
+            .borders(Borders::LEFT)
+            .border_style(border_style)
+            .title(" Outline ");
+        let inner = block.inner(area);
+
+        // The picked row while focused, otherwise the symbol at the cursor
+        let highlighted = if outline.focused {
+            Some(outline.selected)
+        } else {
+            let doc = ctx.editor.current_doc();
+            outline.symbol_at(doc.rope.char_to_line(doc.selection(ctx.editor.tree.focus()).cursor()))
+        };
+        let height = inner.height.max(1) as usize;
+        let offset = highlighted.map_or(0, |row| (row + 1).saturating_sub(height));
+
+        let lines: Vec<Line> = if outline.symbols.is_empty() {
+            vec![Line::from(Span::styled(" No symbols", theme.comment.to_ratatui()))]
+        } else {
+            outline
+                .symbols
+                .iter()
+                .enumerate()
+                .skip(offset)
+                .take(height)
+                .map(|(i, symbol)| {
+                    let indent = " ".repeat(1 + symbol.depth * 2);
+                    let line = Line::from(vec![
+                        Span::raw(indent),
+                        Span::styled(format!("{} ", symbol.kind), theme.keyword.to_ratatui()),
+                        Span::styled(symbol.name.clone(), theme.foreground.to_ratatui()),
+                    ]);
+                    if Some(i) == highlighted {
+                        line.style(theme.selection.to_ratatui())
+                    } else {
+                        line
+                    }
+                })
+                .collect()
+        };
+
+        frame.render_widget(
+            Paragraph::new(lines).block(block).style(theme.background.to_ratatui()),
+            area,
+        );
+    }
+}
@@ -0,0 +1,72 @@
+use crate::{Component, Context, EditorView};
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, Borders, Clear, Paragraph};
+
+/// Widest the popup gets, borders included
+const MAX_WIDTH: u16 = 100;
+
+/// Peek definition: the lines around a definition in a read-only box under
+/// the cursor line, or above it near the bottom
+pub struct PeekPopup;
+
+impl PeekPopup {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for PeekPopup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Component for PeekPopup {
+    fn render(&self, frame: &mut Frame, area: Rect, ctx: &Context) {
+        let Some(peek) = &ctx.editor.peek else {
+            return;
+        };
+        let Some((_, cursor_y)) = EditorView::new().cursor(area, ctx) else {
+            return;
+        };
+
+        let popup_style = ctx.editor.theme.popup.to_ratatui();
+        let number_style = popup_style.patch(ctx.editor.theme.comment.to_ratatui());
+        let target_style = popup_style.patch(ctx.editor.theme.selection.to_ratatui());
+        let last_line = peek.first_line + peek.lines.len();
+        let number_width = last_line.to_string().len();
+        let lines: Vec<Line> = peek
+            .lines
+            .iter()
+            .enumerate()
+            .map(|(row, text)| {
+                let line = peek.first_line + row;
+                let text_style = if line == peek.target { target_style } else { popup_style };
+                Line::from(vec![
+                    Span::styled(format!(" {:>width$} ", line + 1, width = number_width), number_style),
+                    Span::styled(text.replace('\t', "    "), text_style),
+                ])
+            })
+            .collect();
+
+        let width = area.width.min(MAX_WIDTH);
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let y = if cursor_y + 1 + height <= area.bottom() {
+            cursor_y + 1
+        } else {
+            cursor_y.saturating_sub(height).max(area.y)
+        };
+        let popup_area = Rect { x: area.x, y, width, height };
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(ctx.editor.theme.popup_border.to_ratatui())
+            .title(format!(" {} ", peek.title));
+
+        frame.render_widget(Clear, popup_area);
+        frame.render_widget(
+            Paragraph::new(lines).block(block).style(popup_style),
+            popup_area,
+        );
+    }
+}
@@ -0,0 +1,326 @@
+use crate::tabline::git_status_span;
+use crate::{Component, Context, EventResult};
+use lite_config::{Action, Key, KeyEvent, Modifier};
+use lite_core::fuzzy_score;
+use lite_view::Editor;
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, Borders, Clear, Paragraph};
+use std::sync::mpsc;
+use unicode_width::UnicodeWidthStr;
+
+/// Narrowest picker that gets a preview beside its list
+const MIN_PREVIEW_WIDTH: u16 = 80;
+
+/// Lines shown beside the list for the highlighted item
+#[derive(Clone)]
+pub struct Preview {
+    pub lines: Vec<String>,
+    /// Index into `lines` of the line the item is about
+    pub highlight: Option<usize>,
+}
+
+/// Items listed in the background, for a picker opened before them
+pub struct Loaded<T> {
+    pub items: Vec<T>,
+    /// Git status marker of each item, or none at all
+    pub markers: Vec<Option<&'static str>>,
+    /// Index into `items` of the one to highlight first
+    pub selected: Option<usize>,
+    /// Shown after the count in the title, e.g. that the list was cut short
+    pub note: Option<String>,
+}
+
+type Format<T> = Box<dyn Fn(&T) -> String>;
+type PreviewFn<T> = Box<dyn Fn(&T, &Editor) -> Option<Preview>>;
+type Select<T> = Box<dyn Fn(&T, &mut Editor)>;
+
+/// Popup narrowing a list of items down by a fuzzy query. Enter hands the
+/// highlighted item to the picker's callback and closes it; Esc closes it.
+pub struct Picker<T> {
+    title: String,
+    items: Vec<T>,
+    format: Format<T>,
+    /// Text of each item, shown and matched against
+    labels: Vec<String>,
+    /// Git status marker shown after each label, not matched against
+    markers: Vec<Option<&'static str>>,
+    preview: Option<PreviewFn<T>>,
+    on_select: Select<T>,
+    query: String,
+    /// Indices into `items` matching the query, best first
+    matches: Vec<usize>,
+    /// Index into `matches` of the highlighted item
+    selected: usize,
+    /// Where the items come from while they're being listed
+    loading: Option<mpsc::Receiver<Loaded<T>>>,
+    note: Option<String>,
+}
+
+impl<T: 'static> Picker<T> {
+    pub fn new(
+        title: impl Into<String>,
+        items: Vec<T>,
+        format: impl Fn(&T) -> String + 'static,
+        on_select: impl Fn(&T, &mut Editor) + 'static,
+    ) -> Self {
+        let format: Format<T> = Box::new(format);
+        let labels = items.iter().map(&format).collect();
+        let mut picker = Self {
+            title: title.into(),
+            items,
+            format,
+            labels,
+            markers: Vec::new(),
+            preview: None,
+            on_select: Box::new(on_select),
+            query: String::new(),
+            matches: Vec::new(),
+            selected: 0,
+            loading: None,
+            note: None,
+        };
+        picker.filter();
+        picker
+    }
+
+    /// Take the items from `loading` once they're listed, showing none
+    /// until then
+    pub fn with_loading(mut self, loading: mpsc::Receiver<Loaded<T>>) -> Self {
+        self.loading = Some(loading);
+        self
+    }
+
+    /// Show the git status marker `marker` returns after each item
+    pub fn with_markers(mut self, marker: impl Fn(&T) -> Option<&'static str>) -> Self {
+        self.markers = self.items.iter().map(marker).collect();
+        self
+    }
+
+    /// Show what `preview` returns for the highlighted item beside the list
+    pub fn with_preview(mut self, preview: impl Fn(&T, &Editor) -> Option<Preview> + 'static) -> Self {
+        self.preview = Some(Box::new(preview));
+        self
+    }
+
+    /// Start with an item other than the first highlighted
+    pub fn with_selected(mut self, index: usize) -> Self {
+        self.select(index);
+        self
+    }
+
+    /// Highlight the item at `index` if it matches
+    fn select(&mut self, index: usize) {
+        if let Some(selected) = self.matches.iter().position(|&i| i == index) {
+            self.selected = selected;
+        }
+    }
+
+    /// The highlighted item
+    pub fn selected_item(&self) -> Option<&T> {
+        self.matches.get(self.selected).map(|&i| &self.items[i])
+    }
+
+    /// Match the items against the query again. Without a query they keep
+    /// their order.
+    fn filter(&mut self) {
+        let mut scored: Vec<(i64, usize)> = self
+            .labels
+            .iter()
+            .enumerate()
+            .filter_map(|(i, label)| Some((fuzzy_score(&self.query, label)?, i)))
+            .collect();
+        if !self.query.trim().is_empty() {
+            scored.sort_by_key(|&(score, i)| (-score, i));
+        }
+        self.matches = scored.into_iter().map(|(_, i)| i).collect();
+        self.selected = 0;
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let last = self.matches.len().saturating_sub(1) as isize;
+        self.selected = (self.selected as isize + delta).clamp(0, last) as usize;
+    }
+
+    /// Where the box goes in `area`
+    fn popup_area(&self, area: Rect) -> Rect {
+        let width = if area.width >= MIN_PREVIEW_WIDTH + 4 {
+            (area.width - 4).min(140)
+        } else {
+            area.width.saturating_sub(2).max(area.width.min(20))
+        };
+        let height = (area.height.saturating_sub(2) * 3 / 4).max(area.height.min(5));
+        Rect {
+            x: area.x + area.width.saturating_sub(width) / 2,
+            y: area.y + area.height.saturating_sub(height) / 2,
+            width,
+            height,
+        }
+    }
+}
+
+impl<T: 'static> Component for Picker<T> {
+    fn render(&self, frame: &mut Frame, area: Rect, ctx: &Context) {
+        let theme = &ctx.editor.theme;
+        let popup_style = theme.popup.to_ratatui();
+        let popup_area = self.popup_area(area);
+        let count = match (&self.loading, &self.note) {
+            (Some(_), _) => "listing...".to_string(),
+            (None, Some(note)) => format!("{}/{}, {}", self.matches.len(), self.items.len(), note),
+            (None, None) => format!("{}/{}", self.matches.len(), self.items.len()),
+        };
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(theme.popup_border.to_ratatui())
+            .title(format!(" {} ({}) ", self.title, count));
+        let inner = block.inner(popup_area);
+        frame.render_widget(Clear, popup_area);
+        frame.render_widget(block.style(popup_style), popup_area);
+        if inner.height == 0 {
+            return;
+        }
+
+        // The query on top, the list under it and the preview to its right
+        let query_area = Rect { height: 1, ..inner };
+        frame.render_widget(Paragraph::new(format!("> {}", self.query)).style(popup_style), query_area);
+        let body = Rect {
+            y: inner.y + 1,
+            height: inner.height - 1,
+            ..inner
+        };
+        let preview = self.preview.as_ref().filter(|_| popup_area.width >= MIN_PREVIEW_WIDTH);
+        let (list_area, preview_area) = match preview {
+            Some(_) => {
+                let list_width = body.width * 2 / 5;
+                let preview_area = Rect {
+                    x: body.x + list_width,
+                    width: body.width - list_width,
+                    ..body
+                };
+                (Rect { width: list_width, ..body }, Some(preview_area))
+            }
+            None => (body, None),
+        };
+
+        let rows = list_area.height as usize;
+        let first = (self.selected + 1).saturating_sub(rows);
+        let selected_style = popup_style.patch(theme.selection.to_ratatui());
+        let lines: Vec<Line> = self
+            .matches
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(rows)
+            .map(|(row, &i)| {
+                let style = if row == self.selected { selected_style } else { popup_style };
+                let label = Span::styled(format!(" {}", self.labels[i]), style);
+                match self.markers.get(i).copied().flatten() {
+                    Some(marker) => Line::from(vec![label, Span::styled(" ", style), git_status_span(marker, style, ctx)]),
+                    None => Line::from(label),
+                }
+            })
+            .collect();
+        let lines = if lines.is_empty() {
+            vec![Line::from(Span::styled(" No matches", popup_style.patch(theme.comment.to_ratatui())))]
+        } else {
+            lines
+        };
+        frame.render_widget(Paragraph::new(lines).style(popup_style), list_area);
+
+        let (Some(preview), Some(preview_area)) = (preview, preview_area) else {
+            return;
+        };
+        let Some(preview) = self.selected_item().and_then(|item| preview(item, ctx.editor)) else {
+            return;
+        };
+        let block = Block::default()
+            .borders(Borders::LEFT)
+            .border_style(theme.popup_border.to_ratatui());
+        let height = block.inner(preview_area).height as usize;
+        // Keep the line the item is about in the upper part of the preview
+        let first = preview.highlight.map_or(0, |line| line.saturating_sub(height / 3));
+        let lines: Vec<Line> = preview
+            .lines
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(height)
+            .map(|(i, line)| {
+                let style = if Some(i) == preview.highlight { selected_style } else { popup_style };
+                Line::from(Span::styled(format!(" {}", line.replace('\t', "    ")), style))
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(lines).block(block).style(popup_style), preview_area);
+    }
+
+    fn handle_key(&mut self, event: &KeyEvent, ctx: &mut Context) -> EventResult {
+        let page = 10;
+        match (&event.key, event.modifiers) {
+            (Key::Escape, _) => return EventResult::Action(Action::Noop),
+            (Key::Enter, Modifier::NONE) => {
+                if let Some(item) = self.selected_item() {
+                    (self.on_select)(item, ctx.editor);
+                }
+                return EventResult::Action(Action::Noop);
+            }
+            (Key::Up, Modifier::NONE) | (Key::Tab, Modifier::SHIFT) | (Key::Char('p'), Modifier::CTRL) => {
+                self.move_selection(-1)
+            }
+            (Key::Down, Modifier::NONE) | (Key::Tab, Modifier::NONE) | (Key::Char('n'), Modifier::CTRL) => {
+                self.move_selection(1)
+            }
+            (Key::PageUp, Modifier::NONE) => self.move_selection(-page),
+            (Key::PageDown, Modifier::NONE) => self.move_selection(page),
+            (Key::Char(c), Modifier::NONE) | (Key::Char(c), Modifier::SHIFT) => {
+                self.query.push(*c);
+                self.filter();
+            }
+            (Key::Backspace, Modifier::NONE) => {
+                self.query.pop();
+                self.filter();
+            }
+            (Key::Char('u'), Modifier::CTRL) => {
+                self.query.clear();
+                self.filter();
+            }
+            _ => {}
+        }
+        EventResult::Consumed
+    }
+
+    fn cursor(&self, area: Rect, _ctx: &Context) -> Option<(u16, u16)> {
+        let popup_area = self.popup_area(area);
+        let x = popup_area.x + 3 + self.query.width() as u16;
+        Some((x.min(popup_area.right().saturating_sub(2)), popup_area.y + 1))
+    }
+
+    fn is_popup(&self) -> bool {
+        true
+    }
+
+    fn poll(&mut self) -> bool {
+        let Some(loaded) = self.loading.as_ref().map(mpsc::Receiver::try_recv) else {
+            return false;
+        };
+        let loaded = match loaded {
+            Ok(loaded) => loaded,
+            Err(mpsc::TryRecvError::Empty) => return false,
+            Err(mpsc::TryRecvError::Disconnected) => {
+                self.loading = None;
+                return true;
+            }
+        };
+        self.loading = None;
+        self.labels = loaded.items.iter().map(&self.format).collect();
+        self.items = loaded.items;
+        self.markers = loaded.markers;
+        self.note = loaded.note;
+        self.filter();
+        // What was typed meanwhile picks the item instead
+        if self.query.is_empty() {
+            if let Some(selected) = loaded.selected {
+                self.select(selected);
+            }
+        }
+        true
+    }
+}
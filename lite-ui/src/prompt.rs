@@ -1,5 +1,7 @@
 use crate::{Component, Context, EventResult};
-use lite_config::{Action, Key, KeyEvent, Modifier};
+use lite_config::{Action, Key, KeyEvent, Modifier, SearchCase};
+use lite_core::Selection;
+use lite_view::{Editor, Severity};
 use ratatui::prelude::*;
 use ratatui::widgets::Paragraph;
 
@@ -11,23 +13,73 @@ pub enum PromptType {
     SaveAs,
     Open,
     GotoLine,
+    /// Save/discard/cancel question before closing a modified buffer
+    CloseBuffer,
+    /// Name of a new file in the file tree, a trailing `/` makes a directory
+    CreateFile,
+    /// New name of the entry picked in the file tree
+    RenameFile,
+    /// Yes/no question before deleting the entry picked in the file tree
+    DeleteFile,
 }
 
 /// Input prompt for commands, search, etc.
 pub struct Prompt {
     prompt_type: PromptType,
+    message: Option<String>,
     input: String,
     cursor: usize,
     submitted: bool,
+    /// Where a search prompt started, once it has had a key
+    origin: Option<SearchOrigin>,
+    /// The earlier input shown while going through the history, and what
+    /// was typed before, which entries have to start with
+    recalled: Option<(usize, String)>,
+    /// Lists the inputs completing what's typed, for Tab
+    complete: Option<fn(&Editor, &str) -> Vec<String>>,
+    /// The inputs Tab is going through, and which one is shown
+    completions: Option<(Vec<String>, usize)>,
+}
+
+/// The state a search prompt started from, put back if it's cancelled
+struct SearchOrigin {
+    selection: Selection,
+    scroll: (usize, usize),
+    query: String,
+}
+
+impl SearchOrigin {
+    fn new(editor: &Editor) -> Self {
+        let view = editor.current_view();
+        Self {
+            selection: editor.current_doc().selection(view.id),
+            scroll: (view.scroll_y, view.scroll_x),
+            query: editor.search_query.clone(),
+        }
+    }
+
+    fn restore(&self, editor: &mut Editor) {
+        let view_id = editor.tree.focus();
+        editor.current_doc_mut().set_selection(view_id, self.selection.clone());
+        let view = editor.current_view_mut();
+        (view.scroll_y, view.scroll_x) = self.scroll;
+        editor.search_query = self.query.clone();
+        editor.clear_search_decorations();
+    }
 }
 
 impl Prompt {
     pub fn new(prompt_type: PromptType) -> Self {
         Self {
             prompt_type,
+            message: None,
             input: String::new(),
             cursor: 0,
             submitted: false,
+            origin: None,
+            recalled: None,
+            complete: None,
+            completions: None,
         }
     }
 
@@ -37,6 +89,25 @@ impl Prompt {
         self
     }
 
+    /// Put the cursor at a byte offset of the input rather than its end
+    pub fn with_cursor(mut self, cursor: usize) -> Self {
+        self.cursor = cursor.min(self.input.len());
+        self
+    }
+
+    /// Complete the input with Tab, going through the inputs a function
+    /// lists for what's typed
+    pub fn with_completion(mut self, complete: fn(&Editor, &str) -> Vec<String>) -> Self {
+        self.complete = Some(complete);
+        self
+    }
+
+    /// Replace the default prefix with a custom message
+    pub fn with_message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+
     pub fn prompt_type(&self) -> PromptType {
         self.prompt_type
     }
@@ -49,14 +120,108 @@ impl Prompt {
         self.submitted
     }
 
-    fn prefix(&self) -> &str {
+    /// Name of the history kept of this kind of prompt's inputs; questions
+    /// have none
+    pub fn history_kind(&self) -> Option<&'static str> {
         match self.prompt_type {
+            PromptType::Command => Some("command"),
+            PromptType::Search => Some("search"),
+            PromptType::SaveAs => Some("save-as"),
+            PromptType::Open => Some("open"),
+            PromptType::GotoLine => Some("goto-line"),
+            PromptType::CreateFile => Some("create-file"),
+            PromptType::RenameFile => Some("rename-file"),
+            PromptType::CloseBuffer | PromptType::DeleteFile => None,
+        }
+    }
+
+    /// Show the next older input (`older`) or newer one starting with what
+    /// was typed, and what was typed after the newest
+    fn recall(&mut self, editor: &Editor, older: bool) {
+        let Some(history) = self.history_kind().and_then(|kind| editor.prompt_history.get(kind)) else {
+            return;
+        };
+        let (index, typed) = match self.recalled.take() {
+            Some((index, typed)) => (Some(index), typed),
+            None => (None, self.input.clone()),
+        };
+        let matches = |i: &usize| history[*i].starts_with(&typed) && history[*i] != typed;
+        let next = match (index, older) {
+            (None, true) => (0..history.len()).find(matches),
+            (Some(index), true) => (index + 1..history.len()).find(matches).or(Some(index)),
+            (Some(index), false) => (0..index).rev().find(matches),
+            (None, false) => None,
+        };
+        self.input = match next {
+            Some(index) => history[index].clone(),
+            None => typed.clone(),
+        };
+        self.cursor = self.input.len();
+        self.recalled = next.map(|index| (index, typed));
+    }
+
+    /// Put in the next completion (`forward`) or the previous one. The
+    /// first Tab lists what completes the input and puts in the first, or
+    /// with Shift the last; more Tabs cycle through that list while the
+    /// input is still the one put in. A single completion isn't kept, so
+    /// the next Tab completes from it.
+    fn complete(&mut self, editor: &mut Editor, forward: bool) {
+        let Some(complete) = self.complete else {
+            return;
+        };
+        let (completions, index) = match self.completions.take() {
+            Some((completions, index)) if completions.get(index) == Some(&self.input) => {
+                let len = completions.len();
+                let index = if forward { (index + 1) % len } else { (index + len - 1) % len };
+                (completions, index)
+            }
+            _ => {
+                let completions = complete(editor, &self.input);
+                if completions.is_empty() {
+                    editor.set_status("No completions", Severity::Info);
+                    return;
+                }
+                let index = if forward { 0 } else { completions.len() - 1 };
+                (completions, index)
+            }
+        };
+        self.input = completions[index].clone();
+        self.cursor = self.input.len();
+        if completions.len() > 1 {
+            editor.set_status(format!("completion {} of {}", index + 1, completions.len()), Severity::Info);
+            self.completions = Some((completions, index));
+        } else {
+            editor.clear_status();
+        }
+    }
+
+    /// The text before the input. A search shows when it doesn't use
+    /// smart case or only finds whole words.
+    fn prefix(&self, editor: &Editor) -> String {
+        if let Some(message) = &self.message {
+            return message.clone();
+        }
+        if self.prompt_type == PromptType::Search {
+            let case = match editor.config.editor.search_case {
+                SearchCase::Smart => "",
+                SearchCase::Sensitive => "(Aa) ",
+                SearchCase::Ignore => "(aa) ",
+            };
+            let word = if editor.search_whole_word { "(word) " } else { "" };
+            return format!("{}{}/", case, word);
+        }
+        let prefix = match self.prompt_type {
             PromptType::Command => ":",
             PromptType::Search => "/",
             PromptType::SaveAs => "Save as: ",
             PromptType::Open => "Open: ",
             PromptType::GotoLine => "Goto line: ",
-        }
+            PromptType::CloseBuffer => "Save changes? (y/n, Esc to cancel) ",
+            PromptType::CreateFile => "New file (end with / for a directory): ",
+            PromptType::RenameFile => "Rename to: ",
+            PromptType::DeleteFile => "Delete? (y/n) ",
+        };
+        prefix.to_string()
     }
 
     fn insert_char(&mut self, c: char) {
@@ -101,33 +266,89 @@ impl Prompt {
         self.input.clear();
         self.cursor = 0;
     }
+
+    /// Search as you type: select the first match from where the prompt
+    /// started, or go back there while the query is empty
+    fn search(&self, editor: &mut Editor) {
+        let Some(origin) = &self.origin else {
+            return;
+        };
+        if self.input.is_empty() {
+            origin.restore(editor);
+            editor.clear_status();
+            return;
+        }
+        editor.search_query = self.input.clone();
+        editor.find_from(origin.selection.primary().start(), true);
+    }
 }
 
 impl Component for Prompt {
     fn render(&self, frame: &mut Frame, area: Rect, ctx: &Context) {
         let style = ctx.editor.theme.popup.to_ratatui();
-        let prefix = self.prefix();
+        let prefix = self.prefix(ctx.editor);
         let text = format!("{}{}", prefix, self.input);
         let prompt = Paragraph::new(text).style(style);
         frame.render_widget(prompt, area);
     }
 
-    fn handle_key(&mut self, event: &KeyEvent, _ctx: &mut Context) -> EventResult {
+    fn handle_key(&mut self, event: &KeyEvent, ctx: &mut Context) -> EventResult {
+        if self.prompt_type == PromptType::CloseBuffer {
+            return match event.key {
+                Key::Char('y') | Key::Char('Y') => {
+                    EventResult::Action(Action::ExecuteCloseBuffer(true))
+                }
+                Key::Char('n') | Key::Char('N') => {
+                    EventResult::Action(Action::ExecuteCloseBuffer(false))
+                }
+                Key::Escape | Key::Char('c') | Key::Char('C') => EventResult::Action(Action::Noop),
+                _ => EventResult::Consumed,
+            };
+        }
+        if self.prompt_type == PromptType::DeleteFile {
+            return match event.key {
+                Key::Char('y') | Key::Char('Y') => EventResult::Action(Action::ExecuteDeleteFile),
+                Key::Char('n') | Key::Char('N') | Key::Escape => EventResult::Action(Action::Noop),
+                _ => EventResult::Consumed,
+            };
+        }
+
+        if self.prompt_type == PromptType::Search && self.origin.is_none() {
+            self.origin = Some(SearchOrigin::new(ctx.editor));
+        }
+        let input = self.input.clone();
+        // Editing starts the history over from the new input
+        if !matches!(event.key, Key::Up | Key::Down) {
+            self.recalled = None;
+        }
+        if event.key != Key::Tab {
+            self.completions = None;
+        }
+
         match (&event.key, event.modifiers) {
             // Cancel
             (Key::Escape, _) => {
+                if let Some(origin) = &self.origin {
+                    origin.restore(ctx.editor);
+                }
                 return EventResult::Action(Action::Noop);
             }
 
             // Submit
             (Key::Enter, Modifier::NONE) => {
                 self.submitted = true;
+                if let Some(kind) = self.history_kind() {
+                    ctx.editor.remember_input(kind, &self.input);
+                }
                 let action = match self.prompt_type {
                     PromptType::GotoLine => Action::ExecuteGotoLine(self.input.clone()),
                     PromptType::Search => Action::ExecuteSearch(self.input.clone()),
                     PromptType::Open => Action::ExecuteOpen(self.input.clone()),
                     PromptType::SaveAs => Action::ExecuteSaveAs(self.input.clone()),
-                    _ => Action::Noop,
+                    PromptType::Command => Action::ExecuteCommand(self.input.clone()),
+                    PromptType::CreateFile => Action::ExecuteCreateFile(self.input.clone()),
+                    PromptType::RenameFile => Action::ExecuteRenameFile(self.input.clone()),
+                    PromptType::CloseBuffer | PromptType::DeleteFile => Action::Noop,
                 };
                 return EventResult::Action(action);
             }
@@ -161,19 +382,58 @@ impl Component for Prompt {
                 self.move_end();
             }
 
+            // Completions, Shift+Tab going back through them
+            (Key::Tab, Modifier::NONE) if self.complete.is_some() => self.complete(ctx.editor, true),
+            (Key::Tab, Modifier::SHIFT) if self.complete.is_some() => self.complete(ctx.editor, false),
+
+            // Earlier inputs
+            (Key::Up, Modifier::NONE) => self.recall(ctx.editor, true),
+            (Key::Down, Modifier::NONE) => self.recall(ctx.editor, false),
+
             // Clear input
             (Key::Char('u'), Modifier::CTRL) => {
                 self.clear();
             }
 
+            // Case sensitivity and whole words, for this search and the ones after it
+            (Key::Char('c'), Modifier::ALT) if self.prompt_type == PromptType::Search => {
+                let case = &mut ctx.editor.config.editor.search_case;
+                *case = case.next();
+                let message = match *case {
+                    SearchCase::Smart => "Smart case: ignored unless the search has capitals",
+                    SearchCase::Sensitive => "Case sensitive",
+                    SearchCase::Ignore => "Ignoring case",
+                };
+                ctx.editor.set_status(message, Severity::Info);
+                if !self.input.is_empty() {
+                    self.search(ctx.editor);
+                }
+                return EventResult::Consumed;
+            }
+            (Key::Char('w'), Modifier::ALT) if self.prompt_type == PromptType::Search => {
+                ctx.editor.search_whole_word = !ctx.editor.search_whole_word;
+                let message = match ctx.editor.search_whole_word {
+                    true => "Whole words only",
+                    false => "Matches inside words too",
+                };
+                ctx.editor.set_status(message, Severity::Info);
+                if !self.input.is_empty() {
+                    self.search(ctx.editor);
+                }
+                return EventResult::Consumed;
+            }
+
             _ => return EventResult::Ignored,
         }
 
+        if self.prompt_type == PromptType::Search && self.input != input {
+            self.search(ctx.editor);
+        }
         EventResult::Consumed
     }
 
-    fn cursor(&self, area: Rect, _ctx: &Context) -> Option<(u16, u16)> {
-        let prefix_len = self.prefix().len();
+    fn cursor(&self, area: Rect, ctx: &Context) -> Option<(u16, u16)> {
+        let prefix_len = self.prefix(ctx.editor).len();
         let cursor_x = area.x + prefix_len as u16 + self.cursor as u16;
         Some((cursor_x, area.y))
     }
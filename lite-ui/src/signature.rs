@@ -0,0 +1,83 @@
+use crate::{Component, Context, EditorView};
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, Borders, Clear, Paragraph};
+
+/// Most overloads shown at once
+const MAX_ROWS: usize = 5;
+
+/// Signature help, anchored above the cursor (below it near the top) so it
+/// doesn't cover the completion menu
+pub struct SignatureHelpPopup;
+
+impl SignatureHelpPopup {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for SignatureHelpPopup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Component for SignatureHelpPopup {
+    fn render(&self, frame: &mut Frame, area: Rect, ctx: &Context) {
+        let Some(help) = &ctx.editor.signature_help else {
+            return;
+        };
+        let Some((cursor_x, cursor_y)) = EditorView::new().cursor(area, ctx) else {
+            return;
+        };
+
+        // Keep the active overload in the shown rows
+        let rows = help.signatures.len().min(MAX_ROWS);
+        let first = help.active.saturating_sub(rows - 1);
+        let shown = &help.signatures[first..first + rows];
+
+        let popup_style = ctx.editor.theme.popup.to_ratatui();
+        let inactive_style = popup_style.patch(ctx.editor.theme.comment.to_ratatui());
+        let parameter_style = popup_style.patch(ctx.editor.theme.selection.to_ratatui());
+        let lines: Vec<Line> = shown
+            .iter()
+            .enumerate()
+            .map(|(row, signature)| {
+                if first + row != help.active {
+                    return Line::from(Span::styled(format!(" {} ", signature.label), inactive_style));
+                }
+                let chars: Vec<char> = signature.label.chars().collect();
+                let range = signature
+                    .active_range()
+                    .filter(|range| range.start <= range.end && range.end <= chars.len())
+                    .unwrap_or(0..0);
+                let text = |range: std::ops::Range<usize>| chars[range].iter().collect::<String>();
+                Line::from(vec![
+                    Span::styled(format!(" {}", text(0..range.start)), popup_style),
+                    Span::styled(text(range.clone()), parameter_style),
+                    Span::styled(format!("{} ", text(range.end..chars.len())), popup_style),
+                ])
+            })
+            .collect();
+
+        let content_width = lines.iter().map(Line::width).max().unwrap_or(0);
+        let width = (content_width as u16 + 2).min(area.width);
+        let height = (rows as u16 + 2).min(area.height);
+        let x = cursor_x.min(area.right().saturating_sub(width)).max(area.x);
+        let y = if cursor_y >= area.y + height {
+            cursor_y - height
+        } else {
+            (cursor_y + 1).min(area.bottom().saturating_sub(height))
+        };
+        let popup_area = Rect { x, y, width, height };
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(ctx.editor.theme.popup_border.to_ratatui());
+
+        frame.render_widget(Clear, popup_area);
+        frame.render_widget(
+            Paragraph::new(lines).block(block).style(popup_style),
+            popup_area,
+        );
+    }
+}
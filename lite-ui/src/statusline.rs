@@ -1,7 +1,15 @@
 use crate::{Component, Context};
 use lite_core::RopeExt;
+use lite_view::{todo, DiagnosticSeverity, ServerState};
 use ratatui::prelude::*;
 use ratatui::widgets::Paragraph;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Frames of the spinner shown while a language server is busy
+const SPINNER: &[char] = &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// Longest progress message shown, so the position stays visible
+const MAX_PROGRESS_LEN: usize = 40;
 
 /// Status line at the bottom of the editor
 pub struct StatusLine;
@@ -36,14 +44,32 @@ impl Component for StatusLine {
 
         // Right side: position, language, encoding
         let language = doc.language.as_deref().unwrap_or("text");
-        let encoding = doc.encoding;
+        let encoding = if doc.bom {
+            format!("{} BOM", doc.encoding)
+        } else {
+            doc.encoding.to_string()
+        };
         let line_ending = match doc.line_ending {
             lite_view::LineEnding::LF => "LF",
             lite_view::LineEnding::CRLF => "CRLF",
         };
 
-        let position_info = format!("{}:{}", line, col);
-        let right_info = format!(" {} | {} | {} ", language, encoding, line_ending);
+        // With several cursors, how many and how much they select
+        let position_info = match selection.len() {
+            1 => format!("{}:{}", line, col),
+            n => {
+                let chars: usize = selection.ranges().iter().map(|range| range.len()).sum();
+                format!("{} selections ({} chars) {}:{}", n, chars, line, col)
+            }
+        };
+        let right_info = match ctx.editor.workspace_of(doc.id) {
+            // Name the folder a document belongs to once several are open
+            Some(workspace) => {
+                let name = workspace.file_name().unwrap_or(workspace.as_os_str()).to_string_lossy();
+                format!(" {} | {} | {} | {} ", name, language, encoding, line_ending)
+            }
+            None => format!(" {} | {} | {} ", language, encoding, line_ending),
+        };
 
         // Check for status message
         let (left_text, _left_style) = if let Some((msg, severity)) = &ctx.editor.status_msg {
@@ -53,6 +79,14 @@ impl Component for StatusLine {
                 lite_view::Severity::Error => ctx.editor.theme.error.to_ratatui(),
             };
             (msg.clone(), style)
+        } else if let Some(version) = &ctx.editor.update_available {
+            (
+                format!(
+                    " {}{}  Update v{} available (Alt+U to install, Esc to dismiss)",
+                    filename, modified_indicator, version
+                ),
+                ctx.editor.theme.info.to_ratatui(),
+            )
         } else {
             (
                 format!(" {}{}", filename, modified_indicator),
@@ -61,20 +95,84 @@ impl Component for StatusLine {
         };
 
         // Build the status line
-        let status_style = ctx.editor.theme.statusline.to_ratatui();
+        let mut status_style = ctx.editor.theme.statusline.to_ratatui();
+        if ctx.editor.bell_flashing(lite_config::Bell::Statusline) {
+            status_style = status_style.add_modifier(Modifier::REVERSED);
+        }
+
+        // Diagnostic counts, shown only when there are any
+        let count = |severity| doc.diagnostics.iter().filter(|d| d.severity == severity).count();
+        let mut diagnostic_spans = Vec::new();
+        // Timings of the last frame with input, for `debug_metrics = "screen"`
+        if let Some(metrics) = &ctx.editor.frame_metrics {
+            let style = if metrics.over_budget() {
+                ctx.editor.theme.warning
+            } else {
+                ctx.editor.theme.comment
+            };
+            diagnostic_spans.push(Span::styled(format!("{} ", metrics), status_style.patch(style.to_ratatui())));
+        }
+        for (severity, symbol, style) in [
+            (DiagnosticSeverity::Error, "●", ctx.editor.theme.error),
+            (DiagnosticSeverity::Warning, "▲", ctx.editor.theme.warning),
+        ] {
+            let n = count(severity);
+            if n > 0 {
+                diagnostic_spans.push(Span::styled(
+                    format!("{} {} ", symbol, n),
+                    status_style.patch(style.to_ratatui()),
+                ));
+            }
+        }
+        // Project-wide TODO counts, once scanned
+        if let Some(todos) = &ctx.editor.todos {
+            let keywords = &ctx.editor.config.editor.todo_keywords;
+            for (keyword, n) in todo::counts(todos, keywords) {
+                diagnostic_spans.push(Span::styled(
+                    format!("{} {} ", keyword, n),
+                    status_style.patch(ctx.editor.theme.comment.to_ratatui()),
+                ));
+            }
+        }
+        // The language server, with a spinner while it starts or works. A
+        // slow terminal gets a still one.
+        if let Some(server) = &doc.server {
+            let spinner = || {
+                if ctx.editor.low_bandwidth {
+                    return '…';
+                }
+                let millis = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+                SPINNER[(millis / 80) as usize % SPINNER.len()]
+            };
+            let text = match &server.state {
+                ServerState::Starting => format!("{} {} starting ", spinner(), server.name),
+                ServerState::Busy(message) => {
+                    let mut shown: String = message.chars().take(MAX_PROGRESS_LEN).collect();
+                    if shown.len() < message.len() {
+                        shown.push('…');
+                    }
+                    format!("{} {}: {} ", spinner(), server.name, shown)
+                }
+                ServerState::Ready => format!("{} ", server.name),
+            };
+            diagnostic_spans.push(Span::styled(
+                text,
+                status_style.patch(ctx.editor.theme.comment.to_ratatui()),
+            ));
+        }
+        let diagnostics_len: usize = diagnostic_spans.iter().map(|span| span.content.chars().count()).sum();
 
         // Calculate padding
-        let left_len = left_text.len();
-        let right_len = position_info.len() + right_info.len();
-        let padding = area.width as usize - left_len.min(area.width as usize) - right_len.min(area.width as usize - left_len);
-
-        let status_text = format!(
-            "{}{}{}{}",
-            left_text,
-            " ".repeat(padding.max(1)),
-            position_info,
-            right_info
-        );
+        let width = area.width as usize;
+        let left_len = left_text.chars().count().min(width);
+        let right_len = (diagnostics_len + position_info.len() + right_info.chars().count()).min(width - left_len);
+        let padding = width - left_len - right_len;
+
+        let mut spans = vec![Span::raw(left_text), Span::raw(" ".repeat(padding.max(1)))];
+        spans.extend(diagnostic_spans);
+        spans.push(Span::raw(position_info));
+        spans.push(Span::raw(right_info));
+        let status_text = Line::from(spans);
 
         let status = Paragraph::new(status_text).style(status_style);
         frame.render_widget(status, area);
@@ -2,7 +2,8 @@ use crate::{Component, Context};
 use ratatui::prelude::*;
 use ratatui::widgets::Paragraph;
 
-/// Tab line showing open buffers
+/// Tab line showing open buffers, and the layout pages at the right once
+/// there's more than one
 pub struct TabLine;
 
 impl TabLine {
@@ -17,6 +18,17 @@ impl Default for TabLine {
     }
 }
 
+/// A git status marker such as `M` or `??`, colored like the gutter marks
+pub(crate) fn git_status_span(marker: &'static str, style: Style, ctx: &Context) -> Span<'static> {
+    let theme = &ctx.editor.theme;
+    let color = match marker {
+        "A" | "??" => theme.diff_add,
+        "D" => theme.diff_delete,
+        _ => theme.diff_modify,
+    };
+    Span::styled(marker, style.patch(color.to_ratatui()))
+}
+
 impl Component for TabLine {
     fn render(&self, frame: &mut Frame, area: Rect, ctx: &Context) {
         let current_doc_id = ctx.editor.current_view().doc_id;
@@ -34,9 +46,13 @@ impl Component for TabLine {
                 tab_style
             };
 
-            // Add tab number
+            // Add tab number, and the git status of the file after the title
             let tab_text = format!(" {}:{} ", i + 1, title);
             spans.push(Span::styled(tab_text, style));
+            if let Some(marker) = ctx.editor.documents.get(doc_id).and_then(|doc| doc.git_status) {
+                spans.push(git_status_span(marker, style, ctx));
+                spans.push(Span::styled(" ", style));
+            }
 
             // Add separator
             if i < buffers.len() - 1 {
@@ -44,16 +60,42 @@ impl Component for TabLine {
             }
         }
 
+        let mut pages = Vec::new();
+        if ctx.editor.pages().count() > 1 {
+            pages.push(Span::styled("│", tab_style));
+            for (i, title) in ctx.editor.page_titles().into_iter().enumerate() {
+                let style = if i == ctx.editor.current_page() {
+                    tab_active_style
+                } else {
+                    tab_style
+                };
+                pages.push(Span::styled(format!(" [{}] ", title), style));
+            }
+        }
+        let pages_width = (pages.iter().map(|s| s.width()).sum::<usize>() as u16).min(area.width);
+        let tabs_area = Rect {
+            width: area.width - pages_width,
+            ..area
+        };
+
         // Fill remaining space
         let used_width: usize = spans.iter().map(|s| s.width()).sum();
-        if used_width < area.width as usize {
+        if used_width < tabs_area.width as usize {
             spans.push(Span::styled(
-                " ".repeat(area.width as usize - used_width),
+                " ".repeat(tabs_area.width as usize - used_width),
                 tab_style,
             ));
         }
 
         let tabs = Paragraph::new(Line::from(spans));
-        frame.render_widget(tabs, area);
+        frame.render_widget(tabs, tabs_area);
+        if pages_width > 0 {
+            let pages_area = Rect {
+                x: area.right() - pages_width,
+                width: pages_width,
+                ..area
+            };
+            frame.render_widget(Paragraph::new(Line::from(pages)), pages_area);
+        }
     }
 }
@@ -0,0 +1,161 @@
+//! Virtual text: display-only text attached to a position of a document.
+//!
+//! Blame, inlay hints, diagnostics and code lenses all show text that isn't
+//! part of the file. Each feature adds its annotations under its own source
+//! name and replaces them by clearing that source. Positions are chars and
+//! move with edits like folds do.
+
+use lite_core::{ChangeSet, Rope};
+
+/// Where an annotation's text is drawn relative to its position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    /// Inline, just before the char at the position
+    Before,
+    /// Inline, just after the char at the position
+    After,
+    /// After the end of the position's line
+    EndOfLine,
+    /// On a row of its own above the position's line, lined up with its
+    /// indentation
+    Above,
+}
+
+/// How an annotation's text is colored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AnnotationStyle {
+    #[default]
+    Comment,
+    Hint,
+    Info,
+    Warning,
+    Error,
+}
+
+/// Text drawn at a position without being part of the document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Annotation {
+    /// Char the annotation is attached to
+    pub pos: usize,
+    pub placement: Placement,
+    pub text: String,
+    pub style: AnnotationStyle,
+    /// The feature that added it, such as `"blame"` or `"inlay-hints"`
+    pub source: &'static str,
+}
+
+impl Annotation {
+    pub fn new(source: &'static str, pos: usize, placement: Placement, text: impl Into<String>) -> Self {
+        Self {
+            pos,
+            placement,
+            text: text.into(),
+            style: AnnotationStyle::default(),
+            source,
+        }
+    }
+
+    pub fn with_style(mut self, style: AnnotationStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Whether it's drawn within the text of its line
+    pub fn is_inline(&self) -> bool {
+        matches!(self.placement, Placement::Before | Placement::After)
+    }
+}
+
+/// Annotations of a document, sorted by position
+#[derive(Debug, Clone, Default)]
+pub struct Annotations {
+    annotations: Vec<Annotation>,
+}
+
+impl Annotations {
+    pub fn is_empty(&self) -> bool {
+        self.annotations.is_empty()
+    }
+
+    pub fn add(&mut self, annotation: Annotation) {
+        let i = self.annotations.partition_point(|other| other.pos <= annotation.pos);
+        self.annotations.insert(i, annotation);
+    }
+
+    /// Remove what a source added
+    pub fn clear(&mut self, source: &str) {
+        self.annotations.retain(|annotation| annotation.source != source);
+    }
+
+    /// Replace what a source added
+    pub fn set(&mut self, source: &'static str, annotations: impl IntoIterator<Item = Annotation>) {
+        self.clear(source);
+        for annotation in annotations {
+            self.add(Annotation { source, ..annotation });
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Annotation> {
+        self.annotations.iter()
+    }
+
+    /// Annotations whose position is on a line, in order
+    pub fn on_line<'a>(&'a self, rope: &Rope, line: usize) -> impl Iterator<Item = &'a Annotation> {
+        let start = rope.line_to_char(line);
+        let end = match line + 1 < rope.len_lines() {
+            true => rope.line_to_char(line + 1),
+            false => rope.len_chars() + 1,
+        };
+        let first = self.annotations.partition_point(|annotation| annotation.pos < start);
+        let last = self.annotations.partition_point(|annotation| annotation.pos < end);
+        self.annotations[first..last].iter()
+    }
+
+    /// Whether a line has rows of annotations above it
+    pub fn has_above(&self, rope: &Rope, line: usize) -> bool {
+        self.on_line(rope, line).any(|annotation| annotation.placement == Placement::Above)
+    }
+
+    /// Move the annotations across an edit. Those in deleted text stay where
+    /// it was.
+    pub fn map(&mut self, changes: &ChangeSet) {
+        for annotation in &mut self.annotations {
+            annotation.pos = changes.map_pos(annotation.pos);
+        }
+        self.annotations.sort_by_key(|annotation| annotation.pos);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lite_core::Transaction;
+
+    fn texts<'a>(annotations: impl Iterator<Item = &'a Annotation>) -> Vec<&'a str> {
+        annotations.map(|annotation| annotation.text.as_str()).collect()
+    }
+
+    #[test]
+    fn test_annotations() {
+        let mut rope = Rope::from("let x = 1;\nfn main() {}\n");
+        let mut annotations = Annotations::default();
+        annotations.add(Annotation::new("blame", 11, Placement::EndOfLine, "you, today"));
+        annotations.add(Annotation::new("inlay-hints", 5, Placement::After, ": i32"));
+        annotations.add(Annotation::new("code-lens", 11, Placement::Above, "Run"));
+        assert_eq!(texts(annotations.on_line(&rope, 0)), vec![": i32"]);
+        assert_eq!(texts(annotations.on_line(&rope, 1)), vec!["you, today", "Run"]);
+        assert!(annotations.has_above(&rope, 1) && !annotations.has_above(&rope, 0));
+
+        // Edits before move them, and each source is replaced on its own
+        let tx = Transaction::insert(rope.len_chars(), 0, "// x\n");
+        tx.apply(&mut rope);
+        annotations.map(&tx.changes);
+        assert_eq!(annotations.iter().map(|annotation| annotation.pos).collect::<Vec<_>>(), vec![10, 16, 16]);
+        assert_eq!(texts(annotations.on_line(&rope, 2)), vec!["you, today", "Run"]);
+        annotations.set("blame", [Annotation::new("", 0, Placement::EndOfLine, "me")]);
+        assert_eq!(texts(annotations.on_line(&rope, 0)), vec!["me"]);
+        assert_eq!(annotations.iter().filter(|annotation| annotation.source == "blame").count(), 1);
+        annotations.clear("inlay-hints");
+        assert_eq!(texts(annotations.iter()), vec!["me", "Run"]);
+    }
+}
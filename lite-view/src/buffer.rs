@@ -0,0 +1,34 @@
+use crate::{DocumentId, Editor};
+use lite_config::KeyEvent;
+
+/// What backs a document's text
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum BufferKind {
+    /// A regular document, optionally backed by a file
+    #[default]
+    File,
+    /// A generated buffer (help, diagnostics, git status, ...) with a display name
+    Virtual(String),
+}
+
+impl BufferKind {
+    /// Check if this is a virtual buffer
+    pub fn is_virtual(&self) -> bool {
+        matches!(self, BufferKind::Virtual(_))
+    }
+}
+
+/// Custom key handling for virtual buffers.
+///
+/// Handlers are registered with `Editor::open_virtual` and get the first
+/// chance at every key pressed while their buffer is focused.
+pub trait BufferHandler {
+    /// Handle a key event. Return `true` if the key was consumed.
+    fn handle_key(&mut self, editor: &mut Editor, doc_id: DocumentId, key: &KeyEvent) -> bool;
+
+    /// Write the buffer somewhere, for buffers that edit something other
+    /// than a file. Return `true` if saving was handled.
+    fn save(&mut self, _editor: &mut Editor, _doc_id: DocumentId) -> bool {
+        false
+    }
+}
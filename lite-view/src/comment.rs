@@ -0,0 +1,65 @@
+//! Comment syntax of languages
+
+/// Token starting a line comment in a language, if it has line comments
+pub fn line_comment(language: &str) -> Option<&'static str> {
+    Some(match language {
+        "rust" | "c" | "cpp" | "go" | "java" | "javascript" | "typescript" | "swift" | "kotlin"
+        | "scala" | "php" | "scss" | "less" => "//",
+        "python" | "ruby" | "bash" | "fish" | "powershell" | "elixir" | "yaml" | "toml"
+        | "makefile" | "dockerfile" | "git-commit" | "git-rebase" => "#",
+        "lua" | "sql" | "haskell" => "--",
+        "erlang" | "latex" => "%",
+        "clojure" => ";",
+        "vim" => "\"",
+        _ => return None,
+    })
+}
+
+/// Tokens opening and closing a block comment in a language, if it has
+/// block comments
+pub fn block_comment(language: &str) -> Option<(&'static str, &'static str)> {
+    Some(match language {
+        "rust" | "c" | "cpp" | "go" | "java" | "javascript" | "typescript" | "swift" | "kotlin"
+        | "scala" | "php" | "css" | "scss" | "less" | "sql" => ("/*", "*/"),
+        "html" | "xml" | "markdown" => ("<!--", "-->"),
+        "lua" => ("--[[", "]]"),
+        "haskell" => ("{-", "-}"),
+        "ocaml" => ("(*", "*)"),
+        _ => return None,
+    })
+}
+
+/// The comment prefix of a line starting with a line comment: the token,
+/// repeats of its last char as in `///`, the `!` of `//!` and the blanks
+/// after them. `None` if the line isn't a comment, or is a `#!` shebang.
+pub fn line_prefix<'a>(line: &'a str, token: &str) -> Option<&'a str> {
+    let indent = line.len() - line.trim_start_matches([' ', '\t']).len();
+    let rest = line[indent..].strip_prefix(token)?;
+    if token == "#" && rest.starts_with('!') {
+        return None;
+    }
+    let last = token.chars().next_back()?;
+    let mut marks = rest.len() - rest.trim_start_matches(last).len();
+    if token == "//" && rest[marks..].starts_with('!') {
+        marks += 1;
+    }
+    let blanks = rest[marks..].len() - rest[marks..].trim_start_matches([' ', '\t']).len();
+    Some(&line[indent..indent + token.len() + marks + blanks])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_prefix() {
+        assert_eq!(line_prefix("    /// Doc", "//"), Some("/// "));
+        assert_eq!(line_prefix("//! crate", "//"), Some("//! "));
+        assert_eq!(line_prefix("\t#  note", "#"), Some("#  "));
+        assert_eq!(line_prefix("-- query", "--"), Some("-- "));
+        assert_eq!(line_prefix("#!/bin/sh", "#"), None);
+        assert_eq!(line_prefix("let x = 1; // trailing", "//"), None);
+        assert_eq!(line_comment("python"), Some("#"));
+        assert_eq!(line_comment("json"), None);
+    }
+}
@@ -0,0 +1,130 @@
+//! Comparing two open documents line by line
+
+use crate::{Document, DocumentId};
+use lite_core::{Hunk, RopeExt};
+use std::ops::Range;
+
+/// Two documents being compared and where they differ
+#[derive(Debug, Clone)]
+pub struct BufferDiff {
+    pub left: DocumentId,
+    pub right: DocumentId,
+    /// `before` ranges are lines of the left document, `after` of the right
+    pub hunks: Vec<Hunk>,
+    /// Aligned row shown at the top of both views, see `rows`
+    pub top: usize,
+    /// Document versions the hunks were computed for
+    versions: (usize, usize),
+    /// Line counts of the documents
+    lens: (usize, usize),
+}
+
+impl BufferDiff {
+    pub fn new(left: &Document, right: &Document) -> Self {
+        let mut diff = Self {
+            left: left.id,
+            right: right.id,
+            hunks: Vec::new(),
+            top: 0,
+            versions: (usize::MAX, usize::MAX),
+            lens: (0, 0),
+        };
+        diff.update(left, right);
+        diff
+    }
+
+    /// Recompute the hunks if either document changed. Returns whether
+    /// they were.
+    pub fn update(&mut self, left: &Document, right: &Document) -> bool {
+        let versions = (left.version(), right.version());
+        if versions == self.versions {
+            return false;
+        }
+        let left_lines = lines(left);
+        let right_lines = lines(right);
+        self.hunks = lite_core::diff(&left_lines, &right_lines);
+        self.versions = versions;
+        self.lens = (left_lines.len(), right_lines.len());
+        true
+    }
+
+    /// Whether a document is one of the compared ones
+    pub fn contains(&self, doc_id: DocumentId) -> bool {
+        doc_id == self.left || doc_id == self.right
+    }
+
+    /// The document a document is compared with
+    pub fn other(&self, doc_id: DocumentId) -> DocumentId {
+        if doc_id == self.left {
+            self.right
+        } else {
+            self.left
+        }
+    }
+
+    /// Lines of each hunk in one of the documents, as (this side, other side)
+    pub fn ranges(&self, doc_id: DocumentId) -> impl Iterator<Item = (Range<usize>, Range<usize>)> + '_ {
+        let left = doc_id == self.left;
+        self.hunks.iter().map(move |hunk| {
+            if left {
+                (hunk.before.clone(), hunk.after.clone())
+            } else {
+                (hunk.after.clone(), hunk.before.clone())
+            }
+        })
+    }
+
+    /// The lines of both documents side by side, as (left, right). Equal
+    /// lines share a row; within a hunk the shorter side is padded with
+    /// `None` so the lines after it line up again.
+    pub fn rows(&self) -> Vec<(Option<usize>, Option<usize>)> {
+        let mut rows = Vec::new();
+        let (mut left, mut right) = (0, 0);
+        for hunk in &self.hunks {
+            while left < hunk.before.start && right < hunk.after.start {
+                rows.push((Some(left), Some(right)));
+                left += 1;
+                right += 1;
+            }
+            for i in 0..hunk.before.len().max(hunk.after.len()) {
+                let side = |range: &Range<usize>| (i < range.len()).then_some(range.start + i);
+                rows.push((side(&hunk.before), side(&hunk.after)));
+            }
+            (left, right) = (hunk.before.end, hunk.after.end);
+        }
+        while left < self.lens.0 || right < self.lens.1 {
+            rows.push(((left < self.lens.0).then_some(left), (right < self.lens.1).then_some(right)));
+            left += 1;
+            right += 1;
+        }
+        rows
+    }
+
+    /// One document's side of `rows`
+    pub fn side(&self, doc_id: DocumentId) -> Vec<Option<usize>> {
+        let left = doc_id == self.left;
+        self.rows()
+            .into_iter()
+            .map(|(l, r)| if left { l } else { r })
+            .collect()
+    }
+
+    /// The hunk at a line of one of the documents. Lines missing on this
+    /// side belong to the line they would come before.
+    pub fn hunk_at(&self, doc_id: DocumentId, line: usize) -> Option<(Range<usize>, Range<usize>)> {
+        self.ranges(doc_id)
+            .find(|(this, _)| this.contains(&line) || (this.is_empty() && this.start == line))
+    }
+}
+
+/// Lines of a document without their endings, so a missing final newline
+/// only changes the last line
+fn lines(doc: &Document) -> Vec<String> {
+    (0..doc.len_lines())
+        .map(|line| {
+            let len = doc.rope.line_len_chars(line);
+            let start = doc.rope.line_to_char(line);
+            doc.rope.slice(start..start + len).to_string()
+        })
+        .collect()
+}
@@ -0,0 +1,190 @@
+//! State of the completion menu
+
+use crate::{DocumentId, ViewId};
+use lite_core::Change;
+use std::ops::Range;
+
+/// A replacement of a char range
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+/// A completion candidate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletionItem {
+    pub label: String,
+    /// Type or signature shown next to the label
+    pub detail: Option<String>,
+    /// Text matched against what has been typed
+    pub filter_text: String,
+    /// Edit inserting the item, relative to the cursor it was requested at
+    pub edit: TextEdit,
+    /// Other edits, like adding an import
+    pub additional_edits: Vec<TextEdit>,
+}
+
+/// An open completion menu
+#[derive(Debug, Clone)]
+pub struct Completion {
+    pub doc_id: DocumentId,
+    pub view_id: ViewId,
+    /// Cursor when completion was requested
+    pub trigger: usize,
+    /// Start of the word being completed
+    pub start: usize,
+    pub items: Vec<CompletionItem>,
+    /// Indices of the items matching the typed text, best first
+    pub matches: Vec<usize>,
+    /// Index into `matches` of the highlighted item
+    pub selected: usize,
+}
+
+impl Completion {
+    pub fn new(
+        doc_id: DocumentId,
+        view_id: ViewId,
+        trigger: usize,
+        start: usize,
+        items: Vec<CompletionItem>,
+    ) -> Self {
+        let matches = (0..items.len()).collect();
+        Self {
+            doc_id,
+            view_id,
+            trigger,
+            start,
+            items,
+            matches,
+            selected: 0,
+        }
+    }
+
+    /// Keep the items matching `typed`: prefix matches first, then items
+    /// containing its chars in order, both ignoring case
+    pub fn filter(&mut self, typed: &str) {
+        let typed = typed.to_lowercase();
+        let mut scored: Vec<(usize, usize)> = self
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(i, item)| {
+                let text = item.filter_text.to_lowercase();
+                if text.starts_with(&typed) {
+                    Some((0, i))
+                } else if is_subsequence(&typed, &text) {
+                    Some((1, i))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        scored.sort();
+        self.matches = scored.into_iter().map(|(_, i)| i).collect();
+        self.selected = 0;
+    }
+
+    /// The highlighted item
+    pub fn selected_item(&self) -> Option<&CompletionItem> {
+        self.matches.get(self.selected).map(|&i| &self.items[i])
+    }
+
+    /// Highlight the next or previous match, wrapping around
+    pub fn cycle(&mut self, forward: bool) {
+        let len = self.matches.len();
+        if len == 0 {
+            return;
+        }
+        self.selected = if forward {
+            (self.selected + 1) % len
+        } else {
+            (self.selected + len - 1) % len
+        };
+    }
+
+    /// Changes inserting `item` with the cursor now at `cursor`, sorted by
+    /// position, and the cursor position after applying them
+    pub fn changes(&self, item: &CompletionItem, cursor: usize) -> (Vec<Change>, usize) {
+        // Text typed since the request is replaced too, along with whatever
+        // the edit covered after the cursor
+        let after_trigger = item.edit.range.end.saturating_sub(self.trigger);
+        let main = Change::replace(
+            item.edit.range.start,
+            cursor.max(item.edit.range.start) + after_trigger,
+            item.edit.text.clone(),
+        );
+
+        let mut changes: Vec<Change> = item
+            .additional_edits
+            .iter()
+            .filter(|edit| edit.range.end <= main.start || edit.range.start >= main.end)
+            .map(|edit| Change::replace(edit.range.start, edit.range.end, edit.text.clone()))
+            .collect();
+        // Edits before the inserted text shift where the cursor lands
+        let shift: isize = changes
+            .iter()
+            .filter(|change| change.end <= main.start)
+            .map(|change| change.insert.chars().count() as isize - (change.end - change.start) as isize)
+            .sum();
+        let new_cursor = (main.start as isize + shift) as usize + main.insert.chars().count();
+
+        changes.push(main);
+        changes.sort_by_key(|change| change.start);
+        (changes, new_cursor)
+    }
+}
+
+fn is_subsequence(needle: &str, haystack: &str) -> bool {
+    let mut haystack = haystack.chars();
+    needle.chars().all(|c| haystack.any(|h| h == c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(label: &str, range: Range<usize>) -> CompletionItem {
+        CompletionItem {
+            label: label.to_string(),
+            detail: None,
+            filter_text: label.to_string(),
+            edit: TextEdit {
+                range,
+                text: label.to_string(),
+            },
+            additional_edits: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_filter() {
+        let items = vec![item("into_iter", 4..6), item("iter", 4..6), item("map", 4..6)];
+        let mut completion = Completion::new(DocumentId::next(), ViewId::next(), 6, 4, items);
+
+        completion.filter("it");
+        assert_eq!(completion.selected_item().unwrap().label, "iter");
+        completion.cycle(true);
+        assert_eq!(completion.selected_item().unwrap().label, "into_iter");
+
+        completion.filter("MP");
+        assert_eq!(completion.matches, vec![2]);
+    }
+
+    #[test]
+    fn test_changes_cover_typed_text() {
+        let mut item = item("iter", 4..6);
+        item.additional_edits.push(TextEdit {
+            range: 0..0,
+            text: "use x;\n".to_string(),
+        });
+        let completion = Completion::new(DocumentId::next(), ViewId::next(), 6, 4, vec![item.clone()]);
+
+        // Typed one more char after requesting
+        let (changes, cursor) = completion.changes(&item, 7);
+        assert_eq!(cursor, 15);
+        assert_eq!(changes.len(), 2);
+        assert_eq!((changes[0].start, changes[0].end), (0, 0));
+        assert_eq!((changes[1].start, changes[1].end), (4, 7));
+    }
+}
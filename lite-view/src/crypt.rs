@@ -0,0 +1,81 @@
+//! Running encryption filters over file contents
+
+use std::io::Error;
+use std::path::Path;
+#[cfg(feature = "fs")]
+use {
+    std::io::{Read, Write},
+    std::process::{Command, Stdio},
+};
+
+/// Pass `input` through a shell command and return what it prints.
+///
+/// Fails if the command exits unsuccessfully, with its error output as the
+/// message.
+pub fn run_filter(command: &str, input: &[u8]) -> std::io::Result<Vec<u8>> {
+    run_filter_in(command, &[], input, None)
+}
+
+/// `run_filter` with the command run in a directory rather than the
+/// working directory. `args` are the command's `$1`, `$2` and so on, so
+/// paths reach it without being parsed by the shell.
+#[cfg(feature = "fs")]
+pub fn run_filter_in(command: &str, args: &[&str], input: &[u8], dir: Option<&Path>) -> std::io::Result<Vec<u8>> {
+    let mut command_line = Command::new("sh");
+    command_line.arg("-c").arg(command).arg("sh").args(args);
+    if let Some(dir) = dir {
+        command_line.current_dir(dir);
+    }
+    let mut child = command_line
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Feed stdin from another thread so a command writing before it has read
+    // everything can't fill its output pipe and block us both
+    let mut stdin = child.stdin.take().ok_or_else(|| Error::other("no stdin"))?;
+    let input = input.to_vec();
+    let writer = std::thread::spawn(move || stdin.write_all(&input));
+
+    let mut output = Vec::new();
+    if let Some(mut stdout) = child.stdout.take() {
+        stdout.read_to_end(&mut output)?;
+    }
+    let mut errors = String::new();
+    if let Some(mut stderr) = child.stderr.take() {
+        stderr.read_to_string(&mut errors)?;
+    }
+    let status = child.wait()?;
+    // A command may exit without reading all its input; its status decides
+    let _ = writer.join();
+
+    if !status.success() {
+        let program = command.split_whitespace().next().unwrap_or(command);
+        let message = match errors.lines().find(|line| !line.trim().is_empty()) {
+            Some(line) => format!("{} failed: {}", program, line.trim()),
+            None => format!("{} failed ({})", program, status),
+        };
+        return Err(Error::other(message));
+    }
+    Ok(output)
+}
+
+/// Builds without files run no commands either
+#[cfg(not(feature = "fs"))]
+pub fn run_filter_in(_command: &str, _args: &[&str], _input: &[u8], _dir: Option<&Path>) -> std::io::Result<Vec<u8>> {
+    Err(Error::new(std::io::ErrorKind::Unsupported, "no commands in this build"))
+}
+
+#[cfg(all(test, feature = "fs"))]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_filter() {
+        assert_eq!(run_filter("tr a-z A-Z", b"secret").unwrap(), b"SECRET");
+        let error = run_filter("echo 'bad key' >&2; exit 2", b"").unwrap_err();
+        assert_eq!(error.to_string(), "echo failed: bad key");
+    }
+}
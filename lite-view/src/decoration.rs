@@ -0,0 +1,190 @@
+//! Decorations: styles laid over ranges of a document's text.
+//!
+//! Search matches, the occurrences of a symbol and the lines compared
+//! buffers differ on are worked out when they change, not as the text is
+//! drawn. Each feature sets its decorations under its own source name,
+//! either for one view or for every view of the document. Ranges are chars
+//! and move with edits like annotations do.
+
+use crate::ViewId;
+use lite_core::ChangeSet;
+use std::ops::Range;
+
+/// How a decorated range is drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecorationStyle {
+    /// A match of the search query
+    SearchMatch,
+    /// Another place the symbol under the cursor is used
+    Reference,
+    /// Line that differs between compared buffers
+    DiffLine,
+    /// Line only the right compared buffer has
+    DiffLineAdded,
+    /// Line only the left compared buffer has
+    DiffLineRemoved,
+}
+
+/// A style over a range of text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decoration {
+    pub range: Range<usize>,
+    pub style: DecorationStyle,
+    /// The feature that set it, such as `"search"` or `"diff"`
+    pub source: &'static str,
+    /// The view it shows in, or `None` for all of them
+    pub view: Option<ViewId>,
+}
+
+impl Decoration {
+    pub fn new(source: &'static str, range: Range<usize>, style: DecorationStyle) -> Self {
+        Self {
+            range,
+            style,
+            source,
+            view: None,
+        }
+    }
+
+    /// Show it only in one view
+    pub fn in_view(mut self, view: ViewId) -> Self {
+        self.view = Some(view);
+        self
+    }
+
+    fn shows_in(&self, view: ViewId) -> bool {
+        self.view.is_none_or(|shown| shown == view)
+    }
+}
+
+/// Decorations of a document, sorted by where they start
+#[derive(Debug, Clone, Default)]
+pub struct Decorations {
+    decorations: Vec<Decoration>,
+    /// Length of the longest range, so those overlapping a line are found
+    /// by binary search from both ends
+    longest: usize,
+}
+
+impl Decorations {
+    pub fn is_empty(&self) -> bool {
+        self.decorations.is_empty()
+    }
+
+    pub fn add(&mut self, decoration: Decoration) {
+        if decoration.range.is_empty() {
+            return;
+        }
+        self.longest = self.longest.max(decoration.range.len());
+        let i = self.decorations.partition_point(|other| other.range.start <= decoration.range.start);
+        self.decorations.insert(i, decoration);
+    }
+
+    /// Remove what a source set, in every view
+    pub fn clear(&mut self, source: &str) {
+        self.retain(|decoration| decoration.source != source);
+    }
+
+    /// Remove what a source set for one view
+    pub fn clear_in_view(&mut self, source: &str, view: ViewId) {
+        self.retain(|decoration| decoration.source != source || decoration.view != Some(view));
+    }
+
+    fn retain(&mut self, keep: impl FnMut(&Decoration) -> bool) {
+        self.decorations.retain(keep);
+        self.longest = self.decorations.iter().map(|decoration| decoration.range.len()).max().unwrap_or(0);
+    }
+
+    /// Replace what a source set. The new decorations replace those for the
+    /// same views, so a source can decorate views differently.
+    pub fn set(
+        &mut self,
+        source: &'static str,
+        view: Option<ViewId>,
+        decorations: impl IntoIterator<Item = Decoration>,
+    ) {
+        match view {
+            Some(view) => self.clear_in_view(source, view),
+            None => self.clear(source),
+        }
+        let decorations = decorations.into_iter().filter(|decoration| !decoration.range.is_empty());
+        for decoration in decorations {
+            self.longest = self.longest.max(decoration.range.len());
+            self.decorations.push(Decoration { source, view, ..decoration });
+        }
+        // Added at once, as a search sets thousands of matches
+        self.decorations.sort_by_key(|decoration| decoration.range.start);
+    }
+
+    /// Forget the decorations of a closed view
+    pub fn remove_view(&mut self, view: ViewId) {
+        self.retain(|decoration| decoration.view != Some(view));
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Decoration> {
+        self.decorations.iter()
+    }
+
+    /// Decorations shown in a view that overlap a range of chars, in order
+    pub fn overlapping(&self, view: ViewId, range: Range<usize>) -> impl Iterator<Item = &Decoration> {
+        let start = self.decorations.partition_point(|decoration| decoration.range.start + self.longest <= range.start);
+        let end = self.decorations.partition_point(|decoration| decoration.range.start < range.end);
+        self.decorations[start..end.max(start)]
+            .iter()
+            .filter(move |decoration| decoration.range.end > range.start && decoration.shows_in(view))
+    }
+
+    /// Move the decorations across an edit. Those whose text was deleted go.
+    pub fn map(&mut self, changes: &ChangeSet) {
+        for decoration in &mut self.decorations {
+            let start = changes.map_pos(decoration.range.start);
+            decoration.range = start..changes.map_pos(decoration.range.end).max(start);
+        }
+        self.retain(|decoration| !decoration.range.is_empty());
+        self.decorations.sort_by_key(|decoration| decoration.range.start);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lite_core::{Rope, Transaction};
+
+    fn ranges<'a>(decorations: impl Iterator<Item = &'a Decoration>) -> Vec<Range<usize>> {
+        decorations.map(|decoration| decoration.range.clone()).collect()
+    }
+
+    #[test]
+    fn test_decorations() {
+        let (left, right) = (ViewId::next(), ViewId::next());
+        let mut decorations = Decorations::default();
+        let matches = [0..3, 8..11].map(|range| Decoration::new("", range, DecorationStyle::SearchMatch));
+        decorations.set("search", Some(left), matches);
+        decorations.add(Decoration::new("diff", 4..12, DecorationStyle::DiffLine));
+        assert_eq!(ranges(decorations.overlapping(left, 2..9)), vec![0..3, 4..12, 8..11]);
+        assert_eq!(ranges(decorations.overlapping(right, 0..20)), vec![4..12]);
+
+        // Deleting a match drops it, and the rest move
+        let mut rope = Rope::from("foo bar foo\n");
+        let tx = Transaction::replace(rope.len_chars(), 0, 4, String::new());
+        tx.apply(&mut rope);
+        decorations.map(&tx.changes);
+        assert_eq!(ranges(decorations.iter()), vec![0..8, 4..7]);
+
+        decorations.set("search", Some(right), [Decoration::new("", 0..3, DecorationStyle::SearchMatch)]);
+        decorations.clear_in_view("search", left);
+        assert_eq!(ranges(decorations.overlapping(left, 0..8)), vec![0..8]);
+        decorations.remove_view(right);
+        decorations.clear("diff");
+        assert!(decorations.is_empty());
+
+        // A line finds those over it among many, including a long one from before
+        let matches = (0..1000).map(|i| Decoration::new("", i * 10..i * 10 + 3, DecorationStyle::SearchMatch));
+        decorations.set("search", None, matches);
+        decorations.add(Decoration::new("diff", 100..600, DecorationStyle::DiffLine));
+        assert_eq!(ranges(decorations.overlapping(left, 502..520)), vec![100..600, 500..503, 510..513]);
+        assert_eq!(ranges(decorations.overlapping(left, 604..609)), Vec::<Range<usize>>::new());
+        decorations.clear("diff");
+        assert_eq!(ranges(decorations.overlapping(left, 604..612)), vec![610..613]);
+    }
+}
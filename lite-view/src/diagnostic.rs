@@ -0,0 +1,39 @@
+//! Problems reported for ranges of a document
+
+use std::ops::Range;
+
+/// How serious a diagnostic is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DiagnosticSeverity {
+    Error,
+    Warning,
+    Info,
+    Hint,
+}
+
+/// A problem at a range of a document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Char range, kept in place as the document is edited
+    pub range: Range<usize>,
+    pub severity: DiagnosticSeverity,
+    pub message: String,
+    /// What reported it, e.g. "toml" or a language server name
+    pub source: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new(range: Range<usize>, severity: DiagnosticSeverity, message: impl Into<String>) -> Self {
+        Self {
+            range,
+            severity,
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    pub fn with_source(mut self, source: impl Into<String>) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+}
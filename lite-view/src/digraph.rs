@@ -0,0 +1,120 @@
+//! Digraphs: two characters typed after Ctrl+K that stand for one, such as
+//! `e:` for `ë` or `->` for `→`. The names follow RFC 1345, as in Vim, and
+//! either order works. More can be added under `[editor.digraphs]`.
+
+use std::collections::BTreeMap;
+
+/// Letters taking an accent, the character naming it, and the letters with it
+const ACCENTS: &[(&str, char, &str)] = &[
+    ("aeiouyAEIOU", ':', "äëïöüÿÄËÏÖÜ"),
+    ("aeiouyAEIOUY", '\'', "áéíóúýÁÉÍÓÚÝ"),
+    ("aeiouAEIOU", '!', "àèìòùÀÈÌÒÙ"),
+    ("aeiouAEIOU", '>', "âêîôûÂÊÎÔÛ"),
+    ("anoANO", '?', "ãñõÃÑÕ"),
+    ("cC", ',', "çÇ"),
+    ("aA", 'a', "åÅ"),
+];
+
+const SYMBOLS: &[(&str, char)] = &[
+    ("ss", 'ß'),
+    ("ae", 'æ'),
+    ("AE", 'Æ'),
+    ("o/", 'ø'),
+    ("O/", 'Ø'),
+    ("Eu", '€'),
+    ("Pd", '£'),
+    ("Ye", '¥'),
+    ("Ct", '¢'),
+    ("Co", '©'),
+    ("Rg", '®'),
+    ("TM", '™'),
+    ("SE", '§'),
+    ("PI", '¶'),
+    ("DG", '°'),
+    ("+-", '±'),
+    ("*X", '×'),
+    ("-:", '÷'),
+    ("!=", '≠'),
+    ("=<", '≤'),
+    (">=", '≥'),
+    ("?2", '≈'),
+    ("00", '∞'),
+    ("->", '→'),
+    ("<-", '←'),
+    ("-!", '↑'),
+    ("-v", '↓'),
+    ("=>", '⇒'),
+    ("<<", '«'),
+    (">>", '»'),
+    ("!I", '¡'),
+    ("?I", '¿'),
+    ("-N", '–'),
+    ("-M", '—'),
+    ("'6", '‘'),
+    ("'9", '’'),
+    ("\"6", '“'),
+    ("\"9", '”'),
+    (",.", '…'),
+    (".M", '·'),
+    ("NS", '\u{a0}'),
+    ("12", '½'),
+    ("14", '¼'),
+    ("34", '¾'),
+    ("1S", '¹'),
+    ("2S", '²'),
+    ("3S", '³'),
+    ("My", 'µ'),
+    ("a*", 'α'),
+    ("b*", 'β'),
+    ("g*", 'γ'),
+    ("d*", 'δ'),
+    ("e*", 'ε'),
+    ("l*", 'λ'),
+    ("m*", 'μ'),
+    ("p*", 'π'),
+    ("s*", 'σ'),
+    ("OK", '✓'),
+    ("XX", '✗'),
+];
+
+/// The character two typed ones stand for. `extra` is the configured
+/// table, which wins over the built-in one.
+pub fn lookup(extra: &BTreeMap<String, char>, first: char, second: char) -> Option<char> {
+    let find = |a: char, b: char| {
+        let name: String = [a, b].into_iter().collect();
+        if let Some(c) = extra.get(&name) {
+            return Some(*c);
+        }
+        if let Some((_, c)) = SYMBOLS.iter().find(|(symbol, _)| *symbol == name) {
+            return Some(*c);
+        }
+        ACCENTS.iter().find_map(|(letters, mark, accented)| {
+            let index = letters.chars().position(|letter| letter == a).filter(|_| b == *mark)?;
+            accented.chars().nth(index)
+        })
+    };
+    find(first, second).or_else(|| find(second, first))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup() {
+        let mut extra = BTreeMap::new();
+        assert_eq!(lookup(&extra, 'e', ':'), Some('ë'));
+        assert_eq!(lookup(&extra, ':', 'e'), Some('ë'));
+        assert_eq!(lookup(&extra, 'Y', '\''), Some('Ý'));
+        assert_eq!(lookup(&extra, 'n', '?'), Some('ñ'));
+        assert_eq!(lookup(&extra, '-', '>'), Some('→'));
+        assert_eq!(lookup(&extra, '>', '-'), Some('→'));
+        assert_eq!(lookup(&extra, 'Y', ':'), None);
+        assert_eq!(lookup(&extra, 'q', 'q'), None);
+
+        extra.insert("qq".to_string(), 'ℚ');
+        extra.insert("->".to_string(), '⟶');
+        assert_eq!(lookup(&extra, 'q', 'q'), Some('ℚ'));
+        assert_eq!(lookup(&extra, '-', '>'), Some('⟶'));
+    }
+}
@@ -1,8 +1,34 @@
+use crate::annotation::{Annotations, Placement};
+use crate::decoration::Decorations;
+use crate::diagnostic::Diagnostic;
+use crate::fold::Folds;
 use crate::history::History;
-use lite_core::{Range, Rope, Selection, Transaction};
-use std::collections::HashMap;
-use std::path::PathBuf;
+use crate::server_status::ServerStatus;
+use crate::table::{self, TableLayout};
+use crate::syntax::highlighter;
+use crate::{crypt, files, BufferKind, HighlightSpan};
+use lite_config::CryptFilter;
+use lite_core::{
+    visual_col, visual_width, ChangeSet, Hunk, Position, Range, Rope, RopeExt, RopeGraphemes, RopeSlice, Selection,
+    Transaction,
+};
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Maximum number of changesets kept for `Document::changes_since`
+const MAX_CHANGE_LOG: usize = 1000;
+
+/// Lines at least this many chars long keep column checkpoints, so drawing
+/// them scrolled to the right doesn't walk them from the start
+const CHECKPOINT_LINE_LEN: usize = 1024;
+
+/// Chars between two column checkpoints of a line
+const CHECKPOINT_INTERVAL: usize = 256;
+
+/// UTF-8 byte order mark
+const BOM: char = '\u{feff}';
 
 /// Unique identifier for documents
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -32,6 +58,13 @@ pub struct Document {
     pub path: Option<PathBuf>,
     /// Whether the document has been modified
     pub modified: bool,
+    /// File-backed or virtual
+    pub kind: BufferKind,
+    /// Whether edits are rejected
+    pub read_only: bool,
+    /// Chars at the end of the text that edits can't change, such as the
+    /// staged diff under a commit message
+    pub read_only_tail: usize,
     /// Selection per view
     selections: HashMap<crate::ViewId, Selection>,
     /// Undo/redo history
@@ -40,12 +73,73 @@ pub struct Document {
     pub line_ending: LineEnding,
     /// File encoding (currently only UTF-8)
     pub encoding: &'static str,
+    /// Whether the file starts with a byte order mark. It's kept out of the
+    /// text and written back on save.
+    pub bom: bool,
+    /// Whether the file on disk has the byte order mark, as of the last load
+    /// or save
+    saved_bom: bool,
+    /// Filter the file is decrypted and encrypted with; the plaintext only
+    /// ever lives in memory
+    pub crypt: Option<CryptFilter>,
     /// Language identifier (for syntax highlighting)
     pub language: Option<String>,
-    /// Last saved version (for tracking modifications)
-    last_saved_version: usize,
+    /// Highlights set by the embedding app, with the version they were set
+    /// at; drawn instead of the language's until the text changes
+    pub highlights: Option<(usize, Vec<HighlightSpan>)>,
+    /// Cell delimiter while shown as an aligned table
+    pub table_delimiter: Option<char>,
+    /// Column layout of the table and the version it was sized at; drawing
+    /// and moving the cursor need it many times between edits
+    table_cache: Mutex<Option<(usize, TableLayout)>>,
+    /// Column checkpoints of long lines, for the version and tab width they
+    /// were measured at
+    column_cache: Mutex<ColumnCache>,
+    /// The language's highlights and the version and language they were
+    /// found for; every frame draws them
+    syntax_cache: Mutex<Option<SyntaxCache>>,
+    /// Sections folded away
+    pub folds: Folds,
+    /// Text drawn with the document that isn't part of it
+    pub annotations: Annotations,
+    /// Styles laid over ranges of the text
+    pub decorations: Decorations,
+    /// Problems found in the text, sorted by position
+    pub diagnostics: Vec<Diagnostic>,
+    /// The language server handling the document, if one is running
+    pub server: Option<ServerStatus>,
+    /// Lines changed since the last commit; `before` ranges are lines of the
+    /// committed file, `after` of this document
+    pub git_hunks: Vec<Hunk>,
+    /// Lines changed since they were staged; `before` ranges are lines of
+    /// the index
+    pub git_unstaged: Vec<Hunk>,
+    /// Committed lines missing from this document, by the line they were
+    /// above
+    pub git_removed: Vec<(usize, Vec<String>)>,
+    /// Git status of the file as `git status --short` marks it, such as `M`
+    /// or `??`; `None` without uncommitted changes
+    pub git_status: Option<&'static str>,
     /// Current version counter
     version: usize,
+    /// Applied changesets, each tagged with the version it was applied to
+    change_log: VecDeque<(usize, ChangeSet)>,
+}
+
+/// A consumer's read position in a document's stream of changes.
+///
+/// Obtained from `Document::subscribe` and advanced by `Document::poll_changes`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChangeSubscription {
+    doc_id: DocumentId,
+    version: usize,
+}
+
+impl ChangeSubscription {
+    /// The document version this subscriber has caught up to
+    pub fn version(&self) -> usize {
+        self.version
+    }
 }
 
 /// Line ending style
@@ -73,6 +167,38 @@ impl LineEnding {
     }
 }
 
+/// Column checkpoints of long lines: chars into the line at grapheme
+/// boundaries, about `CHECKPOINT_INTERVAL` apart, and their display columns
+#[derive(Debug, Default)]
+struct ColumnCache {
+    version: usize,
+    tab_width: usize,
+    lines: HashMap<usize, Vec<(usize, usize)>>,
+}
+
+/// Highlights of a language for a version of the text
+#[derive(Debug)]
+struct SyntaxCache {
+    version: usize,
+    language: String,
+    spans: Arc<[HighlightSpan]>,
+}
+
+fn column_checkpoints(line: RopeSlice, tab_width: usize) -> Vec<(usize, usize)> {
+    let mut checkpoints = Vec::new();
+    let (mut chars, mut col) = (0, 0);
+    let mut next = CHECKPOINT_INTERVAL;
+    for grapheme in RopeGraphemes::new(line) {
+        if chars >= next {
+            checkpoints.push((chars, col));
+            next = chars + CHECKPOINT_INTERVAL;
+        }
+        col += visual_width(grapheme, col, tab_width);
+        chars += grapheme.chars().count();
+    }
+    checkpoints
+}
+
 impl Document {
     /// Create a new empty document
     pub fn new() -> Self {
@@ -81,13 +207,33 @@ impl Document {
             rope: Rope::new(),
             path: None,
             modified: false,
+            kind: BufferKind::File,
+            read_only: false,
+            read_only_tail: 0,
             selections: HashMap::new(),
             history: History::new(),
             line_ending: LineEnding::LF,
             encoding: "utf-8",
+            bom: false,
+            saved_bom: false,
+            crypt: None,
             language: None,
-            last_saved_version: 0,
+            table_delimiter: None,
+            table_cache: Mutex::new(None),
+            column_cache: Mutex::default(),
+            syntax_cache: Mutex::new(None),
+            folds: Folds::default(),
+            annotations: Annotations::default(),
+            decorations: Decorations::default(),
+            diagnostics: Vec::new(),
+            server: None,
+            git_hunks: Vec::new(),
+            git_unstaged: Vec::new(),
+            git_removed: Vec::new(),
+            git_status: None,
             version: 0,
+            highlights: None,
+            change_log: VecDeque::new(),
         }
     }
 
@@ -100,36 +246,130 @@ impl Document {
             rope: Rope::from(text),
             path: None,
             modified: false,
+            kind: BufferKind::File,
+            read_only: false,
+            read_only_tail: 0,
             selections: HashMap::new(),
             history: History::new(),
             line_ending,
             encoding: "utf-8",
+            bom: false,
+            saved_bom: false,
+            crypt: None,
             language: None,
-            last_saved_version: 0,
+            table_delimiter: None,
+            table_cache: Mutex::new(None),
+            column_cache: Mutex::default(),
+            syntax_cache: Mutex::new(None),
+            folds: Folds::default(),
+            annotations: Annotations::default(),
+            decorations: Decorations::default(),
+            diagnostics: Vec::new(),
+            server: None,
+            git_hunks: Vec::new(),
+            git_unstaged: Vec::new(),
+            git_removed: Vec::new(),
+            git_status: None,
             version: 0,
+            highlights: None,
+            change_log: VecDeque::new(),
         }
     }
 
     /// Open a document from file
     pub fn open(path: impl Into<PathBuf>) -> std::io::Result<Self> {
         let path = path.into();
-        let text = std::fs::read_to_string(&path)?;
-        let line_ending = LineEnding::detect(&text);
-        let language = detect_language(&path);
+        let text = files::read_to_string(&path)?;
+        Ok(Self::from_file(path, &text, None))
+    }
+
+    /// Open an encrypted file, decrypting it with `filter`
+    pub fn open_encrypted(path: impl Into<PathBuf>, filter: CryptFilter) -> std::io::Result<Self> {
+        let path = path.into();
+        let plaintext = crypt::run_filter(&filter.decrypt, &files::read(&path)?)?;
+        let text = String::from_utf8(plaintext)
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidData, "decrypted text is not UTF-8"))?;
+        Ok(Self::from_file(path, &text, Some(filter)))
+    }
 
-        Ok(Self {
+    /// Document for the contents of a file
+    fn from_file(path: PathBuf, text: &str, crypt: Option<CryptFilter>) -> Self {
+        let (text, bom) = match text.strip_prefix(BOM) {
+            Some(rest) => (rest, true),
+            None => (text, false),
+        };
+        let line_ending = LineEnding::detect(text);
+        // notes.md.gpg is markdown
+        let language = match crypt {
+            Some(_) => detect_language(&path.with_extension("")),
+            None => detect_language(&path),
+        };
+
+        Self {
             id: DocumentId::next(),
             rope: Rope::from(text),
             path: Some(path),
             modified: false,
+            kind: BufferKind::File,
+            read_only: false,
+            read_only_tail: 0,
             selections: HashMap::new(),
             history: History::new(),
             line_ending,
             encoding: "utf-8",
+            bom,
+            saved_bom: bom,
+            crypt,
+            table_delimiter: language.as_deref().and_then(table::delimiter_for),
+            table_cache: Mutex::new(None),
+            column_cache: Mutex::default(),
+            syntax_cache: Mutex::new(None),
+            folds: Folds::default(),
+            annotations: Annotations::default(),
+            decorations: Decorations::default(),
+            diagnostics: Vec::new(),
+            server: None,
+            git_hunks: Vec::new(),
+            git_unstaged: Vec::new(),
+            git_removed: Vec::new(),
+            git_status: None,
             language,
-            last_saved_version: 0,
             version: 0,
-        })
+            highlights: None,
+            change_log: VecDeque::new(),
+        }
+    }
+
+    /// Create a read-only virtual document (help, diagnostics list, ...)
+    pub fn new_virtual(name: impl Into<String>, text: impl AsRef<str>) -> Self {
+        let mut doc = Self::from_text(text);
+        doc.kind = BufferKind::Virtual(name.into());
+        doc.read_only = true;
+        doc
+    }
+
+    /// Check if this is a virtual document
+    pub fn is_virtual(&self) -> bool {
+        self.kind.is_virtual()
+    }
+
+    /// Replace the whole content, bypassing read-only and undo history.
+    ///
+    /// Used by the owners of virtual documents to refresh what they show.
+    pub fn set_text(&mut self, text: impl AsRef<str>) {
+        let text = text.as_ref();
+        let len = self.len_chars();
+        let tx = Transaction::replace(len, 0, len, text.to_string());
+        tx.apply(&mut self.rope);
+
+        let new_len = self.len_chars();
+        for selection in self.selections.values_mut() {
+            *selection = selection.transform(|range| {
+                Range::new(range.anchor.min(new_len), range.head.min(new_len))
+            });
+        }
+
+        self.record_change(tx.changes);
     }
 
     /// Save the document to its path
@@ -139,21 +379,105 @@ impl Document {
             .as_ref()
             .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "No path set"))?;
 
-        let text = self.text();
-        std::fs::write(path, text)?;
+        let mut text = self.text();
+        if self.bom {
+            text.insert(0, BOM);
+        }
+        match &self.crypt {
+            Some(filter) => files::write(path, crypt::run_filter(&filter.encrypt, text.as_bytes())?)?,
+            None => files::write(path, text)?,
+        }
 
         self.modified = false;
-        self.last_saved_version = self.version;
+        self.saved_bom = self.bom;
+        self.history.mark_saved();
         Ok(())
     }
 
+    /// Add or remove the byte order mark written on save
+    pub fn set_bom(&mut self, bom: bool) {
+        self.bom = bom;
+        self.modified = self.is_unsaved();
+    }
+
+    /// Whether the text or its byte order mark differ from the file's, as
+    /// of the last load or save
+    fn is_unsaved(&self) -> bool {
+        !self.history.is_saved() || self.bom != self.saved_bom
+    }
+
     /// Save the document to a new path
     pub fn save_as(&mut self, path: impl Into<PathBuf>) -> std::io::Result<()> {
-        self.path = Some(path.into());
-        self.language = self.path.as_ref().and_then(|p| detect_language(p));
+        let path = path.into();
+        self.language = match self.crypt {
+            Some(_) => detect_language(&path.with_extension("")),
+            None => detect_language(&path),
+        };
+        self.path = Some(path);
+        self.table_delimiter = self.language.as_deref().and_then(table::delimiter_for);
         self.save()
     }
 
+    /// Column layout while shown as a table, sized again only once the
+    /// text has changed
+    pub fn table_layout(&self) -> Option<TableLayout> {
+        let delimiter = self.table_delimiter?;
+        let mut cache = self.table_cache.lock().unwrap_or_else(|e| e.into_inner());
+        match &*cache {
+            Some((version, table)) if *version == self.version && table.delimiter == delimiter => Some(table.clone()),
+            _ => {
+                let table = TableLayout::new(&self.rope, delimiter);
+                *cache = Some((self.version, table.clone()));
+                Some(table)
+            }
+        }
+    }
+
+    /// Where to start walking a line to reach display column `col`: a char
+    /// on a grapheme boundary at or before it, and its display column.
+    /// Long lines start from the nearest checkpoint, others from 0.
+    pub fn seek_display_col(&self, line: usize, col: usize, tab_width: usize) -> (usize, usize) {
+        let text = self.rope.line(line);
+        if text.len_chars() < CHECKPOINT_LINE_LEN {
+            return (0, 0);
+        }
+        let mut cache = self.column_cache.lock().unwrap_or_else(|e| e.into_inner());
+        if (cache.version, cache.tab_width) != (self.version, tab_width) {
+            *cache = ColumnCache {
+                version: self.version,
+                tab_width,
+                lines: HashMap::new(),
+            };
+        }
+        let checkpoints = cache
+            .lines
+            .entry(line)
+            .or_insert_with(|| column_checkpoints(text, tab_width));
+        let i = checkpoints.partition_point(|&(_, checkpoint)| checkpoint <= col);
+        i.checked_sub(1).map_or((0, 0), |i| checkpoints[i])
+    }
+
+    /// Display column of a position: tabs reach the next tab stop and wide
+    /// graphemes take two columns, or cells are padded while shown as a table
+    pub fn display_col(&self, pos: Position, tab_width: usize) -> usize {
+        match self.table_layout() {
+            Some(table) => table.display_col(&table::line_text(&self.rope, pos.line), pos.col),
+            None => visual_col(self.rope.line(pos.line), pos.col, tab_width),
+        }
+    }
+
+    /// Rows shown above a line before its own: committed lines deleted
+    /// there when `show_deleted`, and virtual text placed above it
+    pub fn rows_above(&self, line: usize, show_deleted: bool) -> usize {
+        let deleted = self.git_removed.iter().filter(|(above, _)| show_deleted && *above == line);
+        let deleted = deleted.map(|(_, lines)| lines.len()).sum::<usize>();
+        let above = match line < self.len_lines() {
+            true => self.annotations.on_line(&self.rope, line).filter(|a| a.placement == Placement::Above).count(),
+            false => 0,
+        };
+        deleted + above
+    }
+
     /// Get the full text content
     pub fn text(&self) -> String {
         self.rope.to_string()
@@ -161,6 +485,9 @@ impl Document {
 
     /// Get the file name (or "untitled")
     pub fn name(&self) -> &str {
+        if let BufferKind::Virtual(name) = &self.kind {
+            return name;
+        }
         self.path
             .as_ref()
             .and_then(|p| p.file_name())
@@ -200,7 +527,7 @@ impl Document {
 
     /// Apply a transaction to the document
     pub fn apply(&mut self, tx: &Transaction, view_id: crate::ViewId) -> bool {
-        if tx.is_empty() {
+        if tx.is_empty() || self.read_only || !self.keeps_tail(tx) {
             return false;
         }
 
@@ -211,31 +538,41 @@ impl Document {
         // Apply changes
         tx.apply(&mut self.rope);
 
+        // Keep every view's selection pointing at the same text
+        self.map_selections(&tx.changes);
+
         // Update selection if provided
         if let Some(ref sel) = tx.selection {
             self.set_selection(view_id, sel.clone());
-        } else {
-            // Map existing selection through the changes
-            let sel = &self.selection(view_id);
-            let new_sel = sel.transform(|range| {
-                let anchor = tx.changes.map_pos(range.anchor);
-                let head = tx.changes.map_pos(range.head);
-                Range::new(anchor, head)
-            });
-            self.set_selection(view_id, new_sel);
         }
 
         // Push to history
         self.history.push(inverse);
 
-        self.version += 1;
-        self.modified = self.version != self.last_saved_version;
+        self.record_change(tx.changes.clone());
+        self.modified = self.is_unsaved();
 
         true
     }
 
+    /// Whether a transaction leaves the read-only tail as it is
+    fn keeps_tail(&self, tx: &Transaction) -> bool {
+        if self.read_only_tail == 0 {
+            return true;
+        }
+        let mut rope = self.rope.clone();
+        tx.apply(&mut rope);
+        let (len, old_len) = (rope.len_chars(), self.rope.len_chars());
+        len >= self.read_only_tail
+            && rope.slice(len - self.read_only_tail..)
+                == self.rope.slice(old_len.saturating_sub(self.read_only_tail)..)
+    }
+
     /// Undo the last change
     pub fn undo(&mut self, view_id: crate::ViewId) -> bool {
+        if self.read_only {
+            return false;
+        }
         if let Some(tx) = self.history.undo() {
             // Get inverse before applying
             let old_sel = self.selection(view_id);
@@ -243,6 +580,7 @@ impl Document {
 
             // Apply undo
             tx.apply(&mut self.rope);
+            self.map_selections(&tx.changes);
 
             // Restore selection
             if let Some(ref sel) = tx.selection {
@@ -252,8 +590,8 @@ impl Document {
             // Push to redo
             self.history.push_redo(inverse);
 
-            self.version += 1;
-            self.modified = self.version != self.last_saved_version;
+            self.record_change(tx.changes);
+            self.modified = self.is_unsaved();
             true
         } else {
             false
@@ -262,6 +600,9 @@ impl Document {
 
     /// Redo the last undone change
     pub fn redo(&mut self, view_id: crate::ViewId) -> bool {
+        if self.read_only {
+            return false;
+        }
         if let Some(tx) = self.history.redo() {
             // Get inverse before applying
             let old_sel = self.selection(view_id);
@@ -269,23 +610,183 @@ impl Document {
 
             // Apply redo
             tx.apply(&mut self.rope);
+            self.map_selections(&tx.changes);
 
             // Restore selection
             if let Some(ref sel) = tx.selection {
                 self.set_selection(view_id, sel.clone());
             }
 
-            // Push back to undo
-            self.history.push(inverse);
+            // Push back to undo, keeping what's left to redo
+            self.history.push_undo(inverse);
 
-            self.version += 1;
-            self.modified = self.version != self.last_saved_version;
+            self.record_change(tx.changes);
+            self.modified = self.is_unsaved();
             true
         } else {
             false
         }
     }
 
+    /// Get the current version (incremented on every change)
+    pub fn version(&self) -> usize {
+        self.version
+    }
+
+    /// Highlights set for the current text, if any
+    pub fn custom_highlights(&self) -> Option<&[HighlightSpan]> {
+        match &self.highlights {
+            Some((version, spans)) if *version == self.version => Some(spans),
+            _ => None,
+        }
+    }
+
+    /// Highlights of the current text: those set for it, else the
+    /// language's, found again only after the text or language changed
+    pub fn syntax_highlights(&self) -> Arc<[HighlightSpan]> {
+        if let Some(spans) = self.custom_highlights() {
+            return spans.into();
+        }
+        let Some(language) = &self.language else {
+            return Arc::new([]);
+        };
+        let mut cache = self.syntax_cache.lock().unwrap_or_else(|e| e.into_inner());
+        match &*cache {
+            Some(cached) if cached.version == self.version && cached.language == *language => cached.spans.clone(),
+            _ => {
+                let spans: Arc<[HighlightSpan]> = highlighter().highlight(language, &self.text()).into();
+                *cache = Some(SyntaxCache {
+                    version: self.version,
+                    language: language.clone(),
+                    spans: spans.clone(),
+                });
+                spans
+            }
+        }
+    }
+
+    /// Whether `syntax_highlights` has nothing left to find
+    pub fn highlights_ready(&self) -> bool {
+        let Some(language) = &self.language else {
+            return true;
+        };
+        let cache = self.syntax_cache.lock().unwrap_or_else(|e| e.into_inner());
+        self.custom_highlights().is_some()
+            || cache.as_ref().is_some_and(|cached| cached.version == self.version && cached.language == *language)
+    }
+
+    /// Get all changes made since `version`, composed into a single changeset.
+    ///
+    /// Returns `None` if `version` is newer than the document or too old to
+    /// still be in the change log; the caller should then resync from the full text.
+    pub fn changes_since(&self, version: usize) -> Option<ChangeSet> {
+        if version > self.version {
+            return None;
+        }
+        if version == self.version {
+            return Some(ChangeSet::identity(self.len_chars()));
+        }
+
+        let start = self.change_log.iter().position(|(v, _)| *v == version)?;
+        let mut entries = self.change_log.iter().skip(start).map(|(_, cs)| cs);
+        let first = entries.next()?.clone();
+        entries.try_fold(first, |acc, cs| acc.compose(cs))
+    }
+
+    /// Subscribe to changes starting from the current version
+    pub fn subscribe(&self) -> ChangeSubscription {
+        ChangeSubscription {
+            doc_id: self.id,
+            version: self.version,
+        }
+    }
+
+    /// Get the changes a subscriber hasn't seen yet and advance it to the current version.
+    ///
+    /// Returns `None` if the subscriber fell behind the change log (or belongs to
+    /// another document); it is still advanced, so the caller should resync once.
+    pub fn poll_changes(&self, subscription: &mut ChangeSubscription) -> Option<ChangeSet> {
+        let changes = if subscription.doc_id == self.id {
+            self.changes_since(subscription.version)
+        } else {
+            None
+        };
+        subscription.doc_id = self.id;
+        subscription.version = self.version;
+        changes
+    }
+
+    /// Map the selections of all views through an applied changeset
+    fn map_selections(&mut self, changes: &ChangeSet) {
+        for selection in self.selections.values_mut() {
+            *selection = selection.map(changes);
+        }
+    }
+
+    /// A transaction replacing whole lines with others, given without their
+    /// line endings. Lines past the last one are appended.
+    pub fn replace_lines(&self, range: std::ops::Range<usize>, lines: &[impl AsRef<str>]) -> Transaction {
+        let rope = &self.rope;
+        let eol = self.line_ending.as_str();
+        let line_end = |line: usize| rope.line_to_char(line) + rope.line_len_chars(line);
+        let (start, end, text) = if range.end < rope.len_lines() {
+            let text: String = lines.iter().map(|line| format!("{}{}", line.as_ref(), eol)).collect();
+            (rope.line_to_char(range.start), rope.line_to_char(range.end), text)
+        } else if range.start >= rope.len_lines() {
+            // Lines added after the last one
+            let text: String = lines.iter().map(|line| format!("{}{}", eol, line.as_ref())).collect();
+            (rope.len_chars(), rope.len_chars(), text)
+        } else if lines.is_empty() && range.start > 0 {
+            // Removing the last lines takes the line break before them too
+            (line_end(range.start - 1), rope.len_chars(), String::new())
+        } else {
+            let text = lines.iter().map(AsRef::as_ref).collect::<Vec<_>>().join(eol);
+            (rope.line_to_char(range.start), rope.len_chars(), text)
+        };
+        Transaction::replace(rope.len_chars(), start, end, text)
+    }
+
+    /// Drop what was kept for text the document no longer has: highlights,
+    /// table layouts and column checkpoints of older versions. Then release
+    /// the memory the history and change log no longer use.
+    pub fn compact(&mut self) {
+        let version = self.version;
+        if self.highlights.as_ref().is_some_and(|(set_at, _)| *set_at != version) {
+            self.highlights = None;
+        }
+        let syntax_cache = self.syntax_cache.get_mut().unwrap_or_else(|e| e.into_inner());
+        if syntax_cache.as_ref().is_some_and(|cached| cached.version != version) {
+            *syntax_cache = None;
+        }
+        let table_cache = self.table_cache.get_mut().unwrap_or_else(|e| e.into_inner());
+        if table_cache.as_ref().is_some_and(|(sized_at, _)| *sized_at != version) {
+            *table_cache = None;
+        }
+        let column_cache = self.column_cache.get_mut().unwrap_or_else(|e| e.into_inner());
+        if column_cache.version != version {
+            *column_cache = ColumnCache::default();
+        }
+        self.history.shrink_to_fit();
+        self.change_log.shrink_to_fit();
+    }
+
+    /// Record an applied changeset and bump the version
+    fn record_change(&mut self, changes: ChangeSet) {
+        self.folds.map(&changes, &self.rope);
+        self.annotations.map(&changes);
+        self.decorations.map(&changes);
+        for diagnostic in &mut self.diagnostics {
+            let start = changes.map_pos(diagnostic.range.start);
+            let end = changes.map_pos(diagnostic.range.end).max(start);
+            diagnostic.range = start..end;
+        }
+        self.change_log.push_back((self.version, changes));
+        if self.change_log.len() > MAX_CHANGE_LOG {
+            self.change_log.pop_front();
+        }
+        self.version += 1;
+    }
+
     /// Get line count
     pub fn len_lines(&self) -> usize {
         self.rope.len_lines()
@@ -301,9 +802,10 @@ impl Document {
         self.rope.len_chars() == 0
     }
 
-    /// Remove view's selection when view is closed
+    /// Remove view's selection and decorations when view is closed
     pub fn remove_view(&mut self, view_id: crate::ViewId) {
         self.selections.remove(&view_id);
+        self.decorations.remove_view(view_id);
     }
 }
 
@@ -314,7 +816,16 @@ impl Default for Document {
 }
 
 /// Detect language from file extension
-fn detect_language(path: &PathBuf) -> Option<String> {
+pub(crate) fn detect_language(path: &Path) -> Option<String> {
+    // Files git opens in `core.editor`
+    match path.file_name()?.to_str()? {
+        "COMMIT_EDITMSG" | "MERGE_MSG" | "TAG_EDITMSG" | "SQUASH_MSG" => {
+            return Some("git-commit".to_string())
+        }
+        "git-rebase-todo" => return Some("git-rebase".to_string()),
+        _ => {}
+    }
+
     let ext = path.extension()?.to_str()?;
     let lang = match ext {
         "rs" => "rust",
@@ -347,10 +858,13 @@ fn detect_language(path: &PathBuf) -> Option<String> {
         "scss" | "sass" => "scss",
         "less" => "less",
         "json" => "json",
+        "csv" => "csv",
+        "tsv" | "tab" => "tsv",
         "yaml" | "yml" => "yaml",
         "toml" => "toml",
         "xml" => "xml",
         "md" | "markdown" => "markdown",
+        "diff" | "patch" => "diff",
         "tex" => "latex",
         "vim" => "vim",
         "dockerfile" | "Dockerfile" => "dockerfile",
@@ -359,3 +873,220 @@ fn detect_language(path: &PathBuf) -> Option<String> {
     };
     Some(lang.to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changes_since() {
+        let mut doc = Document::from_text("hello world");
+        let view_id = crate::ViewId::next();
+        let base = doc.version();
+        let original = doc.rope.clone();
+
+        doc.apply(&Transaction::insert(11, 5, " beautiful"), view_id);
+        doc.apply(&Transaction::replace(21, 16, 21, "rust"), view_id);
+        assert_eq!(doc.version(), base + 2);
+
+        let changes = doc.changes_since(base).unwrap();
+        let mut rope = original;
+        changes.apply(&mut rope);
+        assert_eq!(rope, doc.rope);
+
+        assert!(doc.changes_since(doc.version()).unwrap().is_empty());
+        assert!(doc.changes_since(doc.version() + 1).is_none());
+    }
+
+    #[test]
+    fn test_table_layout_follows_edits() {
+        let mut doc = Document::from_text("a,bb\nccc,d\n");
+        let view_id = crate::ViewId::next();
+        doc.table_delimiter = Some(',');
+        assert_eq!(doc.table_layout().unwrap().widths, vec![3, 2]);
+        assert_eq!(doc.table_layout().unwrap().widths, vec![3, 2]);
+
+        doc.apply(&Transaction::insert(doc.len_chars(), 0, "eeee,"), view_id);
+        assert_eq!(doc.table_layout().unwrap().widths, vec![4, 1, 2]);
+        doc.table_delimiter = Some('\t');
+        assert_eq!(doc.table_layout().unwrap().widths, vec![9]);
+    }
+
+    #[test]
+    fn test_syntax_highlights_are_kept_until_an_edit() {
+        let mut doc = Document::from_text("x = 1\n");
+        let view_id = crate::ViewId::next();
+        assert!(doc.highlights_ready());
+        doc.language = Some("python".to_string());
+        assert!(!doc.highlights_ready());
+        let spans = doc.syntax_highlights();
+        assert!(doc.highlights_ready());
+        assert!(Arc::ptr_eq(&spans, &doc.syntax_highlights()));
+
+        doc.apply(&Transaction::insert(doc.len_chars(), 0, "y = 2\n"), view_id);
+        assert!(!doc.highlights_ready());
+        // Compacting drops what was found for the old text
+        doc.compact();
+        assert!(doc.syntax_cache.lock().unwrap().is_none());
+        doc.syntax_highlights();
+        doc.compact();
+        assert!(doc.highlights_ready());
+    }
+
+    #[test]
+    fn test_seek_display_col() {
+        let line = format!("{}\n", "\t中a".repeat(1000));
+        let mut doc = Document::from_text(format!("short\n{}", line));
+        assert_eq!(doc.seek_display_col(0, 3, 4), (0, 0));
+
+        let (chars, col) = doc.seek_display_col(1, 3000, 4);
+        assert!(col <= 3000 && 3000 - col < CHECKPOINT_INTERVAL * 4);
+        assert_eq!(col, visual_col(doc.rope.line(1), chars, 4));
+
+        // Checkpoints are measured again for another tab width or text
+        let (chars, col) = doc.seek_display_col(1, 3000, 8);
+        assert_eq!(col, visual_col(doc.rope.line(1), chars, 8));
+        let view_id = crate::ViewId::next();
+        doc.apply(&Transaction::insert(doc.len_chars(), 6, "中"), view_id);
+        let (chars, col) = doc.seek_display_col(1, 3000, 8);
+        assert_eq!(col, visual_col(doc.rope.line(1), chars, 8));
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_bom_is_hidden_and_restored() {
+        let path = std::env::temp_dir().join(format!("lite-bom-{}.txt", std::process::id()));
+        std::fs::write(&path, "\u{feff}hello\n").unwrap();
+
+        let mut doc = Document::open(&path).unwrap();
+        assert!(doc.bom);
+        assert_eq!(doc.text(), "hello\n");
+        doc.save().unwrap();
+        let with_bom = std::fs::read(&path).unwrap();
+
+        doc.set_bom(false);
+        assert!(doc.modified);
+        // Edits and their undo leave the byte order mark to save
+        let view_id = crate::ViewId::next();
+        doc.apply(&Transaction::insert(doc.len_chars(), 0, "x"), view_id);
+        doc.undo(view_id);
+        assert!(doc.modified);
+        doc.set_bom(true);
+        assert!(!doc.modified);
+        doc.set_bom(false);
+        doc.save().unwrap();
+        assert!(!doc.modified);
+        let without_bom = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(with_bom, b"\xef\xbb\xbfhello\n");
+        assert_eq!(without_bom, b"hello\n");
+    }
+
+    #[cfg(all(unix, feature = "fs"))]
+    #[test]
+    fn test_encrypted_file_roundtrip() {
+        let path = std::env::temp_dir().join(format!("lite-crypt-{}.md.b64", std::process::id()));
+        std::fs::write(&path, "aGVsbG8K").unwrap();
+        let filter = CryptFilter {
+            extensions: vec!["b64".to_string()],
+            decrypt: "base64 -d".to_string(),
+            encrypt: "base64".to_string(),
+        };
+
+        let mut doc = Document::open_encrypted(&path, filter).unwrap();
+        assert_eq!(doc.text(), "hello\n");
+        assert_eq!(doc.language.as_deref(), Some("markdown"));
+        doc.apply(&Transaction::insert(6, 5, " world"), crate::ViewId::next());
+        doc.save().unwrap();
+        let saved = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(saved.trim(), "aGVsbG8gd29ybGQK");
+    }
+
+    #[test]
+    fn test_virtual_document_is_read_only() {
+        let mut doc = Document::new_virtual("help", "some text");
+        let view_id = crate::ViewId::next();
+        assert_eq!(doc.name(), "help");
+        assert!(!doc.apply(&Transaction::insert(9, 0, "x"), view_id));
+
+        doc.set_selection(view_id, Selection::point(9));
+        doc.set_text("new");
+        assert_eq!(doc.text(), "new");
+        assert_eq!(doc.selection(view_id).cursor(), 3);
+        assert!(!doc.modified);
+
+        // Only the text before a read-only tail can change
+        let mut doc = Document::from_text("\n# diff");
+        doc.read_only_tail = 6;
+        assert!(doc.apply(&Transaction::insert(7, 0, "msg"), view_id));
+        assert!(doc.apply(&Transaction::insert(10, 3, "!"), view_id));
+        assert!(!doc.apply(&Transaction::insert(11, 7, "x"), view_id));
+        assert!(!doc.apply(&Transaction::delete(11, 4, 6), view_id));
+        assert_eq!(doc.text(), "msg!\n# diff");
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_undo_to_saved_state_is_unmodified() {
+        let path = std::env::temp_dir().join(format!("lite-saved-{}.txt", std::process::id()));
+        std::fs::write(&path, "a").unwrap();
+        let mut doc = Document::open(&path).unwrap();
+        let view_id = crate::ViewId::next();
+
+        doc.apply(&Transaction::insert(1, 1, "b"), view_id);
+        doc.apply(&Transaction::insert(2, 2, "c"), view_id);
+        doc.undo(view_id);
+        doc.save().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(!doc.modified);
+
+        doc.undo(view_id);
+        assert!(doc.modified);
+        doc.redo(view_id);
+        assert!(!doc.modified);
+        doc.redo(view_id);
+        assert!(doc.modified);
+        assert_eq!(doc.text(), "abc");
+        doc.undo(view_id);
+        assert!(!doc.modified);
+
+        // Going back and editing makes the saved state unreachable
+        doc.undo(view_id);
+        doc.apply(&Transaction::insert(1, 1, "x"), view_id);
+        doc.undo(view_id);
+        assert!(doc.modified);
+    }
+
+    #[test]
+    fn test_poll_changes() {
+        let mut doc = Document::from_text("abc");
+        let view_id = crate::ViewId::next();
+        let mut sub = doc.subscribe();
+
+        doc.apply(&Transaction::insert(3, 3, "d"), view_id);
+        let changes = doc.poll_changes(&mut sub).unwrap();
+        assert_eq!(changes.new_len(), 4);
+        assert_eq!(sub.version(), doc.version());
+
+        assert!(doc.poll_changes(&mut sub).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_apply_remaps_other_views() {
+        let mut doc = Document::from_text("hello beautiful world");
+        let editing = crate::ViewId::next();
+        let other = crate::ViewId::next();
+        doc.set_selection(editing, Selection::point(5));
+        doc.set_selection(other, Selection::single(Range::new(16, 21)));
+
+        doc.apply(&Transaction::delete(21, 5, 15), editing);
+        assert_eq!(doc.text(), "hello world");
+        assert_eq!(doc.selection(other), Selection::single(Range::new(6, 11)));
+
+        doc.undo(editing);
+        assert_eq!(doc.selection(other), Selection::single(Range::new(16, 21)));
+    }
+}
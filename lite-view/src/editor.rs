@@ -1,7 +1,37 @@
-use crate::{Document, DocumentId, Layout, Tree, View, ViewId};
-use lite_config::{Config, Keymap, Theme};
-use std::collections::HashMap;
-use std::path::PathBuf;
+use crate::{
+    fold, outline, todo, workspace, BufferDiff, BufferHandler, BufferKind, Completion, DebugCommand, Decoration,
+    DecorationStyle, Document, DocumentId, FileTree, FrameMetrics, Layout, Outline, Peek, Rect, SignatureHelp, Symbol,
+    Tree, View, ViewId,
+};
+use lite_config::{Bell, BufferOrder, Config, KeyEvent, Keymap, Theme};
+use lite_core::{Range, RopeExt, Selection, Transaction};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{mpsc, Arc};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
+
+/// Columns the outline panel takes from the views
+const OUTLINE_WIDTH: u16 = 32;
+
+/// Columns the file tree takes from the views
+const FILE_TREE_WIDTH: u16 = 30;
+
+/// How long a visual bell stays on screen
+const BELL_FLASH: Duration = Duration::from_millis(150);
+
+/// Inputs remembered per kind of prompt
+pub const MAX_PROMPT_HISTORY: usize = 100;
+
+/// What a background job does to the editor once it's done
+pub type JobCallback = Box<dyn FnOnce(&mut Editor) + Send>;
+
+/// Whether project scans leave a file or directory out, as `.gitignore`
+/// says; the frontend knows git, lite-view doesn't
+pub type IgnoreFilter = Arc<dyn Fn(&Path) -> bool + Send + Sync>;
 
 /// Message severity for status messages
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -11,14 +41,41 @@ pub enum Severity {
     Error,
 }
 
+/// Transient state of the most-recently-used buffer switcher (Ctrl+Tab)
+#[derive(Debug, Clone)]
+pub struct BufferSwitcher {
+    /// Buffers in MRU order, the current one first
+    pub candidates: Vec<DocumentId>,
+    /// Index of the highlighted candidate
+    pub selected: usize,
+}
+
+/// A layout page: splits of its own, kept while another page is shown, like
+/// vim's tab pages
+#[derive(Debug)]
+struct Page {
+    /// Name shown in the tabline, empty until the page is named
+    name: String,
+    /// The page's splits, `None` while it's the current page and they're
+    /// `Editor::tree`
+    tree: Option<Tree>,
+}
+
 /// Global editor state
 pub struct Editor {
     /// All open documents
     pub documents: HashMap<DocumentId, Document>,
     /// All views
     pub views: HashMap<ViewId, View>,
-    /// Layout tree
+    /// Layout tree of the current page
     pub tree: Tree,
+    /// Layout pages, in order
+    pages: Vec<Page>,
+    /// Index of the current page
+    page: usize,
+    /// Scroll position the views bound with `bind_scroll` share; each is
+    /// its offset from it
+    scroll_bind_top: isize,
     /// Editor configuration
     pub config: Config,
     /// Current theme
@@ -27,18 +84,87 @@ pub struct Editor {
     pub keymap: Keymap,
     /// Status message
     pub status_msg: Option<(String, Severity)>,
+    /// Newer release found by the update check, shown until dismissed
+    pub update_available: Option<String>,
+    /// Timings of the last frame with input, shown when `debug_metrics` is
+    /// `screen`
+    pub frame_metrics: Option<FrameMetrics>,
+    /// When an action last had nothing to do, for the `bell` option
+    pub bell: Option<Instant>,
+    /// Whether to draw less for a slow terminal, see `LowBandwidth`
+    pub low_bandwidth: bool,
     /// Whether the editor should quit
     pub should_quit: bool,
-    /// Command line mode (for :commands)
-    pub command_mode: bool,
-    /// Command line input
-    pub command_input: String,
+    /// `debug-record` or `debug-replay` command for the application
+    pub debug_command: Option<DebugCommand>,
+    /// Set on the scratch editor a recording is replayed into: a recording
+    /// holds placeholders for what was typed, which must not reach a file
+    pub sandboxed: bool,
     /// Search mode
     pub search_mode: bool,
     /// Search query
     pub search_query: String,
+    /// Whether the search only finds the query as a whole word, not inside
+    /// a longer one
+    pub search_whole_word: bool,
+    /// Views coloring the search matches, with the version of their
+    /// document the matches were found in
+    search_decorated: HashMap<ViewId, usize>,
     /// Clipboard content
     pub clipboard: String,
+    /// Text an input method is composing, drawn at the cursor until it's
+    /// committed
+    pub preedit: Option<String>,
+    /// The keys of a digraph typed so far after Ctrl+K, which are shown as
+    /// the preedit until the second one
+    pub digraph: Option<String>,
+    /// Key handlers for virtual buffers
+    buffer_handlers: HashMap<DocumentId, Box<dyn BufferHandler>>,
+    /// Documents in the order they were opened
+    buffer_order: Vec<DocumentId>,
+    /// Documents ordered by most recent use, most recent first
+    buffer_mru: Vec<DocumentId>,
+    /// Buffer switcher overlay, while it's open
+    pub buffer_switcher: Option<BufferSwitcher>,
+    /// Completion menu, while it's open
+    pub completion: Option<Completion>,
+    /// Open signature help popup
+    pub signature_help: Option<SignatureHelp>,
+    /// Open peek definition popup
+    pub peek: Option<Peek>,
+    /// Outline panel of the current document, while it's open
+    pub outline: Option<Outline>,
+    /// File tree sidebar, while it's open
+    pub file_tree: Option<FileTree>,
+    /// The file and the symbols around the cursor, outermost first
+    pub breadcrumbs: Vec<String>,
+    /// Symbols of a document at a version, for the breadcrumbs
+    symbols: Option<(DocumentId, usize, Vec<Symbol>)>,
+    /// Result of the last project scan for TODO comments, kept up to date
+    /// as files are saved
+    pub todos: Option<Vec<todo::Todo>>,
+    /// A running TODO scan
+    todo_scan: Option<mpsc::Receiver<Vec<todo::Todo>>>,
+    /// Files the TODO scan leaves out besides hidden and build ones
+    pub ignored_files: Option<IgnoreFilter>,
+    /// Jobs started by `spawn_job` still running
+    jobs: Vec<mpsc::Receiver<JobCallback>>,
+    /// Two documents being compared
+    pub diff: Option<BufferDiff>,
+    /// Project roots set by hand for some buffers
+    root_overrides: HashMap<DocumentId, PathBuf>,
+    /// Folders added to the session, as absolute paths
+    workspaces: Vec<PathBuf>,
+    /// Files opened, most recent first, as absolute paths
+    pub recent_files: Vec<PathBuf>,
+    /// Inputs given to prompts, most recent first, by kind of prompt such
+    /// as `search`
+    pub prompt_history: BTreeMap<String, Vec<String>>,
+    /// A glob given to `:e` or the Open prompt and the files it matched,
+    /// for the application to offer in a picker
+    pub glob_matches: Option<(String, Vec<PathBuf>)>,
+    /// Screen area shared by all views
+    area: Rect,
 }
 
 impl Editor {
@@ -61,16 +187,52 @@ impl Editor {
             documents,
             views,
             tree: Tree::new(view_id),
+            pages: vec![Page {
+                name: String::new(),
+                tree: None,
+            }],
+            page: 0,
+            scroll_bind_top: 0,
             config: Config::default(),
             theme: Theme::default(),
             keymap: Keymap::default(),
             status_msg: None,
+            update_available: None,
+            frame_metrics: None,
+            bell: None,
+            low_bandwidth: false,
             should_quit: false,
-            command_mode: false,
-            command_input: String::new(),
+            debug_command: None,
+            sandboxed: false,
             search_mode: false,
             search_query: String::new(),
+            search_whole_word: false,
+            search_decorated: HashMap::new(),
             clipboard: String::new(),
+            preedit: None,
+            digraph: None,
+            buffer_handlers: HashMap::new(),
+            buffer_order: vec![doc_id],
+            buffer_mru: vec![doc_id],
+            buffer_switcher: None,
+            completion: None,
+            signature_help: None,
+            peek: None,
+            outline: None,
+            file_tree: None,
+            breadcrumbs: Vec::new(),
+            symbols: None,
+            todos: None,
+            todo_scan: None,
+            ignored_files: None,
+            jobs: Vec::new(),
+            diff: None,
+            root_overrides: HashMap::new(),
+            recent_files: Vec::new(),
+            prompt_history: BTreeMap::new(),
+            glob_matches: None,
+            workspaces: Vec::new(),
+            area: Rect::new(0, 0, 80, 24),
         }
     }
 
@@ -109,51 +271,139 @@ impl Editor {
 
         if let Some(id) = existing_id {
             self.switch_to_document(id);
+            self.add_recent(&path);
             return Ok(id);
         }
 
-        // Open new document
-        let doc = Document::open(&path)?;
-        let doc_id = doc.id;
-        self.documents.insert(doc_id, doc);
-
-        // Create a view for it
-        let view = View::new(doc_id);
-        let view_id = view.id;
-        self.views.insert(view_id, view);
+        // Open new document, decrypting it if it's encrypted
+        let doc = match self.config.crypt_filter(&path) {
+            Some(filter) => Document::open_encrypted(&path, filter.clone())?,
+            None => Document::open(&path)?,
+        };
+        let doc_id = self.add_document(doc);
 
-        // Replace current view's document or add to tree
+        // Show it in the current view; the previous document stays open in the background
         let current_view = self.current_view_mut();
         let old_doc_id = current_view.doc_id;
-        current_view.doc_id = doc_id;
+        current_view.set_doc(doc_id);
+        self.touch_buffer(doc_id);
 
-        // Clean up old document if not used elsewhere
-        self.cleanup_document(old_doc_id);
+        // An untouched scratch buffer is just replaced
+        self.drop_if_pristine(old_doc_id);
 
+        self.add_recent(&path);
         self.set_status(format!("Opened: {}", path.display()), Severity::Info);
         Ok(doc_id)
     }
 
+    /// Put a file first among the recent files
+    fn add_recent(&mut self, path: &Path) {
+        let Ok(path) = std::path::absolute(path) else {
+            return;
+        };
+        self.recent_files.retain(|recent| *recent != path);
+        self.recent_files.insert(0, path);
+    }
+
+    /// Put an input first in the history of a kind of prompt
+    pub fn remember_input(&mut self, kind: &str, input: &str) {
+        if input.is_empty() {
+            return;
+        }
+        let history = self.prompt_history.entry(kind.to_string()).or_default();
+        history.retain(|earlier| earlier != input);
+        history.insert(0, input.to_string());
+        history.truncate(MAX_PROMPT_HISTORY);
+    }
+
     /// Create a new empty document
     pub fn new_document(&mut self) -> DocumentId {
-        let doc = Document::new();
-        let doc_id = doc.id;
-        self.documents.insert(doc_id, doc);
+        let doc_id = self.add_document(Document::new());
 
         // Update current view to show new document
         let view_id = self.tree.focus();
         if let Some(view) = self.views.get_mut(&view_id) {
-            let old_doc_id = view.doc_id;
-            view.doc_id = doc_id;
-            self.cleanup_document(old_doc_id);
+            view.set_doc(doc_id);
+            self.touch_buffer(doc_id);
         }
 
         doc_id
     }
 
+    /// Show a virtual document in the current view, with an optional key handler
+    pub fn open_virtual(
+        &mut self,
+        doc: Document,
+        handler: Option<Box<dyn BufferHandler>>,
+    ) -> DocumentId {
+        let doc_id = self.add_document(doc);
+        if let Some(handler) = handler {
+            self.buffer_handlers.insert(doc_id, handler);
+        }
+
+        let view_id = self.tree.focus();
+        if let Some(view) = self.views.get_mut(&view_id) {
+            view.set_doc(doc_id);
+            self.touch_buffer(doc_id);
+        }
+
+        doc_id
+    }
+
+    /// Find an open virtual document by its display name
+    pub fn find_virtual(&self, name: &str) -> Option<DocumentId> {
+        self.documents
+            .values()
+            .find(|doc| doc.kind == BufferKind::Virtual(name.to_string()))
+            .map(|doc| doc.id)
+    }
+
+    /// Check if the current buffer has a key handler
+    pub fn has_buffer_handler(&self) -> bool {
+        self.buffer_handlers.contains_key(&self.current_view().doc_id)
+    }
+
+    /// Let the current buffer's handler process a key. Returns `true` if consumed.
+    pub fn handle_buffer_key(&mut self, key: &KeyEvent) -> bool {
+        let doc_id = self.current_view().doc_id;
+        self.with_buffer_handler(doc_id, |handler, editor| handler.handle_key(editor, doc_id, key))
+    }
+
+    /// Run `f` with the handler of a buffer taken out of the editor, so it
+    /// can borrow the editor mutably. Returns `false` without a handler.
+    fn with_buffer_handler(
+        &mut self,
+        doc_id: DocumentId,
+        f: impl FnOnce(&mut dyn BufferHandler, &mut Editor) -> bool,
+    ) -> bool {
+        let Some(mut handler) = self.buffer_handlers.remove(&doc_id) else {
+            return false;
+        };
+
+        let result = f(handler.as_mut(), self);
+
+        // The handler may have closed its own buffer
+        if self.documents.contains_key(&doc_id) {
+            self.buffer_handlers.entry(doc_id).or_insert(handler);
+        }
+        result
+    }
+
     /// Save the current document
     pub fn save(&mut self) -> Result<(), std::io::Error> {
+        self.check_sandbox()?;
+        let doc_id = self.current_view().doc_id;
+        if self.with_buffer_handler(doc_id, |handler, editor| handler.save(editor, doc_id)) {
+            return Ok(());
+        }
+
         let doc = self.current_doc_mut();
+        if doc.is_virtual() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "Virtual buffers can't be saved",
+            ));
+        }
         if doc.path.is_none() {
             return Err(std::io::Error::new(
                 std::io::ErrorKind::NotFound,
@@ -163,15 +413,171 @@ impl Editor {
         doc.save()?;
         let name = doc.name().to_string();
         self.set_status(format!("Saved: {}", name), Severity::Info);
+        self.rescan_todos(doc_id);
         Ok(())
     }
 
+    /// Refuse to write files from a `sandboxed` editor
+    fn check_sandbox(&self) -> Result<(), std::io::Error> {
+        match self.sandboxed {
+            true => Err(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                "Nothing is saved during a replay",
+            )),
+            false => Ok(()),
+        }
+    }
+
+    /// Save a document for one of the `auto_save_on` triggers. Only files
+    /// with changes are saved; virtual buffers and those saving through a
+    /// handler, such as commit messages, are left alone.
+    pub fn auto_save(&mut self, doc_id: DocumentId) {
+        if self.sandboxed || self.buffer_handlers.contains_key(&doc_id) {
+            return;
+        }
+        let Some(doc) = self.documents.get_mut(&doc_id) else {
+            return;
+        };
+        if !doc.modified || doc.read_only || doc.is_virtual() || doc.path.is_none() {
+            return;
+        }
+        match doc.save() {
+            Ok(()) => self.rescan_todos(doc_id),
+            Err(e) => {
+                let message = format!("Error saving {}: {}", doc.name(), e);
+                self.set_status(message, Severity::Error);
+            }
+        }
+    }
+
+    /// Scan the workspaces, or the project of the working directory, for
+    /// TODO comments in the background. `poll_todos` picks up the result.
+    /// Builds without files have none to scan.
+    pub fn scan_todos(&mut self) {
+        if !cfg!(feature = "fs") {
+            return;
+        }
+        let roots = self.search_roots();
+        let keywords = self.config.editor.todo_keywords.clone();
+        let ignored = self.ignored_files.clone();
+
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let ignored = |path: &Path| ignored.as_ref().is_some_and(|ignored| ignored(path));
+            let _ = tx.send(todo::scan_dirs(&roots, &keywords, &ignored));
+        });
+        self.todo_scan = Some(rx);
+    }
+
+    /// Run `work` on another thread, and the callback it returns on the
+    /// editor once it's done, from `poll_jobs`
+    pub fn spawn_job(&mut self, work: impl FnOnce() -> JobCallback + Send + 'static) {
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = tx.send(work());
+        });
+        self.jobs.push(rx);
+    }
+
+    /// Run the callbacks of the jobs that are done. Returns `true` if any were.
+    pub fn poll_jobs(&mut self) -> bool {
+        let mut done = Vec::new();
+        self.jobs.retain(|job| match job.try_recv() {
+            Ok(callback) => {
+                done.push(callback);
+                false
+            }
+            Err(mpsc::TryRecvError::Empty) => true,
+            Err(mpsc::TryRecvError::Disconnected) => false,
+        });
+        let any = !done.is_empty();
+        for callback in done {
+            callback(self);
+        }
+        any
+    }
+
+    /// Wait for the running jobs, and those their callbacks start, for
+    /// scripts and tests that need the result before going on. Returns
+    /// `true` if there were any.
+    pub fn wait_jobs(&mut self) -> bool {
+        let mut any = false;
+        while !self.jobs.is_empty() {
+            for job in std::mem::take(&mut self.jobs) {
+                if let Ok(callback) = job.recv() {
+                    callback(self);
+                    any = true;
+                }
+            }
+        }
+        any
+    }
+
+    /// Folders project-wide searches look in: the workspaces, or the project
+    /// of the working directory
+    pub fn search_roots(&self) -> Vec<PathBuf> {
+        if self.workspaces.is_empty() {
+            let cwd = std::env::current_dir().unwrap_or_default();
+            vec![workspace::find_root(&cwd).unwrap_or(cwd)]
+        } else {
+            self.workspaces.clone()
+        }
+    }
+
+    /// Store the result of a finished TODO scan. Returns `true` if one finished.
+    pub fn poll_todos(&mut self) -> bool {
+        let Some(scan) = &self.todo_scan else {
+            return false;
+        };
+        match scan.try_recv() {
+            Ok(todos) => {
+                self.todos = Some(todos);
+                self.todo_scan = None;
+                true
+            }
+            Err(mpsc::TryRecvError::Empty) => false,
+            Err(mpsc::TryRecvError::Disconnected) => {
+                self.todo_scan = None;
+                false
+            }
+        }
+    }
+
+    /// Whether a TODO scan is running
+    pub fn scanning_todos(&self) -> bool {
+        self.todo_scan.is_some()
+    }
+
+    /// Replace the scanned TODOs of a saved document with its current ones
+    fn rescan_todos(&mut self, doc_id: DocumentId) {
+        let (Some(todos), Some(doc)) = (&mut self.todos, self.documents.get(&doc_id)) else {
+            return;
+        };
+        let Some(path) = doc.path.as_deref().map(todo::display_path) else {
+            return;
+        };
+        todos.retain(|todo| todo.path != path);
+        if self.ignored_files.as_ref().is_some_and(|ignored| ignored(&path)) {
+            return;
+        }
+        todos.extend(todo::scan_text(&path, &doc.text(), &self.config.editor.todo_keywords));
+        todos.sort_by(|a, b| (&a.path, a.line).cmp(&(&b.path, b.line)));
+    }
+
     /// Save the current document with a new path
     pub fn save_as(&mut self, path: impl Into<PathBuf>) -> Result<(), std::io::Error> {
+        self.check_sandbox()?;
         let path = path.into();
+        let filter = self.config.crypt_filter(&path).cloned();
         let doc = self.current_doc_mut();
+        // Encrypted text stays encrypted under any name
+        if filter.is_some() {
+            doc.crypt = filter;
+        }
         doc.save_as(&path)?;
+        let doc_id = doc.id;
         self.set_status(format!("Saved: {}", path.display()), Severity::Info);
+        self.rescan_todos(doc_id);
         Ok(())
     }
 
@@ -180,65 +586,840 @@ impl Editor {
         if self.documents.contains_key(&doc_id) {
             let view_id = self.tree.focus();
             if let Some(view) = self.views.get_mut(&view_id) {
-                view.doc_id = doc_id;
+                view.set_doc(doc_id);
+            }
+            self.touch_buffer(doc_id);
+        }
+    }
+
+    /// Register a new document
+    fn add_document(&mut self, doc: Document) -> DocumentId {
+        let doc_id = doc.id;
+        self.documents.insert(doc_id, doc);
+        self.buffer_order.push(doc_id);
+        doc_id
+    }
+
+    /// Move a document to the front of the MRU order
+    fn touch_buffer(&mut self, doc_id: DocumentId) {
+        // The front one is the buffer being left
+        if let Some(&previous) = self.buffer_mru.first() {
+            if previous != doc_id && self.config.editor.auto_save_on.buffer_switch {
+                self.auto_save(previous);
+            }
+        }
+        self.buffer_mru.retain(|id| *id != doc_id);
+        self.buffer_mru.insert(0, doc_id);
+    }
+
+    /// Get open documents in most-recently-used order
+    pub fn buffers_mru(&self) -> &[DocumentId] {
+        &self.buffer_mru
+    }
+
+    /// Open the buffer switcher, or move its highlight if it's already open
+    pub fn cycle_buffer_switcher(&mut self, forward: bool) {
+        if let Some(switcher) = &mut self.buffer_switcher {
+            let len = switcher.candidates.len();
+            switcher.selected = if forward {
+                (switcher.selected + 1) % len
+            } else {
+                (switcher.selected + len - 1) % len
+            };
+            return;
+        }
+
+        let candidates = self.buffer_mru.clone();
+        if candidates.len() < 2 {
+            return;
+        }
+        let selected = if forward { 1 } else { candidates.len() - 1 };
+        self.buffer_switcher = Some(BufferSwitcher {
+            candidates,
+            selected,
+        });
+    }
+
+    /// Close the buffer switcher and switch to the highlighted buffer
+    pub fn commit_buffer_switcher(&mut self) {
+        if let Some(switcher) = self.buffer_switcher.take() {
+            if let Some(&doc_id) = switcher.candidates.get(switcher.selected) {
+                self.switch_to_document(doc_id);
+            }
+        }
+    }
+
+    /// Close the buffer switcher without switching
+    pub fn cancel_buffer_switcher(&mut self) {
+        self.buffer_switcher = None;
+    }
+
+    /// Project root of a document: its override if one is set, else the
+    /// session folder containing it, else the project its file is in (by
+    /// its language's root markers first), else the working directory's
+    /// project, else the working directory itself
+    pub fn workspace_root(&self, doc_id: DocumentId) -> PathBuf {
+        if let Some(root) = self.root_overrides.get(&doc_id) {
+            return root.clone();
+        }
+        if let Some(workspace) = self.workspace_of(doc_id) {
+            return workspace.to_path_buf();
+        }
+        let cwd = std::env::current_dir().unwrap_or_default();
+        self.documents
+            .get(&doc_id)
+            .and_then(|doc| {
+                let path = doc.path.as_deref()?;
+                let markers = doc
+                    .language
+                    .as_deref()
+                    .and_then(|language| self.config.languages.get(language))
+                    .map_or(&[][..], |config| &config.roots[..]);
+                workspace::find_root_with(path, markers)
+            })
+            .or_else(|| workspace::find_root(&cwd))
+            .unwrap_or(cwd)
+    }
+
+    /// Folders added to the session
+    pub fn workspaces(&self) -> &[PathBuf] {
+        &self.workspaces
+    }
+
+    /// Add a folder to the session, returning its absolute path
+    pub fn add_workspace(&mut self, dir: &Path) -> Result<PathBuf, std::io::Error> {
+        let dir = std::path::absolute(dir)?;
+        if !dir.is_dir() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("not a directory: {}", dir.display()),
+            ));
+        }
+        if !self.workspaces.contains(&dir) {
+            self.workspaces.push(dir.clone());
+        }
+        Ok(dir)
+    }
+
+    /// Remove a folder from the session. Returns `false` if it wasn't added.
+    pub fn remove_workspace(&mut self, dir: &Path) -> bool {
+        let Ok(dir) = std::path::absolute(dir) else {
+            return false;
+        };
+        let len = self.workspaces.len();
+        self.workspaces.retain(|workspace| *workspace != dir);
+        self.workspaces.len() != len
+    }
+
+    /// The innermost session folder containing a document's file
+    pub fn workspace_of(&self, doc_id: DocumentId) -> Option<&Path> {
+        let path = self.documents.get(&doc_id)?.path.as_deref()?;
+        let path = std::path::absolute(path).ok()?;
+        self.workspaces
+            .iter()
+            .filter(|workspace| path.starts_with(workspace))
+            .max_by_key(|workspace| workspace.components().count())
+            .map(PathBuf::as_path)
+    }
+
+    /// Use `root` as a document's project root instead of detecting it
+    pub fn set_workspace_root(&mut self, doc_id: DocumentId, root: Option<PathBuf>) {
+        match root {
+            Some(root) => self.root_overrides.insert(doc_id, root),
+            None => self.root_overrides.remove(&doc_id),
+        };
+    }
+
+    /// Change the working directory. Paths of open documents are made
+    /// absolute first so they keep pointing at the same files.
+    pub fn change_dir(&mut self, dir: &Path) -> Result<(), std::io::Error> {
+        let dir = std::path::absolute(dir)?;
+        if !dir.is_dir() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("not a directory: {}", dir.display()),
+            ));
+        }
+        for doc in self.documents.values_mut() {
+            if let Some(path) = &mut doc.path {
+                if path.is_relative() {
+                    *path = std::path::absolute(&*path)?;
+                }
             }
         }
+        std::env::set_current_dir(&dir)
+    }
+
+    /// Refilter the completion menu after an edit or cursor move, closing it
+    /// once the cursor leaves the word being completed or nothing matches
+    pub fn update_completion(&mut self) {
+        let Some(completion) = &self.completion else {
+            return;
+        };
+        let view_id = self.tree.focus();
+        let doc = self.current_doc();
+        let cursor = doc.selection(view_id).primary().head;
+        let same_line = completion.start <= doc.rope.len_chars()
+            && doc.rope.char_to_line(completion.start) == doc.rope.char_to_line(cursor);
+        if completion.doc_id != doc.id
+            || completion.view_id != view_id
+            || cursor < completion.start
+            || !same_line
+        {
+            self.completion = None;
+            return;
+        }
+
+        let typed = doc.rope.slice(completion.start..cursor).to_string();
+        if let Some(completion) = &mut self.completion {
+            completion.filter(&typed);
+            if completion.matches.is_empty() {
+                self.completion = None;
+            }
+        }
+    }
+
+    /// Close the completion menu and insert the highlighted item
+    pub fn accept_completion(&mut self) {
+        let Some(completion) = self.completion.take() else {
+            return;
+        };
+        let Some(item) = completion.selected_item() else {
+            return;
+        };
+        let view_id = self.tree.focus();
+        let doc = self.current_doc_mut();
+        if completion.doc_id != doc.id {
+            return;
+        }
+
+        let cursor = doc.selection(view_id).primary().head;
+        let (changes, new_cursor) = completion.changes(item, cursor);
+        let tx = Transaction::changes(doc.rope.len_chars(), &changes)
+            .with_selection(Selection::point(new_cursor));
+        doc.apply(&tx, view_id);
+        self.ensure_cursor_visible();
+    }
+
+    /// Close the completion menu without inserting anything
+    pub fn cancel_completion(&mut self) {
+        self.completion = None;
+    }
+
+    /// Close signature help once the cursor leaves the call it was shown for
+    pub fn update_signature_help(&mut self) {
+        let Some(help) = &self.signature_help else {
+            return;
+        };
+        let view_id = self.tree.focus();
+        let doc = self.current_doc();
+        let cursor = doc.selection(view_id).primary().head;
+        let open = help.doc_id == doc.id
+            && help.view_id == view_id
+            && help.trigger <= cursor
+            && cursor <= doc.rope.len_chars()
+            && help.covers(&doc.rope.slice(help.trigger..cursor).to_string());
+        if !open {
+            self.signature_help = None;
+        }
+    }
+
+    /// Close the peek popup once the cursor moves away from where it was
+    /// asked for, or the document changes under it
+    pub fn update_peek(&mut self) {
+        let Some(peek) = &self.peek else {
+            return;
+        };
+        let view_id = self.tree.focus();
+        let doc = self.current_doc();
+        let cursor = doc.selection(view_id).primary().head;
+        if peek.doc_id != doc.id || peek.view_id != view_id || peek.trigger != cursor {
+            self.peek = None;
+        }
+    }
+
+    /// Compare two documents side by side: `left` in the current view and
+    /// `right` in a new split next to it
+    pub fn diff_buffers(&mut self, left: DocumentId, right: DocumentId) {
+        let (Some(left_doc), Some(right_doc)) = (self.documents.get(&left), self.documents.get(&right))
+        else {
+            return;
+        };
+        self.diff = Some(BufferDiff::new(left_doc, right_doc));
+        self.decorate_diff();
+
+        self.switch_to_document(left);
+        self.split(Layout::Vertical);
+        self.switch_to_document(right);
+    }
+
+    /// Recompute the compared documents' differences after edits, ending
+    /// the comparison once either is closed
+    pub fn update_diff(&mut self) {
+        let Some(diff) = &mut self.diff else {
+            return;
+        };
+        let changed = match (self.documents.get(&diff.left), self.documents.get(&diff.right)) {
+            (Some(left), Some(right)) => diff.update(left, right),
+            _ => {
+                self.diff = None;
+                true
+            }
+        };
+        if changed {
+            self.decorate_diff();
+        }
+    }
+
+    /// Stop comparing documents
+    pub fn stop_diff(&mut self) {
+        self.diff = None;
+        self.decorate_diff();
+    }
+
+    /// Color the lines the compared documents differ on: those only one side
+    /// has count as removed on the left and added on the right, those both
+    /// sides changed as modified
+    fn decorate_diff(&mut self) {
+        for doc in self.documents.values_mut() {
+            doc.decorations.clear("diff");
+        }
+        let Some(diff) = &self.diff else {
+            return;
+        };
+        for doc_id in [diff.left, diff.right] {
+            let Some(doc) = self.documents.get_mut(&doc_id) else {
+                continue;
+            };
+            let left = doc_id == diff.left;
+            let decorations: Vec<Decoration> = diff
+                .ranges(doc_id)
+                .map(|(this, other)| {
+                    let style = match (other.is_empty(), left) {
+                        (false, _) => DecorationStyle::DiffLine,
+                        (true, true) => DecorationStyle::DiffLineRemoved,
+                        (true, false) => DecorationStyle::DiffLineAdded,
+                    };
+                    let end = this.end.min(doc.len_lines());
+                    let chars = doc.rope.line_to_char(this.start.min(end))..doc.rope.line_to_char(end);
+                    Decoration::new("diff", chars, style)
+                })
+                .collect();
+            doc.decorations.set("diff", None, decorations);
+        }
+    }
+
+    /// Scroll the compared views together: the aligned row at their top
+    /// follows the focused one's scrolling and keeps its cursor in sight,
+    /// and each view starts at its first line from that row on
+    pub fn sync_diff_scroll(&mut self) {
+        let view = &self.views[&self.tree.focus()];
+        let Some(diff) = self.diff.as_mut().filter(|diff| diff.contains(view.doc_id)) else {
+            return;
+        };
+        let doc = &self.documents[&view.doc_id];
+        let side = diff.side(doc.id);
+        let first_line = |side: &[Option<usize>], top: usize| side.iter().skip(top).find_map(|line| *line);
+        let row_of = |line: usize| side.iter().position(|row| *row == Some(line)).unwrap_or(0);
+
+        // A view scrolled some other way, by moving the cursor say, moves the rows
+        let mut top = diff.top.min(side.len().saturating_sub(1));
+        if first_line(&side, top) != Some(view.scroll_y) {
+            top = row_of(view.scroll_y);
+        }
+        // Padding rows above the cursor can push it below the view
+        let cursor = row_of(doc.rope.char_to_line(doc.selection(view.id).cursor()));
+        let height = (view.height as usize).max(1);
+        top = top.min(cursor).max((cursor + 1).saturating_sub(height));
+        diff.top = top;
+
+        let sides = [(diff.left, diff.side(diff.left)), (diff.right, diff.side(diff.right))];
+        for view in self.views.values_mut() {
+            if let Some((_, side)) = sides.iter().find(|(doc_id, _)| *doc_id == view.doc_id) {
+                if let Some(line) = first_line(side, top) {
+                    view.scroll_y = line;
+                }
+            }
+        }
+    }
+
+    /// Make a view scroll with the other bound views, of any documents,
+    /// keeping the lines between them as they are now, or stop it
+    pub fn bind_scroll(&mut self, view_id: ViewId, bind: bool) {
+        let shown = self.tree.views();
+        let any_bound = shown.iter().any(|id| self.views.get(id).is_some_and(|view| view.scroll_bind.is_some()));
+        let Some(view) = self.views.get_mut(&view_id) else {
+            return;
+        };
+        match bind {
+            true if view.scroll_bind.is_some() => {}
+            true => {
+                if !any_bound {
+                    self.scroll_bind_top = view.scroll_y as isize;
+                }
+                view.scroll_bind = Some(view.scroll_y as isize - self.scroll_bind_top);
+            }
+            false => view.scroll_bind = None,
+        }
+    }
+
+    /// Split the current view to show the lines after it, the two bound to
+    /// scroll as one tall page, like Emacs' follow mode
+    pub fn follow(&mut self) {
+        let left = self.tree.focus();
+        self.split(Layout::Vertical);
+        let right = self.tree.focus();
+        let view = &self.views[&left];
+        let doc = &self.documents[&view.doc_id];
+        let below = (view.scroll_y + view.height as usize).min(doc.len_lines() - 1);
+        // Its cursor goes where it doesn't scroll the view
+        let scrolloff = self.config.editor.scrolloff.min(view.height.saturating_sub(1) as usize / 2);
+        let pos = doc.rope.line_to_char((below + scrolloff).min(doc.len_lines() - 1));
+        self.current_doc_mut().set_selection(right, Selection::point(pos));
+        self.current_view_mut().scroll_y = below;
+
+        self.bind_scroll(left, true);
+        self.bind_scroll(right, true);
+        self.tree.set_focus(left);
+    }
+
+    /// Scroll the bound views of the current page together: one scrolled
+    /// since the last call, the focused one first, moves the rest by as
+    /// much. Their cursors move along as far as it takes to stay in sight.
+    pub fn sync_scroll_bind(&mut self) {
+        let shown = self.tree.views();
+        let bound = |view: &View, top: isize| {
+            let offset = view.scroll_bind?;
+            let last = self.documents.get(&view.doc_id)?.len_lines() - 1;
+            Some((offset, (top + offset).clamp(0, last as isize) as usize))
+        };
+        let focus = self.tree.focus();
+        let moved = std::iter::once(focus)
+            .chain(shown.iter().copied())
+            .filter_map(|view_id| self.views.get(&view_id))
+            .find_map(|view| {
+                let (offset, scroll_y) = bound(view, self.scroll_bind_top)?;
+                (view.scroll_y != scroll_y).then_some((view.id, view.scroll_y as isize - offset))
+            });
+        let Some((moved, top)) = moved else {
+            return;
+        };
+        self.scroll_bind_top = top;
+        let scrolls: Vec<(ViewId, usize)> = shown
+            .iter()
+            .filter_map(|view_id| self.views.get(view_id))
+            .filter(|view| view.id != moved)
+            .filter_map(|view| Some((view.id, bound(view, top)?.1)))
+            .collect();
+        for (view_id, scroll_y) in scrolls {
+            let view = self.views.get_mut(&view_id).expect("Bound views exist");
+            view.scroll_y = scroll_y;
+            let height = view.height as usize;
+            let scrolloff = self.config.editor.scrolloff.min(height.saturating_sub(1) / 2);
+            let doc = self.documents.get_mut(&view.doc_id).expect("Bound views show a document");
+            let line = doc.rope.char_to_line(doc.selection(view_id).cursor());
+            let first = scroll_y + scrolloff;
+            let in_sight = line.clamp(first, (scroll_y + height).saturating_sub(scrolloff + 1).max(first));
+            let in_sight = in_sight.min(doc.len_lines() - 1);
+            if in_sight != line {
+                let pos = doc.rope.line_to_char(in_sight);
+                doc.set_selection(view_id, Selection::point(pos));
+            }
+        }
+    }
+
+    /// Copy the hunk under the cursor to the other compared document, or
+    /// with `push` false, replace it with the other document's version.
+    /// Returns `false` if the cursor isn't in a hunk.
+    pub fn transfer_hunk(&mut self, push: bool) -> bool {
+        let doc_id = self.current_view().doc_id;
+        let Some(diff) = self.diff.as_ref().filter(|diff| diff.contains(doc_id)) else {
+            return false;
+        };
+        let doc = self.current_doc();
+        let line = doc.rope.char_to_line(doc.selection(self.tree.focus()).cursor());
+        let Some((this, other)) = diff.hunk_at(doc_id, line) else {
+            return false;
+        };
+        let (source, source_lines, target, target_lines) = if push {
+            (doc_id, this, diff.other(doc_id), other)
+        } else {
+            (diff.other(doc_id), other, doc_id, this)
+        };
+
+        let source = &self.documents[&source];
+        let lines: Vec<String> = source_lines
+            .map(|line| {
+                let start = source.rope.line_to_char(line);
+                source.rope.slice(start..start + source.rope.line_len_chars(line)).to_string()
+            })
+            .collect();
+
+        let tx = self.documents[&target].replace_lines(target_lines, &lines);
+        let view_id = self
+            .views
+            .values()
+            .find(|view| view.doc_id == target)
+            .map_or(self.tree.focus(), |view| view.id);
+        if let Some(doc) = self.documents.get_mut(&target) {
+            doc.apply(&tx, view_id);
+        }
+        self.update_diff();
+        true
+    }
+
+    /// Move the cursor to the start of the next or previous hunk of a
+    /// compared document, or of the changes since the last commit when the
+    /// document isn't compared. Returns `false` if there is none that way.
+    pub fn goto_hunk(&mut self, forward: bool) -> bool {
+        let doc_id = self.current_view().doc_id;
+        let view_id = self.tree.focus();
+        let doc = self.current_doc();
+        let line = doc.rope.char_to_line(doc.selection(view_id).cursor());
+        let ranges: Vec<_> = match &self.diff {
+            Some(diff) if diff.contains(doc_id) => diff.ranges(doc_id).map(|(this, _)| this).collect(),
+            _ => doc.git_hunks.iter().map(|hunk| hunk.after.clone()).collect(),
+        };
+        let starts = ranges.iter().map(|this| this.start.min(doc.len_lines() - 1));
+        let target = if forward {
+            starts.filter(|start| *start > line).min()
+        } else {
+            starts.filter(|start| *start < line).max()
+        };
+        let Some(target) = target else {
+            return false;
+        };
+
+        let doc = self.current_doc_mut();
+        let pos = doc.rope.line_to_char(target);
+        doc.set_selection(view_id, Selection::point(pos));
+        self.ensure_cursor_visible();
+        true
+    }
+
+    /// Restore the lines of the git hunk under the cursor to their committed
+    /// version, given the committed text. Returns `false` if the cursor isn't
+    /// in a hunk.
+    pub fn revert_hunk(&mut self, base: &str) -> bool {
+        let view_id = self.tree.focus();
+        let doc = self.current_doc();
+        let line = doc.rope.char_to_line(doc.selection(view_id).cursor());
+        let Some(hunk) = doc.git_hunks.iter().find(|hunk| {
+            hunk.after.contains(&line) || (hunk.after.is_empty() && hunk.after.start == line)
+        }) else {
+            return false;
+        };
+        let lines: Vec<&str> = base.lines().skip(hunk.before.start).take(hunk.before.len()).collect();
+        let tx = doc.replace_lines(hunk.after.clone(), &lines);
+        let doc = self.current_doc_mut();
+        doc.apply(&tx, view_id);
+        // The next diff is computed from the new text; until then the hunk is gone
+        doc.git_hunks.clear();
+        doc.git_unstaged.clear();
+        doc.git_removed.clear();
+        true
+    }
+
+    /// Char positions where the search query starts in the current
+    /// document, ignoring case as the `search_case` option says and only
+    /// as a whole word if `search_whole_word` is set
+    pub fn search_matches(&self) -> Vec<usize> {
+        self.search_matches_in(self.current_doc())
+    }
+
+    fn search_matches_in(&self, doc: &Document) -> Vec<usize> {
+        if self.search_query.is_empty() {
+            return Vec::new();
+        }
+        let text = doc.text();
+        let mut matches = Vec::new();
+        if self.config.editor.search_case.ignores_case(&self.search_query) {
+            matches = matches_ignoring_case(&text, &self.search_query);
+        } else {
+            let (mut byte, mut char_idx) = (0, 0);
+            for (i, _) in text.match_indices(self.search_query.as_str()) {
+                char_idx += text[byte..i].chars().count();
+                byte = i;
+                matches.push(char_idx);
+            }
+        }
+
+        // Ends of the query that are word chars have to be ends of words
+        if self.search_whole_word {
+            let len = self.search_query.chars().count();
+            let query = lite_core::Rope::from(self.search_query.as_str());
+            let (first, last) = (query.is_word_char(0), query.is_word_char(len - 1));
+            matches.retain(|start| {
+                (!first || is_word_boundary(&doc.rope, *start)) && (!last || is_word_boundary(&doc.rope, start + len))
+            });
+        }
+        matches
+    }
+
+    /// Select the next match of the search query after the selection, or the
+    /// previous one before it, wrapping around the document. The status
+    /// tells which match it is, as in "match 4 of 23", and when the search
+    /// went past the end of the document.
+    pub fn find(&mut self, forward: bool) -> bool {
+        let primary = *self.current_doc().selection(self.tree.focus()).primary();
+        self.find_from(if forward { primary.end() } else { primary.start() }, forward)
+    }
+
+    /// Select the first match of the search query at or after `pos`, or the
+    /// last one before it, wrapping around the document
+    pub fn find_from(&mut self, pos: usize, forward: bool) -> bool {
+        if self.search_query.is_empty() {
+            self.set_status("Nothing to find", Severity::Info);
+            self.ring_bell();
+            return false;
+        }
+        let matches = self.search_matches();
+        self.decorate_search_matches(&matches);
+        if matches.is_empty() {
+            self.set_status("Not found", Severity::Error);
+            self.ring_bell();
+            return false;
+        }
+
+        let view_id = self.tree.focus();
+        let found = if forward {
+            matches.iter().position(|start| *start >= pos)
+        } else {
+            matches.iter().rposition(|start| *start < pos)
+        };
+        let index = found.unwrap_or(if forward { 0 } else { matches.len() - 1 });
+        let start = matches[index];
+        let end = start + self.search_query.chars().count();
+        self.current_doc_mut()
+            .set_selection(view_id, Selection::single(Range::new(start, end)));
+        self.ensure_cursor_visible();
+        let wrapped = match found {
+            Some(_) => "",
+            None if forward => ", wrapped to the top",
+            None => ", wrapped to the bottom",
+        };
+        self.set_status(format!("match {} of {}{}", index + 1, matches.len(), wrapped), Severity::Info);
+        true
     }
 
-    /// Split the current view
+    /// Color the matches of the search query in the current view
+    fn decorate_search_matches(&mut self, matches: &[usize]) {
+        let view_id = self.tree.focus();
+        self.decorate_search_matches_in(view_id, matches);
+    }
+
+    fn decorate_search_matches_in(&mut self, view_id: ViewId, matches: &[usize]) {
+        let len = self.search_query.chars().count();
+        let decorations = matches
+            .iter()
+            .map(|start| Decoration::new("search", *start..start + len, DecorationStyle::SearchMatch));
+        let doc = self.documents.get_mut(&self.views[&view_id].doc_id).expect("Views show a document");
+        doc.decorations.set("search", Some(view_id), decorations);
+        self.search_decorated.insert(view_id, doc.version());
+    }
+
+    /// Stop coloring the matches of the search query in the current view
+    pub fn clear_search_decorations(&mut self) {
+        let view_id = self.tree.focus();
+        self.current_doc_mut().decorations.clear_in_view("search", view_id);
+        self.search_decorated.remove(&view_id);
+    }
+
+    /// Find the search matches again in views whose document was edited
+    /// since they were colored. Until the user is `idle` the old ones just
+    /// move with the edits; searching a large file on every key is slow.
+    pub fn update_search_decorations(&mut self, idle: bool) {
+        if !idle {
+            return;
+        }
+        self.search_decorated.retain(|view_id, _| self.views.contains_key(view_id));
+        let stale: Vec<ViewId> = self
+            .search_decorated
+            .iter()
+            .filter(|(view_id, version)| self.documents[&self.views[view_id].doc_id].version() != **version)
+            .map(|(view_id, _)| *view_id)
+            .collect();
+        for view_id in stale {
+            let matches = self.search_matches_in(&self.documents[&self.views[&view_id].doc_id]);
+            self.decorate_search_matches_in(view_id, &matches);
+        }
+    }
+
+    /// Split the current view. The new view starts where the current one is.
     pub fn split(&mut self, layout: Layout) {
-        let current_doc_id = self.current_view().doc_id;
-        let new_view = View::new(current_doc_id);
+        let view = self.current_view();
+        let mut new_view = View::new(view.doc_id);
+        new_view.scroll_y = view.scroll_y;
+        new_view.scroll_x = view.scroll_x;
         let new_view_id = new_view.id;
+        let selection = self.current_doc().selection(view.id);
+
         self.views.insert(new_view_id, new_view);
+        self.current_doc_mut().set_selection(new_view_id, selection);
         self.tree.split(new_view_id, layout);
+        self.relayout();
     }
 
-    /// Close the current view
+    /// Close the current view. Closing the last view of a layout page closes
+    /// the page; that of the last page returns `false`.
     pub fn close_view(&mut self) -> bool {
         let view_id = self.tree.focus();
 
         if let Some(new_focus) = self.tree.close(view_id) {
-            let doc_id = self.views.remove(&view_id).map(|v| v.doc_id);
-
-            // Clean up document if needed
-            if let Some(doc_id) = doc_id {
-                self.cleanup_document(doc_id);
-            }
-
+            // Documents stay open; only forget the view's selections
+            self.forget_view(view_id);
             self.tree.set_focus(new_focus);
+            self.relayout();
             true
         } else {
-            // Last view - check if we should quit
-            false
+            // The last view of a page closes the page, and of the last page
+            // the caller may quit
+            self.close_page()
+        }
+    }
+
+    fn forget_view(&mut self, view_id: ViewId) {
+        self.views.remove(&view_id);
+        for doc in self.documents.values_mut() {
+            doc.remove_view(view_id);
         }
     }
 
-    /// Close the current buffer
-    pub fn close_buffer(&mut self) -> bool {
+    /// Names of the layout pages, in order. Pages not named are `""`.
+    pub fn pages(&self) -> impl Iterator<Item = &str> {
+        self.pages.iter().map(|page| page.name.as_str())
+    }
+
+    /// The layout pages as the tabline shows them: the number, from 1, and
+    /// the name if there's one
+    pub fn page_titles(&self) -> Vec<String> {
+        self.pages()
+            .enumerate()
+            .map(|(i, name)| match name {
+                "" => (i + 1).to_string(),
+                _ => format!("{}:{}", i + 1, name),
+            })
+            .collect()
+    }
+
+    /// Index of the current layout page
+    pub fn current_page(&self) -> usize {
+        self.page
+    }
+
+    /// Add a layout page after the current one and switch to it. It starts
+    /// with one view of the current buffer, where the current view is.
+    pub fn new_page(&mut self, name: &str) {
+        let view = self.current_view();
+        let mut new_view = View::new(view.doc_id);
+        new_view.scroll_y = view.scroll_y;
+        new_view.scroll_x = view.scroll_x;
+        let new_view_id = new_view.id;
+        let selection = self.current_doc().selection(view.id);
+
+        self.views.insert(new_view_id, new_view);
+        self.current_doc_mut().set_selection(new_view_id, selection);
+        let tree = std::mem::replace(&mut self.tree, Tree::new(new_view_id));
+        self.pages[self.page].tree = Some(tree);
+        self.page += 1;
+        self.pages.insert(
+            self.page,
+            Page {
+                name: name.to_string(),
+                tree: None,
+            },
+        );
+        self.relayout();
+    }
+
+    /// Show another layout page. Returns `false` if there's no such page.
+    pub fn switch_page(&mut self, index: usize) -> bool {
+        let Some(tree) = self.pages.get_mut(index).and_then(|page| page.tree.take()) else {
+            return index == self.page;
+        };
+        let tree = std::mem::replace(&mut self.tree, tree);
+        self.pages[self.page].tree = Some(tree);
+        self.page = index;
+        self.reset_scroll_bind();
+        self.relayout();
+        true
+    }
+
+    /// Take the shared scroll position from the bound views of the page now
+    /// shown, which scrolled apart from those of other pages
+    fn reset_scroll_bind(&mut self) {
+        let views = self.tree.views();
+        let bound = views.iter().filter_map(|view_id| self.views.get(view_id));
+        if let Some(top) = bound.filter_map(|view| Some(view.scroll_y as isize - view.scroll_bind?)).next() {
+            self.scroll_bind_top = top;
+        }
+    }
+
+    /// Show the next layout page, or the previous one, wrapping around
+    pub fn cycle_page(&mut self, forward: bool) {
+        let len = self.pages.len();
+        let index = match forward {
+            true => (self.page + 1) % len,
+            false => (self.page + len - 1) % len,
+        };
+        self.switch_page(index);
+    }
+
+    /// Name the current layout page
+    pub fn rename_page(&mut self, name: &str) {
+        self.pages[self.page].name = name.to_string();
+    }
+
+    /// Close the current layout page and its views, showing the one after
+    /// it. The last page stays, returning `false`.
+    pub fn close_page(&mut self) -> bool {
+        if self.pages.len() == 1 {
+            return false;
+        }
+        self.pages.remove(self.page);
+        self.page = self.page.min(self.pages.len() - 1);
+        let tree = self.pages[self.page].tree.take().expect("Other pages keep their tree");
+        for view_id in std::mem::replace(&mut self.tree, tree).views() {
+            self.forget_view(view_id);
+        }
+        self.reset_scroll_bind();
+        self.relayout();
+        true
+    }
+
+    /// Close the current buffer. See `close_document`.
+    pub fn close_buffer(&mut self, force: bool) -> bool {
         let doc_id = self.current_view().doc_id;
-        let doc = self.documents.get(&doc_id);
-
-        if let Some(doc) = doc {
-            if doc.modified {
-                self.set_status(
-                    "Buffer has unsaved changes. Use :q! to force quit.",
-                    Severity::Warning,
-                );
-                return false;
-            }
+        self.close_document(doc_id, force)
+    }
+
+    /// Close a document.
+    ///
+    /// Refuses to close a document with unsaved changes unless `force` is set.
+    /// Every view showing it switches to the most recently used other document.
+    pub fn close_document(&mut self, doc_id: DocumentId, force: bool) -> bool {
+        if !force && self.documents.get(&doc_id).is_some_and(|doc| doc.modified) {
+            self.set_status("Buffer has unsaved changes", Severity::Warning);
+            return false;
         }
 
-        // Find another document to switch to
+        // Switch to the most recently used other document
         let other_doc_id = self
-            .documents
-            .keys()
+            .buffer_mru
+            .iter()
             .find(|&&id| id != doc_id)
             .copied();
 
         if let Some(other_id) = other_doc_id {
-            self.switch_to_document(other_id);
-            self.documents.remove(&doc_id);
+            if self.current_view().doc_id == doc_id {
+                self.switch_to_document(other_id);
+            }
+            for view in self.views.values_mut() {
+                if view.doc_id == doc_id {
+                    view.set_doc(other_id);
+                }
+            }
+            self.remove_document(doc_id);
             true
         } else {
             // Last document - quit
@@ -247,11 +1428,26 @@ impl Editor {
         }
     }
 
-    /// Clean up a document if no views reference it
-    fn cleanup_document(&mut self, doc_id: DocumentId) {
-        let is_used = self.views.values().any(|v| v.doc_id == doc_id);
-        if !is_used {
-            self.documents.remove(&doc_id);
+    /// Drop an empty, unnamed, unmodified document that no view shows anymore
+    fn drop_if_pristine(&mut self, doc_id: DocumentId) {
+        let pristine = self.documents.get(&doc_id).is_some_and(|doc| {
+            doc.path.is_none() && !doc.is_virtual() && !doc.modified && doc.is_empty()
+        });
+        let is_shown = self.views.values().any(|v| v.doc_id == doc_id);
+        if pristine && !is_shown {
+            self.remove_document(doc_id);
+        }
+    }
+
+    /// Drop a document and everything tracked for it
+    fn remove_document(&mut self, doc_id: DocumentId) {
+        self.documents.remove(&doc_id);
+        self.buffer_handlers.remove(&doc_id);
+        self.root_overrides.remove(&doc_id);
+        self.buffer_order.retain(|id| *id != doc_id);
+        self.buffer_mru.retain(|id| *id != doc_id);
+        for view in self.views.values_mut() {
+            view.forget_doc(doc_id);
         }
     }
 
@@ -265,18 +1461,291 @@ impl Editor {
         self.status_msg = None;
     }
 
-    /// Get list of open buffers
+    /// Signal that an action had nothing to do, as the `bell` option asks
+    pub fn ring_bell(&mut self) {
+        if self.config.editor.bell != Bell::Off {
+            self.bell = Some(Instant::now());
+        }
+    }
+
+    /// Whether the flash of a visual `bell` is on screen
+    pub fn bell_flashing(&self, bell: Bell) -> bool {
+        self.config.editor.bell == bell && self.bell.is_some_and(|rung| rung.elapsed() < BELL_FLASH)
+    }
+
+    /// Get open documents in the configured buffer order
+    pub fn buffer_ids(&self) -> &[DocumentId] {
+        match self.config.editor.buffer_order {
+            BufferOrder::Insertion => &self.buffer_order,
+            BufferOrder::Mru => &self.buffer_mru,
+        }
+    }
+
+    /// Switch to the next open buffer, or the previous one, wrapping around.
+    /// They're taken in the order they were opened whatever the configured
+    /// order is, since in MRU order the current buffer is always first.
+    pub fn cycle_buffer(&mut self, forward: bool) {
+        let current = self.current_view().doc_id;
+        let Some(i) = self.buffer_order.iter().position(|id| *id == current) else {
+            return;
+        };
+        let len = self.buffer_order.len();
+        let i = if forward { (i + 1) % len } else { (i + len - 1) % len };
+        self.switch_to_document(self.buffer_order[i]);
+    }
+
+    /// Get list of open buffers, in the configured buffer order
     pub fn buffer_list(&self) -> Vec<(DocumentId, String)> {
-        self.documents
+        self.buffer_ids()
             .iter()
-            .map(|(id, doc)| (*id, doc.title()))
+            .filter_map(|id| self.documents.get(id).map(|doc| (*id, doc.title())))
             .collect()
     }
 
-    /// Resize the focused view
-    pub fn resize(&mut self, width: u16, height: u16) {
-        let view = self.current_view_mut();
-        view.set_size(width, height.saturating_sub(2)); // Reserve for status/tab lines
+    /// Resize the screen area shared by all views and lay the splits out again.
+    ///
+    /// Every view keeps its cursor visible in its new size.
+    pub fn resize(&mut self, area: Rect) {
+        self.area = area;
+        self.relayout();
+
+        for view in self.views.values_mut() {
+            if let Some(doc) = self.documents.get(&view.doc_id) {
+                let pos = doc.rope.char_to_position(doc.selection(view.id).cursor());
+                let col = doc.display_col(pos, self.config.editor.tab_width);
+                view.ensure_cursor_visible(pos.line, col, &self.config.editor);
+            }
+        }
+    }
+
+    /// Columns of the editor area the outline panel takes, on the right
+    pub fn outline_width(&self, width: u16) -> u16 {
+        match self.outline {
+            Some(_) => OUTLINE_WIDTH.min(width / 2),
+            None => 0,
+        }
+    }
+
+    /// Columns of the editor area the file tree takes, on the left
+    pub fn file_tree_width(&self, width: u16) -> u16 {
+        match self.file_tree {
+            Some(_) => FILE_TREE_WIDTH.min(width / 3),
+            None => 0,
+        }
+    }
+
+    /// Open the file tree of a directory with its focus, focus it if it's
+    /// open, or close it if it has the focus already. The caller lists the
+    /// entries.
+    pub fn toggle_file_tree(&mut self, root: PathBuf) {
+        match &self.file_tree {
+            Some(tree) if tree.focused => self.file_tree = None,
+            Some(_) => {}
+            None => self.file_tree = Some(FileTree::new(root)),
+        }
+        if let Some(tree) = &mut self.file_tree {
+            tree.focused = true;
+            if let Some(outline) = &mut self.outline {
+                outline.focused = false;
+            }
+        }
+        self.resize(self.area);
+    }
+
+    /// Open the outline panel with its focus, focus it if it's open, or
+    /// close it if it has the focus already
+    pub fn toggle_outline(&mut self) {
+        match &self.outline {
+            Some(outline) if outline.focused => self.outline = None,
+            Some(_) => {}
+            None => self.outline = Some(Outline::new(self.current_doc())),
+        }
+        let line = self.cursor_line();
+        if let Some(outline) = &mut self.outline {
+            outline.focused = true;
+            outline.selected = outline.symbol_at(line).unwrap_or(0);
+            if let Some(tree) = &mut self.file_tree {
+                tree.focused = false;
+            }
+        }
+        self.resize(self.area);
+    }
+
+    /// Follow edits and document switches in the outline panel
+    pub fn update_outline(&mut self) {
+        let doc_id = self.current_view().doc_id;
+        if let (Some(outline), Some(doc)) = (&mut self.outline, self.documents.get(&doc_id)) {
+            outline.update(doc);
+        }
+    }
+
+    /// Follow the cursor in the breadcrumbs. The symbols of an edited
+    /// document are only read again once the user is `idle`: that parses
+    /// the whole file, too slow to do on every key in a large one.
+    pub fn update_breadcrumbs(&mut self, idle: bool) {
+        if !self.config.editor.breadcrumbs {
+            self.breadcrumbs.clear();
+            return;
+        }
+        let doc = &self.documents[&self.views[&self.tree.focus()].doc_id];
+        let fresh = match &self.symbols {
+            Some((id, version, _)) if *id == doc.id => *version == doc.version() || !idle,
+            _ => false,
+        };
+        if !fresh {
+            let symbols = outline::symbols(doc.language.as_deref().unwrap_or_default(), &doc.text());
+            self.symbols = Some((doc.id, doc.version(), symbols));
+        }
+
+        // The file, relative to the working directory when it's inside
+        let mut crumbs: Vec<String> = match &doc.path {
+            Some(path) => {
+                let cwd = std::env::current_dir().unwrap_or_default();
+                let relative = path.strip_prefix(&cwd).unwrap_or(path);
+                match relative.is_relative() {
+                    true => relative.iter().map(|part| part.to_string_lossy().into_owned()).collect(),
+                    false => vec![doc.name().to_string()],
+                }
+            }
+            None => vec![doc.name().to_string()],
+        };
+        if let Some((_, _, symbols)) = &self.symbols {
+            let line = self.cursor_line();
+            crumbs.extend(
+                outline::path_at(symbols, line)
+                    .into_iter()
+                    .map(|symbol| format!("{} {}", symbol.kind, symbol.name)),
+            );
+        }
+        self.breadcrumbs = crumbs;
+    }
+
+    /// Move the outline panel's pick by `delta` rows
+    pub fn move_outline_selection(&mut self, delta: isize) {
+        if let Some(outline) = &mut self.outline {
+            let last = outline.symbols.len().saturating_sub(1);
+            outline.selected = outline.selected.saturating_add_signed(delta).min(last);
+        }
+    }
+
+    /// Move the cursor to the symbol picked in the outline panel and give
+    /// the focus back to the document
+    pub fn goto_outline_selection(&mut self) {
+        let Some(outline) = &mut self.outline else {
+            return;
+        };
+        outline.focused = false;
+        if let Some(line) = outline.symbols.get(outline.selected).map(|symbol| symbol.lines.start) {
+            self.goto_line(line);
+        }
+    }
+
+    /// Put the cursor on the first non-blank char of a line and scroll to it
+    pub fn goto_line(&mut self, line: usize) {
+        let view_id = self.tree.focus();
+        let doc = self.current_doc_mut();
+        let line = line.min(doc.len_lines() - 1);
+        let indent = doc.rope.line(line).chars().take_while(|c| *c == ' ' || *c == '\t').count();
+        let pos = doc.rope.line_to_char(line) + indent;
+        doc.set_selection(view_id, Selection::point(pos));
+        self.ensure_cursor_visible();
+    }
+
+    /// Line of the focused view's cursor
+    fn cursor_line(&self) -> usize {
+        let doc = self.current_doc();
+        doc.rope.char_to_line(doc.selection(self.tree.focus()).cursor())
+    }
+
+    /// Scroll the focused view so its cursor is visible
+    pub fn ensure_cursor_visible(&mut self) {
+        self.follow_changes();
+        let view_id = self.tree.focus();
+        let tab_width = self.config.editor.tab_width;
+        let doc = self.current_doc_mut();
+        let pos = doc.rope.char_to_position(doc.selection(view_id).cursor());
+        // A cursor moved into a fold, as by a search, opens it
+        while doc.folds.reveal(&doc.rope, pos.line) {}
+        let hidden = doc.folds.hidden(&doc.rope);
+        let col = doc.display_col(pos, tab_width);
+        let is_table = doc.table_delimiter.is_some();
+        let doc_id = doc.id;
+        let show_deleted = self.config.editor.show_deleted_lines && !self.low_bandwidth;
+        let doc = &self.documents[&doc_id];
+        if let Some(view) = self.views.get_mut(&view_id) {
+            let (scroll_y, shown) = (view.scroll_y, view.shown_scroll_y());
+            // Scrolling counts the rows shown, not the lines folded away
+            view.scroll_y = fold::row(&hidden, scroll_y);
+            view.ensure_cursor_visible(fold::row(&hidden, pos.line), col, &self.config.editor);
+            view.scroll_y = fold::line(&hidden, view.scroll_y);
+            // The pinned header row hides the first scrolled line
+            if is_table && pos.line > 0 && pos.line == view.scroll_y {
+                view.scroll_y -= 1;
+            }
+            // Deleted lines and virtual text shown above lines take rows too,
+            // so scroll further until the cursor line still fits
+            if !is_table {
+                let height = view.height as usize;
+                let scrolloff = self.config.editor.scrolloff.min(height.saturating_sub(1) / 2);
+                let bottom = (pos.line + scrolloff).min(doc.len_lines() - 1).max(pos.line);
+                let rows = |line: usize| match fold::is_hidden(&hidden, line) {
+                    true => 0,
+                    false => 1 + doc.rows_above(line, show_deleted),
+                };
+                let mut shown_rows: usize = (view.scroll_y..=bottom).map(rows).sum();
+                while shown_rows > height && view.scroll_y < pos.line {
+                    shown_rows -= rows(view.scroll_y);
+                    view.scroll_y += 1;
+                }
+            }
+            if self.config.editor.smooth_scroll && !self.low_bandwidth && view.scroll_y != scroll_y {
+                view.animate_scroll(shown);
+            }
+        }
+    }
+
+    /// Draw less for a slow terminal: no animations, link underlines,
+    /// deleted lines or spinners. Scrolls under way finish at once.
+    pub fn set_low_bandwidth(&mut self, on: bool) {
+        self.low_bandwidth = on;
+        if on {
+            while self.animate_scroll() {}
+        }
+    }
+
+    /// Show the next frame of animated scrolls. Returns whether any view is
+    /// still animating.
+    pub fn animate_scroll(&mut self) -> bool {
+        let mut animating = false;
+        for view in self.views.values_mut() {
+            animating |= view.advance_animation();
+        }
+        animating
+    }
+
+    /// Move every view along with edits above what it shows, see
+    /// `View::follow_changes`
+    pub fn follow_changes(&mut self) {
+        for view in self.views.values_mut() {
+            if let Some(doc) = self.documents.get(&view.doc_id) {
+                view.follow_changes(doc);
+            }
+        }
+    }
+
+    /// Give every view the area of its split
+    fn relayout(&mut self) {
+        let tree_width = self.file_tree_width(self.area.width);
+        let area = Rect {
+            x: self.area.x + tree_width,
+            width: self.area.width - tree_width - self.outline_width(self.area.width),
+            ..self.area
+        };
+        for (view_id, area) in self.tree.layout(area) {
+            if let Some(view) = self.views.get_mut(&view_id) {
+                view.set_area(area);
+            }
+        }
     }
 }
 
@@ -285,3 +1754,638 @@ impl Default for Editor {
         Self::new()
     }
 }
+
+/// Whether a char position is between a word char and another char, as
+/// `\b` in a regex
+fn is_word_boundary(rope: &lite_core::Rope, pos: usize) -> bool {
+    let before = pos > 0 && rope.is_word_char(pos - 1);
+    before != rope.is_word_char(pos)
+}
+
+/// Char positions where `query` starts in `text` with letters of either
+/// case. Letters are compared one for one, so a match is as long as the
+/// query.
+fn matches_ignoring_case(text: &str, query: &str) -> Vec<usize> {
+    let same = |a: char, b: char| a == b || a.to_lowercase().eq(b.to_lowercase());
+    let query: Vec<char> = query.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    (0..(text.len() + 1).saturating_sub(query.len()))
+        .filter(|start| query.iter().zip(&text[*start..]).all(|(a, b)| same(*a, *b)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::annotation::{Annotation, Placement};
+    use lite_config::SearchCase;
+    use lite_core::Transaction;
+
+    fn type_text(editor: &mut Editor, text: &str) {
+        let view_id = editor.tree.focus();
+        let doc = editor.current_doc_mut();
+        let tx = Transaction::insert(doc.len_chars(), 0, text.to_string());
+        doc.apply(&tx, view_id);
+    }
+
+    #[test]
+    fn test_documents_outlive_views() {
+        let mut editor = Editor::new();
+        let first = editor.current_view().doc_id;
+        type_text(&mut editor, "scratch");
+
+        let second = editor.new_document();
+        assert_eq!(editor.current_view().doc_id, second);
+        assert!(editor.documents.contains_key(&first));
+
+        let ids: Vec<_> = editor.buffer_list().into_iter().map(|(id, _)| id).collect();
+        assert_eq!(ids, vec![first, second]);
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_recent_files() {
+        let dir = std::env::temp_dir().join(format!("lite-recent-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (a, b) = (dir.join("a.txt"), dir.join("b.txt"));
+        std::fs::write(&a, "a").unwrap();
+        std::fs::write(&b, "b").unwrap();
+
+        let mut editor = Editor::new();
+        editor.open(&a).unwrap();
+        editor.open(&b).unwrap();
+        assert_eq!(editor.recent_files, vec![b.clone(), a.clone()]);
+        // Going back to an open file counts too
+        editor.open(&a).unwrap();
+        assert_eq!(editor.recent_files, vec![a, b]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_close_buffer_refuses_unsaved_changes() {
+        let mut editor = Editor::new();
+        let first = editor.current_view().doc_id;
+        editor.split(Layout::Vertical);
+        editor.new_document();
+        editor.switch_to_document(first);
+        type_text(&mut editor, "unsaved");
+
+        assert!(!editor.close_buffer(false));
+        assert!(editor.documents.contains_key(&first));
+
+        // Every view showing the closed document moves elsewhere
+        assert!(editor.close_buffer(true));
+        assert!(!editor.documents.contains_key(&first));
+        assert!(editor.views.values().all(|v| v.doc_id != first));
+    }
+
+    #[test]
+    fn test_buffer_switcher_mru() {
+        let mut editor = Editor::new();
+        let first = editor.current_view().doc_id;
+        let second = editor.new_document();
+        let third = editor.new_document();
+        assert_eq!(editor.buffers_mru(), &[third, second, first]);
+
+        editor.cycle_buffer_switcher(true);
+        editor.cycle_buffer_switcher(true);
+        editor.commit_buffer_switcher();
+        assert_eq!(editor.current_view().doc_id, first);
+        assert_eq!(editor.buffers_mru(), &[first, third, second]);
+
+        editor.config.editor.buffer_order = BufferOrder::Mru;
+        assert_eq!(editor.buffer_ids(), &[first, third, second]);
+    }
+
+    #[test]
+    fn test_cycle_buffer_in_mru_order() {
+        let mut editor = Editor::new();
+        editor.config.editor.buffer_order = BufferOrder::Mru;
+        let first = editor.current_view().doc_id;
+        let second = editor.new_document();
+        let third = editor.new_document();
+
+        // Each step goes on to another buffer rather than back
+        editor.cycle_buffer(true);
+        assert_eq!(editor.current_view().doc_id, first);
+        editor.cycle_buffer(true);
+        assert_eq!(editor.current_view().doc_id, second);
+        editor.cycle_buffer(true);
+        assert_eq!(editor.current_view().doc_id, third);
+        editor.cycle_buffer(false);
+        assert_eq!(editor.current_view().doc_id, second);
+    }
+
+    #[test]
+    fn test_accept_completion() {
+        let mut editor = Editor::new();
+        type_text(&mut editor, "x.it");
+        let view_id = editor.tree.focus();
+        editor.current_doc_mut().set_selection(view_id, Selection::point(4));
+
+        let item = crate::CompletionItem {
+            label: "iter".to_string(),
+            detail: None,
+            filter_text: "iter".to_string(),
+            edit: crate::TextEdit {
+                range: 2..4,
+                text: "iter()".to_string(),
+            },
+            additional_edits: Vec::new(),
+        };
+        let doc_id = editor.current_doc().id;
+        editor.completion = Some(Completion::new(doc_id, view_id, 4, 2, vec![item]));
+        editor.update_completion();
+        assert!(editor.completion.is_some());
+
+        editor.accept_completion();
+        assert_eq!(editor.current_doc().text(), "x.iter()");
+        assert_eq!(editor.current_doc().selection(view_id).primary().head, 8);
+        assert!(editor.completion.is_none());
+    }
+
+    #[test]
+    fn test_signature_help_closes_with_call() {
+        let mut editor = Editor::new();
+        type_text(&mut editor, "f(a)");
+        let view_id = editor.tree.focus();
+        editor.current_doc_mut().set_selection(view_id, Selection::point(3));
+        editor.signature_help = Some(SignatureHelp {
+            doc_id: editor.current_doc().id,
+            view_id,
+            trigger: 2,
+            signatures: Vec::new(),
+            active: 0,
+        });
+
+        editor.update_signature_help();
+        assert!(editor.signature_help.is_some());
+
+        editor.current_doc_mut().set_selection(view_id, Selection::point(4));
+        editor.update_signature_help();
+        assert!(editor.signature_help.is_none());
+    }
+
+    #[test]
+    fn test_find_cycles_through_matches() {
+        let mut editor = Editor::new();
+        type_text(&mut editor, "ä x ä x ä");
+        editor.search_query = "ä".to_string();
+        assert_eq!(editor.search_matches(), vec![0, 4, 8]);
+
+        let view_id = editor.tree.focus();
+        let selected = |editor: &Editor| *editor.current_doc().selection(view_id).primary();
+        editor.current_doc_mut().set_selection(view_id, Selection::point(2));
+        assert!(editor.find(true));
+        assert_eq!(selected(&editor), Range::new(4, 5));
+        assert_eq!(editor.status_msg.as_ref().unwrap().0, "match 2 of 3");
+        assert!(editor.find(true));
+        assert!(editor.find(true));
+        assert_eq!(selected(&editor), Range::new(0, 1));
+        assert_eq!(editor.status_msg.as_ref().unwrap().0, "match 1 of 3, wrapped to the top");
+
+        assert!(editor.find(false));
+        assert_eq!(selected(&editor), Range::new(8, 9));
+        assert_eq!(editor.status_msg.as_ref().unwrap().0, "match 3 of 3, wrapped to the bottom");
+        editor.search_query = "y".to_string();
+        assert!(!editor.find(true));
+    }
+
+    #[test]
+    fn test_search_case() {
+        let mut editor = Editor::new();
+        type_text(&mut editor, "Straße STRASSE straße");
+        editor.search_query = "straße".to_string();
+        assert_eq!(editor.search_matches(), vec![0, 15]);
+        editor.search_query = "Straße".to_string();
+        assert_eq!(editor.search_matches(), vec![0]);
+        editor.config.editor.search_case = SearchCase::Ignore;
+        assert_eq!(editor.search_matches(), vec![0, 15]);
+        editor.config.editor.search_case = SearchCase::Sensitive;
+        editor.search_query = "straße".to_string();
+        assert_eq!(editor.search_matches(), vec![15]);
+    }
+
+    #[test]
+    fn test_search_whole_word() {
+        let mut editor = Editor::new();
+        type_text(&mut editor, "id identifier id_2 (id) xid ID");
+        editor.search_query = "id".to_string();
+        assert_eq!(editor.search_matches().len(), 6);
+        editor.search_whole_word = true;
+        assert_eq!(editor.search_matches(), vec![0, 20, 28]);
+        // Only ends at word chars need a boundary
+        editor.search_query = "(id".to_string();
+        assert_eq!(editor.search_matches(), vec![19]);
+    }
+
+    #[test]
+    fn test_search_decorations() {
+        let mut editor = Editor::new();
+        type_text(&mut editor, "foo bar foo");
+        editor.search_query = "foo".to_string();
+        assert!(editor.find(true));
+        let view_id = editor.tree.focus();
+        let matches = |editor: &Editor| {
+            let decorations = editor.current_doc().decorations.overlapping(view_id, 0..20);
+            decorations.map(|decoration| decoration.range.clone()).collect::<Vec<_>>()
+        };
+        assert_eq!(matches(&editor), vec![0..3, 8..11]);
+
+        // Matches an edit makes are found once the user pauses
+        type_text(&mut editor, " foo");
+        editor.update_search_decorations(false);
+        assert_eq!(matches(&editor), vec![4..7, 12..15]);
+        editor.update_search_decorations(true);
+        assert_eq!(matches(&editor), vec![1..4, 4..7, 12..15]);
+        editor.clear_search_decorations();
+        type_text(&mut editor, " foo");
+        editor.update_search_decorations(true);
+        assert!(matches(&editor).is_empty());
+    }
+
+    #[test]
+    fn test_transfer_hunk() {
+        let mut editor = Editor::new();
+        let left = editor.add_document(Document::from_text("a\nb\nc"));
+        let right = editor.add_document(Document::from_text("a\nx\ny\nc\nd"));
+        editor.diff_buffers(left, right);
+        assert_eq!(editor.diff.as_ref().unwrap().hunks.len(), 2);
+        let decorations = |editor: &Editor, doc_id| {
+            let doc = &editor.documents[&doc_id];
+            doc.decorations.iter().map(|decoration| (decoration.range.clone(), decoration.style)).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            decorations(&editor, right),
+            vec![(2..6, DecorationStyle::DiffLine), (8..9, DecorationStyle::DiffLineAdded)]
+        );
+
+        // Pull the changed line into the right side, then push its extra
+        // last line to the left
+        let view_id = editor.tree.focus();
+        editor.current_doc_mut().set_selection(view_id, Selection::point(2));
+        assert!(editor.transfer_hunk(false));
+        assert_eq!(editor.documents[&right].text(), "a\nb\nc\nd");
+
+        assert!(editor.goto_hunk(true));
+        assert!(editor.transfer_hunk(true));
+        assert_eq!(editor.documents[&left].text(), "a\nb\nc\nd");
+        assert!(editor.diff.as_ref().unwrap().hunks.is_empty());
+        assert!(!editor.transfer_hunk(true));
+        assert!(decorations(&editor, left).is_empty() && decorations(&editor, right).is_empty());
+    }
+
+    #[test]
+    fn test_diff_rows_scroll_together() {
+        let mut editor = Editor::new();
+        editor.resize(Rect::new(0, 0, 80, 3));
+        let left = editor.add_document(Document::from_text("a\nb\nc"));
+        let right = editor.add_document(Document::from_text("a\nx\ny\nc\nd"));
+        editor.diff_buffers(left, right);
+        let diff = editor.diff.as_ref().unwrap();
+        assert_eq!(
+            diff.rows(),
+            vec![
+                (Some(0), Some(0)),
+                (Some(1), Some(1)),
+                (None, Some(2)),
+                (Some(2), Some(3)),
+                (None, Some(4)),
+            ]
+        );
+
+        // The cursor on the last right line scrolls both sides to the row of `y`
+        let view_id = editor.tree.focus();
+        editor.current_doc_mut().set_selection(view_id, Selection::point(8));
+        editor.sync_diff_scroll();
+        editor.sync_diff_scroll();
+        assert_eq!(editor.diff.as_ref().unwrap().top, 2);
+        let scroll = |doc_id| editor.views.values().find(|view| view.doc_id == doc_id).unwrap().scroll_y;
+        assert_eq!((scroll(left), scroll(right)), (2, 2));
+    }
+
+    #[test]
+    fn test_git_hunks() {
+        let mut editor = Editor::new();
+        let doc_id = editor.add_document(Document::from_text("a\nx\nc\nd\n"));
+        editor.switch_to_document(doc_id);
+        let base = "a\nb\nc\n";
+        editor.current_doc_mut().git_hunks = lite_core::diff(
+            &base.lines().collect::<Vec<_>>(),
+            &["a", "x", "c", "d"],
+        );
+
+        let view_id = editor.tree.focus();
+        let cursor_line = |editor: &Editor| {
+            let doc = editor.current_doc();
+            doc.rope.char_to_line(doc.selection(view_id).cursor())
+        };
+        assert!(editor.goto_hunk(true));
+        assert!(editor.goto_hunk(true));
+        assert_eq!(cursor_line(&editor), 3);
+        assert!(!editor.goto_hunk(true));
+        assert!(editor.goto_hunk(false));
+        assert_eq!(cursor_line(&editor), 1);
+
+        // Reverting is one undo step
+        assert!(editor.revert_hunk(base));
+        assert_eq!(editor.current_doc().text(), "a\nb\nc\nd\n");
+        assert!(!editor.revert_hunk(base));
+        editor.current_doc_mut().undo(view_id);
+        assert_eq!(editor.current_doc().text(), "a\nx\nc\nd\n");
+    }
+
+    #[test]
+    fn test_outline() {
+        let mut editor = Editor::new();
+        editor.resize(Rect::new(0, 1, 100, 20));
+        type_text(&mut editor, "# Intro\ntext\n## Usage\n  more\n");
+        editor.current_doc_mut().language = Some("markdown".to_string());
+        let view_id = editor.tree.focus();
+        editor.current_doc_mut().set_selection(view_id, Selection::point(20));
+
+        // Opens on the symbol around the cursor, next to the views
+        editor.toggle_outline();
+        let outline = editor.outline.as_ref().unwrap();
+        assert!(outline.focused);
+        assert_eq!(outline.selected, 1);
+        assert_eq!(editor.views[&view_id].area.width, 68);
+
+        editor.move_outline_selection(-5);
+        editor.goto_outline_selection();
+        assert_eq!(editor.current_doc().selection(view_id).cursor(), 0);
+        assert!(!editor.outline.as_ref().unwrap().focused);
+
+        // Edits show up, and toggling twice more closes the panel
+        type_text(&mut editor, "# Top\n");
+        editor.update_outline();
+        assert_eq!(editor.outline.as_ref().unwrap().symbols.len(), 3);
+        editor.toggle_outline();
+        editor.toggle_outline();
+        assert!(editor.outline.is_none());
+        assert_eq!(editor.views[&view_id].area.width, 100);
+    }
+
+    #[test]
+    fn test_file_tree() {
+        let mut editor = Editor::new();
+        editor.resize(Rect::new(0, 1, 100, 20));
+        let view_id = editor.tree.focus();
+
+        // The tree takes the left side, the outline the right
+        editor.toggle_file_tree(PathBuf::from("/p"));
+        assert!(editor.file_tree.as_ref().unwrap().focused);
+        editor.toggle_outline();
+        assert!(!editor.file_tree.as_ref().unwrap().focused);
+        assert_eq!(editor.views[&view_id].area, Rect::new(30, 1, 38, 20));
+
+        editor.toggle_file_tree(PathBuf::from("/p"));
+        editor.toggle_file_tree(PathBuf::from("/p"));
+        assert!(editor.file_tree.is_none());
+        assert_eq!(editor.views[&view_id].area.x, 0);
+    }
+
+    #[test]
+    fn test_breadcrumbs() {
+        let mut editor = Editor::new();
+        type_text(&mut editor, "# Guide\n## Setup\nrun it\n");
+        editor.current_doc_mut().language = Some("markdown".to_string());
+        editor.current_doc_mut().path = Some(std::env::current_dir().unwrap().join("docs/guide.md"));
+        let view_id = editor.tree.focus();
+        editor.current_doc_mut().set_selection(view_id, Selection::point(18));
+        editor.update_breadcrumbs(false);
+        assert_eq!(editor.breadcrumbs, vec!["docs", "guide.md", "# Guide", "## Setup"]);
+
+        // Edits show once the user is idle
+        let doc = editor.current_doc_mut();
+        doc.apply(&Transaction::insert(24, 24, "### Linux\n"), view_id);
+        doc.set_selection(view_id, Selection::point(24));
+        editor.update_breadcrumbs(false);
+        assert!(!editor.breadcrumbs.contains(&"### Linux".to_string()));
+        editor.update_breadcrumbs(true);
+        assert_eq!(editor.breadcrumbs, vec!["docs", "guide.md", "# Guide", "## Setup", "### Linux"]);
+
+        editor.config.editor.breadcrumbs = false;
+        editor.update_breadcrumbs(false);
+        assert!(editor.breadcrumbs.is_empty());
+    }
+
+    #[test]
+    fn test_workspace_of() {
+        let mut editor = Editor::new();
+        let outer = std::env::temp_dir();
+        let inner = outer.join(format!("lite-workspace-{}", std::process::id()));
+        std::fs::create_dir_all(&inner).unwrap();
+        editor.add_workspace(&outer).unwrap();
+        editor.add_workspace(&inner).unwrap();
+        std::fs::remove_dir_all(&inner).unwrap();
+
+        let doc_id = editor.current_doc().id;
+        assert_eq!(editor.workspace_of(doc_id), None);
+        editor.current_doc_mut().path = Some(inner.join("notes.txt"));
+        assert_eq!(editor.workspace_of(doc_id), Some(inner.as_path()));
+        assert_eq!(editor.workspace_root(doc_id), inner);
+
+        assert!(editor.remove_workspace(&inner));
+        assert_eq!(editor.workspace_of(doc_id), Some(outer.as_path()));
+    }
+
+    #[test]
+    fn test_pages() {
+        let mut editor = Editor::new();
+        editor.resize(Rect::new(0, 1, 80, 20));
+        let first = editor.tree.focus();
+        editor.split(Layout::Vertical);
+        let code = editor.tree.views();
+
+        editor.new_page("tests");
+        let tests = editor.tree.focus();
+        assert_eq!(editor.tree.views(), vec![tests]);
+        assert_eq!(editor.current_view().area.width, 80);
+        assert_eq!(editor.pages().collect::<Vec<_>>(), vec!["", "tests"]);
+        assert_eq!(editor.current_page(), 1);
+
+        // Each page keeps its splits and focus
+        editor.cycle_page(true);
+        assert_eq!(editor.tree.views(), code);
+        editor.tree.set_focus(first);
+        assert!(!editor.switch_page(2));
+        editor.rename_page("code");
+        editor.cycle_page(false);
+        assert_eq!(editor.tree.focus(), tests);
+        editor.cycle_page(false);
+        assert_eq!(editor.tree.focus(), first);
+
+        // Closing a page's last view closes the page and forgets its views
+        editor.switch_page(1);
+        assert!(editor.close_view());
+        assert!(!editor.views.contains_key(&tests));
+        assert_eq!(editor.pages().collect::<Vec<_>>(), vec!["code"]);
+        assert_eq!(editor.tree.views(), code);
+        assert!(!editor.close_page());
+    }
+
+    #[test]
+    fn test_scroll_counts_rows_above_lines() {
+        let mut editor = Editor::new();
+        editor.resize(Rect::new(0, 1, 80, 12));
+        type_text(&mut editor, &"line\n".repeat(40));
+        let height = editor.current_view().height as usize;
+        let doc = editor.current_doc_mut();
+        for line in 0..40 {
+            let pos = doc.rope.line_to_char(line);
+            doc.annotations.add(Annotation::new("code-lens", pos, Placement::Above, "Run"));
+        }
+        doc.git_removed.push((20, vec!["old".to_string(); 3]));
+
+        // Every line shows with the row above it, and the deleted lines too
+        editor.config.editor.scrolloff = 0;
+        editor.config.editor.show_deleted_lines = true;
+        let view_id = editor.tree.focus();
+        let pos = editor.current_doc().rope.line_to_char(20);
+        editor.current_doc_mut().set_selection(view_id, Selection::point(pos));
+        editor.ensure_cursor_visible();
+        let doc = editor.current_doc();
+        let scroll_y = editor.current_view().scroll_y;
+        let rows = (scroll_y..=20).map(|line| 1 + doc.rows_above(line, true)).sum::<usize>();
+        assert!(rows <= height, "{rows} rows from line {scroll_y} in {height}");
+        assert!(rows + 2 > height);
+    }
+
+    #[test]
+    fn test_scroll_bind() {
+        let mut editor = Editor::new();
+        editor.resize(Rect::new(0, 1, 80, 20));
+        type_text(&mut editor, &"line\n".repeat(100));
+        let first = editor.tree.focus();
+        editor.current_view_mut().scroll_y = 10;
+        editor.split(Layout::Vertical);
+        let second = editor.tree.focus();
+        editor.current_view_mut().scroll_y = 15;
+        editor.bind_scroll(first, true);
+        editor.bind_scroll(second, true);
+
+        // Either moves the other, keeping the lines between them
+        editor.current_view_mut().scroll_y = 20;
+        editor.sync_scroll_bind();
+        assert_eq!(editor.views[&first].scroll_y, 15);
+        editor.views.get_mut(&first).unwrap().scroll_y = 2;
+        editor.sync_scroll_bind();
+        assert_eq!(editor.views[&second].scroll_y, 7);
+        editor.views.get_mut(&first).unwrap().scroll_y = 0;
+        editor.sync_scroll_bind();
+        assert_eq!(editor.views[&second].scroll_y, 5);
+
+        // Follow mode shows the lines after the view beside it
+        editor.bind_scroll(second, false);
+        editor.views.get_mut(&first).unwrap().scroll_y = 30;
+        editor.tree.set_focus(first);
+        editor.sync_scroll_bind();
+        assert_eq!(editor.views[&second].scroll_y, 5);
+        editor.follow();
+        let third = editor.tree.views()[1];
+        let height = editor.views[&first].height as usize;
+        assert_eq!(editor.views[&third].scroll_y, 30 + height);
+        editor.current_view_mut().scroll_y = 40;
+        editor.sync_scroll_bind();
+        assert_eq!(editor.views[&third].scroll_y, 40 + height);
+        assert_eq!(editor.views[&second].scroll_y, 5);
+
+        // Views bound on another page scroll on their own
+        editor.new_page("other");
+        let fourth = editor.tree.focus();
+        editor.split(Layout::Vertical);
+        let fifth = editor.tree.focus();
+        editor.bind_scroll(fourth, true);
+        editor.bind_scroll(fifth, true);
+        editor.current_view_mut().scroll_y = 60;
+        editor.sync_scroll_bind();
+        assert_eq!(editor.views[&fourth].scroll_y, 60);
+        assert_eq!((editor.views[&first].scroll_y, editor.views[&third].scroll_y), (40, 40 + height));
+        editor.switch_page(0);
+        editor.sync_scroll_bind();
+        assert_eq!(editor.views[&third].scroll_y, 40 + height);
+        editor.views.get_mut(&first).unwrap().scroll_y = 50;
+        editor.sync_scroll_bind();
+        assert_eq!(editor.views[&third].scroll_y, 50 + height);
+        assert_eq!(editor.views[&fourth].scroll_y, 60);
+    }
+
+    #[test]
+    fn test_split_views_scroll_independently() {
+        let mut editor = Editor::new();
+        editor.resize(Rect::new(0, 1, 80, 20));
+        let first = editor.tree.focus();
+        editor.current_view_mut().scroll_y = 7;
+
+        editor.split(Layout::Horizontal);
+        let second = editor.tree.focus();
+        assert_eq!(editor.views[&second].scroll_y, 7);
+        assert_eq!(editor.views[&first].area, Rect::new(0, 1, 80, 10));
+        assert_eq!(editor.views[&second].area, Rect::new(0, 12, 80, 9));
+
+        let config = lite_config::EditorConfig {
+            scrolloff: 0,
+            ..Default::default()
+        };
+        editor.current_view_mut().ensure_cursor_visible(40, 0, &config);
+        assert_eq!(editor.views[&second].scroll_y, 32);
+        assert_eq!(editor.views[&first].scroll_y, 7);
+
+        // Switching documents and back restores the scroll position
+        let doc_id = editor.current_view().doc_id;
+        editor.new_document();
+        assert_eq!(editor.current_view().scroll_y, 0);
+        editor.switch_to_document(doc_id);
+        assert_eq!(editor.current_view().scroll_y, 32);
+    }
+
+    #[test]
+    fn test_views_follow_edits_above() {
+        let mut editor = Editor::new();
+        let lines: String = (0..100).map(|i| format!("line {}\n", i)).collect();
+        type_text(&mut editor, &lines);
+        let first = editor.tree.focus();
+        editor.split(Layout::Vertical);
+        let second = editor.tree.focus();
+        editor.views.get_mut(&first).unwrap().scroll_y = 50;
+        editor.follow_changes();
+
+        // Lines added and removed above the first view from the second one
+        let doc = editor.current_doc_mut();
+        doc.apply(&Transaction::insert(doc.len_chars(), 0, "a\nb\nc\n"), second);
+        editor.follow_changes();
+        assert_eq!(editor.views[&first].scroll_y, 53);
+        let doc = editor.current_doc_mut();
+        doc.apply(&Transaction::delete(doc.len_chars(), 0, 16), second);
+        editor.follow_changes();
+        assert_eq!(editor.views[&first].scroll_y, 49);
+
+        // Text inserted at the top line shows, a scrolled view stays put
+        let top = editor.current_doc().rope.line_to_char(49);
+        let doc = editor.current_doc_mut();
+        doc.apply(&Transaction::insert(doc.len_chars(), top, "new\n"), second);
+        editor.follow_changes();
+        assert_eq!(editor.views[&first].scroll_y, 49);
+        editor.views.get_mut(&first).unwrap().scroll_y = 10;
+        let doc = editor.current_doc_mut();
+        doc.apply(&Transaction::insert(doc.len_chars(), 0, "x\n"), second);
+        editor.follow_changes();
+        assert_eq!(editor.views[&first].scroll_y, 10);
+    }
+
+    #[test]
+    fn test_resize_relayouts_all_views() {
+        let mut editor = Editor::new();
+        editor.resize(Rect::new(0, 1, 80, 20));
+        let first = editor.tree.focus();
+        editor.split(Layout::Vertical);
+        let second = editor.tree.focus();
+
+        editor.resize(Rect::new(0, 1, 121, 30));
+        assert_eq!(editor.views[&first].area, Rect::new(0, 1, 60, 30));
+        assert_eq!(editor.views[&second].area, Rect::new(61, 1, 60, 30));
+        assert_eq!(editor.views[&first].height, 30);
+    }
+}
@@ -0,0 +1,169 @@
+//! The file tree sidebar: a directory and the subdirectories opened in it,
+//! flattened into rows. Reading directories is left to the caller.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// A file or directory shown in the tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeEntry {
+    pub path: PathBuf,
+    pub name: String,
+    pub dir: bool,
+    /// Directories it's nested in below the root
+    pub depth: usize,
+}
+
+/// The file tree panel
+#[derive(Debug, Clone)]
+pub struct FileTree {
+    pub root: PathBuf,
+    /// Visible rows, each expanded directory followed by its entries
+    pub entries: Vec<TreeEntry>,
+    /// Directories showing their entries
+    pub expanded: HashSet<PathBuf>,
+    /// Row picked while the panel has focus
+    pub selected: usize,
+    /// Whether keys go to the panel rather than the document
+    pub focused: bool,
+}
+
+impl FileTree {
+    pub fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            entries: Vec::new(),
+            expanded: HashSet::new(),
+            selected: 0,
+            focused: false,
+        }
+    }
+
+    /// List the rows again, keeping the same entry picked if it's still
+    /// there. `read` gives the names in a directory and whether each is a
+    /// directory, in the order to show them.
+    pub fn rebuild(&mut self, read: &dyn Fn(&Path) -> Vec<(String, bool)>) {
+        let picked = self.selected_entry().map(|entry| entry.path.clone());
+        let mut entries = Vec::new();
+        self.list(&self.root, 0, read, &mut entries);
+        self.entries = entries;
+        match picked {
+            Some(path) => self.select_path(&path),
+            None => self.move_selection(0),
+        }
+    }
+
+    fn list(&self, dir: &Path, depth: usize, read: &dyn Fn(&Path) -> Vec<(String, bool)>, out: &mut Vec<TreeEntry>) {
+        for (name, is_dir) in read(dir) {
+            let path = dir.join(&name);
+            let expanded = is_dir && self.expanded.contains(&path);
+            out.push(TreeEntry {
+                path: path.clone(),
+                name,
+                dir: is_dir,
+                depth,
+            });
+            if expanded {
+                self.list(&path, depth + 1, read, out);
+            }
+        }
+    }
+
+    /// The picked row, if the tree has any
+    pub fn selected_entry(&self) -> Option<&TreeEntry> {
+        self.entries.get(self.selected)
+    }
+
+    /// Move the pick by `delta` rows
+    pub fn move_selection(&mut self, delta: isize) {
+        let last = self.entries.len().saturating_sub(1);
+        self.selected = self.selected.saturating_add_signed(delta).min(last);
+    }
+
+    /// Pick the row of a path, or the nearest row if it isn't shown
+    pub fn select_path(&mut self, path: &Path) {
+        match self.entries.iter().position(|entry| entry.path == path) {
+            Some(row) => self.selected = row,
+            None => self.move_selection(0),
+        }
+    }
+
+    /// Directory new entries go in: the picked one, or the one holding the
+    /// picked file
+    pub fn target_dir(&self) -> PathBuf {
+        match self.selected_entry() {
+            Some(entry) if entry.dir => entry.path.clone(),
+            Some(entry) => entry.path.parent().unwrap_or(&self.root).to_path_buf(),
+            None => self.root.clone(),
+        }
+    }
+
+    /// Expand or collapse the picked directory. Returns `false` if a file
+    /// is picked.
+    pub fn toggle_selected(&mut self) -> bool {
+        let Some(entry) = self.selected_entry().filter(|entry| entry.dir) else {
+            return false;
+        };
+        let path = entry.path.clone();
+        if !self.expanded.remove(&path) {
+            self.expanded.insert(path);
+        }
+        true
+    }
+
+    /// Collapse the picked directory, or pick the directory holding the
+    /// picked entry
+    pub fn collapse_selected(&mut self) {
+        let Some(entry) = self.selected_entry().cloned() else {
+            return;
+        };
+        if entry.dir && self.expanded.remove(&entry.path) {
+            return;
+        }
+        if let Some(parent) = entry.path.parent() {
+            self.select_path(parent);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(dir: &Path) -> Vec<(String, bool)> {
+        let names: &[(&str, bool)] = match dir.to_str().unwrap() {
+            "/p" => &[("src", true), ("docs", true), ("README.md", false)],
+            "/p/src" => &[("main.rs", false)],
+            _ => &[],
+        };
+        names.iter().map(|(name, dir)| (name.to_string(), *dir)).collect()
+    }
+
+    fn rows(tree: &FileTree) -> Vec<(usize, &str)> {
+        tree.entries.iter().map(|entry| (entry.depth, entry.name.as_str())).collect()
+    }
+
+    #[test]
+    fn test_expand_collapse() {
+        let mut tree = FileTree::new(PathBuf::from("/p"));
+        tree.rebuild(&read);
+        assert_eq!(rows(&tree), vec![(0, "src"), (0, "docs"), (0, "README.md")]);
+
+        assert!(tree.toggle_selected());
+        tree.rebuild(&read);
+        assert_eq!(rows(&tree), vec![(0, "src"), (1, "main.rs"), (0, "docs"), (0, "README.md")]);
+        tree.move_selection(1);
+        assert_eq!(tree.target_dir(), PathBuf::from("/p/src"));
+        assert!(!tree.toggle_selected());
+
+        // Left on a file goes to its directory, then collapses it
+        tree.collapse_selected();
+        assert_eq!(tree.selected, 0);
+        tree.collapse_selected();
+        tree.rebuild(&read);
+        assert_eq!(rows(&tree).len(), 3);
+        tree.move_selection(2);
+        assert_eq!(tree.selected_entry().unwrap().name, "README.md");
+        assert_eq!(tree.target_dir(), PathBuf::from("/p"));
+    }
+}
@@ -0,0 +1,30 @@
+//! Reading and writing the files of documents. Without the `fs` feature,
+//! as in the browser, there are no files and every access fails.
+
+#[cfg(feature = "fs")]
+pub use std::fs::{read, read_to_string, write};
+
+#[cfg(not(feature = "fs"))]
+pub use unsupported::{read, read_to_string, write};
+
+#[cfg(not(feature = "fs"))]
+mod unsupported {
+    use std::io::{Error, ErrorKind, Result};
+    use std::path::Path;
+
+    fn no_files() -> Error {
+        Error::new(ErrorKind::Unsupported, "no files in this build")
+    }
+
+    pub fn read(_path: impl AsRef<Path>) -> Result<Vec<u8>> {
+        Err(no_files())
+    }
+
+    pub fn read_to_string(_path: impl AsRef<Path>) -> Result<String> {
+        Err(no_files())
+    }
+
+    pub fn write(_path: impl AsRef<Path>, _contents: impl AsRef<[u8]>) -> Result<()> {
+        Err(no_files())
+    }
+}
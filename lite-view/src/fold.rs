@@ -0,0 +1,291 @@
+//! Folding: hiding the lines of a section under the line starting it.
+//!
+//! Where sections are comes from a provider for the document's language:
+//! Markdown headings own the lines up to the next heading of their level
+//! or higher, and other text folds by indentation. Folds are kept as char
+//! positions so edits elsewhere don't move them.
+
+use crate::outline;
+use lite_core::{ChangeSet, Rope, RopeExt};
+use std::ops::Range;
+
+/// A section that can be folded: `lines.start` stays shown and the rest of
+/// `lines` hides
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoldRange {
+    pub lines: Range<usize>,
+    /// A heading's level, or how deep the section is nested
+    pub level: usize,
+}
+
+/// The sections of a text that can be folded, by their first line
+pub fn ranges(rope: &Rope, language: Option<&str>) -> Vec<FoldRange> {
+    match language {
+        Some("markdown") => heading_ranges(rope),
+        _ => indent_ranges(rope),
+    }
+}
+
+/// Markdown sections, without the blank lines ending them so headings stay
+/// apart when folded
+fn heading_ranges(rope: &Rope) -> Vec<FoldRange> {
+    outline::symbols("markdown", &rope.to_string())
+        .into_iter()
+        .map(|heading| FoldRange {
+            lines: heading.lines.start..last_text_line(rope, heading.lines.clone()) + 1,
+            level: heading.kind.len(),
+        })
+        .filter(|range| range.lines.len() > 1)
+        .collect()
+}
+
+/// Lines followed by more indented ones, which make up their section
+fn indent_ranges(rope: &Rope) -> Vec<FoldRange> {
+    let indent = |line: usize| {
+        let text = rope.line(line);
+        let blanks = text.chars().take_while(|c| *c == ' ' || *c == '\t').count();
+        (blanks < rope.line_len_chars(line)).then_some(blanks)
+    };
+    let mut ranges = Vec::new();
+    // Sections still open: first line, its indentation and where it's at
+    let mut open: Vec<(usize, usize, usize)> = Vec::new();
+    let mut last_text = 0;
+    for line in 0..rope.len_lines() {
+        let Some(width) = indent(line) else {
+            continue;
+        };
+        while let Some(&(start, _, index)) = open.last().filter(|(_, open, _)| *open >= width) {
+            open.pop();
+            ranges[index] = FoldRange {
+                lines: start..last_text + 1,
+                level: open.len() + 1,
+            };
+        }
+        open.push((line, width, ranges.len()));
+        ranges.push(FoldRange { lines: line..line, level: 0 });
+        last_text = line;
+    }
+    for (depth, (start, _, index)) in open.iter().enumerate() {
+        ranges[*index] = FoldRange {
+            lines: *start..last_text + 1,
+            level: depth + 1,
+        };
+    }
+    ranges.retain(|range| range.lines.len() > 1);
+    ranges
+}
+
+/// The last line of a range with more than blanks on it, or its first
+fn last_text_line(rope: &Rope, lines: Range<usize>) -> usize {
+    let start = lines.start;
+    lines.rev().find(|line| !rope.line(*line).to_string().trim().is_empty()).unwrap_or(start)
+}
+
+/// What a section shows after cycling it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cycle {
+    /// Only its first line
+    Folded,
+    /// Its subsections, folded
+    Children,
+    /// All of it
+    Subtree,
+}
+
+/// Folded sections of a document
+#[derive(Debug, Clone, Default)]
+pub struct Folds {
+    /// Chars where the first and last line of each fold start
+    folds: Vec<(usize, usize)>,
+}
+
+impl Folds {
+    pub fn is_empty(&self) -> bool {
+        self.folds.is_empty()
+    }
+
+    /// Hide the lines of a section after its first
+    pub fn fold(&mut self, rope: &Rope, lines: Range<usize>) {
+        if lines.len() > 1 && !self.is_folded(rope, lines.start) {
+            self.folds.push((rope.line_to_char(lines.start), rope.line_to_char(lines.end - 1)));
+        }
+    }
+
+    /// Show the section starting at a line again. Returns whether it was
+    /// folded.
+    pub fn unfold(&mut self, rope: &Rope, line: usize) -> bool {
+        let len = self.folds.len();
+        self.folds.retain(|(start, _)| rope.char_to_line(*start) != line);
+        self.folds.len() != len
+    }
+
+    pub fn clear(&mut self) {
+        self.folds.clear();
+    }
+
+    /// Whether the section starting at a line is folded
+    pub fn is_folded(&self, rope: &Rope, line: usize) -> bool {
+        self.folds.iter().any(|(start, _)| rope.char_to_line(*start) == line)
+    }
+
+    /// The lines folds hide, sorted and merged
+    pub fn hidden(&self, rope: &Rope) -> Vec<Range<usize>> {
+        let mut hidden: Vec<Range<usize>> = self
+            .folds
+            .iter()
+            .map(|(start, last)| rope.char_to_line(*start) + 1..rope.char_to_line(*last) + 1)
+            .collect();
+        hidden.sort_unstable_by_key(|lines| lines.start);
+        let mut merged: Vec<Range<usize>> = Vec::new();
+        for lines in hidden {
+            match merged.last_mut() {
+                Some(last) if lines.start <= last.end => last.end = last.end.max(lines.end),
+                _ => merged.push(lines),
+            }
+        }
+        merged
+    }
+
+    /// Show the lines of every fold hiding a line. Returns whether any did.
+    pub fn reveal(&mut self, rope: &Rope, line: usize) -> bool {
+        let len = self.folds.len();
+        self.folds
+            .retain(|(start, last)| !(rope.char_to_line(*start) + 1..=rope.char_to_line(*last)).contains(&line));
+        self.folds.len() != len
+    }
+
+    /// Move the folds across an edit. Folds left without lines to hide go.
+    pub fn map(&mut self, changes: &ChangeSet, rope: &Rope) {
+        for (start, last) in &mut self.folds {
+            *start = changes.map_pos(*start);
+            *last = changes.map_pos(*last);
+        }
+        self.folds.retain(|(start, last)| rope.char_to_line(*last) > rope.char_to_line(*start));
+    }
+
+    /// Cycle a section the way Org mode does: folded, then showing its
+    /// subsections folded, then all of it. `None` if no section starts at
+    /// the line.
+    pub fn cycle(&mut self, rope: &Rope, ranges: &[FoldRange], line: usize) -> Option<Cycle> {
+        let section = ranges.iter().find(|range| range.lines.start == line)?;
+        let inner: Vec<&FoldRange> = ranges
+            .iter()
+            .filter(|range| range.lines.start > line && range.lines.start < section.lines.end)
+            .collect();
+        // Subsections not inside another one
+        let mut children: Vec<&FoldRange> = Vec::new();
+        for range in &inner {
+            if children.last().is_none_or(|child| range.lines.start >= child.lines.end) {
+                children.push(range);
+            }
+        }
+
+        if self.is_folded(rope, line) {
+            self.unfold(rope, line);
+            for range in &inner {
+                self.unfold(rope, range.lines.start);
+            }
+            if children.is_empty() {
+                return Some(Cycle::Subtree);
+            }
+            for child in children {
+                self.fold(rope, child.lines.clone());
+            }
+            Some(Cycle::Children)
+        } else if inner.iter().any(|range| self.is_folded(rope, range.lines.start)) {
+            for range in &inner {
+                self.unfold(rope, range.lines.start);
+            }
+            Some(Cycle::Subtree)
+        } else {
+            self.fold(rope, section.lines.clone());
+            Some(Cycle::Folded)
+        }
+    }
+}
+
+/// Whether a line is in one of the sorted hidden ranges
+pub fn is_hidden(hidden: &[Range<usize>], line: usize) -> bool {
+    let i = hidden.partition_point(|lines| lines.end <= line);
+    hidden.get(i).is_some_and(|lines| lines.contains(&line))
+}
+
+/// The row a line shows on, counting from the top of the text
+pub fn row(hidden: &[Range<usize>], line: usize) -> usize {
+    let above = hidden.iter().map(|lines| (lines.start.min(line)..lines.end.min(line)).len());
+    line - above.sum::<usize>()
+}
+
+/// The line shown on a row, the other way around from [`row`]
+pub fn line(hidden: &[Range<usize>], row: usize) -> usize {
+    let mut line = row;
+    for lines in hidden {
+        if lines.start > line {
+            break;
+        }
+        line += lines.len();
+    }
+    line
+}
+
+/// The line a cursor moving a line up or down from `line` lands on,
+/// stepping over hidden ones
+pub fn step(hidden: &[Range<usize>], line: usize, down: bool, len_lines: usize) -> usize {
+    let mut next = line;
+    loop {
+        next = match down {
+            true if next + 1 < len_lines => next + 1,
+            false if next > 0 => next - 1,
+            _ => return line,
+        };
+        if !is_hidden(hidden, next) {
+            return next;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(ranges: &[FoldRange]) -> Vec<(Range<usize>, usize)> {
+        ranges.iter().map(|range| (range.lines.clone(), range.level)).collect()
+    }
+
+    #[test]
+    fn test_ranges() {
+        let markdown = Rope::from("# A\ntext\n\n## B\nmore\n\n### C\n\n# D\n## E\n");
+        assert_eq!(lines(&ranges(&markdown, Some("markdown"))), vec![(0..7, 1), (3..7, 2), (8..10, 1)]);
+
+        let code = Rope::from("fn a() {\n    if x {\n        y\n    }\n\n}\nfn b() {}\n");
+        assert_eq!(lines(&ranges(&code, None)), vec![(0..4, 1), (1..3, 2)]);
+    }
+
+    #[test]
+    fn test_cycle() {
+        let rope = Rope::from("# A\n## B\nb\n### C\nc\n## D\nd\n");
+        let ranges = ranges(&rope, Some("markdown"));
+        let mut folds = Folds::default();
+        assert_eq!(folds.cycle(&rope, &ranges, 0), Some(Cycle::Folded));
+        assert_eq!(folds.hidden(&rope), vec![1..7]);
+        assert_eq!(folds.cycle(&rope, &ranges, 0), Some(Cycle::Children));
+        assert_eq!(folds.hidden(&rope), vec![2..5, 6..7]);
+        assert_eq!(folds.cycle(&rope, &ranges, 0), Some(Cycle::Subtree));
+        assert!(folds.is_empty());
+        assert_eq!(folds.cycle(&rope, &ranges, 2), None);
+
+        // Steps skip what's hidden, and edits above move the folds
+        folds.fold(&rope, 1..5);
+        let hidden = folds.hidden(&rope);
+        assert_eq!(step(&hidden, 1, true, 8), 5);
+        assert_eq!(step(&hidden, 5, false, 8), 1);
+        assert_eq!((row(&hidden, 5), line(&hidden, 2)), (2, 5));
+        let mut rope = rope;
+        let tx = lite_core::Transaction::insert(rope.len_chars(), 0, "intro\n");
+        tx.apply(&mut rope);
+        folds.map(&tx.changes, &rope);
+        assert_eq!(folds.hidden(&rope), vec![3..6]);
+        assert!(folds.reveal(&rope, 4));
+        assert!(folds.is_empty());
+    }
+}
@@ -10,6 +10,9 @@ pub struct History {
     undo_stack: Vec<Transaction>,
     /// Redo stack
     redo_stack: Vec<Transaction>,
+    /// Length of the undo stack when the document was saved; `None` once no
+    /// sequence of undos and redos leads back there
+    saved: Option<usize>,
 }
 
 impl History {
@@ -18,20 +21,29 @@ impl History {
         Self {
             undo_stack: Vec::new(),
             redo_stack: Vec::new(),
+            saved: Some(0),
         }
     }
 
-    /// Push a transaction to the undo stack
+    /// Push a transaction to the undo stack for a new edit
     pub fn push(&mut self, tx: Transaction) {
-        // Clear redo stack on new edit
+        // Clear redo stack on new edit, along with the saved state if it was in there
+        if self.saved.is_some_and(|saved| saved > self.undo_stack.len()) {
+            self.saved = None;
+        }
         self.redo_stack.clear();
 
-        // Add to undo stack
+        self.push_undo(tx);
+    }
+
+    /// Push a transaction to the undo stack, keeping the redo stack (used internally)
+    pub fn push_undo(&mut self, tx: Transaction) {
         self.undo_stack.push(tx);
 
         // Limit history size
         if self.undo_stack.len() > MAX_HISTORY_SIZE {
             self.undo_stack.remove(0);
+            self.saved = self.saved.and_then(|saved| saved.checked_sub(1));
         }
     }
 
@@ -62,10 +74,27 @@ impl History {
 
     /// Clear all history
     pub fn clear(&mut self) {
+        self.saved = self.is_saved().then_some(0);
         self.undo_stack.clear();
         self.redo_stack.clear();
     }
 
+    /// Remember the current state as the saved one
+    pub fn mark_saved(&mut self) {
+        self.saved = Some(self.undo_stack.len());
+    }
+
+    /// Whether undos and redos led back to the saved state
+    pub fn is_saved(&self) -> bool {
+        self.saved == Some(self.undo_stack.len())
+    }
+
+    /// Release memory the stacks no longer use
+    pub fn shrink_to_fit(&mut self) {
+        self.undo_stack.shrink_to_fit();
+        self.redo_stack.shrink_to_fit();
+    }
+
     /// Get the number of undo states
     pub fn undo_count(&self) -> usize {
         self.undo_stack.len()
@@ -1,15 +1,53 @@
 //! Editor state and view management for lite editor
 
+pub mod annotation;
+mod buffer;
+pub mod comment;
+mod compare;
+mod completion;
+mod crypt;
+pub mod decoration;
+mod diagnostic;
+pub mod digraph;
 mod document;
 mod editor;
+mod file_tree;
+mod files;
+pub mod fold;
 mod history;
+mod line_syntax;
+mod metrics;
+pub mod outline;
+mod peek;
+pub mod link;
+mod signature;
+mod server_status;
+#[cfg(feature = "syntax")]
+pub mod structure;
 pub mod syntax;
+pub mod table;
 mod tree;
+pub mod todo;
 mod view;
+pub mod workspace;
 
-pub use document::{Document, DocumentId, LineEnding};
-pub use editor::{Editor, Severity};
+pub use annotation::{Annotation, AnnotationStyle, Annotations, Placement};
+pub use buffer::{BufferHandler, BufferKind};
+pub use compare::BufferDiff;
+pub use crypt::run_filter_in;
+pub use decoration::{Decoration, DecorationStyle, Decorations};
+pub use completion::{Completion, CompletionItem, TextEdit};
+pub use diagnostic::{Diagnostic, DiagnosticSeverity};
+pub use document::{ChangeSubscription, Document, DocumentId, LineEnding};
+pub use editor::{BufferSwitcher, Editor, IgnoreFilter, JobCallback, Severity, MAX_PROMPT_HISTORY};
+pub use file_tree::{FileTree, TreeEntry};
 pub use history::History;
-pub use syntax::{highlighter, Highlight, HighlightSpan, Highlighter};
-pub use tree::{Layout, Tree};
+pub use metrics::{DebugCommand, FrameMetrics, FRAME_BUDGET};
+pub use outline::{Outline, Symbol};
+pub use peek::{Peek, PEEK_LINES};
+pub use server_status::{ServerState, ServerStatus};
+pub use signature::{Signature, SignatureHelp};
+pub use syntax::{highlighter, Highlight, HighlightSpan, Highlighter, LONG_LINE_LEN};
+pub use table::TableLayout;
+pub use tree::{Layout, Rect, Tree};
 pub use view::{View, ViewId};
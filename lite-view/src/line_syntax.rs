@@ -0,0 +1,207 @@
+//! Line-based highlighters for git formats that don't need a parser
+
+use crate::syntax::{Highlight, HighlightSpan};
+
+/// Recommended length of a commit summary line
+const SUMMARY_WIDTH: usize = 50;
+/// Recommended length of commit body lines
+const BODY_WIDTH: usize = 72;
+
+/// Marker git puts above the diff in `git commit --verbose`; nothing below it is committed
+const SCISSORS: &str = "# ------------------------ >8 ------------------------";
+
+/// Check if a language is handled here
+pub fn supports(language: &str) -> bool {
+    matches!(language, "git-commit" | "git-rebase" | "diff")
+}
+
+/// Highlight a source in one of the supported languages
+pub fn highlight(language: &str, source: &str) -> Option<Vec<HighlightSpan>> {
+    let mut spans = Vec::new();
+    match language {
+        "git-commit" => highlight_commit(source, &mut spans),
+        "git-rebase" => {
+            for (start, line) in lines(source) {
+                highlight_rebase_line(start, line, &mut spans);
+            }
+        }
+        "diff" => {
+            for (start, line) in lines(source) {
+                highlight_diff_line(start, line, &mut spans);
+            }
+        }
+        _ => return None,
+    }
+    Some(spans)
+}
+
+/// Iterate over lines with their byte offsets, without line endings
+fn lines(source: &str) -> impl Iterator<Item = (usize, &str)> {
+    source.split_inclusive('\n').scan(0, |offset, line| {
+        let start = *offset;
+        *offset += line.len();
+        Some((start, line.trim_end_matches(['\n', '\r'])))
+    })
+}
+
+fn span(spans: &mut Vec<HighlightSpan>, start: usize, end: usize, highlight: Highlight) {
+    if start < end {
+        spans.push(HighlightSpan {
+            start,
+            end,
+            highlight,
+        });
+    }
+}
+
+/// Flag the part of a line past `width` characters
+fn overflow(spans: &mut Vec<HighlightSpan>, start: usize, line: &str, width: usize) {
+    if let Some((byte, _)) = line.char_indices().nth(width) {
+        span(spans, start + byte, start + line.len(), Highlight::Overlong);
+    }
+}
+
+/// Commit messages: comments, the 50 column summary, a blank second line and
+/// 72 column body lines
+fn highlight_commit(source: &str, spans: &mut Vec<HighlightSpan>) {
+    let mut message_line = 0;
+    let mut in_diff = false;
+
+    for (start, line) in lines(source) {
+        if in_diff {
+            highlight_diff_line(start, line, spans);
+            continue;
+        }
+        if line == SCISSORS {
+            in_diff = true;
+            span(spans, start, start + line.len(), Highlight::Comment);
+            continue;
+        }
+        if line.starts_with('#') {
+            span(spans, start, start + line.len(), Highlight::Comment);
+            continue;
+        }
+
+        match message_line {
+            0 => overflow(spans, start, line, SUMMARY_WIDTH),
+            1 => span(spans, start, start + line.len(), Highlight::Overlong),
+            _ => overflow(spans, start, line, BODY_WIDTH),
+        }
+        message_line += 1;
+    }
+}
+
+/// Unified diffs: file headers, hunk headers, added and removed lines
+fn highlight_diff_line(start: usize, line: &str, spans: &mut Vec<HighlightSpan>) {
+    let end = start + line.len();
+    if line.starts_with("diff ")
+        || line.starts_with("index ")
+        || line.starts_with("--- ")
+        || line.starts_with("+++ ")
+    {
+        span(spans, start, end, Highlight::Keyword);
+    } else if let Some(rest) = line.strip_prefix("@@") {
+        // Only the range is the hunk header, the rest is context
+        let header_end = rest.find("@@").map_or(line.len(), |i| i + 4);
+        span(spans, start, start + header_end, Highlight::DiffHunk);
+        span(spans, start + header_end, end, Highlight::Function);
+    } else if line.starts_with('+') {
+        span(spans, start, end, Highlight::DiffAdded);
+    } else if line.starts_with('-') {
+        span(spans, start, end, Highlight::DiffRemoved);
+    }
+}
+
+/// Interactive rebase todo lists: commands, commit hashes and comments
+fn highlight_rebase_line(start: usize, line: &str, spans: &mut Vec<HighlightSpan>) {
+    let trimmed = line.trim_start();
+    let indent = line.len() - trimmed.len();
+    if trimmed.starts_with('#') {
+        span(spans, start, start + line.len(), Highlight::Comment);
+        return;
+    }
+
+    let command_len = trimmed.find(char::is_whitespace).unwrap_or(trimmed.len());
+    let command = &trimmed[..command_len];
+    let takes_commit = matches!(
+        command,
+        "pick" | "p" | "reword" | "r" | "edit" | "e" | "squash" | "s" | "fixup" | "f" | "drop"
+            | "d"
+    );
+    if !takes_commit && !matches!(
+        command,
+        "exec" | "x" | "break" | "b" | "label" | "l" | "reset" | "t" | "merge" | "m"
+            | "update-ref" | "u"
+    ) {
+        return;
+    }
+
+    let command_start = start + indent;
+    span(spans, command_start, command_start + command_len, Highlight::Keyword);
+
+    if takes_commit {
+        let rest = &trimmed[command_len..];
+        let hash_offset = rest.len() - rest.trim_start().len();
+        let hash_len = rest
+            .trim_start()
+            .find(char::is_whitespace)
+            .unwrap_or(rest.trim_start().len());
+        let hash_start = command_start + command_len + hash_offset;
+        span(spans, hash_start, hash_start + hash_len, Highlight::Constant);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn highlights(language: &str, source: &str) -> Vec<(String, Highlight)> {
+        highlight(language, source)
+            .unwrap()
+            .into_iter()
+            .map(|s| (source[s.start..s.end].to_string(), s.highlight))
+            .collect()
+    }
+
+    #[test]
+    fn test_commit_message() {
+        let summary = format!("{}{}", "a".repeat(50), "overflow");
+        let source = format!("{}\nnot blank\n# comment\n", summary);
+        assert_eq!(
+            highlights("git-commit", &source),
+            vec![
+                ("overflow".to_string(), Highlight::Overlong),
+                ("not blank".to_string(), Highlight::Overlong),
+                ("# comment".to_string(), Highlight::Comment),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff() {
+        let source = "--- a/x\n+++ b/x\n@@ -1 +1 @@ fn main\n-old\n+new\n same\n";
+        assert_eq!(
+            highlights("diff", source),
+            vec![
+                ("--- a/x".to_string(), Highlight::Keyword),
+                ("+++ b/x".to_string(), Highlight::Keyword),
+                ("@@ -1 +1 @@".to_string(), Highlight::DiffHunk),
+                (" fn main".to_string(), Highlight::Function),
+                ("-old".to_string(), Highlight::DiffRemoved),
+                ("+new".to_string(), Highlight::DiffAdded),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rebase_todo() {
+        assert_eq!(
+            highlights("git-rebase", "pick 1a2b3c Fix bug\n# help\n"),
+            vec![
+                ("pick".to_string(), Highlight::Keyword),
+                ("1a2b3c".to_string(), Highlight::Constant),
+                ("# help".to_string(), Highlight::Comment),
+            ]
+        );
+    }
+}
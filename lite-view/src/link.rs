@@ -0,0 +1,229 @@
+//! URLs and `file:line[:col]` references found in text
+
+use std::ops::Range;
+use std::path::PathBuf;
+
+/// Schemes recognized as URLs
+const SCHEMES: &[&str] = &["https://", "http://", "ftp://", "file://"];
+
+/// What a link points at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkTarget {
+    Url(String),
+    /// A file location; line and column count from 1
+    File {
+        path: PathBuf,
+        line: usize,
+        col: Option<usize>,
+    },
+}
+
+/// A link and the chars of the line it covers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    pub range: Range<usize>,
+    pub target: LinkTarget,
+}
+
+/// Find the links on a line, in order
+pub fn find_links(line: &str) -> Vec<Link> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut links: Vec<Link> = traceback_link(line).into_iter().collect();
+
+    let mut start = 0;
+    while start < chars.len() {
+        if is_delimiter(chars[start]) {
+            start += 1;
+            continue;
+        }
+        let mut end = start;
+        while end < chars.len() && !is_delimiter(chars[end]) {
+            end += 1;
+        }
+
+        let word: String = chars[start..end].iter().collect();
+        let link = url_link(&word, start)
+            .or_else(|| paren_link(&word, start))
+            .or_else(|| file_link(&word, start));
+        if let Some(link) = link.filter(|link| !links.iter().any(|other| overlaps(other, link))) {
+            links.push(link);
+        }
+        start = end;
+    }
+    links.sort_by_key(|link| link.range.start);
+    links
+}
+
+fn overlaps(a: &Link, b: &Link) -> bool {
+    a.range.start < b.range.end && b.range.start < a.range.end
+}
+
+/// The link under a char column of a line
+pub fn link_at(line: &str, col: usize) -> Option<Link> {
+    find_links(line)
+        .into_iter()
+        .find(|link| link.range.contains(&col) || link.range.end == col)
+}
+
+/// Chars that never appear in links, so they split words
+fn is_delimiter(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '"' | '\'' | '`' | '<' | '>')
+}
+
+/// A word containing a URL; punctuation ending a sentence and a closing
+/// bracket without its opening one aren't part of it
+fn url_link(word: &str, offset: usize) -> Option<Link> {
+    let (start, scheme) = SCHEMES
+        .iter()
+        .filter_map(|scheme| Some((word.find(scheme)?, scheme)))
+        .min()?;
+
+    let mut url = word[start..].trim_end_matches(['.', ',', ';', ':', '!', '?']);
+    for (open, close) in [('(', ')'), ('[', ']')] {
+        if url.ends_with(close) && url.matches(close).count() > url.matches(open).count() {
+            url = &url[..url.len() - 1];
+        }
+    }
+    if url.len() == scheme.len() {
+        return None;
+    }
+
+    let start = offset + word[..start].chars().count();
+    Some(Link {
+        range: start..start + url.chars().count(),
+        target: LinkTarget::Url(url.to_string()),
+    })
+}
+
+/// A word like `src/main.rs:12` or `(main.rs:12:5)`, as compilers and
+/// test runners print them
+fn file_link(word: &str, offset: usize) -> Option<Link> {
+    let lead = word.len() - word.trim_start_matches(['(', '[']).len();
+    let word = word[lead..].trim_end_matches([')', ']', ',', ';', '.', ':']);
+
+    let (rest, last) = word.rsplit_once(':')?;
+    if !is_number(last) {
+        return None;
+    }
+    let (path, line, col) = match rest.rsplit_once(':') {
+        Some((path, line)) if is_number(line) => (path, line, Some(last)),
+        _ => (rest, last, None),
+    };
+
+    let looks_like_path = (path.contains('.') || path.contains('/')) && !path.contains("://");
+    if !looks_like_path || path.ends_with('.') {
+        return None;
+    }
+
+    let start = offset + word[..lead].chars().count();
+    Some(Link {
+        range: start..start + word.chars().count(),
+        target: LinkTarget::File {
+            path: PathBuf::from(path),
+            line: line.parse().ok()?,
+            col: col.and_then(|col| col.parse().ok()),
+        },
+    })
+}
+
+/// A word like `src/app.ts(12,5):`, as MSVC and tsc print them
+fn paren_link(word: &str, offset: usize) -> Option<Link> {
+    let word = word.trim_end_matches([':', ',']);
+    let (path, place) = word.strip_suffix(')')?.rsplit_once('(')?;
+    let (line, col) = match place.split_once(',') {
+        Some((line, col)) => (line, Some(col)),
+        None => (place, None),
+    };
+    if !is_number(line) || !col.is_none_or(is_number) || !path.contains('.') || path.contains("://") {
+        return None;
+    }
+
+    Some(Link {
+        range: offset..offset + word.chars().count(),
+        target: LinkTarget::File {
+            path: PathBuf::from(path),
+            line: line.parse().ok()?,
+            col: col.and_then(|col| col.parse().ok()),
+        },
+    })
+}
+
+/// A Python traceback line, `File "app/main.py", line 12, in run`; the
+/// link covers the path through the line number
+fn traceback_link(line: &str) -> Option<Link> {
+    let start = line.find("File \"")? + "File \"".len();
+    let (path, rest) = line[start..].split_once('"')?;
+    let number = rest.strip_prefix(", line ")?;
+    let digits = number.len() - number.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if path.is_empty() || digits == 0 {
+        return None;
+    }
+
+    let end = line.len() - number.len() + digits;
+    let first = line[..start].chars().count();
+    Some(Link {
+        range: first..first + line[start..end].chars().count(),
+        target: LinkTarget::File {
+            path: PathBuf::from(path),
+            line: number[..digits].parse().ok()?,
+            col: None,
+        },
+    })
+}
+
+fn is_number(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, line: usize, col: Option<usize>) -> LinkTarget {
+        LinkTarget::File {
+            path: PathBuf::from(path),
+            line,
+            col,
+        }
+    }
+
+    #[test]
+    fn test_find_links() {
+        let links = find_links("see (https://example.com/a_(b)) and https://x.org/q?a=1.");
+        let targets: Vec<_> = links.iter().map(|link| link.target.clone()).collect();
+        assert_eq!(
+            targets,
+            vec![
+                LinkTarget::Url("https://example.com/a_(b)".to_string()),
+                LinkTarget::Url("https://x.org/q?a=1".to_string()),
+            ]
+        );
+        assert_eq!(links[0].range, 5..30);
+
+        let links = find_links("  --> src/main.rs:12:5");
+        assert_eq!(links[0].target, file("src/main.rs", 12, Some(5)));
+        assert_eq!(links[0].range, 6..22);
+
+        assert_eq!(find_links("at (lib.rs:7)")[0].target, file("lib.rs", 7, None));
+        assert!(find_links("time 12:30:00, ratio 3:2, http://").is_empty());
+
+        let links = find_links("src/app.ts(12,5): error TS2322");
+        assert_eq!(links[0].target, file("src/app.ts", 12, Some(5)));
+        assert_eq!(links[0].range, 0..16);
+        assert_eq!(find_links("main.cpp(7): warning")[0].target, file("main.cpp", 7, None));
+        assert!(find_links("call f(1,2) and print(3)").is_empty());
+
+        let links = find_links(r#"  File "app/main.py", line 12, in run"#);
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].target, file("app/main.py", 12, None));
+        assert_eq!(links[0].range, 8..29);
+    }
+
+    #[test]
+    fn test_link_at() {
+        let line = "error at a.rs:3 here";
+        assert_eq!(link_at(line, 9).map(|link| link.range), Some(9..15));
+        assert_eq!(link_at(line, 15).map(|link| link.range), Some(9..15));
+        assert_eq!(link_at(line, 2), None);
+    }
+}
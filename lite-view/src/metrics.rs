@@ -0,0 +1,60 @@
+//! Timings of the main loop, reported when `debug_metrics` is on, and
+//! recordings of its input for bug reports
+
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Time a frame has at 60 frames a second
+pub const FRAME_BUDGET: Duration = Duration::from_micros(16_667);
+
+/// Timings of one frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameMetrics {
+    /// From the first input handled for the frame reaching the main loop
+    /// until the frame was flushed; `None` for frames without input
+    pub latency: Option<Duration>,
+    /// Drawing and flushing the frame
+    pub frame: Duration,
+    /// Syntax highlighting while drawing
+    pub highlight: Duration,
+    /// The step of the main loop that took longest, and how long
+    pub slowest: (&'static str, Duration),
+}
+
+impl FrameMetrics {
+    /// Whether the frame took longer than `FRAME_BUDGET`
+    pub fn over_budget(&self) -> bool {
+        self.latency.unwrap_or(self.slowest.1) > FRAME_BUDGET
+    }
+}
+
+impl fmt::Display for FrameMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        if let Some(latency) = self.latency {
+            write!(f, "input {:.1}ms ", ms(latency))?;
+        }
+        write!(
+            f,
+            "frame {:.1}ms highlight {:.1}ms slowest {} {:.1}ms",
+            ms(self.frame),
+            ms(self.highlight),
+            self.slowest.0,
+            ms(self.slowest.1)
+        )
+    }
+}
+/// A recording command from the prompt, carried out by the application,
+/// which owns the input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DebugCommand {
+    /// Start recording input into a file
+    Record(PathBuf),
+    /// Stop recording
+    StopRecording,
+    /// Play a recording back
+    Replay(PathBuf),
+    /// Close the editor a recording was played back into
+    StopReplay,
+}
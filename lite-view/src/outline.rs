@@ -0,0 +1,308 @@
+//! The outline of a document: its functions, types and headings, nested as
+//! in the source. Code is read with tree-sitter, Markdown by its headings.
+
+use crate::{Document, DocumentId};
+use std::ops::Range;
+#[cfg(feature = "syntax")]
+use tree_sitter::{Node, Parser};
+
+/// A named item of the outline
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    /// Short label of the kind, such as `fn`, `class` or `#`
+    pub kind: &'static str,
+    /// How many symbols it's nested in
+    pub depth: usize,
+    /// Lines the symbol spans
+    pub lines: Range<usize>,
+}
+
+/// The outline panel: symbols of the current document and the row picked
+#[derive(Debug, Clone)]
+pub struct Outline {
+    pub doc_id: DocumentId,
+    pub symbols: Vec<Symbol>,
+    /// Row picked while the panel has focus
+    pub selected: usize,
+    /// Whether keys go to the panel rather than the document
+    pub focused: bool,
+    /// Document version the symbols were read from
+    version: usize,
+}
+
+impl Outline {
+    pub fn new(doc: &Document) -> Self {
+        Self {
+            doc_id: doc.id,
+            symbols: symbols(doc.language.as_deref().unwrap_or_default(), &doc.text()),
+            selected: 0,
+            focused: false,
+            version: doc.version(),
+        }
+    }
+
+    /// Read the symbols again if the document changed or is another one
+    pub fn update(&mut self, doc: &Document) {
+        if doc.id != self.doc_id || doc.version() != self.version {
+            let selected = self.selected;
+            *self = Self {
+                focused: self.focused,
+                ..Self::new(doc)
+            };
+            self.selected = selected.min(self.symbols.len().saturating_sub(1));
+        }
+    }
+
+    /// The innermost symbol containing a line
+    pub fn symbol_at(&self, line: usize) -> Option<usize> {
+        self.symbols.iter().rposition(|symbol| symbol.lines.contains(&line))
+    }
+}
+
+/// The symbols containing a line, outermost first
+pub fn path_at(symbols: &[Symbol], line: usize) -> Vec<&Symbol> {
+    let mut path: Vec<&Symbol> = Vec::new();
+    for symbol in symbols.iter().filter(|symbol| symbol.lines.contains(&line)) {
+        // Symbols on one line as their parent end up side by side
+        path.truncate(symbol.depth);
+        path.push(symbol);
+    }
+    path
+}
+
+/// Symbols of a source in order; none for languages without an outline
+pub fn symbols(language: &str, source: &str) -> Vec<Symbol> {
+    if language == "markdown" {
+        return headings(source);
+    }
+    tree_symbols(language, source)
+}
+
+/// Builds without tree-sitter only outline Markdown
+#[cfg(not(feature = "syntax"))]
+fn tree_symbols(_language: &str, _source: &str) -> Vec<Symbol> {
+    Vec::new()
+}
+
+#[cfg(feature = "syntax")]
+fn tree_symbols(language: &str, source: &str) -> Vec<Symbol> {
+    let Some((grammar, kinds)) = grammar(language) else {
+        return Vec::new();
+    };
+    let mut parser = Parser::new();
+    let Some(tree) = parser.set_language(&grammar).ok().and_then(|_| parser.parse(source, None)) else {
+        return Vec::new();
+    };
+    let mut symbols = Vec::new();
+    collect(tree.root_node(), source, kinds, 0, &mut symbols);
+    symbols
+}
+
+/// Grammar of a language and its node kinds that are symbols, with labels
+#[cfg(feature = "syntax")]
+fn grammar(language: &str) -> Option<(tree_sitter::Language, &'static [(&'static str, &'static str)])> {
+    const RUST: &[(&str, &str)] = &[
+        ("function_item", "fn"),
+        ("function_signature_item", "fn"),
+        ("struct_item", "struct"),
+        ("enum_item", "enum"),
+        ("union_item", "union"),
+        ("trait_item", "trait"),
+        ("impl_item", "impl"),
+        ("mod_item", "mod"),
+        ("macro_definition", "macro"),
+        ("const_item", "const"),
+        ("static_item", "static"),
+        ("type_item", "type"),
+    ];
+    const PYTHON: &[(&str, &str)] = &[("function_definition", "def"), ("class_definition", "class")];
+    const JAVASCRIPT: &[(&str, &str)] = &[
+        ("function_declaration", "function"),
+        ("generator_function_declaration", "function"),
+        ("class_declaration", "class"),
+        ("method_definition", "method"),
+        ("interface_declaration", "interface"),
+        ("type_alias_declaration", "type"),
+        ("enum_declaration", "enum"),
+    ];
+    const GO: &[(&str, &str)] = &[
+        ("function_declaration", "func"),
+        ("method_declaration", "func"),
+        ("type_spec", "type"),
+    ];
+    const C: &[(&str, &str)] = &[
+        ("function_definition", "fn"),
+        ("struct_specifier", "struct"),
+        ("union_specifier", "union"),
+        ("enum_specifier", "enum"),
+        ("class_specifier", "class"),
+        ("namespace_definition", "namespace"),
+    ];
+    Some(match language {
+        "rust" => (tree_sitter_rust::LANGUAGE.into(), RUST),
+        "python" => (tree_sitter_python::LANGUAGE.into(), PYTHON),
+        "javascript" => (tree_sitter_javascript::LANGUAGE.into(), JAVASCRIPT),
+        "typescript" => (tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(), JAVASCRIPT),
+        "go" => (tree_sitter_go::LANGUAGE.into(), GO),
+        "c" => (tree_sitter_c::LANGUAGE.into(), C),
+        "cpp" => (tree_sitter_cpp::LANGUAGE.into(), C),
+        _ => return None,
+    })
+}
+
+#[cfg(feature = "syntax")]
+fn collect(node: Node, source: &str, kinds: &[(&str, &'static str)], depth: usize, out: &mut Vec<Symbol>) {
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        let symbol = kinds
+            .iter()
+            .find(|(kind, _)| *kind == child.kind())
+            .and_then(|(_, label)| Some((symbol_name(child, source)?, *label)));
+        match symbol {
+            Some((name, kind)) => {
+                out.push(Symbol {
+                    name,
+                    kind,
+                    depth,
+                    lines: child.start_position().row..child.end_position().row + 1,
+                });
+                collect(child, source, kinds, depth + 1, out);
+            }
+            None => collect(child, source, kinds, depth, out),
+        }
+    }
+}
+
+/// Name a symbol node is listed under. Declarations without a body, such
+/// as `struct foo *p`, aren't symbols.
+#[cfg(feature = "syntax")]
+fn symbol_name(node: Node, source: &str) -> Option<String> {
+    let text = |node: Node| source[node.byte_range()].split_whitespace().collect::<Vec<_>>().join(" ");
+    if node.kind() == "impl_item" {
+        let ty = text(node.child_by_field_name("type")?);
+        return Some(match node.child_by_field_name("trait") {
+            Some(tr) => format!("{} for {}", text(tr), ty),
+            None => ty,
+        });
+    }
+    if node.kind().ends_with("_specifier") && node.child_by_field_name("body").is_none() {
+        return None;
+    }
+    let mut name = node.child_by_field_name("name");
+    // C functions name their declarator, possibly wrapped in pointers
+    let mut declarator = node.child_by_field_name("declarator");
+    while name.is_none() {
+        let current = declarator?;
+        if current.kind().ends_with("identifier") {
+            name = Some(current);
+        }
+        declarator = current.child_by_field_name("declarator");
+    }
+    name.map(text)
+}
+
+/// ATX headings of a Markdown source, skipping fenced code
+fn headings(source: &str) -> Vec<Symbol> {
+    let mut headings: Vec<Symbol> = Vec::new();
+    let mut levels: Vec<usize> = Vec::new();
+    let mut fence: Option<&str> = None;
+    for (line, text) in source.lines().enumerate() {
+        let trimmed = text.trim_start();
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+            continue;
+        }
+        let level = trimmed.len() - trimmed.trim_start_matches('#').len();
+        let title = &trimmed[level..];
+        if !(1..=6).contains(&level) || !(title.is_empty() || title.starts_with(' ')) {
+            continue;
+        }
+
+        // A heading ends where one of the same or a higher level starts
+        while levels.last().is_some_and(|&open| open >= level) {
+            levels.pop();
+        }
+        for heading in headings.iter_mut().filter(|heading| heading.lines.end == usize::MAX) {
+            if heading.depth >= levels.len() {
+                heading.lines.end = line;
+            }
+        }
+        headings.push(Symbol {
+            name: title.trim().trim_end_matches('#').trim_end().to_string(),
+            kind: &"######"[..level],
+            depth: levels.len(),
+            lines: line..usize::MAX,
+        });
+        levels.push(level);
+    }
+    let end = source.lines().count();
+    for heading in &mut headings {
+        heading.lines.end = heading.lines.end.min(end);
+    }
+    headings
+}
+
+#[cfg(all(test, feature = "syntax"))]
+mod tests {
+    use super::*;
+
+    fn names(symbols: &[Symbol]) -> Vec<(usize, &str, &str)> {
+        symbols.iter().map(|s| (s.depth, s.kind, s.name.as_str())).collect()
+    }
+
+    #[test]
+    fn test_symbols() {
+        let go = "package main\n\ntype Point struct {\n\tX int\n}\n\nfunc (p Point) Len() int {\n\treturn 0\n}\n";
+        let symbols = symbols("go", go);
+        assert_eq!(names(&symbols), vec![(0, "type", "Point"), (0, "func", "Len")]);
+        assert_eq!(symbols[1].lines, 6..9);
+
+        let js = "class Shape {\n  area() {}\n}\nfunction main() {}\n";
+        assert_eq!(
+            names(&super::symbols("javascript", js)),
+            vec![(0, "class", "Shape"), (1, "method", "area"), (0, "function", "main")]
+        );
+
+        let rust = "mod shapes {\n    pub struct Circle;\n    impl Area for Circle {\n        fn area(&self) -> f64 { 0.0 }\n    }\n}\nfn main() {}\n";
+        assert_eq!(
+            names(&super::symbols("rust", rust)),
+            vec![
+                (0, "mod", "shapes"),
+                (1, "struct", "Circle"),
+                (1, "impl", "Area for Circle"),
+                (2, "fn", "area"),
+                (0, "fn", "main"),
+            ]
+        );
+
+        let python = "class Shape:\n    def area(self):\n        pass\n";
+        assert_eq!(
+            names(&super::symbols("python", python)),
+            vec![(0, "class", "Shape"), (1, "def", "area")]
+        );
+
+        let c = "struct node;\nstatic int *make(void) { return 0; }\n";
+        assert_eq!(names(&super::symbols("c", c)), vec![(0, "fn", "make")]);
+
+        let markdown = "# Title\n## Install\n```\n# not a heading\n```\n## Usage\n#hashtag\n";
+        let symbols = super::symbols("markdown", markdown);
+        assert_eq!(
+            names(&symbols),
+            vec![(0, "#", "Title"), (1, "##", "Install"), (1, "##", "Usage")]
+        );
+        assert_eq!(symbols[0].lines, 0..7);
+        assert_eq!(symbols[1].lines, 1..5);
+        assert!(super::symbols("json", "{}").is_empty());
+
+        let path: Vec<_> = path_at(&symbols, 3).into_iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(path, vec!["Title", "Install"]);
+        assert_eq!(path_at(&symbols, 0).len(), 1);
+    }
+}
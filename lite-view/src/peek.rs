@@ -0,0 +1,70 @@
+//! State of the peek definition popup
+
+use crate::{DocumentId, ViewId};
+use lite_core::Rope;
+
+/// Lines of the definition's file shown at once
+pub const PEEK_LINES: usize = 8;
+
+/// Lines shown above the definition for context
+const CONTEXT_LINES: usize = 1;
+
+/// A read-only look at a definition, shown under the line it was asked
+/// from until the cursor moves or Escape closes it
+#[derive(Debug, Clone)]
+pub struct Peek {
+    pub doc_id: DocumentId,
+    pub view_id: ViewId,
+    /// Cursor when the definition was asked for
+    pub trigger: usize,
+    /// Where the definition is, such as `src/main.rs:12`
+    pub title: String,
+    /// Number of the first shown line, from 0
+    pub first_line: usize,
+    /// Shown lines without their line endings
+    pub lines: Vec<String>,
+    /// Line of the definition, from 0
+    pub target: usize,
+}
+
+impl Peek {
+    /// Show the lines of `text` around line `target`
+    pub fn new(doc_id: DocumentId, view_id: ViewId, trigger: usize, title: String, text: &Rope, target: usize) -> Self {
+        let len_lines = text.len_lines();
+        let target = target.min(len_lines.saturating_sub(1));
+        let first_line = target.saturating_sub(CONTEXT_LINES);
+        let lines = (first_line..len_lines.min(first_line + PEEK_LINES))
+            .map(|line| {
+                let line = text.line(line).to_string();
+                line.trim_end_matches(['\n', '\r']).to_string()
+            })
+            .collect();
+        Self {
+            doc_id,
+            view_id,
+            trigger,
+            title,
+            first_line,
+            lines,
+            target,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window() {
+        let text: String = (0..20).map(|i| format!("line {}\r\n", i)).collect();
+        let peek = Peek::new(DocumentId::next(), ViewId::next(), 0, "a.rs:6".into(), &Rope::from_str(&text), 5);
+        assert_eq!(peek.first_line, 4);
+        assert_eq!(peek.lines.len(), PEEK_LINES);
+        assert_eq!(peek.lines[1], "line 5");
+
+        let peek = Peek::new(DocumentId::next(), ViewId::next(), 0, "a.rs:1".into(), &Rope::from_str("fn a() {}"), 3);
+        assert_eq!((peek.first_line, peek.target), (0, 0));
+        assert_eq!(peek.lines, vec!["fn a() {}"]);
+    }
+}
@@ -0,0 +1,20 @@
+//! What the language server of a document is doing
+
+/// A language server as shown in the statusline
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerStatus {
+    /// Name of the server command
+    pub name: String,
+    pub state: ServerState,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServerState {
+    /// Spawned and not done with the initialize handshake yet
+    Starting,
+    /// Answering requests, results reflect the whole project
+    Ready,
+    /// Work the server reported progress for, such as indexing; results may
+    /// be incomplete until it's done
+    Busy(String),
+}
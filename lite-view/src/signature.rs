@@ -0,0 +1,72 @@
+//! State of the signature help popup
+
+use crate::{DocumentId, ViewId};
+use std::ops::Range;
+
+/// One overload of the function being called
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    pub label: String,
+    /// Char ranges of the parameters within the label
+    pub parameters: Vec<Range<usize>>,
+    /// Parameter the cursor is at, if the server knows
+    pub active_parameter: Option<usize>,
+}
+
+impl Signature {
+    /// Char range of the highlighted parameter within the label
+    pub fn active_range(&self) -> Option<Range<usize>> {
+        self.parameters.get(self.active_parameter?).cloned()
+    }
+}
+
+/// Open signature help for a call
+#[derive(Debug, Clone)]
+pub struct SignatureHelp {
+    pub doc_id: DocumentId,
+    pub view_id: ViewId,
+    /// Cursor when help was requested
+    pub trigger: usize,
+    pub signatures: Vec<Signature>,
+    /// Index of the overload that matches best
+    pub active: usize,
+}
+
+impl SignatureHelp {
+    /// Whether the call is still open after `text` was typed at the
+    /// trigger: it stays on the line and no `)` closes the call
+    pub fn covers(&self, text: &str) -> bool {
+        let mut depth = 0i32;
+        for c in text.chars() {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                '\n' => return false,
+                _ => {}
+            }
+            if depth < 0 {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_covers() {
+        let help = SignatureHelp {
+            doc_id: DocumentId::next(),
+            view_id: ViewId::next(),
+            trigger: 0,
+            signatures: Vec::new(),
+            active: 0,
+        };
+        assert!(help.covers("a, f(b)"));
+        assert!(!help.covers("a)"));
+        assert!(!help.covers("a,\n"));
+    }
+}
@@ -0,0 +1,544 @@
+//! Structural tools for data files: JSON formatting, syntax checks for
+//! JSON/YAML/TOML and moving between sibling keys.
+//!
+//! JSON goes through its tree-sitter parser, so key order and number
+//! spelling survive formatting. TOML is checked with the `toml` parser and
+//! YAML, which has no parser here, with line-based checks.
+
+use std::ops::Range;
+use tree_sitter::{Node, Parser, Tree};
+
+/// A syntax problem at a byte range of the source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+    pub range: Range<usize>,
+    pub message: String,
+}
+
+impl SyntaxError {
+    fn new(range: Range<usize>, message: impl Into<String>) -> Self {
+        Self {
+            range,
+            message: message.into(),
+        }
+    }
+}
+
+/// Check if a language has a syntax check
+pub fn can_validate(language: &str) -> bool {
+    matches!(language, "json" | "yaml" | "toml")
+}
+
+/// Find syntax errors in a source
+pub fn validate(language: &str, source: &str) -> Vec<SyntaxError> {
+    match language {
+        "json" => parse_json(source).map_or_else(|errors| errors, |_| Vec::new()),
+        "toml" => match source.parse::<toml::Table>() {
+            Ok(_) => Vec::new(),
+            Err(e) => {
+                let range = e.span().unwrap_or(0..0);
+                vec![SyntaxError::new(range, e.message())]
+            }
+        },
+        "yaml" => validate_yaml(source),
+        _ => Vec::new(),
+    }
+}
+
+/// Reformat JSON, indented with `indent` or minified when `None`.
+///
+/// Sources with comments are refused rather than losing them.
+pub fn format_json(source: &str, indent: Option<&str>) -> Result<String, Vec<SyntaxError>> {
+    let tree = parse_json(source)?;
+    let root = tree.root_node();
+
+    let mut cursor = root.walk();
+    let values: Vec<Node> = root.named_children(&mut cursor).collect();
+    if let Some(comment) = values.iter().find(|node| node.kind() == "comment") {
+        return Err(vec![SyntaxError::new(
+            comment.byte_range(),
+            "comments can't be kept when formatting",
+        )]);
+    }
+
+    let mut out = String::new();
+    let mut formatter = JsonFormatter { source, indent, out: &mut out };
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            formatter.out.push('\n');
+        }
+        formatter.value(*value, 0)?;
+    }
+    if indent.is_some() {
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+struct JsonFormatter<'a> {
+    source: &'a str,
+    indent: Option<&'a str>,
+    out: &'a mut String,
+}
+
+impl JsonFormatter<'_> {
+    fn value(&mut self, node: Node, depth: usize) -> Result<(), Vec<SyntaxError>> {
+        match node.kind() {
+            "object" | "array" => {
+                let (open, close) = if node.kind() == "object" { ('{', '}') } else { ('[', ']') };
+                let mut cursor = node.walk();
+                let children: Vec<Node> = node.named_children(&mut cursor).collect();
+                if let Some(comment) = children.iter().find(|n| n.kind() == "comment") {
+                    return Err(vec![SyntaxError::new(
+                        comment.byte_range(),
+                        "comments can't be kept when formatting",
+                    )]);
+                }
+
+                self.out.push(open);
+                for (i, child) in children.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push(',');
+                    }
+                    self.newline(depth + 1);
+                    if child.kind() == "pair" {
+                        self.pair(*child, depth + 1)?;
+                    } else {
+                        self.value(*child, depth + 1)?;
+                    }
+                }
+                if !children.is_empty() {
+                    self.newline(depth);
+                }
+                self.out.push(close);
+            }
+            _ => self.out.push_str(&self.source[node.byte_range()]),
+        }
+        Ok(())
+    }
+
+    fn pair(&mut self, node: Node, depth: usize) -> Result<(), Vec<SyntaxError>> {
+        let (Some(key), Some(value)) = (node.child_by_field_name("key"), node.child_by_field_name("value")) else {
+            return Err(vec![SyntaxError::new(node.byte_range(), "incomplete pair")]);
+        };
+        self.out.push_str(&self.source[key.byte_range()]);
+        self.out.push_str(if self.indent.is_some() { ": " } else { ":" });
+        self.value(value, depth)
+    }
+
+    fn newline(&mut self, depth: usize) {
+        if let Some(indent) = self.indent {
+            self.out.push('\n');
+            self.out.push_str(&indent.repeat(depth));
+        }
+    }
+}
+
+/// Parse JSON, failing with the positions of syntax errors
+fn parse_json(source: &str) -> Result<Tree, Vec<SyntaxError>> {
+    let mut parser = Parser::new();
+    let parsed = parser
+        .set_language(&tree_sitter_json::LANGUAGE.into())
+        .ok()
+        .and_then(|_| parser.parse(source, None));
+    let Some(tree) = parsed else {
+        return Err(vec![SyntaxError::new(0..0, "JSON parser unavailable")]);
+    };
+
+    if !tree.root_node().has_error() {
+        return Ok(tree);
+    }
+    let mut errors = Vec::new();
+    collect_errors(tree.root_node(), &mut errors);
+    Err(errors)
+}
+
+fn collect_errors(node: Node, errors: &mut Vec<SyntaxError>) {
+    if node.is_missing() {
+        errors.push(SyntaxError::new(
+            node.byte_range(),
+            format!("missing {}", node.kind()),
+        ));
+    } else if node.is_error() {
+        errors.push(SyntaxError::new(node.byte_range(), "unexpected syntax"));
+    } else if node.has_error() {
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            collect_errors(child, errors);
+        }
+    }
+}
+
+/// Line-based YAML checks: tab indentation, unterminated quotes, unbalanced
+/// flow collections and duplicate keys within a mapping. The bodies of
+/// block scalars (`|` and `>`) are text and aren't checked, and quoted
+/// scalars may go on over the lines indented past their key.
+fn validate_yaml(source: &str) -> Vec<SyntaxError> {
+    let mut errors = Vec::new();
+    // Keys seen per indentation level of the enclosing mappings
+    let mut keys: Vec<(usize, Vec<String>)> = Vec::new();
+    // Indentation of the key whose block scalar the lines are part of
+    let mut block: Option<usize> = None;
+    // Quoted scalar going on past its line: the quote, the indentation of
+    // its key and the line it starts on
+    let mut quoted: Option<(char, usize, Range<usize>)> = None;
+    let mut offset = 0;
+
+    for raw_line in source.split_inclusive('\n') {
+        let line = raw_line.trim_end_matches(['\n', '\r']);
+        let start = offset;
+        offset += raw_line.len();
+
+        let content = line.trim_start_matches(' ');
+        let indent = line.len() - content.len();
+        if content.trim().is_empty() {
+            continue;
+        }
+        let document_marker = content == "---" || content == "...";
+        if block.is_some_and(|level| indent > level && !document_marker) {
+            continue;
+        }
+        block = None;
+        if let Some((quote, level, range)) = quoted.take() {
+            if indent > level && !document_marker {
+                // The rest of the line after the closing quote is left alone
+                if closing_quote(content, quote).is_none() {
+                    quoted = Some((quote, level, range));
+                }
+                continue;
+            }
+            errors.push(SyntaxError::new(range, "unterminated quoted string"));
+        }
+
+        if content.starts_with('#') {
+            continue;
+        }
+        if document_marker {
+            keys.clear();
+            continue;
+        }
+        if content.starts_with('\t') {
+            errors.push(SyntaxError::new(
+                start + indent..start + indent + 1,
+                "tabs are not allowed for indentation",
+            ));
+            continue;
+        }
+
+        let code = strip_yaml_comment(content);
+        let (open_quote, depth) = scan_yaml_line(code);
+        if depth < 0 {
+            // Flow collections may continue on the next lines, so only a
+            // surplus of closing brackets is certain to be wrong
+            errors.push(SyntaxError::new(start + indent..start + line.len(), "unbalanced brackets"));
+            continue;
+        }
+
+        // Sequence items start a new mapping
+        let (key_indent, code) = match code.strip_prefix("- ") {
+            Some(rest) => (indent + 2, rest.trim_start()),
+            None => (indent, code),
+        };
+        let key = yaml_key(code);
+        let value = match &key {
+            Some(_) => code.split_once(": ").map_or("", |(_, value)| value.trim()),
+            None => code,
+        };
+        // Quotes in plain scalars like `it's` are text
+        if let Some(quote) = open_quote.filter(|_| value.starts_with(['"', '\''])) {
+            let level = if key.is_some() { key_indent } else { indent };
+            quoted = Some((quote, level, start + indent..start + line.len()));
+        }
+        if value.starts_with(['|', '>']) && value[1..].chars().all(|c| matches!(c, '-' | '+' | '0'..='9')) {
+            block = Some(if key.is_some() { key_indent } else { indent });
+        }
+
+        let indent = key_indent;
+        keys.retain(|(level, _)| *level <= indent);
+        if code.starts_with("- ") || code == "-" {
+            continue;
+        }
+        let Some(key) = key else {
+            continue;
+        };
+        if content.starts_with("- ") {
+            keys.retain(|(level, _)| *level < indent);
+        }
+        match keys.last_mut() {
+            Some((level, seen)) if *level == indent => {
+                if seen.contains(&key) {
+                    let key_start = start + line.len() - code.len();
+                    errors.push(SyntaxError::new(
+                        key_start..key_start + key.len(),
+                        format!("duplicate key {}", key),
+                    ));
+                } else {
+                    seen.push(key);
+                }
+            }
+            _ => keys.push((indent, vec![key])),
+        }
+    }
+    if let Some((_, _, range)) = quoted {
+        errors.push(SyntaxError::new(range, "unterminated quoted string"));
+    }
+    errors
+}
+
+/// The key of a `key: value` line
+fn yaml_key(code: &str) -> Option<String> {
+    if code.starts_with(['{', '[', '"', '\'', '|', '>', '&', '*', '!']) {
+        return None;
+    }
+    let colon = code.find(": ").or_else(|| code.strip_suffix(':').map(str::len))?;
+    Some(code[..colon].trim_end().to_string())
+}
+
+/// Remove a trailing comment, respecting quotes
+fn strip_yaml_comment(code: &str) -> &str {
+    let mut quote = None;
+    let mut previous = ' ';
+    for (i, c) in code.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if q == c => quote = None,
+            (None, '#') if previous == ' ' => return code[..i].trim_end(),
+            _ => {}
+        }
+        previous = c;
+    }
+    code
+}
+
+/// The quote a line leaves open, and how many more flow collections it
+/// opens than it closes
+fn scan_yaml_line(code: &str) -> (Option<char>, i32) {
+    let mut quote = None;
+    let mut depth = 0;
+    let mut chars = code.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some('"'), '\\') => {
+                chars.next();
+            }
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if q == c => quote = None,
+            (None, '[' | '{') => depth += 1,
+            (None, ']' | '}') => depth -= 1,
+            _ => {}
+        }
+    }
+    (quote, depth)
+}
+
+/// Byte offset just past the quote closing a scalar that started on an
+/// earlier line. `''` inside single quotes is a quote, not the end.
+fn closing_quote(line: &str, quote: char) -> Option<usize> {
+    let mut chars = line.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' if quote == '"' => {
+                chars.next();
+            }
+            '\'' if quote == '\'' && chars.peek().is_some_and(|&(_, next)| next == '\'') => {
+                chars.next();
+            }
+            _ if c == quote => return Some(i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Byte offset of the next or previous key at the same level as the key
+/// around `offset`
+pub fn sibling_key(language: &str, source: &str, offset: usize, forward: bool) -> Option<usize> {
+    match language {
+        "json" => json_sibling(source, offset, forward),
+        "yaml" => line_sibling(source, offset, forward, yaml_line_level),
+        "toml" => line_sibling(source, offset, forward, toml_line_level),
+        _ => None,
+    }
+}
+
+fn json_sibling(source: &str, offset: usize, forward: bool) -> Option<usize> {
+    let mut parser = Parser::new();
+    parser.set_language(&tree_sitter_json::LANGUAGE.into()).ok()?;
+    let tree = parser.parse(source, None)?;
+
+    let mut node = tree.root_node().descendant_for_byte_range(offset, offset)?;
+    while node.kind() != "pair" {
+        node = node.parent()?;
+    }
+    let mut sibling = node;
+    loop {
+        sibling = if forward {
+            sibling.next_named_sibling()?
+        } else {
+            sibling.prev_named_sibling()?
+        };
+        if sibling.kind() == "pair" {
+            return Some(sibling.start_byte());
+        }
+    }
+}
+
+/// Level of a line that starts a key, used to find its siblings: a lower
+/// level ends the search
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum LineLevel {
+    /// A line that ends the enclosing block
+    Boundary(usize),
+    Key(usize),
+}
+
+fn yaml_line_level(line: &str) -> Option<LineLevel> {
+    let content = line.trim_start_matches(' ');
+    if content.is_empty() || content.starts_with('#') {
+        return None;
+    }
+    let indent = line.len() - content.len();
+    if yaml_key(content.strip_prefix("- ").unwrap_or(content)).is_some() {
+        Some(LineLevel::Key(indent))
+    } else {
+        Some(LineLevel::Boundary(indent))
+    }
+}
+
+fn toml_line_level(line: &str) -> Option<LineLevel> {
+    let content = line.trim_start();
+    if content.is_empty() || content.starts_with('#') {
+        None
+    } else if content.starts_with('[') {
+        // Tables are siblings of each other and end the keys before them
+        Some(LineLevel::Key(0))
+    } else if content.contains('=') {
+        Some(LineLevel::Key(1))
+    } else {
+        None
+    }
+}
+
+fn line_sibling(
+    source: &str,
+    offset: usize,
+    forward: bool,
+    level_of: fn(&str) -> Option<LineLevel>,
+) -> Option<usize> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for raw_line in source.split_inclusive('\n') {
+        lines.push((start, raw_line.trim_end_matches(['\n', '\r'])));
+        start += raw_line.len();
+    }
+    let current = lines
+        .iter()
+        .rposition(|(start, _)| *start <= offset)?;
+    let LineLevel::Key(level) = level_of(lines[current].1)? else {
+        return None;
+    };
+
+    let candidates: Box<dyn Iterator<Item = &(usize, &str)>> = if forward {
+        Box::new(lines[current + 1..].iter())
+    } else {
+        Box::new(lines[..current].iter().rev())
+    };
+    for (start, line) in candidates {
+        let indent = line.len() - line.trim_start().len();
+        match level_of(line) {
+            Some(LineLevel::Key(other)) if other == level => return Some(start + indent),
+            Some(LineLevel::Key(other)) | Some(LineLevel::Boundary(other)) if other < level => {
+                return None
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_json() {
+        let source = r#"{"b": [1, 2.50, {}], "a": {"x": null}}"#;
+        assert_eq!(
+            format_json(source, Some("  ")).unwrap(),
+            "{\n  \"b\": [\n    1,\n    2.50,\n    {}\n  ],\n  \"a\": {\n    \"x\": null\n  }\n}\n"
+        );
+        assert_eq!(
+            format_json(source, None).unwrap(),
+            r#"{"b":[1,2.50,{}],"a":{"x":null}}"#
+        );
+        assert!(format_json("{\"a\": }", None).is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate("json", "[1, 2]").is_empty());
+        assert!(!validate("json", "[1, 2").is_empty());
+
+        let errors = validate("toml", "a = 1\nb = \n");
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].range.start >= 6);
+
+        let yaml = "a: 1\nb:\n  c: 'x\n  d: 2\n  d: 3\n\tE: 4\na: 5\n";
+        let messages: Vec<String> = validate("yaml", yaml).into_iter().map(|e| e.message).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "unterminated quoted string",
+                "duplicate key d",
+                "tabs are not allowed for indentation",
+                "duplicate key a",
+            ]
+        );
+        assert!(validate("yaml", "- a: 1\n- a: 2\nit: it's fine\n").is_empty());
+        assert!(validate("yaml", "escaped: \"say \\\"hi\\\"\"\n").is_empty());
+    }
+
+    #[test]
+    fn test_validate_yaml_block_scalars() {
+        // Block scalar bodies are text, whatever they look like
+        let yaml = "script: |\n  a: 1\n  a: 2\n\n  'not closed\nfolded: >-\n  b: [\n- |\n  c: 3\n  c: 4\nscript: 5\n";
+        let messages: Vec<String> = validate("yaml", yaml).into_iter().map(|e| e.message).collect();
+        assert_eq!(messages, vec!["duplicate key script"]);
+
+        // The body ends at the first line not indented past the key
+        let yaml = "a:\n  text: |\n    x: 1\n  b: 1\n  b: 2\n";
+        let messages: Vec<String> = validate("yaml", yaml).into_iter().map(|e| e.message).collect();
+        assert_eq!(messages, vec!["duplicate key b"]);
+    }
+
+    #[test]
+    fn test_validate_yaml_multiline_quotes() {
+        let yaml = "a: \"one\n  two: 2\n  three\"\nb: 'it''s\n  still: quoted'\nc: 1\n- \"item\n  more\"\n";
+        assert!(validate("yaml", yaml).is_empty());
+
+        // A quote open at the end of the file or under a line no deeper than
+        // its key is unterminated, reported where it starts
+        let yaml = "a: 1\nb: \"open\n  more\n";
+        let errors = validate("yaml", yaml);
+        assert_eq!(errors, vec![SyntaxError::new(5..13, "unterminated quoted string")]);
+    }
+
+    #[test]
+    fn test_sibling_key() {
+        let json = r#"{"a": {"x": 1, "y": 2}, "b": 3}"#;
+        assert_eq!(sibling_key("json", json, 2, true), Some(24));
+        assert_eq!(sibling_key("json", json, 8, true), Some(15));
+        assert_eq!(sibling_key("json", json, 24, false), Some(1));
+        assert_eq!(sibling_key("json", json, 24, true), None);
+
+        let yaml = "a:\n  x: 1\n  y: 2\nb: 3\n";
+        assert_eq!(sibling_key("yaml", yaml, 0, true), Some(17));
+        assert_eq!(sibling_key("yaml", yaml, 5, true), Some(12));
+        assert_eq!(sibling_key("yaml", yaml, 12, true), None);
+
+        let toml = "a = 1\n[t]\nb = 2\nc = 3\n[u]\n";
+        assert_eq!(sibling_key("toml", toml, 10, true), Some(16));
+        assert_eq!(sibling_key("toml", toml, 16, true), None);
+        assert_eq!(sibling_key("toml", toml, 6, true), Some(22));
+    }
+}
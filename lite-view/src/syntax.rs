@@ -1,7 +1,13 @@
+use crate::line_syntax;
 use once_cell::sync::Lazy;
-use std::collections::HashMap;
-use streaming_iterator::StreamingIterator;
-use tree_sitter::{Language, Parser, Query, QueryCursor};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+#[cfg(feature = "syntax")]
+use {
+    std::collections::HashMap,
+    streaming_iterator::StreamingIterator,
+    tree_sitter::{Language, Parser, Query, QueryCursor},
+};
 
 /// Highlight category for theming
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -21,6 +27,11 @@ pub enum Highlight {
     Label,
     Namespace,
     Attribute,
+    DiffAdded,
+    DiffRemoved,
+    DiffHunk,
+    /// Text past a recommended line length
+    Overlong,
 }
 
 impl Highlight {
@@ -67,11 +78,18 @@ impl Highlight {
 
             "attribute" | "tag" | "tag.attribute" => Highlight::Attribute,
 
+            "diff.plus" => Highlight::DiffAdded,
+            "diff.minus" => Highlight::DiffRemoved,
+
             _ => return None,
         })
     }
 }
 
+/// Lines longer than this many chars aren't highlighted by tree-sitter:
+/// matching the queries over a minified file's one line is what makes it slow
+pub const LONG_LINE_LEN: usize = 10_000;
+
 /// A highlighted span in the document
 #[derive(Debug, Clone)]
 pub struct HighlightSpan {
@@ -81,6 +99,7 @@ pub struct HighlightSpan {
 }
 
 /// Language configuration with parser and queries
+#[cfg(feature = "syntax")]
 struct LanguageConfig {
     language: Language,
     highlight_query: Query,
@@ -96,11 +115,24 @@ pub fn highlighter() -> &'static Highlighter {
 
 /// Syntax highlighter using tree-sitter
 pub struct Highlighter {
+    #[cfg(feature = "syntax")]
     languages: HashMap<&'static str, LanguageConfig>,
+    /// Nanoseconds spent highlighting, for `debug_metrics`
+    time: AtomicU64,
 }
 
 impl Highlighter {
     fn new() -> Self {
+        Self {
+            #[cfg(feature = "syntax")]
+            languages: Self::grammars(),
+            time: AtomicU64::new(0),
+        }
+    }
+
+    /// Tree-sitter grammars and highlight queries, by language
+    #[cfg(feature = "syntax")]
+    fn grammars() -> HashMap<&'static str, LanguageConfig> {
         let mut languages = HashMap::new();
 
         // Rust
@@ -198,10 +230,10 @@ impl Highlighter {
         ) {
             languages.insert("markdown", config);
         }
-
-        Self { languages }
+        languages
     }
 
+    #[cfg(feature = "syntax")]
     fn create_config(language: Language, query_str: &str) -> Result<LanguageConfig, tree_sitter::QueryError> {
         let highlight_query = Query::new(&language, query_str)?;
         Ok(LanguageConfig {
@@ -212,11 +244,50 @@ impl Highlighter {
 
     /// Check if a language is supported
     pub fn supports(&self, language: &str) -> bool {
-        self.languages.contains_key(language)
+        #[cfg(feature = "syntax")]
+        if self.languages.contains_key(language) {
+            return true;
+        }
+        line_syntax::supports(language)
     }
 
     /// Highlight a document and return spans
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn highlight(&self, language: &str, source: &str) -> Vec<HighlightSpan> {
+        let started = std::time::Instant::now();
+        let spans = self.highlight_source(language, source);
+        let nanos = started.elapsed().as_nanos() as u64;
+        self.time.fetch_add(nanos, Ordering::Relaxed);
+        spans
+    }
+
+    /// Highlight a document and return spans. The browser has no clock
+    /// `Instant` can read, so nothing is timed.
+    #[cfg(target_arch = "wasm32")]
     pub fn highlight(&self, language: &str, source: &str) -> Vec<HighlightSpan> {
+        self.highlight_source(language, source)
+    }
+
+    /// Time spent highlighting since the last call
+    pub fn take_time(&self) -> Duration {
+        Duration::from_nanos(self.time.swap(0, Ordering::Relaxed))
+    }
+
+    fn highlight_source(&self, language: &str, source: &str) -> Vec<HighlightSpan> {
+        if let Some(spans) = line_syntax::highlight(language, source) {
+            return spans;
+        }
+        self.highlight_tree(language, source)
+    }
+
+    /// Builds without tree-sitter only highlight the line-based languages
+    #[cfg(not(feature = "syntax"))]
+    fn highlight_tree(&self, _language: &str, _source: &str) -> Vec<HighlightSpan> {
+        Vec::new()
+    }
+
+    #[cfg(feature = "syntax")]
+    fn highlight_tree(&self, language: &str, source: &str) -> Vec<HighlightSpan> {
         let Some(config) = self.languages.get(language) else {
             return Vec::new();
         };
@@ -233,19 +304,27 @@ impl Highlighter {
         let mut cursor = QueryCursor::new();
         let mut spans = Vec::new();
 
-        let mut matches = cursor.matches(&config.highlight_query, tree.root_node(), source.as_bytes());
-
-        while let Some(match_) = matches.next() {
-            for capture in match_.captures {
-                let capture_name = &config.highlight_query.capture_names()[capture.index as usize];
-
-                if let Some(highlight) = Highlight::from_capture(capture_name) {
-                    let node = capture.node;
-                    spans.push(HighlightSpan {
-                        start: node.start_byte(),
-                        end: node.end_byte(),
-                        highlight,
-                    });
+        // Only the text between long lines is matched. Nodes reaching into
+        // a range from before it were matched with the range before.
+        for range in short_line_ranges(source) {
+            cursor.set_byte_range(range.clone());
+            let mut matches = cursor.matches(&config.highlight_query, tree.root_node(), source.as_bytes());
+
+            while let Some(match_) = matches.next() {
+                for capture in match_.captures {
+                    let capture_name = &config.highlight_query.capture_names()[capture.index as usize];
+
+                    if let Some(highlight) = Highlight::from_capture(capture_name) {
+                        let node = capture.node;
+                        if node.start_byte() < range.start {
+                            continue;
+                        }
+                        spans.push(HighlightSpan {
+                            start: node.start_byte(),
+                            end: node.end_byte(),
+                            highlight,
+                        });
+                    }
                 }
             }
         }
@@ -270,3 +349,42 @@ impl Highlighter {
             .collect()
     }
 }
+
+/// Byte ranges of a source between the lines longer than `LONG_LINE_LEN`
+#[cfg(feature = "syntax")]
+fn short_line_ranges(source: &str) -> Vec<std::ops::Range<usize>> {
+    let mut ranges = Vec::new();
+    let (mut start, mut offset) = (0, 0);
+    for line in source.split_inclusive('\n') {
+        let end = offset + line.len();
+        if line.len() > LONG_LINE_LEN && line.chars().count() > LONG_LINE_LEN {
+            if offset > start {
+                ranges.push(start..offset);
+            }
+            start = end;
+        }
+        offset = end;
+    }
+    if offset > start {
+        ranges.push(start..offset);
+    }
+    ranges
+}
+
+#[cfg(all(test, feature = "syntax"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_long_lines_are_not_highlighted() {
+        let long = format!("var s = \"{}\"\n", "x".repeat(LONG_LINE_LEN));
+        let source = format!("func a() {{}}\n{}func b() {{}}\n", long);
+        let long_line = 12..12 + long.len();
+        assert_eq!(short_line_ranges(&source), vec![0..long_line.start, long_line.end..source.len()]);
+
+        let spans = highlighter().highlight("go", &source);
+        assert!(spans.iter().any(|span| span.end <= long_line.start));
+        assert!(spans.iter().any(|span| span.start >= long_line.end));
+        assert!(spans.iter().all(|span| !long_line.contains(&span.start)));
+    }
+}
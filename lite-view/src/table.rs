@@ -0,0 +1,130 @@
+//! Column-aligned display of CSV/TSV files.
+//!
+//! Alignment is purely visual: padding is added when rendering and the
+//! document text is never changed.
+
+use lite_core::Rope;
+use std::ops::Range;
+
+/// Cells wider than this don't widen their column
+const MAX_CELL_WIDTH: usize = 40;
+/// Rows scanned to size the columns
+const MAX_SCANNED_ROWS: usize = 10_000;
+
+/// Character shown in place of the delimiter
+pub const COLUMN_SEPARATOR: char = '│';
+
+/// The delimiter of a language shown as a table, if any
+pub fn delimiter_for(language: &str) -> Option<char> {
+    match language {
+        "csv" => Some(','),
+        "tsv" => Some('\t'),
+        _ => None,
+    }
+}
+
+/// Char ranges of the cells in a line, excluding delimiters.
+/// Delimiters inside double-quoted fields don't split cells.
+pub fn cell_ranges(line: &str, delimiter: char) -> Vec<Range<usize>> {
+    let mut cells = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    let mut len = 0;
+
+    for (i, c) in line.chars().enumerate() {
+        if c == '"' {
+            quoted = !quoted;
+        } else if c == delimiter && !quoted {
+            cells.push(start..i);
+            start = i + 1;
+        }
+        len = i + 1;
+    }
+    cells.push(start..len);
+    cells
+}
+
+/// Column widths of a table document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableLayout {
+    pub delimiter: char,
+    pub widths: Vec<usize>,
+}
+
+impl TableLayout {
+    /// Size the columns to fit the document's cells
+    pub fn new(rope: &Rope, delimiter: char) -> Self {
+        let mut widths: Vec<usize> = Vec::new();
+        for line in 0..rope.len_lines().min(MAX_SCANNED_ROWS) {
+            let line = line_text(rope, line);
+            for (i, cell) in cell_ranges(&line, delimiter).into_iter().enumerate() {
+                let width = cell.len().min(MAX_CELL_WIDTH);
+                match widths.get_mut(i) {
+                    Some(w) => *w = (*w).max(width),
+                    None => widths.push(width),
+                }
+            }
+        }
+        Self { delimiter, widths }
+    }
+
+    /// Lay out a line: for each display column, the char offset shown there,
+    /// or `None` for padding. Delimiters are followed by one space.
+    pub fn layout_line(&self, line: &str) -> Vec<Option<usize>> {
+        let cells = cell_ranges(line, self.delimiter);
+        let mut columns = Vec::new();
+        for (i, cell) in cells.iter().enumerate() {
+            columns.extend(cell.clone().map(Some));
+            if i + 1 < cells.len() {
+                let width = self.widths.get(i).copied().unwrap_or(0);
+                columns.extend(std::iter::repeat_n(None, width.saturating_sub(cell.len())));
+                columns.push(Some(cell.end));
+                columns.push(None);
+            }
+        }
+        columns
+    }
+
+    /// Display column of the char at `col` in `line`; past the end of the line
+    /// columns continue one per char
+    pub fn display_col(&self, line: &str, col: usize) -> usize {
+        let columns = self.layout_line(line);
+        match columns.iter().position(|c| *c == Some(col)) {
+            Some(x) => x,
+            None => columns.len() + col.saturating_sub(line.chars().count()),
+        }
+    }
+}
+
+/// Get a line's text without its line ending
+pub fn line_text(rope: &Rope, line: usize) -> String {
+    let text: String = rope.line(line).chars().collect();
+    text.trim_end_matches(['\n', '\r']).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cell_ranges() {
+        assert_eq!(cell_ranges("a,bb,", ','), vec![0..1, 2..4, 5..5]);
+        assert_eq!(cell_ranges("\"x,y\",z", ','), vec![0..5, 6..7]);
+    }
+
+    #[test]
+    fn test_layout() {
+        let rope = Rope::from("name,age\nbob,7\n");
+        let table = TableLayout::new(&rope, ',');
+        assert_eq!(table.widths, vec![4, 3]);
+
+        // "bob │ 7"
+        let columns = table.layout_line("bob,7");
+        assert_eq!(
+            columns,
+            vec![Some(0), Some(1), Some(2), None, Some(3), None, Some(4)]
+        );
+        assert_eq!(table.display_col("bob,7", 4), 6);
+        assert_eq!(table.display_col("bob,7", 5), 7);
+    }
+}
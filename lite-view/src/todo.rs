@@ -0,0 +1,175 @@
+//! Collecting TODO/FIXME style comments across a project
+
+use crate::document::detect_language;
+use crate::{comment, workspace};
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// A comment starting with one of the keywords
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Todo {
+    /// Relative to the working directory when inside it
+    pub path: PathBuf,
+    /// Line index, from 0
+    pub line: usize,
+    pub keyword: String,
+    /// The line from the keyword on
+    pub text: String,
+}
+
+/// Find the keywords in the comments of a file's text, or anywhere in
+/// files of languages whose comments aren't known, such as plain text.
+/// They must stand alone as words, so `TODOS` or `my_todo` don't count.
+pub fn scan_text(path: &Path, text: &str, keywords: &[String]) -> Vec<Todo> {
+    let language = detect_language(path).unwrap_or_default();
+    let syntax = (comment::line_comment(&language), comment::block_comment(&language));
+    let mut in_block = false;
+    let mut todos = Vec::new();
+    for (line_idx, line) in text.lines().enumerate() {
+        let comments = match syntax {
+            (None, None) => std::iter::once(0..line.len()).collect(),
+            (line_token, block) => comments(line, line_token, block, &mut in_block),
+        };
+        let found = keywords.iter().filter_map(|keyword| {
+            line.match_indices(keyword.as_str())
+                .find(|(i, _)| {
+                    let before = line[..*i].chars().next_back();
+                    let after = line[i + keyword.len()..].chars().next();
+                    let commented = comments.iter().any(|comment| comment.contains(i));
+                    commented && !before.is_some_and(is_word_char) && !after.is_some_and(is_word_char)
+                })
+                .map(|(i, _)| (i, keyword))
+        });
+        if let Some((i, keyword)) = found.min() {
+            todos.push(Todo {
+                path: path.to_path_buf(),
+                line: line_idx,
+                keyword: keyword.clone(),
+                text: line[i..].trim_end().to_string(),
+            });
+        }
+    }
+    todos
+}
+
+/// Byte ranges of a line inside comments. `in_block` is whether a block
+/// comment is open at its start, and is left as it is at its end. Double
+/// quoted strings are skipped, so a comment token in one doesn't count.
+fn comments(
+    line: &str,
+    line_token: Option<&str>,
+    block: Option<(&str, &str)>,
+    in_block: &mut bool,
+) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut block_start = in_block.then_some(0);
+    let mut in_string = false;
+    let mut chars = line.char_indices();
+    while let Some((i, c)) = chars.next() {
+        let rest = &line[i..];
+        match block_start {
+            Some(start) => {
+                if let Some((_, close)) = block.filter(|(_, close)| rest.starts_with(close)) {
+                    ranges.push(start..i);
+                    block_start = None;
+                    chars.nth(close.len().saturating_sub(2));
+                }
+            }
+            None if in_string => match c {
+                '\\' => {
+                    chars.next();
+                }
+                '"' => in_string = false,
+                _ => {}
+            },
+            None => {
+                if let Some((open, _)) = block.filter(|(open, _)| rest.starts_with(open)) {
+                    block_start = Some(i + open.len());
+                    chars.nth(open.len().saturating_sub(2));
+                } else if line_token.is_some_and(|token| rest.starts_with(token)) {
+                    ranges.push(i..line.len());
+                    break;
+                } else if c == '"' {
+                    in_string = true;
+                }
+            }
+        }
+    }
+    if let Some(start) = block_start {
+        ranges.push(start.min(line.len())..line.len());
+    }
+    *in_block = block_start.is_some();
+    ranges
+}
+
+/// Scan every text file under the roots, skipping hidden and build
+/// directories and what `ignored` says to, such as files `.gitignore` lists
+pub fn scan_dirs(roots: &[PathBuf], keywords: &[String], ignored: &dyn Fn(&Path) -> bool) -> Vec<Todo> {
+    let mut todos = Vec::new();
+    for path in workspace::text_files(roots, ignored) {
+        // Files that aren't UTF-8 are binary as far as this is concerned
+        if let Ok(text) = fs::read_to_string(&path) {
+            todos.extend(scan_text(&display_path(&path), &text, keywords));
+        }
+    }
+
+    todos.sort_by(|a, b| (&a.path, a.line).cmp(&(&b.path, b.line)));
+    todos
+}
+
+/// A path relative to the working directory if it's inside it
+pub fn display_path(path: &Path) -> PathBuf {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    match std::env::current_dir() {
+        Ok(cwd) => path.strip_prefix(&cwd).map(Path::to_path_buf).unwrap_or(path),
+        Err(_) => path,
+    }
+}
+
+/// Number of todos per keyword, in keyword order, leaving out those with none
+pub fn counts<'a>(todos: &[Todo], keywords: &'a [String]) -> Vec<(&'a str, usize)> {
+    keywords
+        .iter()
+        .map(|keyword| {
+            let n = todos.iter().filter(|todo| &todo.keyword == keyword).count();
+            (keyword.as_str(), n)
+        })
+        .filter(|(_, n)| *n > 0)
+        .collect()
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_text() {
+        let keywords = vec!["TODO".to_string(), "FIXME".to_string()];
+        let text = "fn main() {\n    // TODO(ana): handle errors\n    let TODOS = 1; // FIXME\n    my_TODO();\n}\n";
+        let todos = scan_text(Path::new("main.rs"), text, &keywords);
+
+        let found: Vec<_> = todos.iter().map(|todo| (todo.line, todo.text.as_str())).collect();
+        assert_eq!(found, vec![(1, "TODO(ana): handle errors"), (2, "FIXME")]);
+        assert_eq!(counts(&todos, &keywords), vec![("TODO", 1), ("FIXME", 1)]);
+    }
+
+    #[test]
+    fn test_scan_text_only_in_comments() {
+        let keywords = vec!["TODO".to_string(), "FIXME".to_string()];
+        let lines = |path: &str, text: &str| {
+            let todos = scan_text(Path::new(path), text, &keywords);
+            todos.iter().map(|todo| todo.line).collect::<Vec<_>>()
+        };
+        let rust = "let s = \"TODO: not a comment // FIXME\";\nlet t = 1; /* TODO one\nFIXME two */ TODO\nenum State { TODO }\n";
+        assert_eq!(lines("lib.rs", rust), vec![1, 2]);
+        assert_eq!(lines("run.py", "x = \"TODO\"  # FIXME later\nTODO = 1\n"), vec![0]);
+        assert_eq!(lines("page.html", "<p>TODO</p>\n<!-- TODO: fix -->\n"), vec![1]);
+        // Plain text is all prose
+        assert_eq!(lines("notes.txt", "TODO: call back\n"), vec![0]);
+    }
+}
@@ -7,6 +7,26 @@ pub enum Layout {
     Vertical,
 }
 
+/// A screen area in terminal cells
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rect {
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Rect {
+    pub fn new(x: u16, y: u16, width: u16, height: u16) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
 /// A node in the layout tree
 #[derive(Debug)]
 pub enum Node {
@@ -48,6 +68,47 @@ impl Node {
         }
     }
 
+    /// Divide `area` among the views in this subtree.
+    ///
+    /// Vertical splits sit side by side, horizontal splits are stacked; siblings
+    /// are separated by a one-cell gap.
+    fn layout(&self, area: Rect, out: &mut Vec<(ViewId, Rect)>) {
+        match self {
+            Node::Leaf(id) => out.push((*id, area)),
+            Node::Container {
+                layout,
+                children,
+                ratios,
+            } => {
+                let total = match layout {
+                    Layout::Vertical => area.width,
+                    Layout::Horizontal => area.height,
+                };
+                let gaps = children.len().saturating_sub(1) as u16;
+                let available = total.saturating_sub(gaps) as f32;
+
+                let mut cumulative = 0.0;
+                let mut offset = 0u16;
+                for (i, child) in children.iter().enumerate() {
+                    cumulative += ratios.get(i).copied().unwrap_or(0.0);
+                    let end = if i + 1 == children.len() {
+                        total
+                    } else {
+                        ((available * cumulative).round() as u16 + i as u16).min(total)
+                    };
+                    let size = end.saturating_sub(offset);
+
+                    let child_area = match layout {
+                        Layout::Vertical => Rect::new(area.x + offset, area.y, size, area.height),
+                        Layout::Horizontal => Rect::new(area.x, area.y + offset, area.width, size),
+                    };
+                    child.layout(child_area, out);
+                    offset = (end + 1).min(total);
+                }
+            }
+        }
+    }
+
     /// Check if this node contains a view
     pub fn contains(&self, view_id: ViewId) -> bool {
         match self {
@@ -144,6 +205,13 @@ impl Tree {
         self.root.views()
     }
 
+    /// Compute the screen area of every view
+    pub fn layout(&self, area: Rect) -> Vec<(ViewId, Rect)> {
+        let mut out = Vec::new();
+        self.root.layout(area, &mut out);
+        out
+    }
+
     /// Split the focused view
     pub fn split(&mut self, new_view_id: ViewId, layout: Layout) {
         let old_focus = self.focus;
@@ -272,3 +340,28 @@ impl Default for Tree {
         Self::new(ViewId::default())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout() {
+        let first = ViewId::next();
+        let second = ViewId::next();
+        let third = ViewId::next();
+        let mut tree = Tree::new(first);
+        tree.split(second, Layout::Vertical);
+        tree.split(third, Layout::Horizontal);
+
+        let layout = tree.layout(Rect::new(0, 1, 81, 20));
+        assert_eq!(
+            layout,
+            vec![
+                (first, Rect::new(0, 1, 40, 20)),
+                (second, Rect::new(41, 1, 40, 10)),
+                (third, Rect::new(41, 12, 40, 9)),
+            ]
+        );
+    }
+}
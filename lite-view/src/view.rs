@@ -1,6 +1,14 @@
-use crate::DocumentId;
+use crate::{ChangeSubscription, Document, DocumentId, Rect};
+use lite_config::EditorConfig;
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
+/// Ticks an animated scroll takes to arrive
+const SCROLL_FRAMES: usize = 4;
+
+/// Scrolls by fewer lines than this jump without animation
+const ANIMATED_JUMP: usize = 5;
+
 /// Unique identifier for views
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct ViewId(usize);
@@ -35,6 +43,29 @@ pub struct View {
     pub height: u16,
     /// Gutter width (line numbers, etc.)
     pub gutter_width: u16,
+    /// Screen area of this view's split
+    pub area: Rect,
+    /// Scroll offsets of documents this view showed before, keyed by document
+    doc_scroll: HashMap<DocumentId, (usize, usize)>,
+    /// Where the first visible line was when last synced with the document
+    anchor: Option<ScrollAnchor>,
+    /// Line an animated scroll started from and the ticks since
+    animation: Option<(usize, usize)>,
+    /// Keep the cursor line in the middle of the view, as on a typewriter
+    pub typewriter: bool,
+    /// While the view scrolls with the other bound views, its offset from
+    /// the scroll position they share (see `Editor::bind_scroll`)
+    pub scroll_bind: Option<isize>,
+}
+
+/// The text at the top of a view, followed through edits so they don't move it
+#[derive(Debug)]
+struct ScrollAnchor {
+    changes: ChangeSubscription,
+    /// Char index of the start of the first visible line
+    pos: usize,
+    /// `scroll_y` at the time; a different one means the view was scrolled
+    line: usize,
 }
 
 impl View {
@@ -48,7 +79,58 @@ impl View {
             width: 80,
             height: 24,
             gutter_width: 4,
+            area: Rect::new(0, 0, 80, 24),
+            doc_scroll: HashMap::new(),
+            anchor: None,
+            animation: None,
+            typewriter: false,
+            scroll_bind: None,
+        }
+    }
+
+    /// Keep the same text at the top of the view after edits to its
+    /// document, so changes above it (formatting, other views, other
+    /// cursors) don't make the visible text jump. A view scrolled since the
+    /// last call keeps its new position.
+    pub fn follow_changes(&mut self, doc: &Document) {
+        if let Some(anchor) = &mut self.anchor {
+            let changes = doc.poll_changes(&mut anchor.changes);
+            if let Some(changes) = changes.filter(|_| anchor.line == self.scroll_y) {
+                // Text inserted right at the top belongs to the line above's
+                // end, so it shows rather than pushing the top line down
+                let pos = match anchor.pos {
+                    0 => 0,
+                    pos => (changes.map_pos(pos - 1) + 1).min(doc.len_chars()),
+                };
+                self.scroll_y = doc.rope.char_to_line(pos);
+            }
+        }
+
+        let line = self.scroll_y.min(doc.len_lines().saturating_sub(1));
+        self.anchor = Some(ScrollAnchor {
+            changes: doc.subscribe(),
+            pos: doc.rope.line_to_char(line),
+            line: self.scroll_y,
+        });
+    }
+
+    /// Show another document, remembering how far the current one was scrolled
+    pub fn set_doc(&mut self, doc_id: DocumentId) {
+        if doc_id == self.doc_id {
+            return;
         }
+        self.doc_scroll
+            .insert(self.doc_id, (self.scroll_y, self.scroll_x));
+        let (scroll_y, scroll_x) = self.doc_scroll.remove(&doc_id).unwrap_or((0, 0));
+        self.doc_id = doc_id;
+        self.scroll_y = scroll_y;
+        self.animation = None;
+        self.scroll_x = scroll_x;
+    }
+
+    /// Forget the remembered scroll position of a closed document
+    pub fn forget_doc(&mut self, doc_id: DocumentId) {
+        self.doc_scroll.remove(&doc_id);
     }
 
     /// Get the effective editing area width
@@ -56,33 +138,78 @@ impl View {
         self.width.saturating_sub(self.gutter_width)
     }
 
-    /// Update viewport size
-    pub fn set_size(&mut self, width: u16, height: u16) {
-        self.width = width;
-        self.height = height;
+    /// Move the view to a new screen area
+    pub fn set_area(&mut self, area: Rect) {
+        self.area = area;
+        self.width = area.width;
+        self.height = area.height;
     }
 
-    /// Ensure cursor is visible, adjusting scroll if needed
-    pub fn ensure_cursor_visible(&mut self, cursor_line: usize, cursor_col: usize, scrolloff: usize) {
-        let scrolloff = scrolloff.min(self.height as usize / 2);
+    /// Ensure cursor is visible, adjusting scroll if needed.
+    ///
+    /// Keeps `scrolloff` lines and `sidescrolloff` columns around the cursor and
+    /// scrolls by at least `scroll_jump` once it gets closer to an edge. In
+    /// typewriter mode the cursor line stays centered instead.
+    pub fn ensure_cursor_visible(&mut self, cursor_line: usize, cursor_col: usize, config: &EditorConfig) {
+        let jump = config.scroll_jump.max(1);
 
         // Vertical scrolling
-        if cursor_line < self.scroll_y + scrolloff {
-            self.scroll_y = cursor_line.saturating_sub(scrolloff);
-        }
-        let bottom_limit = self.scroll_y + self.height as usize - scrolloff - 1;
-        if cursor_line > bottom_limit {
-            self.scroll_y = cursor_line + scrolloff + 1 - self.height as usize;
+        let height = self.height as usize;
+        if self.typewriter {
+            self.scroll_y = cursor_line.saturating_sub(height.saturating_sub(1) / 2);
+        } else if height > 0 {
+            let scrolloff = config.scrolloff.min((height - 1) / 2);
+            if cursor_line < self.scroll_y + scrolloff {
+                let needed = cursor_line.saturating_sub(scrolloff);
+                self.scroll_y = needed.min(self.scroll_y.saturating_sub(jump));
+            } else if cursor_line + scrolloff >= self.scroll_y + height {
+                let needed = cursor_line + scrolloff + 1 - height;
+                self.scroll_y = needed.max(self.scroll_y + jump);
+            }
         }
 
         // Horizontal scrolling
         let text_width = self.text_width() as usize;
-        if cursor_col < self.scroll_x {
-            self.scroll_x = cursor_col;
+        if text_width > 0 {
+            let sidescrolloff = config.sidescrolloff.min((text_width - 1) / 2);
+            if cursor_col < self.scroll_x + sidescrolloff {
+                let needed = cursor_col.saturating_sub(sidescrolloff);
+                self.scroll_x = needed.min(self.scroll_x.saturating_sub(jump));
+            } else if cursor_col + sidescrolloff >= self.scroll_x + text_width {
+                let needed = cursor_col + sidescrolloff + 1 - text_width;
+                self.scroll_x = needed.max(self.scroll_x + jump);
+            }
         }
-        if cursor_col >= self.scroll_x + text_width {
-            self.scroll_x = cursor_col - text_width + 1;
+    }
+
+    /// Animate a scroll to `scroll_y` from a line that was shown at the top,
+    /// if it's a large jump
+    pub fn animate_scroll(&mut self, from: usize) {
+        self.animation = (self.scroll_y.abs_diff(from) >= ANIMATED_JUMP).then_some((from, 0));
+    }
+
+    /// The first line shown: `scroll_y`, or on the way there while animating.
+    /// The animation slows down as it arrives.
+    pub fn shown_scroll_y(&self) -> usize {
+        let Some((from, frame)) = self.animation else {
+            return self.scroll_y;
+        };
+        let left = (SCROLL_FRAMES - frame - 1) as f64 / SCROLL_FRAMES as f64;
+        let progress = 1.0 - left * left;
+        let (from, to) = (from as f64, self.scroll_y as f64);
+        (from + (to - from) * progress).round() as usize
+    }
+
+    /// Show the next frame of an animated scroll. Returns whether the view
+    /// is still animating.
+    pub fn advance_animation(&mut self) -> bool {
+        if let Some((_, frame)) = &mut self.animation {
+            *frame += 1;
+            if *frame + 1 >= SCROLL_FRAMES {
+                self.animation = None;
+            }
         }
+        self.animation.is_some()
     }
 
     /// Get the range of visible lines
@@ -120,3 +247,76 @@ impl View {
         self.gutter_width = digits + 2; // 1 space on each side
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn view(width: u16, height: u16) -> View {
+        let mut view = View::new(DocumentId::next());
+        view.set_area(Rect::new(0, 0, width, height));
+        view.gutter_width = 0;
+        view
+    }
+
+    #[test]
+    fn test_scroll_jump() {
+        let config = EditorConfig {
+            scrolloff: 2,
+            scroll_jump: 5,
+            ..Default::default()
+        };
+        let mut view = view(80, 10);
+        view.ensure_cursor_visible(8, 0, &config);
+        assert_eq!(view.scroll_y, 5);
+
+        // Far jumps land the cursor at the scrolloff margin
+        view.ensure_cursor_visible(100, 0, &config);
+        assert_eq!(view.scroll_y, 93);
+        view.ensure_cursor_visible(94, 0, &config);
+        assert_eq!(view.scroll_y, 88);
+    }
+
+    #[test]
+    fn test_sidescrolloff() {
+        let config = EditorConfig {
+            sidescrolloff: 3,
+            ..Default::default()
+        };
+        let mut view = view(20, 10);
+        view.ensure_cursor_visible(0, 17, &config);
+        assert_eq!(view.scroll_x, 1);
+        view.ensure_cursor_visible(0, 3, &config);
+        assert_eq!(view.scroll_x, 0);
+    }
+
+    #[test]
+    fn test_typewriter() {
+        let config = EditorConfig::default();
+        let mut view = view(80, 11);
+        view.typewriter = true;
+        view.ensure_cursor_visible(3, 0, &config);
+        assert_eq!(view.scroll_y, 0);
+        view.ensure_cursor_visible(20, 0, &config);
+        assert_eq!(view.scroll_y, 15);
+        view.ensure_cursor_visible(19, 0, &config);
+        assert_eq!(view.scroll_y, 14);
+    }
+
+    #[test]
+    fn test_animated_scroll() {
+        let mut view = view(80, 10);
+        view.scroll_y = 2;
+        view.animate_scroll(0);
+        assert_eq!(view.shown_scroll_y(), 2);
+
+        view.scroll_y = 100;
+        view.animate_scroll(0);
+        let mut shown = vec![view.shown_scroll_y()];
+        while view.advance_animation() {
+            shown.push(view.shown_scroll_y());
+        }
+        shown.push(view.shown_scroll_y());
+        assert_eq!(shown, vec![44, 75, 94, 100]);
+    }
+}
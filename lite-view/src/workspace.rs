@@ -0,0 +1,123 @@
+//! Finding the project a file belongs to
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directories never searched besides hidden ones
+pub const SKIPPED_DIRS: &[&str] = &["target", "node_modules"];
+
+/// Larger files are assumed to be generated or data
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Most files one search reads, so searching a huge tree stays cheap
+const MAX_FILES: usize = 20_000;
+
+/// Files or directories marking the top of a project, checked when a file
+/// isn't in a git repository
+pub const ROOT_MARKERS: &[&str] = &[
+    "Cargo.toml",
+    "package.json",
+    "go.mod",
+    "pyproject.toml",
+    "setup.py",
+    "CMakeLists.txt",
+    "Makefile",
+    ".lite.toml",
+];
+
+/// Root of the project containing `path`: the enclosing git repository, or
+/// else the nearest directory with a root marker
+pub fn find_root(path: &Path) -> Option<PathBuf> {
+    let path = std::path::absolute(path).ok()?;
+    let start = if path.is_dir() { path.as_path() } else { path.parent()? };
+
+    if let Some(git_root) = start.ancestors().find(|dir| dir.join(".git").exists()) {
+        return Some(git_root.to_path_buf());
+    }
+    start
+        .ancestors()
+        .find(|dir| ROOT_MARKERS.iter().any(|marker| dir.join(marker).exists()))
+        .map(Path::to_path_buf)
+}
+
+/// Nearest directory containing `path` with one of a language's own root
+/// markers, else the same as `find_root`
+pub fn find_root_with(path: &Path, markers: &[String]) -> Option<PathBuf> {
+    let path = std::path::absolute(path).ok()?;
+    let start = if path.is_dir() { path.as_path() } else { path.parent()? };
+    start
+        .ancestors()
+        .find(|dir| markers.iter().any(|marker| dir.join(marker).exists()))
+        .map(Path::to_path_buf)
+        .or_else(|| find_root(&path))
+}
+
+/// The files under the roots a project-wide search reads, skipping hidden
+/// and build directories, those `ignored` says to and files too large to
+/// be source
+pub fn text_files(roots: &[PathBuf], ignored: &dyn Fn(&Path) -> bool) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut stack: Vec<PathBuf> = roots.to_vec();
+
+    while let Some(dir) = stack.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut entries: Vec<_> = entries.flatten().collect();
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if ignored(&entry.path()) {
+                continue;
+            }
+            if file_type.is_dir() {
+                if !name.starts_with('.') && !SKIPPED_DIRS.contains(&name.as_ref()) {
+                    stack.push(entry.path());
+                }
+                continue;
+            }
+            if !file_type.is_file() || entry.metadata().map_or(true, |m| m.len() > MAX_FILE_SIZE) {
+                continue;
+            }
+            if files.len() == MAX_FILES {
+                return files;
+            }
+            files.push(entry.path());
+        }
+    }
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_find_root() {
+        let base = std::env::temp_dir().join(format!("lite-root-{}", std::process::id()));
+        let crate_dir = base.join("repo/crates/app");
+        fs::create_dir_all(crate_dir.join("src")).unwrap();
+        fs::write(crate_dir.join("Cargo.toml"), "").unwrap();
+        let file = crate_dir.join("src/main.rs");
+
+        // The nearest marker without a repository, the repository with one
+        let found = find_root(&file);
+        fs::create_dir_all(base.join("repo/.git")).unwrap();
+        let found_in_repo = find_root(&file);
+        // A language's own markers win over the repository
+        let found_by_marker = find_root_with(&file, &["Cargo.toml".to_string()]);
+        let found_without_marker = find_root_with(&file, &["go.mod".to_string()]);
+        fs::remove_dir_all(&base).unwrap();
+
+        assert_eq!(found, Some(crate_dir.clone()));
+        assert_eq!(found_in_repo, Some(base.join("repo")));
+        assert_eq!(found_by_marker, Some(crate_dir));
+        assert_eq!(found_without_marker, Some(base.join("repo")));
+    }
+}
@@ -0,0 +1,143 @@
+//! Functions the WebAssembly module exports to the page. Strings go in as
+//! a pointer and a length into memory the page got from `lite_alloc`, and
+//! come out through `lite_output` and `lite_output_len`.
+
+use crate::WebEditor;
+use std::cell::RefCell;
+
+thread_local! {
+    /// The page's editor, once started
+    static EDITOR: RefCell<Option<WebEditor>> = const { RefCell::new(None) };
+    /// The last string returned to the page
+    static OUTPUT: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
+fn with_editor<R: Default>(f: impl FnOnce(&mut WebEditor) -> R) -> R {
+    EDITOR.with_borrow_mut(|editor| editor.as_mut().map(f).unwrap_or_default())
+}
+
+/// Read a string the page wrote into memory
+///
+/// # Safety
+///
+/// `ptr` must point at `len` readable bytes.
+unsafe fn string_arg(ptr: *const u8, len: usize) -> String {
+    if len == 0 {
+        return String::new();
+    }
+    String::from_utf8_lossy(std::slice::from_raw_parts(ptr, len)).into_owned()
+}
+
+fn set_output(text: String) {
+    OUTPUT.with_borrow_mut(|output| *output = text);
+}
+
+/// Memory for the page to write a string of `len` bytes into
+#[no_mangle]
+pub extern "C" fn lite_alloc(len: usize) -> *mut u8 {
+    let mut buffer = Vec::<u8>::with_capacity(len);
+    let ptr = buffer.as_mut_ptr();
+    std::mem::forget(buffer);
+    ptr
+}
+
+/// Give back memory from `lite_alloc`
+///
+/// # Safety
+///
+/// `ptr` and `len` must be those of one `lite_alloc` call, freed once.
+#[no_mangle]
+pub unsafe extern "C" fn lite_free(ptr: *mut u8, len: usize) {
+    drop(Vec::from_raw_parts(ptr, 0, len));
+}
+
+/// Start an editor on a grid of the given size
+#[no_mangle]
+pub extern "C" fn lite_start(width: u16, height: u16) {
+    EDITOR.with_borrow_mut(|editor| *editor = Some(WebEditor::new(width, height)));
+}
+
+#[no_mangle]
+pub extern "C" fn lite_resize(width: u16, height: u16) {
+    with_editor(|editor| editor.resize(width, height));
+}
+
+/// Replace the buffer's text, highlighted as a language; an empty language
+/// means none
+///
+/// # Safety
+///
+/// Both strings must be as `string_arg` requires.
+#[no_mangle]
+pub unsafe extern "C" fn lite_set_text(ptr: *const u8, len: usize, language: *const u8, language_len: usize) {
+    let text = string_arg(ptr, len);
+    let language = string_arg(language, language_len);
+    with_editor(|editor| editor.set_text(&text, Some(language.as_str()).filter(|l| !l.is_empty())));
+}
+
+/// Insert typed text
+///
+/// # Safety
+///
+/// The string must be as `string_arg` requires.
+#[no_mangle]
+pub unsafe extern "C" fn lite_type(ptr: *const u8, len: usize) {
+    let text = string_arg(ptr, len);
+    with_editor(|editor| editor.type_text(&text));
+}
+
+/// Show text an input method is composing; empty when it's given up
+///
+/// # Safety
+///
+/// The string must be as `string_arg` requires.
+#[no_mangle]
+pub unsafe extern "C" fn lite_compose(ptr: *const u8, len: usize) {
+    let text = string_arg(ptr, len);
+    with_editor(|editor| editor.compose(&text));
+}
+
+/// Insert what an input method composed
+///
+/// # Safety
+///
+/// The string must be as `string_arg` requires.
+#[no_mangle]
+pub unsafe extern "C" fn lite_commit(ptr: *const u8, len: usize) {
+    let text = string_arg(ptr, len);
+    with_editor(|editor| editor.commit(&text));
+}
+
+/// Press a key, see `WebEditor::key`
+///
+/// # Safety
+///
+/// The string must be as `string_arg` requires.
+#[no_mangle]
+pub unsafe extern "C" fn lite_key(ptr: *const u8, len: usize) -> bool {
+    let key = string_arg(ptr, len);
+    with_editor(|editor| editor.key(&key))
+}
+
+/// Draw the screen; the HTML is the output
+#[no_mangle]
+pub extern "C" fn lite_render() {
+    set_output(with_editor(|editor| editor.render()));
+}
+
+/// Put the buffer's text in the output
+#[no_mangle]
+pub extern "C" fn lite_text() {
+    set_output(with_editor(|editor| editor.text()));
+}
+
+/// Where the output is, valid until the next call
+#[no_mangle]
+pub extern "C" fn lite_output() -> *const u8 {
+    OUTPUT.with_borrow(|output| output.as_ptr())
+}
+
+#[no_mangle]
+pub extern "C" fn lite_output_len() -> usize {
+    OUTPUT.with_borrow(|output| output.len())
+}
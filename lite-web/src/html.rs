@@ -0,0 +1,180 @@
+//! The cells of a frame as HTML: a row per line, and a span per run of
+//! cells styled alike
+
+use lite_core::grapheme_width;
+use ratatui::buffer::Buffer;
+use ratatui::style::{Color, Modifier, Style};
+use std::fmt::Write;
+
+/// Rows of a buffer, each a `div`, with the cell under the cursor in a
+/// span of class `cursor`
+pub fn screen(buffer: &Buffer, cursor: Option<(u16, u16)>) -> String {
+    let area = buffer.area;
+    let mut html = String::new();
+    for y in area.top()..area.bottom() {
+        html.push_str("<div class=\"row\">");
+        let mut run = String::new();
+        let mut run_style = None;
+        let mut covered = 0;
+        for x in area.left()..area.right() {
+            let cell = &buffer[(x, y)];
+            // The cells a wide character spreads over are drawn with it
+            if covered > 0 {
+                covered -= 1;
+                continue;
+            }
+            covered = grapheme_width(cell.symbol()).saturating_sub(1);
+            if cursor == Some((x, y)) {
+                flush(&mut html, &mut run, run_style.take());
+                html.push_str("<span class=\"cursor\">");
+                escape(&mut html, cell.symbol());
+                html.push_str("</span>");
+                continue;
+            }
+            let style = cell.style();
+            if run_style != Some(style) {
+                flush(&mut html, &mut run, run_style.take());
+                run_style = Some(style);
+            }
+            escape(&mut run, cell.symbol());
+        }
+        flush(&mut html, &mut run, run_style);
+        html.push_str("</div>");
+    }
+    html
+}
+
+/// Write a run of cells and start a new one
+fn flush(html: &mut String, run: &mut String, style: Option<Style>) {
+    if run.is_empty() {
+        return;
+    }
+    let css = style.map(css).unwrap_or_default();
+    if css.is_empty() {
+        html.push_str(run);
+    } else {
+        let _ = write!(html, "<span style=\"{}\">{}</span>", css, run);
+    }
+    run.clear();
+}
+
+fn escape(html: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            c => html.push(c),
+        }
+    }
+}
+
+/// Inline CSS of a cell style
+fn css(style: Style) -> String {
+    let (mut fg, mut bg) = (style.fg, style.bg);
+    if style.add_modifier.contains(Modifier::REVERSED) {
+        (fg, bg) = (bg, fg);
+    }
+    let mut css = String::new();
+    if let Some(color) = fg.and_then(color) {
+        let _ = write!(css, "color:{};", color);
+    }
+    if let Some(color) = bg.and_then(color) {
+        let _ = write!(css, "background:{};", color);
+    }
+    let modifiers = style.add_modifier;
+    if modifiers.contains(Modifier::BOLD) {
+        css.push_str("font-weight:bold;");
+    }
+    if modifiers.contains(Modifier::ITALIC) {
+        css.push_str("font-style:italic;");
+    }
+    if modifiers.contains(Modifier::DIM) {
+        css.push_str("opacity:0.6;");
+    }
+    match (modifiers.contains(Modifier::UNDERLINED), modifiers.contains(Modifier::CROSSED_OUT)) {
+        (true, true) => css.push_str("text-decoration:underline line-through;"),
+        (true, false) => css.push_str("text-decoration:underline;"),
+        (false, true) => css.push_str("text-decoration:line-through;"),
+        (false, false) => {}
+    }
+    css
+}
+
+/// A terminal color as CSS; the default colors are left to the page
+fn color(color: Color) -> Option<String> {
+    let (r, g, b) = match color {
+        Color::Reset => return None,
+        Color::Rgb(r, g, b) => (r, g, b),
+        Color::Indexed(i) => indexed(i),
+        Color::Black => indexed(0),
+        Color::Red => indexed(1),
+        Color::Green => indexed(2),
+        Color::Yellow => indexed(3),
+        Color::Blue => indexed(4),
+        Color::Magenta => indexed(5),
+        Color::Cyan => indexed(6),
+        Color::Gray => indexed(7),
+        Color::DarkGray => indexed(8),
+        Color::LightRed => indexed(9),
+        Color::LightGreen => indexed(10),
+        Color::LightYellow => indexed(11),
+        Color::LightBlue => indexed(12),
+        Color::LightMagenta => indexed(13),
+        Color::LightCyan => indexed(14),
+        Color::White => indexed(15),
+    };
+    Some(format!("#{:02x}{:02x}{:02x}", r, g, b))
+}
+
+/// A color of the xterm palette: 16 named colors, a 6×6×6 cube and 24
+/// grays
+fn indexed(i: u8) -> (u8, u8, u8) {
+    const NAMED: [(u8, u8, u8); 16] = [
+        (0, 0, 0),
+        (205, 49, 49),
+        (13, 188, 121),
+        (229, 229, 16),
+        (36, 114, 200),
+        (188, 63, 188),
+        (17, 168, 205),
+        (229, 229, 229),
+        (102, 102, 102),
+        (241, 76, 76),
+        (35, 209, 139),
+        (245, 245, 67),
+        (59, 142, 234),
+        (214, 112, 214),
+        (41, 184, 219),
+        (255, 255, 255),
+    ];
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    match i {
+        0..=15 => NAMED[i as usize],
+        16..=231 => {
+            let i = (i - 16) as usize;
+            (LEVELS[i / 36], LEVELS[i / 6 % 6], LEVELS[i % 6])
+        }
+        _ => {
+            let gray = 8 + 10 * (i - 232);
+            (gray, gray, gray)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_css() {
+        assert_eq!(color(Color::Rgb(40, 44, 52)).as_deref(), Some("#282c34"));
+        assert_eq!(color(Color::Indexed(196)).as_deref(), Some("#ff0000"));
+        assert_eq!(color(Color::Indexed(244)).as_deref(), Some("#808080"));
+        assert_eq!(color(Color::Reset), None);
+
+        let style = Style::default().fg(Color::Black).bg(Color::White).add_modifier(Modifier::REVERSED);
+        assert_eq!(css(style), "color:#ffffff;background:#000000;");
+    }
+}
@@ -0,0 +1,136 @@
+//! lite in the browser: the editor core compiled to WebAssembly and drawn
+//! as HTML, for playgrounds and doc pages
+//!
+//! ```sh
+//! cargo build -p lite-web --target wasm32-unknown-unknown --release
+//! ```
+//!
+//! The module exports plain functions (see `ffi`), so `www/index.html` loads
+//! it without generated bindings. There are no files, language servers or
+//! tree-sitter grammars in the browser: buffers are set from the page, and
+//! only the line-based languages such as Markdown are highlighted.
+
+mod ffi;
+mod html;
+
+use lite_config::KeyEvent;
+use lite_ui::{Component, Context, EditorState, EditorWidget, StatusLine};
+use ratatui::backend::TestBackend;
+use ratatui::layout::Rect;
+use ratatui::Terminal;
+
+/// An editor drawing into a grid of cells the page shows
+pub struct WebEditor {
+    state: EditorState,
+    /// Cells of the last frame; `TestBackend` keeps them in memory
+    terminal: Terminal<TestBackend>,
+}
+
+impl WebEditor {
+    /// An editor with an empty buffer on a grid of the given size
+    pub fn new(width: u16, height: u16) -> Self {
+        let terminal = Terminal::new(TestBackend::new(width, height)).expect("an in-memory backend never fails");
+        let mut web = Self {
+            state: EditorState::new(),
+            terminal,
+        };
+        web.resize(width, height);
+        web
+    }
+
+    /// Fit the grid to a new size
+    pub fn resize(&mut self, width: u16, height: u16) {
+        self.terminal.backend_mut().resize(width, height);
+        let _ = self.terminal.resize(Rect::new(0, 0, width, height));
+        // The bottom row is the statusline
+        let editor = self.state.editor_mut();
+        editor.resize(lite_view::Rect::new(0, 0, width, height.saturating_sub(1)));
+    }
+
+    /// Replace the buffer's text, highlighted as `language`
+    pub fn set_text(&mut self, text: &str, language: Option<&str>) {
+        self.state.set_text(text, language);
+    }
+
+    /// Text of the buffer
+    pub fn text(&self) -> String {
+        self.state.text()
+    }
+
+    /// Insert typed text at the cursor, over the selection
+    pub fn type_text(&mut self, text: &str) {
+        self.state.type_text(text);
+    }
+
+    /// Show text an input method is composing, see `EditorState::set_preedit`
+    pub fn compose(&mut self, text: &str) {
+        self.state.set_preedit(text);
+    }
+
+    /// Insert what an input method composed
+    pub fn commit(&mut self, text: &str) {
+        self.state.commit(text);
+    }
+
+    /// Press a key named as in keymaps, such as `"Ctrl+Z"` or `"Left"`.
+    /// Returns whether the key did anything, so the page can leave the
+    /// others to the browser.
+    pub fn key(&mut self, key: &str) -> bool {
+        match key.parse::<KeyEvent>() {
+            Ok(event) => self.state.handle_key(event),
+            Err(_) => false,
+        }
+    }
+
+    /// Draw the editor and return the screen as HTML
+    pub fn render(&mut self) -> String {
+        let state = &mut self.state;
+        let _ = self.terminal.draw(|frame| {
+            let area = frame.area();
+            let editor_area = Rect {
+                height: area.height.saturating_sub(1),
+                ..area
+            };
+            let status_area = Rect {
+                y: editor_area.bottom(),
+                height: area.height - editor_area.height,
+                ..area
+            };
+            frame.render_stateful_widget(EditorWidget::new(), editor_area, state);
+            StatusLine::new().render(frame, status_area, &Context::new(state.editor_mut()));
+        });
+        html::screen(self.terminal.backend().buffer(), self.state.cursor())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_editing() {
+        let mut web = WebEditor::new(40, 6);
+        web.set_text("# Title\n", Some("markdown"));
+        assert!(web.key("Ctrl+End"));
+        web.type_text("body <b>");
+        assert!(web.key("Backspace"));
+        assert!(web.key("Enter"));
+        assert_eq!(web.text(), "# Title\nbody <b\n");
+        assert!(web.key("Ctrl+Z"));
+        assert_eq!(web.text(), "# Title\nbody <b");
+        // Keys the browser should handle itself
+        assert!(!web.key("Ctrl+O"));
+
+        let html = web.render();
+        assert!(html.contains("body &lt;b"));
+        assert!(html.contains("class=\"cursor\""));
+
+        // Composed text shows at the cursor and edits the buffer on commit
+        web.compose("にほ");
+        assert!(web.render().contains("にほ"));
+        assert_eq!(web.text(), "# Title\nbody <b");
+        web.commit("日本");
+        assert_eq!(web.text(), "# Title\nbody <b日本");
+        assert!(!web.render().contains("にほ"));
+    }
+}